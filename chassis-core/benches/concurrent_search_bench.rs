@@ -0,0 +1,155 @@
+//! Search latency under the single-writer, multi-reader model.
+//!
+//! Indexes the first half of a benchmark dataset (see `dataset`), splits the
+//! index into handles and measures searches on one `ReadHandle` while other
+//! reader threads search the same index. Each reader count runs twice: with
+//! the writer idle, and with the writer inserting the second half of the
+//! dataset (over and over) for the whole measurement. The gap between the two
+//! is the cost of sharing the index with a writer: searches wait while an
+//! insert holds the lock.
+//!
+//! Criterion reports the mean; the p50 and p99 latency of the measured reader,
+//! and the inserts the writer completed meanwhile, are printed in a table once
+//! the group finishes.
+//!
+//! ```bash
+//! cargo bench --bench concurrent_search_bench
+//! CHASSIS_BENCH_LIMIT=5000 cargo bench --bench concurrent_search_bench
+//! ```
+
+// Only the vectors are used here; the ground truth is for `recall_bench`
+#[allow(dead_code)]
+mod dataset;
+
+use chassis_core::{IndexOptions, VectorIndex};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use dataset::Dataset;
+use std::hint::black_box;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+/// Neighbors requested per query
+const K: usize = 10;
+
+/// Reader threads searching at once, the measured one included
+const READER_COUNTS: [usize; 4] = [1, 2, 4, 8];
+
+/// Build an index over `vectors`
+fn build_index(vectors: &[Vec<f32>], dims: u32) -> (VectorIndex, TempDir) {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let mut index = VectorIndex::open(
+        temp_dir.path().join("concurrent.chassis"),
+        dims,
+        IndexOptions::default(),
+    )
+    .expect("Failed to open index");
+
+    index.reserve(vectors.len() as u64).expect("Failed to reserve");
+    for vector in vectors {
+        index.add(vector).expect("Failed to add vector");
+    }
+    index.flush().expect("Failed to flush");
+
+    (index, temp_dir)
+}
+
+/// The latency below which a fraction `p` of `samples` fall
+fn percentile(samples: &[Duration], p: f64) -> Duration {
+    if samples.is_empty() {
+        return Duration::ZERO;
+    }
+    samples[((samples.len() - 1) as f64 * p).round() as usize]
+}
+
+/// Benchmark: search latency by reader count, with and without a writer
+fn bench_concurrent_search(c: &mut Criterion) {
+    let data = Dataset::from_env(K);
+    let (initial, pending) = data.base.split_at(data.base.len() / 2);
+    let (index, _temp) = build_index(initial, data.dims);
+    let (mut writer, reader) = index.into_handles();
+    let queries = &data.queries;
+    let mut report = Vec::new();
+
+    let mut group = c.benchmark_group(format!("concurrent_search/{}", data.name));
+    group.sample_size(20);
+    group.throughput(Throughput::Elements(1));
+
+    for readers in READER_COUNTS {
+        for with_writer in [false, true] {
+            let stop = AtomicBool::new(false);
+            let mut latencies = Vec::new();
+
+            let inserted = std::thread::scope(|scope| {
+                for thread in 1..readers {
+                    let reader = reader.clone();
+                    let stop = &stop;
+                    scope.spawn(move || {
+                        let mut next = thread;
+                        while !stop.load(Ordering::Relaxed) {
+                            black_box(reader.search(&queries[next % queries.len()], K).unwrap());
+                            next += 1;
+                        }
+                    });
+                }
+
+                let writer = &mut writer;
+                let stop = &stop;
+                let inserts = with_writer.then(|| {
+                    scope.spawn(move || {
+                        let mut inserted = 0;
+                        while !stop.load(Ordering::Relaxed) {
+                            writer.add(&pending[inserted % pending.len()]).unwrap();
+                            inserted += 1;
+                        }
+                        inserted
+                    })
+                });
+
+                let name = if with_writer { "writer_inserting" } else { "writer_idle" };
+                group.bench_function(BenchmarkId::new(name, readers), |b| {
+                    b.iter_custom(|iters| {
+                        let mut total = Duration::ZERO;
+                        for i in 0..iters {
+                            let query = &queries[i as usize % queries.len()];
+                            let start = Instant::now();
+                            black_box(reader.search(query, K).unwrap());
+                            let elapsed = start.elapsed();
+                            latencies.push(elapsed);
+                            total += elapsed;
+                        }
+                        total
+                    });
+                });
+
+                stop.store(true, Ordering::Relaxed);
+                inserts.map_or(0, |inserts| inserts.join().unwrap())
+            });
+
+            latencies.sort_unstable();
+            report.push((
+                readers,
+                with_writer,
+                percentile(&latencies, 0.5),
+                percentile(&latencies, 0.99),
+                inserted,
+            ));
+        }
+    }
+
+    group.finish();
+
+    eprintln!("\n{} search latency, k={K}, {} vectors at start", data.name, initial.len());
+    eprintln!("{:>7} {:>8} {:>10} {:>10} {:>8}", "readers", "writer", "p50", "p99", "inserts");
+    for (readers, with_writer, p50, p99, inserted) in report {
+        let writer = if with_writer { "inserts" } else { "idle" };
+        eprintln!(
+            "{readers:>7} {writer:>8} {:>10} {:>10} {inserted:>8}",
+            format!("{p50:.1?}"),
+            format!("{p99:.1?}")
+        );
+    }
+}
+
+criterion_group!(benches, bench_concurrent_search);
+criterion_main!(benches);
@@ -0,0 +1,210 @@
+//! Datasets for recall benchmarks.
+//!
+//! A dataset is a set of base vectors to index, a set of queries, and the
+//! exact nearest neighbors of each query (the ground truth recall is scored
+//! against). Two sources are supported, chosen with environment variables:
+//!
+//! - Synthetic (default): Gaussian clusters around random centers, generated
+//!   from a fixed seed so every run indexes the same vectors. Queries are
+//!   drawn from the same clusters.
+//! - SIFT1M (`CHASSIS_BENCH_DATASET=sift1m`): the 1M x 128 TEXMEX corpus,
+//!   read from `CHASSIS_BENCH_DATA` (default `target/bench-data`). If
+//!   `sift/sift_base.fvecs` is missing there, it is downloaded and unpacked
+//!   with `curl` and `tar`.
+//!
+//! `CHASSIS_BENCH_LIMIT` caps the number of base vectors (and queries), so
+//! SIFT1M can be benchmarked on a subset. Ground truth is computed by brute
+//! force with the scalar distance kernel, so it does not depend on the SIMD
+//! code under test; SIFT's published ground truth is used only for the full
+//! corpus.
+
+use chassis_core::distance::squared_euclidean_scalar;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where `fetch_sift()` downloads the corpus from
+const SIFT_URL: &str = "ftp://ftp.irisa.fr/local/texmex/corpus/sift.tar.gz";
+
+/// Base vectors, queries and their exact nearest neighbors
+pub struct Dataset {
+    /// Short name for reports
+    pub name: String,
+
+    /// Vector dimensions
+    pub dims: u32,
+
+    /// Vectors to index, in ID order
+    pub base: Vec<Vec<f32>>,
+
+    /// Query vectors
+    pub queries: Vec<Vec<f32>>,
+
+    /// IDs of the nearest base vectors of each query, nearest first
+    pub ground_truth: Vec<Vec<u64>>,
+}
+
+impl Dataset {
+    /// Loads the dataset selected by the environment, with `k` neighbors of
+    /// ground truth per query
+    pub fn from_env(k: usize) -> Self {
+        let limit = std::env::var("CHASSIS_BENCH_LIMIT")
+            .ok()
+            .map(|limit| limit.parse().expect("CHASSIS_BENCH_LIMIT must be a number of vectors"));
+
+        match std::env::var("CHASSIS_BENCH_DATASET").as_deref() {
+            Ok("sift1m") => Self::sift1m(&data_dir(), limit, k),
+            Ok("synthetic") | Err(_) => {
+                Self::synthetic(limit.unwrap_or(20_000), 100, 128, 64, k, 0x5eed)
+            }
+            Ok(other) => panic!("Unknown CHASSIS_BENCH_DATASET {other:?}: use synthetic or sift1m"),
+        }
+    }
+
+    /// `count` base vectors and `queries` queries in `clusters` Gaussian
+    /// clusters of `dims` dimensions
+    pub fn synthetic(
+        count: usize,
+        queries: usize,
+        dims: u32,
+        clusters: usize,
+        k: usize,
+        seed: u64,
+    ) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let centers: Vec<Vec<f32>> = (0..clusters)
+            .map(|_| (0..dims).map(|_| rng.random_range(-1.0..1.0)).collect())
+            .collect();
+        let sample = |rng: &mut StdRng| {
+            let center = &centers[rng.random_range(0..clusters)];
+            center.iter().map(|&c| c + 0.15 * gaussian(rng)).collect::<Vec<f32>>()
+        };
+
+        let base: Vec<Vec<f32>> = (0..count).map(|_| sample(&mut rng)).collect();
+        let queries: Vec<Vec<f32>> = (0..queries).map(|_| sample(&mut rng)).collect();
+        let ground_truth = exact_neighbors(&base, &queries, k);
+        Self { name: format!("synthetic-{count}x{dims}"), dims, base, queries, ground_truth }
+    }
+
+    /// The SIFT1M corpus from `dir`, downloading it first if needed
+    pub fn sift1m(dir: &Path, limit: Option<usize>, k: usize) -> Self {
+        let sift = dir.join("sift");
+        if !sift.join("sift_base.fvecs").exists() {
+            fetch_sift(dir);
+        }
+
+        let base = read_fvecs(&sift.join("sift_base.fvecs"), limit);
+        let queries = read_fvecs(&sift.join("sift_query.fvecs"), limit);
+        let ground_truth = if base.len() == 1_000_000 {
+            read_ivecs(&sift.join("sift_groundtruth.ivecs"), queries.len(), k)
+        } else {
+            exact_neighbors(&base, &queries, k)
+        };
+
+        Self {
+            name: format!("sift-{}", base.len()),
+            dims: base[0].len() as u32,
+            base,
+            queries,
+            ground_truth,
+        }
+    }
+}
+
+/// `CHASSIS_BENCH_DATA`, or `target/bench-data` in the workspace
+fn data_dir() -> PathBuf {
+    std::env::var_os("CHASSIS_BENCH_DATA").map_or_else(
+        || Path::new(env!("CARGO_MANIFEST_DIR")).join("../target/bench-data"),
+        PathBuf::from,
+    )
+}
+
+/// Downloads and unpacks the SIFT corpus into `dir/sift`
+fn fetch_sift(dir: &Path) {
+    std::fs::create_dir_all(dir).expect("Failed to create the benchmark data directory");
+    let archive = dir.join("sift.tar.gz");
+    eprintln!("Downloading SIFT1M from {SIFT_URL} into {}", dir.display());
+
+    let status = Command::new("curl")
+        .args(["--fail", "--location", "--output"])
+        .arg(&archive)
+        .arg(SIFT_URL)
+        .status()
+        .expect("Failed to run curl");
+    assert!(status.success(), "Downloading {SIFT_URL} failed");
+
+    let status = Command::new("tar")
+        .arg("-xzf")
+        .arg(&archive)
+        .arg("-C")
+        .arg(dir)
+        .status()
+        .expect("Failed to run tar");
+    assert!(status.success(), "Unpacking {} failed", archive.display());
+    std::fs::remove_file(&archive).ok();
+}
+
+/// Reads up to `limit` vectors from an `.fvecs` file (each a little-endian
+/// `u32` dimension count followed by that many `f32`s)
+fn read_fvecs(path: &Path, limit: Option<usize>) -> Vec<Vec<f32>> {
+    read_vecs(path, limit, f32::from_le_bytes)
+}
+
+/// Reads the first `k` neighbors of the first `count` rows of an `.ivecs` file
+fn read_ivecs(path: &Path, count: usize, k: usize) -> Vec<Vec<u64>> {
+    read_vecs(path, Some(count), |bytes| u64::from(u32::from_le_bytes(bytes)))
+        .into_iter()
+        .map(|mut row| {
+            row.truncate(k);
+            row
+        })
+        .collect()
+}
+
+fn read_vecs<T>(path: &Path, limit: Option<usize>, decode: fn([u8; 4]) -> T) -> Vec<Vec<T>> {
+    let file =
+        File::open(path).unwrap_or_else(|e| panic!("Failed to open {}: {e}", path.display()));
+    let mut reader = BufReader::new(file);
+    let mut rows = Vec::new();
+    let mut word = [0u8; 4];
+
+    while limit.is_none_or(|limit| rows.len() < limit) && reader.read_exact(&mut word).is_ok() {
+        let dims = u32::from_le_bytes(word) as usize;
+        let mut bytes = vec![0u8; dims * 4];
+        reader.read_exact(&mut bytes).expect("Truncated vector file");
+        let (values, _) = bytes.as_chunks::<4>();
+        rows.push(values.iter().map(|&value| decode(value)).collect());
+    }
+    rows
+}
+
+/// Brute-force k nearest neighbors of each query, ties broken by ID
+fn exact_neighbors(base: &[Vec<f32>], queries: &[Vec<f32>], k: usize) -> Vec<Vec<u64>> {
+    queries
+        .iter()
+        .map(|query| {
+            let mut scored: Vec<(f32, u64)> = base
+                .iter()
+                .enumerate()
+                .map(|(id, vector)| (squared_euclidean_scalar(query, vector), id as u64))
+                .collect();
+            let order = |a: &(f32, u64), b: &(f32, u64)| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1));
+            if k < scored.len() {
+                scored.select_nth_unstable_by(k, order);
+                scored.truncate(k);
+            }
+            scored.sort_unstable_by(order);
+            scored.into_iter().map(|(_, id)| id).collect()
+        })
+        .collect()
+}
+
+/// Standard normal sample (Box-Muller)
+fn gaussian(rng: &mut StdRng) -> f32 {
+    let u1: f32 = rng.random_range(f32::EPSILON..1.0);
+    let u2: f32 = rng.random();
+    (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+}
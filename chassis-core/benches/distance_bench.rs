@@ -4,12 +4,15 @@
 //! - Absolute timings for SIMD vs scalar
 //! - Scaling with vector dimensions
 //! - Different vector patterns (aligned, unaligned, sparse)
+//! - L2 against squared L2 and L1
 //!
 //! Expected results:
 //! - AVX2: 4-6x speedup on 768-1536D
 //! - NEON: 3-5x speedup on 768-1536D
 
-use chassis_core::distance::{euclidean_distance, euclidean_distance_scalar};
+use chassis_core::distance::{
+    euclidean_distance, euclidean_distance_scalar, manhattan_distance, squared_euclidean,
+};
 use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
 use std::hint::black_box;
 
@@ -277,6 +280,30 @@ fn bench_cache_effects(c: &mut Criterion) {
     group.finish();
 }
 
+/// Benchmark: L2 against its square (no sqrt) and L1 at embedding sizes
+fn bench_kernels(c: &mut Criterion) {
+    let mut group = c.benchmark_group("kernels");
+    group.sample_size(500);
+
+    for dims in [384, 1536] {
+        let a: Vec<f32> = (0..dims).map(|i| (i as f32 * 0.1).sin()).collect();
+        let b: Vec<f32> = (0..dims).map(|i| (i as f32 * 0.1).cos()).collect();
+        group.throughput(Throughput::Elements(dims));
+
+        group.bench_with_input(BenchmarkId::new("euclidean", dims), &dims, |bench, _| {
+            bench.iter(|| black_box(euclidean_distance(black_box(&a), black_box(&b))));
+        });
+        group.bench_with_input(BenchmarkId::new("squared_euclidean", dims), &dims, |bench, _| {
+            bench.iter(|| black_box(squared_euclidean(black_box(&a), black_box(&b))));
+        });
+        group.bench_with_input(BenchmarkId::new("manhattan", dims), &dims, |bench, _| {
+            bench.iter(|| black_box(manhattan_distance(black_box(&a), black_box(&b))));
+        });
+    }
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_distance_by_dimension,
@@ -289,6 +316,7 @@ criterion_group!(
     bench_realistic_embeddings,
     bench_memory_bandwidth,
     bench_cache_effects,
+    bench_kernels,
 );
 
 criterion_main!(benches);
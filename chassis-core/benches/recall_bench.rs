@@ -0,0 +1,96 @@
+//! Recall vs QPS curves across HNSW parameters.
+//!
+//! Builds one index per `(M, ef_construction)` pair over a benchmark dataset
+//! (see `dataset`: synthetic clusters by default, SIFT1M on request) and
+//! measures the throughput of searching every query at several `ef` values.
+//! Criterion reports each point as queries per second; the recall of the same
+//! point is scored against the dataset's exact ground truth and printed in a
+//! table once the group finishes, giving one recall/QPS curve per `M`.
+//!
+//! ```bash
+//! cargo bench --bench recall_bench
+//! CHASSIS_BENCH_DATASET=sift1m CHASSIS_BENCH_LIMIT=100000 cargo bench --bench recall_bench
+//! ```
+
+mod dataset;
+
+use chassis_core::eval::evaluate_recall_sweep;
+use chassis_core::{IndexOptions, VectorIndex};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use dataset::Dataset;
+use std::hint::black_box;
+use std::time::Instant;
+use tempfile::TempDir;
+
+/// Neighbors requested per query
+const K: usize = 10;
+
+/// `(M, ef_construction)` pairs to build indexes with
+const BUILD_PARAMS: [(u16, usize); 3] = [(8, 100), (16, 200), (32, 200)];
+
+/// Search beams measured on every index
+const EF_VALUES: [usize; 5] = [16, 32, 64, 128, 256];
+
+/// Build an index over the dataset's base vectors
+fn build_index(data: &Dataset, m: u16, ef_construction: usize) -> (VectorIndex, TempDir) {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let options = IndexOptions { max_connections: m, ef_construction, ..IndexOptions::default() };
+    let mut index = VectorIndex::open(temp_dir.path().join("recall.chassis"), data.dims, options)
+        .expect("Failed to open index");
+
+    let start = Instant::now();
+    index.reserve(data.base.len() as u64).expect("Failed to reserve");
+    for vector in &data.base {
+        index.add(vector).expect("Failed to add vector");
+    }
+    index.flush().expect("Failed to flush");
+    eprintln!(
+        "Built {} with M={m}, ef_construction={ef_construction} in {:.1?}",
+        data.name,
+        start.elapsed()
+    );
+
+    (index, temp_dir)
+}
+
+/// Benchmark: queries per second at each `ef`, with the recall of each point
+fn bench_recall_vs_qps(c: &mut Criterion) {
+    let data = Dataset::from_env(K);
+    let mut curve = Vec::new();
+
+    for (m, ef_construction) in BUILD_PARAMS {
+        let (index, _temp) = build_index(&data, m, ef_construction);
+        let reports =
+            evaluate_recall_sweep(&index, &data.queries, &data.ground_truth, K, &EF_VALUES)
+                .expect("Failed to evaluate recall");
+
+        let mut group = c.benchmark_group(format!("recall_qps/{}/M{m}", data.name));
+        group.sample_size(10);
+        group.throughput(Throughput::Elements(data.queries.len() as u64));
+
+        for report in reports {
+            let ef = report.ef;
+            group.bench_with_input(BenchmarkId::new("ef", ef), &ef, |b, &ef| {
+                b.iter(|| {
+                    for query in &data.queries {
+                        black_box(index.graph().search(query, K, ef).unwrap());
+                    }
+                });
+            });
+
+            let qps = 1.0 / report.latency_mean.as_secs_f64();
+            curve.push((m, ef_construction, ef, report.recall, qps));
+        }
+
+        group.finish();
+    }
+
+    eprintln!("\n{} recall@{K} vs QPS (single thread)", data.name);
+    eprintln!("{:>4} {:>6} {:>5} {:>8} {:>10}", "M", "ef_c", "ef", "recall", "QPS");
+    for (m, ef_construction, ef, recall, qps) in curve {
+        eprintln!("{m:>4} {ef_construction:>6} {ef:>5} {recall:>8.4} {qps:>10.0}");
+    }
+}
+
+criterion_group!(benches, bench_recall_vs_qps);
+criterion_main!(benches);
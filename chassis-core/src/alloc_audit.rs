@@ -0,0 +1,76 @@
+//! Heap allocation counting for hot-path tests (`alloc-audit` feature)
+//!
+//! The search hot path promises to allocate nothing once its scratch buffers
+//! are warm. `CountingAllocator` makes that checkable: a test binary installs
+//! it as its global allocator and wraps the code under test in
+//! `count_allocations()`.
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static ALLOCATOR: CountingAllocator = CountingAllocator;
+//!
+//! let (results, allocations) = count_allocations(|| graph.search_layer_into(...));
+//! assert_eq!(allocations, 0);
+//! ```
+//!
+//! Counts are per thread, so tests running in parallel do not see each
+//! other's allocations, and work a search hands to other threads is not
+//! counted. Without the allocator installed every count is zero.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+thread_local! {
+    /// Allocations (including reallocations) made by this thread so far
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Global allocator that forwards to `System` and counts allocations per thread
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CountingAllocator;
+
+impl CountingAllocator {
+    fn record() {
+        // `try_with` fails while the thread-local is being torn down
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+    }
+}
+
+// SAFETY: every method forwards to `System` with the caller's arguments
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        Self::record();
+        // SAFETY: forwarded unchanged from the caller
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        Self::record();
+        // SAFETY: forwarded unchanged from the caller
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        Self::record();
+        // SAFETY: forwarded unchanged from the caller
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: forwarded unchanged from the caller
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+/// Allocations made by this thread since it started
+pub fn allocations() -> u64 {
+    ALLOCATIONS.with(Cell::get)
+}
+
+/// Run `f`, returning its result and the number of allocations it made on this
+/// thread
+pub fn count_allocations<R>(f: impl FnOnce() -> R) -> (R, u64) {
+    let before = allocations();
+    let result = f();
+    (result, allocations() - before)
+}
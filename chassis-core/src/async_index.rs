@@ -0,0 +1,281 @@
+//! Tokio wrapper for embedding an index in async servers.
+//!
+//! Every `VectorIndex` call blocks: searches read the mapped file and may
+//! wait on page faults, and `flush()` waits for `fsync`. Called directly from
+//! an async task, they stall every other task on the same worker thread.
+//! `AsyncVectorIndex` runs each call on Tokio's blocking thread pool
+//! (`spawn_blocking`) and awaits it instead.
+//!
+//! # Synchronization
+//!
+//! As with `ReadHandle` and `WriteHandle`, the index sits behind a
+//! reader-writer lock: searches hold it shared for their own duration, writes
+//! hold it exclusively for one call, so searches never overlap a remap. The
+//! lock is taken on the blocking thread, never on an executor thread, and the
+//! length is published through an atomic so `len()` never waits.
+//!
+//! The searches themselves still block as described in `handle.rs`: they wait
+//! for the write in progress, though not for a `flush()`'s `fsync`, and a
+//! write waits for the searches in progress. Each waiting call holds a thread
+//! of the blocking pool meanwhile, so a long write can tie up one thread per
+//! queued search. Writes from different clones also take a mutex, which keeps
+//! them out while a flush syncs with the lock released.
+//!
+//! Blocking tasks cannot borrow from the caller, so vectors and queries are
+//! passed by value. A panic inside a call is resumed in the awaiting task.
+
+use crate::error::{ErrorKind, Tagged};
+use crate::{IndexOptions, SearchOptions, SearchResult, VectorIndex};
+use anyhow::Result;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+
+/// State shared by the clones of an `AsyncVectorIndex`
+#[derive(Debug)]
+struct Shared {
+    index: RwLock<VectorIndex>,
+
+    /// Held by every write for its whole duration, including a flush's sync
+    writer: Mutex<()>,
+
+    /// Vectors visible to searches, published after each completed write
+    len: AtomicU64,
+}
+
+/// A cloneable index handle whose calls run on Tokio's blocking thread pool
+///
+/// Clones share one index, so the handle can be stored in server state and
+/// used from any task. Writes from several clones are serialized. Methods
+/// must be awaited inside a Tokio runtime.
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// use chassis_core::{AsyncVectorIndex, IndexOptions};
+///
+/// let index = AsyncVectorIndex::open("embeddings.chassis", 4, IndexOptions::default()).await?;
+/// index.add(vec![1.0; 4]).await?;
+/// index.flush().await?;
+/// let results = index.search(vec![1.0; 4], 10).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AsyncVectorIndex {
+    shared: Arc<Shared>,
+}
+
+impl AsyncVectorIndex {
+    /// Wrap an open index
+    pub fn new(index: VectorIndex) -> Self {
+        let len = AtomicU64::new(index.len());
+        let writer = Mutex::new(());
+        Self { shared: Arc::new(Shared { index: RwLock::new(index), writer, len }) }
+    }
+
+    /// Open or create an index on a blocking thread; see `VectorIndex::open()`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for the same reasons as `VectorIndex::open()`.
+    pub async fn open<P: AsRef<Path>>(path: P, dims: u32, options: IndexOptions) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let index = run_blocking(move || VectorIndex::open(path, dims, options)).await??;
+        Ok(Self::new(index))
+    }
+
+    /// Add a vector; searches started after it completes see it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for the same reasons as `VectorIndex::add()`.
+    pub async fn add(&self, vector: Vec<f32>) -> Result<u64> {
+        self.write(move |index| index.add(&vector)).await?
+    }
+
+    /// Add a vector under a key; see `VectorIndex::add_with_key()`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for the same reasons as `VectorIndex::add_with_key()`.
+    pub async fn add_with_key(&self, key: String, vector: Vec<f32>) -> Result<u64> {
+        self.write(move |index| index.add_with_key(&key, &vector)).await?
+    }
+
+    /// Delete a vector; see `VectorIndex::delete()`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for the same reasons as `VectorIndex::delete()`.
+    pub async fn delete(&self, id: u64) -> Result<bool> {
+        self.write(move |index| index.delete(id)).await?
+    }
+
+    /// Search for k nearest neighbors; see `VectorIndex::search()`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions
+    pub async fn search(&self, query: Vec<f32>, k: usize) -> Result<Vec<SearchResult>> {
+        self.read(move |index| index.search(&query, k)).await?
+    }
+
+    /// Search with per-search options; see `VectorIndex::search_with_options()`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions
+    pub async fn search_with_options(
+        &self,
+        query: Vec<f32>,
+        k: usize,
+        options: SearchOptions,
+    ) -> Result<Vec<SearchResult>> {
+        self.read(move |index| index.search_with_options(&query, k, &options)).await?
+    }
+
+    /// Flush all changes to disk; searches run while the file syncs
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the flush fails.
+    pub async fn flush(&self) -> Result<()> {
+        let shared = Arc::clone(&self.shared);
+        run_blocking(move || {
+            let _writer = shared.writer.lock().unwrap_or_else(PoisonError::into_inner);
+            VectorIndex::flush_locked(&shared.index)
+        })
+        .await?
+    }
+
+    /// Run `f` with shared access to the index on a blocking thread
+    ///
+    /// Use this for calls without an async wrapper, or several reads that
+    /// must see the same state.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error only if the runtime shut down before `f` ran.
+    pub async fn read<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&VectorIndex) -> R + Send + 'static,
+    ) -> Result<R> {
+        let shared = Arc::clone(&self.shared);
+        run_blocking(move || f(&shared.index.read().unwrap_or_else(PoisonError::into_inner))).await
+    }
+
+    /// Run `f` with exclusive access to the index on a blocking thread
+    ///
+    /// Searches wait until `f` returns, so keep it short: for example, add a
+    /// small batch of vectors in one go.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error only if the runtime shut down before `f` ran.
+    pub async fn write<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut VectorIndex) -> R + Send + 'static,
+    ) -> Result<R> {
+        let shared = Arc::clone(&self.shared);
+        run_blocking(move || {
+            // Inserts write in crash-consistent order, so a panicked one
+            // leaves the state a crash would (see `handle.rs`)
+            let _writer = shared.writer.lock().unwrap_or_else(PoisonError::into_inner);
+            let mut index = shared.index.write().unwrap_or_else(PoisonError::into_inner);
+            let result = f(&mut index);
+            shared.len.store(index.len(), Ordering::Release);
+            result
+        })
+        .await
+    }
+
+    /// Get the number of vectors visible to searches (never blocks)
+    pub fn len(&self) -> u64 {
+        self.shared.len.load(Ordering::Acquire)
+    }
+
+    /// Check if the index is empty (never blocks)
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Take the index back once every other clone has been dropped
+    ///
+    /// # Errors
+    ///
+    /// Returns the handle unchanged if another clone is still alive.
+    pub fn into_inner(self) -> Result<VectorIndex, Self> {
+        Arc::try_unwrap(self.shared)
+            .map(|shared| shared.index.into_inner().unwrap_or_else(PoisonError::into_inner))
+            .map_err(|shared| Self { shared })
+    }
+}
+
+/// Run `f` on the blocking thread pool, resuming its panic in the caller
+async fn run_blocking<R: Send + 'static>(f: impl FnOnce() -> R + Send + 'static) -> Result<R> {
+    match tokio::task::spawn_blocking(f).await {
+        Ok(result) => Ok(result),
+        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+        Err(_) => anyhow::bail!(Tagged::new(
+            ErrorKind::Other,
+            "Index call cancelled: the Tokio runtime is shutting down"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_clones_search_while_another_adds() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let index =
+            AsyncVectorIndex::open(temp_file.path(), 8, IndexOptions::default()).await.unwrap();
+        index.add(vec![0.0; 8]).await.unwrap();
+
+        let searcher = {
+            let index = index.clone();
+            tokio::spawn(async move {
+                let mut seen = 0;
+                while seen < 200 {
+                    let len = index.len();
+                    assert!(len >= seen, "length went backwards");
+                    let results = index.search(vec![0.0; 8], 5).await.unwrap();
+                    assert_eq!(results[0].id, 0);
+                    seen = len;
+                }
+            })
+        };
+
+        // Enough vectors to grow and remap the file under the searches
+        for i in 1..200 {
+            index.add(vec![i as f32; 8]).await.unwrap();
+        }
+        searcher.await.unwrap();
+
+        index.flush().await.unwrap();
+        assert_eq!(index.read(VectorIndex::durable_len).await.unwrap(), 200);
+        assert!(index.delete(3).await.unwrap());
+        let err = index.search(vec![0.0; 4], 5).await.unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::DimensionMismatch);
+
+        let index = index.into_inner().unwrap();
+        assert_eq!(index.len(), 200);
+        assert_eq!(index.deleted_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_panic_in_call_resumes_in_caller() {
+        let index =
+            AsyncVectorIndex::new(VectorIndex::in_memory(4, IndexOptions::default()).unwrap());
+        let other = index.clone();
+        let task = tokio::spawn(async move { other.write(|_| panic!("boom")).await });
+        assert!(task.await.unwrap_err().is_panic());
+
+        // The lock is usable after the panic
+        index.add(vec![1.0; 4]).await.unwrap();
+        assert_eq!(index.len(), 1);
+    }
+}
@@ -0,0 +1,53 @@
+//! Cooperative cancellation of searches and bulk builds.
+//!
+//! A `CancelToken` is a shared flag: the application keeps one clone, passes
+//! another to the operation (`SearchOptions::cancel`, `HnswBuilder::cancel_token`),
+//! and calls `cancel()` from any thread when the result is no longer wanted,
+//! for example when the user typed another character. The operation checks
+//! the flag between units of work and fails with `ErrorKind::Cancelled`.
+
+use crate::error::{ErrorKind, Tagged};
+use anyhow::Result;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Shared flag that stops the operations it was passed to
+///
+/// Clones share the flag. A token stays cancelled: create a new one for the
+/// next operation.
+///
+/// ```
+/// use chassis_core::CancelToken;
+///
+/// let token = CancelToken::new();
+/// let handle = token.clone();
+/// std::thread::spawn(move || handle.cancel()).join().unwrap();
+/// assert!(token.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// A token that is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask every operation holding a clone of this token to stop
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether `cancel()` was called on this token or a clone
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Fail with `Cancelled` if the token was cancelled
+    pub(crate) fn check(&self, operation: &str) -> Result<()> {
+        if self.is_cancelled() {
+            anyhow::bail!(Tagged::new(ErrorKind::Cancelled, format!("{} cancelled", operation)));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,494 @@
+//! Several independent indexes in one file, addressed by name.
+//!
+//! A collection file starts with a 64 KiB catalog followed by one index image
+//! per collection. Each image is a complete Chassis index (header, vectors,
+//! graph, metadata) with its own dimensions and HNSW parameters, mapped from
+//! its own page-aligned region of the file:
+//!
+//! ```text
+//! [catalog 64 KiB][image "docs" ...slack][image "images" ...slack]...
+//! ```
+//!
+//! The file holds a single exclusive lock for all collections, and
+//! `Collections::flush()` commits them all. An image that outgrows its region
+//! is copied to a larger region at the end of the file; the catalog points at
+//! the new region only once the copy is flushed, so a crash leaves the last
+//! flushed copy in place.
+
+use crate::error::{ErrorKind, Tagged};
+use crate::header::HEADER_SIZE;
+use crate::storage::{Storage, Window};
+use crate::{Distance, IndexOptions, VectorIndex};
+use anyhow::{Context, Result};
+use fs2::FileExt;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Magic bytes at the start of a collection file
+pub const COLLECTIONS_MAGIC: &[u8; 8] = b"CHASCOLL";
+
+/// Collection file format version
+const CATALOG_VERSION: u32 = 1;
+
+/// Size of the catalog at the start of the file
+const CATALOG_SIZE: usize = 64 * 1024;
+
+/// Offset of the first catalog entry
+const ENTRIES_OFFSET: usize = 64;
+
+/// Size of one catalog entry
+const ENTRY_SIZE: usize = 128;
+
+/// Longest collection name in bytes (UTF-8)
+pub const MAX_NAME_LEN: usize = 64;
+
+/// Most collections a file can hold
+pub const MAX_COLLECTIONS: usize = (CATALOG_SIZE - ENTRIES_OFFSET) / ENTRY_SIZE;
+
+/// Region reserved for a new collection (sparse until written)
+///
+/// Covers the vector zone slack in front of a new graph zone, so small
+/// collections never move.
+const INITIAL_CAPACITY: u64 = 16 * 1024 * 1024;
+
+/// Catalog entry fields, as offsets within the entry
+const NAME_RANGE: std::ops::Range<usize> = 0..64;
+const WINDOW_OFFSET: usize = 64; // base, capacity, len: 3 x u64
+const DIMS_RANGE: std::ops::Range<usize> = 88..92;
+const MAX_CONNECTIONS_RANGE: std::ops::Range<usize> = 92..94;
+const MAX_LAYERS_OFFSET: usize = 94; // u8, 0 = 16
+const EF_CONSTRUCTION_RANGE: std::ops::Range<usize> = 96..100;
+const EF_SEARCH_RANGE: std::ops::Range<usize> = 100..104;
+const INPUT_DIMENSIONS_RANGE: std::ops::Range<usize> = 104..108;
+const MAX_CONNECTIONS_0_RANGE: std::ops::Range<usize> = 108..110; // 0 = 2 x max_connections
+
+/// A file holding several named indexes behind one lock
+///
+/// # Example
+///
+/// ```no_run
+/// use chassis_core::{Collections, IndexOptions};
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let mut file = Collections::open("app.chassis")?;
+/// file.create("text", 768, IndexOptions::default())?;
+/// file.create("images", 512, IndexOptions::default())?;
+///
+/// file.get_mut("text").unwrap().add(&vec![0.1; 768])?;
+/// file.get_mut("images").unwrap().add(&vec![0.2; 512])?;
+/// file.flush()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Collections {
+    // Indexes hold clones of `file`; they are dropped before it is unlocked
+    names: Vec<String>,
+    indexes: Vec<VectorIndex>,
+    file: File,
+    path: PathBuf,
+}
+
+impl Collections {
+    /// Open a collection file, creating an empty one if it does not exist
+    ///
+    /// Collections are reopened with the HNSW parameters, element type and
+    /// normalization they were created with, and default version policy and memory mode.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The file is locked by another process
+    /// - The file exists but is not a collection file (e.g. a plain index)
+    /// - A collection image is corrupted
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("Failed to open collection file: {}", path.display()))?;
+
+        file.try_lock_exclusive().context(Tagged::new(
+            ErrorKind::Locked,
+            "Chassis file is already open by another process",
+        ))?;
+
+        let mut collections =
+            Self { names: Vec::new(), indexes: Vec::new(), file, path: path.to_path_buf() };
+
+        if collections.file.metadata()?.len() == 0 {
+            let mut catalog = [0u8; ENTRIES_OFFSET];
+            catalog[0..8].copy_from_slice(COLLECTIONS_MAGIC);
+            catalog[8..12].copy_from_slice(&CATALOG_VERSION.to_le_bytes());
+            collections.write_at(0, &catalog)?;
+            collections.file.set_len(CATALOG_SIZE as u64)?;
+            collections.file.sync_all()?;
+        }
+
+        let mut catalog = vec![0u8; CATALOG_SIZE];
+        collections.read_catalog(&mut catalog)?;
+
+        let count = u32::from_le_bytes(catalog[12..16].try_into().unwrap()) as usize;
+        if count > MAX_COLLECTIONS {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                format!("Collection catalog lists {} collections (max {})", count, MAX_COLLECTIONS)
+            ));
+        }
+
+        for slot in 0..count {
+            let offset = ENTRIES_OFFSET + slot * ENTRY_SIZE;
+            let entry = &catalog[offset..offset + ENTRY_SIZE];
+            let index = collections.open_entry(offset as u64, entry)?;
+            collections.names.push(decode_name(entry)?);
+            collections.indexes.push(index);
+        }
+
+        Ok(collections)
+    }
+
+    /// Create a new, empty collection and return it
+    ///
+    /// The collection is recorded in the catalog immediately; its vectors
+    /// become durable on `flush()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - `name` is empty, longer than `MAX_NAME_LEN` bytes, or contains NUL
+    /// - A collection named `name` already exists
+    /// - The file already holds `MAX_COLLECTIONS` collections
+    /// - `options.input_dimensions` is smaller than `dims`
+    /// - `options.distance` is not Euclidean (`open()` could not supply a custom one)
+    /// - `options.page_size` is not 4096
+    pub fn create(
+        &mut self,
+        name: &str,
+        dims: u32,
+        options: IndexOptions,
+    ) -> Result<&mut VectorIndex> {
+        if name.is_empty() || name.len() > MAX_NAME_LEN || name.contains('\0') {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "Collection name must be 1-{} bytes without NUL, got {:?}",
+                    MAX_NAME_LEN, name
+                )
+            ));
+        }
+        if self.position(name).is_some() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!("Collection {:?} already exists", name)
+            ));
+        }
+        if self.names.len() >= MAX_COLLECTIONS {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!("Collection file is full ({} collections)", MAX_COLLECTIONS)
+            ));
+        }
+        VectorIndex::check_input_dimensions(dims, &options)?;
+        VectorIndex::check_max_layers(&options)?;
+        VectorIndex::check_max_connections_0(&options)?;
+        if options.distance != Distance::Euclidean {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Collections only support Euclidean distance"
+            ));
+        }
+        if options.page_size != crate::storage::PAGE_SIZE {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Collection indexes use 4 KiB pages"
+            ));
+        }
+
+        let slot = (ENTRIES_OFFSET + self.names.len() * ENTRY_SIZE) as u64;
+        let base = Storage::page_align(self.file.metadata()?.len() as usize) as u64;
+        let window = Window { slot: slot + WINDOW_OFFSET as u64, base, capacity: INITIAL_CAPACITY };
+        self.file.set_len(base + INITIAL_CAPACITY)?;
+
+        let storage = Storage::open_window(&self.path, &self.file, window, 0, dims, true)?;
+
+        // Entry first, count second: a crash in between leaves an unused region
+        let mut entry = [0u8; ENTRY_SIZE];
+        entry[NAME_RANGE][..name.len()].copy_from_slice(name.as_bytes());
+        entry[WINDOW_OFFSET..WINDOW_OFFSET + 8].copy_from_slice(&base.to_le_bytes());
+        entry[WINDOW_OFFSET + 8..WINDOW_OFFSET + 16]
+            .copy_from_slice(&INITIAL_CAPACITY.to_le_bytes());
+        entry[WINDOW_OFFSET + 16..WINDOW_OFFSET + 24]
+            .copy_from_slice(&(HEADER_SIZE as u64).to_le_bytes());
+        entry[DIMS_RANGE].copy_from_slice(&dims.to_le_bytes());
+        entry[MAX_CONNECTIONS_RANGE].copy_from_slice(&options.max_connections.to_le_bytes());
+        entry[MAX_CONNECTIONS_0_RANGE]
+            .copy_from_slice(&options.max_connections_0.unwrap_or(0).to_le_bytes());
+        entry[MAX_LAYERS_OFFSET] = options.max_layers;
+        entry[EF_CONSTRUCTION_RANGE].copy_from_slice(&to_u32(options.ef_construction)?);
+        entry[EF_SEARCH_RANGE].copy_from_slice(&to_u32(options.ef_search)?);
+        entry[INPUT_DIMENSIONS_RANGE]
+            .copy_from_slice(&options.input_dimensions.unwrap_or(0).to_le_bytes());
+        self.write_at(slot, &entry)?;
+        self.file.sync_data()?;
+
+        let count = self.names.len() as u32 + 1;
+        self.write_at(12, &count.to_le_bytes())?;
+        self.file.sync_data()?;
+
+        let index = VectorIndex::from_storage(storage, options)?;
+        self.names.push(name.to_string());
+        self.indexes.push(index);
+        Ok(self.indexes.last_mut().unwrap())
+    }
+
+    /// Returns the collection named `name`
+    pub fn get(&self, name: &str) -> Option<&VectorIndex> {
+        self.position(name).map(|i| &self.indexes[i])
+    }
+
+    /// Returns the collection named `name` for writing
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut VectorIndex> {
+        self.position(name).map(|i| &mut self.indexes[i])
+    }
+
+    /// Names of all collections, in creation order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+
+    /// Number of collections
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Returns true if the file holds no collections
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Flush every collection to disk
+    ///
+    /// Collections are committed one after another; a crash part way through
+    /// can leave some collections at their previous flush.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first flush error; later collections are not flushed.
+    pub fn flush(&mut self) -> Result<()> {
+        for (name, index) in self.names.iter().zip(&mut self.indexes) {
+            index.flush().with_context(|| format!("Failed to flush collection {:?}", name))?;
+        }
+        Ok(())
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    /// Map the image described by a catalog entry at file offset `slot`
+    fn open_entry(&self, slot: u64, entry: &[u8]) -> Result<VectorIndex> {
+        let field =
+            |offset: usize| u64::from_le_bytes(entry[offset..offset + 8].try_into().unwrap());
+        let u32_at =
+            |range: std::ops::Range<usize>| u32::from_le_bytes(entry[range].try_into().unwrap());
+
+        let window = Window {
+            slot: slot + WINDOW_OFFSET as u64,
+            base: field(WINDOW_OFFSET),
+            capacity: field(WINDOW_OFFSET + 8),
+        };
+        if !window.base.is_multiple_of(crate::storage::PAGE_SIZE as u64) {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                format!("Collection image at unaligned offset {}", window.base)
+            ));
+        }
+        let len = field(WINDOW_OFFSET + 16);
+        let dims = u32_at(DIMS_RANGE);
+
+        let storage = Storage::open_window(&self.path, &self.file, window, len, dims, false)?;
+
+        // The image header records the element type and normalization, even for
+        // empty collections
+        let input_dimensions = u32_at(INPUT_DIMENSIONS_RANGE);
+        let options = IndexOptions {
+            max_connections: u16::from_le_bytes(entry[MAX_CONNECTIONS_RANGE].try_into().unwrap()),
+            max_connections_0: match u16::from_le_bytes(
+                entry[MAX_CONNECTIONS_0_RANGE].try_into().unwrap(),
+            ) {
+                0 => None,
+                m0 => Some(m0),
+            },
+            max_layers: match entry[MAX_LAYERS_OFFSET] {
+                0 => IndexOptions::default().max_layers,
+                layers => layers,
+            },
+            ef_construction: u32_at(EF_CONSTRUCTION_RANGE) as usize,
+            ef_search: u32_at(EF_SEARCH_RANGE) as usize,
+            input_dimensions: (input_dimensions != 0).then_some(input_dimensions),
+            element_type: storage.element_type(),
+            normalize: storage.normalized(),
+            ..IndexOptions::default()
+        };
+
+        VectorIndex::from_storage(storage, options)
+    }
+
+    fn read_catalog(&self, catalog: &mut [u8]) -> Result<()> {
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(catalog).map_err(|_| {
+            Tagged::new(ErrorKind::Corrupted, "Collection file is shorter than its catalog")
+        })?;
+
+        if &catalog[0..8] != COLLECTIONS_MAGIC {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                format!("Not a collection file: {}", self.path.display())
+            ));
+        }
+        let version = u32::from_le_bytes(catalog[8..12].try_into().unwrap());
+        if version != CATALOG_VERSION {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::IncompatibleVersion,
+                format!("Unsupported collection catalog version {}", version)
+            ));
+        }
+        Ok(())
+    }
+
+    fn write_at(&self, offset: u64, bytes: &[u8]) -> Result<()> {
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(bytes).context("Failed to write collection catalog")
+    }
+}
+
+impl Drop for Collections {
+    fn drop(&mut self) {
+        // Release the mappings before giving up the lock
+        self.indexes.clear();
+        let _ = self.file.unlock();
+    }
+}
+
+fn decode_name(entry: &[u8]) -> Result<String> {
+    let name = &entry[NAME_RANGE];
+    let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+    String::from_utf8(name[..len].to_vec())
+        .map_err(|_| Tagged::new(ErrorKind::Corrupted, "Collection name is not UTF-8").into())
+}
+
+fn to_u32(value: usize) -> Result<[u8; 4]> {
+    u32::try_from(value).map(u32::to_le_bytes).map_err(|_| {
+        Tagged::new(ErrorKind::InvalidArgument, format!("Parameter {} exceeds u32", value)).into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_collections_reopen_with_own_dims_and_params() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        {
+            let mut file = Collections::open(path).unwrap();
+            let text = file
+                .create(
+                    "text",
+                    8,
+                    IndexOptions {
+                        max_connections: 8,
+                        max_connections_0: Some(24),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            for i in 0..20 {
+                text.add(&[i as f32; 8]).unwrap();
+            }
+            let images = file.create("images", 3, IndexOptions::default()).unwrap();
+            images.add(&[1.0, 2.0, 3.0]).unwrap();
+            file.flush().unwrap();
+        }
+
+        let file = Collections::open(path).unwrap();
+        assert_eq!(file.names().collect::<Vec<_>>(), ["text", "images"]);
+
+        let text = file.get("text").unwrap();
+        assert_eq!(text.len(), 20);
+        assert_eq!(text.dimensions(), 8);
+        assert_eq!(text.options().max_connections_0, Some(24));
+        assert_eq!(text.search(&[7.0; 8], 1).unwrap()[0].id, 7);
+
+        let images = file.get("images").unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images.dimensions(), 3);
+        assert_eq!(images.options().max_connections_0, Some(32));
+        assert!(file.get("audio").is_none());
+    }
+
+    #[test]
+    fn test_collection_grows_past_its_region() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        let dims = 4096;
+        let options =
+            IndexOptions { max_connections: 8, ef_construction: 16, ..Default::default() };
+
+        {
+            let mut file = Collections::open(path).unwrap();
+            file.create("big", dims, options.clone()).unwrap();
+            file.create("small", 2, IndexOptions::default()).unwrap();
+            file.get_mut("small").unwrap().add(&[1.0, 1.0]).unwrap();
+
+            // 16 KiB per vector: 600 vectors push the graph zone past the 16 MiB region
+            let big = file.get_mut("big").unwrap();
+            for i in 0..600 {
+                big.add(&vec![i as f32; dims as usize]).unwrap();
+            }
+            file.flush().unwrap();
+        }
+
+        // "big" moved behind "small"
+        let file_len = std::fs::metadata(path).unwrap().len();
+        assert!(file_len > CATALOG_SIZE as u64 + 2 * INITIAL_CAPACITY);
+
+        let file = Collections::open(path).unwrap();
+        let big = file.get("big").unwrap();
+        assert_eq!(big.len(), 600);
+        assert_eq!(big.search(&vec![123.0; dims as usize], 1).unwrap()[0].id, 123);
+        assert_eq!(file.get("small").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_collections_reject_bad_names_and_plain_indexes() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut file = Collections::open(temp_file.path()).unwrap();
+        file.create("a", 4, IndexOptions::default()).unwrap();
+
+        for name in ["", "a", &"x".repeat(MAX_NAME_LEN + 1)] {
+            let err = file.create(name, 4, IndexOptions::default()).unwrap_err();
+            assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+        }
+
+        // The collection file is locked as a whole
+        let err = Collections::open(temp_file.path()).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Locked);
+        drop(file);
+
+        let plain = NamedTempFile::new().unwrap();
+        VectorIndex::open(plain.path(), 4, IndexOptions::default()).unwrap().flush().unwrap();
+        let err = Collections::open(plain.path()).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Corrupted);
+    }
+}
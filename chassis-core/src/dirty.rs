@@ -0,0 +1,107 @@
+//! Tracking of the mapped pages written since the last flush.
+//!
+//! `Storage` marks every byte range it writes through the mapping. A flush
+//! then syncs only those pages instead of the whole mapping, and
+//! `flush_async()` hands them to the OS for write-back before syncing the file
+//! on a background thread. Ranges are page-aligned and merged; past
+//! `MAX_RANGES` the closest neighbours are coalesced, so the tracker stays
+//! small and may only over-approximate the dirty set.
+
+use crate::storage::PAGE_SIZE;
+use std::ops::Range;
+
+/// Most disjoint ranges kept before neighbours are merged
+const MAX_RANGES: usize = 32;
+
+/// Sorted, disjoint, page-aligned byte ranges of the mapping that are dirty
+#[derive(Debug, Default)]
+pub(crate) struct DirtyPages {
+    ranges: Vec<Range<usize>>,
+}
+
+impl DirtyPages {
+    /// Record that `offset..offset + len` was written.
+    pub(crate) fn mark(&mut self, offset: usize, len: usize) {
+        if len == 0 {
+            return;
+        }
+        let start = offset - offset % PAGE_SIZE;
+        let end = offset.saturating_add(len).next_multiple_of(PAGE_SIZE);
+
+        // Absorb every range that overlaps or touches the new one
+        let first = self.ranges.partition_point(|r| r.end < start);
+        let last = self.ranges.partition_point(|r| r.start <= end);
+        let merged = if first < last {
+            self.ranges[first].start.min(start)..self.ranges[last - 1].end.max(end)
+        } else {
+            start..end
+        };
+        self.ranges.splice(first..last, [merged]);
+
+        if self.ranges.len() > MAX_RANGES {
+            self.merge_closest();
+        }
+    }
+
+    /// Merge the two neighbouring ranges with the smallest gap between them.
+    fn merge_closest(&mut self) {
+        let Some(i) =
+            (1..self.ranges.len()).min_by_key(|&i| self.ranges[i].start - self.ranges[i - 1].end)
+        else {
+            return;
+        };
+        let next = self.ranges.remove(i);
+        self.ranges[i - 1].end = next.end;
+    }
+
+    /// Dirty ranges, in address order.
+    pub(crate) fn ranges(&self) -> &[Range<usize>] {
+        &self.ranges
+    }
+
+    /// Forget every range (after they were synced or handed to the OS).
+    pub(crate) fn clear(&mut self) {
+        self.ranges.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranges_are_page_aligned_and_merged() {
+        let mut dirty = DirtyPages::default();
+        dirty.mark(10, 4);
+        dirty.mark(PAGE_SIZE * 4 + 1, PAGE_SIZE);
+        assert_eq!(dirty.ranges(), [0..PAGE_SIZE, PAGE_SIZE * 4..PAGE_SIZE * 6]);
+
+        // Touching and overlapping writes join their neighbours
+        dirty.mark(PAGE_SIZE, PAGE_SIZE * 3);
+        assert_eq!(dirty.ranges(), std::slice::from_ref(&(0..PAGE_SIZE * 6)));
+
+        dirty.mark(0, 0);
+        assert_eq!(dirty.ranges().len(), 1);
+        dirty.clear();
+        assert!(dirty.ranges().is_empty());
+    }
+
+    #[test]
+    fn test_range_count_is_bounded() {
+        let mut dirty = DirtyPages::default();
+        for page in 0..100 {
+            // Every other page, with one wider gap in the middle
+            let gap = if page >= 50 { 8 } else { 2 };
+            dirty.mark(page * gap * PAGE_SIZE, 1);
+        }
+        assert_eq!(dirty.ranges().len(), MAX_RANGES);
+        assert!(dirty.ranges().windows(2).all(|w| w[0].end < w[1].start));
+
+        // Every marked page is still covered
+        for page in 0..100 {
+            let gap = if page >= 50 { 8 } else { 2 };
+            let offset = page * gap * PAGE_SIZE;
+            assert!(dirty.ranges().iter().any(|r| r.contains(&offset)));
+        }
+    }
+}
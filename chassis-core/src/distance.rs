@@ -10,12 +10,364 @@
 //!
 //! Expected speedup: 4-6x on high-dimensional vectors (768-1536D)
 
-/// Distance metric for vector comparison
-#[derive(Debug, Clone, Copy)]
-pub enum DistanceMetric {
+use crate::element::ElementType;
+
+/// Longest name a `CustomDistance` can record in the file header, in bytes
+pub const MAX_DISTANCE_NAME_LEN: usize = 64;
+
+/// Prefix of the names built-in distances record; custom names cannot use it
+const BUILTIN_NAME_PREFIX: &str = "chassis:";
+
+/// Name `Distance::DotProduct` records in the file header
+const DOT_PRODUCT_NAME: &str = "chassis:dot-product";
+
+/// Name `Distance::Manhattan` records in the file header
+const MANHATTAN_NAME: &str = "chassis:manhattan";
+
+/// Name `Distance::SquaredEuclidean` records in the file header
+const SQUARED_EUCLIDEAN_NAME: &str = "chassis:squared-euclidean";
+
+/// Distance function an index is built and searched with
+///
+/// Graph edges are chosen with this function, so construction and search must
+/// agree on it. A custom function's name is recorded in the file, and opening
+/// the file with a different distance is an error.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Distance {
+    /// L2 distance, SIMD-accelerated and computed on half-width vectors without widening
+    #[default]
     Euclidean,
-    Cosine,
+
+    /// Negated inner product `-(a·b)`, for maximum inner product search (MIPS)
+    ///
+    /// For embeddings scored by dot product, whose norms carry meaning (such
+    /// as recommendation models); use `normalize` instead when only the
+    /// direction matters. The graph is built and searched with the same
+    /// order, so the nearest results are those with the largest inner
+    /// product. Distances are negative for vectors pointing the same way, and
+    /// `search_within()` accepts negative radii: `-0.8` finds inner products
+    /// of at least 0.8.
     DotProduct,
+
+    /// L1 distance, the sum of absolute component differences, SIMD-accelerated
+    ///
+    /// Less sensitive than L2 to a few components that differ by a lot.
+    Manhattan,
+
+    /// L2 distance squared
+    ///
+    /// Ranks exactly like `Euclidean`, which is searched with the same squared
+    /// distances internally, but returns them without the square root:
+    /// `search_within()` radii are squared distances too.
+    SquaredEuclidean,
+
+    /// A user-provided function
+    Custom(CustomDistance),
+}
+
+impl Distance {
+    /// Distance between `a` and `b` under this function
+    #[inline]
+    pub fn compute(&self, a: &[f32], b: &[f32]) -> f32 {
+        let metric = Metric::new(*self, false, ElementType::F32);
+        metric.reported(metric.compute(a, b))
+    }
+
+    /// Name recorded in the file header; `None` for Euclidean, which records nothing
+    pub(crate) fn name(&self) -> Option<&'static str> {
+        match self {
+            Self::Euclidean => None,
+            Self::DotProduct => Some(DOT_PRODUCT_NAME),
+            Self::Manhattan => Some(MANHATTAN_NAME),
+            Self::SquaredEuclidean => Some(SQUARED_EUCLIDEAN_NAME),
+            Self::Custom(custom) => Some(custom.name),
+        }
+    }
+
+    /// Describes the distance recorded as `name`, for error messages
+    pub(crate) fn describe(name: Option<&str>) -> String {
+        match name {
+            None => "Euclidean distance".to_string(),
+            Some(DOT_PRODUCT_NAME) => "dot product distance".to_string(),
+            Some(MANHATTAN_NAME) => "Manhattan distance".to_string(),
+            Some(SQUARED_EUCLIDEAN_NAME) => "squared Euclidean distance".to_string(),
+            Some(name) => format!("custom distance {:?}", name),
+        }
+    }
+
+    /// Whether distances can be negative (and so can search radii)
+    pub(crate) fn can_be_negative(&self) -> bool {
+        matches!(self, Self::DotProduct)
+    }
+}
+
+/// A user-provided distance function and the name identifying it
+///
+/// The function receives two vectors of the index's dimensions (half-width
+/// vectors are widened to `f32` first) and returns a non-negative distance,
+/// smaller meaning closer. It should be symmetric: construction compares
+/// stored vectors in either order.
+///
+/// The name is what the file records, so it must change whenever the
+/// function's results do, for example when the weights of a weighted
+/// Euclidean distance are retrained. It must be 1 to
+/// [`MAX_DISTANCE_NAME_LEN`] bytes without NUL characters, and may not start
+/// with `chassis:`, which is reserved for built-in distances.
+///
+/// ```
+/// use chassis_core::{CustomDistance, Distance};
+///
+/// fn weighted(a: &[f32], b: &[f32]) -> f32 {
+///     const WEIGHTS: [f32; 3] = [4.0, 1.0, 0.25];
+///     a.iter().zip(b).zip(WEIGHTS).map(|((x, y), w)| w * (x - y) * (x - y)).sum::<f32>().sqrt()
+/// }
+///
+/// let distance = Distance::Custom(CustomDistance::new("weighted-l2-v1", weighted));
+/// assert_eq!(distance.compute(&[0.0, 0.0, 0.0], &[1.0, 0.0, 0.0]), 2.0);
+/// ```
+#[derive(Clone, Copy)]
+pub struct CustomDistance {
+    name: &'static str,
+    function: fn(&[f32], &[f32]) -> f32,
+}
+
+impl CustomDistance {
+    /// Wraps `function` under `name`
+    pub const fn new(name: &'static str, function: fn(&[f32], &[f32]) -> f32) -> Self {
+        Self { name, function }
+    }
+
+    /// Name recorded in the file header
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Whether `name` can be recorded in the file header
+    pub(crate) fn has_valid_name(&self) -> bool {
+        !self.name.is_empty()
+            && self.name.len() <= MAX_DISTANCE_NAME_LEN
+            && !self.name.contains('\0')
+            && !self.name.starts_with(BUILTIN_NAME_PREFIX)
+    }
+}
+
+/// Distances are identified by name, as in the file header
+impl PartialEq for CustomDistance {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for CustomDistance {}
+
+impl std::fmt::Debug for CustomDistance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("CustomDistance").field(&self.name).finish()
+    }
+}
+
+/// Kernel an index compares vectors with, resolved from its distance and normalization
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Metric {
+    /// Ranked by squared L2, reported as L2
+    Euclidean,
+
+    /// Ranked and reported by squared L2
+    SquaredEuclidean,
+
+    Manhattan,
+
+    /// `1 - a·b`: the cosine distance of vectors normalized on insert
+    UnitCosine,
+
+    /// Number of components whose signs differ: the distance of `Binary` vectors
+    Hamming,
+
+    /// `-(a·b)`: maximum inner product search
+    InnerProduct,
+
+    Custom(CustomDistance),
+}
+
+impl Metric {
+    /// Normalized vectors switch Euclidean distance to the dot product kernel,
+    /// and binary ones to Hamming distance
+    pub(crate) fn new(distance: Distance, normalized: bool, element_type: ElementType) -> Self {
+        match distance {
+            Distance::Euclidean if element_type == ElementType::Binary => Self::Hamming,
+            Distance::Euclidean if normalized => Self::UnitCosine,
+            Distance::Euclidean => Self::Euclidean,
+            Distance::SquaredEuclidean => Self::SquaredEuclidean,
+            Distance::Manhattan => Self::Manhattan,
+            Distance::DotProduct => Self::InnerProduct,
+            Distance::Custom(custom) => Self::Custom(custom),
+        }
+    }
+
+    /// Ranking distance between `a` and `b`; see `reported()`
+    #[inline]
+    pub(crate) fn compute(&self, a: &[f32], b: &[f32]) -> f32 {
+        match self {
+            Self::Euclidean | Self::SquaredEuclidean => squared_euclidean(a, b),
+            Self::Manhattan => manhattan_distance(a, b),
+            // Rounding can push the dot product of near-identical vectors past 1
+            Self::UnitCosine => (1.0 - dot_product(a, b)).max(0.0),
+            Self::Hamming => {
+                a.iter().zip(b).filter(|&(x, y)| (*x > 0.0) != (*y > 0.0)).count() as f32
+            }
+            Self::InnerProduct => -dot_product(a, b),
+            Self::Custom(custom) => (custom.function)(a, b),
+        }
+    }
+
+    /// The distance callers see for a ranking distance
+    ///
+    /// Searches compare the squared L2 distance instead of L2, saving a
+    /// square root per comparison, and take it only for returned results.
+    #[inline]
+    pub(crate) fn reported(&self, ranked: f32) -> f32 {
+        match self {
+            Self::Euclidean => ranked.sqrt(),
+            _ => ranked,
+        }
+    }
+
+    /// The ranking distance of a distance callers gave, such as a search radius
+    #[inline]
+    pub(crate) fn ranked(&self, reported: f32) -> f32 {
+        match self {
+            Self::Euclidean if reported >= 0.0 => reported * reported,
+            _ => reported,
+        }
+    }
+}
+
+/// Scales `vector` to unit length; returns `false` if it has no direction
+pub(crate) fn normalize(vector: &mut [f32]) -> bool {
+    let norm = dot_product(vector, vector).sqrt();
+    if norm == 0.0 || !norm.is_finite() {
+        return false;
+    }
+
+    vector.iter_mut().for_each(|x| *x /= norm);
+    true
+}
+
+/// Bits of an `f32` that are all set for NaN and the infinities
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+const EXPONENT_MASK: u32 = 0x7f80_0000;
+
+/// Returns `true` if no component of `vector` is NaN or infinite
+///
+/// # Architecture Dispatch
+///
+/// - x86_64 + AVX2: exponent compare, 32 floats per iteration (runtime detection)
+/// - aarch64: NEON exponent compare, 16 floats per iteration
+/// - Fallback: Portable scalar implementation
+#[inline]
+pub(crate) fn all_finite(vector: &[f32]) -> bool {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { all_finite_avx2(vector) };
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        unsafe { all_finite_neon(vector) }
+    }
+
+    #[cfg(not(target_arch = "aarch64"))]
+    all_finite_scalar(vector)
+}
+
+/// Scalar finiteness scan (portable fallback)
+#[inline]
+fn all_finite_scalar(vector: &[f32]) -> bool {
+    vector.iter().all(|x| x.is_finite())
+}
+
+/// AVX2 finiteness scan: a lane is non-finite when its exponent bits are all set
+///
+/// Lanes are OR-ed across the block so the loop has a single branch per
+/// 32 floats.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn all_finite_avx2(vector: &[f32]) -> bool {
+    use std::arch::x86_64::*;
+
+    let len = vector.len();
+    let mut i = 0;
+    let mask = _mm256_set1_epi32(EXPONENT_MASK as i32);
+    let non_finite = |p: *const f32| {
+        // SAFETY: callers pass pointers with 8 readable floats
+        let bits = unsafe { _mm256_loadu_si256(p.cast()) };
+        _mm256_cmpeq_epi32(_mm256_and_si256(bits, mask), mask)
+    };
+
+    while i + 32 <= len {
+        let p = unsafe { vector.as_ptr().add(i) };
+        let hits = unsafe {
+            _mm256_or_si256(
+                _mm256_or_si256(non_finite(p), non_finite(p.add(8))),
+                _mm256_or_si256(non_finite(p.add(16)), non_finite(p.add(24))),
+            )
+        };
+        if _mm256_testz_si256(hits, hits) == 0 {
+            return false;
+        }
+        i += 32;
+    }
+
+    while i + 8 <= len {
+        let hits = non_finite(unsafe { vector.as_ptr().add(i) });
+        if _mm256_testz_si256(hits, hits) == 0 {
+            return false;
+        }
+        i += 8;
+    }
+
+    all_finite_scalar(&vector[i..])
+}
+
+/// NEON finiteness scan with the block layout of `all_finite_avx2`
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn all_finite_neon(vector: &[f32]) -> bool {
+    use std::arch::aarch64::*;
+
+    let len = vector.len();
+    let mut i = 0;
+    let mask = vdupq_n_u32(EXPONENT_MASK);
+    let non_finite = |p: *const f32| {
+        // SAFETY: callers pass pointers with 4 readable floats
+        let bits = unsafe { vld1q_u32(p.cast()) };
+        vceqq_u32(vandq_u32(bits, mask), mask)
+    };
+
+    while i + 16 <= len {
+        let p = unsafe { vector.as_ptr().add(i) };
+        let hits = unsafe {
+            vorrq_u32(
+                vorrq_u32(non_finite(p), non_finite(p.add(4))),
+                vorrq_u32(non_finite(p.add(8)), non_finite(p.add(12))),
+            )
+        };
+        if vmaxvq_u32(hits) != 0 {
+            return false;
+        }
+        i += 16;
+    }
+
+    while i + 4 <= len {
+        if vmaxvq_u32(non_finite(unsafe { vector.as_ptr().add(i) })) != 0 {
+            return false;
+        }
+        i += 4;
+    }
+
+    all_finite_scalar(&vector[i..])
 }
 
 /// Compute L2 (Euclidean) distance between two vectors with SIMD acceleration.
@@ -63,7 +415,14 @@ pub fn euclidean_distance_scalar(a: &[f32], b: &[f32]) -> f32 {
     sum.sqrt()
 }
 
-/// AVX2 implementation with 4-way accumulator unrolling (x86_64 only)
+/// AVX2 L2 distance: the square root of `squared_euclidean_avx2`
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2", enable = "fma")]
+unsafe fn euclidean_distance_avx2(a: &[f32], b: &[f32]) -> f32 {
+    unsafe { squared_euclidean_avx2(a, b) }.sqrt()
+}
+
+/// AVX2 squared L2 with 4-way accumulator unrolling (x86_64 only)
 ///
 /// # Optimization Strategy
 ///
@@ -82,7 +441,7 @@ pub fn euclidean_distance_scalar(a: &[f32], b: &[f32]) -> f32 {
 /// Scalar tail: Process final <8 elements
 #[cfg(target_arch = "x86_64")]
 #[target_feature(enable = "avx2", enable = "fma")]
-unsafe fn euclidean_distance_avx2(a: &[f32], b: &[f32]) -> f32 {
+unsafe fn squared_euclidean_avx2(a: &[f32], b: &[f32]) -> f32 {
     use std::arch::x86_64::*;
 
     let len = a.len();
@@ -155,10 +514,17 @@ unsafe fn euclidean_distance_avx2(a: &[f32], b: &[f32]) -> f32 {
         i += 1;
     }
 
-    total.sqrt()
+    total
+}
+
+/// NEON L2 distance: the square root of `squared_euclidean_neon`
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn euclidean_distance_neon(a: &[f32], b: &[f32]) -> f32 {
+    unsafe { squared_euclidean_neon(a, b) }.sqrt()
 }
 
-/// NEON implementation with 4-way accumulator unrolling (aarch64)
+/// NEON squared L2 with 4-way accumulator unrolling (aarch64)
 ///
 /// # Optimization Strategy
 ///
@@ -166,7 +532,7 @@ unsafe fn euclidean_distance_avx2(a: &[f32], b: &[f32]) -> f32 {
 /// NEON processes 4 floats per vector (vs 8 for AVX2), so main loop processes 16 floats.
 #[cfg(target_arch = "aarch64")]
 #[target_feature(enable = "neon")]
-unsafe fn euclidean_distance_neon(a: &[f32], b: &[f32]) -> f32 {
+unsafe fn squared_euclidean_neon(a: &[f32], b: &[f32]) -> f32 {
     use std::arch::aarch64::*;
 
     let len = a.len();
@@ -230,106 +596,717 @@ unsafe fn euclidean_distance_neon(a: &[f32], b: &[f32]) -> f32 {
         i += 1;
     }
 
-    total.sqrt()
+    total
 }
 
-/// Compute cosine distance (1 - cosine similarity).
+/// Compute squared L2 distance: `euclidean_distance` without the square root.
 ///
-/// Returns `1.0` when either vector has zero norm because cosine similarity is
-/// undefined for zero vectors.
+/// Ranks vectors in the same order as L2 distance at less cost per comparison,
+/// for callers that only compare distances (brute-force scans, reranking).
+///
+/// # Architecture Dispatch
+///
+/// - x86_64 + AVX2: 4 accumulators, 32 floats per iteration (runtime detection)
+/// - aarch64: NEON, 4 accumulators, 16 floats per iteration
+/// - Fallback: Portable scalar implementation
 #[inline]
-pub fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+pub fn squared_euclidean(a: &[f32], b: &[f32]) -> f32 {
     debug_assert_eq!(a.len(), b.len());
 
-    let dot: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
-    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
-    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
-    let norm_product = norm_a * norm_b;
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { squared_euclidean_avx2(a, b) };
+        }
+    }
 
-    if norm_product == 0.0 {
-        return 1.0;
+    #[cfg(target_arch = "aarch64")]
+    {
+        unsafe { squared_euclidean_neon(a, b) }
     }
 
-    1.0 - (dot / norm_product)
+    #[cfg(not(target_arch = "aarch64"))]
+    squared_euclidean_scalar(a, b)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_euclidean_distance_basic() {
-        let a = vec![1.0, 2.0, 3.0];
-        let b = vec![4.0, 5.0, 6.0];
+/// Scalar squared L2 (portable fallback)
+#[inline]
+pub fn squared_euclidean_scalar(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
 
-        let dist = euclidean_distance(&a, &b);
-        let expected = ((3.0_f32).powi(2) * 3.0).sqrt();
+/// Compute L1 (Manhattan) distance: the sum of absolute component differences.
+///
+/// # Architecture Dispatch
+///
+/// - x86_64 + AVX2: 4 accumulators, 32 floats per iteration (runtime detection)
+/// - aarch64: NEON absolute difference (`fabd`), 4 accumulators, 16 floats per iteration
+/// - Fallback: Portable scalar implementation
+#[inline]
+pub fn manhattan_distance(a: &[f32], b: &[f32]) -> f32 {
+    debug_assert_eq!(a.len(), b.len());
 
-        assert!((dist - expected).abs() < 1e-6);
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { manhattan_distance_avx2(a, b) };
+        }
     }
 
-    #[test]
-    fn test_cosine_distance() {
-        let a = vec![1.0, 0.0, 0.0];
-        let b = vec![0.0, 1.0, 0.0];
-
-        let dist = cosine_distance(&a, &b);
-        assert!((dist - 1.0).abs() < 1e-6); // Orthogonal vectors
+    #[cfg(target_arch = "aarch64")]
+    {
+        unsafe { manhattan_distance_neon(a, b) }
     }
 
-    #[test]
-    fn test_cosine_distance_zero_norm() {
-        let zero = vec![0.0, 0.0, 0.0];
-        let non_zero = vec![1.0, 0.0, 0.0];
+    #[cfg(not(target_arch = "aarch64"))]
+    manhattan_distance_scalar(a, b)
+}
 
-        let zero_to_zero = cosine_distance(&zero, &zero);
-        let zero_to_non_zero = cosine_distance(&zero, &non_zero);
+/// Scalar L1 distance (portable fallback)
+#[inline]
+pub fn manhattan_distance_scalar(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum()
+}
 
-        assert_eq!(zero_to_zero, 1.0);
-        assert_eq!(zero_to_non_zero, 1.0);
-    }
+/// AVX2 L1 distance with the accumulator layout of `squared_euclidean_avx2`
+///
+/// The absolute value clears the sign bit with `andnot`.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn manhattan_distance_avx2(a: &[f32], b: &[f32]) -> f32 {
+    use std::arch::x86_64::*;
 
-    #[test]
-    fn test_simd_correctness_small() {
-        // Test with small vectors (exercises scalar tail)
-        for size in [3, 7, 15, 31] {
-            let a: Vec<f32> = (0..size).map(|i| i as f32 * 0.1).collect();
-            let b: Vec<f32> = (0..size).map(|i| (i as f32) * 0.1 + 0.5).collect();
+    let len = a.len();
+    let mut i = 0;
+    let sign = _mm256_set1_ps(-0.0);
+    let abs_diff = |pa: *const f32, pb: *const f32| {
+        // SAFETY: callers pass pointers with 8 readable floats
+        let (va, vb) = unsafe { (_mm256_loadu_ps(pa), _mm256_loadu_ps(pb)) };
+        _mm256_andnot_ps(sign, _mm256_sub_ps(va, vb))
+    };
 
-            let simd_result = euclidean_distance(&a, &b);
-            let scalar_result = euclidean_distance_scalar(&a, &b);
+    let mut sum0 = _mm256_setzero_ps();
+    let mut sum1 = _mm256_setzero_ps();
+    let mut sum2 = _mm256_setzero_ps();
+    let mut sum3 = _mm256_setzero_ps();
 
-            assert!(
-                (simd_result - scalar_result).abs() < 1e-5,
-                "SIMD mismatch at size {}: simd={}, scalar={}",
-                size,
-                simd_result,
-                scalar_result
-            );
+    while i + 32 <= len {
+        let (pa, pb) = unsafe { (a.as_ptr().add(i), b.as_ptr().add(i)) };
+        unsafe {
+            sum0 = _mm256_add_ps(sum0, abs_diff(pa, pb));
+            sum1 = _mm256_add_ps(sum1, abs_diff(pa.add(8), pb.add(8)));
+            sum2 = _mm256_add_ps(sum2, abs_diff(pa.add(16), pb.add(16)));
+            sum3 = _mm256_add_ps(sum3, abs_diff(pa.add(24), pb.add(24)));
         }
+        i += 32;
     }
 
-    #[test]
-    fn test_simd_correctness_large() {
-        // Test with large vectors (exercises main loop)
-        for size in [128, 384, 768, 1536] {
-            let a: Vec<f32> = (0..size).map(|i| (i as f32).sin()).collect();
-            let b: Vec<f32> = (0..size).map(|i| (i as f32).cos()).collect();
+    while i + 8 <= len {
+        let (pa, pb) = unsafe { (a.as_ptr().add(i), b.as_ptr().add(i)) };
+        sum0 = _mm256_add_ps(sum0, abs_diff(pa, pb));
+        i += 8;
+    }
 
-            let simd_result = euclidean_distance(&a, &b);
-            let scalar_result = euclidean_distance_scalar(&a, &b);
+    let sum = _mm256_add_ps(_mm256_add_ps(sum0, sum1), _mm256_add_ps(sum2, sum3));
+    horizontal_sum_avx2(sum) + manhattan_distance_scalar(&a[i..], &b[i..])
+}
 
-            // Tolerance accounts for different accumulation order
-            assert!(
-                (simd_result - scalar_result).abs() < 1e-4,
-                "SIMD mismatch at size {}: simd={}, scalar={}, diff={}",
-                size,
-                simd_result,
-                scalar_result,
-                (simd_result - scalar_result).abs()
-            );
-        }
-    }
+/// NEON L1 distance with the accumulator layout of `squared_euclidean_neon`
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn manhattan_distance_neon(a: &[f32], b: &[f32]) -> f32 {
+    use std::arch::aarch64::*;
+
+    let len = a.len();
+    let mut i = 0;
+
+    let mut sum0 = vdupq_n_f32(0.0);
+    let mut sum1 = vdupq_n_f32(0.0);
+    let mut sum2 = vdupq_n_f32(0.0);
+    let mut sum3 = vdupq_n_f32(0.0);
+
+    while i + 16 <= len {
+        unsafe {
+            let pa = a.as_ptr().add(i);
+            let pb = b.as_ptr().add(i);
+            sum0 = vaddq_f32(sum0, vabdq_f32(vld1q_f32(pa), vld1q_f32(pb)));
+            sum1 = vaddq_f32(sum1, vabdq_f32(vld1q_f32(pa.add(4)), vld1q_f32(pb.add(4))));
+            sum2 = vaddq_f32(sum2, vabdq_f32(vld1q_f32(pa.add(8)), vld1q_f32(pb.add(8))));
+            sum3 = vaddq_f32(sum3, vabdq_f32(vld1q_f32(pa.add(12)), vld1q_f32(pb.add(12))));
+        }
+        i += 16;
+    }
+
+    while i + 4 <= len {
+        let (va, vb) = unsafe { (vld1q_f32(a.as_ptr().add(i)), vld1q_f32(b.as_ptr().add(i))) };
+        sum0 = vaddq_f32(sum0, vabdq_f32(va, vb));
+        i += 4;
+    }
+
+    let sum = vaddq_f32(vaddq_f32(sum0, sum1), vaddq_f32(sum2, sum3));
+    vaddvq_f32(sum) + manhattan_distance_scalar(&a[i..], &b[i..])
+}
+
+/// Compute L2 distance between an `f32` query and a stored IEEE half-precision vector.
+///
+/// `stored` holds raw `f16` bits. Components are widened in registers, so the
+/// stored vector is never copied out as `f32`.
+///
+/// # Architecture Dispatch
+///
+/// - x86_64 + AVX2 + F16C: `vcvtph2ps`, 16 components per iteration (runtime detection)
+/// - aarch64 + FP16: `fcvtl`, 8 components per iteration (runtime detection)
+/// - Fallback: Portable scalar implementation
+#[inline]
+pub fn euclidean_distance_f16(query: &[f32], stored: &[u16]) -> f32 {
+    squared_euclidean_f16(query, stored).sqrt()
+}
+
+/// Squared L2 distance between an `f32` query and a stored `f16` vector,
+/// with the kernels of `euclidean_distance_f16`
+#[inline]
+pub fn squared_euclidean_f16(query: &[f32], stored: &[u16]) -> f32 {
+    debug_assert_eq!(query.len(), stored.len());
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2")
+            && is_x86_feature_detected!("fma")
+            && is_x86_feature_detected!("f16c")
+        {
+            return unsafe { squared_euclidean_f16_avx2(query, stored) };
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("fp16") {
+            return unsafe { squared_euclidean_f16_neon(query, stored) };
+        }
+    }
+
+    squared_euclidean_half_scalar(query, stored, crate::element::f16_to_f32)
+}
+
+/// Compute L2 distance between an `f32` query and a stored bfloat16 vector.
+///
+/// `stored` holds raw `bf16` bits, which widen to `f32` with a 16-bit shift.
+///
+/// # Architecture Dispatch
+///
+/// - x86_64 + AVX2: 16 components per iteration (runtime detection)
+/// - aarch64: NEON, 8 components per iteration
+/// - Fallback: Portable scalar implementation
+#[inline]
+pub fn euclidean_distance_bf16(query: &[f32], stored: &[u16]) -> f32 {
+    squared_euclidean_bf16(query, stored).sqrt()
+}
+
+/// Squared L2 distance between an `f32` query and a stored `bf16` vector,
+/// with the kernels of `euclidean_distance_bf16`
+#[inline]
+pub fn squared_euclidean_bf16(query: &[f32], stored: &[u16]) -> f32 {
+    debug_assert_eq!(query.len(), stored.len());
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+            return unsafe { squared_euclidean_bf16_avx2(query, stored) };
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        return unsafe { squared_euclidean_bf16_neon(query, stored) };
+    }
+
+    #[allow(unreachable_code)]
+    squared_euclidean_half_scalar(query, stored, crate::element::bf16_to_f32)
+}
+
+/// Scalar squared L2 for half-width stored vectors (portable fallback)
+#[inline]
+fn squared_euclidean_half_scalar(query: &[f32], stored: &[u16], widen: fn(u16) -> f32) -> f32 {
+    let mut sum = 0.0_f32;
+
+    for (&q, &s) in query.iter().zip(stored) {
+        let diff = q - widen(s);
+        sum += diff * diff;
+    }
+
+    sum
+}
+
+/// Sum the 8 lanes of an AVX register
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+fn horizontal_sum_avx2(v: std::arch::x86_64::__m256) -> f32 {
+    use std::arch::x86_64::*;
+
+    let sum128 = _mm_add_ps(_mm256_castps256_ps128(v), _mm256_extractf128_ps(v, 1));
+    let sum64 = _mm_add_ps(sum128, _mm_movehl_ps(sum128, sum128));
+    _mm_cvtss_f32(_mm_add_ss(sum64, _mm_shuffle_ps(sum64, sum64, 0x55)))
+}
+
+/// AVX2 + F16C implementation for `f16` storage (x86_64 only)
+///
+/// Two accumulators: the conversion adds a port-5 shuffle per 8 components,
+/// so more accumulators do not raise throughput.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2", enable = "fma", enable = "f16c")]
+unsafe fn squared_euclidean_f16_avx2(query: &[f32], stored: &[u16]) -> f32 {
+    use std::arch::x86_64::*;
+
+    let len = query.len();
+    let mut i = 0;
+
+    let mut sum0 = _mm256_setzero_ps();
+    let mut sum1 = _mm256_setzero_ps();
+
+    while i + 16 <= len {
+        let q0 = unsafe { _mm256_loadu_ps(query.as_ptr().add(i)) };
+        let s0 = _mm256_cvtph_ps(unsafe { _mm_loadu_si128(stored.as_ptr().add(i).cast()) });
+        let diff0 = _mm256_sub_ps(q0, s0);
+
+        let q1 = unsafe { _mm256_loadu_ps(query.as_ptr().add(i + 8)) };
+        let s1 = _mm256_cvtph_ps(unsafe { _mm_loadu_si128(stored.as_ptr().add(i + 8).cast()) });
+        let diff1 = _mm256_sub_ps(q1, s1);
+
+        sum0 = _mm256_fmadd_ps(diff0, diff0, sum0);
+        sum1 = _mm256_fmadd_ps(diff1, diff1, sum1);
+
+        i += 16;
+    }
+
+    while i + 8 <= len {
+        let q = unsafe { _mm256_loadu_ps(query.as_ptr().add(i)) };
+        let s = _mm256_cvtph_ps(unsafe { _mm_loadu_si128(stored.as_ptr().add(i).cast()) });
+        let diff = _mm256_sub_ps(q, s);
+        sum0 = _mm256_fmadd_ps(diff, diff, sum0);
+        i += 8;
+    }
+
+    let mut total = horizontal_sum_avx2(_mm256_add_ps(sum0, sum1));
+
+    while i < len {
+        let diff = query[i] - crate::element::f16_to_f32(stored[i]);
+        total += diff * diff;
+        i += 1;
+    }
+
+    total
+}
+
+/// AVX2 implementation for `bf16` storage (x86_64 only)
+///
+/// Widens by zero-extending to 32 bits and shifting into the high half.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2", enable = "fma")]
+unsafe fn squared_euclidean_bf16_avx2(query: &[f32], stored: &[u16]) -> f32 {
+    use std::arch::x86_64::*;
+
+    let widen =
+        |bits: __m128i| _mm256_castsi256_ps(_mm256_slli_epi32(_mm256_cvtepu16_epi32(bits), 16));
+
+    let len = query.len();
+    let mut i = 0;
+
+    let mut sum0 = _mm256_setzero_ps();
+    let mut sum1 = _mm256_setzero_ps();
+
+    while i + 16 <= len {
+        let q0 = unsafe { _mm256_loadu_ps(query.as_ptr().add(i)) };
+        let s0 = widen(unsafe { _mm_loadu_si128(stored.as_ptr().add(i).cast()) });
+        let diff0 = _mm256_sub_ps(q0, s0);
+
+        let q1 = unsafe { _mm256_loadu_ps(query.as_ptr().add(i + 8)) };
+        let s1 = widen(unsafe { _mm_loadu_si128(stored.as_ptr().add(i + 8).cast()) });
+        let diff1 = _mm256_sub_ps(q1, s1);
+
+        sum0 = _mm256_fmadd_ps(diff0, diff0, sum0);
+        sum1 = _mm256_fmadd_ps(diff1, diff1, sum1);
+
+        i += 16;
+    }
+
+    while i + 8 <= len {
+        let q = unsafe { _mm256_loadu_ps(query.as_ptr().add(i)) };
+        let s = widen(unsafe { _mm_loadu_si128(stored.as_ptr().add(i).cast()) });
+        let diff = _mm256_sub_ps(q, s);
+        sum0 = _mm256_fmadd_ps(diff, diff, sum0);
+        i += 8;
+    }
+
+    let mut total = horizontal_sum_avx2(_mm256_add_ps(sum0, sum1));
+
+    while i < len {
+        let diff = query[i] - crate::element::bf16_to_f32(stored[i]);
+        total += diff * diff;
+        i += 1;
+    }
+
+    total
+}
+
+/// NEON implementation for `f16` storage (aarch64 with FP16)
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon", enable = "fp16")]
+unsafe fn squared_euclidean_f16_neon(query: &[f32], stored: &[u16]) -> f32 {
+    use std::arch::aarch64::*;
+
+    let len = query.len();
+    let mut i = 0;
+
+    let mut sum0 = vdupq_n_f32(0.0);
+    let mut sum1 = vdupq_n_f32(0.0);
+
+    while i + 8 <= len {
+        let q0 = unsafe { vld1q_f32(query.as_ptr().add(i)) };
+        let s0 = vcvt_f32_f16(vreinterpret_f16_u16(unsafe { vld1_u16(stored.as_ptr().add(i)) }));
+        let diff0 = vsubq_f32(q0, s0);
+
+        let q1 = unsafe { vld1q_f32(query.as_ptr().add(i + 4)) };
+        let s1 =
+            vcvt_f32_f16(vreinterpret_f16_u16(unsafe { vld1_u16(stored.as_ptr().add(i + 4)) }));
+        let diff1 = vsubq_f32(q1, s1);
+
+        sum0 = vfmaq_f32(sum0, diff0, diff0);
+        sum1 = vfmaq_f32(sum1, diff1, diff1);
+
+        i += 8;
+    }
+
+    let mut total = vaddvq_f32(vaddq_f32(sum0, sum1));
+
+    while i < len {
+        let diff = query[i] - crate::element::f16_to_f32(stored[i]);
+        total += diff * diff;
+        i += 1;
+    }
+
+    total
+}
+
+/// NEON implementation for `bf16` storage (aarch64)
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn squared_euclidean_bf16_neon(query: &[f32], stored: &[u16]) -> f32 {
+    use std::arch::aarch64::*;
+
+    let len = query.len();
+    let mut i = 0;
+
+    let mut sum0 = vdupq_n_f32(0.0);
+    let mut sum1 = vdupq_n_f32(0.0);
+
+    while i + 8 <= len {
+        let q0 = unsafe { vld1q_f32(query.as_ptr().add(i)) };
+        let s0 =
+            vreinterpretq_f32_u32(vshll_n_u16::<16>(unsafe { vld1_u16(stored.as_ptr().add(i)) }));
+        let diff0 = vsubq_f32(q0, s0);
+
+        let q1 = unsafe { vld1q_f32(query.as_ptr().add(i + 4)) };
+        let s1 = vreinterpretq_f32_u32(vshll_n_u16::<16>(unsafe {
+            vld1_u16(stored.as_ptr().add(i + 4))
+        }));
+        let diff1 = vsubq_f32(q1, s1);
+
+        sum0 = vfmaq_f32(sum0, diff0, diff0);
+        sum1 = vfmaq_f32(sum1, diff1, diff1);
+
+        i += 8;
+    }
+
+    let mut total = vaddvq_f32(vaddq_f32(sum0, sum1));
+
+    while i < len {
+        let diff = query[i] - crate::element::bf16_to_f32(stored[i]);
+        total += diff * diff;
+        i += 1;
+    }
+
+    total
+}
+
+/// Compute the dot product of two vectors with SIMD acceleration.
+///
+/// For unit vectors `1.0 - dot_product(a, b)` is their cosine distance, which
+/// is how indexes opened with `IndexOptions::normalize` compare vectors.
+///
+/// # Architecture Dispatch
+///
+/// - x86_64 + AVX2: 4 accumulators, 32 floats per iteration (runtime detection)
+/// - aarch64: NEON, 4 accumulators, 16 floats per iteration
+/// - Fallback: Portable scalar implementation
+#[inline]
+pub fn dot_product(a: &[f32], b: &[f32]) -> f32 {
+    debug_assert_eq!(a.len(), b.len());
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { dot_product_avx2(a, b) };
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        unsafe { dot_product_neon(a, b) }
+    }
+
+    #[cfg(not(target_arch = "aarch64"))]
+    dot_product_scalar(a, b)
+}
+
+/// Scalar dot product (portable fallback)
+#[inline]
+pub fn dot_product_scalar(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// AVX2 dot product with the accumulator layout of `euclidean_distance_avx2`
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2", enable = "fma")]
+unsafe fn dot_product_avx2(a: &[f32], b: &[f32]) -> f32 {
+    use std::arch::x86_64::*;
+
+    let len = a.len();
+    let mut i = 0;
+
+    let mut sum0 = _mm256_setzero_ps();
+    let mut sum1 = _mm256_setzero_ps();
+    let mut sum2 = _mm256_setzero_ps();
+    let mut sum3 = _mm256_setzero_ps();
+
+    while i + 32 <= len {
+        unsafe {
+            let pa = a.as_ptr().add(i);
+            let pb = b.as_ptr().add(i);
+            sum0 = _mm256_fmadd_ps(_mm256_loadu_ps(pa), _mm256_loadu_ps(pb), sum0);
+            sum1 = _mm256_fmadd_ps(_mm256_loadu_ps(pa.add(8)), _mm256_loadu_ps(pb.add(8)), sum1);
+            sum2 = _mm256_fmadd_ps(_mm256_loadu_ps(pa.add(16)), _mm256_loadu_ps(pb.add(16)), sum2);
+            sum3 = _mm256_fmadd_ps(_mm256_loadu_ps(pa.add(24)), _mm256_loadu_ps(pb.add(24)), sum3);
+        }
+        i += 32;
+    }
+
+    while i + 8 <= len {
+        let va = unsafe { _mm256_loadu_ps(a.as_ptr().add(i)) };
+        let vb = unsafe { _mm256_loadu_ps(b.as_ptr().add(i)) };
+        sum0 = _mm256_fmadd_ps(va, vb, sum0);
+        i += 8;
+    }
+
+    let sum = _mm256_add_ps(_mm256_add_ps(sum0, sum1), _mm256_add_ps(sum2, sum3));
+    horizontal_sum_avx2(sum) + dot_product_scalar(&a[i..], &b[i..])
+}
+
+/// NEON dot product with the accumulator layout of `euclidean_distance_neon`
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn dot_product_neon(a: &[f32], b: &[f32]) -> f32 {
+    use std::arch::aarch64::*;
+
+    let len = a.len();
+    let mut i = 0;
+
+    let mut sum0 = vdupq_n_f32(0.0);
+    let mut sum1 = vdupq_n_f32(0.0);
+    let mut sum2 = vdupq_n_f32(0.0);
+    let mut sum3 = vdupq_n_f32(0.0);
+
+    while i + 16 <= len {
+        unsafe {
+            let pa = a.as_ptr().add(i);
+            let pb = b.as_ptr().add(i);
+            sum0 = vfmaq_f32(sum0, vld1q_f32(pa), vld1q_f32(pb));
+            sum1 = vfmaq_f32(sum1, vld1q_f32(pa.add(4)), vld1q_f32(pb.add(4)));
+            sum2 = vfmaq_f32(sum2, vld1q_f32(pa.add(8)), vld1q_f32(pb.add(8)));
+            sum3 = vfmaq_f32(sum3, vld1q_f32(pa.add(12)), vld1q_f32(pb.add(12)));
+        }
+        i += 16;
+    }
+
+    while i + 4 <= len {
+        let (va, vb) = unsafe { (vld1q_f32(a.as_ptr().add(i)), vld1q_f32(b.as_ptr().add(i))) };
+        sum0 = vfmaq_f32(sum0, va, vb);
+        i += 4;
+    }
+
+    let sum = vaddq_f32(vaddq_f32(sum0, sum1), vaddq_f32(sum2, sum3));
+    vaddvq_f32(sum) + dot_product_scalar(&a[i..], &b[i..])
+}
+
+/// Count the bits that differ between two packed binary vectors.
+///
+/// # Architecture Dispatch
+///
+/// - x86_64 + POPCNT: 4 accumulators, 4 words per iteration (runtime detection)
+/// - aarch64: NEON byte popcount (`cnt`), 2 words per iteration
+/// - Fallback: Portable scalar implementation
+#[inline]
+pub fn hamming_distance(a: &[u64], b: &[u64]) -> u32 {
+    debug_assert_eq!(a.len(), b.len());
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("popcnt") {
+            return unsafe { hamming_distance_popcnt(a, b) };
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        unsafe { hamming_distance_neon(a, b) }
+    }
+
+    #[cfg(not(target_arch = "aarch64"))]
+    hamming_distance_scalar(a, b)
+}
+
+/// Scalar Hamming distance (portable fallback)
+#[inline]
+pub fn hamming_distance_scalar(a: &[u64], b: &[u64]) -> u32 {
+    a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum()
+}
+
+/// Hardware popcount with independent accumulators (x86_64 only)
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "popcnt")]
+unsafe fn hamming_distance_popcnt(a: &[u64], b: &[u64]) -> u32 {
+    use std::arch::x86_64::_popcnt64;
+
+    let (a_blocks, a_tail) = a.as_chunks::<4>();
+    let (b_blocks, b_tail) = b.as_chunks::<4>();
+
+    let mut sums = [0_i32; 4];
+    for (x, y) in a_blocks.iter().zip(b_blocks) {
+        for lane in 0..4 {
+            sums[lane] += _popcnt64((x[lane] ^ y[lane]) as i64);
+        }
+    }
+
+    sums.iter().sum::<i32>() as u32 + hamming_distance_scalar(a_tail, b_tail)
+}
+
+/// NEON Hamming distance: per-byte popcount, widened pairwise into u32 lanes
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn hamming_distance_neon(a: &[u64], b: &[u64]) -> u32 {
+    use std::arch::aarch64::*;
+
+    let len = a.len();
+    let mut i = 0;
+    let mut sum = vdupq_n_u32(0);
+
+    while i + 2 <= len {
+        let (va, vb) = unsafe { (vld1q_u64(a.as_ptr().add(i)), vld1q_u64(b.as_ptr().add(i))) };
+        let x = veorq_u64(va, vb);
+        let bytes = vcntq_u8(vreinterpretq_u8_u64(x));
+        sum = vpadalq_u16(sum, vpaddlq_u8(bytes));
+        i += 2;
+    }
+
+    vaddvq_u32(sum) + hamming_distance_scalar(&a[i..], &b[i..])
+}
+
+/// Compute cosine distance (1 - cosine similarity).
+///
+/// Returns `1.0` when either vector has zero norm because cosine similarity is
+/// undefined for zero vectors.
+#[inline]
+pub fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+    debug_assert_eq!(a.len(), b.len());
+
+    let dot: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_product = norm_a * norm_b;
+
+    if norm_product == 0.0 {
+        return 1.0;
+    }
+
+    1.0 - (dot / norm_product)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_euclidean_distance_basic() {
+        let a = vec![1.0, 2.0, 3.0];
+        let b = vec![4.0, 5.0, 6.0];
+
+        let dist = euclidean_distance(&a, &b);
+        let expected = ((3.0_f32).powi(2) * 3.0).sqrt();
+
+        assert!((dist - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_cosine_distance() {
+        let a = vec![1.0, 0.0, 0.0];
+        let b = vec![0.0, 1.0, 0.0];
+
+        let dist = cosine_distance(&a, &b);
+        assert!((dist - 1.0).abs() < 1e-6); // Orthogonal vectors
+    }
+
+    #[test]
+    fn test_cosine_distance_zero_norm() {
+        let zero = vec![0.0, 0.0, 0.0];
+        let non_zero = vec![1.0, 0.0, 0.0];
+
+        let zero_to_zero = cosine_distance(&zero, &zero);
+        let zero_to_non_zero = cosine_distance(&zero, &non_zero);
+
+        assert_eq!(zero_to_zero, 1.0);
+        assert_eq!(zero_to_non_zero, 1.0);
+    }
+
+    #[test]
+    fn test_simd_correctness_small() {
+        // Test with small vectors (exercises scalar tail)
+        for size in [3, 7, 15, 31] {
+            let a: Vec<f32> = (0..size).map(|i| i as f32 * 0.1).collect();
+            let b: Vec<f32> = (0..size).map(|i| (i as f32) * 0.1 + 0.5).collect();
+
+            let simd_result = euclidean_distance(&a, &b);
+            let scalar_result = euclidean_distance_scalar(&a, &b);
+
+            assert!(
+                (simd_result - scalar_result).abs() < 1e-5,
+                "SIMD mismatch at size {}: simd={}, scalar={}",
+                size,
+                simd_result,
+                scalar_result
+            );
+        }
+    }
+
+    #[test]
+    fn test_simd_correctness_large() {
+        // Test with large vectors (exercises main loop)
+        for size in [128, 384, 768, 1536] {
+            let a: Vec<f32> = (0..size).map(|i| (i as f32).sin()).collect();
+            let b: Vec<f32> = (0..size).map(|i| (i as f32).cos()).collect();
+
+            let simd_result = euclidean_distance(&a, &b);
+            let scalar_result = euclidean_distance_scalar(&a, &b);
+
+            // Tolerance accounts for different accumulation order
+            assert!(
+                (simd_result - scalar_result).abs() < 1e-4,
+                "SIMD mismatch at size {}: simd={}, scalar={}, diff={}",
+                size,
+                simd_result,
+                scalar_result,
+                (simd_result - scalar_result).abs()
+            );
+        }
+    }
 
     #[test]
     fn test_simd_random_vectors() {
@@ -394,6 +1371,165 @@ mod tests {
         assert!((dist - expected).abs() < 1e-5);
     }
 
+    #[test]
+    fn test_dot_product_matches_scalar() {
+        for dims in [1, 7, 8, 31, 33, 128, 1000] {
+            let a: Vec<f32> = (0..dims).map(|i| (i as f32 * 0.37).sin()).collect();
+            let b: Vec<f32> = (0..dims).map(|i| (i as f32 * 0.11).cos()).collect();
+
+            let simd = dot_product(&a, &b);
+            let scalar = dot_product_scalar(&a, &b);
+            assert!(
+                (simd - scalar).abs() < 1e-3,
+                "dims {}: simd={}, scalar={}",
+                dims,
+                simd,
+                scalar
+            );
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut v = vec![3.0, 0.0, 4.0];
+        assert!(normalize(&mut v));
+        assert_eq!(v, [0.6, 0.0, 0.8]);
+        assert_eq!(Metric::UnitCosine.compute(&v, &v), 0.0);
+        assert!((Metric::UnitCosine.compute(&v, &[0.0, 1.0, 0.0]) - 1.0).abs() < 1e-6);
+
+        assert!(!normalize(&mut [0.0; 3]));
+        assert!(!normalize(&mut [f32::INFINITY, 0.0]));
+    }
+
+    #[test]
+    fn test_all_finite_finds_every_position() {
+        for size in [0, 3, 8, 15, 16, 31, 33, 768] {
+            let mut v: Vec<f32> = (0..size).map(|i| (i as f32).sin() * 1e30).collect();
+            v.iter_mut().step_by(5).for_each(|x| *x = f32::MAX);
+            assert!(all_finite(&v), "{} components", size);
+
+            for position in 0..size {
+                for bad in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, -f32::NAN] {
+                    let saved = std::mem::replace(&mut v[position], bad);
+                    assert!(!all_finite(&v), "{} at {} of {}", bad, position, size);
+                    v[position] = saved;
+                }
+            }
+        }
+
+        assert!(all_finite(&[f32::MIN_POSITIVE / 2.0, -0.0]));
+    }
+
+    #[test]
+    fn test_dot_product_distance() {
+        let metric = Metric::new(Distance::DotProduct, true, ElementType::F32);
+        assert_eq!(metric.compute(&[1.0, 2.0], &[3.0, -1.0]), -1.0);
+        // A longer vector in the same direction is closer
+        assert!(
+            metric.compute(&[1.0, 0.0], &[4.0, 0.0]) < metric.compute(&[1.0, 0.0], &[1.0, 0.0])
+        );
+
+        assert_eq!(Distance::DotProduct.name(), Some(DOT_PRODUCT_NAME));
+        assert_eq!(Distance::describe(Some(DOT_PRODUCT_NAME)), "dot product distance");
+        let reserved = CustomDistance::new("chassis:mine", euclidean_distance);
+        assert!(!reserved.has_valid_name());
+    }
+
+    #[test]
+    fn test_half_kernels_match_scalar() {
+        use crate::element::{f32_to_bf16, f32_to_f16};
+
+        for size in [3, 8, 15, 16, 31, 768, 1536] {
+            let query: Vec<f32> = (0..size).map(|i| (i as f32).sin()).collect();
+            let stored: Vec<f32> = (0..size).map(|i| (i as f32 * 0.7).cos()).collect();
+
+            let f16: Vec<u16> = stored.iter().map(|&x| f32_to_f16(x)).collect();
+            let bf16: Vec<u16> = stored.iter().map(|&x| f32_to_bf16(x)).collect();
+
+            let pairs = [
+                (
+                    euclidean_distance_f16(&query, &f16),
+                    squared_euclidean_half_scalar(&query, &f16, crate::element::f16_to_f32).sqrt(),
+                ),
+                (
+                    euclidean_distance_bf16(&query, &bf16),
+                    squared_euclidean_half_scalar(&query, &bf16, crate::element::bf16_to_f32)
+                        .sqrt(),
+                ),
+            ];
+            for (simd_result, scalar_result) in pairs {
+                assert!(
+                    (simd_result - scalar_result).abs() < 1e-4,
+                    "Half kernel mismatch at size {}: simd={}, scalar={}",
+                    size,
+                    simd_result,
+                    scalar_result
+                );
+            }
+
+            // Quantization error stays small relative to the f32 distance
+            let exact = euclidean_distance(&query, &stored);
+            assert!((pairs[0].0 - exact).abs() < 1e-2 * exact.max(1.0));
+            assert!((pairs[1].0 - exact).abs() < 5e-2 * exact.max(1.0));
+        }
+    }
+
+    #[test]
+    fn test_hamming_kernel_matches_scalar() {
+        for words in [0, 1, 3, 4, 7, 16, 64] {
+            let a: Vec<u64> =
+                (0..words).map(|i| (i as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)).collect();
+            let b: Vec<u64> = (0..words).map(|i| !(i as u64) << (i % 7)).collect();
+
+            let expected: u32 = a.iter().zip(&b).map(|(x, y)| (x ^ y).count_ones()).sum();
+            assert_eq!(hamming_distance(&a, &b), expected, "{} words", words);
+            assert_eq!(hamming_distance(&a, &a), 0);
+        }
+
+        let a = [0.5, -1.0, 0.0, 2.0];
+        assert_eq!(Metric::Hamming.compute(&a, &[1.0, 1.0, 1.0, 1.0]), 2.0);
+    }
+
+    #[test]
+    fn test_l1_and_squared_kernels_match_scalar() {
+        for size in [0, 3, 8, 15, 16, 31, 33, 768, 1536] {
+            let a: Vec<f32> = (0..size).map(|i| (i as f32).sin()).collect();
+            let b: Vec<f32> = (0..size).map(|i| (i as f32 * 0.7).cos()).collect();
+
+            let pairs = [
+                (manhattan_distance(&a, &b), manhattan_distance_scalar(&a, &b)),
+                (squared_euclidean(&a, &b), squared_euclidean_scalar(&a, &b)),
+            ];
+            for (simd_result, scalar_result) in pairs {
+                assert!(
+                    (simd_result - scalar_result).abs() < 1e-5 * scalar_result.max(100.0),
+                    "Kernel mismatch at size {}: simd={}, scalar={}",
+                    size,
+                    simd_result,
+                    scalar_result
+                );
+            }
+
+            let euclidean = euclidean_distance(&a, &b);
+            assert!(
+                (squared_euclidean(&a, &b) - euclidean * euclidean).abs()
+                    < 1e-3 * euclidean.max(1.0)
+            );
+        }
+
+        let (a, b) = ([1.0, -2.0, 3.0], [0.0, 2.0, 1.0]);
+        assert_eq!(Distance::Manhattan.compute(&a, &b), 7.0);
+        assert_eq!(Distance::SquaredEuclidean.compute(&a, &b), 21.0);
+        assert_eq!(Distance::Euclidean.compute(&a, &b), 21.0_f32.sqrt());
+        assert_eq!(Distance::DotProduct.compute(&a, &b), 1.0);
+
+        // Euclidean ranks by the squared distance and reports its square root
+        assert_eq!(Metric::Euclidean.compute(&a, &b), 21.0);
+        assert_eq!(Metric::Euclidean.ranked(Metric::Euclidean.reported(16.0)), 16.0);
+        assert_eq!(Metric::SquaredEuclidean.reported(21.0), 21.0);
+        assert_eq!(Distance::describe(Distance::Manhattan.name()), "Manhattan distance");
+    }
+
     #[cfg(target_arch = "x86_64")]
     #[test]
     fn test_avx2_specific() {
@@ -0,0 +1,416 @@
+//! On-disk element types for stored vectors.
+//!
+//! Vectors are always passed in and returned as `f32`. The element type only
+//! decides how each component is written to the vector zone:
+//!
+//! - `F32`: 4 bytes, exact (default)
+//! - `F16`: IEEE 754 half precision, 2 bytes, ~3 significant digits, range ±65504
+//! - `BF16`: bfloat16, 2 bytes, ~2 significant digits, full `f32` range
+//! - `Binary`: 1 bit, set for components above zero, packed into 64-bit words
+//!
+//! Half-width types halve the vector zone, which dominates file size for large
+//! embeddings (a 1536-dim vector is 6 KiB as `f32`, 3 KiB as `f16`). Conversion
+//! rounds to nearest, ties to even. `Binary` shrinks it 32-fold (192 bytes) and
+//! compares vectors by Hamming distance, for binary-hash embeddings.
+
+use crate::distance::{self, Metric};
+use crate::header::MAX_DIMENSIONS;
+use std::fmt;
+use std::mem::MaybeUninit;
+
+/// Encoding of vector components in the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ElementType {
+    /// 32-bit IEEE 754 float
+    #[default]
+    F32,
+
+    /// 16-bit IEEE 754 half-precision float
+    F16,
+
+    /// 16-bit brain float (truncated `f32` exponent range)
+    BF16,
+
+    /// One bit per component, set for values above zero; searched by Hamming distance
+    Binary,
+}
+
+impl ElementType {
+    /// Bytes per vector component, rounded up to a whole byte for `Binary`
+    ///
+    /// Use `vector_bytes()` for the size of a whole vector.
+    #[must_use]
+    pub const fn size(self) -> usize {
+        match self {
+            Self::F32 => 4,
+            Self::F16 | Self::BF16 => 2,
+            Self::Binary => 1,
+        }
+    }
+
+    /// Bytes a vector of `dims` components takes in the vector zone
+    ///
+    /// `Binary` vectors are padded to whole 64-bit words.
+    #[must_use]
+    pub const fn vector_bytes(self, dims: usize) -> usize {
+        match self {
+            Self::Binary => binary_words(dims) * 8,
+            _ => dims * self.size(),
+        }
+    }
+
+    /// Code stored in the file header
+    pub(crate) const fn code(self) -> u32 {
+        match self {
+            Self::F32 => 0,
+            Self::F16 => 1,
+            Self::BF16 => 2,
+            Self::Binary => 3,
+        }
+    }
+
+    /// Parses a header code; `None` for codes written by a newer library
+    pub(crate) const fn from_code(code: u32) -> Option<Self> {
+        match code {
+            0 => Some(Self::F32),
+            1 => Some(Self::F16),
+            2 => Some(Self::BF16),
+            3 => Some(Self::Binary),
+            _ => None,
+        }
+    }
+
+    /// Encodes one component
+    pub(crate) fn encode(self, value: f32) -> u16 {
+        match self {
+            Self::F16 => f32_to_f16(value),
+            Self::BF16 => f32_to_bf16(value),
+            Self::F32 | Self::Binary => unreachable!("only half-width components are encoded"),
+        }
+    }
+}
+
+impl fmt::Display for ElementType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::F32 => "f32",
+            Self::F16 => "f16",
+            Self::BF16 => "bf16",
+            Self::Binary => "binary",
+        })
+    }
+}
+
+/// A stored vector borrowed from the mapping, in its on-disk encoding
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum StoredVector<'a> {
+    F32(&'a [f32]),
+    F16(&'a [u16]),
+    BF16(&'a [u16]),
+    Binary { words: &'a [u64], dims: usize },
+}
+
+/// An `f32` query prepared once per search for the index's metric
+///
+/// For Hamming distance its signs are packed up front, instead of on every
+/// comparison with a binary vector.
+pub(crate) struct Query<'a> {
+    values: &'a [f32],
+    /// Packed signs of `values`; only filled in for `Metric::Hamming`
+    words: [u64; MAX_BINARY_WORDS],
+}
+
+impl<'a> Query<'a> {
+    /// Prepares `values` (at most `MAX_DIMENSIONS` components) for `metric`
+    #[inline]
+    pub(crate) fn new(values: &'a [f32], metric: Metric) -> Self {
+        let mut words = [0_u64; MAX_BINARY_WORDS];
+        if metric == Metric::Hamming {
+            binarize(values, &mut words);
+        }
+        Self { values, words }
+    }
+
+    /// Packed signs of the query, as many words as a stored binary vector
+    fn words(&self) -> &[u64] {
+        &self.words[..binary_words(self.values.len())]
+    }
+}
+
+impl StoredVector<'_> {
+    /// Distance from a prepared query
+    #[inline]
+    pub(crate) fn distance_to(&self, query: &Query<'_>, metric: Metric) -> f32 {
+        match (metric, self) {
+            (Metric::Hamming, Self::Binary { words, .. }) => {
+                distance::hamming_distance(query.words(), words) as f32
+            }
+            _ => self.distance_to_f32(query.values, metric),
+        }
+    }
+
+    /// Distance from an `f32` vector; L2 runs without widening the stored vector in memory
+    #[inline]
+    fn distance_to_f32(&self, query: &[f32], metric: Metric) -> f32 {
+        match (metric, self) {
+            (Metric::Euclidean | Metric::SquaredEuclidean, Self::F32(v)) => {
+                distance::squared_euclidean(query, v)
+            }
+            (Metric::Euclidean | Metric::SquaredEuclidean, Self::F16(v)) => {
+                distance::squared_euclidean_f16(query, v)
+            }
+            (Metric::Euclidean | Metric::SquaredEuclidean, Self::BF16(v)) => {
+                distance::squared_euclidean_bf16(query, v)
+            }
+            _ => self.with_f32(|v| metric.compute(query, v)),
+        }
+    }
+
+    /// Distance between two stored vectors
+    #[inline]
+    pub(crate) fn distance(&self, other: &StoredVector<'_>, metric: Metric) -> f32 {
+        if let (
+            Metric::Hamming,
+            Self::Binary { words: a, .. },
+            StoredVector::Binary { words: b, .. },
+        ) = (metric, self, other)
+        {
+            return distance::hamming_distance(a, b) as f32;
+        }
+        self.with_f32(|v| other.distance_to_f32(v, metric))
+    }
+
+    /// Runs `f` on the vector as `f32`, widening half-width vectors on the stack
+    #[inline]
+    fn with_f32<R>(&self, f: impl FnOnce(&[f32]) -> R) -> R {
+        match self {
+            Self::F32(v) => f(v),
+            Self::F16(_) | Self::BF16(_) | Self::Binary { .. } => {
+                // Dimensions are capped by the header; only `dims()` slots are written
+                let mut buffer = [MaybeUninit::<f32>::uninit(); MAX_DIMENSIONS as usize];
+                f(self.widen_uninit(&mut buffer[..self.dims()]))
+            }
+        }
+    }
+
+    /// Number of components
+    fn dims(&self) -> usize {
+        match self {
+            Self::F32(v) => v.len(),
+            Self::F16(v) | Self::BF16(v) => v.len(),
+            Self::Binary { dims, .. } => *dims,
+        }
+    }
+
+    /// Decodes the vector into an owned `f32` copy
+    pub(crate) fn to_vec(self) -> Vec<f32> {
+        match self {
+            Self::F32(v) => v.to_vec(),
+            Self::F16(v) => v.iter().map(|&bits| f16_to_f32(bits)).collect(),
+            Self::BF16(v) => v.iter().map(|&bits| bf16_to_f32(bits)).collect(),
+            Self::Binary { .. } => {
+                let mut vector = vec![0.0; self.dims()];
+                self.widen_into(&mut vector);
+                vector
+            }
+        }
+    }
+
+    pub(crate) fn widen_into(&self, out: &mut [f32]) {
+        // SAFETY: `MaybeUninit<f32>` has the layout of `f32`, and only
+        // initialized values are written through the cast
+        let out = unsafe { &mut *(std::ptr::from_mut(out) as *mut [MaybeUninit<f32>]) };
+        self.widen_uninit(out);
+    }
+
+    /// Decodes the vector into `out`, exactly `dims()` slots, without
+    /// requiring them to be initialized first
+    fn widen_uninit<'b>(&self, out: &'b mut [MaybeUninit<f32>]) -> &'b [f32] {
+        assert_eq!(out.len(), self.dims(), "output must hold every component");
+        match self {
+            Self::F32(v) => {
+                for (o, &x) in out.iter_mut().zip(*v) {
+                    o.write(x);
+                }
+            }
+            Self::F16(v) => {
+                for (o, &b) in out.iter_mut().zip(*v) {
+                    o.write(f16_to_f32(b));
+                }
+            }
+            Self::BF16(v) => {
+                for (o, &b) in out.iter_mut().zip(*v) {
+                    o.write(bf16_to_f32(b));
+                }
+            }
+            Self::Binary { words, .. } => {
+                for (i, o) in out.iter_mut().enumerate() {
+                    o.write(f32::from(u8::from(words[i / 64] >> (i % 64) & 1 == 1)));
+                }
+            }
+        }
+        // SAFETY: each variant holds `dims()` components, so every slot of
+        // `out` was written above
+        unsafe { &*(std::ptr::from_ref(out) as *const [f32]) }
+    }
+}
+
+/// Most words a binary vector can take
+pub(crate) const MAX_BINARY_WORDS: usize = binary_words(MAX_DIMENSIONS as usize);
+
+/// 64-bit words holding `dims` bits
+pub(crate) const fn binary_words(dims: usize) -> usize {
+    dims.div_ceil(64)
+}
+
+/// Packs the signs of `values` into `words`: bit `i % 64` of word `i / 64` is
+/// set if component `i` is above zero
+pub(crate) fn binarize(values: &[f32], words: &mut [u64]) {
+    for (word, chunk) in words.iter_mut().zip(values.chunks(64)) {
+        *word = chunk
+            .iter()
+            .enumerate()
+            .fold(0, |bits, (i, &value)| bits | u64::from(value > 0.0) << i);
+    }
+}
+
+/// Converts to IEEE 754 half precision; out-of-range values become infinity
+pub(crate) fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x007f_ffff;
+
+    // Infinity and NaN (keep NaN quiet and non-zero)
+    if exponent == 0xff {
+        let nan = if mantissa != 0 { 0x0200 } else { 0 };
+        return sign | 0x7c00 | nan | (mantissa >> 13) as u16;
+    }
+
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    if half_exponent <= 0 {
+        // Below 2^-25 even the smallest subnormal rounds to zero
+        if half_exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x0080_0000;
+        let shift = (14 - half_exponent) as u32;
+        let half = mantissa >> shift;
+        let rest = mantissa & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        let round = u32::from(rest > halfway || (rest == halfway && half & 1 == 1));
+        return sign | (half + round) as u16;
+    }
+
+    // A carry out of the mantissa correctly bumps the exponent (up to infinity)
+    let half = ((half_exponent as u32) << 10) | (mantissa >> 13);
+    let rest = mantissa & 0x1fff;
+    let round = u32::from(rest > 0x1000 || (rest == 0x1000 && half & 1 == 1));
+    sign | (half + round) as u16
+}
+
+/// Converts from IEEE 754 half precision (exact)
+pub(crate) fn f16_to_f32(bits: u16) -> f32 {
+    let sign = u32::from(bits & 0x8000) << 16;
+    let exponent = u32::from((bits >> 10) & 0x1f);
+    let mantissa = u32::from(bits & 0x03ff);
+
+    let magnitude = match exponent {
+        // Zero and subnormals: mantissa * 2^-24 is exact in f32
+        0 => (mantissa as f32 * (1.0 / 16_777_216.0)).to_bits(),
+        0x1f => 0x7f80_0000 | (mantissa << 13),
+        _ => ((exponent + 127 - 15) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(sign | magnitude)
+}
+
+/// Converts to bfloat16
+pub(crate) fn f32_to_bf16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    if value.is_nan() {
+        return ((bits >> 16) | 0x0040) as u16;
+    }
+    let round = 0x7fff + ((bits >> 16) & 1);
+    (bits.wrapping_add(round) >> 16) as u16
+}
+
+/// Converts from bfloat16 (exact)
+pub(crate) fn bf16_to_f32(bits: u16) -> f32 {
+    f32::from_bits(u32::from(bits) << 16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_f16_roundtrip_and_rounding() {
+        for value in [0.0, -0.0, 1.0, -2.5, 0.333_251_95, 65504.0, 6.103_515_6e-5, 5.960_464_5e-8] {
+            assert_eq!(f16_to_f32(f32_to_f16(value)).to_bits(), value.to_bits(), "{}", value);
+        }
+
+        // Ties round to even: 2049 lies halfway between 2048 and 2050
+        assert_eq!(f16_to_f32(f32_to_f16(2049.0)), 2048.0);
+        assert_eq!(f16_to_f32(f32_to_f16(2051.0)), 2052.0);
+
+        assert_eq!(f16_to_f32(f32_to_f16(1e6)), f32::INFINITY);
+        assert_eq!(f16_to_f32(f32_to_f16(-1e6)), f32::NEG_INFINITY);
+        assert_eq!(f32_to_f16(1e-10), 0);
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+
+        // Every finite half survives a round trip through f32
+        for bits in 0..=u16::MAX {
+            if (bits >> 10) & 0x1f != 0x1f {
+                assert_eq!(f32_to_f16(f16_to_f32(bits)), bits);
+            }
+        }
+    }
+
+    #[test]
+    fn test_binary_packing() {
+        let values: Vec<f32> = (0..70).map(|i| if i % 3 == 0 { 0.5 } else { -0.5 }).collect();
+        let mut words = [0_u64; 2];
+        binarize(&values, &mut words);
+        assert_eq!(words[0] & 0b1111, 0b1001);
+        assert_eq!(words[1] >> 6, 0, "padding bits stay clear");
+        assert_eq!(ElementType::Binary.vector_bytes(70), 16);
+
+        let stored = StoredVector::Binary { words: &words, dims: 70 };
+        let decoded = stored.to_vec();
+        assert_eq!(decoded.len(), 70);
+        assert!(decoded.iter().zip(&values).all(|(&bit, &value)| (bit == 1.0) == (value > 0.0)));
+
+        let mut flipped = values.clone();
+        flipped[0] = -1.0;
+        flipped[68] = 1.0;
+        let query = Query::new(&flipped, Metric::Hamming);
+        assert_eq!(stored.distance_to(&query, Metric::Hamming), 2.0);
+        assert_eq!(stored.distance(&stored, Metric::Hamming), 0.0);
+    }
+
+    #[test]
+    fn test_bf16_roundtrip_and_rounding() {
+        for value in [0.0, -1.0, 3.0e38, 1.0e-38, 0.5] {
+            let back = bf16_to_f32(f32_to_bf16(value));
+            assert!((back - value).abs() <= value.abs() / 128.0, "{} -> {}", value, back);
+        }
+        assert_eq!(bf16_to_f32(f32_to_bf16(1.0 + 1.0 / 256.0)), 1.0);
+        assert_eq!(bf16_to_f32(f32_to_bf16(1.0 + 3.0 / 256.0)), 1.0 + 4.0 / 256.0);
+        assert!(bf16_to_f32(f32_to_bf16(f32::NAN)).is_nan());
+    }
+
+    #[test]
+    fn test_half_width_distances_widen_every_component() {
+        let values: Vec<f32> = (0..100).map(|i| i as f32 / 8.0 - 6.0).collect();
+        let halves: Vec<u16> = values.iter().map(|&v| f32_to_f16(v)).collect();
+        let stored = StoredVector::F16(&halves);
+        let query = Query::new(&values, Metric::InnerProduct);
+        let expected = Metric::InnerProduct.compute(&values, &values);
+        assert_eq!(stored.distance_to(&query, Metric::InnerProduct), expected);
+        assert_eq!(stored.distance(&stored, Metric::InnerProduct), expected);
+    }
+}
@@ -0,0 +1,45 @@
+//! Hooks for generating embeddings from text.
+//!
+//! Chassis stores and searches vectors; it does not ship an embedding model.
+//! An application that embeds text with a local model (or a remote API)
+//! implements `Embedder` and attaches it with `VectorIndex::set_embedder()`,
+//! after which `add_text()` and `search_text()` take the text directly.
+//! Closures `Fn(&str) -> Vec<f32>` are embedders too.
+
+use anyhow::Result;
+use std::fmt;
+use std::sync::Arc;
+
+/// Turns text into the vectors stored in an index
+///
+/// `embed()` must return vectors with the index's `input_dimensions()`, and
+/// should embed queries and documents the same way. It runs synchronously on
+/// the calling thread; the handles of `into_handles()` call it without
+/// holding the index lock, so a slow model does not block other threads.
+pub trait Embedder: Send + Sync {
+    /// Embed `text` as one vector
+    ///
+    /// # Errors
+    ///
+    /// Errors are returned unchanged from `add_text()` and `search_text()`.
+    fn embed(&self, text: &str) -> Result<Vec<f32>>;
+}
+
+impl<F> Embedder for F
+where
+    F: Fn(&str) -> Vec<f32> + Send + Sync,
+{
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        Ok(self(text))
+    }
+}
+
+/// The embedder attached to an index, if any
+#[derive(Clone, Default)]
+pub(crate) struct EmbedderSlot(pub(crate) Option<Arc<dyn Embedder>>);
+
+impl fmt::Debug for EmbedderSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.0.is_some() { "Embedder(Some(..))" } else { "Embedder(None)" })
+    }
+}
@@ -0,0 +1,720 @@
+//! Encrypted index files: the index image sealed block by block with AES-256-GCM.
+//!
+//! `VectorIndex::open_encrypted()` keeps the plaintext image in anonymous
+//! memory and stores it in the file as 4 KiB blocks, each encrypted and
+//! authenticated on its own, so a flush re-encrypts only the pages it changed.
+//! Opening reads only the container header: a block is authenticated and
+//! decrypted into memory the first time it is read or written, so RAM follows
+//! the pages an index touches rather than its file size, and zero-copy vector
+//! slices point into the decrypted image.
+//!
+//! ```text
+//! Offset  Size              Field
+//! ------  ----              -----
+//! 0       8                 magic: "CHASENC\0"
+//! 8       4                 container version: u32 (1)
+//! 12      4                 block size: u32 (4096)
+//! 16      16                salt: random per file, bound into every tag
+//! 32      64                commit record, slot 0
+//! 96      64                commit record, slot 1
+//! 4096    2 * SLOT_SIZE * n slots of the n image blocks, two per block
+//! ```
+//!
+//! A commit record holds a sequence number and the image length, with a tag
+//! over both; it is written when a flush changes the length, and is what tells
+//! a wrong key from the right one. A block slot holds a nonce, a generation
+//! and a tag, followed by the ciphertext. Writes alternate between the two
+//! slots of a block (and between the two commit records) and opening takes
+//! the newest one that authenticates, so a write torn by a crash leaves the
+//! previous version readable, as with an unsynced page of a mapped file.
+//!
+//! Nonces are random, so copies of a file that later diverge never share
+//! one; a key should seal fewer than 2^32 blocks in total. Tags bind each
+//! block to its position in its file, but not to the other blocks: someone
+//! who can write the file can roll single blocks back to versions they
+//! copied earlier.
+//!
+//! The cipher is the RustCrypto `aes-gcm` crate, which uses AES-NI, the ARMv8
+//! crypto extension or a bitsliced software AES, all free of table lookups.
+
+use crate::error::{ErrorKind, Tagged};
+use crate::storage::PAGE_SIZE;
+use aes_gcm::aead::{AeadInPlace, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce, Tag};
+use anyhow::{Context, Result};
+use fs2::FileExt;
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+use zeroize::Zeroize;
+
+/// Size of a key, in bytes
+const KEY_LEN: usize = 32;
+
+/// Size of a nonce, in bytes
+const NONCE_LEN: usize = 12;
+
+/// Size of an authentication tag, in bytes
+const TAG_LEN: usize = 16;
+
+/// Magic bytes at the start of an encrypted index file
+const MAGIC: &[u8; 8] = b"CHASENC\0";
+
+/// Container layout version
+const CONTAINER_VERSION: u32 = 1;
+
+/// Plaintext bytes per block
+const BLOCK_SIZE: usize = PAGE_SIZE;
+
+/// Bytes in front of the first block slot
+const HEADER_LEN: usize = 4096;
+
+/// Size of the per-file salt
+const SALT_LEN: usize = 16;
+
+/// Offset of the first commit record
+const RECORD_OFFSET: usize = 32;
+
+/// Size of a commit record
+const RECORD_LEN: usize = 64;
+
+/// Nonce, generation and tag in front of a block's ciphertext
+const SLOT_PREFIX: usize = NONCE_LEN + 8 + TAG_LEN;
+
+/// Size of a block slot
+const SLOT_SIZE: usize = SLOT_PREFIX + BLOCK_SIZE;
+
+/// 256-bit key for `VectorIndex::open_encrypted()`
+///
+/// Each tenant or user can have an index under its own key; where the key
+/// comes from (a platform keychain, a key derived from a passphrase) is up to
+/// the application. The bytes are overwritten when the key is dropped, and
+/// `Debug` does not print them.
+#[derive(Clone)]
+pub struct EncryptionKey {
+    bytes: [u8; KEY_LEN],
+}
+
+impl EncryptionKey {
+    /// Wraps 32 bytes of key material
+    pub fn from_bytes(bytes: [u8; KEY_LEN]) -> Self {
+        Self { bytes }
+    }
+
+    /// Generates a random key from the operating system's entropy source
+    pub fn generate() -> Self {
+        Self { bytes: rand::random() }
+    }
+
+    /// The key material, for storing the key
+    pub fn as_bytes(&self) -> &[u8; KEY_LEN] {
+        &self.bytes
+    }
+}
+
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+impl Drop for EncryptionKey {
+    fn drop(&mut self) {
+        self.bytes.zeroize();
+    }
+}
+
+/// An open encrypted index file
+pub(crate) struct EncryptedFile {
+    /// File handle (owns the exclusive lock)
+    file: File,
+
+    cipher: Aes256Gcm,
+
+    salt: [u8; SALT_LEN],
+
+    /// Blocks of the committed image not decrypted into memory yet
+    sealed: Vec<AtomicBool>,
+
+    /// Decryption state; the lock also makes one thread at a time unseal
+    unsealing: Mutex<Unsealing>,
+
+    /// Sequence number of the newest commit record (0 before the first)
+    sequence: u64,
+
+    /// Image length named by that record
+    image_len: usize,
+}
+
+/// State of `EncryptedFile` that unsealing a block updates
+struct Unsealing {
+    /// Generation of the newest slot of each block of the committed image;
+    /// 0 for blocks that are still sealed
+    generations: Vec<u64>,
+
+    /// Buffer for the two slots of the block being unsealed
+    pair: Vec<u8>,
+}
+
+impl std::fmt::Debug for EncryptedFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptedFile")
+            .field("image_len", &self.image_len)
+            .field("sequence", &self.sequence)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EncryptedFile {
+    /// Opens and locks the encrypted file at `path`, creating it if missing
+    ///
+    /// A new file holds no image until the first `commit()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Locked` if another handle holds the file, `Corrupted` if it
+    /// is not an encrypted index, and `InvalidArgument` if no commit record
+    /// authenticates under `key`.
+    pub(crate) fn open(path: &Path, key: &EncryptionKey) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("Failed to open chassis file: {}", path.display()))?;
+        file.try_lock_exclusive().context(Tagged::new(
+            ErrorKind::Locked,
+            "Chassis file is already open by another process",
+        ))?;
+
+        let mut encrypted = Self {
+            file,
+            cipher: Aes256Gcm::new(&key.bytes.into()),
+            salt: [0; SALT_LEN],
+            sealed: Vec::new(),
+            unsealing: Mutex::new(Unsealing { generations: Vec::new(), pair: Vec::new() }),
+            sequence: 0,
+            image_len: 0,
+        };
+
+        if encrypted.file.metadata()?.len() == 0 {
+            encrypted.salt = rand::random();
+            let mut header = [0u8; HEADER_LEN];
+            header[..8].copy_from_slice(MAGIC);
+            header[8..12].copy_from_slice(&CONTAINER_VERSION.to_le_bytes());
+            header[12..16].copy_from_slice(&(BLOCK_SIZE as u32).to_le_bytes());
+            header[16..32].copy_from_slice(&encrypted.salt);
+            encrypted.write_at(0, &header)?;
+            encrypted.file.sync_all()?;
+            return Ok(encrypted);
+        }
+
+        encrypted.read_header(path)?;
+        Ok(encrypted)
+    }
+
+    /// Checks the container header and loads the newest valid commit record
+    fn read_header(&mut self, path: &Path) -> Result<()> {
+        let mut header = [0u8; HEADER_LEN];
+        read_exact_at(&self.file, &mut header, 0).map_err(|_| {
+            Tagged::new(ErrorKind::Corrupted, "File is not an encrypted Chassis index")
+        })?;
+        if &header[..8] != MAGIC {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                "File is not an encrypted Chassis index"
+            ));
+        }
+
+        let version = u32::from_le_bytes(header[8..12].try_into()?);
+        let block_size = u32::from_le_bytes(header[12..16].try_into()?);
+        if version != CONTAINER_VERSION || block_size as usize != BLOCK_SIZE {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                format!(
+                    "Unsupported encrypted index layout: version {}, {} byte blocks",
+                    version, block_size
+                )
+            ));
+        }
+        self.salt.copy_from_slice(&header[16..32]);
+
+        let mut written = false;
+        for slot in 0..2 {
+            let offset = RECORD_OFFSET + slot * RECORD_LEN;
+            let record: &[u8; RECORD_LEN] = header[offset..offset + RECORD_LEN].try_into()?;
+            let sequence = u64::from_le_bytes(record[..8].try_into()?);
+            let image_len = u64::from_le_bytes(record[8..16].try_into()?);
+            written |= sequence != 0;
+
+            let nonce = record[16..28].try_into()?;
+            let tag = record[28..44].try_into()?;
+            let aad = self.record_aad(sequence, image_len);
+            if sequence > self.sequence && self.open_in_place(nonce, &aad, &mut [], tag) {
+                self.sequence = sequence;
+                self.image_len = usize::try_from(image_len)?;
+            }
+        }
+
+        if written && self.sequence == 0 {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "Cannot decrypt {}: wrong encryption key, or the file header is damaged",
+                    path.display()
+                )
+            ));
+        }
+
+        // Slots past the committed image are left over from a shrink
+        // interrupted by a crash; drop them before they could be mistaken for
+        // newer versions of blocks the image grows back into
+        let blocks = self.image_len.div_ceil(BLOCK_SIZE);
+        let expected = Self::file_len(blocks);
+        let actual = self.file.metadata()?.len();
+        if actual < expected {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                format!("Encrypted index file is truncated: {} of {} bytes", actual, expected)
+            ));
+        }
+        if actual > expected {
+            self.file.set_len(expected)?;
+        }
+
+        self.sealed = (0..blocks).map(|_| AtomicBool::new(true)).collect();
+        self.unsealing.get_mut().unwrap_or_else(PoisonError::into_inner).generations =
+            vec![0; blocks];
+        Ok(())
+    }
+
+    /// Length of the committed image, in bytes
+    pub(crate) fn image_len(&self) -> usize {
+        self.image_len
+    }
+
+    /// The locked file
+    pub(crate) fn file(&self) -> &File {
+        &self.file
+    }
+
+    /// Authenticates and decrypts the sealed blocks overlapping `range` into
+    /// the plaintext image at `image`, which is `image_len` bytes long
+    ///
+    /// Blocks that are already decrypted are skipped without taking a lock.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Corrupted` if a block has no slot that authenticates.
+    ///
+    /// # Safety
+    ///
+    /// `image` must be valid for writes of `image_len` bytes, and nothing may
+    /// read or write the bytes of a block that is still sealed: they are
+    /// written through `image`.
+    pub(crate) unsafe fn unseal(
+        &self,
+        image: *mut u8,
+        image_len: usize,
+        range: Range<usize>,
+    ) -> Result<()> {
+        let blocks =
+            range.start / BLOCK_SIZE..range.end.div_ceil(BLOCK_SIZE).min(self.sealed.len());
+        for index in blocks {
+            if !self.sealed[index].load(Ordering::Acquire) {
+                continue;
+            }
+            let mut unsealing = self.unsealing.lock().unwrap_or_else(PoisonError::into_inner);
+            if !self.sealed[index].load(Ordering::Acquire) {
+                continue;
+            }
+
+            let Unsealing { generations, pair } = &mut *unsealing;
+            pair.resize(2 * SLOT_SIZE, 0);
+            read_exact_at(&self.file, pair, Self::slot_offset(index, 0))?;
+            let generation = self.open_block(index, pair)?;
+
+            let start = index * BLOCK_SIZE;
+            let len = BLOCK_SIZE.min(image_len.saturating_sub(start));
+            let slot = (generation % 2) as usize * SLOT_SIZE + SLOT_PREFIX;
+            // SAFETY: `start + len` is within the image (upheld by the caller),
+            // and the block is sealed, so nothing references these bytes yet;
+            // the lock keeps other threads from writing them at the same time
+            unsafe { std::ptr::copy_nonoverlapping(pair[slot..].as_ptr(), image.add(start), len) };
+            pair.zeroize();
+
+            generations[index] = generation;
+            self.sealed[index].store(false, Ordering::Release);
+        }
+        Ok(())
+    }
+
+    /// Returns true if the block holding image offset `offset` is still sealed
+    pub(crate) fn is_sealed(&self, offset: usize) -> bool {
+        self.sealed.get(offset / BLOCK_SIZE).is_some_and(|sealed| sealed.load(Ordering::Acquire))
+    }
+
+    /// Drops the blocks at or past `len` from the image, after it shrank
+    ///
+    /// Their memory is gone, so they must not be unsealed into the image if
+    /// it grows back; they then read as zeros, like the grown part of a file.
+    /// Their generations are still read from the file, so the next commit
+    /// writes a newer version of each.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the slots cannot be read.
+    pub(crate) fn discard(&mut self, len: usize) -> Result<()> {
+        let generations =
+            &mut self.unsealing.get_mut().unwrap_or_else(PoisonError::into_inner).generations;
+        let mut prefix = [0u8; SLOT_PREFIX];
+        for (index, sealed) in self.sealed.iter().enumerate().skip(len.div_ceil(BLOCK_SIZE)) {
+            if !sealed.swap(false, Ordering::Relaxed) {
+                continue;
+            }
+            for slot in 0..2 {
+                read_exact_at(&self.file, &mut prefix, Self::slot_offset(index, slot))?;
+                generations[index] = generations[index].max(slot_generation(&prefix));
+            }
+        }
+        Ok(())
+    }
+
+    /// Decrypts the newest slot of block `index` that authenticates, in place
+    /// in `pair` (its two slots), and returns its generation
+    fn open_block(&self, index: usize, pair: &mut [u8]) -> Result<u64> {
+        let mut slots: Vec<&mut [u8]> = pair.chunks_mut(SLOT_SIZE).collect();
+        slots.sort_by_key(|slot| std::cmp::Reverse(slot_generation(slot)));
+
+        for slot in slots {
+            let generation = slot_generation(slot);
+            let (prefix, ciphertext) = slot.split_at_mut(SLOT_PREFIX);
+            let nonce = prefix[..NONCE_LEN].try_into()?;
+            let tag = prefix[NONCE_LEN + 8..].try_into()?;
+            let aad = self.block_aad(index as u64, generation);
+            if generation != 0 && self.open_in_place(nonce, &aad, ciphertext, tag) {
+                return Ok(generation);
+            }
+        }
+        anyhow::bail!(Tagged::new(
+            ErrorKind::Corrupted,
+            format!("Encrypted block {} failed authentication", index)
+        ))
+    }
+
+    /// Seals the blocks of `image` that overlap `dirty`, and every block past
+    /// the previous commit, then makes them durable
+    ///
+    /// Blocks that were never unsealed cannot have changed and are skipped.
+    /// A commit record naming the new length is written (after the blocks
+    /// are synced) only when the length changed.
+    pub(crate) fn commit(&mut self, image: &[u8], dirty: &[Range<usize>]) -> Result<()> {
+        let blocks = image.len().div_ceil(BLOCK_SIZE);
+        let generations =
+            &mut self.unsealing.get_mut().unwrap_or_else(PoisonError::into_inner).generations;
+        let committed = generations.len().min(blocks);
+        generations.resize(blocks, 0);
+        self.sealed.truncate(blocks);
+
+        let mut pending: Vec<Range<usize>> = dirty
+            .iter()
+            .map(|range| range.start / BLOCK_SIZE..range.end.div_ceil(BLOCK_SIZE).min(committed))
+            .filter(|range| !range.is_empty())
+            .collect();
+        pending.push(committed..blocks);
+
+        let mut slot = vec![0u8; SLOT_SIZE];
+        for index in pending.into_iter().flatten() {
+            if self.sealed.get(index).is_some_and(|sealed| sealed.load(Ordering::Relaxed)) {
+                continue;
+            }
+            let generations =
+                &mut self.unsealing.get_mut().unwrap_or_else(PoisonError::into_inner).generations;
+            let generation = generations[index] + 1;
+            let nonce: [u8; NONCE_LEN] = rand::random();
+            let plaintext = &image[index * BLOCK_SIZE..image.len().min((index + 1) * BLOCK_SIZE)];
+
+            let (prefix, ciphertext) = slot.split_at_mut(SLOT_PREFIX);
+            ciphertext.fill(0);
+            ciphertext[..plaintext.len()].copy_from_slice(plaintext);
+            let tag =
+                self.seal_in_place(&nonce, &self.block_aad(index as u64, generation), ciphertext);
+            prefix[..NONCE_LEN].copy_from_slice(&nonce);
+            prefix[NONCE_LEN..NONCE_LEN + 8].copy_from_slice(&generation.to_le_bytes());
+            prefix[NONCE_LEN + 8..].copy_from_slice(&tag);
+
+            let offset = Self::slot_offset(index, generation);
+            self.write_at(offset, &slot)?;
+            self.unsealing.get_mut().unwrap_or_else(PoisonError::into_inner).generations[index] =
+                generation;
+        }
+        slot.zeroize();
+
+        if image.len() == self.image_len {
+            self.file.sync_data()?;
+            return Ok(());
+        }
+
+        // The record may only name blocks that are already on disk
+        self.file.sync_data()?;
+        let sequence = self.sequence + 1;
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let tag =
+            self.seal_in_place(&nonce, &self.record_aad(sequence, image.len() as u64), &mut []);
+        let mut record = [0u8; RECORD_LEN];
+        record[..8].copy_from_slice(&sequence.to_le_bytes());
+        record[8..16].copy_from_slice(&(image.len() as u64).to_le_bytes());
+        record[16..28].copy_from_slice(&nonce);
+        record[28..44].copy_from_slice(&tag);
+        self.write_at((RECORD_OFFSET + (sequence % 2) as usize * RECORD_LEN) as u64, &record)?;
+
+        self.file.set_len(Self::file_len(blocks))?;
+        self.file.sync_all()?;
+        self.sequence = sequence;
+        self.image_len = image.len();
+        Ok(())
+    }
+
+    /// Reads the whole file (ciphertext only), for snapshots
+    pub(crate) fn read_all(&self) -> Result<Vec<u8>> {
+        let mut bytes = vec![0u8; usize::try_from(self.file.metadata()?.len())?];
+        read_exact_at(&self.file, &mut bytes, 0)?;
+        Ok(bytes)
+    }
+
+    /// Encrypts `buffer` in place and returns its tag
+    fn seal_in_place(
+        &self,
+        nonce: &[u8; NONCE_LEN],
+        aad: &[u8],
+        buffer: &mut [u8],
+    ) -> [u8; TAG_LEN] {
+        self.cipher
+            .encrypt_in_place_detached(Nonce::from_slice(nonce), aad, buffer)
+            .expect("blocks are far below the GCM message limit")
+            .into()
+    }
+
+    /// Decrypts `buffer` in place; `false` if it does not authenticate under
+    /// `tag`, in which case `buffer` holds garbage
+    fn open_in_place(
+        &self,
+        nonce: &[u8; NONCE_LEN],
+        aad: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8; TAG_LEN],
+    ) -> bool {
+        self.cipher
+            .decrypt_in_place_detached(Nonce::from_slice(nonce), aad, buffer, Tag::from_slice(tag))
+            .is_ok()
+    }
+
+    /// File length holding `blocks` blocks
+    fn file_len(blocks: usize) -> u64 {
+        (HEADER_LEN + blocks * 2 * SLOT_SIZE) as u64
+    }
+
+    /// Offset of the slot that holds `generation` of block `index`
+    fn slot_offset(index: usize, generation: u64) -> u64 {
+        (HEADER_LEN + (2 * index + (generation % 2) as usize) * SLOT_SIZE) as u64
+    }
+
+    /// Associated data of a block: its position, version and file
+    fn block_aad(&self, index: u64, generation: u64) -> [u8; 40] {
+        let mut aad = [0u8; 40];
+        aad[..8].copy_from_slice(b"block\0\0\0");
+        aad[8..24].copy_from_slice(&self.salt);
+        aad[24..32].copy_from_slice(&index.to_le_bytes());
+        aad[32..].copy_from_slice(&generation.to_le_bytes());
+        aad
+    }
+
+    /// Associated data of a commit record
+    fn record_aad(&self, sequence: u64, image_len: u64) -> [u8; 40] {
+        let mut aad = [0u8; 40];
+        aad[..8].copy_from_slice(b"commit\0\0");
+        aad[8..24].copy_from_slice(&self.salt);
+        aad[24..32].copy_from_slice(&sequence.to_le_bytes());
+        aad[32..].copy_from_slice(&image_len.to_le_bytes());
+        aad
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> std::io::Result<()> {
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(buf)
+    }
+}
+
+/// Generation recorded in a block slot (0 for a slot never written)
+fn slot_generation(slot: &[u8]) -> u64 {
+    u64::from_le_bytes(slot[NONCE_LEN..NONCE_LEN + 8].try_into().expect("8 bytes"))
+}
+
+/// Reads `buf.len()` bytes at `offset` without moving the file position, so
+/// threads unsealing blocks do not race over it
+fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> std::io::Result<()> {
+    #[cfg(unix)]
+    return std::os::unix::fs::FileExt::read_exact_at(file, buf, offset);
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::FileExt;
+        let (mut buf, mut offset) = (buf, offset);
+        while !buf.is_empty() {
+            match file.seek_read(buf, offset)? {
+                0 => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+                n => {
+                    buf = &mut buf[n..];
+                    offset += n as u64;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    /// Unseals the whole committed image of `file` into a buffer
+    fn read_image(file: &EncryptedFile) -> Result<Vec<u8>> {
+        let mut image = vec![0u8; file.image_len()];
+        // SAFETY: `image` is a buffer of the image's length, borrowed by nothing else
+        unsafe { file.unseal(image.as_mut_ptr(), image.len(), 0..image.len())? };
+        Ok(image)
+    }
+
+    fn image(blocks: usize, seed: u8) -> Vec<u8> {
+        (0..blocks * BLOCK_SIZE).map(|i| (i as u8).wrapping_mul(31).wrapping_add(seed)).collect()
+    }
+
+    #[test]
+    fn test_commit_roundtrip_and_wrong_key() {
+        let temp = NamedTempFile::new().unwrap();
+        let key = EncryptionKey::from_bytes([3; KEY_LEN]);
+        let first = image(3, 1);
+        {
+            let mut file = EncryptedFile::open(temp.path(), &key).unwrap();
+            file.commit(&first, &[]).unwrap();
+        }
+
+        let bytes = std::fs::read(temp.path()).unwrap();
+        assert_eq!(bytes.len() as u64, EncryptedFile::file_len(3));
+        assert!(!bytes.windows(64).any(|w| w == &first[..64]));
+
+        let file = EncryptedFile::open(temp.path(), &key).unwrap();
+        assert_eq!(read_image(&file).unwrap(), first);
+        drop(file);
+
+        let err =
+            EncryptedFile::open(temp.path(), &EncryptionKey::from_bytes([4; KEY_LEN])).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+    }
+
+    #[test]
+    fn test_dirty_blocks_alternate_slots_and_survive_a_torn_write() {
+        let temp = NamedTempFile::new().unwrap();
+        let key = EncryptionKey::from_bytes([9; KEY_LEN]);
+        let mut current = image(4, 2);
+        let mut file = EncryptedFile::open(temp.path(), &key).unwrap();
+        file.commit(&current, &[]).unwrap();
+
+        // Only block 2 is rewritten, into the slot its first version did not use
+        let before = std::fs::read(temp.path()).unwrap();
+        current[2 * BLOCK_SIZE + 5] ^= 0xff;
+        file.commit(&current, std::slice::from_ref(&(2 * BLOCK_SIZE..3 * BLOCK_SIZE))).unwrap();
+        let after = std::fs::read(temp.path()).unwrap();
+        let changed: Vec<usize> = (0..8)
+            .filter(|&slot| {
+                let range = HEADER_LEN + slot * SLOT_SIZE..HEADER_LEN + (slot + 1) * SLOT_SIZE;
+                before[range.clone()] != after[range]
+            })
+            .collect();
+        assert_eq!(changed, [4]);
+        drop(file);
+
+        // A torn write of the newer slot falls back to the older version
+        let mut torn = after.clone();
+        torn[HEADER_LEN + 4 * SLOT_SIZE + SLOT_PREFIX + 100] ^= 1;
+        std::fs::write(temp.path(), &torn).unwrap();
+        let file = EncryptedFile::open(temp.path(), &key).unwrap();
+        assert_eq!(read_image(&file).unwrap(), image(4, 2));
+        drop(file);
+
+        // With both slots damaged the block cannot be read
+        torn[HEADER_LEN + 5 * SLOT_SIZE + SLOT_PREFIX] ^= 1;
+        std::fs::write(temp.path(), &torn).unwrap();
+        let file = EncryptedFile::open(temp.path(), &key).unwrap();
+        let err = read_image(&file).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Corrupted);
+    }
+
+    #[test]
+    fn test_blocks_unseal_on_first_access() {
+        let temp = NamedTempFile::new().unwrap();
+        let key = EncryptionKey::from_bytes([6; KEY_LEN]);
+        let first = image(4, 1);
+        EncryptedFile::open(temp.path(), &key).unwrap().commit(&first, &[]).unwrap();
+
+        // Damage block 3, whose only version is in its second slot
+        let mut bytes = std::fs::read(temp.path()).unwrap();
+        bytes[HEADER_LEN + 7 * SLOT_SIZE + SLOT_PREFIX] ^= 1;
+        std::fs::write(temp.path(), &bytes).unwrap();
+
+        let mut file = EncryptedFile::open(temp.path(), &key).unwrap();
+        assert!((0..4).all(|index| file.is_sealed(index * BLOCK_SIZE)));
+        let mut memory = vec![0u8; first.len()];
+        // SAFETY: `memory` is a buffer of the image's length, borrowed by nothing else
+        unsafe { file.unseal(memory.as_mut_ptr(), memory.len(), BLOCK_SIZE + 1..BLOCK_SIZE + 2) }
+            .unwrap();
+        assert!(file.is_sealed(0) && !file.is_sealed(BLOCK_SIZE));
+        assert_eq!(memory[BLOCK_SIZE..2 * BLOCK_SIZE], first[BLOCK_SIZE..2 * BLOCK_SIZE]);
+        assert!(memory[..BLOCK_SIZE].iter().chain(&memory[2 * BLOCK_SIZE..]).all(|&b| b == 0));
+
+        // The damaged block fails only once it is accessed
+        // SAFETY: As above
+        let err =
+            unsafe { file.unseal(memory.as_mut_ptr(), memory.len(), 0..memory.len()) }.unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Corrupted);
+
+        // Discarded blocks are written as newer versions than the ones on disk
+        file.discard(2 * BLOCK_SIZE).unwrap();
+        let second = image(4, 9);
+        memory[2 * BLOCK_SIZE..].copy_from_slice(&second[2 * BLOCK_SIZE..]);
+        file.commit(&memory, std::slice::from_ref(&(2 * BLOCK_SIZE..4 * BLOCK_SIZE))).unwrap();
+        drop(file);
+
+        let file = EncryptedFile::open(temp.path(), &key).unwrap();
+        let read = read_image(&file).unwrap();
+        assert_eq!(read[..2 * BLOCK_SIZE], first[..2 * BLOCK_SIZE]);
+        assert_eq!(read[2 * BLOCK_SIZE..], second[2 * BLOCK_SIZE..]);
+    }
+
+    #[test]
+    fn test_shrink_and_grow_rewrite_the_commit_record() {
+        let temp = NamedTempFile::new().unwrap();
+        let key = EncryptionKey::from_bytes([5; KEY_LEN]);
+        let mut file = EncryptedFile::open(temp.path(), &key).unwrap();
+        file.commit(&image(5, 3), &[]).unwrap();
+        file.commit(&image(2, 3), &[]).unwrap();
+        assert_eq!(std::fs::metadata(temp.path()).unwrap().len(), EncryptedFile::file_len(2));
+
+        let grown = image(3, 7);
+        file.commit(&grown, std::slice::from_ref(&(0..BLOCK_SIZE))).unwrap();
+        drop(file);
+
+        let file = EncryptedFile::open(temp.path(), &key).unwrap();
+        assert_eq!(file.image_len(), grown.len());
+        let read = read_image(&file).unwrap();
+        assert_eq!(read[..BLOCK_SIZE], grown[..BLOCK_SIZE]);
+        assert_eq!(read[BLOCK_SIZE..2 * BLOCK_SIZE], image(2, 3)[BLOCK_SIZE..]);
+        assert_eq!(read[2 * BLOCK_SIZE..], grown[2 * BLOCK_SIZE..]);
+    }
+}
@@ -0,0 +1,153 @@
+//! Typed errors that callers may want to branch on.
+//!
+//! Chassis reports errors through `anyhow::Error`. The types here are carried
+//! inside it and can be recovered with `err.downcast_ref::<T>()`.
+
+use std::fmt;
+use std::path::PathBuf;
+
+/// The index file was deleted or replaced while the index was open.
+///
+/// Returned by `flush()` instead of writing into a file that no longer lives
+/// at `path` (typical with sync tools that replace files atomically). Nothing
+/// is flushed; the in-memory state is intact and can be written out with
+/// `VectorIndex::reattach()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStolen {
+    /// Path the index was opened (or last reattached) at
+    pub path: PathBuf,
+
+    /// `true` if nothing exists at `path`; `false` if a different file does
+    pub deleted: bool,
+}
+
+impl fmt::Display for FileStolen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let what = if self.deleted { "deleted" } else { "replaced by another file" };
+        write!(
+            f,
+            "Index file {} was {} while open; call reattach() to continue",
+            self.path.display(),
+            what
+        )
+    }
+}
+
+impl std::error::Error for FileStolen {}
+
+/// Broad category of an error returned by Chassis
+///
+/// Use `ErrorKind::of()` to branch on the kind of failure without matching on
+/// message text. New kinds may be added in future releases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// An argument or option was out of range
+    InvalidArgument,
+
+    /// A vector or query had the wrong number of dimensions, or the file was
+    /// created with different dimensions
+    DimensionMismatch,
+
+    /// The file is locked by another process (or handle)
+    Locked,
+
+    /// The file is not a Chassis index, or its contents are inconsistent
+    Corrupted,
+
+    /// An operating system I/O call failed
+    Io,
+
+    /// A vector ID or internal offset was out of range
+    OutOfBounds,
+
+    /// The file was deleted or replaced while open (see `FileStolen`)
+    FileStolen,
+
+    /// A write was attempted on a read-only (shared reader) index
+    ReadOnly,
+
+    /// The file was written by a library version refused by `VersionPolicy`,
+    /// is in a format version this library cannot read, or needs an upgrade
+    /// a read-only open cannot perform (see `migrate_file()`)
+    IncompatibleVersion,
+
+    /// The index would grow past `IndexOptions::max_file_bytes`
+    QuotaExceeded,
+
+    /// A progress handler stopped a long-running operation (see
+    /// `VectorIndex::set_progress_handler()`)
+    Cancelled,
+
+    /// Any other failure
+    Other,
+}
+
+impl ErrorKind {
+    /// Classifies an error returned by Chassis
+    pub fn of(err: &anyhow::Error) -> Self {
+        if let Some(tagged) = err.downcast_ref::<Tagged>() {
+            return tagged.kind;
+        }
+
+        if err.downcast_ref::<FileStolen>().is_some() {
+            return Self::FileStolen;
+        }
+
+        if err.chain().any(|cause| cause.downcast_ref::<std::io::Error>().is_some()) {
+            return Self::Io;
+        }
+
+        Self::Other
+    }
+}
+
+/// Error (or context) message tagged with an `ErrorKind`
+///
+/// Used with `anyhow::bail!(Tagged::new(..))` or `.context(Tagged::new(..))`;
+/// displays exactly like the plain message would.
+#[derive(Debug)]
+pub(crate) struct Tagged {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl Tagged {
+    pub(crate) fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into() }
+    }
+}
+
+impl fmt::Display for Tagged {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Tagged {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_error_kind_classification() {
+        let tagged = anyhow::Error::new(Tagged::new(ErrorKind::DimensionMismatch, "bad dims"));
+        assert_eq!(ErrorKind::of(&tagged), ErrorKind::DimensionMismatch);
+        assert_eq!(tagged.to_string(), "bad dims");
+
+        // Tags survive as context and under further context
+        let io = std::io::Error::from(std::io::ErrorKind::WouldBlock);
+        let locked =
+            Err::<(), _>(io).context(Tagged::new(ErrorKind::Locked, "locked")).unwrap_err();
+        assert_eq!(ErrorKind::of(&locked.context("outer")), ErrorKind::Locked);
+
+        let stolen = anyhow::Error::new(FileStolen { path: PathBuf::from("x"), deleted: true });
+        assert_eq!(ErrorKind::of(&stolen), ErrorKind::FileStolen);
+
+        let io = anyhow::Error::new(std::io::Error::other("disk")).context("while flushing");
+        assert_eq!(ErrorKind::of(&io), ErrorKind::Io);
+        assert_eq!(ErrorKind::of(&anyhow::anyhow!("plain")), ErrorKind::Other);
+    }
+}
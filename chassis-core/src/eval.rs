@@ -0,0 +1,220 @@
+//! Recall and latency evaluation for tuning `M` and `ef`.
+//!
+//! HNSW search is approximate; how approximate depends on `max_connections`,
+//! `ef_construction` and `ef_search`. This module measures it against an exact
+//! baseline so parameters can be picked from numbers instead of guesses:
+//!
+//! - `exact_search()` ranks every stored vector (brute force), using the same
+//!   distance kernels as the graph.
+//! - `ground_truth()` runs `exact_search()` for a set of queries.
+//! - `evaluate_recall()` searches the graph and reports recall@k and latency
+//!   percentiles; `evaluate_recall_sweep()` repeats that for several `ef`
+//!   values without reopening the index.
+//!
+//! Evaluation searches are not counted in `export_workload_profile()`.
+//!
+//! ```no_run
+//! use chassis_core::eval::{evaluate_recall_sweep, ground_truth};
+//! use chassis_core::{IndexOptions, VectorIndex};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let index = VectorIndex::open("embeddings.chassis", 768, IndexOptions::default())?;
+//! let queries: Vec<Vec<f32>> = vec![vec![0.0; 768]; 100];
+//!
+//! let truth = ground_truth(&index, &queries, 10)?;
+//! for report in evaluate_recall_sweep(&index, &queries, &truth, 10, &[16, 32, 64, 128])? {
+//!     println!("ef={} recall={:.3} p99={:?}", report.ef, report.recall, report.latency_p99);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{ErrorKind, Tagged};
+use crate::{SearchResult, VectorIndex};
+use anyhow::Result;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// Outcome of `evaluate_recall()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecallReport {
+    /// Neighbors requested per query
+    pub k: usize,
+
+    /// Search beam used (`max(ef, k)` is what the graph explores)
+    pub ef: usize,
+
+    /// Number of queries evaluated
+    pub queries: usize,
+
+    /// Fraction of true nearest neighbors found, averaged over queries (0.0..=1.0)
+    pub recall: f64,
+
+    /// Mean search latency
+    pub latency_mean: Duration,
+
+    /// Median search latency
+    pub latency_p50: Duration,
+
+    /// 90th percentile search latency
+    pub latency_p90: Duration,
+
+    /// 99th percentile search latency
+    pub latency_p99: Duration,
+
+    /// Slowest search
+    pub latency_max: Duration,
+}
+
+/// Exact k nearest neighbors by scanning every stored vector
+///
+/// Same as `VectorIndex::search_exact()`, but not counted in the workload
+/// profile. Includes unflushed vectors. Ties are broken by ID.
+///
+/// # Errors
+///
+/// Returns an error if the query dimensions don't match the index or a vector
+/// cannot be read.
+pub fn exact_search(index: &VectorIndex, query: &[f32], k: usize) -> Result<Vec<SearchResult>> {
+    let query = index.stored_prefix(query, "Query")?;
+    index.scan_nearest(&query, k, u64::MAX)
+}
+
+/// IDs of the exact k nearest neighbors of each query
+///
+/// # Errors
+///
+/// Returns an error if any query has the wrong dimensions.
+pub fn ground_truth<Q: AsRef<[f32]>>(
+    index: &VectorIndex,
+    queries: &[Q],
+    k: usize,
+) -> Result<Vec<Vec<u64>>> {
+    queries
+        .iter()
+        .map(|query| {
+            Ok(exact_search(index, query.as_ref(), k)?.into_iter().map(|r| r.id).collect())
+        })
+        .collect()
+}
+
+/// Measures recall@k and latency of the index's configured `ef_search`
+///
+/// `ground_truth[i]` lists the true nearest neighbors of `queries[i]`, nearest
+/// first, for example from `ground_truth()` or a benchmark dataset. Only its
+/// first `k` entries are used. A query whose truth has fewer than `k` entries
+/// (a small index) is scored against the entries it has.
+///
+/// # Errors
+///
+/// Returns an error if `queries` and `ground_truth` differ in length, `k` is
+/// zero, or a search fails.
+pub fn evaluate_recall<Q: AsRef<[f32]>>(
+    index: &VectorIndex,
+    queries: &[Q],
+    ground_truth: &[Vec<u64>],
+    k: usize,
+) -> Result<RecallReport> {
+    evaluate(index, queries, ground_truth, k, index.options.ef_search)
+}
+
+/// Runs `evaluate_recall()` once per `ef` value
+///
+/// The index is searched with each `ef` in turn instead of its configured
+/// `ef_search`, so a recall/latency curve can be drawn from one open index.
+///
+/// # Errors
+///
+/// Same as `evaluate_recall()`.
+pub fn evaluate_recall_sweep<Q: AsRef<[f32]>>(
+    index: &VectorIndex,
+    queries: &[Q],
+    ground_truth: &[Vec<u64>],
+    k: usize,
+    ef_values: &[usize],
+) -> Result<Vec<RecallReport>> {
+    ef_values.iter().map(|&ef| evaluate(index, queries, ground_truth, k, ef)).collect()
+}
+
+fn evaluate<Q: AsRef<[f32]>>(
+    index: &VectorIndex,
+    queries: &[Q],
+    ground_truth: &[Vec<u64>],
+    k: usize,
+    ef: usize,
+) -> Result<RecallReport> {
+    if queries.len() != ground_truth.len() {
+        anyhow::bail!(Tagged::new(
+            ErrorKind::InvalidArgument,
+            format!("Got {} queries but ground truth for {}", queries.len(), ground_truth.len())
+        ));
+    }
+    if k == 0 {
+        anyhow::bail!(Tagged::new(ErrorKind::InvalidArgument, "k must be at least 1"));
+    }
+
+    let mut latencies = Vec::with_capacity(queries.len());
+    let mut recall_sum = 0.0;
+    for (query, truth) in queries.iter().zip(ground_truth) {
+        let query = index.stored_prefix(query.as_ref(), "Query")?;
+
+        let start = Instant::now();
+        let results = index.graph.search(&query, k, ef)?;
+        latencies.push(start.elapsed());
+
+        recall_sum += recall_at_k(&results, truth, k);
+    }
+
+    latencies.sort_unstable();
+    let total: Duration = latencies.iter().sum();
+    Ok(RecallReport {
+        k,
+        ef,
+        queries: queries.len(),
+        recall: if queries.is_empty() { 1.0 } else { recall_sum / queries.len() as f64 },
+        latency_mean: total.checked_div(latencies.len() as u32).unwrap_or_default(),
+        latency_p50: percentile(&latencies, 50),
+        latency_p90: percentile(&latencies, 90),
+        latency_p99: percentile(&latencies, 99),
+        latency_max: latencies.last().copied().unwrap_or_default(),
+    })
+}
+
+/// Fraction of the first `k` true neighbors that appear in `results`
+fn recall_at_k(results: &[SearchResult], truth: &[u64], k: usize) -> f64 {
+    let truth = &truth[..truth.len().min(k)];
+    if truth.is_empty() {
+        return 1.0;
+    }
+    let found: HashSet<u64> = results.iter().take(k).map(|r| r.id).collect();
+    truth.iter().filter(|id| found.contains(id)).count() as f64 / truth.len() as f64
+}
+
+/// Nearest-rank percentile of sorted samples
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (sorted.len() * p).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recall_and_percentiles() {
+        let results: Vec<SearchResult> =
+            [3, 1, 9].iter().map(|&id| SearchResult { id, distance: 0.0 }).collect();
+        assert_eq!(recall_at_k(&results, &[1, 2, 3, 4], 3), 2.0 / 3.0);
+        assert_eq!(recall_at_k(&results, &[9], 3), 1.0);
+        assert_eq!(recall_at_k(&results, &[], 3), 1.0);
+
+        let samples: Vec<Duration> = (1..=200).map(Duration::from_micros).collect();
+        assert_eq!(percentile(&samples, 50), Duration::from_micros(100));
+        assert_eq!(percentile(&samples, 99), Duration::from_micros(198));
+        assert_eq!(percentile(&samples[..1], 99), Duration::from_micros(1));
+        assert_eq!(percentile(&[], 50), Duration::ZERO);
+    }
+}
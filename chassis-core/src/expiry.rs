@@ -0,0 +1,189 @@
+//! Expiration times attached to vectors, for cache-like indexes.
+//!
+//! Applications that use an index as short-lived memory (recent conversation
+//! turns, cached tool results) give vectors a deadline with
+//! `VectorIndex::add_with_expiry()`. Nothing expires on its own:
+//! `VectorIndex::purge_expired()` deletes every vector whose deadline has
+//! passed, so the application decides when the work happens and what "now" is.
+//!
+//! IDs are dense, so the map is a vector indexed by vector ID, persisted as a
+//! metadata section, with the deadlines changed by each flush in journal
+//! records (`metadata::array_delta()`):
+//!
+//! ```text
+//! Offset  Size       Field
+//! ------  ----       -----
+//! 0       8          count: u64
+//! 8       8 * count  deadline of each vector ID: u64 seconds since the Unix epoch (u64::MAX = none)
+//! ```
+
+use crate::error::{ErrorKind, Tagged};
+use crate::metadata::{self, Changes, JournaledMap, SectionTag};
+use anyhow::Result;
+use std::collections::BTreeSet;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Metadata section tag for the persisted expiry map.
+pub(crate) const EXPIRY_SECTION: &SectionTag = b"EXPIRY\0\0";
+
+/// Stored for vectors that never expire.
+const NEVER: u64 = u64::MAX;
+
+/// Deadline of each vector, indexed by vector ID.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ExpiryMap {
+    /// Seconds since the Unix epoch, or `NEVER`; may be shorter than the index
+    deadlines: Vec<u64>,
+
+    /// Changed since it was last written to the file
+    changes: Changes,
+}
+
+impl ExpiryMap {
+    /// Deadline of vector `id`, if it has one
+    pub(crate) fn get(&self, id: u64) -> Option<SystemTime> {
+        let deadline = *self.deadlines.get(usize::try_from(id).ok()?)?;
+        (deadline != NEVER).then(|| UNIX_EPOCH + Duration::from_secs(deadline))
+    }
+
+    /// Set or clear the deadline of vector `id`
+    ///
+    /// Deadlines are kept to the second, rounded down; times before the Unix
+    /// epoch count as the epoch.
+    pub(crate) fn set(&mut self, id: u64, expires_at: Option<SystemTime>) {
+        let index = id as usize;
+        if index >= self.deadlines.len() {
+            if expires_at.is_none() {
+                return;
+            }
+            self.changes.touch_range(self.deadlines.len() as u64..id);
+            self.deadlines.resize(index + 1, NEVER);
+        }
+        self.deadlines[index] = expires_at.map_or(NEVER, unix_secs);
+        self.changes.touch(id);
+    }
+
+    /// IDs whose deadline is at or before `now`, in ascending order
+    pub(crate) fn expired(&self, now: SystemTime) -> Vec<u64> {
+        let now = unix_secs(now);
+        (0u64..)
+            .zip(&self.deadlines)
+            .filter(|&(_, &deadline)| deadline != NEVER && deadline <= now)
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Drop deadlines of IDs at or past `count` (vectors rolled back on open)
+    pub(crate) fn truncate(&mut self, count: u64) {
+        let count = usize::try_from(count).unwrap_or(usize::MAX);
+        if self.deadlines.len() > count {
+            self.changes.touch_range(count as u64..self.deadlines.len() as u64);
+            self.deadlines.truncate(count);
+        }
+    }
+
+    /// Heap bytes held by the map
+    pub(crate) fn heap_bytes(&self) -> usize {
+        self.deadlines.capacity() * std::mem::size_of::<u64>()
+    }
+}
+
+impl JournaledMap for ExpiryMap {
+    const TAG: &'static SectionTag = EXPIRY_SECTION;
+
+    fn changes(&self) -> &Changes {
+        &self.changes
+    }
+
+    fn changes_mut(&mut self) -> &mut Changes {
+        &mut self.changes
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 * (self.deadlines.len() + 1));
+        bytes.extend_from_slice(&(self.deadlines.len() as u64).to_le_bytes());
+        for deadline in &self.deadlines {
+            bytes.extend_from_slice(&deadline.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (chunks, rest) = bytes.as_chunks::<8>();
+        let count = chunks.first().map(|chunk| u64::from_le_bytes(*chunk));
+        if !rest.is_empty()
+            || count.and_then(|count| count.checked_add(1)) != Some(chunks.len() as u64)
+        {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                format!("Expiry section size mismatch: {} bytes", bytes.len())
+            ));
+        }
+
+        let deadlines = chunks[1..].iter().map(|chunk| u64::from_le_bytes(*chunk)).collect();
+        Ok(Self { deadlines, changes: Changes::default() })
+    }
+
+    fn delta_bytes(&self, ids: &BTreeSet<u64>) -> Vec<u8> {
+        metadata::array_delta(&self.deadlines, ids)
+    }
+
+    fn apply_delta(&mut self, bytes: &[u8]) -> Result<()> {
+        metadata::apply_array_delta(&mut self.deadlines, NEVER, bytes, "Expiry")
+    }
+}
+
+/// Whole seconds from the Unix epoch to `time`, 0 for earlier times
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs().min(NEVER - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_expiry_map_roundtrip() {
+        let mut map = ExpiryMap::default();
+        map.set(3, Some(at(100)));
+        map.set(1, Some(at(50)));
+        map.set(10, None);
+        assert!(!map.changes().is_empty());
+
+        let restored = ExpiryMap::from_bytes(&map.to_bytes()).unwrap();
+        assert_eq!(restored.get(1), Some(at(50)));
+        assert_eq!(restored.get(2), None);
+        assert_eq!(restored.get(3), Some(at(100)));
+        assert_eq!(restored.get(10), None);
+        assert!(restored.changes().is_empty());
+
+        let bytes = map.to_bytes();
+        assert!(ExpiryMap::from_bytes(&bytes[..bytes.len() - 8]).is_err());
+        assert!(ExpiryMap::from_bytes(&[]).is_err());
+    }
+
+    #[test]
+    fn test_expired_compares_whole_seconds() {
+        let mut map = ExpiryMap::default();
+        map.set(0, Some(at(100) + Duration::from_millis(900)));
+        map.set(2, Some(at(200)));
+        map.set(4, Some(UNIX_EPOCH - Duration::from_secs(5)));
+
+        assert_eq!(map.expired(at(99)), vec![4]);
+        assert_eq!(map.expired(at(100)), vec![0, 4]);
+        assert_eq!(map.expired(at(1000)), vec![0, 2, 4]);
+
+        let mut restored = ExpiryMap::from_bytes(&map.to_bytes()).unwrap();
+        map.changes_mut().rewritten(0);
+        map.set(2, None);
+        map.truncate(3);
+        assert_eq!(map.expired(at(1000)), vec![0]);
+
+        restored.apply_delta(&map.delta_bytes(map.changes().ids().unwrap())).unwrap();
+        assert_eq!(restored.expired(at(1000)), vec![0]);
+    }
+}
@@ -0,0 +1,684 @@
+//! Streaming export to JSON Lines and Arrow IPC files.
+//!
+//! `VectorIndex::export()` writes one row per live vector, in ID order, so
+//! the contents of an index can be loaded into analytics tools or other
+//! databases without a `get_vector()` loop in application code. Each row holds
+//! the vector's ID, its stored components, and whatever the application
+//! attached to it: key, group, tags and expiration time.
+//!
+//! - JSON Lines: one object per line, e.g.
+//!   `{"id":7,"vector":[0.5,-1],"key":"doc:7","group":3,"tags":[1,4],"expires_at":1767225600}`.
+//!   Absent attributes are left out; `expires_at` is in seconds since the Unix
+//!   epoch, and non-finite components are written as `null`.
+//! - Arrow IPC: an Arrow file ("Feather v2") with the columns
+//!
+//! ```text
+//! Column      Type                               Nullable
+//! ------      ----                               --------
+//! id          uint64                             no
+//! vector      fixed_size_list<float32>[dims]     no
+//! key         utf8                               yes
+//! group       uint64                             yes
+//! tags        list<uint32>                       no (empty list if none)
+//! expires_at  timestamp[s, tz=UTC]               yes
+//! ```
+//!
+//! Rows are written as they are read, in record batches of `BATCH_ROWS` for
+//! Arrow, so memory use does not grow with the index. The Arrow metadata is
+//! encoded here rather than through the `arrow` crates, which would dwarf the
+//! rest of the dependency tree.
+
+use crate::VectorIndex;
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// File format written by `VectorIndex::export()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// JSON Lines: one JSON object per vector
+    Jsonl,
+
+    /// Arrow IPC file format, readable by pyarrow, pandas, Polars and DuckDB
+    ArrowIpc,
+}
+
+/// Rows per Arrow record batch
+const BATCH_ROWS: usize = 1024;
+
+/// Magic string at both ends of an Arrow file (padded to 8 bytes at the start)
+const ARROW_MAGIC: &[u8; 6] = b"ARROW1";
+
+/// Marks the start of an encapsulated Arrow IPC message
+const CONTINUATION: u32 = 0xFFFF_FFFF;
+
+/// Arrow `MetadataVersion::V5`
+const METADATA_V5: u64 = 4;
+
+/// Arrow `MessageHeader` union tags
+const HEADER_SCHEMA: u64 = 1;
+const HEADER_RECORD_BATCH: u64 = 3;
+
+/// Arrow `Type` union tags
+const TYPE_INT: u64 = 2;
+const TYPE_FLOATING_POINT: u64 = 3;
+const TYPE_UTF8: u64 = 5;
+const TYPE_TIMESTAMP: u64 = 10;
+const TYPE_LIST: u64 = 12;
+const TYPE_FIXED_SIZE_LIST: u64 = 16;
+
+/// Arrow `Precision::SINGLE`
+const PRECISION_SINGLE: u64 = 1;
+
+/// Arrow `TimeUnit::SECOND`
+const UNIT_SECOND: u64 = 0;
+
+impl VectorIndex {
+    /// Write every live vector to a new file at `path`
+    ///
+    /// Rows come in ID order and skip deleted vectors. Each holds the ID, the
+    /// vector as stored (see `get_vector()`), and its key, group, tags and
+    /// expiration time, if set. Rows are streamed to the file rather than
+    /// collected first. An existing file at `path` is replaced.
+    ///
+    /// Vectors added since the last `flush()` are included.
+    ///
+    /// # Returns
+    ///
+    /// The number of rows written
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created or written.
+    pub fn export<P: AsRef<Path>>(&self, path: P, format: ExportFormat) -> Result<u64> {
+        let path = path.as_ref();
+        let file =
+            File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut out = Output { inner: BufWriter::new(file), position: 0 };
+
+        let rows = match format {
+            ExportFormat::Jsonl => self.write_jsonl(&mut out),
+            ExportFormat::ArrowIpc => self.write_arrow(&mut out),
+        }
+        .and_then(|rows| {
+            out.inner.flush()?;
+            Ok(rows)
+        })
+        .with_context(|| format!("Failed to export to {}", path.display()))?;
+        Ok(rows)
+    }
+
+    /// Calls `f` with the ID and decoded vector of each live vector, in order
+    fn for_each_row(&self, mut f: impl FnMut(u64, &[f32]) -> Result<()>) -> Result<()> {
+        for id in 0..self.len() {
+            if self.graph.is_deleted(id)? {
+                continue;
+            }
+            f(id, &self.decoded_vector(id)?)?;
+        }
+        Ok(())
+    }
+
+    fn write_jsonl(&self, out: &mut Output<impl Write>) -> Result<u64> {
+        let mut rows = 0;
+        let mut line = String::new();
+        self.for_each_row(|id, vector| {
+            use std::fmt::Write;
+
+            line.clear();
+            write!(line, "{{\"id\":{},\"vector\":[", id)?;
+            for (i, value) in vector.iter().enumerate() {
+                if i > 0 {
+                    line.push(',');
+                }
+                if value.is_finite() {
+                    write!(line, "{}", value)?;
+                } else {
+                    line.push_str("null");
+                }
+            }
+            line.push(']');
+            if let Some(key) = self.key_for_id(id) {
+                line.push_str(",\"key\":");
+                push_json_string(&mut line, key);
+            }
+            if let Some(group) = self.group_of(id) {
+                write!(line, ",\"group\":{}", group)?;
+            }
+            let tags = self.tags_of(id);
+            if !tags.is_empty() {
+                line.push_str(",\"tags\":[");
+                for (i, tag) in tags.iter().enumerate() {
+                    if i > 0 {
+                        line.push(',');
+                    }
+                    write!(line, "{}", tag)?;
+                }
+                line.push(']');
+            }
+            if let Some(expires_at) = self.expiry_of(id) {
+                write!(line, ",\"expires_at\":{}", unix_secs(expires_at))?;
+            }
+            line.push_str("}\n");
+
+            out.put(line.as_bytes())?;
+            rows += 1;
+            Ok(())
+        })?;
+        Ok(rows)
+    }
+
+    fn write_arrow(&self, out: &mut Output<impl Write>) -> Result<u64> {
+        let dims = self.dimensions() as usize;
+
+        out.put(ARROW_MAGIC)?;
+        out.put(&[0; 2])?;
+        let schema = arrow_schema(dims);
+        let schema_message = Table::default()
+            .with(0, Value::Scalar(METADATA_V5, 2))
+            .with(1, Value::Scalar(HEADER_SCHEMA, 1))
+            .with(2, Value::Table(schema.clone()));
+        out.put_message(&schema_message.finish(), &[])?;
+
+        let mut blocks = Vec::new();
+        let mut batch = Batch::default();
+        let mut rows = 0;
+        self.for_each_row(|id, vector| {
+            batch.push(
+                id,
+                vector,
+                self.key_for_id(id),
+                self.group_of(id),
+                &self.tags_of(id),
+                self.expiry_of(id),
+            );
+            rows += 1;
+            if batch.rows == BATCH_ROWS {
+                blocks.extend_from_slice(&batch.write(out, dims)?);
+                batch = Batch::default();
+            }
+            Ok(())
+        })?;
+        if batch.rows > 0 {
+            blocks.extend_from_slice(&batch.write(out, dims)?);
+        }
+
+        // End-of-stream marker, then the footer indexing the record batches
+        out.put(&CONTINUATION.to_le_bytes())?;
+        out.put(&0u32.to_le_bytes())?;
+        let footer = Table::default()
+            .with(0, Value::Scalar(METADATA_V5, 2))
+            .with(1, Value::Table(schema))
+            .with(2, Value::Structs(0, Vec::new()))
+            .with(3, Value::Structs(blocks.len() / 24, blocks))
+            .finish();
+        out.put(&footer)?;
+        out.put(&(footer.len() as u32).to_le_bytes())?;
+        out.put(ARROW_MAGIC)?;
+        Ok(rows)
+    }
+}
+
+/// Seconds since the Unix epoch; times before it count as the epoch
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs())
+}
+
+/// Appends `value` as a quoted JSON string
+fn push_json_string(line: &mut String, value: &str) {
+    line.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => line.push_str("\\\""),
+            '\\' => line.push_str("\\\\"),
+            '\n' => line.push_str("\\n"),
+            '\r' => line.push_str("\\r"),
+            '\t' => line.push_str("\\t"),
+            c if u32::from(c) < 0x20 => {
+                line.push_str(&format!("\\u{:04x}", u32::from(c)));
+            }
+            c => line.push(c),
+        }
+    }
+    line.push('"');
+}
+
+/// Export destination that tracks the file offset, for the Arrow footer
+struct Output<W> {
+    inner: W,
+    position: u64,
+}
+
+impl<W: Write> Output<W> {
+    fn put(&mut self, bytes: &[u8]) -> Result<()> {
+        self.inner.write_all(bytes)?;
+        self.position += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Writes an encapsulated IPC message and returns its footer `Block`
+    fn put_message(&mut self, metadata: &[u8], body: &[u8]) -> Result<[u8; 24]> {
+        let offset = self.position;
+        let padded = metadata.len().next_multiple_of(8);
+        self.put(&CONTINUATION.to_le_bytes())?;
+        self.put(&(padded as u32).to_le_bytes())?;
+        self.put(metadata)?;
+        self.put(&[0; 8][..padded - metadata.len()])?;
+        self.put(body)?;
+
+        let mut block = [0u8; 24];
+        block[0..8].copy_from_slice(&offset.to_le_bytes());
+        block[8..12].copy_from_slice(&(8 + padded as u32).to_le_bytes());
+        block[16..24].copy_from_slice(&(body.len() as u64).to_le_bytes());
+        Ok(block)
+    }
+}
+
+/// Columns of one Arrow record batch, as little-endian buffers
+#[derive(Default)]
+struct Batch {
+    rows: usize,
+    ids: Vec<u8>,
+    values: Vec<u8>,
+    key_validity: Validity,
+    key_offsets: Vec<u8>,
+    key_data: Vec<u8>,
+    group_validity: Validity,
+    groups: Vec<u8>,
+    tag_offsets: Vec<u8>,
+    tags: Vec<u8>,
+    tag_count: usize,
+    expiry_validity: Validity,
+    expiry: Vec<u8>,
+}
+
+impl Batch {
+    fn push(
+        &mut self,
+        id: u64,
+        vector: &[f32],
+        key: Option<&str>,
+        group: Option<u64>,
+        tags: &[u32],
+        expires_at: Option<SystemTime>,
+    ) {
+        if self.rows == 0 {
+            self.key_offsets.extend_from_slice(&0i32.to_le_bytes());
+            self.tag_offsets.extend_from_slice(&0i32.to_le_bytes());
+        }
+        self.rows += 1;
+
+        self.ids.extend_from_slice(&id.to_le_bytes());
+        vector.iter().for_each(|value| self.values.extend_from_slice(&value.to_le_bytes()));
+
+        self.key_validity.push(key.is_some());
+        self.key_data.extend_from_slice(key.unwrap_or_default().as_bytes());
+        self.key_offsets.extend_from_slice(&(self.key_data.len() as i32).to_le_bytes());
+
+        self.group_validity.push(group.is_some());
+        self.groups.extend_from_slice(&group.unwrap_or_default().to_le_bytes());
+
+        tags.iter().for_each(|tag| self.tags.extend_from_slice(&tag.to_le_bytes()));
+        self.tag_count += tags.len();
+        self.tag_offsets.extend_from_slice(&(self.tag_count as i32).to_le_bytes());
+
+        self.expiry_validity.push(expires_at.is_some());
+        let secs = expires_at.map_or(0, |time| unix_secs(time).min(i64::MAX as u64));
+        self.expiry.extend_from_slice(&secs.to_le_bytes());
+    }
+
+    /// Writes the batch as a record batch message and returns its footer `Block`
+    fn write(&self, out: &mut Output<impl Write>, dims: usize) -> Result<[u8; 24]> {
+        let rows = self.rows as u64;
+        let none = Validity::default();
+
+        // Field nodes (length, null count) and buffers, in schema order with
+        // each list's child after the list
+        let columns: [(u64, &Validity, &[&[u8]]); 8] = [
+            (rows, &none, &[&self.ids]),
+            (rows, &none, &[]),
+            (rows * dims as u64, &none, &[&self.values]),
+            (rows, &self.key_validity, &[&self.key_offsets, &self.key_data]),
+            (rows, &self.group_validity, &[&self.groups]),
+            (rows, &none, &[&self.tag_offsets]),
+            (self.tag_count as u64, &none, &[&self.tags]),
+            (rows, &self.expiry_validity, &[&self.expiry]),
+        ];
+
+        let mut nodes = Vec::new();
+        let mut buffers = Vec::new();
+        let mut body = Vec::new();
+        let mut add_buffer = |bytes: &[u8]| {
+            buffers.extend_from_slice(&(body.len() as u64).to_le_bytes());
+            buffers.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+            body.extend_from_slice(bytes);
+            body.resize(body.len().next_multiple_of(8), 0);
+        };
+        for (length, validity, data) in columns {
+            nodes.extend_from_slice(&length.to_le_bytes());
+            nodes.extend_from_slice(&validity.nulls.to_le_bytes());
+            // A column without nulls may leave out its validity bitmap
+            add_buffer(if validity.nulls > 0 { &validity.bits } else { &[] });
+            data.iter().for_each(|bytes| add_buffer(bytes));
+        }
+
+        let record_batch = Table::default()
+            .with(0, Value::Scalar(rows, 8))
+            .with(1, Value::Structs(nodes.len() / 16, nodes))
+            .with(2, Value::Structs(buffers.len() / 16, buffers));
+        let message = Table::default()
+            .with(0, Value::Scalar(METADATA_V5, 2))
+            .with(1, Value::Scalar(HEADER_RECORD_BATCH, 1))
+            .with(2, Value::Table(record_batch))
+            .with(3, Value::Scalar(body.len() as u64, 8));
+        out.put_message(&message.finish(), &body)
+    }
+}
+
+/// Arrow validity bitmap: bit `i % 8` of byte `i / 8` is set if row `i` is valid
+#[derive(Default)]
+struct Validity {
+    bits: Vec<u8>,
+    len: usize,
+    nulls: u64,
+}
+
+impl Validity {
+    fn push(&mut self, valid: bool) {
+        if self.len.is_multiple_of(8) {
+            self.bits.push(0);
+        }
+        if valid {
+            self.bits[self.len / 8] |= 1 << (self.len % 8);
+        } else {
+            self.nulls += 1;
+        }
+        self.len += 1;
+    }
+}
+
+/// The `Schema` table of the exported columns
+fn arrow_schema(dims: usize) -> Table {
+    let int = |bits: u64, signed: bool| {
+        Table::default().with(0, Value::Scalar(bits, 4)).with(1, Value::Scalar(signed.into(), 1))
+    };
+    let float = Table::default().with(0, Value::Scalar(PRECISION_SINGLE, 2));
+    let timestamp = Table::default()
+        .with(0, Value::Scalar(UNIT_SECOND, 2))
+        .with(1, Value::String("UTC".into()));
+
+    Table::default().with(
+        1,
+        Value::Tables(vec![
+            arrow_field("id", false, TYPE_INT, int(64, false), Vec::new()),
+            arrow_field(
+                "vector",
+                false,
+                TYPE_FIXED_SIZE_LIST,
+                Table::default().with(0, Value::Scalar(dims as u64, 4)),
+                vec![arrow_field("item", false, TYPE_FLOATING_POINT, float, Vec::new())],
+            ),
+            arrow_field("key", true, TYPE_UTF8, Table::default(), Vec::new()),
+            arrow_field("group", true, TYPE_INT, int(64, false), Vec::new()),
+            arrow_field(
+                "tags",
+                false,
+                TYPE_LIST,
+                Table::default(),
+                vec![arrow_field("item", false, TYPE_INT, int(32, false), Vec::new())],
+            ),
+            arrow_field("expires_at", true, TYPE_TIMESTAMP, timestamp, Vec::new()),
+        ]),
+    )
+}
+
+/// A `Field` table
+fn arrow_field(
+    name: &str,
+    nullable: bool,
+    type_tag: u64,
+    type_table: Table,
+    children: Vec<Table>,
+) -> Table {
+    Table::default()
+        .with(0, Value::String(name.into()))
+        .with(1, Value::Scalar(nullable.into(), 1))
+        .with(2, Value::Scalar(type_tag, 1))
+        .with(3, Value::Table(type_table))
+        .with(5, Value::Tables(children))
+}
+
+/// A FlatBuffers table to be serialized: `(field slot, value)` pairs
+///
+/// Only what Arrow metadata needs is supported. Buffers are laid out front to
+/// back: each vtable right before its table, and the objects a table points
+/// to after it, as the unsigned offsets of the format require.
+#[derive(Debug, Clone, Default)]
+struct Table(Vec<(u16, Value)>);
+
+#[derive(Debug, Clone)]
+enum Value {
+    /// Little-endian scalar of 1, 2, 4 or 8 bytes
+    Scalar(u64, usize),
+    Table(Table),
+    String(String),
+    Tables(Vec<Table>),
+    /// Vector of 8-byte aligned structs: element count and packed elements
+    Structs(usize, Vec<u8>),
+}
+
+impl Value {
+    /// Size of the value inside its table (offsets for all but scalars)
+    fn inline_size(&self) -> usize {
+        match self {
+            Self::Scalar(_, size) => *size,
+            _ => 4,
+        }
+    }
+}
+
+impl Table {
+    fn with(mut self, slot: u16, value: Value) -> Self {
+        self.0.push((slot, value));
+        self
+    }
+
+    /// Serializes the table as the root of a FlatBuffers buffer
+    fn finish(&self) -> Vec<u8> {
+        let mut buffer = vec![0; 4];
+        let root = self.write(&mut buffer);
+        buffer[..4].copy_from_slice(&(root as u32).to_le_bytes());
+        buffer
+    }
+
+    /// Appends the vtable, table and referenced objects; returns the table offset
+    fn write(&self, buffer: &mut Vec<u8>) -> usize {
+        // Inline layout after the vtable offset, each field at its natural
+        // alignment (the table itself starts 8-byte aligned)
+        let mut offsets = Vec::with_capacity(self.0.len());
+        let mut size = 4usize;
+        for (_, value) in &self.0 {
+            let width = value.inline_size();
+            size = size.next_multiple_of(width);
+            offsets.push(size);
+            size += width;
+        }
+
+        let slots = self.0.iter().map(|&(slot, _)| usize::from(slot) + 1).max().unwrap_or(0);
+        let mut vtable = vec![0u16; 2 + slots];
+        vtable[0] = (2 * vtable.len()) as u16;
+        vtable[1] = size as u16;
+        for (&(slot, _), &offset) in self.0.iter().zip(&offsets) {
+            vtable[2 + usize::from(slot)] = offset as u16;
+        }
+        pad(buffer, 2);
+        let vtable_at = buffer.len();
+        vtable.iter().for_each(|entry| buffer.extend_from_slice(&entry.to_le_bytes()));
+
+        pad(buffer, 8);
+        let start = buffer.len();
+        buffer.resize(start + size, 0);
+        buffer[start..start + 4].copy_from_slice(&((start - vtable_at) as i32).to_le_bytes());
+
+        let mut references = Vec::new();
+        for ((_, value), &offset) in self.0.iter().zip(&offsets) {
+            let at = start + offset;
+            match value {
+                Value::Scalar(bits, width) => {
+                    buffer[at..at + width].copy_from_slice(&bits.to_le_bytes()[..*width]);
+                }
+                _ => references.push((at, value)),
+            }
+        }
+        for (at, value) in references {
+            let target = match value {
+                Value::Scalar(..) => unreachable!("scalars are stored inline"),
+                Value::Table(table) => table.write(buffer),
+                Value::String(string) => {
+                    pad(buffer, 4);
+                    let target = buffer.len();
+                    buffer.extend_from_slice(&(string.len() as u32).to_le_bytes());
+                    buffer.extend_from_slice(string.as_bytes());
+                    buffer.push(0);
+                    target
+                }
+                Value::Tables(tables) => {
+                    pad(buffer, 4);
+                    let target = buffer.len();
+                    buffer.extend_from_slice(&(tables.len() as u32).to_le_bytes());
+                    buffer.resize(target + 4 + 4 * tables.len(), 0);
+                    for (i, table) in tables.iter().enumerate() {
+                        let element = target + 4 + 4 * i;
+                        let table_at = table.write(buffer);
+                        buffer[element..element + 4]
+                            .copy_from_slice(&((table_at - element) as u32).to_le_bytes());
+                    }
+                    target
+                }
+                Value::Structs(count, bytes) => {
+                    // The elements after the length must be 8-byte aligned
+                    pad(buffer, 8);
+                    buffer.extend_from_slice(&[0; 4]);
+                    let target = buffer.len();
+                    buffer.extend_from_slice(&(*count as u32).to_le_bytes());
+                    buffer.extend_from_slice(bytes);
+                    target
+                }
+            };
+            buffer[at..at + 4].copy_from_slice(&((target - at) as u32).to_le_bytes());
+        }
+        start
+    }
+}
+
+/// Zero-pads `buffer` to a multiple of `align` bytes
+fn pad(buffer: &mut Vec<u8>, align: usize) {
+    buffer.resize(buffer.len().next_multiple_of(align), 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IndexOptions;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    /// Index of four 3-dimensional vectors with every kind of attribute; vector 2 is deleted
+    fn sample_index() -> VectorIndex {
+        let mut index = VectorIndex::in_memory(3, IndexOptions::default()).unwrap();
+        index.add_with_key("doc:\"0\"\n", &[0.5, -1.0, 2.0]).unwrap();
+        index.add_to_group(7, &[1.0, 0.0, 0.25]).unwrap();
+        index.add(&[3.0, 3.0, 3.0]).unwrap();
+        index.add_with_tags(&[0.0, 1.0, 0.0], &[4, 1]).unwrap();
+        index.set_expiry(3, Some(UNIX_EPOCH + Duration::from_secs(1_767_225_600))).unwrap();
+        index.delete(2).unwrap();
+        index
+    }
+
+    #[test]
+    fn test_export_jsonl() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("rows.jsonl");
+        assert_eq!(sample_index().export(&path, ExportFormat::Jsonl).unwrap(), 3);
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                r#"{"id":0,"vector":[0.5,-1,2],"key":"doc:\"0\"\n"}"#,
+                r#"{"id":1,"vector":[1,0,0.25],"group":7}"#,
+                r#"{"id":3,"vector":[0,1,0],"tags":[1,4],"expires_at":1767225600}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_export_empty_index() {
+        let dir = TempDir::new().unwrap();
+        let index = VectorIndex::in_memory(3, IndexOptions::default()).unwrap();
+        let jsonl = dir.path().join("rows.jsonl");
+        let arrow = dir.path().join("rows.arrow");
+        assert_eq!(index.export(&jsonl, ExportFormat::Jsonl).unwrap(), 0);
+        assert_eq!(index.export(&arrow, ExportFormat::ArrowIpc).unwrap(), 0);
+        assert!(std::fs::read(&jsonl).unwrap().is_empty());
+        assert!(std::fs::read(&arrow).unwrap().ends_with(ARROW_MAGIC));
+    }
+
+    #[test]
+    fn test_export_arrow_layout() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("rows.arrow");
+        let mut index = sample_index();
+        for i in 0..BATCH_ROWS {
+            index.add(&[i as f32, 0.0, 0.0]).unwrap();
+        }
+        assert_eq!(index.export(&path, ExportFormat::ArrowIpc).unwrap(), 3 + BATCH_ROWS as u64);
+
+        let file = std::fs::read(&path).unwrap();
+        assert_eq!(&file[..8], b"ARROW1\0\0");
+        assert!(file.ends_with(ARROW_MAGIC));
+
+        // The footer sits between the end-of-stream marker and its length
+        let footer_len =
+            u32::from_le_bytes(file[file.len() - 10..file.len() - 6].try_into().unwrap());
+        let footer_start = file.len() - 10 - footer_len as usize;
+        assert_eq!(&file[footer_start - 8..footer_start], &[0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0]);
+
+        // Two record batches, each starting with a continuation marker and
+        // holding the vectors of its rows
+        let message_starts: Vec<usize> = (8..footer_start - 8)
+            .step_by(8)
+            .filter(|&at| file[at..at + 4] == CONTINUATION.to_le_bytes())
+            .collect();
+        assert_eq!(message_starts.len(), 3);
+        let first_vector: Vec<u8> =
+            [0.5f32, -1.0, 2.0].iter().flat_map(|v| v.to_le_bytes()).collect();
+        assert!(file.windows(12).any(|window| window == first_vector));
+    }
+
+    #[test]
+    fn test_table_layout() {
+        // Root offset, vtable (2 slots), table with a u8 and a string offset,
+        // then the string
+        let bytes = Table::default()
+            .with(0, Value::Scalar(9, 1))
+            .with(1, Value::String("ab".into()))
+            .finish();
+        let u16_at = |at: usize| u16::from_le_bytes(bytes[at..at + 2].try_into().unwrap());
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+
+        let table = u32_at(0) as usize;
+        assert_eq!(table % 8, 0);
+        let vtable = table - u32_at(table) as usize;
+        assert_eq!((u16_at(vtable), u16_at(vtable + 2)), (8, 12));
+        assert_eq!(bytes[table + usize::from(u16_at(vtable + 4))], 9);
+
+        let field = table + usize::from(u16_at(vtable + 6));
+        let string = field + u32_at(field) as usize;
+        assert_eq!(u32_at(string), 2);
+        assert_eq!(&bytes[string + 4..string + 7], b"ab\0");
+    }
+}
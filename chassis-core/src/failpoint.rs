@@ -0,0 +1,78 @@
+//! Failpoints for crash-consistency tests (`failpoints` feature)
+//!
+//! A failpoint is a named spot in a write path. Once armed, the write fails
+//! there with an `Io` error and leaves the file exactly as a process killed at
+//! that instant would: everything written before the failpoint is in the
+//! mapping, nothing after it is. Dropping the index without flushing and
+//! opening the file again then exercises the recovery path of `open()`.
+//!
+//! Failpoints are armed per thread, so tests running in parallel do not
+//! trigger each other's failpoints. Writes made on a background flush thread
+//! never trigger them.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::error::{ErrorKind, Tagged};
+
+/// After `add()` persisted the vector and bumped the storage count, before the
+/// graph node is written
+pub const AFTER_VECTOR_PERSIST: &str = "after-vector-persist";
+
+/// After the new node's record was written, before one of its backlinks is
+/// added to a neighbor
+pub const MID_BACKLINK_UPDATE: &str = "mid-backlink-update";
+
+/// During `flush()`, after vectors and node records were synced, before the
+/// graph header with the new node count is written
+pub const BEFORE_HEADER_WRITE: &str = "before-header-write";
+
+/// After `delete()` set a node's deleted flag, before the deleted count in
+/// the graph header is updated
+pub const MID_DELETE: &str = "mid-delete";
+
+/// After a deleted node's slot was given a new vector and node record,
+/// before the record is linked back from its neighbors
+pub const MID_REUSE: &str = "mid-reuse";
+
+thread_local! {
+    /// Armed failpoints and how many passes each lets through before failing
+    static ARMED: RefCell<HashMap<&'static str, u32>> = RefCell::new(HashMap::new());
+}
+
+/// Arm the failpoint `name` on this thread
+///
+/// The next `skip` passes through the failpoint go through; the one after
+/// fails and disarms it.
+pub fn arm(name: &'static str, skip: u32) {
+    ARMED.with(|armed| armed.borrow_mut().insert(name, skip));
+}
+
+/// Disarm all failpoints on this thread
+pub fn disarm_all() {
+    ARMED.with(|armed| armed.borrow_mut().clear());
+}
+
+/// Fails if `name` is armed and has no passes left
+pub(crate) fn hit(name: &'static str) -> Result<()> {
+    let triggered = ARMED.with(|armed| {
+        let mut armed = armed.borrow_mut();
+        match armed.get_mut(name) {
+            Some(0) => {
+                armed.remove(name);
+                true
+            }
+            Some(skip) => {
+                *skip -= 1;
+                false
+            }
+            None => false,
+        }
+    });
+    if triggered {
+        anyhow::bail!(Tagged::new(ErrorKind::Io, format!("Failpoint {} triggered", name)));
+    }
+    Ok(())
+}
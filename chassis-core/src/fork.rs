@@ -0,0 +1,86 @@
+//! Copy-on-write forks of an index for what-if experiments.
+//!
+//! An app that wants to preview the effect of some inserts or deletes, say to
+//! show search results "as if" a batch of notes were imported, can fork the
+//! index with `VectorIndex::fork_in_memory()`, change the fork freely and
+//! drop it. The fork maps the index file again privately: pages are shared
+//! with the file until the fork writes them, so a small change costs the
+//! pages it touches (the new vectors and the node records they link to)
+//! rather than a copy of the index. Once the fork grows past the end of the
+//! file, it moves to a full copy in anonymous memory.
+
+use crate::VectorIndex;
+use crate::instrument::Hooks;
+use crate::profile::WorkloadStats;
+use anyhow::Result;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+/// A writable, in-memory fork of an index, from `VectorIndex::fork_in_memory()`
+///
+/// Dereferences to a `VectorIndex` with the full API. Nothing it does reaches
+/// the original index or its file: `flush()` only finalizes the fork's own
+/// image, and `persist_to()` writes it to a new file. The fork borrows the
+/// original, which cannot be modified while the fork exists.
+#[derive(Debug)]
+pub struct IndexFork<'a> {
+    index: VectorIndex,
+
+    /// Pages the fork has not written still show the original's file
+    _original: PhantomData<&'a VectorIndex>,
+}
+
+impl Deref for IndexFork<'_> {
+    type Target = VectorIndex;
+
+    fn deref(&self) -> &VectorIndex {
+        &self.index
+    }
+}
+
+impl DerefMut for IndexFork<'_> {
+    fn deref_mut(&mut self) -> &mut VectorIndex {
+        &mut self.index
+    }
+}
+
+impl VectorIndex {
+    /// Fork the index into a writable copy-on-write overlay held in memory
+    ///
+    /// The fork starts with every vector currently in the index, including
+    /// unflushed ones, along with their keys, groups and tags, and accepts
+    /// inserts, deletes and updates like any writable index, even when this
+    /// index is a shared reader or sealed. Drop it to discard the changes.
+    /// Instrumentation is not carried over.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index belongs to a collection file, or the OS
+    /// refuses the mapping.
+    pub fn fork_in_memory(&self) -> Result<IndexFork<'_>> {
+        let graph = self.graph.fork()?;
+        let index = VectorIndex {
+            graph,
+            options: self.options.clone(),
+            ml: self.ml,
+            durable_count: Arc::new(AtomicU64::new(self.durable_count.load(Ordering::Acquire))),
+            unflushed_inserts: 0,
+            last_flush: Instant::now(),
+            #[cfg(feature = "linalg")]
+            rotation: self.rotation.clone(),
+            keys: self.keys.clone(),
+            groups: self.groups.clone(),
+            tags: self.tags.clone(),
+            expiry: self.expiry.clone(),
+            free_ids: self.free_ids.clone(),
+            stats: WorkloadStats::new(),
+            hooks: Hooks::default(),
+            embedder: self.embedder.clone(),
+            progress: self.progress.clone(),
+        };
+        Ok(IndexFork { index, _original: PhantomData })
+    }
+}
@@ -0,0 +1,183 @@
+//! IDs of deleted vectors whose slots new inserts may reuse.
+//!
+//! Deletion only flags a node, so an index that keeps deleting and adding
+//! grows forever. With `IdReuse::ReuseDeleted`, `VectorIndex::add()` takes
+//! an ID from this list instead and overwrites the deleted vector and node
+//! record in place. The list is kept under either policy, so an index can
+//! switch to reuse later, and persisted as a metadata section, with the
+//! positions pushed or popped by each flush in journal records
+//! (`metadata::array_delta()`):
+//!
+//! ```text
+//! Offset  Size       Field
+//! ------  ----       -----
+//! 0       8          count: u64
+//! 8       8 * count  deleted vector IDs: u64, reused last to first
+//! ```
+//!
+//! The node flags remain the authority on what is deleted: on open, IDs that
+//! are no longer deleted are dropped, and a list that misses deletions (from
+//! files written before it was stored, or deletions that reached the disk
+//! without a flush) is rebuilt from the flags.
+
+use crate::error::{ErrorKind, Tagged};
+use crate::metadata::{self, Changes, JournaledMap, SectionTag};
+use anyhow::Result;
+use std::collections::BTreeSet;
+
+/// Metadata section tag for the persisted free list.
+pub(crate) const FREE_IDS_SECTION: &SectionTag = b"FREEIDS\0";
+
+/// Deleted vector IDs available for reuse.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FreeIds {
+    ids: Vec<u64>,
+
+    /// Positions changed since the list was last written to the file
+    changes: Changes,
+}
+
+impl FreeIds {
+    /// Free list holding `ids`, to be written on the next flush
+    pub(crate) fn rebuilt(ids: Vec<u64>) -> Self {
+        let mut changes = Changes::default();
+        changes.touch_all();
+        Self { ids, changes }
+    }
+
+    /// Number of IDs available for reuse
+    pub(crate) fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Add the ID of a newly deleted vector
+    pub(crate) fn push(&mut self, id: u64) {
+        self.changes.touch(self.ids.len() as u64);
+        self.ids.push(id);
+    }
+
+    /// The ID the next reuse takes, if any
+    pub(crate) fn next(&self) -> Option<u64> {
+        self.ids.last().copied()
+    }
+
+    /// Remove the ID returned by `next()` once its slot has been reused
+    pub(crate) fn take_next(&mut self) {
+        if self.ids.pop().is_some() {
+            self.changes.touch(self.ids.len() as u64);
+        }
+    }
+
+    /// Keep only the IDs for which `keep` returns `true`
+    pub(crate) fn retain(&mut self, mut keep: impl FnMut(u64) -> Result<bool>) -> Result<()> {
+        let before = self.ids.len();
+        let mut result = Ok(());
+        self.ids.retain(|&id| match keep(id) {
+            Ok(keep) => keep,
+            Err(error) => {
+                result = Err(error);
+                true
+            }
+        });
+        if self.ids.len() != before {
+            self.changes.touch_all();
+        }
+        result
+    }
+
+    /// Heap bytes held by the list
+    pub(crate) fn heap_bytes(&self) -> usize {
+        self.ids.capacity() * std::mem::size_of::<u64>()
+    }
+}
+
+impl JournaledMap for FreeIds {
+    const TAG: &'static SectionTag = FREE_IDS_SECTION;
+
+    fn changes(&self) -> &Changes {
+        &self.changes
+    }
+
+    fn changes_mut(&mut self) -> &mut Changes {
+        &mut self.changes
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 * (self.ids.len() + 1));
+        bytes.extend_from_slice(&(self.ids.len() as u64).to_le_bytes());
+        for id in &self.ids {
+            bytes.extend_from_slice(&id.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (chunks, rest) = bytes.as_chunks::<8>();
+        let count = chunks.first().map(|chunk| u64::from_le_bytes(*chunk));
+        if !rest.is_empty()
+            || count.and_then(|count| count.checked_add(1)) != Some(chunks.len() as u64)
+        {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                format!("Free ID section size mismatch: {} bytes", bytes.len())
+            ));
+        }
+
+        let ids = chunks[1..].iter().map(|chunk| u64::from_le_bytes(*chunk)).collect();
+        Ok(Self { ids, changes: Changes::default() })
+    }
+
+    fn delta_bytes(&self, positions: &BTreeSet<u64>) -> Vec<u8> {
+        metadata::array_delta(&self.ids, positions)
+    }
+
+    fn apply_delta(&mut self, bytes: &[u8]) -> Result<()> {
+        metadata::apply_array_delta(&mut self.ids, 0, bytes, "Free ID")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_free_ids_roundtrip() {
+        let mut free = FreeIds::default();
+        free.push(4);
+        free.push(9);
+        assert!(!free.changes().is_empty());
+        assert_eq!(free.next(), Some(9));
+
+        let restored = FreeIds::from_bytes(&free.to_bytes()).unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(restored.next(), Some(9));
+        assert!(restored.changes().is_empty());
+
+        let bytes = free.to_bytes();
+        assert!(FreeIds::from_bytes(&bytes[..bytes.len() - 8]).is_err());
+        assert!(FreeIds::from_bytes(&[]).is_err());
+    }
+
+    #[test]
+    fn test_free_ids_take_and_retain() {
+        let mut free = FreeIds::rebuilt(vec![1, 3, 5, 7]);
+        assert!(free.changes().ids().is_none());
+        let mut restored = FreeIds::from_bytes(&free.to_bytes()).unwrap();
+        free.changes_mut().rewritten(0);
+
+        free.take_next();
+        free.take_next();
+        free.push(8);
+        assert_eq!(free.next(), Some(8));
+        restored.apply_delta(&free.delta_bytes(free.changes().ids().unwrap())).unwrap();
+        assert_eq!(restored.to_bytes(), free.to_bytes());
+
+        free.changes_mut().rewritten(0);
+        free.retain(|id| Ok(id < 4)).unwrap();
+        assert!(free.changes().ids().is_none());
+        assert_eq!(free.len(), 2);
+        assert_eq!(free.next(), Some(3));
+
+        assert!(free.retain(|_| anyhow::bail!("read failed")).is_err());
+    }
+}
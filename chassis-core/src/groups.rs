@@ -0,0 +1,178 @@
+//! Group IDs attached to vectors, for grouped search.
+//!
+//! Applications that split one document into several vectors (chunks, pages,
+//! frames) give them the same group ID with `VectorIndex::add_to_group()`, and
+//! `VectorIndex::search_grouped()` collapses hits from one group.
+//!
+//! IDs are dense, so the map is a vector indexed by vector ID, persisted as a
+//! metadata section, with the groups changed by each flush in journal records
+//! (`metadata::array_delta()`):
+//!
+//! ```text
+//! Offset  Size       Field
+//! ------  ----       -----
+//! 0       8          count: u64
+//! 8       8 * count  group of each vector ID: u64 (u64::MAX = none)
+//! ```
+
+use crate::error::{ErrorKind, Tagged};
+use crate::metadata::{self, Changes, JournaledMap, SectionTag};
+use anyhow::Result;
+use std::collections::BTreeSet;
+
+/// Metadata section tag for the persisted group map.
+pub(crate) const GROUPS_SECTION: &SectionTag = b"GROUPS\0\0";
+
+/// Stored for vectors without a group.
+const NO_GROUP: u64 = u64::MAX;
+
+/// Group ID of each vector, indexed by vector ID.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct GroupMap {
+    /// `NO_GROUP` for vectors without one; may be shorter than the index
+    groups: Vec<u64>,
+
+    /// Changed since it was last written to the file
+    changes: Changes,
+}
+
+impl GroupMap {
+    /// Check that `group` can be stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidArgument` for `u64::MAX`, which marks vectors without a group.
+    pub(crate) fn validate(group: u64) -> Result<()> {
+        if group == NO_GROUP {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Group ID u64::MAX is reserved for vectors without a group"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Group of vector `id`, if any
+    pub(crate) fn get(&self, id: u64) -> Option<u64> {
+        let group = *self.groups.get(usize::try_from(id).ok()?)?;
+        (group != NO_GROUP).then_some(group)
+    }
+
+    /// Set or clear the group of vector `id`
+    pub(crate) fn set(&mut self, id: u64, group: Option<u64>) {
+        let index = id as usize;
+        if index >= self.groups.len() {
+            if group.is_none() {
+                return;
+            }
+            self.changes.touch_range(self.groups.len() as u64..id);
+            self.groups.resize(index + 1, NO_GROUP);
+        }
+        self.groups[index] = group.unwrap_or(NO_GROUP);
+        self.changes.touch(id);
+    }
+
+    /// Drop groups of IDs at or past `count` (vectors rolled back on open)
+    pub(crate) fn truncate(&mut self, count: u64) {
+        let count = usize::try_from(count).unwrap_or(usize::MAX);
+        if self.groups.len() > count {
+            self.changes.touch_range(count as u64..self.groups.len() as u64);
+            self.groups.truncate(count);
+        }
+    }
+
+    /// Heap bytes held by the map
+    pub(crate) fn heap_bytes(&self) -> usize {
+        self.groups.capacity() * std::mem::size_of::<u64>()
+    }
+}
+
+impl JournaledMap for GroupMap {
+    const TAG: &'static SectionTag = GROUPS_SECTION;
+
+    fn changes(&self) -> &Changes {
+        &self.changes
+    }
+
+    fn changes_mut(&mut self) -> &mut Changes {
+        &mut self.changes
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 * (self.groups.len() + 1));
+        bytes.extend_from_slice(&(self.groups.len() as u64).to_le_bytes());
+        for group in &self.groups {
+            bytes.extend_from_slice(&group.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (chunks, rest) = bytes.as_chunks::<8>();
+        let count = chunks.first().map(|chunk| u64::from_le_bytes(*chunk));
+        if !rest.is_empty()
+            || count.and_then(|count| count.checked_add(1)) != Some(chunks.len() as u64)
+        {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                format!("Group section size mismatch: {} bytes", bytes.len())
+            ));
+        }
+
+        let groups = chunks[1..].iter().map(|chunk| u64::from_le_bytes(*chunk)).collect();
+        Ok(Self { groups, changes: Changes::default() })
+    }
+
+    fn delta_bytes(&self, ids: &BTreeSet<u64>) -> Vec<u8> {
+        metadata::array_delta(&self.groups, ids)
+    }
+
+    fn apply_delta(&mut self, bytes: &[u8]) -> Result<()> {
+        metadata::apply_array_delta(&mut self.groups, NO_GROUP, bytes, "Group")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_map_roundtrip() {
+        let mut map = GroupMap::default();
+        map.set(3, Some(7));
+        map.set(1, Some(7));
+        map.set(10, None);
+        assert!(!map.changes().is_empty());
+
+        let restored = GroupMap::from_bytes(&map.to_bytes()).unwrap();
+        assert_eq!(restored.get(1), Some(7));
+        assert_eq!(restored.get(2), None);
+        assert_eq!(restored.get(3), Some(7));
+        assert_eq!(restored.get(10), None);
+        assert!(restored.changes().is_empty());
+
+        let bytes = map.to_bytes();
+        assert!(GroupMap::from_bytes(&bytes[..bytes.len() - 8]).is_err());
+        assert!(GroupMap::from_bytes(&[]).is_err());
+    }
+
+    #[test]
+    fn test_group_map_truncate() {
+        let mut map = GroupMap::default();
+        map.set(2, Some(1));
+        map.set(6, Some(1));
+        let mut restored = GroupMap::from_bytes(&map.to_bytes()).unwrap();
+        map.changes_mut().rewritten(0);
+
+        map.truncate(4);
+        map.set(5, Some(2));
+        assert!(!map.changes().is_empty());
+        assert_eq!(map.get(2), Some(1));
+        assert_eq!(map.get(4), None);
+        assert_eq!(map.get(6), None);
+
+        // The journal record replays the truncation and the gap it refilled
+        restored.apply_delta(&map.delta_bytes(map.changes().ids().unwrap())).unwrap();
+        assert_eq!(restored.to_bytes(), map.to_bytes());
+    }
+}
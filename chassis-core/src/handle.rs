@@ -0,0 +1,452 @@
+//! Split read/write handles for sharing one index between threads.
+//!
+//! `VectorIndex` follows the borrow rules of ADR-0003: searches take `&self`,
+//! inserts take `&mut self`. That enforces a single writer, but it also means
+//! an index cannot sit behind an `Arc` while a writer thread keeps inserting.
+//! `VectorIndex::into_handles()` splits it into one `WriteHandle` and any
+//! number of cloneable `ReadHandle`s that can be sent to other threads.
+//!
+//! # Synchronization
+//!
+//! Searches read the mapped file in place, and an insert may remap it when the
+//! file grows, so a search must never overlap a write (ADR-0003: "search
+//! operations must be scoped to a stable mapping"). The handles share the index
+//! behind a reader-writer lock: each search holds it shared for its own
+//! duration, each insert holds it exclusively for one `add()`. Many searches
+//! run in parallel.
+//!
+//! The lengths are published through atomics beside the lock, so `len()` and
+//! `durable_len()` never block behind a write.
+//!
+//! # Blocking
+//!
+//! Searches are not lock-free. A lock-free scheme would have to keep every
+//! superseded mapping alive until the searches using it drain (epoch-based
+//! retirement), since a search holds slices of the mapping it started on;
+//! the handles deliberately don't, and keep the lock instead. So:
+//!
+//! - A search waits for the write in progress, whatever it is: one `add()`
+//!   (longer when it grows the file) or a whole `WriteHandle::write()`
+//!   closure. `flush()` only holds the lock while it hands the changes to
+//!   the OS; searches run while the file syncs.
+//! - A write waits for every search in progress to finish.
+//! - Whether new searches queue behind a waiting write, or a steady stream of
+//!   searches can keep it waiting, depends on the platform's `RwLock`; the
+//!   standard library promises neither.
+//!
+//! Keep writes short (small batches) where search latency matters.
+
+use crate::{GroupedResult, KeyedResult, SearchOptions, SearchResult, VectorIndex, VectorResult};
+use anyhow::Result;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// State shared by the handles of one index
+#[derive(Debug)]
+struct Shared {
+    index: RwLock<VectorIndex>,
+
+    /// Vectors visible to searches, published after each completed insert
+    len: AtomicU64,
+
+    /// The index's durable count, which a background flush also advances
+    durable: Arc<AtomicU64>,
+}
+
+impl Shared {
+    /// Lock for reading, ignoring poisoning: inserts write in crash-consistent
+    /// order, so a panicked insert leaves the state a crash would
+    fn read(&self) -> RwLockReadGuard<'_, VectorIndex> {
+        self.index.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, VectorIndex> {
+        self.index.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The only handle that can modify a shared index
+///
+/// Not `Clone`: there is one writer per index. Obtain readers with `reader()`.
+#[derive(Debug)]
+pub struct WriteHandle {
+    shared: Arc<Shared>,
+}
+
+/// A cloneable, thread-safe handle for searching a shared index
+///
+/// Every method locks the index only for the duration of one call.
+#[derive(Debug, Clone)]
+pub struct ReadHandle {
+    shared: Arc<Shared>,
+}
+
+impl VectorIndex {
+    /// Split the index into a writer and a reader that can be shared across threads
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use chassis_core::{IndexOptions, VectorIndex};
+    ///
+    /// let index = VectorIndex::open("embeddings.chassis", 4, IndexOptions::default())?;
+    /// let (mut writer, reader) = index.into_handles();
+    ///
+    /// let searcher = std::thread::spawn(move || reader.search(&[0.0; 4], 10));
+    /// writer.add(&[1.0; 4])?;
+    /// searcher.join().unwrap()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn into_handles(self) -> (WriteHandle, ReadHandle) {
+        let len = AtomicU64::new(self.len());
+        let durable = Arc::clone(&self.durable_count);
+        let shared = Arc::new(Shared { index: RwLock::new(self), len, durable });
+        (WriteHandle { shared: Arc::clone(&shared) }, ReadHandle { shared })
+    }
+}
+
+impl WriteHandle {
+    /// Add a vector; searches started afterwards see it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for the same reasons as `VectorIndex::add()`.
+    pub fn add(&mut self, vector: &[f32]) -> Result<u64> {
+        let mut index = self.shared.write();
+        let result = index.add(vector);
+        self.shared.len.store(index.len(), Ordering::Release);
+        result
+    }
+
+    /// Embed `text` and add the vector; see `VectorIndex::add_text()`
+    ///
+    /// The text is embedded before the index is locked, so searches run
+    /// while the embedder does.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for the same reasons as `VectorIndex::add_text()`.
+    pub fn add_text(&mut self, text: &str) -> Result<u64> {
+        let embedder = self.shared.read().embedder("add_text")?;
+        self.add(&embedder.embed(text)?)
+    }
+
+    /// Run `f` with exclusive access to the index
+    ///
+    /// Searches wait until `f` returns, so keep it short: for example, add a
+    /// small batch of vectors in one go.
+    pub fn write<R>(&mut self, f: impl FnOnce(&mut VectorIndex) -> R) -> R {
+        let mut index = self.shared.write();
+        let result = f(&mut index);
+        self.shared.len.store(index.len(), Ordering::Release);
+        result
+    }
+
+    /// Flush all changes to disk
+    ///
+    /// Searches only wait while the changes are handed to the OS, not for
+    /// the `fsync`; see `VectorIndex::flush_locked()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the flush fails.
+    pub fn flush(&mut self) -> Result<()> {
+        // `&mut self` keeps other writes out while the lock is released
+        #[cfg(not(target_arch = "wasm32"))]
+        return VectorIndex::flush_locked(&self.shared.index);
+
+        #[cfg(target_arch = "wasm32")]
+        self.shared.write().flush()
+    }
+
+    /// Start a flush that completes on a background thread
+    ///
+    /// Searches only wait while the flush is handed off; see
+    /// `VectorIndex::flush_async()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the previous background flush failed or this one
+    /// cannot start.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn flush_async(&mut self) -> Result<()> {
+        self.shared.write().flush_async()
+    }
+
+    /// Create another reader for this index
+    pub fn reader(&self) -> ReadHandle {
+        ReadHandle { shared: Arc::clone(&self.shared) }
+    }
+
+    /// Get the number of vectors in the index
+    pub fn len(&self) -> u64 {
+        self.shared.len.load(Ordering::Acquire)
+    }
+
+    /// Check if the index is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Take the index back once every reader has been dropped
+    ///
+    /// # Errors
+    ///
+    /// Returns the handle unchanged if a `ReadHandle` is still alive.
+    pub fn into_inner(self) -> Result<VectorIndex, Self> {
+        Arc::try_unwrap(self.shared)
+            .map(|shared| shared.index.into_inner().unwrap_or_else(PoisonError::into_inner))
+            .map_err(|shared| Self { shared })
+    }
+}
+
+impl ReadHandle {
+    /// Search for k nearest neighbors; see `VectorIndex::search()`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions
+    pub fn search(&self, query: &[f32], k: usize) -> Result<Vec<SearchResult>> {
+        self.shared.read().search(query, k)
+    }
+
+    /// Embed `text` and search for it; see `VectorIndex::search_text()`
+    ///
+    /// The text is embedded before the index is locked, so the writer is not
+    /// held up by the embedder.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for the same reasons as `VectorIndex::search_text()`.
+    pub fn search_text(&self, text: &str, k: usize) -> Result<Vec<SearchResult>> {
+        let embedder = self.shared.read().embedder("search_text")?;
+        self.search(&embedder.embed(text)?, k)
+    }
+
+    /// Search with per-search options; see `VectorIndex::search_with_options()`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions
+    pub fn search_with_options(
+        &self,
+        query: &[f32],
+        k: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchResult>> {
+        self.shared.read().search_with_options(query, k, options)
+    }
+
+    /// Search for several queries at once; see `VectorIndex::search_batch()`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any query's dimensions don't match index dimensions
+    pub fn search_batch(&self, queries: &[&[f32]], k: usize) -> Result<Vec<Vec<SearchResult>>> {
+        self.shared.read().search_batch(queries, k)
+    }
+
+    /// Search and copy out the stored vectors; see `VectorIndex::search_with_vectors()`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions
+    pub fn search_with_vectors(&self, query: &[f32], k: usize) -> Result<Vec<VectorResult>> {
+        self.shared.read().search_with_vectors(query, k)
+    }
+
+    /// Search and return the keys of the results; see `VectorIndex::search_with_keys()`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions
+    pub fn search_with_keys(&self, query: &[f32], k: usize) -> Result<Vec<KeyedResult>> {
+        self.shared.read().search_with_keys(query, k)
+    }
+
+    /// Search for the k nearest groups; see `VectorIndex::search_grouped()`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions, or
+    /// `InvalidArgument` if `group_size` is 0
+    pub fn search_grouped(
+        &self,
+        query: &[f32],
+        k: usize,
+        group_size: usize,
+    ) -> Result<Vec<GroupedResult>> {
+        self.shared.read().search_grouped(query, k, group_size)
+    }
+
+    /// Search for k varied neighbors; see `VectorIndex::search_diverse()`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions, or
+    /// `InvalidArgument` if `lambda` is not between 0 and 1
+    pub fn search_diverse(
+        &self,
+        query: &[f32],
+        k: usize,
+        lambda: f32,
+    ) -> Result<Vec<SearchResult>> {
+        self.shared.read().search_diverse(query, k, lambda)
+    }
+
+    /// Search among vectors with matching tags; see `VectorIndex::search_with_tags()`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions, or
+    /// `InvalidArgument` if a tag exceeds `MAX_TAG`
+    pub fn search_with_tags(
+        &self,
+        query: &[f32],
+        k: usize,
+        include: &[u32],
+        exclude: &[u32],
+    ) -> Result<Vec<SearchResult>> {
+        self.shared.read().search_with_tags(query, k, include, exclude)
+    }
+
+    /// Find all vectors within `max_distance`; see `VectorIndex::search_within()`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions, or
+    /// if `max_distance` is negative or NaN
+    pub fn search_within(&self, query: &[f32], max_distance: f32) -> Result<Vec<SearchResult>> {
+        self.shared.read().search_within(query, max_distance)
+    }
+
+    /// Find the exact k nearest neighbors; see `VectorIndex::search_exact()`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions
+    pub fn search_exact(&self, query: &[f32], k: usize) -> Result<Vec<SearchResult>> {
+        self.shared.read().search_exact(query, k)
+    }
+
+    /// Run `f` with shared access to the index
+    ///
+    /// The writer waits until `f` returns. Use this for several reads that
+    /// must see the same state, such as a search followed by `len()`.
+    pub fn read<R>(&self, f: impl FnOnce(&VectorIndex) -> R) -> R {
+        f(&self.shared.read())
+    }
+
+    /// Get the number of vectors visible to searches (never blocks)
+    pub fn len(&self) -> u64 {
+        self.shared.len.load(Ordering::Acquire)
+    }
+
+    /// Check if the index is empty (never blocks)
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the number of vectors that were durable at the last completed flush (never blocks)
+    pub fn durable_len(&self) -> u64 {
+        self.shared.durable.load(Ordering::Acquire)
+    }
+
+    /// Get the dimensionality of vectors in this index
+    pub fn dimensions(&self) -> u32 {
+        self.shared.read().dimensions()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IndexOptions;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_readers_search_while_writer_inserts() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let index = VectorIndex::open(temp_file.path(), 8, IndexOptions::default()).unwrap();
+        let (mut writer, reader) = index.into_handles();
+        writer.add(&[0.0; 8]).unwrap();
+
+        std::thread::scope(|scope| {
+            for _ in 0..2 {
+                let reader = reader.clone();
+                scope.spawn(move || {
+                    let mut seen = 0;
+                    while seen < 200 {
+                        let len = reader.len();
+                        assert!(len >= seen, "length went backwards");
+                        let results = reader.search(&[0.0; 8], 5).unwrap();
+                        assert_eq!(results[0].id, 0);
+                        assert!(results.iter().all(|r| r.id < reader.len()));
+                        seen = len;
+                    }
+                });
+            }
+
+            // Enough vectors to grow and remap the file under the readers
+            for i in 1..200 {
+                writer.add(&[i as f32; 8]).unwrap();
+            }
+        });
+
+        assert_eq!(reader.len(), 200);
+        assert_eq!(reader.search(&[123.0; 8], 1).unwrap()[0].id, 123);
+        writer.flush().unwrap();
+        assert_eq!(reader.durable_len(), 200);
+    }
+
+    #[test]
+    fn test_flush_makes_reused_slots_durable() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let options =
+            IndexOptions { id_reuse: crate::IdReuse::ReuseDeleted, ..IndexOptions::default() };
+        let index = VectorIndex::open(temp_file.path(), 8, options.clone()).unwrap();
+        let (mut writer, reader) = index.into_handles();
+        for i in 0..50 {
+            writer.add(&[i as f32; 8]).unwrap();
+        }
+        writer.write(|index| index.delete(20)).unwrap();
+        writer.flush().unwrap();
+
+        assert_eq!(writer.add(&[500.0; 8]).unwrap(), 20);
+        let searcher = {
+            let reader = reader.clone();
+            std::thread::spawn(move || {
+                for _ in 0..50 {
+                    assert_eq!(reader.search(&[10.0; 8], 1).unwrap()[0].id, 10);
+                }
+            })
+        };
+        writer.flush().unwrap();
+        searcher.join().unwrap();
+        assert_eq!(reader.durable_len(), 50);
+
+        drop(reader);
+        drop(writer.into_inner().unwrap());
+        let index = VectorIndex::open(temp_file.path(), 8, options).unwrap();
+        assert_eq!(index.deleted_count(), 0);
+        assert_eq!(index.search(&[500.0; 8], 1).unwrap()[0].id, 20);
+    }
+
+    #[test]
+    fn test_into_inner_waits_for_readers() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let index = VectorIndex::open(temp_file.path(), 8, IndexOptions::default()).unwrap();
+        let (mut writer, reader) = index.into_handles();
+        writer.write(|index| {
+            for i in 0..10 {
+                index.add(&[i as f32; 8]).unwrap();
+            }
+        });
+        assert_eq!(reader.len(), 10);
+
+        let writer = writer.into_inner().unwrap_err();
+        drop(reader);
+        let index = writer.into_inner().unwrap();
+        assert_eq!(index.len(), 10);
+    }
+}
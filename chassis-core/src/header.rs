@@ -1,10 +1,45 @@
+use crate::element::ElementType;
+use std::fmt;
 use std::mem;
 
 /// Magic bytes identifying a Chassis index file
 pub const MAGIC: &[u8; 8] = b"CHASSIS\0";
 
 /// Current file format version
-pub const VERSION: u32 = 1;
+///
+/// Version 8 files store a compressed, read-only graph, version 7 files hold
+/// vectors not yet linked into the graph, version 6
+/// files lay their zones out on pages larger than 4 KiB, version 5
+/// files store two-tier graph records, version 4 files keep their
+/// graph in a separate file, version 3 files are searched with a custom
+/// distance function or hold normalized vectors, and version 2 files store
+/// vectors with a half-width or binary element type. Other files are still
+/// written as version 1 so older libraries can open them.
+pub const VERSION: u32 = 8;
+
+/// Oldest file format version this library opens
+pub const MIN_VERSION: u32 = 1;
+
+/// Format version of files storing `f32` vectors
+const F32_VERSION: u32 = 1;
+
+/// Format version of files storing half-width (or binary) vectors
+const HALF_WIDTH_VERSION: u32 = 2;
+
+/// Format version of files with a custom distance or normalized vectors
+const CUSTOM_METRIC_VERSION: u32 = 3;
+
+/// Format version of files with a separate graph file
+const GRAPH_FILE_VERSION: u32 = 4;
+
+/// Format version of files with two-tier graph records
+const TWO_TIER_VERSION: u32 = 5;
+
+/// Format version of files laid out on pages larger than 4 KiB
+const PAGE_SIZE_VERSION: u32 = 6;
+
+/// Format version of files holding vectors not yet linked into the graph
+const UNLINKED_VERSION: u32 = 7;
 
 /// Magic bytes for the extended layout metadata stored in `Header::reserved`.
 const LAYOUT_MAGIC: &[u8; 8] = b"CHLAYOUT";
@@ -15,11 +50,120 @@ const LAYOUT_VERSION: u32 = 1;
 const LAYOUT_MAGIC_RANGE: std::ops::Range<usize> = 0..8;
 const LAYOUT_VERSION_RANGE: std::ops::Range<usize> = 8..12;
 const GRAPH_OFFSET_RANGE: std::ops::Range<usize> = 16..24;
+const METADATA_OFFSET_RANGE: std::ops::Range<usize> = 24..32;
+const METADATA_LEN_RANGE: std::ops::Range<usize> = 32..40;
+const WRITER_VERSION_RANGE: std::ops::Range<usize> = 40..46;
+const BUILD_START_RANGE: std::ops::Range<usize> = 48..56;
+const BUILD_TOTAL_RANGE: std::ops::Range<usize> = 56..64;
+const ELEMENT_TYPE_RANGE: std::ops::Range<usize> = 64..68;
+const DISTANCE_NAME_RANGE: std::ops::Range<usize> = 68..132;
+const FLAGS_RANGE: std::ops::Range<usize> = 132..136;
+const PAGE_SIZE_RANGE: std::ops::Range<usize> = 136..140;
+const LINKED_COUNT_RANGE: std::ops::Range<usize> = 140..148;
+const CREATED_AT_RANGE: std::ops::Range<usize> = 148..156;
+const CREATED_BY_RANGE: std::ops::Range<usize> = 156..162;
+const USER_META_LEN_RANGE: std::ops::Range<usize> = 162..164;
+const USER_META_RANGE: std::ops::Range<usize> = 168..424;
+const COMMIT_EPOCH_RANGE: std::ops::Range<usize> = 424..432;
+const PREVIOUS_METADATA_OFFSET_RANGE: std::ops::Range<usize> = 432..440;
+const PREVIOUS_METADATA_LEN_RANGE: std::ops::Range<usize> = 440..448;
+const METADATA_EPOCH_RANGE: std::ops::Range<usize> = 448..456;
+const METADATA_FLOOR_RANGE: std::ops::Range<usize> = 456..464;
+
+/// Maximum length of the application metadata stored in the header
+pub const MAX_USER_META_LEN: usize = USER_META_RANGE.end - USER_META_RANGE.start;
+
+/// `FLAGS_RANGE` bit: vectors were normalized on insert
+const FLAG_NORMALIZED: u32 = 1;
+
+/// `FLAGS_RANGE` bit: the graph zone lives in a separate graph file
+const FLAG_GRAPH_FILE: u32 = 2;
+
+/// `FLAGS_RANGE` bit: the graph stores two-tier node records
+const FLAG_TWO_TIER_GRAPH: u32 = 4;
+
+/// `FLAGS_RANGE` bit: nodes from `LINKED_COUNT_RANGE` on have no neighbors yet
+const FLAG_UNLINKED_NODES: u32 = 8;
+
+/// `FLAGS_RANGE` bit: a writer has the file open and has not closed it after
+/// its last commit
+const FLAG_WRITER_OPEN: u32 = 16;
+
+/// `FLAGS_RANGE` bit: the graph stores compressed node records and is read-only
+const FLAG_COMPRESSED_GRAPH: u32 = 32;
 
 /// Maximum supported vector dimensions.
 /// This is a sanity check to catch corrupted headers.
 /// Typical embeddings are 384-1536 dimensions.
-const MAX_DIMENSIONS: u32 = 4096;
+pub(crate) const MAX_DIMENSIONS: u32 = 4096;
+
+/// Largest page size an index can be laid out with (one x86-64 huge page)
+pub const MAX_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+/// Returns `true` for page sizes an index can use: powers of two from the
+/// header size (4 KiB) to `MAX_PAGE_SIZE`
+pub(crate) fn is_valid_page_size(page_size: usize) -> bool {
+    page_size.is_power_of_two() && (HEADER_SIZE..=MAX_PAGE_SIZE).contains(&page_size)
+}
+
+/// Version of the Chassis library, as recorded in the file header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LibraryVersion {
+    /// Major version
+    pub major: u16,
+
+    /// Minor version
+    pub minor: u16,
+
+    /// Patch version
+    pub patch: u16,
+}
+
+impl LibraryVersion {
+    /// Version of this library
+    pub const CURRENT: Self = Self {
+        major: parse_version_part(env!("CARGO_PKG_VERSION_MAJOR")),
+        minor: parse_version_part(env!("CARGO_PKG_VERSION_MINOR")),
+        patch: parse_version_part(env!("CARGO_PKG_VERSION_PATCH")),
+    };
+
+    /// Returns `true` if `self` has a higher `major.minor` than `other`
+    #[must_use]
+    pub fn is_newer_minor_than(&self, other: &Self) -> bool {
+        (self.major, self.minor) > (other.major, other.minor)
+    }
+
+    fn to_le_bytes(self) -> [u8; 6] {
+        let mut bytes = [0; 6];
+        bytes[0..2].copy_from_slice(&self.major.to_le_bytes());
+        bytes[2..4].copy_from_slice(&self.minor.to_le_bytes());
+        bytes[4..6].copy_from_slice(&self.patch.to_le_bytes());
+        bytes
+    }
+
+    fn from_le_bytes(bytes: &[u8]) -> Self {
+        let part = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+        Self { major: part(0), minor: part(2), patch: part(4) }
+    }
+}
+
+impl fmt::Display for LibraryVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Parses one numeric component of the crate version at compile time.
+const fn parse_version_part(part: &str) -> u16 {
+    let bytes = part.as_bytes();
+    let mut value: u16 = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        value = value * 10 + (bytes[i] - b'0') as u16;
+        i += 1;
+    }
+    value
+}
 
 /// Header structure for Chassis index files.
 /// The header is always 4096 bytes (one page) to ensure proper alignment.
@@ -47,16 +191,30 @@ pub const HEADER_SIZE: usize = mem::size_of::<Header>();
 impl Header {
     /// Creates a new header with the specified dimensions
     pub fn new(dimensions: u32) -> Self {
-        Self { magic: *MAGIC, version: VERSION, dimensions, count: 0, reserved: [0; 4072] }
+        Self { magic: *MAGIC, version: F32_VERSION, dimensions, count: 0, reserved: [0; 4072] }
+    }
+
+    /// Returns `true` if this library can read a file in this header's format
+    ///
+    /// The compatibility policy: a file records the lowest format version
+    /// able to read it, and a library reads every version from `MIN_VERSION`
+    /// to its own `VERSION`. Files from older releases may still need an
+    /// in-place upgrade on open (see `migrate_file()`); files in a newer
+    /// format are refused with `IncompatibleVersion` rather than misread.
+    #[must_use]
+    pub fn is_compatible(&self) -> bool {
+        self.magic == *MAGIC && (MIN_VERSION..=VERSION).contains(&self.version)
     }
 
     /// Validates the header for correctness and compatibility
     pub fn is_valid(&self) -> bool {
-        self.magic == *MAGIC
-            && self.version > 0
-            && self.version <= VERSION
+        self.is_compatible()
             && self.dimensions > 0
             && self.dimensions <= MAX_DIMENSIONS
+            && self.element_type().is_some()
+            && self.distance_name_bytes().is_none_or(|name| std::str::from_utf8(name).is_ok())
+            && is_valid_page_size(self.page_size())
+            && self.user_meta_len() <= MAX_USER_META_LEN
     }
 
     /// Returns the header as a byte slice for writing to disk
@@ -73,11 +231,10 @@ impl Header {
         self.reserved = other.reserved;
     }
 
-    /// Returns the persisted graph zone offset, if this file uses the extended layout.
-    #[must_use]
-    pub fn graph_offset(&self) -> Option<u64> {
+    /// Returns `true` if the reserved bytes carry the extended layout metadata.
+    fn has_layout(&self) -> bool {
         if self.reserved[LAYOUT_MAGIC_RANGE] != *LAYOUT_MAGIC {
-            return None;
+            return false;
         }
 
         let layout_version = u32::from_le_bytes(
@@ -85,25 +242,473 @@ impl Header {
                 .try_into()
                 .expect("layout version range must be four bytes"),
         );
-        if layout_version != LAYOUT_VERSION {
+        layout_version == LAYOUT_VERSION
+    }
+
+    /// Reads a little-endian `u64` from the extended layout metadata.
+    fn layout_u64(&self, range: std::ops::Range<usize>) -> u64 {
+        u64::from_le_bytes(
+            self.reserved[range].try_into().expect("layout field must be eight bytes"),
+        )
+    }
+
+    /// Stamps the extended layout magic and version into the reserved bytes.
+    fn mark_layout(&mut self) {
+        self.reserved[LAYOUT_MAGIC_RANGE].copy_from_slice(LAYOUT_MAGIC);
+        self.reserved[LAYOUT_VERSION_RANGE].copy_from_slice(&LAYOUT_VERSION.to_le_bytes());
+    }
+
+    /// Returns the persisted graph zone offset, if this file uses the extended layout.
+    #[must_use]
+    pub fn graph_offset(&self) -> Option<u64> {
+        if !self.has_layout() {
             return None;
         }
 
-        let offset = u64::from_le_bytes(
-            self.reserved[GRAPH_OFFSET_RANGE]
-                .try_into()
-                .expect("graph offset range must be eight bytes"),
-        );
-
+        let offset = self.layout_u64(GRAPH_OFFSET_RANGE);
         (offset != 0).then_some(offset)
     }
 
     /// Persists the graph zone offset in the reserved header metadata.
     pub fn set_graph_offset(&mut self, offset: u64) {
-        self.reserved[LAYOUT_MAGIC_RANGE].copy_from_slice(LAYOUT_MAGIC);
-        self.reserved[LAYOUT_VERSION_RANGE].copy_from_slice(&LAYOUT_VERSION.to_le_bytes());
+        self.mark_layout();
         self.reserved[GRAPH_OFFSET_RANGE].copy_from_slice(&offset.to_le_bytes());
     }
+
+    /// Returns the persisted metadata zone as `(offset, len)`, if one has been written.
+    #[must_use]
+    pub fn metadata_zone(&self) -> Option<(u64, u64)> {
+        if !self.has_layout() {
+            return None;
+        }
+
+        let offset = self.layout_u64(METADATA_OFFSET_RANGE);
+        let len = self.layout_u64(METADATA_LEN_RANGE);
+        (offset != 0).then_some((offset, len))
+    }
+
+    /// Persists the metadata zone location in the reserved header metadata.
+    pub fn set_metadata_zone(&mut self, offset: u64, len: u64) {
+        self.mark_layout();
+        self.reserved[METADATA_OFFSET_RANGE].copy_from_slice(&offset.to_le_bytes());
+        self.reserved[METADATA_LEN_RANGE].copy_from_slice(&len.to_le_bytes());
+    }
+
+    /// Returns the metadata zone to fall back to if the current one does not
+    /// validate, as `(offset, len)`
+    #[must_use]
+    pub fn previous_metadata_zone(&self) -> Option<(u64, u64)> {
+        if !self.has_layout() {
+            return None;
+        }
+
+        let offset = self.layout_u64(PREVIOUS_METADATA_OFFSET_RANGE);
+        let len = self.layout_u64(PREVIOUS_METADATA_LEN_RANGE);
+        (offset != 0).then_some((offset, len))
+    }
+
+    /// Records the metadata zone to fall back to, or clears it with `None`
+    pub fn set_previous_metadata_zone(&mut self, zone: Option<(u64, u64)>) {
+        let (offset, len) = zone.unwrap_or((0, 0));
+        self.mark_layout();
+        self.reserved[PREVIOUS_METADATA_OFFSET_RANGE].copy_from_slice(&offset.to_le_bytes());
+        self.reserved[PREVIOUS_METADATA_LEN_RANGE].copy_from_slice(&len.to_le_bytes());
+    }
+
+    /// Returns the commit epoch that makes the current metadata zone durable
+    /// (0 for zones written before this was recorded)
+    #[must_use]
+    pub fn metadata_epoch(&self) -> u64 {
+        if !self.has_layout() {
+            return 0;
+        }
+
+        self.layout_u64(METADATA_EPOCH_RANGE)
+    }
+
+    /// Records the commit epoch that makes the current metadata zone durable
+    pub fn set_metadata_epoch(&mut self, epoch: u64) {
+        self.mark_layout();
+        self.reserved[METADATA_EPOCH_RANGE].copy_from_slice(&epoch.to_le_bytes());
+    }
+
+    /// Returns the start of the area the metadata zones are written in, if recorded
+    #[must_use]
+    pub fn metadata_floor(&self) -> Option<u64> {
+        if !self.has_layout() {
+            return None;
+        }
+
+        let floor = self.layout_u64(METADATA_FLOOR_RANGE);
+        (floor != 0).then_some(floor)
+    }
+
+    /// Records the start of the area the metadata zones are written in
+    pub fn set_metadata_floor(&mut self, floor: u64) {
+        self.mark_layout();
+        self.reserved[METADATA_FLOOR_RANGE].copy_from_slice(&floor.to_le_bytes());
+    }
+
+    /// Returns the version of the library that last committed this file, if recorded.
+    ///
+    /// Files last written before versions were recorded return `None`.
+    #[must_use]
+    pub fn writer_version(&self) -> Option<LibraryVersion> {
+        if !self.has_layout() {
+            return None;
+        }
+
+        let version = LibraryVersion::from_le_bytes(&self.reserved[WRITER_VERSION_RANGE]);
+        (version != LibraryVersion { major: 0, minor: 0, patch: 0 }).then_some(version)
+    }
+
+    /// Records the version of the library writing this file.
+    pub fn set_writer_version(&mut self, version: LibraryVersion) {
+        self.mark_layout();
+        self.reserved[WRITER_VERSION_RANGE].copy_from_slice(&version.to_le_bytes());
+    }
+
+    /// Returns the last bulk build as `(start count, total vectors)`, if one was recorded.
+    #[must_use]
+    pub fn build_checkpoint(&self) -> Option<(u64, u64)> {
+        if !self.has_layout() {
+            return None;
+        }
+
+        let total = self.layout_u64(BUILD_TOTAL_RANGE);
+        (total != 0).then(|| (self.layout_u64(BUILD_START_RANGE), total))
+    }
+
+    /// Records a bulk build of `total` vectors starting at vector count `start`.
+    pub fn set_build_checkpoint(&mut self, start: u64, total: u64) {
+        self.mark_layout();
+        self.reserved[BUILD_START_RANGE].copy_from_slice(&start.to_le_bytes());
+        self.reserved[BUILD_TOTAL_RANGE].copy_from_slice(&total.to_le_bytes());
+    }
+
+    /// Returns the encoding of stored vector components
+    ///
+    /// Returns `None` if the file uses an element type this library does not know.
+    #[must_use]
+    pub fn element_type(&self) -> Option<ElementType> {
+        if !self.has_layout() {
+            return Some(ElementType::F32);
+        }
+
+        ElementType::from_code(u32::from_le_bytes(
+            self.reserved[ELEMENT_TYPE_RANGE].try_into().expect("element type must be four bytes"),
+        ))
+    }
+
+    /// Records the encoding of stored vector components.
+    ///
+    /// Types other than `F32` raise the format version so that libraries which
+    /// only read `f32` vectors reject the file instead of misreading it.
+    pub fn set_element_type(&mut self, element_type: ElementType) {
+        self.mark_layout();
+        self.reserved[ELEMENT_TYPE_RANGE].copy_from_slice(&element_type.code().to_le_bytes());
+        self.update_version();
+    }
+
+    /// Returns the name of the custom distance function the index was built with
+    ///
+    /// Returns `None` for Euclidean distance, which records no name.
+    #[must_use]
+    pub fn distance_name(&self) -> Option<&str> {
+        // Non-UTF-8 names are rejected by `is_valid()`
+        self.distance_name_bytes().and_then(|name| std::str::from_utf8(name).ok())
+    }
+
+    /// Records the name of the custom distance function, or `None` for Euclidean.
+    ///
+    /// A name raises the format version so that libraries which only compute
+    /// Euclidean distance reject the file instead of searching it wrongly.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` is longer than `MAX_DISTANCE_NAME_LEN` bytes.
+    pub fn set_distance_name(&mut self, name: Option<&str>) {
+        let name = name.unwrap_or_default().as_bytes();
+        assert!(name.len() <= DISTANCE_NAME_RANGE.len(), "distance name too long");
+
+        self.mark_layout();
+        let field = &mut self.reserved[DISTANCE_NAME_RANGE];
+        field.fill(0);
+        field[..name.len()].copy_from_slice(name);
+        self.update_version();
+    }
+
+    /// Returns `true` if vectors are normalized to unit length on insert
+    #[must_use]
+    pub fn normalized(&self) -> bool {
+        self.has_layout() && self.flags() & FLAG_NORMALIZED != 0
+    }
+
+    /// Records whether vectors are normalized to unit length on insert.
+    ///
+    /// Normalization raises the format version like a custom distance: older
+    /// libraries would add vectors without normalizing them.
+    pub fn set_normalized(&mut self, normalized: bool) {
+        self.mark_layout();
+        let flags = if normalized {
+            self.flags() | FLAG_NORMALIZED
+        } else {
+            self.flags() & !FLAG_NORMALIZED
+        };
+        self.reserved[FLAGS_RANGE].copy_from_slice(&flags.to_le_bytes());
+        self.update_version();
+    }
+
+    /// Returns `true` if the graph zone is stored in a separate graph file
+    #[must_use]
+    pub fn graph_file(&self) -> bool {
+        self.has_layout() && self.flags() & FLAG_GRAPH_FILE != 0
+    }
+
+    /// Records that the graph zone is stored in a separate graph file.
+    ///
+    /// Raises the format version to 4: an older library would find no graph
+    /// zone in the index file and start a new, empty graph.
+    pub fn set_graph_file(&mut self) {
+        self.mark_layout();
+        let flags = self.flags() | FLAG_GRAPH_FILE;
+        self.reserved[FLAGS_RANGE].copy_from_slice(&flags.to_le_bytes());
+        self.update_version();
+    }
+
+    /// Returns `true` if the graph stores two-tier node records
+    #[must_use]
+    pub fn two_tier_graph(&self) -> bool {
+        self.has_layout() && self.flags() & FLAG_TWO_TIER_GRAPH != 0
+    }
+
+    /// Records that the graph stores two-tier node records.
+    ///
+    /// Raises the format version to 5: an older library would read
+    /// the compact records as fixed-width ones.
+    pub fn set_two_tier_graph(&mut self) {
+        self.mark_layout();
+        let flags = self.flags() | FLAG_TWO_TIER_GRAPH;
+        self.reserved[FLAGS_RANGE].copy_from_slice(&flags.to_le_bytes());
+        self.update_version();
+    }
+
+    /// Returns `true` if the graph stores compressed node records
+    #[must_use]
+    pub fn compressed_graph(&self) -> bool {
+        self.has_layout() && self.flags() & FLAG_COMPRESSED_GRAPH != 0
+    }
+
+    /// Records that the graph stores compressed node records, in the index
+    /// file itself.
+    ///
+    /// Clears the graph file and two-tier flags, which describe the layout
+    /// the compressed graph replaces, and raises the format version to 8: an
+    /// older library would read the compressed records as fixed-width ones.
+    pub fn set_compressed_graph(&mut self) {
+        self.mark_layout();
+        let flags =
+            (self.flags() | FLAG_COMPRESSED_GRAPH) & !(FLAG_GRAPH_FILE | FLAG_TWO_TIER_GRAPH);
+        self.reserved[FLAGS_RANGE].copy_from_slice(&flags.to_le_bytes());
+        self.update_version();
+    }
+
+    /// Returns the page size the file's zones are aligned to
+    ///
+    /// The vector zone starts one page into the file. Files that record no
+    /// page size use 4 KiB, the header size.
+    #[must_use]
+    pub fn page_size(&self) -> usize {
+        if !self.has_layout() {
+            return HEADER_SIZE;
+        }
+
+        let page_size = u32::from_le_bytes(
+            self.reserved[PAGE_SIZE_RANGE].try_into().expect("page size must be four bytes"),
+        );
+        if page_size == 0 { HEADER_SIZE } else { page_size as usize }
+    }
+
+    /// Records the page size the file's zones are aligned to.
+    ///
+    /// Sizes above 4 KiB move the vector zone, so they raise the format
+    /// version to 6: an older library would read vectors from the
+    /// padding after the header.
+    pub fn set_page_size(&mut self, page_size: u32) {
+        self.mark_layout();
+        let page_size = if page_size as usize == HEADER_SIZE { 0 } else { page_size };
+        self.reserved[PAGE_SIZE_RANGE].copy_from_slice(&page_size.to_le_bytes());
+        self.update_version();
+    }
+
+    /// Returns the number of nodes linked into the graph, if the nodes after
+    /// them were inserted without neighbors
+    #[must_use]
+    pub fn linked_count(&self) -> Option<u64> {
+        (self.has_layout() && self.flags() & FLAG_UNLINKED_NODES != 0)
+            .then(|| self.layout_u64(LINKED_COUNT_RANGE))
+    }
+
+    /// Records that only the first `linked` nodes are linked into the graph,
+    /// or with `None` that every node is.
+    ///
+    /// Unlinked nodes raise the format version to 7: an older library would
+    /// search them through a graph that cannot reach them.
+    pub fn set_linked_count(&mut self, linked: Option<u64>) {
+        self.mark_layout();
+        let flags = match linked {
+            Some(_) => self.flags() | FLAG_UNLINKED_NODES,
+            None => self.flags() & !FLAG_UNLINKED_NODES,
+        };
+        self.reserved[FLAGS_RANGE].copy_from_slice(&flags.to_le_bytes());
+        self.reserved[LINKED_COUNT_RANGE].copy_from_slice(&linked.unwrap_or(0).to_le_bytes());
+        self.update_version();
+    }
+
+    /// Returns when the file was created, in seconds since the Unix epoch, if recorded
+    #[must_use]
+    pub fn created_at(&self) -> Option<u64> {
+        if !self.has_layout() {
+            return None;
+        }
+
+        let created_at = self.layout_u64(CREATED_AT_RANGE);
+        (created_at != 0).then_some(created_at)
+    }
+
+    /// Returns the version of the library that created this file, if recorded
+    #[must_use]
+    pub fn created_by(&self) -> Option<LibraryVersion> {
+        if !self.has_layout() {
+            return None;
+        }
+
+        let version = LibraryVersion::from_le_bytes(&self.reserved[CREATED_BY_RANGE]);
+        (version != LibraryVersion { major: 0, minor: 0, patch: 0 }).then_some(version)
+    }
+
+    /// Records when and by which library version the file was created
+    ///
+    /// A `created_at` of 0 records no time, for platforms without a clock.
+    pub fn set_created(&mut self, created_at: u64, version: LibraryVersion) {
+        self.mark_layout();
+        self.reserved[CREATED_AT_RANGE].copy_from_slice(&created_at.to_le_bytes());
+        self.reserved[CREATED_BY_RANGE].copy_from_slice(&version.to_le_bytes());
+    }
+
+    /// Returns the application metadata, empty if none was set
+    #[must_use]
+    pub fn user_meta(&self) -> &[u8] {
+        if !self.has_layout() {
+            return &[];
+        }
+
+        // Oversized lengths are rejected by `is_valid()`
+        let len = self.user_meta_len().min(MAX_USER_META_LEN);
+        &self.reserved[USER_META_RANGE][..len]
+    }
+
+    /// Replaces the application metadata
+    ///
+    /// The bytes are opaque to Chassis and do not affect the format version,
+    /// so older libraries open the file and keep them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `meta` is longer than `MAX_USER_META_LEN` bytes.
+    pub fn set_user_meta(&mut self, meta: &[u8]) {
+        assert!(meta.len() <= MAX_USER_META_LEN, "metadata too long");
+
+        self.mark_layout();
+        let len = u16::try_from(meta.len()).expect("metadata length must fit in u16");
+        self.reserved[USER_META_LEN_RANGE].copy_from_slice(&len.to_le_bytes());
+        let field = &mut self.reserved[USER_META_RANGE];
+        field.fill(0);
+        field[..meta.len()].copy_from_slice(meta);
+    }
+
+    /// Returns the number of commits made to this file, 0 if none were recorded
+    #[must_use]
+    pub fn commit_epoch(&self) -> u64 {
+        if !self.has_layout() {
+            return 0;
+        }
+
+        self.layout_u64(COMMIT_EPOCH_RANGE)
+    }
+
+    /// Records the number of commits made to this file.
+    pub fn set_commit_epoch(&mut self, epoch: u64) {
+        self.mark_layout();
+        self.reserved[COMMIT_EPOCH_RANGE].copy_from_slice(&epoch.to_le_bytes());
+    }
+
+    /// Returns `true` if a writer opened the file and did not close it
+    /// cleanly after its last commit (it is still open, or it crashed)
+    #[must_use]
+    pub fn writer_open(&self) -> bool {
+        self.has_layout() && self.flags() & FLAG_WRITER_OPEN != 0
+    }
+
+    /// Records that a writer has the file open, or with `false` that it
+    /// closed it with every write committed.
+    ///
+    /// Older libraries ignore the bit, so it does not affect the format version.
+    pub fn set_writer_open(&mut self, open: bool) {
+        self.mark_layout();
+        let flags =
+            if open { self.flags() | FLAG_WRITER_OPEN } else { self.flags() & !FLAG_WRITER_OPEN };
+        self.reserved[FLAGS_RANGE].copy_from_slice(&flags.to_le_bytes());
+    }
+
+    fn user_meta_len(&self) -> usize {
+        if !self.has_layout() {
+            return 0;
+        }
+
+        let len = u16::from_le_bytes(
+            self.reserved[USER_META_LEN_RANGE]
+                .try_into()
+                .expect("metadata length must be two bytes"),
+        );
+        usize::from(len)
+    }
+
+    fn flags(&self) -> u32 {
+        u32::from_le_bytes(self.reserved[FLAGS_RANGE].try_into().expect("flags must be four bytes"))
+    }
+
+    /// Raw distance name, without its NUL padding
+    fn distance_name_bytes(&self) -> Option<&[u8]> {
+        if !self.has_layout() {
+            return None;
+        }
+
+        let field = &self.reserved[DISTANCE_NAME_RANGE];
+        let len = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+        (len != 0).then(|| &field[..len])
+    }
+
+    /// Sets the lowest format version able to read the recorded layout
+    fn update_version(&mut self) {
+        self.version = if self.compressed_graph() {
+            VERSION
+        } else if self.linked_count().is_some() {
+            UNLINKED_VERSION
+        } else if self.page_size() != HEADER_SIZE {
+            PAGE_SIZE_VERSION
+        } else if self.two_tier_graph() {
+            TWO_TIER_VERSION
+        } else if self.graph_file() {
+            GRAPH_FILE_VERSION
+        } else if self.distance_name_bytes().is_some() || self.normalized() {
+            CUSTOM_METRIC_VERSION
+        } else if self.element_type() != Some(ElementType::F32) {
+            HALF_WIDTH_VERSION
+        } else {
+            F32_VERSION
+        };
+    }
 }
 
 #[cfg(test)]
@@ -140,6 +745,22 @@ mod tests {
         assert!(!header.is_valid());
     }
 
+    #[test]
+    fn test_compatible_versions() {
+        let mut header = Header::new(768);
+        assert!(header.is_compatible());
+
+        header.version = VERSION;
+        assert!(header.is_compatible());
+
+        header.version = VERSION + 1;
+        assert!(!header.is_compatible());
+        assert!(!header.is_valid());
+
+        header.version = MIN_VERSION - 1;
+        assert!(!header.is_compatible());
+    }
+
     #[test]
     fn test_invalid_magic() {
         let mut header = Header::new(768);
@@ -159,4 +780,232 @@ mod tests {
         let restored = unsafe { std::ptr::read_unaligned(bytes.as_ptr().cast::<Header>()) };
         assert_eq!(restored.graph_offset(), Some(8192));
     }
+
+    #[test]
+    fn test_metadata_zone_roundtrip() {
+        let mut header = Header::new(768);
+        assert_eq!(header.metadata_zone(), None);
+
+        header.set_graph_offset(8192);
+        assert_eq!(header.metadata_zone(), None);
+
+        header.set_metadata_zone(1 << 20, 96);
+        assert_eq!(header.metadata_zone(), Some((1 << 20, 96)));
+        assert_eq!(header.graph_offset(), Some(8192));
+
+        assert_eq!(header.previous_metadata_zone(), None);
+        header.set_previous_metadata_zone(Some((2 << 20, 64)));
+        header.set_metadata_epoch(7);
+        header.set_metadata_floor(1 << 20);
+        assert_eq!(header.previous_metadata_zone(), Some((2 << 20, 64)));
+        assert_eq!(header.metadata_epoch(), 7);
+        assert_eq!(header.metadata_floor(), Some(1 << 20));
+        header.set_previous_metadata_zone(None);
+        assert_eq!(header.previous_metadata_zone(), None);
+        assert_eq!(header.metadata_zone(), Some((1 << 20, 96)));
+    }
+
+    #[test]
+    fn test_build_checkpoint_roundtrip() {
+        let mut header = Header::new(768);
+        assert_eq!(header.build_checkpoint(), None);
+
+        header.set_graph_offset(8192);
+        header.set_build_checkpoint(40, 1_000_000);
+        assert_eq!(header.build_checkpoint(), Some((40, 1_000_000)));
+        assert_eq!(header.graph_offset(), Some(8192));
+    }
+
+    #[test]
+    fn test_linked_count_raises_version() {
+        let mut header = Header::new(768);
+        assert_eq!(header.linked_count(), None);
+
+        header.set_normalized(true);
+        header.set_linked_count(Some(0));
+        assert_eq!(header.linked_count(), Some(0));
+        assert_eq!(header.version, UNLINKED_VERSION);
+
+        header.set_linked_count(None);
+        assert_eq!(header.linked_count(), None);
+        assert!(header.normalized());
+        assert_eq!(header.version, CUSTOM_METRIC_VERSION);
+    }
+
+    #[test]
+    fn test_creation_and_user_meta_roundtrip() {
+        let mut header = Header::new(768);
+        assert_eq!(header.created_at(), None);
+        assert_eq!(header.created_by(), None);
+        assert_eq!(header.user_meta(), b"");
+
+        header.set_created(1_700_000_000, LibraryVersion::CURRENT);
+        header.set_user_meta(b"model=all-MiniLM-L6-v2");
+        assert_eq!(header.created_at(), Some(1_700_000_000));
+        assert_eq!(header.created_by(), Some(LibraryVersion::CURRENT));
+        assert_eq!(header.user_meta(), b"model=all-MiniLM-L6-v2");
+        assert_eq!(header.version, F32_VERSION);
+        assert!(header.is_valid());
+
+        header.set_user_meta(&[7; MAX_USER_META_LEN]);
+        assert_eq!(header.user_meta(), &[7; MAX_USER_META_LEN]);
+        header.set_user_meta(b"");
+        assert_eq!(header.user_meta(), b"");
+
+        header.reserved[USER_META_LEN_RANGE].copy_from_slice(&300u16.to_le_bytes());
+        assert!(!header.is_valid());
+    }
+
+    #[test]
+    fn test_commit_epoch_and_writer_open_flag() {
+        let mut header = Header::new(768);
+        assert_eq!(header.commit_epoch(), 0);
+        assert!(!header.writer_open());
+
+        header.set_graph_file();
+        header.set_writer_open(true);
+        header.set_commit_epoch(42);
+        assert!(header.writer_open());
+        assert!(header.graph_file());
+        assert_eq!(header.commit_epoch(), 42);
+        assert_eq!(header.version, GRAPH_FILE_VERSION);
+
+        header.set_writer_open(false);
+        assert!(!header.writer_open());
+        assert!(header.graph_file());
+        assert!(header.is_valid());
+    }
+
+    #[test]
+    fn test_element_type_roundtrip() {
+        let mut header = Header::new(768);
+        assert_eq!(header.element_type(), Some(ElementType::F32));
+        assert_eq!(header.version, 1);
+
+        header.set_element_type(ElementType::F16);
+        assert_eq!(header.element_type(), Some(ElementType::F16));
+        assert_eq!(header.version, HALF_WIDTH_VERSION);
+        assert!(header.is_valid());
+
+        header.reserved[ELEMENT_TYPE_RANGE].copy_from_slice(&7u32.to_le_bytes());
+        assert_eq!(header.element_type(), None);
+        assert!(!header.is_valid());
+    }
+
+    #[test]
+    fn test_distance_name_roundtrip() {
+        let mut header = Header::new(768);
+        header.set_element_type(ElementType::F16);
+        assert_eq!(header.distance_name(), None);
+
+        header.set_distance_name(Some("weighted-l2"));
+        assert_eq!(header.distance_name(), Some("weighted-l2"));
+        assert_eq!(header.element_type(), Some(ElementType::F16));
+        assert_eq!(header.version, CUSTOM_METRIC_VERSION);
+        assert!(header.is_valid());
+
+        header.set_distance_name(None);
+        assert_eq!(header.distance_name(), None);
+        assert_eq!(header.version, HALF_WIDTH_VERSION);
+
+        header.reserved[DISTANCE_NAME_RANGE.start] = 0xff;
+        assert_eq!(header.distance_name(), None);
+        assert!(!header.is_valid());
+    }
+
+    #[test]
+    fn test_normalized_roundtrip() {
+        let mut header = Header::new(768);
+        assert!(!header.normalized());
+
+        header.set_normalized(true);
+        assert!(header.normalized());
+        assert_eq!(header.element_type(), Some(ElementType::F32));
+        assert_eq!(header.version, CUSTOM_METRIC_VERSION);
+        assert!(header.is_valid());
+
+        header.set_normalized(false);
+        assert!(!header.normalized());
+        assert_eq!(header.version, F32_VERSION);
+    }
+
+    #[test]
+    fn test_graph_file_flag() {
+        let mut header = Header::new(768);
+        assert!(!header.graph_file());
+
+        header.set_normalized(true);
+        header.set_graph_file();
+        assert!(header.graph_file());
+        assert!(header.normalized());
+        assert_eq!(header.version, GRAPH_FILE_VERSION);
+        assert!(header.is_valid());
+
+        header.set_two_tier_graph();
+        assert!(header.two_tier_graph());
+        assert!(header.graph_file());
+        assert_eq!(header.version, TWO_TIER_VERSION);
+        assert!(header.is_valid());
+
+        header.set_compressed_graph();
+        assert!(header.compressed_graph());
+        assert!(!header.two_tier_graph());
+        assert!(!header.graph_file());
+        assert!(header.normalized());
+        assert_eq!(header.version, VERSION);
+        assert!(header.is_valid());
+    }
+
+    #[test]
+    fn test_page_size_roundtrip() {
+        let mut header = Header::new(768);
+        assert_eq!(header.page_size(), 4096);
+
+        header.set_two_tier_graph();
+        header.set_page_size(16384);
+        assert_eq!(header.page_size(), 16384);
+        assert_eq!(header.version, PAGE_SIZE_VERSION);
+        assert!(header.is_valid());
+
+        header.set_page_size(4096);
+        assert_eq!(header.page_size(), 4096);
+        assert_eq!(header.version, TWO_TIER_VERSION);
+
+        header.set_page_size(12288);
+        assert!(!header.is_valid());
+        header.set_page_size(2 * MAX_PAGE_SIZE as u32);
+        assert!(!header.is_valid());
+    }
+
+    #[test]
+    fn test_writer_version_roundtrip() {
+        let mut header = Header::new(768);
+        header.set_graph_offset(8192);
+        assert_eq!(header.writer_version(), None);
+
+        let version = LibraryVersion { major: 1, minor: 300, patch: 2 };
+        header.set_writer_version(version);
+        assert_eq!(header.writer_version(), Some(version));
+        assert_eq!(header.graph_offset(), Some(8192));
+        assert_eq!(version.to_string(), "1.300.2");
+
+        assert_eq!(
+            LibraryVersion::CURRENT.to_string(),
+            env!("CARGO_PKG_VERSION").split(['-', '+']).next().unwrap()
+        );
+        assert!(
+            LibraryVersion { major: 0, minor: 7, patch: 0 }.is_newer_minor_than(&LibraryVersion {
+                major: 0,
+                minor: 6,
+                patch: 9
+            })
+        );
+        assert!(
+            !LibraryVersion { major: 0, minor: 6, patch: 9 }.is_newer_minor_than(&LibraryVersion {
+                major: 0,
+                minor: 6,
+                patch: 3
+            })
+        );
+    }
 }
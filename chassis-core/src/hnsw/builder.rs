@@ -1,16 +1,107 @@
-use crate::Storage;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::error::{ErrorKind, Tagged};
 use crate::hnsw::graph::HnswGraph;
 use crate::hnsw::{HnswParams, layer_from_uniform};
+use crate::{CancelToken, Storage};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{IndexOptions, VectorIndex};
+#[cfg(not(target_arch = "wasm32"))]
+use anyhow::Context;
 use anyhow::Result;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::{Read, Seek, SeekFrom};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+/// Vectors read from the source per chunk in `build_from_reader`
+const DEFAULT_CHUNK_SIZE: usize = 1024;
+
+/// Vectors inserted between flushes in `build_from_reader`
+const DEFAULT_FLUSH_INTERVAL: u64 = 65_536;
+
+/// Progress of the last bulk build recorded in an index file
+///
+/// Returned by `VectorIndex::build_progress()`. Only vectors made durable by a
+/// flush are counted, so after a crash this is where the build resumes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuildProgress {
+    /// Byte offset in the source of the next vector to read
+    pub input_offset: u64,
+
+    /// Vectors of the build that are durable in the index
+    pub vectors_done: u64,
+
+    /// Vectors the build was asked to ingest
+    pub total: u64,
+}
+
+impl BuildProgress {
+    /// Returns `true` if every vector of the build is durable
+    pub fn is_complete(&self) -> bool {
+        self.vectors_done >= self.total
+    }
+}
 
 /// Builder for constructing HNSW index
 pub struct HnswBuilder {
     params: HnswParams,
+
+    /// Vectors buffered per read from the source
+    chunk_size: usize,
+
+    /// Vectors inserted between flushes
+    flush_interval: u64,
+
+    /// Scale the construction beam with the index size
+    adaptive_ef_construction: bool,
+
+    /// Stops `build_from_reader` between inserts once cancelled
+    cancel: Option<CancelToken>,
 }
 
 impl HnswBuilder {
     pub fn new(params: HnswParams) -> Self {
-        Self { params }
+        Self {
+            params,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            adaptive_ef_construction: false,
+            cancel: None,
+        }
+    }
+
+    /// Set how many vectors `build_from_reader` buffers per read (at least 1)
+    #[must_use]
+    pub fn chunk_size(mut self, vectors: usize) -> Self {
+        self.chunk_size = vectors.max(1);
+        self
+    }
+
+    /// Set how many vectors `build_from_reader` inserts between flushes (at least 1)
+    #[must_use]
+    pub fn flush_interval(mut self, vectors: u64) -> Self {
+        self.flush_interval = vectors.max(1);
+        self
+    }
+
+    /// Let `build_from_reader` narrow the construction beam while the index
+    /// is small (see `IndexOptions::adaptive_ef_construction`)
+    #[must_use]
+    pub fn adaptive_ef_construction(mut self, enabled: bool) -> Self {
+        self.adaptive_ef_construction = enabled;
+        self
+    }
+
+    /// Let `build_from_reader` and `resume_from_reader` stop early when `token`
+    /// is cancelled
+    ///
+    /// The token is checked before each insert. A cancelled build flushes the
+    /// vectors inserted so far and fails with `ErrorKind::Cancelled`;
+    /// `resume_from_reader` continues it later.
+    #[must_use]
+    pub fn cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
     }
 
     /// Build index from existing storage
@@ -27,6 +118,157 @@ impl HnswBuilder {
         Ok(graph)
     }
 
+    /// Build an index at `path` by streaming `count` vectors from `reader`
+    ///
+    /// The source holds packed little-endian `f32` values, `dims` per vector,
+    /// with no header (a raw embedding dump). Only one chunk of vectors is held
+    /// in memory at a time, and the index is flushed every `flush_interval`
+    /// vectors so its dirty pages become clean and the OS can evict them. This
+    /// keeps memory bounded when the index is larger than RAM.
+    ///
+    /// Vectors are appended if the file already holds an index. The graph uses
+    /// this builder's `max_connections`, `max_connections_0`, `ef_construction`,
+    /// `ef_search`, `max_layers`, `distance` and `normalize`; `ml` is derived
+    /// from `max_connections`. The beam narrows while the index is small if
+    /// `adaptive_ef_construction` is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index cannot be opened, the reader fails or ends
+    /// before `count` vectors, or an insert or flush fails. Vectors inserted
+    /// before the last successful flush are durable, and the build can be
+    /// continued from there with `resume_from_reader`. Returns `Cancelled`
+    /// after flushing if the `cancel_token` was cancelled.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn build_from_reader<P: AsRef<Path>, R: Read>(
+        &self,
+        path: P,
+        mut reader: R,
+        dims: u32,
+        count: u64,
+    ) -> Result<VectorIndex> {
+        let mut index = self.open_index(path, dims)?;
+        index.begin_build(count);
+        self.ingest(&mut index, &mut reader, 0, count)?;
+        Ok(index)
+    }
+
+    /// Continue an interrupted `build_from_reader` from its last flush
+    ///
+    /// `reader` must be the same source, positioned anywhere: it is seeked to
+    /// `BuildProgress::input_offset` (relative to the start of the source).
+    /// If the file holds no recorded build, this starts one like
+    /// `build_from_reader`; if the recorded build is complete, nothing is read.
+    /// Use `VectorIndex::build_progress()` to resume by hand from sources that
+    /// cannot seek.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the recorded build was for a different `count`, or
+    /// for the same reasons as `build_from_reader`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn resume_from_reader<P: AsRef<Path>, R: Read + Seek>(
+        &self,
+        path: P,
+        mut reader: R,
+        dims: u32,
+        count: u64,
+    ) -> Result<VectorIndex> {
+        let mut index = self.open_index(path, dims)?;
+
+        let done = match index.build_progress() {
+            Some(progress) if progress.total != count => {
+                anyhow::bail!(Tagged::new(
+                    ErrorKind::InvalidArgument,
+                    format!(
+                        "Recorded build is for {} vectors, but {} were requested",
+                        progress.total, count
+                    )
+                ));
+            }
+            Some(progress) if progress.is_complete() => return Ok(index),
+            Some(progress) => {
+                reader
+                    .seek(SeekFrom::Start(progress.input_offset))
+                    .context("Failed to seek to the resume offset")?;
+                progress.vectors_done
+            }
+            None => {
+                index.begin_build(count);
+                0
+            }
+        };
+
+        self.ingest(&mut index, &mut reader, done, count)?;
+        Ok(index)
+    }
+
+    /// Open the index a bulk build writes into
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_index<P: AsRef<Path>>(&self, path: P, dims: u32) -> Result<VectorIndex> {
+        let options = IndexOptions {
+            max_connections: self.params.max_connections,
+            max_connections_0: self.params.max_connections_0,
+            ef_construction: self.params.ef_construction,
+            ef_search: self.params.ef_search,
+            adaptive_ef_construction: self.adaptive_ef_construction,
+            max_layers: self.params.max_layers,
+            distance: self.params.distance,
+            normalize: self.params.normalize,
+            ..IndexOptions::default()
+        };
+        VectorIndex::open(path, dims, options)
+    }
+
+    /// Insert vectors `done..count` of a build from `reader`, flushing periodically
+    #[cfg(not(target_arch = "wasm32"))]
+    fn ingest<R: Read>(
+        &self,
+        index: &mut VectorIndex,
+        reader: &mut R,
+        mut done: u64,
+        count: u64,
+    ) -> Result<()> {
+        index.reserve(count - done)?;
+
+        let dims = index.dimensions() as usize;
+        let vector_bytes = dims * std::mem::size_of::<f32>();
+        let mut buffer = vec![0u8; self.chunk_size * vector_bytes];
+        let mut vector = vec![0.0f32; dims];
+
+        let mut since_flush = 0u64;
+        while done < count {
+            let chunk_len = (count - done).min(self.chunk_size as u64) as usize;
+            let chunk = &mut buffer[..chunk_len * vector_bytes];
+            reader.read_exact(chunk).with_context(|| {
+                format!("Failed to read vectors {}..{} of {}", done, done + chunk_len as u64, count)
+            })?;
+
+            for raw in chunk.chunks_exact(vector_bytes) {
+                let (values, _) = raw.as_chunks::<4>();
+                for (dst, src) in vector.iter_mut().zip(values) {
+                    *dst = f32::from_le_bytes(*src);
+                }
+                if let Some(cancel) = &self.cancel
+                    && let Err(e) = cancel.check("Build")
+                {
+                    index.flush()?;
+                    return Err(e);
+                }
+                index.add(&vector)?;
+            }
+
+            done += chunk_len as u64;
+            since_flush += chunk_len as u64;
+            if since_flush >= self.flush_interval {
+                index.flush()?;
+                since_flush = 0;
+            }
+        }
+
+        index.flush()
+    }
+
     /// Select layer for new node using exponential decay
     fn select_layer(&self) -> usize {
         let uniform: f32 = rand::random();
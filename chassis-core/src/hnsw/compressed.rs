@@ -0,0 +1,132 @@
+//! Compressed node records for read-only graphs
+//!
+//! A fixed-width record reserves eight bytes for every neighbor slot, used or
+//! not, so it can be rewritten in place. A graph that no longer changes can
+//! drop the empty slots and store each list sorted, as the first ID followed
+//! by the gaps between IDs, in LEB128 varints. Neighbors tend to have nearby
+//! IDs, so most gaps fit in one to three bytes.
+//!
+//! ```text
+//! [Graph header] [Record offsets: (node_count + 1) x u64] [Records, by node ID]
+//!
+//! record = node header (16 bytes), then for each layer:
+//!          neighbor count, first neighbor ID, gap to each next ID (varints)
+//! ```
+//!
+//! Offsets count from the first record, and the last one is where the
+//! records end, so record `i` spans `offsets[i]..offsets[i + 1]`.
+
+use super::node::{NodeHeader, NodeId};
+
+/// Bytes per entry of the record offset table
+pub(crate) const OFFSET_SIZE: usize = 8;
+
+/// Append `value` as an LEB128 varint
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Decode the varint at `*pos` and advance past it, or `None` if it is
+/// truncated or longer than a `u64`
+#[inline]
+pub(crate) fn read_varint(bytes: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = *bytes.get(*pos)?;
+        *pos += 1;
+        if shift > 63 || (shift == 63 && byte > 1) {
+            return None;
+        }
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte < 0x80 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+}
+
+/// Append a compressed record: `header` (the node header of a fixed-width or
+/// compact record) without its overflow slot, then `layers`, sorted in place
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn encode_record(out: &mut Vec<u8>, header: &[u8], layers: &mut [Vec<NodeId>]) {
+    out.extend_from_slice(&header[..NodeHeader::OVERFLOW_SLOT_OFFSET]);
+    out.extend_from_slice(&[0; NodeHeader::SIZE - NodeHeader::OVERFLOW_SLOT_OFFSET]);
+
+    for neighbors in layers {
+        neighbors.sort_unstable();
+        write_varint(out, neighbors.len() as u64);
+        let mut previous = 0;
+        for &neighbor in neighbors.iter() {
+            write_varint(out, neighbor - previous);
+            previous = neighbor;
+        }
+    }
+}
+
+/// Position of the first neighbor of `layer` in a compressed record, and the
+/// number of neighbors it has
+///
+/// The caller checks `layer` against the record's layer count.
+pub(crate) fn find_layer(record: &[u8], layer: usize) -> Option<(usize, usize)> {
+    let mut pos = NodeHeader::SIZE;
+    for _ in 0..layer {
+        let count = read_varint(record, &mut pos)?;
+        for _ in 0..count {
+            read_varint(record, &mut pos)?;
+        }
+    }
+    let count = usize::try_from(read_varint(record, &mut pos)?).ok()?;
+    Some((pos, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varint_roundtrip() {
+        let values = [0, 1, 127, 128, 300, 16_383, 16_384, u64::from(u32::MAX), u64::MAX];
+        let mut bytes = Vec::new();
+        for &value in &values {
+            write_varint(&mut bytes, value);
+        }
+        assert_eq!(bytes[..4], [0, 1, 127, 0x80]);
+
+        let mut pos = 0;
+        for &value in &values {
+            assert_eq!(read_varint(&bytes, &mut pos), Some(value));
+        }
+        assert_eq!(pos, bytes.len());
+        assert_eq!(read_varint(&bytes, &mut pos), None);
+
+        // Truncated, and past 64 bits
+        assert_eq!(read_varint(&[0x80], &mut 0), None);
+        assert_eq!(read_varint(&[0xff; 10], &mut 0), None);
+    }
+
+    #[test]
+    fn test_record_layers_are_sorted_and_delta_encoded() {
+        let mut header = [0u8; NodeHeader::SIZE];
+        header[0] = 5;
+        header[8] = 2;
+        header[NodeHeader::OVERFLOW_SLOT_OFFSET] = 9;
+        let mut layers = vec![vec![300, 7, 12], vec![]];
+
+        let mut record = Vec::new();
+        encode_record(&mut record, &header, &mut layers);
+        assert_eq!(record[NodeHeader::OVERFLOW_SLOT_OFFSET], 0);
+        // Count 3, then 7, 12 - 7 and 300 - 12 (two bytes), then an empty layer
+        assert_eq!(record[NodeHeader::SIZE..], [3, 7, 5, 0xa0, 0x02, 0]);
+
+        let (pos, count) = find_layer(&record, 0).unwrap();
+        assert_eq!((pos, count), (NodeHeader::SIZE + 1, 3));
+        assert_eq!(find_layer(&record, 1), Some((record.len(), 0)));
+        assert_eq!(find_layer(&record, 2), None);
+    }
+}
@@ -6,23 +6,74 @@
 //! - **No HashMap for offsets**: Deterministic formula eliminates memory overhead
 //! - **Zero-allocation iteration**: `neighbors_iter_from_mmap` reads directly from mmap
 //! - **Persistent header**: Entry point and max layer survive restarts
+//!
+//! # Two-Tier Records
+//!
+//! About `1 - 1/M` of the nodes (94% with `M = 16`) live on layer 0 only, so a
+//! record with slots for every layer is mostly empty. New graphs therefore
+//! keep two arrays in the graph zone:
+//!
+//! ```text
+//! [Graph header] [Overflow header] [Compact records, by node ID] [Overflow records, by slot]
+//! ```
+//!
+//! A compact record holds the node header and layer 0; a node above layer 0
+//! also owns an overflow record with layers 1 and up, whose slot is stored in
+//! its header. Both are found with one multiplication. The compact array has
+//! room for `base_capacity` records; when it fills up, the overflow region is
+//! moved further out. Graphs created before this layout keep their
+//! fixed-width records.
+//!
+//! # Compressed Records
+//!
+//! Read-only copies written by `write_compressed_zone()` store variable-length
+//! records behind an offset table instead (see the `compressed` module). They
+//! are only opened by readers: a record cannot grow in place.
+//!
+//! # Buffered Writes
+//!
+//! With a write buffer (`set_write_buffer()`), records are not written to the
+//! mapping as they change but kept in memory and written out in file order on
+//! commit, or when the buffer fills up. Reads look in the buffer first.
 
 use crate::Storage;
+use crate::distance::{Distance, MAX_DISTANCE_NAME_LEN, Metric};
+use crate::element::{ElementType, Query};
+use crate::error::{ErrorKind, Tagged};
 use crate::hnsw::HnswParams;
+use crate::hnsw::compressed::{self, OFFSET_SIZE};
 use crate::hnsw::node::{
     INVALID_NODE_ID, Node, NodeHeader, NodeId, NodeRecord, NodeRecordParams, Offset,
 };
+use crate::hnsw::search::{ResultFilter, ScratchPool};
+use crate::hnsw::write_buffer::{RecordSlot, WriteBuffer};
+use crate::instrument;
+use crate::migrate;
 use anyhow::{Context, Result};
+use std::collections::HashSet;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::Write;
+use std::mem;
+use std::ops::Range;
 
 /// Size of the graph header in bytes
 const GRAPH_HEADER_SIZE: usize = 64;
 
+/// Size of the overflow header that follows the graph header in two-tier graphs
+const OVERFLOW_HEADER_SIZE: usize = 64;
+
+/// Compact records a two-tier graph makes room for at first
+const MIN_BASE_CAPACITY: u64 = 64;
+
 /// Legacy graph offset used by the original sparse layout.
 const LEGACY_GRAPH_ZONE_START: u64 = 1024 * 1024 * 1024;
 
 /// Extra room left after the current vector zone when placing or relocating the graph.
 const VECTOR_ZONE_SLACK: usize = 8 * 1024 * 1024;
 
+/// High-layer nodes kept besides the entry point as alternative search starts.
+pub(crate) const ENTRY_CANDIDATES: usize = 2;
+
 /// Persistent graph header stored at the beginning of the graph zone.
 ///
 /// # Layout (64 bytes, 8-byte aligned)
@@ -31,16 +82,23 @@ const VECTOR_ZONE_SLACK: usize = 8 * 1024 * 1024;
 /// Offset  Size  Field
 /// ------  ----  -----
 /// 0       4     magic:  [u8; 4] (b"HNSW")
-/// 4       4     version: u32
+/// 4       4     version: u32 (1: fixed-width records, 2: two-tier records)
 /// 8       8     entry_point: NodeId (u64)
 /// 16      8     node_count: u64
 /// 24      4     max_layer: u32
 /// 28      2     m: u16
 /// 30      2     m0: u16
 /// 32      1     max_layers: u8
-/// 33      31    _reserved: [u8; 31]
+/// 33      3     _padding: [u8; 3]
+/// 36      4     ef_construction: u32 (0 in files written before it was stored)
+/// 40      8     deleted_count: u64
+/// 48      16    entry_candidates: [NodeId; 2]
 /// Total:  64 bytes
 /// ```
+///
+/// `m`, `m0`, `max_layers` and `ef_construction` are the authoritative build
+/// parameters of the graph: `VectorIndex` takes them from here when it opens
+/// an existing index, whatever the caller's options say.
 #[repr(C, align(8))]
 #[derive(Debug, Clone, Copy)]
 pub struct GraphHeader {
@@ -68,16 +126,32 @@ pub struct GraphHeader {
     /// Maximum layers
     pub max_layers: u8, // u8 at offset 32
 
-    /// Padding to 64 bytes
-    _reserved: [u8; 31], // 31 bytes:  offset 33-63
+    /// Alignment padding
+    _padding: [u8; 3], // 3 bytes: offset 33-35
+
+    /// Construction quality the graph was built with (0 if not recorded)
+    pub ef_construction: u32, // u32 at offset 36
+
+    /// Number of nodes marked deleted
+    pub deleted_count: u64, // u64 at offset 40
+
+    /// High-layer nodes searches may start from besides the entry point
+    /// (INVALID_NODE_ID for unused slots, 0 in files written before they were stored)
+    pub entry_candidates: [NodeId; ENTRY_CANDIDATES], // 16 bytes: offset 48-63
 }
 
 impl GraphHeader {
     /// Magic bytes for graph header validation
     const MAGIC: &'static [u8; 4] = b"HNSW";
 
-    /// Current format version
-    const VERSION: u32 = 1;
+    /// Current format version: two-tier node records
+    const VERSION: u32 = 2;
+
+    /// Format version of graphs with fixed-width node records
+    const FIXED_VERSION: u32 = 1;
+
+    /// Format version of read-only graphs with compressed node records
+    const COMPRESSED_VERSION: u32 = 3;
 
     /// Create a new empty graph header
     #[must_use]
@@ -91,13 +165,30 @@ impl GraphHeader {
             m: params.m,
             m0: params.m0,
             max_layers: params.max_layers,
-            _reserved: [0; 31],
+            _padding: [0; 3],
+            ef_construction: 0,
+            deleted_count: 0,
+            entry_candidates: [INVALID_NODE_ID; ENTRY_CANDIDATES],
         }
     }
 
     /// Validate magic bytes and version
     pub fn is_valid(&self) -> bool {
-        self.magic == *Self::MAGIC && self.version == Self::VERSION
+        self.magic == *Self::MAGIC
+            && matches!(
+                self.version,
+                Self::VERSION | Self::FIXED_VERSION | Self::COMPRESSED_VERSION
+            )
+    }
+
+    /// Returns `true` if node records are split into compact and overflow records
+    pub fn is_two_tier(&self) -> bool {
+        self.version == Self::VERSION
+    }
+
+    /// Returns `true` if node records are compressed and the graph is read-only
+    pub fn is_compressed(&self) -> bool {
+        self.version == Self::COMPRESSED_VERSION
     }
 
     /// Convert to bytes for writing
@@ -113,7 +204,11 @@ impl GraphHeader {
         bytes[28..30].copy_from_slice(&self.m.to_le_bytes());
         bytes[30..32].copy_from_slice(&self.m0.to_le_bytes());
         bytes[32] = self.max_layers;
-        bytes[33..64].copy_from_slice(&self._reserved);
+        bytes[33..36].copy_from_slice(&self._padding);
+        bytes[36..40].copy_from_slice(&self.ef_construction.to_le_bytes());
+        bytes[40..48].copy_from_slice(&self.deleted_count.to_le_bytes());
+        bytes[48..56].copy_from_slice(&self.entry_candidates[0].to_le_bytes());
+        bytes[56..64].copy_from_slice(&self.entry_candidates[1].to_le_bytes());
 
         bytes
     }
@@ -134,9 +229,15 @@ impl GraphHeader {
         let m = u16::from_le_bytes(bytes[28..30].try_into()?);
         let m0 = u16::from_le_bytes(bytes[30..32].try_into()?);
         let max_layers = bytes[32];
+        let ef_construction = u32::from_le_bytes(bytes[36..40].try_into()?);
+        let deleted_count = u64::from_le_bytes(bytes[40..48].try_into()?);
 
-        let mut reserved = [0u8; 31];
-        reserved.copy_from_slice(&bytes[33..64]);
+        let mut padding = [0u8; 3];
+        padding.copy_from_slice(&bytes[33..36]);
+        let entry_candidates = [
+            u64::from_le_bytes(bytes[48..56].try_into()?),
+            u64::from_le_bytes(bytes[56..64].try_into()?),
+        ];
 
         Ok(Self {
             magic,
@@ -147,7 +248,10 @@ impl GraphHeader {
             m,
             m0,
             max_layers,
-            _reserved: reserved,
+            _padding: padding,
+            ef_construction,
+            deleted_count,
+            entry_candidates,
         })
     }
 
@@ -158,6 +262,147 @@ impl GraphHeader {
     }
 }
 
+/// Location and fill of the overflow region, stored right after the graph
+/// header in two-tier graphs.
+///
+/// # Layout (64 bytes)
+///
+/// ```text
+/// Offset  Size  Field
+/// ------  ----  -----
+/// 0       8     base_capacity: u64 (compact records before the overflow region)
+/// 8       8     count: u64 (overflow records allocated)
+/// 16      48    _reserved
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OverflowHeader {
+    /// Compact records the graph has room for before the overflow region
+    pub base_capacity: u64,
+
+    /// Overflow records allocated
+    pub count: u64,
+}
+
+impl OverflowHeader {
+    /// Convert to bytes for writing
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; OVERFLOW_HEADER_SIZE] {
+        let mut bytes = [0u8; OVERFLOW_HEADER_SIZE];
+        bytes[0..8].copy_from_slice(&self.base_capacity.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.count.to_le_bytes());
+        bytes
+    }
+
+    /// Read from bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < OVERFLOW_HEADER_SIZE {
+            anyhow::bail!("Buffer too small for overflow header");
+        }
+        Ok(Self {
+            base_capacity: u64::from_le_bytes(bytes[0..8].try_into()?),
+            count: u64::from_le_bytes(bytes[8..16].try_into()?),
+        })
+    }
+}
+
+/// Where node records live in the graph zone, as offsets from its start
+#[derive(Debug, Clone, Copy)]
+struct RecordLayout {
+    /// Offset of node 0's record
+    records_start: u64,
+
+    /// Bytes per node in the array indexed by node ID: a full record, or a
+    /// compact one in two-tier graphs
+    record_size: u64,
+
+    /// Bytes per overflow record; 0 for fixed-width records
+    overflow_size: u64,
+
+    /// Size and fill of the overflow region (two-tier graphs)
+    overflow: OverflowHeader,
+
+    /// Bytes of the variable-length records after the offset table, in
+    /// compressed graphs
+    compressed_len: Option<u64>,
+}
+
+impl RecordLayout {
+    /// Every record holds all layers
+    fn fixed(params: NodeRecordParams) -> Self {
+        Self {
+            records_start: GRAPH_HEADER_SIZE as u64,
+            record_size: params.record_size() as u64,
+            overflow_size: 0,
+            overflow: OverflowHeader::default(),
+            compressed_len: None,
+        }
+    }
+
+    /// Compact records, plus overflow records for nodes above layer 0
+    fn two_tier(params: NodeRecordParams, overflow: OverflowHeader) -> Self {
+        Self {
+            records_start: (GRAPH_HEADER_SIZE + OVERFLOW_HEADER_SIZE) as u64,
+            record_size: params.compact_record_size() as u64,
+            overflow_size: params.overflow_record_size() as u64,
+            overflow,
+            compressed_len: None,
+        }
+    }
+
+    /// Records of `records_len` bytes in total behind a table of
+    /// `node_count + 1` offsets; `record_size` is 0 as records vary in size
+    fn compressed(node_count: u64, records_len: u64) -> Option<Self> {
+        let table_size = node_count.checked_add(1)?.checked_mul(OFFSET_SIZE as u64)?;
+        Some(Self {
+            records_start: (GRAPH_HEADER_SIZE as u64).checked_add(table_size)?,
+            record_size: 0,
+            overflow_size: 0,
+            overflow: OverflowHeader::default(),
+            compressed_len: Some(records_len),
+        })
+    }
+
+    fn is_two_tier(&self) -> bool {
+        self.overflow_size != 0
+    }
+
+    fn is_compressed(&self) -> bool {
+        self.compressed_len.is_some()
+    }
+
+    /// Offset of node `node_id`'s (compact) record
+    fn record_offset(&self, node_id: NodeId) -> u64 {
+        self.records_start + node_id * self.record_size
+    }
+
+    /// Offset of overflow record `slot`
+    fn overflow_offset(&self, slot: u64) -> u64 {
+        self.records_start
+            + self.overflow.base_capacity * self.record_size
+            + slot * self.overflow_size
+    }
+
+    /// Bytes of a graph zone with `node_count` nodes, headers included
+    fn zone_size(&self, node_count: u64) -> Option<u64> {
+        if let Some(records_len) = self.compressed_len {
+            self.records_start.checked_add(records_len)
+        } else if self.is_two_tier() {
+            self.overflow_end(self.overflow.count)
+        } else {
+            node_count.checked_mul(self.record_size)?.checked_add(self.records_start)
+        }
+    }
+
+    /// End of the overflow region once it holds `count` records
+    fn overflow_end(&self, count: u64) -> Option<u64> {
+        self.overflow
+            .base_capacity
+            .checked_mul(self.record_size)?
+            .checked_add(count.checked_mul(self.overflow_size)?)?
+            .checked_add(self.records_start)
+    }
+}
+
 /// HNSW graph stored in segmented layout:
 /// [Vector Storage] | [Graph Header] | [Node Data]
 ///
@@ -170,58 +415,284 @@ impl GraphHeader {
 pub struct HnswGraph {
     pub(crate) storage: Storage,
 
-    #[allow(dead_code)]
     params: HnswParams,
 
+    /// Distance kernel resolved from `params`
+    metric: Metric,
+
     /// Cached record parameters for O(1) lookup
     pub record_params: NodeRecordParams,
 
     /// Offset where graph section begins (includes header)
     graph_start: Offset,
 
+    /// Where node records live relative to `graph_start`
+    layout: RecordLayout,
+
     /// Entry point node ID (highest layer node)
     pub entry_point: Option<NodeId>,
 
     /// Maximum layer in the graph
     pub max_layer: usize,
 
+    /// Other high-layer nodes with their top layer, highest first; searches
+    /// descend from whichever of these and the entry point is closest
+    pub(crate) entry_candidates: Vec<(NodeId, usize)>,
+
     /// Number of nodes in the graph (tracked for header persistence)
     pub node_count: u64,
+
+    /// Number of nodes marked deleted; searches skip the flag check while it is 0
+    deleted_count: u64,
+
+    /// Deleted nodes reused since the last commit
+    ///
+    /// Their deleted flags stay set in the file until `clear_revived_flags()`
+    /// runs after the new vectors are synced, so a crash in between leaves
+    /// the slot deleted rather than live with a torn vector. `is_deleted()`
+    /// treats them as live meanwhile.
+    revived: HashSet<NodeId>,
+
+    /// Visited filters and heaps reused by searches
+    pub(crate) scratch_pool: ScratchPool,
+
+    /// Records changed since the last commit, if writes are buffered
+    write_buffer: WriteBuffer,
 }
 
 impl HnswGraph {
     /// Opens existing graph or creates new one
-    pub fn open(mut storage: Storage, params: HnswParams) -> Result<Self> {
+    pub fn open(mut storage: Storage, mut params: HnswParams) -> Result<Self> {
+        migrate::run(&mut storage)?;
+        Self::resolve_metric(&mut storage, &mut params)?;
+
         let record_params = params.to_record_params();
-        let graph_start = Self::find_or_create_graph_start(&mut storage, record_params)?;
+        let graph_start = Self::find_or_create_graph_start(&mut storage)?;
 
         // Ensure graph zone has space for header
         let header_end = graph_start as usize + GRAPH_HEADER_SIZE;
         storage.ensure_graph_capacity(header_end)?;
 
         // Try to read existing header
-        let (entry_point, max_layer, node_count) =
+        let (header, layout) =
             match Self::try_read_graph_header(&storage, graph_start, record_params) {
-                Ok(header) => {
-                    // Existing graph found
-                    let entry_point = if header.entry_point == INVALID_NODE_ID {
-                        None
-                    } else {
-                        Some(header.entry_point)
-                    };
-                    (entry_point, header.max_layer as usize, header.node_count)
+                Ok(header) if header.is_compressed() => {
+                    if !storage.is_shared_reader() {
+                        anyhow::bail!(Tagged::new(
+                            ErrorKind::ReadOnly,
+                            "Index has a compressed graph, which cannot be written; \
+                             open it with open_sealed() or open_shared()"
+                        ));
+                    }
+                    let layout = Self::read_compressed_layout(&storage, graph_start, &header)?;
+                    (header, layout)
+                }
+                Ok(header) if header.is_two_tier() => {
+                    let overflow = Self::read_overflow_header(&storage, graph_start, &header)?;
+                    (header, RecordLayout::two_tier(record_params, overflow))
                 }
+                // Existing graph found, with the fixed-width records of earlier versions
+                Ok(header) => (header, RecordLayout::fixed(record_params)),
+                // An existing graph built with other `M`/`max_layers`: reinitializing
+                // it would make recovery discard every stored vector
+                Err(e) if ErrorKind::of(&e) == ErrorKind::InvalidArgument => return Err(e),
+                // A sealed file is read as is and cannot get a fresh graph
+                Err(e) if storage.is_sealed() => return Err(e),
                 Err(_) => {
-                    // New graph - initialize header
-                    let header = GraphHeader::new(record_params);
-                    let bytes = header.to_bytes();
-                    let zone = storage.graph_zone_mut(graph_start as usize, GRAPH_HEADER_SIZE)?;
-                    zone.copy_from_slice(&bytes);
-                    (None, 0, 0)
+                    // New graph - initialize both headers
+                    let mut header = GraphHeader::new(record_params);
+                    header.ef_construction =
+                        u32::try_from(params.ef_construction).unwrap_or(u32::MAX);
+                    let overflow = OverflowHeader::default();
+
+                    let headers_size = GRAPH_HEADER_SIZE + OVERFLOW_HEADER_SIZE;
+                    storage.ensure_graph_capacity(graph_start as usize + headers_size)?;
+                    let zone = storage.graph_zone_mut(graph_start as usize, headers_size)?;
+                    zone[..GRAPH_HEADER_SIZE].copy_from_slice(&header.to_bytes());
+                    zone[GRAPH_HEADER_SIZE..].copy_from_slice(&overflow.to_bytes());
+                    storage.set_two_tier_graph()?;
+                    (header, RecordLayout::two_tier(record_params, overflow))
                 }
             };
+        let entry_point = (header.entry_point != INVALID_NODE_ID).then_some(header.entry_point);
+
+        let metric = Metric::new(params.distance, params.normalize, storage.element_type());
+        let mut graph = Self {
+            storage,
+            params,
+            metric,
+            record_params,
+            graph_start,
+            layout,
+            entry_point,
+            max_layer: header.max_layer as usize,
+            node_count: header.node_count,
+            deleted_count: header.deleted_count,
+            revived: HashSet::new(),
+            entry_candidates: Vec::with_capacity(ENTRY_CANDIDATES),
+            scratch_pool: ScratchPool::default(),
+            write_buffer: WriteBuffer::default(),
+        };
+        graph.load_entry_candidates(header.entry_candidates)?;
+        Ok(graph)
+    }
+
+    /// Copy of this graph over a fork of its storage (see `Storage::fork()`)
+    ///
+    /// The copy starts from the in-memory state, so nodes added since the
+    /// last flush are part of it.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn fork(&self) -> Result<Self> {
+        if self.layout.is_compressed() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::ReadOnly,
+                "Cannot fork an index with a compressed graph"
+            ));
+        }
+        let mut storage = self.storage.fork()?;
+
+        // A shared reader hides ghost vectors in memory only; hide them again
+        if storage.count() > self.node_count {
+            storage.truncate_logical(self.node_count);
+        }
+
+        Ok(Self {
+            storage,
+            params: self.params,
+            metric: self.metric,
+            record_params: self.record_params,
+            graph_start: self.graph_start,
+            layout: self.layout,
+            entry_point: self.entry_point,
+            max_layer: self.max_layer,
+            node_count: self.node_count,
+            deleted_count: self.deleted_count,
+            revived: self.revived.clone(),
+            entry_candidates: self.entry_candidates.clone(),
+            scratch_pool: ScratchPool::default(),
+            write_buffer: self.write_buffer.clone(),
+        })
+    }
+
+    /// Take the entry candidates stored in the header, dropping unused slots,
+    /// duplicates and nodes that are no longer published
+    fn load_entry_candidates(&mut self, candidates: [NodeId; ENTRY_CANDIDATES]) -> Result<()> {
+        for node_id in candidates {
+            if node_id >= self.node_count
+                || Some(node_id) == self.entry_point
+                || self.entry_candidates.iter().any(|&(id, _)| id == node_id)
+            {
+                continue;
+            }
+            let top_layer = self.node_layer_count(node_id)?.saturating_sub(1);
+            if top_layer > 0 {
+                self.entry_candidates.push((node_id, top_layer.min(self.max_layer)));
+            }
+        }
+        self.entry_candidates.sort_by_key(|&(_, layer)| std::cmp::Reverse(layer));
+        Ok(())
+    }
+
+    /// Offer a newly published node (or a displaced entry point) as an entry
+    /// candidate, replacing the lowest one if all slots are taken
+    pub(crate) fn offer_entry_candidate(&mut self, node_id: NodeId, top_layer: usize) {
+        if top_layer == 0 {
+            return;
+        }
+        if self.entry_candidates.len() == ENTRY_CANDIDATES {
+            match self.entry_candidates.last() {
+                Some(&(_, lowest)) if lowest < top_layer => {
+                    self.entry_candidates.pop();
+                }
+                _ => return,
+            }
+        }
+        let position = self.entry_candidates.partition_point(|&(_, layer)| layer >= top_layer);
+        self.entry_candidates.insert(position, (node_id, top_layer));
+    }
+
+    /// Nodes searches start from, with their top layer: the entry point
+    /// first, then the entry candidates, highest layer first
+    pub fn entry_points(&self) -> impl Iterator<Item = (NodeId, usize)> + '_ {
+        let entry = self.entry_point.map(|entry| (entry, self.max_layer));
+        entry.into_iter().chain(self.entry_candidates.iter().copied())
+    }
+
+    /// Number of layers node `node_id` is linked on (its top layer plus one)
+    ///
+    /// Pair with `neighbors_iter_from_mmap()` to walk a node's links layer by layer.
+    pub fn node_layer_count(&self, node_id: NodeId) -> Result<usize> {
+        if node_id >= self.node_count {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!("Node {} does not exist (node count is {})", node_id, self.node_count)
+            ));
+        }
+        let header = NodeHeader::from_bytes(self.get_node_bytes(node_id)?)
+            .map_err(|e| anyhow::anyhow!("Invalid node header for node {}: {}", node_id, e))?;
+        Ok(header.layer_count as usize)
+    }
+
+    /// Check the distance in `params` against the one recorded in `storage` and
+    /// take normalization from a populated index, recording both in an empty one
+    fn resolve_metric(storage: &mut Storage, params: &mut HnswParams) -> Result<()> {
+        let writable_empty = storage.count() == 0 && !storage.is_shared_reader();
+        if storage.element_type() == ElementType::Binary
+            && ((writable_empty && params.normalize) || params.distance != Distance::Euclidean)
+        {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Binary vectors are compared by Hamming distance and cannot be normalized or use another distance"
+            ));
+        }
+
+        if writable_empty {
+            if storage.normalized() != params.normalize {
+                storage.set_normalized(params.normalize)?;
+            }
+        } else {
+            params.normalize = storage.normalized();
+        }
+
+        let distance = &params.distance;
+        if let Distance::Custom(custom) = distance
+            && !custom.has_valid_name()
+        {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "Custom distance names must be 1 to {} bytes without NUL characters or a `chassis:` prefix, got {:?}",
+                    MAX_DISTANCE_NAME_LEN,
+                    custom.name()
+                )
+            ));
+        }
+
+        let requested = distance.name();
+        let recorded = storage.distance_name();
+        if recorded == requested {
+            return Ok(());
+        }
+        if writable_empty {
+            return storage.set_distance_name(requested);
+        }
 
-        Ok(Self { storage, params, record_params, graph_start, entry_point, max_layer, node_count })
+        anyhow::bail!(Tagged::new(
+            ErrorKind::InvalidArgument,
+            format!(
+                "Index was built with {} but opened with {}",
+                Distance::describe(recorded),
+                Distance::describe(requested)
+            )
+        ))
+    }
+
+    /// Graph header persisted in `storage`, if it has one
+    pub(crate) fn stored_header(storage: &Storage) -> Option<GraphHeader> {
+        let graph_start = storage.graph_offset().unwrap_or(LEGACY_GRAPH_ZONE_START);
+        let zone = storage.graph_zone(graph_start as usize, GRAPH_HEADER_SIZE).ok()?;
+        let header = GraphHeader::from_bytes(zone).ok()?;
+        header.is_valid().then_some(header)
     }
 
     /// Try to read graph header if it exists
@@ -234,29 +705,86 @@ impl HnswGraph {
         let header = GraphHeader::from_bytes(zone)?;
 
         if !header.is_valid() {
-            anyhow::bail!("Invalid graph header magic or version");
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                "Invalid graph header magic or version"
+            ));
         }
 
         // Verify params match
         let header_params = header.to_record_params();
         if header_params != expected_params {
-            anyhow::bail!(
-                "Graph header params mismatch: expected {:?}, got {:?}",
-                expected_params,
-                header_params
-            );
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "Graph header params mismatch: expected {:?}, got {:?}",
+                    expected_params, header_params
+                )
+            ));
         }
 
         Ok(header)
     }
 
+    /// Read the overflow header of a two-tier graph, checking it leaves room
+    /// for every node
+    fn read_overflow_header(
+        storage: &Storage,
+        graph_start: Offset,
+        header: &GraphHeader,
+    ) -> Result<OverflowHeader> {
+        let offset = graph_start as usize + GRAPH_HEADER_SIZE;
+        let overflow =
+            OverflowHeader::from_bytes(storage.graph_zone(offset, OVERFLOW_HEADER_SIZE)?)?;
+
+        if header.node_count > overflow.base_capacity || overflow.count > 1 << 32 {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                format!(
+                    "Corrupted overflow header: room for {} compact records and {} overflow records, {} nodes",
+                    overflow.base_capacity, overflow.count, header.node_count
+                )
+            ));
+        }
+        Ok(overflow)
+    }
+
+    /// Layout of a compressed graph, checking its records lie in the zone
+    fn read_compressed_layout(
+        storage: &Storage,
+        graph_start: Offset,
+        header: &GraphHeader,
+    ) -> Result<RecordLayout> {
+        let corrupted = || {
+            Tagged::new(
+                ErrorKind::Corrupted,
+                format!("Corrupted compressed graph: offset table of {} nodes", header.node_count),
+            )
+        };
+        let table_end = RecordLayout::compressed(header.node_count, 0).ok_or_else(corrupted)?;
+        let end_offset = graph_start + table_end.records_start - OFFSET_SIZE as u64;
+        let end_offset = usize::try_from(end_offset).map_err(|_| corrupted())?;
+        let records_len = storage
+            .graph_zone(end_offset, OFFSET_SIZE)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+            .map_err(|_| corrupted())?;
+
+        let layout =
+            RecordLayout::compressed(header.node_count, records_len).ok_or_else(corrupted)?;
+        let records_start = usize::try_from(graph_start + layout.records_start)
+            .context("Graph too large for this platform")?;
+        let records_len = usize::try_from(records_len).map_err(|_| corrupted())?;
+        storage.graph_zone(records_start, records_len).map_err(|_| corrupted())?;
+        Ok(layout)
+    }
+
     /// Read graph header from mmap
     pub fn read_graph_header(&self) -> Result<GraphHeader> {
         let zone = self.storage.graph_zone(self.graph_start as usize, GRAPH_HEADER_SIZE)?;
         let header = GraphHeader::from_bytes(zone)?;
 
         if !header.is_valid() {
-            anyhow::bail!("Invalid graph header");
+            anyhow::bail!(Tagged::new(ErrorKind::Corrupted, "Invalid graph header"));
         }
 
         Ok(header)
@@ -264,16 +792,39 @@ impl HnswGraph {
 
     /// Write graph header to mmap
     pub fn write_graph_header(&mut self) -> Result<()> {
+        let bytes = self.header_bytes();
+        let zone = self.storage.graph_zone_mut(self.graph_start as usize, bytes.len())?;
+        zone.copy_from_slice(&bytes);
+
+        Ok(())
+    }
+
+    /// The graph header and, in two-tier graphs, the overflow header for the
+    /// current in-memory state
+    fn header_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.graph_header().to_bytes().to_vec();
+        if self.layout.is_two_tier() {
+            bytes.extend_from_slice(&self.layout.overflow.to_bytes());
+        }
+        bytes
+    }
+
+    /// Header describing the current in-memory graph state
+    fn graph_header(&self) -> GraphHeader {
         let mut header = GraphHeader::new(self.record_params);
+        if !self.layout.is_two_tier() {
+            header.version = GraphHeader::FIXED_VERSION;
+        }
         header.entry_point = self.entry_point.unwrap_or(INVALID_NODE_ID);
         header.max_layer = self.max_layer as u32;
         header.node_count = self.node_count;
-
-        let bytes = header.to_bytes();
-        let zone = self.storage.graph_zone_mut(self.graph_start as usize, GRAPH_HEADER_SIZE)?;
-        zone.copy_from_slice(&bytes);
-
-        Ok(())
+        header.deleted_count = self.deleted_count;
+        for (slot, &(node_id, _)) in header.entry_candidates.iter_mut().zip(&self.entry_candidates)
+        {
+            *slot = node_id;
+        }
+        header.ef_construction = u32::try_from(self.params.ef_construction).unwrap_or(u32::MAX);
+        header
     }
 
     /// Compute the file offset for a node record.
@@ -289,45 +840,333 @@ impl HnswGraph {
     /// # Formula
     ///
     /// ```text
-    /// offset = graph_start + GRAPH_HEADER_SIZE + (node_id * record_size)
+    /// offset = graph_start + records_start + (node_id * record_size)
     /// ```
+    ///
+    /// `records_start` covers the headers, and in two-tier graphs `record_size`
+    /// is that of the compact record.
     #[inline]
     pub(crate) fn node_offset(&self, node_id: NodeId) -> Offset {
-        let base = self.graph_start + GRAPH_HEADER_SIZE as u64;
-        base + (node_id * self.record_params.record_size() as u64)
+        if self.layout.is_compressed() {
+            let start = self.compressed_span(node_id).map_or(0, |span| span.start);
+            return self.graph_start + self.layout.records_start + start;
+        }
+        self.graph_start + self.layout.record_offset(node_id)
+    }
+
+    /// Bytes of node `node_id`'s record in a compressed graph, relative to
+    /// the first record
+    fn compressed_span(&self, node_id: NodeId) -> Result<Range<u64>> {
+        if node_id >= self.node_count {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!("Node {} does not exist (node count is {})", node_id, self.node_count)
+            ));
+        }
+
+        let offset = self.graph_start as usize + GRAPH_HEADER_SIZE + node_id as usize * OFFSET_SIZE;
+        let table = self.storage.graph_zone(offset, 2 * OFFSET_SIZE)?;
+        let start = u64::from_le_bytes(table[..OFFSET_SIZE].try_into().unwrap());
+        let end = u64::from_le_bytes(table[OFFSET_SIZE..].try_into().unwrap());
+        if start > end
+            || end - start < NodeHeader::SIZE as u64
+            || self.layout.compressed_len.is_none_or(|len| end > len)
+        {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                format!(
+                    "Corrupted compressed graph: record of node {} at {}..{}",
+                    node_id, start, end
+                )
+            ));
+        }
+        Ok(start..end)
+    }
+
+    /// File offset of overflow record `slot` in a two-tier graph
+    #[inline]
+    fn overflow_offset(&self, slot: u64) -> Offset {
+        self.graph_start + self.layout.overflow_offset(slot)
     }
 
     /// Read a node record directly from mmap.
+    ///
+    /// In two-tier graphs the compact record and the overflow record, if any,
+    /// are joined back into a full record.
     pub fn read_node_record(&self, node_id: NodeId) -> Result<NodeRecord> {
-        let record_size = self.record_params.record_size();
-        let offset = self.node_offset(node_id);
+        let bytes = self.get_node_bytes(node_id)?;
+        if self.layout.is_compressed() {
+            return self.read_compressed_record(node_id, bytes);
+        }
+        if !self.layout.is_two_tier() {
+            return NodeRecord::from_bytes(bytes, self.record_params)
+                .map_err(|e| anyhow::anyhow!("Failed to read node record: {}", e));
+        }
 
-        let zone = self.storage.graph_zone(offset as usize, record_size)?;
-        NodeRecord::from_bytes(zone, self.record_params)
+        let header = NodeHeader::from_bytes(bytes)
+            .map_err(|e| anyhow::anyhow!("Failed to read node record: {}", e))?;
+
+        // Slots without an overflow record read as INVALID_NODE_ID
+        let mut full = vec![0xff; self.record_params.record_size()];
+        full[..bytes.len()].copy_from_slice(bytes);
+        if header.layer_count > 1 {
+            let overflow = self.overflow_record(u64::from(header.overflow_slot))?;
+            full[bytes.len()..].copy_from_slice(&overflow[mem::size_of::<NodeId>()..]);
+        }
+        NodeRecord::from_bytes(&full, self.record_params)
             .map_err(|e| anyhow::anyhow!("Failed to read node record: {}", e))
     }
 
+    /// Decode a compressed record into a full one
+    fn read_compressed_record(&self, node_id: NodeId, bytes: &[u8]) -> Result<NodeRecord> {
+        let header = NodeHeader::from_bytes(bytes)
+            .map_err(|e| anyhow::anyhow!("Failed to read node record: {}", e))?;
+        if header.layer_count > self.record_params.max_layers {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                format!("Record of node {} has {} layers", node_id, header.layer_count)
+            ));
+        }
+
+        let mut record = NodeRecord::new(node_id, header.layer_count, self.record_params);
+        record.header.flags = header.flags;
+        let mut neighbors = Vec::new();
+        for layer in 0..header.layer_count as usize {
+            neighbors.clear();
+            neighbors.extend(self.neighbors_iter_from_mmap(node_id, layer)?);
+            record.set_neighbors(layer, &neighbors);
+        }
+        Ok(record)
+    }
+
     /// Write a node record directly to mmap.
     pub fn write_node_record(&mut self, record: &NodeRecord) -> Result<()> {
-        let record_size = self.record_params.record_size();
-        let offset = self.node_offset(record.header.node_id);
+        let node_id = record.header.node_id;
+        let count = node_id.checked_add(1).context("Node ID overflow")?;
+        self.ensure_base_capacity(count)?;
 
-        let required_size = offset as usize + record_size;
+        let required_size = self.node_offset(node_id) as usize + self.layout.record_size as usize;
         self.storage.ensure_graph_capacity(required_size)?;
 
-        let bytes = record.to_bytes();
-        let zone = self.storage.graph_zone_mut(offset as usize, record_size)?;
-        zone.copy_from_slice(&bytes);
+        self.store_record(record)
+    }
+
+    /// Write `record` over the node's (compact) record, and in a two-tier
+    /// graph its upper layers to the node's overflow record
+    ///
+    /// The overflow record is written first, so the compact record never
+    /// points to a slot that does not hold the node's layers yet.
+    fn store_record(&mut self, record: &NodeRecord) -> Result<()> {
+        let node_id = record.header.node_id;
+        let record_size = self.layout.record_size as usize;
+        let mut bytes = record.to_bytes();
+
+        if self.layout.is_two_tier() {
+            let upper = bytes.split_off(record_size);
+            if record.header.layer_count > 1 {
+                let slot = self.overflow_slot(node_id)?;
+                let mut overflow = Vec::with_capacity(self.layout.overflow_size as usize);
+                overflow.extend_from_slice(&node_id.to_le_bytes());
+                overflow.extend_from_slice(&upper);
+                self.put_record(RecordSlot::Overflow(slot), overflow)?;
+
+                let slot = u32::try_from(slot).context("Overflow slot exceeds u32")?;
+                bytes[NodeHeader::OVERFLOW_SLOT_OFFSET..][..4].copy_from_slice(&slot.to_le_bytes());
+            }
+        }
 
+        self.put_record(RecordSlot::Node(node_id), bytes)
+    }
+
+    /// Write a record to the mapping, or to the write buffer if there is one
+    fn put_record(&mut self, slot: RecordSlot, bytes: Vec<u8>) -> Result<()> {
+        if !self.write_buffer.is_enabled() {
+            return self.write_record(slot, &bytes);
+        }
+
+        self.write_buffer.insert(slot, bytes.into_boxed_slice());
+        if self.write_buffer.is_full() {
+            self.drain_write_buffer()?;
+        }
+        Ok(())
+    }
+
+    fn write_record(&mut self, slot: RecordSlot, bytes: &[u8]) -> Result<()> {
+        let offset = match slot {
+            RecordSlot::Node(node_id) => self.node_offset(node_id),
+            RecordSlot::Overflow(slot) => self.overflow_offset(slot),
+        };
+        self.storage.graph_zone_mut(offset as usize, bytes.len())?.copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Write the buffered records to the mapping, in file order
+    ///
+    /// The records are not synced; `commit()` drains the buffer before it
+    /// syncs them.
+    pub(crate) fn drain_write_buffer(&mut self) -> Result<()> {
+        if self.write_buffer.is_empty() {
+            return Ok(());
+        }
+        let records = self.write_buffer.take();
+        for (slot, bytes) in &records {
+            self.write_record(*slot, bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Hold up to `records` changed node records in memory between commits;
+    /// 0 writes them straight to the mapping
+    ///
+    /// Buffered records are written out when the buffer is full or on the
+    /// next commit, whichever comes first, in file order. Lowering the limit
+    /// writes out the records already buffered.
+    pub fn set_write_buffer(&mut self, records: usize) -> Result<()> {
+        if records < self.write_buffer.len() || records == 0 {
+            self.drain_write_buffer()?;
+        }
+        self.write_buffer.set_capacity(records);
+        Ok(())
+    }
+
+    /// Overflow slot for node `node_id`: the one it already owns (an update,
+    /// or an insert retried after a failure), or a newly allocated one
+    fn overflow_slot(&mut self, node_id: NodeId) -> Result<u64> {
+        if let Some(slot) = self.owned_overflow_slot(node_id) {
+            return Ok(slot);
+        }
+
+        let slot = self.layout.overflow.count;
+        if slot > u64::from(u32::MAX) {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                "Overflow region full: a graph holds at most 2^32 nodes above layer 0"
+            ));
+        }
+        let end = self.layout.overflow_end(slot + 1).context("Graph size calculation overflow")?;
+        self.storage.ensure_graph_capacity(
+            usize::try_from(self.graph_start + end).context("Graph too large for this platform")?,
+        )?;
+        self.layout.overflow.count += 1;
+        Ok(slot)
+    }
+
+    /// Slot of the overflow record node `node_id` owns, if its record was
+    /// written with one
+    ///
+    /// The record is trusted only if the overflow record names the node as
+    /// its owner: records past the node count can be left over from a crash,
+    /// and their slots handed out again since.
+    fn owned_overflow_slot(&self, node_id: NodeId) -> Option<u64> {
+        let header = NodeHeader::from_bytes(self.get_node_bytes(node_id).ok()?).ok()?;
+        let slot = u64::from(header.overflow_slot);
+        if header.node_id != node_id || header.layer_count <= 1 {
+            return None;
+        }
+
+        let owner = self.overflow_record(slot).ok()?.first_chunk::<8>()?;
+        (NodeId::from_le_bytes(*owner) == node_id).then_some(slot)
+    }
+
+    /// Bytes of overflow record `slot`: the owner's node ID, then its layers
+    fn overflow_record(&self, slot: u64) -> Result<&[u8]> {
+        if slot >= self.layout.overflow.count {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                format!(
+                    "Overflow slot {} is past the {} allocated",
+                    slot, self.layout.overflow.count
+                )
+            ));
+        }
+        if let Some(bytes) = self.write_buffer.get(RecordSlot::Overflow(slot)) {
+            return Ok(bytes);
+        }
+        self.storage
+            .graph_zone(self.overflow_offset(slot) as usize, self.layout.overflow_size as usize)
+    }
+
+    /// Make room for `count` compact records, moving the overflow region
+    /// further out if they would run into it
+    ///
+    /// Compact records written next would overwrite the old region, so when it
+    /// holds committed records, their copy is synced and the persisted
+    /// overflow header pointed at it before this returns.
+    fn ensure_base_capacity(&mut self, count: u64) -> Result<()> {
+        let overflow = self.layout.overflow;
+        if !self.layout.is_two_tier() || count <= overflow.base_capacity {
+            return Ok(());
+        }
+
+        // A background sync still writes an overflow header for the old region
+        #[cfg(not(target_arch = "wasm32"))]
+        self.storage.wait_for_background()?;
+
+        let old_start = self.overflow_offset(0) as usize;
+        let mut moved = self.layout;
+        moved.overflow.base_capacity =
+            count.max(overflow.base_capacity.saturating_mul(2)).max(MIN_BASE_CAPACITY);
+        let new_start =
+            self.graph_start + moved.overflow_end(0).context("Graph size calculation overflow")?;
+        let new_end = self.graph_start
+            + moved.overflow_end(overflow.count).context("Graph size calculation overflow")?;
+        let new_start = usize::try_from(new_start).context("Graph too large for this platform")?;
+        let new_end = usize::try_from(new_end).context("Graph too large for this platform")?;
+
+        if overflow.count > 0 {
+            let len = new_end - new_start;
+            self.storage.ensure_graph_capacity(new_end)?;
+            let zone = self.storage.graph_zone_mut(old_start, new_end - old_start)?;
+            zone.copy_within(..len, new_start - old_start);
+        }
+        self.layout = moved;
+
+        let offset = self.graph_start as usize + GRAPH_HEADER_SIZE;
+        let persisted =
+            OverflowHeader::from_bytes(self.storage.graph_zone(offset, OVERFLOW_HEADER_SIZE)?)?;
+        if persisted.count > 0 {
+            self.storage.commit()?;
+            let pointed =
+                OverflowHeader { base_capacity: moved.overflow.base_capacity, ..persisted };
+            self.storage
+                .graph_zone_mut(offset, OVERFLOW_HEADER_SIZE)?
+                .copy_from_slice(&pointed.to_bytes());
+            self.storage.commit()?;
+        }
         Ok(())
     }
 
     /// Get raw bytes slice for a node (zero-copy).
+    ///
+    /// In two-tier graphs this is the compact record: the header and layer 0.
     pub fn get_node_bytes(&self, node_id: NodeId) -> Result<&[u8]> {
-        let record_size = self.record_params.record_size();
+        if let Some(bytes) = self.write_buffer.get(RecordSlot::Node(node_id)) {
+            return Ok(bytes);
+        }
+        if self.layout.is_compressed() {
+            let span = self.compressed_span(node_id)?;
+            let start = self.graph_start + self.layout.records_start + span.start;
+            return self.storage.graph_zone(start as usize, (span.end - span.start) as usize);
+        }
         let offset = self.node_offset(node_id);
+        self.storage.graph_zone(offset as usize, self.layout.record_size as usize)
+    }
+
+    /// Ask the OS to start reading a node's vector and record into memory.
+    pub(crate) fn prefetch_node(&self, node_id: NodeId) -> Result<()> {
+        if node_id >= self.node_count() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!("Node {} does not exist (node count is {})", node_id, self.node_count())
+            ));
+        }
 
-        self.storage.graph_zone(offset as usize, record_size)
+        self.storage.prefetch_vector(node_id)?;
+        let len = match self.layout.is_compressed() {
+            true => self.compressed_span(node_id).map_or(0, |span| span.end - span.start),
+            false => self.layout.record_size,
+        };
+        self.storage.prefetch_graph(self.node_offset(node_id) as usize, len as usize)
     }
 
     /// Iterate neighbors directly from mmap bytes (zero-allocation).
@@ -363,7 +1202,19 @@ impl HnswGraph {
 
         // Check if layer is valid
         if layer >= header.layer_count as usize {
-            return Ok(NeighborIterator { bytes: &[], start_offset: 0, count: 0, pos: 0 });
+            return Ok(NeighborIterator::empty());
+        }
+
+        if self.layout.is_compressed() {
+            let (start_offset, count) = compressed::find_layer(bytes, layer)
+                .filter(|&(_, count)| count <= self.record_params.max_neighbors(layer))
+                .ok_or_else(|| {
+                    Tagged::new(
+                        ErrorKind::Corrupted,
+                        format!("Invalid neighbor list for node {} on layer {}", node_id, layer),
+                    )
+                })?;
+            return Ok(NeighborIterator { bytes, start_offset, count, pos: 0, previous: Some(0) });
         }
 
         let layer_offset = self
@@ -372,7 +1223,28 @@ impl HnswGraph {
             .ok_or_else(|| anyhow::anyhow!("Invalid layer"))?;
         let neighbor_count = self.record_params.max_neighbors(layer);
 
-        Ok(NeighborIterator { bytes, start_offset: layer_offset, count: neighbor_count, pos: 0 })
+        // Upper layers of a two-tier node sit in its overflow record, after
+        // the owner ID where a full record has the header and layer 0
+        if layer > 0 && self.layout.is_two_tier() {
+            let bytes = self.overflow_record(u64::from(header.overflow_slot))?;
+            let start_offset =
+                layer_offset - self.layout.record_size as usize + mem::size_of::<NodeId>();
+            return Ok(NeighborIterator {
+                bytes,
+                start_offset,
+                count: neighbor_count,
+                pos: 0,
+                previous: None,
+            });
+        }
+
+        Ok(NeighborIterator {
+            bytes,
+            start_offset: layer_offset,
+            count: neighbor_count,
+            pos: 0,
+            previous: None,
+        })
     }
 
     /// Compute distance using zero-copy vector slice access.
@@ -380,10 +1252,24 @@ impl HnswGraph {
     /// This is the **preferred method** for search because it:
     /// - Does NOT allocate a `Vec<f32>` for the vector
     /// - Reads directly from memory-mapped storage
+    ///
+    /// Returns the ranking distance that graph traversal compares, which for
+    /// `Distance::Euclidean` is the squared L2 distance.
     #[inline]
     pub fn compute_distance_zero_copy(&self, query: &[f32], node_id: NodeId) -> Result<f32> {
-        let vector_slice = self.storage.get_vector_slice(node_id)?;
-        Ok(crate::distance::euclidean_distance(query, vector_slice))
+        self.query_distance(&Query::new(query, self.metric), node_id)
+    }
+
+    /// `compute_distance_zero_copy` for a query prepared once per search
+    #[inline]
+    pub(crate) fn query_distance(&self, query: &Query<'_>, node_id: NodeId) -> Result<f32> {
+        instrument::record_distance(|| self.vector_page(node_id));
+        Ok(self.storage.stored_vector(node_id)?.distance_to(query, self.metric))
+    }
+
+    /// Identifies the page holding the vector of `node_id`, for search traces
+    pub(crate) fn vector_page(&self, node_id: NodeId) -> u64 {
+        instrument::page_key(self.storage.vector_position(node_id), false)
     }
 
     /// Commit graph state (write header and flush to disk).
@@ -410,66 +1296,84 @@ impl HnswGraph {
     /// graph.commit()?;  // Once at the end
     /// ```
     pub fn commit(&mut self) -> Result<()> {
+        // Records are synced before the header that publishes them
+        if !self.write_buffer.is_empty() {
+            self.drain_write_buffer()?;
+            self.storage.commit()?;
+        }
+        fail_point!(crate::failpoint::BEFORE_HEADER_WRITE);
         self.write_graph_header()?;
         self.storage.commit()
     }
 
+    /// Like `commit()`, but syncs the file on a background thread
+    ///
+    /// The graph header for the current state is written to the file only
+    /// after the nodes are durable; `on_durable` runs once it is. See
+    /// `Storage::commit_in_background()`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn commit_in_background(
+        &mut self,
+        on_durable: impl FnOnce() + Send + 'static,
+    ) -> Result<()> {
+        self.drain_write_buffer()?;
+        let header = self.header_bytes();
+        self.storage.commit_in_background(self.graph_start as usize, header, on_durable)
+    }
+
     /// Finds where graph data starts in file
-    fn find_or_create_graph_start(
-        storage: &mut Storage,
-        record_params: NodeRecordParams,
-    ) -> Result<Offset> {
+    fn find_or_create_graph_start(storage: &mut Storage) -> Result<Offset> {
         if let Some(graph_offset) = storage.graph_offset() {
             let vector_end = storage.vector_end()? as u64;
-            if graph_offset < vector_end {
-                anyhow::bail!(
-                    "Corrupted layout: graph offset {} overlaps vector zone ending at {}",
-                    graph_offset,
-                    vector_end
-                );
+            if graph_offset < vector_end && !storage.has_graph_file() {
+                anyhow::bail!(Tagged::new(
+                    ErrorKind::Corrupted,
+                    format!(
+                        "Corrupted layout: graph offset {} overlaps vector zone ending at {}",
+                        graph_offset, vector_end
+                    )
+                ));
             }
             return Ok(graph_offset);
         }
 
-        if let Some(compacted_offset) = Self::compact_legacy_graph_zone(storage, record_params)? {
-            return Ok(compacted_offset);
-        }
-
-        let graph_start = Self::choose_graph_start(storage.vector_end()?)?;
+        let graph_start = Self::choose_graph_start(storage, storage.vector_end()?)?;
         storage.set_graph_offset(graph_start);
         Ok(graph_start)
     }
 
-    fn compact_legacy_graph_zone(
-        storage: &mut Storage,
-        record_params: NodeRecordParams,
-    ) -> Result<Option<Offset>> {
+    /// Move a graph stored at the old fixed offset to the current layout,
+    /// right after the vector zone (the `legacy-graph-zone` migration)
+    pub(crate) fn compact_legacy_graph_zone(storage: &mut Storage) -> Result<()> {
         let legacy_start = LEGACY_GRAPH_ZONE_START as usize;
-        let Ok(header) =
-            Self::try_read_graph_header(storage, LEGACY_GRAPH_ZONE_START, record_params)
-        else {
-            return Ok(None);
+        let Some(header) = Self::stored_header(storage) else {
+            return Ok(());
         };
+        let record_params = header.to_record_params();
 
-        let graph_size = GRAPH_HEADER_SIZE
-            .checked_add(
-                usize::try_from(header.node_count)
-                    .context("Legacy node count too large for this platform")?
-                    .checked_mul(record_params.record_size())
-                    .context("Legacy graph size calculation overflow")?,
-            )
+        let layout = if header.is_two_tier() {
+            let overflow = Self::read_overflow_header(storage, LEGACY_GRAPH_ZONE_START, &header)?;
+            RecordLayout::two_tier(record_params, overflow)
+        } else {
+            RecordLayout::fixed(record_params)
+        };
+        let graph_size = layout
+            .zone_size(header.node_count)
+            .and_then(|size| usize::try_from(size).ok())
             .context("Legacy graph size calculation overflow")?;
 
-        let graph_start = Self::choose_graph_start(storage.vector_end()?)?;
-        storage.move_graph_zone(legacy_start, graph_start as usize, graph_size)?;
-        Ok(Some(graph_start))
+        let graph_start = Self::choose_graph_start(storage, storage.vector_end()?)?;
+        storage.move_graph_zone(legacy_start, graph_start as usize, graph_size)
     }
 
-    fn choose_graph_start(vector_end: usize) -> Result<Offset> {
-        let graph_start = vector_end
-            .checked_add(VECTOR_ZONE_SLACK)
-            .context("Graph offset calculation overflow")?;
-        Ok(Storage::page_align(graph_start) as Offset)
+    fn choose_graph_start(storage: &Storage, vector_end: usize) -> Result<Offset> {
+        // Under a size quota, the slack must leave room for the graph zone
+        let slack = storage.max_file_bytes().map_or(VECTOR_ZONE_SLACK, |max_file_bytes| {
+            VECTOR_ZONE_SLACK.min(usize::try_from(max_file_bytes / 8).unwrap_or(usize::MAX))
+        });
+        let graph_start =
+            vector_end.checked_add(slack).context("Graph offset calculation overflow")?;
+        Ok(storage.zone_align(graph_start) as Offset)
     }
 
     /// Ensure the graph zone will not overlap the next vector append.
@@ -481,13 +1385,13 @@ impl HnswGraph {
             .context("Vector count overflow while preparing insert")?;
         let next_vector_end = self.storage.vector_end_for_count(next_count)?;
 
-        if next_vector_end <= self.graph_start as usize {
+        if next_vector_end <= self.graph_start as usize || self.storage.has_graph_file() {
             return Ok(());
         }
 
         let graph_size = usize::try_from(self.total_graph_size()?)
             .context("Graph size too large for this platform")?;
-        let new_graph_start = Self::choose_graph_start(next_vector_end)?;
+        let new_graph_start = Self::choose_graph_start(&self.storage, next_vector_end)?;
         self.storage.move_graph_zone(
             self.graph_start as usize,
             new_graph_start as usize,
@@ -498,6 +1402,59 @@ impl HnswGraph {
         Ok(())
     }
 
+    /// Make room for `count` vectors and their node records in one step.
+    ///
+    /// Moves the graph zone past the vector zone `count` vectors will need and
+    /// preallocates the file to the end of `count` node records, so inserts up
+    /// to that count neither relocate the graph nor grow the file. With a
+    /// graph file, each file is preallocated to its own zone instead.
+    pub(crate) fn reserve(&mut self, count: u64) -> Result<()> {
+        let vector_end = self.storage.vector_end_for_count(count)?;
+        if self.storage.has_graph_file() {
+            self.storage.preallocate(vector_end)?;
+        } else if vector_end > self.graph_start as usize {
+            let graph_size = usize::try_from(self.total_graph_size()?)
+                .context("Graph size too large for this platform")?;
+            let new_graph_start = Self::choose_graph_start(&self.storage, vector_end)?;
+            self.storage.move_graph_zone(
+                self.graph_start as usize,
+                new_graph_start as usize,
+                graph_size,
+            )?;
+            self.graph_start = new_graph_start;
+        }
+
+        let graph_size = if self.layout.is_two_tier() {
+            self.ensure_base_capacity(count)?;
+            let tall = self.expected_tall_nodes(count.saturating_sub(self.node_count));
+            self.layout
+                .overflow_end(self.layout.overflow.count.saturating_add(tall))
+                .context("Graph size calculation overflow")?
+        } else {
+            Self::checked_total_graph_size(count, self.record_params)?
+        };
+        let graph_size =
+            usize::try_from(graph_size).context("Graph size too large for this platform")?;
+        let graph_end = (self.graph_start as usize)
+            .checked_add(graph_size)
+            .context("Graph zone end overflow")?;
+        self.storage.preallocate_graph(graph_end)
+    }
+
+    /// Overflow records `count` new nodes will need, with a margin of six
+    /// standard deviations above the expected number of nodes drawn above
+    /// layer 0
+    fn expected_tall_nodes(&self, count: u64) -> u64 {
+        if self.params.max_layers <= 1 || count == 0 {
+            return 0;
+        }
+        let p = (-1.0 / f64::from(self.params.ml)).exp();
+        let n = count as f64;
+        let mean = n * p;
+        let bound = mean + 6.0 * (mean * (1.0 - p)).sqrt() + 1.0;
+        (bound.ceil() as u64).min(count)
+    }
+
     /// Inserts a new node into the graph.
     ///
     /// # Node ID Invariant
@@ -555,12 +1512,14 @@ impl HnswGraph {
 
         // In release mode, check and return error instead of UB
         if vector_id != self.node_count {
-            anyhow::bail!(
-                "Node ID invariant violated: expected {}, got {}. \
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!(
+                    "Node ID invariant violated: expected {}, got {}. \
                  Node IDs must be dense and monotonically increasing.",
-                self.node_count,
-                vector_id
-            );
+                    self.node_count, vector_id
+                )
+            ));
         }
 
         let node = Node { id: vector_id, offset: 0, layers: vec![Vec::new(); layer + 1] };
@@ -570,8 +1529,12 @@ impl HnswGraph {
         self.write_node(&node)?;
 
         if self.entry_point.is_none() || layer > self.max_layer {
-            self.entry_point = Some(vector_id);
+            if let Some(previous) = self.entry_point.replace(vector_id) {
+                self.offer_entry_candidate(previous, self.max_layer);
+            }
             self.max_layer = layer;
+        } else {
+            self.offer_entry_candidate(vector_id, layer);
         }
 
         Ok(())
@@ -625,19 +1588,12 @@ impl HnswGraph {
         }
 
         // Write without incrementing count
-        let record_size = self.record_params.record_size();
-        let offset = self.node_offset(node_id);
-
-        let bytes = record.to_bytes();
-        let zone = self.storage.graph_zone_mut(offset as usize, record_size)?;
-        zone.copy_from_slice(&bytes);
-
-        Ok(())
+        self.store_record(record)
     }
 
     /// Returns total size of graph data written so far
     fn total_graph_size(&self) -> Result<u64> {
-        Self::checked_total_graph_size(self.node_count, self.record_params)
+        self.layout.zone_size(self.node_count).context("Graph size calculation overflow")
     }
 
     fn checked_total_graph_size(node_count: u64, record_params: NodeRecordParams) -> Result<u64> {
@@ -650,6 +1606,146 @@ impl HnswGraph {
             .context("Graph size calculation overflow")
     }
 
+    /// Returns the bytes of the graph zone: its headers and the node records
+    pub(crate) fn zone_bytes(&self) -> Result<u64> {
+        self.total_graph_size()
+    }
+
+    /// Counts the nodes on each layer and the edges on layer 0
+    ///
+    /// Reads every node record, so this is linear in the node count.
+    pub(crate) fn layer_census(&self) -> Result<(Vec<u64>, u64)> {
+        let mut layer_nodes = Vec::new();
+        let mut base_edges = 0;
+
+        for node_id in 0..self.node_count {
+            let header = NodeHeader::from_bytes(self.get_node_bytes(node_id)?)
+                .map_err(|e| anyhow::anyhow!("Invalid node header for node {}: {}", node_id, e))?;
+            let layers = header.layer_count as usize;
+            if layer_nodes.len() < layers {
+                layer_nodes.resize(layers, 0);
+            }
+            for count in &mut layer_nodes[..layers] {
+                *count += 1;
+            }
+            base_edges += self.neighbors_iter_from_mmap(node_id, 0)?.count() as u64;
+        }
+
+        Ok((layer_nodes, base_edges))
+    }
+
+    /// Nodes above layer 0 with their top layer, highest first and the entry
+    /// point ahead of its peers
+    ///
+    /// Reads every node header, so this is linear in the node count.
+    pub(crate) fn upper_layer_nodes(&self) -> Result<Vec<(NodeId, usize)>> {
+        let mut nodes = Vec::new();
+        for node_id in 0..self.node_count {
+            let header = NodeHeader::from_bytes(self.get_node_bytes(node_id)?)
+                .map_err(|e| anyhow::anyhow!("Invalid node header for node {}: {}", node_id, e))?;
+            if header.layer_count > 1 {
+                nodes.push((node_id, header.layer_count as usize - 1));
+            }
+        }
+
+        nodes.sort_by_key(|&(node_id, layer)| {
+            (std::cmp::Reverse(layer), Some(node_id) != self.entry_point, node_id)
+        });
+        Ok(nodes)
+    }
+
+    /// Byte range of the graph headers in the graph zone's file
+    pub(crate) fn header_range(&self) -> Range<usize> {
+        let start = self.graph_start as usize;
+        start..start + self.layout.records_start as usize
+    }
+
+    /// Byte ranges holding node `node_id` in the graph zone's file: its
+    /// (compact) record, and its overflow record if it has one
+    pub(crate) fn record_ranges(
+        &self,
+        node_id: NodeId,
+    ) -> Result<(Range<usize>, Option<Range<usize>>)> {
+        let header = NodeHeader::from_bytes(self.get_node_bytes(node_id)?)
+            .map_err(|e| anyhow::anyhow!("Invalid node header for node {}: {}", node_id, e))?;
+        let start = self.node_offset(node_id) as usize;
+        if self.layout.is_compressed() {
+            return Ok((start..start + self.get_node_bytes(node_id)?.len(), None));
+        }
+        let record = start..start + self.layout.record_size as usize;
+
+        if !self.layout.is_two_tier() || header.layer_count <= 1 {
+            return Ok((record, None));
+        }
+        let slot = u64::from(header.overflow_slot);
+        let start = self.overflow_offset(slot) as usize;
+        self.overflow_record(slot)?;
+        Ok((record, Some(start..start + self.layout.overflow_size as usize)))
+    }
+
+    /// Offsets of the records `write_compressed_zone()` writes, relative to
+    /// the first, followed by the end of the last
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn compressed_offsets(&self) -> Result<Vec<u64>> {
+        let mut offsets = Vec::with_capacity(self.node_count as usize + 1);
+        let (mut record, mut layers) = (Vec::new(), Vec::new());
+        offsets.push(0);
+        for node_id in 0..self.node_count {
+            record.clear();
+            self.encode_compressed(node_id, &mut record, &mut layers)?;
+            offsets.push(offsets[node_id as usize] + record.len() as u64);
+        }
+        Ok(offsets)
+    }
+
+    /// Bytes of the compressed graph zone with record `offsets`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn compressed_zone_len(offsets: &[u64]) -> u64 {
+        let table_size = (offsets.len() * OFFSET_SIZE) as u64;
+        GRAPH_HEADER_SIZE as u64 + table_size + offsets.last().copied().unwrap_or(0)
+    }
+
+    /// Write this graph as a compressed, read-only graph zone: its header,
+    /// the record `offsets` from `compressed_offsets()`, then the records
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn write_compressed_zone(&self, offsets: &[u64], out: &mut dyn Write) -> Result<()> {
+        let mut header = self.graph_header();
+        header.version = GraphHeader::COMPRESSED_VERSION;
+        out.write_all(&header.to_bytes())?;
+        for offset in offsets {
+            out.write_all(&offset.to_le_bytes())?;
+        }
+
+        let (mut record, mut layers) = (Vec::new(), Vec::new());
+        for node_id in 0..self.node_count {
+            record.clear();
+            self.encode_compressed(node_id, &mut record, &mut layers)?;
+            out.write_all(&record)?;
+        }
+        Ok(())
+    }
+
+    /// Append node `node_id`'s compressed record to `record`, collecting its
+    /// neighbor lists in `layers`
+    #[cfg(not(target_arch = "wasm32"))]
+    fn encode_compressed(
+        &self,
+        node_id: NodeId,
+        record: &mut Vec<u8>,
+        layers: &mut Vec<Vec<NodeId>>,
+    ) -> Result<()> {
+        let bytes = self.get_node_bytes(node_id)?;
+        let header = NodeHeader::from_bytes(bytes)
+            .map_err(|e| anyhow::anyhow!("Invalid node header for node {}: {}", node_id, e))?;
+        layers.resize_with(header.layer_count as usize, Vec::new);
+        for (layer, neighbors) in layers.iter_mut().enumerate() {
+            neighbors.clear();
+            neighbors.extend(self.neighbors_iter_from_mmap(node_id, layer)?);
+        }
+        compressed::encode_record(record, bytes, layers);
+        Ok(())
+    }
+
     /// Returns the record params for this graph
     #[inline]
     pub fn record_params(&self) -> NodeRecordParams {
@@ -661,6 +1757,212 @@ impl HnswGraph {
     pub fn node_count(&self) -> u64 {
         self.node_count
     }
+
+    /// Returns the distance function used for construction and search
+    #[inline]
+    pub fn distance(&self) -> Distance {
+        self.params.distance
+    }
+
+    /// Returns `true` if vectors are normalized to unit length on insert
+    #[inline]
+    pub fn normalizes(&self) -> bool {
+        self.params.normalize
+    }
+
+    /// Kernel distances are computed with
+    #[inline]
+    pub(crate) fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    /// Returns the number of nodes marked deleted
+    #[inline]
+    pub fn deleted_count(&self) -> u64 {
+        self.deleted_count - self.revived.len() as u64
+    }
+
+    /// Returns `true` if the node is marked deleted
+    #[inline]
+    pub fn is_deleted(&self, node_id: NodeId) -> Result<bool> {
+        if self.deleted_count == 0 {
+            return Ok(false);
+        }
+        Ok(self.flags(node_id)? & NodeHeader::DELETED != 0 && !self.revived.contains(&node_id))
+    }
+
+    /// Returns `true` if the node was reused since the last commit, so the
+    /// file still has it deleted
+    #[inline]
+    pub(crate) fn is_revived(&self, node_id: NodeId) -> bool {
+        !self.revived.is_empty() && self.revived.contains(&node_id)
+    }
+
+    /// Whether any node was reused since the last commit
+    #[inline]
+    pub(crate) fn has_revived(&self) -> bool {
+        !self.revived.is_empty()
+    }
+
+    /// Keep the deleted flag of reused node `node_id` set in the file until
+    /// `clear_revived_flags()`
+    pub(super) fn revive(&mut self, node_id: NodeId) {
+        self.revived.insert(node_id);
+    }
+
+    /// Clear the deleted flags of the nodes reused since the last commit
+    ///
+    /// Call once their vectors are synced; the flags and the deleted count
+    /// reach the file with the next sync.
+    pub(crate) fn clear_revived_flags(&mut self) -> Result<()> {
+        if self.revived.is_empty() {
+            return Ok(());
+        }
+        let revived: Vec<NodeId> = self.revived.drain().collect();
+        for &node_id in &revived {
+            *self.flags_mut(node_id)? &= !NodeHeader::DELETED;
+        }
+        self.store_deleted_count(self.deleted_count - revived.len() as u64)
+    }
+
+    /// Mark a node deleted, returning `false` if it already was
+    ///
+    /// The node keeps its links so searches still route through it, but it is
+    /// never returned as a result. The deletion is durable after the next commit.
+    pub fn mark_deleted(&mut self, node_id: NodeId) -> Result<bool> {
+        if node_id >= self.node_count {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!("Node {} out of bounds (count: {})", node_id, self.node_count)
+            ));
+        }
+        // A node reused since the last commit is still deleted in the file
+        if self.revived.remove(&node_id) {
+            return Ok(true);
+        }
+        if self.is_deleted(node_id)? {
+            return Ok(false);
+        }
+
+        *self.flags_mut(node_id)? |= NodeHeader::DELETED;
+        fail_point!(crate::failpoint::MID_DELETE);
+        self.store_deleted_count(self.deleted_count + 1)?;
+        Ok(true)
+    }
+
+    /// Returns `true` if the node is pinned
+    #[inline]
+    pub fn is_pinned(&self, node_id: NodeId) -> Result<bool> {
+        Ok(self.flags(node_id)? & NodeHeader::PINNED != 0)
+    }
+
+    /// Pin or unpin a node, returning `false` if it already was in that state
+    ///
+    /// Neighbor selection keeps pinned candidates ahead of the diversity
+    /// heuristic, so once a node links to a pinned one, pruning never drops
+    /// the link. Durable after the next commit.
+    pub fn set_pinned(&mut self, node_id: NodeId, pinned: bool) -> Result<bool> {
+        if node_id >= self.node_count {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!("Node {} out of bounds (count: {})", node_id, self.node_count)
+            ));
+        }
+        if self.is_pinned(node_id)? == pinned {
+            return Ok(false);
+        }
+
+        let flags = self.flags_mut(node_id)?;
+        if pinned {
+            *flags |= NodeHeader::PINNED;
+        } else {
+            *flags &= !NodeHeader::PINNED;
+        }
+        Ok(true)
+    }
+
+    /// Flags byte of a node's header
+    #[inline]
+    fn flags(&self, node_id: NodeId) -> Result<u8> {
+        if let Some(bytes) = self.write_buffer.get(RecordSlot::Node(node_id)) {
+            return Ok(bytes[NodeHeader::FLAGS_OFFSET]);
+        }
+        let offset = self.node_offset(node_id) as usize + NodeHeader::FLAGS_OFFSET;
+        Ok(self.storage.graph_zone(offset, 1)?[0])
+    }
+
+    /// Flags byte of a node's header, in the write buffer if the record is there
+    fn flags_mut(&mut self, node_id: NodeId) -> Result<&mut u8> {
+        let offset = self.node_offset(node_id) as usize + NodeHeader::FLAGS_OFFSET;
+        match self.write_buffer.get_mut(RecordSlot::Node(node_id)) {
+            Some(bytes) => Ok(&mut bytes[NodeHeader::FLAGS_OFFSET]),
+            None => Ok(&mut self.storage.graph_zone_mut(offset, 1)?[0]),
+        }
+    }
+
+    /// Count the deleted flags of all nodes and correct `deleted_count()` if it
+    /// disagrees, returning `true` if it did
+    ///
+    /// After a crash the header's count may be from another moment than the
+    /// flags: the OS writes mapped pages back in any order.
+    pub(crate) fn recount_deleted(&mut self) -> Result<bool> {
+        let mut deleted = 0;
+        for node_id in 0..self.node_count {
+            if self.flags(node_id)? & NodeHeader::DELETED != 0 {
+                deleted += 1;
+            }
+        }
+        if deleted == self.deleted_count {
+            return Ok(false);
+        }
+        self.store_deleted_count(deleted)?;
+        Ok(true)
+    }
+
+    /// Check the records of the last `count` published nodes
+    ///
+    /// Each must carry its own ID and a layer count the graph supports.
+    /// Records are synced before the header that publishes them, so a bad
+    /// one means the file was damaged rather than interrupted.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Corrupted` naming the first bad record.
+    pub(crate) fn verify_tail_records(&self, count: u64) -> Result<()> {
+        for node_id in self.node_count.saturating_sub(count)..self.node_count {
+            let header = NodeHeader::from_bytes(self.get_node_bytes(node_id)?);
+            let valid = header.is_ok_and(|header| {
+                header.node_id == node_id && header.layer_count <= self.record_params.max_layers
+            });
+            if !valid {
+                anyhow::bail!(Tagged::new(
+                    ErrorKind::Corrupted,
+                    format!("Index corruption detected: record of node {} is invalid", node_id)
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the number of deleted nodes after a node's flag changed
+    ///
+    /// The count is updated next to the flag so both reach disk in the same
+    /// sync, otherwise a crash before the header write would resurrect the
+    /// node (or hide a reused one).
+    pub(super) fn store_deleted_count(&mut self, count: u64) -> Result<()> {
+        self.deleted_count = count;
+        let count_offset = self.graph_start as usize + 40;
+        self.storage.graph_zone_mut(count_offset, 8)?.copy_from_slice(&count.to_le_bytes());
+        Ok(())
+    }
+
+    /// Returns `true` if a search filtered by `filter` may return `node_id`
+    #[inline]
+    pub(crate) fn accepts(&self, filter: ResultFilter, node_id: NodeId) -> Result<bool> {
+        Ok(node_id < filter.id_limit
+            && !(filter.skip_deleted && self.is_deleted(node_id)?)
+            && filter.predicate.is_none_or(|predicate| predicate(node_id)))
+    }
 }
 
 /// Zero-allocation iterator over neighbors in a layer.
@@ -671,6 +1973,16 @@ pub struct NeighborIterator<'a> {
     start_offset: usize,
     count: usize,
     pos: usize,
+
+    /// In compressed records: the last neighbor decoded, the next one being
+    /// the varint gap at `start_offset` after it
+    previous: Option<NodeId>,
+}
+
+impl NeighborIterator<'_> {
+    fn empty() -> Self {
+        Self { bytes: &[], start_offset: 0, count: 0, pos: 0, previous: None }
+    }
 }
 
 impl<'a> Iterator for NeighborIterator<'a> {
@@ -678,6 +1990,20 @@ impl<'a> Iterator for NeighborIterator<'a> {
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(previous) = self.previous {
+            if self.pos >= self.count {
+                return None;
+            }
+            self.pos += 1;
+            let gap = compressed::read_varint(self.bytes, &mut self.start_offset);
+            let Some(neighbor) = gap.and_then(|gap| previous.checked_add(gap)) else {
+                self.pos = self.count;
+                return None;
+            };
+            self.previous = Some(neighbor);
+            return Some(neighbor);
+        }
+
         while self.pos < self.count {
             let offset = self.start_offset + self.pos * 8;
             self.pos += 1;
@@ -742,6 +2068,9 @@ mod tests {
         header.entry_point = 42;
         header.max_layer = 3;
         header.node_count = 1000;
+        header.ef_construction = 200;
+        header.deleted_count = 7;
+        header.entry_candidates = [5, INVALID_NODE_ID];
 
         let bytes = header.to_bytes();
         let restored = GraphHeader::from_bytes(&bytes).unwrap();
@@ -753,6 +2082,39 @@ mod tests {
         assert_eq!(restored.m, 16);
         assert_eq!(restored.m0, 32);
         assert_eq!(restored.max_layers, 8);
+        assert_eq!(restored.ef_construction, 200);
+        assert_eq!(restored.deleted_count, 7);
+        assert_eq!(restored.entry_candidates, [5, INVALID_NODE_ID]);
+    }
+
+    #[test]
+    fn test_mark_deleted_persists() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        {
+            let mut storage = Storage::open(path, 4).unwrap();
+            for _ in 0..3 {
+                storage.insert(&[1.0; 4]).unwrap();
+            }
+            let mut graph = HnswGraph::open(storage, HnswParams::default()).unwrap();
+            for i in 0..3u64 {
+                graph.insert(i, 0).unwrap();
+            }
+
+            assert!(!graph.is_deleted(1).unwrap());
+            assert!(graph.mark_deleted(1).unwrap());
+            assert!(!graph.mark_deleted(1).unwrap());
+            assert!(graph.mark_deleted(5).is_err());
+            graph.commit().unwrap();
+        }
+
+        let storage = Storage::open(path, 4).unwrap();
+        let graph = HnswGraph::open(storage, HnswParams::default()).unwrap();
+        assert_eq!(graph.deleted_count(), 1);
+        assert!(graph.is_deleted(1).unwrap());
+        assert!(!graph.is_deleted(0).unwrap());
+        assert_eq!(graph.read_node_record(1).unwrap().header.node_id, 1);
     }
 
     #[test]
@@ -767,7 +2129,8 @@ mod tests {
         let params = HnswParams::default();
         let graph = HnswGraph::open(storage, params).unwrap();
 
-        let record_size = graph.record_params().record_size() as u64;
+        // New graphs index compact records by node ID
+        let record_size = graph.record_params().compact_record_size() as u64;
 
         // Verify O(1) offset computation:  offsets should be evenly spaced
         let offset_0 = graph.node_offset(0);
@@ -825,6 +2188,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_entry_candidates_keep_highest_nodes() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        {
+            let mut storage = Storage::open(path, 8).unwrap();
+            for _ in 0..7 {
+                storage.insert(&[1.0; 8]).unwrap();
+            }
+            let mut graph = HnswGraph::open(storage, HnswParams::default()).unwrap();
+            for (id, layer) in [2, 0, 1, 3, 1, 2, 0].into_iter().enumerate() {
+                graph.insert(id as u64, layer).unwrap();
+            }
+
+            // The displaced entry point and the later layer-2 node replace node 2
+            assert_eq!(graph.entry_point, Some(3));
+            assert_eq!(graph.entry_candidates, vec![(0, 2), (5, 2)]);
+            graph.commit().unwrap();
+        }
+
+        let storage = Storage::open(path, 8).unwrap();
+        let graph = HnswGraph::open(storage, HnswParams::default()).unwrap();
+        assert_eq!(graph.entry_candidates, vec![(0, 2), (5, 2)]);
+    }
+
     #[test]
     #[should_panic(expected = "Node ID invariant violated")]
     fn test_insert_out_of_order_panics() {
@@ -892,6 +2281,79 @@ mod tests {
         assert_eq!(read_back.get_neighbors(0), vec![1, 2, 3]);
     }
 
+    #[test]
+    fn test_buffered_records_reach_the_file_on_commit() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        let params = HnswParams::default();
+
+        {
+            let mut storage = Storage::open(path, 4).unwrap();
+            for _ in 0..10 {
+                storage.insert(&[1.0; 4]).unwrap();
+            }
+            let mut graph = HnswGraph::open(storage, params).unwrap();
+            graph.set_write_buffer(100).unwrap();
+
+            graph.insert(0, 1).unwrap();
+            graph.insert(1, 0).unwrap();
+            let mut record = NodeRecord::new(0, 2, params.to_record_params());
+            record.set_neighbors(0, &[1]);
+            record.set_neighbors(1, &[1]);
+            graph.update_node_record(&record).unwrap();
+            assert!(graph.mark_deleted(1).unwrap());
+
+            // Reads see the buffer while the mapping still holds the old bytes
+            let offset = graph.node_offset(0) as usize;
+            let size = graph.layout.record_size as usize;
+            assert_ne!(
+                graph.storage.graph_zone(offset, size).unwrap(),
+                graph.get_node_bytes(0).unwrap()
+            );
+            assert_eq!(graph.neighbors_iter_from_mmap(0, 1).unwrap().collect::<Vec<_>>(), [1]);
+            assert!(graph.is_deleted(1).unwrap());
+
+            graph.commit().unwrap();
+            assert_eq!(
+                graph.storage.graph_zone(offset, size).unwrap(),
+                graph.get_node_bytes(0).unwrap()
+            );
+        }
+
+        let storage = Storage::open(path, 4).unwrap();
+        let graph = HnswGraph::open(storage, params).unwrap();
+        assert_eq!(graph.node_count(), 2);
+        assert_eq!(graph.read_node_record(0).unwrap().get_neighbors(1), vec![1]);
+        assert_eq!(graph.neighbors_iter_from_mmap(0, 0).unwrap().collect::<Vec<_>>(), [1]);
+        assert!(graph.is_deleted(1).unwrap());
+    }
+
+    #[test]
+    fn test_full_write_buffer_is_written_out() {
+        let (mut storage, _temp_file) = create_test_storage(4);
+        for _ in 0..10 {
+            storage.insert(&[1.0; 4]).unwrap();
+        }
+        let mut graph = HnswGraph::open(storage, HnswParams::default()).unwrap();
+        graph.set_write_buffer(3).unwrap();
+
+        graph.insert(0, 0).unwrap();
+        graph.insert(1, 0).unwrap();
+        assert_eq!(graph.write_buffer.len(), 2);
+        // Node 2's overflow record fills the buffer; its compact record comes after
+        graph.insert(2, 1).unwrap();
+        assert_eq!(graph.write_buffer.len(), 1);
+
+        graph.insert(3, 0).unwrap();
+        graph.set_write_buffer(0).unwrap();
+        assert!(graph.write_buffer.is_empty());
+        for node_id in 0..4 {
+            let offset = graph.node_offset(node_id) as usize;
+            let bytes = graph.storage.graph_zone(offset, graph.layout.record_size as usize);
+            assert_eq!(NodeHeader::from_bytes(bytes.unwrap()).unwrap().node_id, node_id);
+        }
+    }
+
     #[test]
     fn test_vector_growth_relocates_graph_without_losing_records() {
         let temp_file = NamedTempFile::new().unwrap();
@@ -914,4 +2376,139 @@ mod tests {
         assert_eq!(relocated_record.header.node_id, 0);
         assert_eq!(relocated_record.header.layer_count, 1);
     }
+
+    /// Inserts nodes `ids`, every fifth one on layer 2 with a link there
+    fn insert_two_tier_nodes(graph: &mut HnswGraph, ids: std::ops::Range<u64>) {
+        for id in ids {
+            let layer = if id % 5 == 0 { 2 } else { 0 };
+            graph.insert(id, layer).unwrap();
+            if layer > 0 {
+                let mut record = graph.read_node_record(id).unwrap();
+                record.set_neighbors(2, &[id + 1]);
+                graph.update_node_record(&record).unwrap();
+            }
+        }
+    }
+
+    fn assert_two_tier_nodes(graph: &HnswGraph, count: u64) {
+        assert_eq!(graph.node_count(), count);
+        for id in 0..count {
+            let record = graph.read_node_record(id).unwrap();
+            if id % 5 == 0 {
+                assert_eq!(record.header.layer_count, 3, "node {}", id);
+                assert_eq!(record.get_neighbors(2), vec![id + 1], "node {}", id);
+                let links: Vec<_> = graph.neighbors_iter_from_mmap(id, 2).unwrap().collect();
+                assert_eq!(links, vec![id + 1], "node {}", id);
+            } else {
+                assert_eq!(record.header.layer_count, 1, "node {}", id);
+            }
+        }
+    }
+
+    #[test]
+    fn test_two_tier_records_survive_overflow_relocation() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        let params = HnswParams::default();
+
+        {
+            let mut storage = Storage::open(path, 8).unwrap();
+            for _ in 0..200 {
+                storage.insert(&[1.0; 8]).unwrap();
+            }
+            let mut graph = HnswGraph::open(storage, params).unwrap();
+            assert!(graph.read_graph_header().unwrap().is_two_tier());
+
+            insert_two_tier_nodes(&mut graph, 0..40);
+            graph.commit().unwrap();
+
+            // Outgrowing the compact array moves the overflow region; the
+            // process then dies without committing
+            insert_two_tier_nodes(&mut graph, 40..150);
+        }
+
+        let storage = Storage::open(path, 8).unwrap();
+        let mut graph = HnswGraph::open(storage, params).unwrap();
+        assert_two_tier_nodes(&graph, 40);
+
+        insert_two_tier_nodes(&mut graph, 40..150);
+        graph.commit().unwrap();
+        drop(graph);
+
+        let storage = Storage::open(path, 8).unwrap();
+        let graph = HnswGraph::open(storage, params).unwrap();
+        assert_two_tier_nodes(&graph, 150);
+
+        let fixed_size = HnswGraph::checked_total_graph_size(150, graph.record_params()).unwrap();
+        assert!(graph.zone_bytes().unwrap() < fixed_size / 2);
+    }
+
+    #[test]
+    fn test_fixed_width_graph_stays_readable() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        let params = HnswParams::default();
+        let record_params = params.to_record_params();
+        let record_size = record_params.record_size();
+
+        // Lay a graph out as versions before two-tier records did
+        {
+            let mut storage = Storage::open(path, 8).unwrap();
+            for _ in 0..10 {
+                storage.insert(&[1.0; 8]).unwrap();
+            }
+            let mut graph = HnswGraph::open(storage, params).unwrap();
+            let start = graph.graph_start as usize;
+
+            let mut header = GraphHeader::new(record_params);
+            header.version = GraphHeader::FIXED_VERSION;
+            header.entry_point = 1;
+            header.max_layer = 1;
+            header.node_count = 3;
+            graph
+                .storage
+                .ensure_graph_capacity(start + GRAPH_HEADER_SIZE + 3 * record_size)
+                .unwrap();
+            graph
+                .storage
+                .graph_zone_mut(start, GRAPH_HEADER_SIZE)
+                .unwrap()
+                .copy_from_slice(&header.to_bytes());
+
+            for id in 0..3u64 {
+                let mut record = NodeRecord::new(id, if id == 1 { 2 } else { 1 }, record_params);
+                record.set_neighbors(0, &[(id + 1) % 3]);
+                if id == 1 {
+                    record.set_neighbors(1, &[7]);
+                }
+                let offset = start + GRAPH_HEADER_SIZE + id as usize * record_size;
+                graph
+                    .storage
+                    .graph_zone_mut(offset, record_size)
+                    .unwrap()
+                    .copy_from_slice(&record.to_bytes());
+            }
+            graph.storage.commit().unwrap();
+        }
+
+        let storage = Storage::open(path, 8).unwrap();
+        let mut graph = HnswGraph::open(storage, params).unwrap();
+        assert_eq!(graph.node_count(), 3);
+        assert_eq!(graph.node_offset(1) - graph.node_offset(0), record_size as u64);
+        assert_eq!(graph.read_node_record(1).unwrap().get_neighbors(1), vec![7]);
+        let links: Vec<_> = graph.neighbors_iter_from_mmap(2, 0).unwrap().collect();
+        assert_eq!(links, vec![0]);
+
+        // New nodes keep the fixed layout
+        graph.insert(3, 1).unwrap();
+        graph.commit().unwrap();
+        drop(graph);
+
+        let storage = Storage::open(path, 8).unwrap();
+        let graph = HnswGraph::open(storage, params).unwrap();
+        assert!(!graph.read_graph_header().unwrap().is_two_tier());
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.read_node_record(3).unwrap().header.layer_count, 2);
+        assert_eq!(graph.read_node_record(1).unwrap().get_neighbors(1), vec![7]);
+    }
 }
@@ -19,39 +19,53 @@
 //! Forward links to non-existent nodes are filtered out during linking.
 
 use crate::hnsw::graph::HnswGraph;
-use crate::hnsw::node::{INVALID_NODE_ID, NodeId, NodeRecord};
+use crate::hnsw::node::{
+    DEFAULT_M0, INVALID_NODE_ID, MAX_CONNECTIONS_0, NodeHeader, NodeId, NodeRecord,
+};
 use anyhow::Result;
 
-/// Maximum neighbors per layer (enforced at compile time for cache sizing)
-const MAX_M: usize = 32;
+/// Candidates the heuristic always considers when it has them: a full
+/// default layer-0 list (M0 = 32) plus the new node
+const MIN_CANDIDATES: usize = DEFAULT_M0 as usize + 1;
 
-/// Stack-allocated distance cache size (33x33 symmetric matrix)
-/// Supports up to M=32 neighbors + 1 new node
-const CACHE_SIZE: usize = (MAX_M + 1) * (MAX_M + 1);
+/// Most candidates the heuristic considers: a full list of the largest
+/// configurable layer 0 plus the new node
+const MAX_CANDIDATES: usize = MAX_CONNECTIONS_0 as usize + 1;
+
+/// Stack-allocated distance cache size for up to `MIN_CANDIDATES` (33x33
+/// symmetric matrix), the default configuration
+const SMALL_CACHE_SIZE: usize = MIN_CANDIDATES * MIN_CANDIDATES;
+
+/// Stack-allocated distance cache size for up to `MAX_CANDIDATES` (65x65),
+/// only used for layer-0 lists above the default size
+const LARGE_CACHE_SIZE: usize = MAX_CANDIDATES * MAX_CANDIDATES;
 
 /// Sentinel value indicating "distance not yet computed"
 const NOT_COMPUTED: f32 = f32::NAN;
 
 /// Stack-allocated lazy distance cache for diversity heuristic
-struct DistanceCache {
+///
+/// `CAPACITY` is the number of matrix cells, at least the square of the
+/// number of candidates.
+struct DistanceCache<const CAPACITY: usize> {
     /// Flat array representing symmetric matrix [i*size + j]
-    data: [f32; CACHE_SIZE],
+    data: [f32; CAPACITY],
     /// Number of candidates (dimension of square matrix)
     size: usize,
 }
 
-impl DistanceCache {
+impl<const CAPACITY: usize> DistanceCache<CAPACITY> {
     /// Create a new uninitialized cache
     #[inline]
     fn new(num_candidates: usize) -> Self {
         debug_assert!(
-            num_candidates <= MAX_M + 1,
-            "Cache overflow: {} candidates exceeds max {}",
+            num_candidates * num_candidates <= CAPACITY,
+            "Cache overflow: {} candidates exceed a cache of {} cells",
             num_candidates,
-            MAX_M + 1
+            CAPACITY
         );
 
-        Self { data: [NOT_COMPUTED; CACHE_SIZE], size: num_candidates }
+        Self { data: [NOT_COMPUTED; CAPACITY], size: num_candidates }
     }
 
     /// Get cached distance or return NAN if not computed
@@ -160,6 +174,7 @@ impl HnswGraph {
                     continue;
                 }
 
+                fail_point!(crate::failpoint::MID_BACKLINK_UPDATE);
                 self.add_backward_link_with_pruning(neighbor_id, node_id, layer)?;
             }
         }
@@ -169,8 +184,8 @@ impl HnswGraph {
 
     /// Publish node to make it visible to readers (Step C).
     ///
-    /// This method updates in-memory counters (node_count, entry_point, max_layer)
-    /// to make the previously-written node visible to readers.
+    /// This method updates in-memory counters (node_count, entry_point, max_layer,
+    /// entry_candidates) to make the previously-written node visible to readers.
     ///
     /// # Arguments
     ///
@@ -193,13 +208,194 @@ impl HnswGraph {
 
         // STEP C: Update in-memory counters
         self.node_count += 1;
+        self.promote_entry(node_id, layer_count);
+
+        Ok(())
+    }
 
-        // Update entry point and max layer if this is the highest layer node
+    /// Make `node_id` the entry point if it is the highest layer node, or
+    /// offer it as an entry candidate otherwise
+    pub(crate) fn promote_entry(&mut self, node_id: NodeId, layer_count: usize) {
         if self.entry_point.is_none() || layer_count - 1 > self.max_layer {
-            self.entry_point = Some(node_id);
+            if let Some(previous) = self.entry_point.replace(node_id) {
+                self.offer_entry_candidate(previous, self.max_layer);
+            }
             self.max_layer = layer_count - 1;
+        } else {
+            self.offer_entry_candidate(node_id, layer_count - 1);
+        }
+    }
+
+    /// Forget the entry point and candidates, so they can be promoted again
+    /// node by node while a graph is linked after the fact
+    pub(crate) fn reset_entry_points(&mut self) {
+        self.entry_point = None;
+        self.max_layer = 0;
+        self.entry_candidates.clear();
+    }
+
+    /// Link published node `node_id`, written without neighbors, to
+    /// `neighbors_per_layer`.
+    ///
+    /// Like `relink_deleted_node()` the node keeps its layer count, and its
+    /// record is rewritten before any backlink. Searches only reach the node
+    /// once other nodes link to it or it is promoted to an entry point.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the node is deleted or the layer counts differ.
+    pub fn link_unlinked_node(
+        &mut self,
+        node_id: NodeId,
+        neighbors_per_layer: &[Vec<NodeId>],
+    ) -> Result<()> {
+        if self.is_deleted(node_id)? {
+            anyhow::bail!("Node {} is deleted and cannot be linked", node_id);
+        }
+
+        let flags = self.read_node_record(node_id)?.header.flags;
+        let filtered_neighbors = self.write_fresh_record(node_id, flags, neighbors_per_layer)?;
+        self.add_backward_links(node_id, &filtered_neighbors)
+    }
+
+    /// Link deleted node `node_id` anew for the vector now stored in its slot,
+    /// and count it as live again.
+    ///
+    /// The file keeps the node deleted until the next commit has synced the
+    /// new vector, so a crash before then never exposes a half-written one.
+    ///
+    /// The node keeps its layer count, and with it the overflow record it
+    /// owns, so `neighbors_per_layer` must cover exactly those layers. Links
+    /// other nodes still hold to it are kept: they now lead to the new vector,
+    /// which searches may reach through them like through any other node.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the node is not deleted or the layer counts differ.
+    pub fn relink_deleted_node(
+        &mut self,
+        node_id: NodeId,
+        neighbors_per_layer: &[Vec<NodeId>],
+    ) -> Result<()> {
+        if !self.is_deleted(node_id)? {
+            anyhow::bail!("Node {} is not deleted and cannot be reused", node_id);
+        }
+
+        // The fresh record keeps the deleted flag until the new vector is
+        // synced (see `clear_revived_flags()`); write it before any backlink.
+        // The new vector does not inherit the pin of the deleted one either
+        let flags = NodeHeader::DELETED;
+        let filtered_neighbors = self.write_fresh_record(node_id, flags, neighbors_per_layer)?;
+        self.revive(node_id);
+        fail_point!(crate::failpoint::MID_REUSE);
+        self.add_backward_links(node_id, &filtered_neighbors)
+    }
+
+    /// Replace the neighbors of node `node_id` with `neighbors_per_layer` and
+    /// link the added ones back, returning whether any layer changed.
+    ///
+    /// The record keeps its layer count and flags and is rewritten in one
+    /// write, so a crash leaves either the old or the new neighbors. Nodes it
+    /// no longer links to keep their own links to it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the layer counts differ.
+    pub fn replace_neighbors(
+        &mut self,
+        node_id: NodeId,
+        neighbors_per_layer: &[Vec<NodeId>],
+    ) -> Result<bool> {
+        let mut record = self.read_node_record(node_id)?;
+        let layer_count = record.header.layer_count as usize;
+        if neighbors_per_layer.len() != layer_count {
+            anyhow::bail!(
+                "Layer count mismatch: expected {}, got {}",
+                layer_count,
+                neighbors_per_layer.len()
+            );
         }
 
+        let mut added = vec![Vec::new(); layer_count];
+        let mut changed = false;
+        for (layer, layer_neighbors) in neighbors_per_layer.iter().enumerate() {
+            let neighbors: Vec<NodeId> = layer_neighbors
+                .iter()
+                .copied()
+                .filter(|&id| id != node_id && id != INVALID_NODE_ID && id < self.node_count)
+                .collect();
+            let current = record.get_neighbors(layer);
+
+            // The same neighbors in another order are no change
+            let (mut sorted, mut sorted_current) = (neighbors.clone(), current.clone());
+            sorted.sort_unstable();
+            sorted_current.sort_unstable();
+            if sorted == sorted_current {
+                continue;
+            }
+
+            changed = true;
+            added[layer] = neighbors.iter().copied().filter(|id| !current.contains(id)).collect();
+            record.set_neighbors(layer, &neighbors);
+        }
+
+        if !changed {
+            return Ok(false);
+        }
+        self.update_node_record(&record)?;
+        self.add_backward_links(node_id, &added)?;
+        Ok(true)
+    }
+
+    /// Overwrite the record of existing node `node_id` with one holding
+    /// `flags` and `neighbors_per_layer`, keeping its layer count, and
+    /// return the neighbors it kept
+    fn write_fresh_record(
+        &mut self,
+        node_id: NodeId,
+        flags: u8,
+        neighbors_per_layer: &[Vec<NodeId>],
+    ) -> Result<Vec<Vec<NodeId>>> {
+        let layer_count = self.read_node_record(node_id)?.header.layer_count as usize;
+        if neighbors_per_layer.len() != layer_count {
+            anyhow::bail!(
+                "Layer count mismatch: expected {}, got {}",
+                layer_count,
+                neighbors_per_layer.len()
+            );
+        }
+
+        let filtered_neighbors: Vec<Vec<NodeId>> = neighbors_per_layer
+            .iter()
+            .map(|layer_neighbors| {
+                layer_neighbors
+                    .iter()
+                    .copied()
+                    .filter(|&id| id != node_id && id != INVALID_NODE_ID && id < self.node_count)
+                    .collect()
+            })
+            .collect();
+
+        let mut node_record = NodeRecord::new(node_id, layer_count as u8, self.record_params);
+        node_record.header.flags = flags;
+        for (layer, neighbors) in filtered_neighbors.iter().enumerate() {
+            node_record.set_neighbors(layer, neighbors);
+        }
+        self.update_node_record(&node_record)?;
+        Ok(filtered_neighbors)
+    }
+
+    /// Link each of `neighbors_per_layer` back to `node_id`
+    fn add_backward_links(
+        &mut self,
+        node_id: NodeId,
+        neighbors_per_layer: &[Vec<NodeId>],
+    ) -> Result<()> {
+        for (layer, neighbors) in neighbors_per_layer.iter().enumerate() {
+            for &neighbor_id in neighbors {
+                self.add_backward_link_with_pruning(neighbor_id, node_id, layer)?;
+            }
+        }
         Ok(())
     }
 
@@ -230,24 +426,28 @@ impl HnswGraph {
         layer: usize,
     ) -> Result<()> {
         let mut record = self.read_node_record(neighbor_id)?;
-        let current_neighbors = record.get_neighbors(layer);
+        let mut current_neighbors = record.get_neighbors(layer);
 
         // Duplicate check (idempotency)
         if current_neighbors.contains(&new_node) {
             return Ok(());
         }
 
+        // Drop links to ghost nodes rolled back on open; their IDs are being reused
+        current_neighbors.retain(|&id| id < self.node_count);
+
         let max_neighbors = self.record_params.max_neighbors(layer);
 
         // Direct insert if space available
         if current_neighbors.len() < max_neighbors {
-            record.add_neighbor(layer, new_node);
+            current_neighbors.push(new_node);
+            record.set_neighbors(layer, &current_neighbors);
             self.update_node_record(&record)?;
             return Ok(());
         }
 
         // Full - combine current neighbors + new node and apply diversity heuristic
-        let mut candidates = current_neighbors.to_vec();
+        let mut candidates = current_neighbors;
         candidates.push(new_node);
 
         let selected = self.select_neighbors_heuristic(
@@ -272,23 +472,27 @@ impl HnswGraph {
     ///
     /// # Algorithm
     ///
-    /// 1. **Input Truncation**: Limit candidates to MAX_M+1 (cache size)
+    /// 1. **Input Truncation**: Limit candidates to `max_count + 1`, but at
+    ///    least 33 (a full default layer 0 plus the new node)
     /// 2. **Local Index Mapping**: Map NodeIds to local indices [0..k)
     /// 3. **Lazy Cache**: Compute distances only when needed, store symmetrically
-    /// 4. **Diversity Phase**: Select candidates closer to base than to selected neighbors
-    /// 5. **Starvation Fallback**: Fill to at least M/2 with k-nearest
-    /// 6. **Connectivity Guarantee**: Ensure priority_node is included if close enough
+    /// 4. **Pinned Phase**: Select pinned candidates first, whatever their distance
+    /// 5. **Diversity Phase**: Select candidates closer to base than to selected neighbors
+    /// 6. **Starvation Fallback**: Fill to at least M/2 with k-nearest
+    /// 7. **Connectivity Guarantee**: Ensure priority_node is included if close enough,
+    ///    making room at the expense of an unpinned neighbor
     ///
     /// # Cache Optimization
     ///
     /// Uses stack-allocated [f32; 1089] cache (33x33 matrix) to eliminate
-    /// redundant distance calculations. Distances are computed lazily and
+    /// redundant distance calculations, or a 65x65 one for layer-0 lists
+    /// configured above 32 neighbors. Distances are computed lazily and
     /// stored symmetrically to halve total calculations.
     ///
     /// # Arguments
     ///
     /// * `base_node` - The node we're selecting neighbors for
-    /// * `candidates` - Pool of candidate neighbors (will be truncated to MAX_M+1)
+    /// * `candidates` - Pool of candidate neighbors (will be truncated, see above)
     /// * `_layer` - Layer index (currently unused, kept for future extensions)
     /// * `max_count` - Maximum number of neighbors to select
     /// * `priority_node` - Optional node to prioritize (for backward linking)
@@ -297,7 +501,7 @@ impl HnswGraph {
     ///
     /// - Cache hit: ~0.5ns (L1 cache lookup)
     /// - Cache miss: ~500ns (distance computation + mmap read)
-    /// - Worst case: O(k²) where k ≤ 33 (M + 1)
+    /// - Worst case: O(k²) where k ≤ 33 (M0 + 1) by default, 65 at most
     pub(crate) fn select_neighbors_heuristic(
         &self,
         base_node: NodeId,
@@ -315,21 +519,42 @@ impl HnswGraph {
             return Ok(candidates.to_vec());
         }
 
-        // Truncate candidates to fit in cache (MAX_M + 1 = 33)
-        let truncated_candidates: Vec<NodeId> =
-            candidates.iter().take(MAX_M + 1).copied().collect();
-
-        debug_assert!(
-            truncated_candidates.len() <= MAX_M + 1,
-            "Too many candidates for cache: {}",
-            truncated_candidates.len()
-        );
+        // Truncate candidates to fit in cache (33 by default, up to 65)
+        let limit = (max_count + 1).clamp(MIN_CANDIDATES, MAX_CANDIDATES);
+        let truncated_candidates: Vec<NodeId> = candidates.iter().take(limit).copied().collect();
+
+        if truncated_candidates.len() <= MIN_CANDIDATES {
+            self.select_with_cache::<SMALL_CACHE_SIZE>(
+                base_node,
+                &truncated_candidates,
+                max_count,
+                priority_node,
+            )
+        } else {
+            self.select_with_cache::<LARGE_CACHE_SIZE>(
+                base_node,
+                &truncated_candidates,
+                max_count,
+                priority_node,
+            )
+        }
+    }
 
+    /// The diversity heuristic of `select_neighbors_heuristic` over
+    /// candidates that fit a `CAPACITY`-cell distance cache
+    fn select_with_cache<const CAPACITY: usize>(
+        &self,
+        base_node: NodeId,
+        truncated_candidates: &[NodeId],
+        max_count: usize,
+        priority_node: Option<NodeId>,
+    ) -> Result<Vec<NodeId>> {
         // Initialize lazy distance cache
-        let mut cache = DistanceCache::new(truncated_candidates.len());
+        let mut cache = DistanceCache::<CAPACITY>::new(truncated_candidates.len());
+        let metric = self.metric();
 
         // Helper: Get distance with lazy computation and memoization
-        let get_distance = |cache: &mut DistanceCache,
+        let get_distance = |cache: &mut DistanceCache<CAPACITY>,
                             storage: &crate::Storage,
                             id1: NodeId,
                             id2: NodeId,
@@ -339,24 +564,24 @@ impl HnswGraph {
             if cache.is_computed(idx1, idx2) {
                 Ok(cache.get(idx1, idx2))
             } else {
-                let vec1 = storage.get_vector_slice(id1)?;
-                let vec2 = storage.get_vector_slice(id2)?;
-                let dist = crate::distance::euclidean_distance(vec1, vec2);
+                let vec1 = storage.stored_vector(id1)?;
+                let vec2 = storage.stored_vector(id2)?;
+                let dist = vec1.distance(&vec2, metric);
                 cache.set(idx1, idx2, dist);
                 Ok(dist)
             }
         };
 
         // Compute distances to base node for all candidates
-        let base_vector = self.storage.get_vector_slice(base_node)?;
+        let base_vector = self.storage.stored_vector(base_node)?;
         let mut distances: Vec<(NodeId, f32, usize)> = truncated_candidates
             .iter()
             .enumerate()
             .map(|(idx, &id)| {
                 let dist = self
                     .storage
-                    .get_vector_slice(id)
-                    .map(|v| crate::distance::euclidean_distance(base_vector, v))
+                    .stored_vector(id)
+                    .map(|v| v.distance(&base_vector, metric))
                     .unwrap_or(f32::MAX);
                 (id, dist, idx)
             })
@@ -365,14 +590,25 @@ impl HnswGraph {
         // Sort by distance for efficient processing
         distances.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
 
-        // DIVERSITY PHASE: Heuristic 2 with lazy cache
+        // PINNED PHASE: Pinned nodes are never pruned from a list that holds them
         let mut selected = Vec::new();
         let mut selected_indices = Vec::new();
+        for (candidate_id, _, candidate_idx) in &distances {
+            if selected.len() < max_count && self.is_pinned(*candidate_id)? {
+                selected.push(*candidate_id);
+                selected_indices.push(*candidate_idx);
+            }
+        }
+        let pinned_count = selected.len();
 
+        // DIVERSITY PHASE: Heuristic 2 with lazy cache
         for (candidate_id, candidate_dist, candidate_idx) in &distances {
             if selected.len() >= max_count {
                 break;
             }
+            if selected.contains(candidate_id) {
+                continue;
+            }
 
             // Check diversity: is candidate closer to base than to any selected neighbor?
             let mut is_diverse = true;
@@ -420,11 +656,13 @@ impl HnswGraph {
             && let Some(pos) = distances.iter().position(|(id, _, _)| *id == priority_node)
             && pos < max_count
         {
-            // Make room by removing the last selected node
-            if selected.len() >= max_count {
+            // Make room by removing the last selected node; pinned ones come first
+            if selected.len() >= max_count && selected.len() > pinned_count {
                 selected.pop();
             }
-            selected.push(priority_node);
+            if selected.len() < max_count {
+                selected.push(priority_node);
+            }
         }
 
         Ok(selected)
@@ -492,7 +730,7 @@ mod tests {
 
     #[test]
     fn test_distance_cache_symmetry() {
-        let mut cache = DistanceCache::new(5);
+        let mut cache = DistanceCache::<SMALL_CACHE_SIZE>::new(5);
 
         cache.set(0, 1, 1.5);
         assert_eq!(cache.get(0, 1), 1.5);
@@ -534,4 +772,26 @@ mod tests {
         // Priority node should be included if it's close enough
         // (exact behavior depends on vector distances)
     }
+
+    #[test]
+    fn test_select_neighbors_heuristic_keeps_pinned() {
+        let (mut graph, _temp) = create_test_graph(128);
+        for id in 0..20 {
+            graph.write_node_and_backlinks(id, 1, &[vec![]]).unwrap();
+            graph.publish_node(id, 1).unwrap();
+        }
+
+        let candidates: Vec<NodeId> = (1..20).collect();
+        let unpinned = graph.select_neighbors_heuristic(0, &candidates, 0, 4, None).unwrap();
+        let dropped = candidates.iter().copied().find(|id| !unpinned.contains(id)).unwrap();
+
+        assert!(graph.set_pinned(dropped, true).unwrap());
+        let result = graph.select_neighbors_heuristic(0, &candidates, 0, 4, None).unwrap();
+        assert!(result.len() <= 4);
+        assert!(result.contains(&dropped));
+
+        // A priority node does not displace a pinned one
+        let result = graph.select_neighbors_heuristic(0, &candidates, 0, 1, Some(1)).unwrap();
+        assert_eq!(result, vec![dropped]);
+    }
 }
@@ -1,21 +1,26 @@
 mod builder;
+mod compressed;
 mod graph;
 mod link;
 pub mod node;
 mod search;
+mod write_buffer;
 
-pub use builder::HnswBuilder;
+pub use builder::{BuildProgress, HnswBuilder};
 pub use graph::HnswGraph;
 
 #[cfg(any(test, feature = "internals"))]
-pub use graph::GraphHeader;
-pub use node::NodeRecordParams;
+pub use graph::{GraphHeader, OverflowHeader};
+pub use node::{MAX_CONNECTIONS_0, NodeRecordParams};
 
 #[cfg(any(test, feature = "internals"))]
 pub use node::{
     INVALID_NODE_ID, Node, NodeHeader, NodeId, NodeRecord, Offset, compute_node_offset,
 };
-pub use search::SearchResult;
+pub(crate) use search::{BudgetMeter, SearchScratch, write_results};
+pub use search::{EarlyTermination, SearchBudget, SearchResult};
+
+use crate::distance::Distance;
 
 /// Select an HNSW layer from a uniform random sample using exponential decay.
 #[inline]
@@ -24,12 +29,32 @@ pub(crate) fn layer_from_uniform(uniform: f32, ml: f32, max_layers: u8) -> usize
     layer.min(max_layers.saturating_sub(1) as usize)
 }
 
+/// Node count at which an adaptive construction beam reaches `ef_construction`
+const ADAPTIVE_EF_FULL_SIZE: f64 = 1_000_000.0;
+
+/// Construction beam for inserting into a graph of `node_count` nodes when it
+/// adapts to the graph size: `ef_construction` scaled by
+/// `ln(node_count) / ln(1_000_000)`, and at least `min_ef` (the layer-0
+/// neighbor count, so a new node can still fill its list)
+///
+/// A beam wider than the graph visits the whole graph anyway, and a small
+/// graph is navigable from anywhere, so the first tens of thousands of inserts
+/// lose little recall to the narrower beam.
+pub(crate) fn adaptive_ef(ef_construction: usize, node_count: u64, min_ef: usize) -> usize {
+    let scale = ((node_count as f64 + 2.0).ln() / ADAPTIVE_EF_FULL_SIZE.ln()).min(1.0);
+    let ef = (ef_construction as f64 * scale).ceil() as usize;
+    ef.clamp(min_ef.min(ef_construction), ef_construction)
+}
+
 /// HNSW construction parameters
 #[derive(Debug, Clone, Copy)]
 pub struct HnswParams {
     /// Maximum connections per node (M)
     pub max_connections: u16,
 
+    /// Maximum connections per node at layer 0 (M0); `None` means `2 * M`
+    pub max_connections_0: Option<u16>,
+
     /// Construction quality (efConstruction)
     pub ef_construction: usize,
 
@@ -41,16 +66,28 @@ pub struct HnswParams {
 
     /// Maximum layers (determines fixed record size)
     pub max_layers: u8,
+
+    /// Distance function used for construction and search
+    pub distance: Distance,
+
+    /// Vectors are stored at unit length (see `IndexOptions::normalize`).
+    ///
+    /// Switches Euclidean distance to the dot product kernel. Stored in the
+    /// file: an index holding vectors keeps the value it was created with.
+    pub normalize: bool,
 }
 
 impl Default for HnswParams {
     fn default() -> Self {
         Self {
             max_connections: 16,
+            max_connections_0: None,
             ef_construction: 200,
             ef_search: 50,
             ml: 1.0 / (16.0_f32).ln(),
             max_layers: 16,
+            distance: Distance::Euclidean,
+            normalize: false,
         }
     }
 }
@@ -59,11 +96,11 @@ impl HnswParams {
     /// Convert to NodeRecordParams for fixed-size record allocation
     #[must_use]
     pub const fn to_record_params(&self) -> NodeRecordParams {
-        NodeRecordParams::new(
-            self.max_connections,
-            self.max_connections * 2, // m0 = 2 * M
-            self.max_layers,
-        )
+        let m0 = match self.max_connections_0 {
+            Some(m0) => m0,
+            None => self.max_connections.saturating_mul(2),
+        };
+        NodeRecordParams::new(self.max_connections, m0, self.max_layers)
     }
 }
 
@@ -88,4 +125,17 @@ mod tests {
         let layer = layer_from_uniform(1.0, 1.0, 16);
         assert_eq!(layer, 0);
     }
+
+    #[test]
+    fn adaptive_ef_grows_with_log_of_size() {
+        assert_eq!(adaptive_ef(200, 0, 32), 32);
+        assert_eq!(adaptive_ef(200, 1_000, 32), 101);
+        assert_eq!(adaptive_ef(200, 10_000, 32), 134);
+        assert_eq!(adaptive_ef(200, 100_000, 32), 167);
+        assert_eq!(adaptive_ef(200, 1_000_000, 32), 200);
+        assert_eq!(adaptive_ef(200, u64::MAX, 32), 200);
+
+        // Never above the configured beam, even when the floor is
+        assert_eq!(adaptive_ef(16, 10, 32), 16);
+    }
 }
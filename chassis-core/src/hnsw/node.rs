@@ -8,6 +8,10 @@
 //! The record reserves space for the **maximum possible** neighbors across all layers.
 //!  Unused neighbor slots are filled with `INVALID_NODE_ID`.
 //!
+//! Two-tier graphs store the same record split in two: a compact record with
+//! the header and layer 0, addressed by node ID, and for the few nodes above
+//! layer 0 an overflow record with the upper layers (see `HnswGraph`).
+//!
 //! # Layout Invariants
 //!
 //! - Record size is immutable once an index is created
@@ -35,6 +39,10 @@ pub const DEFAULT_MAX_LAYERS: u8 = 16;
 /// Maximum connections at layer 0 (typically 2*M)
 pub const DEFAULT_M0: u16 = DEFAULT_M * 2;
 
+/// Largest layer-0 connection count that can be configured
+/// (`IndexOptions::max_connections_0`)
+pub const MAX_CONNECTIONS_0: u16 = 64;
+
 /// Fixed-size on-disk node header.
 ///
 /// # Layout (16 bytes, 8-byte aligned)
@@ -44,8 +52,9 @@ pub const DEFAULT_M0: u16 = DEFAULT_M * 2;
 /// ------  ----  -----
 /// 0       8     node_id:  NodeId
 /// 8       1     layer_count: u8 (highest layer this node belongs to + 1)
-/// 9       1     flags: u8 (reserved for future use)
-/// 10      6     _padding: [u8; 6]
+/// 9       1     flags: u8 (DELETED = 0x01, PINNED = 0x02)
+/// 10      2     _padding: [u8; 2]
+/// 12      4     overflow_slot: u32 (two-tier graphs, nodes above layer 0)
 /// ```
 #[repr(C, align(8))]
 #[derive(Debug, Clone, Copy)]
@@ -56,11 +65,14 @@ pub struct NodeHeader {
     /// Number of layers this node participates in (1 = layer 0 only)
     pub layer_count: u8,
 
-    /// Flags for future extensions (deleted flag, etc.)
+    /// Node flags (`DELETED`, `PINNED`)
     pub flags: u8,
 
     /// Reserved for alignment
-    _padding: [u8; 6],
+    _padding: [u8; 2],
+
+    /// Overflow record holding the upper layers, in two-tier graphs (0 otherwise)
+    pub overflow_slot: u32,
 }
 
 impl NodeHeader {
@@ -70,7 +82,7 @@ impl NodeHeader {
     /// Create a new node header
     #[must_use]
     pub const fn new(node_id: NodeId, layer_count: u8) -> Self {
-        Self { node_id, layer_count, flags: 0, _padding: [0; 6] }
+        Self { node_id, layer_count, flags: 0, _padding: [0; 2], overflow_slot: 0 }
     }
 
     /// Read header from bytes with validation.
@@ -126,15 +138,33 @@ impl NodeHeader {
         unsafe { std::ptr::read_unaligned(bytes.as_ptr().cast::<Self>()) }
     }
 
+    /// Byte offset of `flags` within a serialized header
+    pub(crate) const FLAGS_OFFSET: usize = 9;
+
+    /// `flags` bit marking a deleted node
+    pub(crate) const DELETED: u8 = 0x01;
+
+    /// `flags` bit marking a node the diversity heuristic never prunes
+    pub(crate) const PINNED: u8 = 0x02;
+
+    /// Byte offset of `overflow_slot` within a serialized header
+    pub(crate) const OVERFLOW_SLOT_OFFSET: usize = 12;
+
     /// Check if the node is marked as deleted
     #[must_use]
     pub const fn is_deleted(&self) -> bool {
-        self.flags & 0x01 != 0
+        self.flags & Self::DELETED != 0
     }
 
     /// Mark the node as deleted
     pub fn set_deleted(&mut self) {
-        self.flags |= 0x01;
+        self.flags |= Self::DELETED;
+    }
+
+    /// Check if the node is pinned in the neighbor lists that hold it
+    #[must_use]
+    pub const fn is_pinned(&self) -> bool {
+        self.flags & Self::PINNED != 0
     }
 }
 
@@ -201,6 +231,22 @@ impl NodeRecordParams {
         (total + 7) & !7
     }
 
+    /// Size of a compact record: the header and the layer 0 neighbors.
+    ///
+    /// Two-tier graphs store one per node; the upper layers of a node that
+    /// has them go to an overflow record of `overflow_record_size()` bytes.
+    #[must_use]
+    pub const fn compact_record_size(&self) -> usize {
+        NodeHeader::SIZE + self.m0 as usize * mem::size_of::<NodeId>()
+    }
+
+    /// Size of an overflow record: the owning node ID, then the neighbors of
+    /// layers 1 to `max_layers - 1` as laid out in a full record.
+    #[must_use]
+    pub const fn overflow_record_size(&self) -> usize {
+        mem::size_of::<NodeId>() + self.record_size() - self.compact_record_size()
+    }
+
     /// Calculate the offset of a specific layer's neighbor array within a record.
     ///
     /// Returns `None` if the layer is out of bounds.
@@ -557,6 +603,10 @@ mod tests {
         // Layers 1-3: 3 * 16 * 8 = 384 bytes
         // Total: 16 + 256 + 384 = 656 bytes (already 8-byte aligned)
         assert_eq!(params.record_size(), 656);
+
+        // Two-tier split: header and layer 0, then owner ID and layers 1-3
+        assert_eq!(params.compact_record_size(), 272);
+        assert_eq!(params.overflow_record_size(), 8 + 384);
     }
 
     #[test]
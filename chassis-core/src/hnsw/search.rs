@@ -2,10 +2,12 @@
 //!
 //! # Performance Optimizations
 //!
-//! - Dense visited filter (no HashSet in hot path)
+//! - Dense visited filter (no HashSet in hot path), pooled across searches
 //! - Zero-allocation neighbor iteration via `neighbors_iter_from_mmap()`
-//! - Zero-copy distance computation via `compute_distance_zero_copy()`
+//! - Zero-copy distance computation via `query_distance()`, with the query
+//!   prepared (binarized for Hamming distance) once per search
 //! - NaN-safe ordering with `f32::total_cmp`
+//! - Fixed-size sorted array instead of heaps for small `ef` (`SMALL_EF`)
 //!
 //! # Safety Guarantees
 //!
@@ -14,11 +16,18 @@
 //! - Wait-free multi-reader semantics (immutable &self)
 //! - Deterministic performance
 
+use crate::CancelToken;
+use crate::element::Query;
+use crate::error::{ErrorKind, Tagged};
 use crate::hnsw::graph::HnswGraph;
 use crate::hnsw::node::NodeId;
+use crate::instrument;
 use anyhow::Result;
 use std::cmp::Reverse;
 use std::collections::BinaryHeap;
+use std::sync::{Mutex, PoisonError};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
 
 /// Search result with distance
 #[derive(Debug, Clone)]
@@ -53,32 +62,51 @@ impl Ord for SearchResult {
     }
 }
 
-/// Dense visited filter using a BitSet for maximum cache locality.
+/// Dense visited filter with generation stamps.
 ///
 /// # Design
 ///
-/// Uses a packed bit array (1 bit per node) to fit more status flags into
-/// CPU cache lines.
+/// Stores the generation in which each node was last visited (one `u32` per
+/// node). A node counts as visited when its stamp equals the current
+/// generation, so `reset()` starts a new search by bumping the generation
+/// instead of clearing the array.
 ///
 /// # Performance
 ///
-/// - Memory: 125KB for 1M nodes (vs 1MB for Vec<bool>)
-/// - Cache Density: 512 nodes per cache line (vs 64)
-/// - Init Cost: ~8x faster allocation/zeroing
+/// - Memory: 4MB for 1M nodes, allocated once per pooled filter
+/// - Reset: O(1), except a full clear every 2^32 - 1 searches on wraparound
+/// - Visit: one load and compare, no hashing
+#[derive(Debug, Default)]
 pub struct VisitedFilter {
-    /// Dense bit array: 1 bit per node. Stores 64 nodes per u64.
-    data: Vec<u64>,
+    /// Generation each node was last visited in; 0 never matches
+    stamps: Vec<u32>,
+    /// Stamp of nodes visited by the current search
+    generation: u32,
     /// Capacity track to avoid checking len() bounds repeatedly
     capacity: usize,
 }
 
 impl VisitedFilter {
     /// Create a new visited filter for a graph with `node_count` nodes
-    #[inline]
+    #[cfg(test)]
     pub fn new(node_count: usize) -> Self {
-        // Calculate number of u64s needed: ceil(N / 64)
-        let num_u64s = node_count.div_ceil(64);
-        Self { data: vec![0; num_u64s], capacity: node_count }
+        Self { stamps: vec![0; node_count], generation: 1, capacity: node_count }
+    }
+
+    /// Clear all marks and resize for `node_count` nodes, keeping the allocation
+    #[inline]
+    pub fn reset(&mut self, node_count: usize) {
+        if self.stamps.len() < node_count {
+            self.stamps.resize(node_count, 0);
+        }
+        self.capacity = node_count;
+
+        self.generation = self.generation.wrapping_add(1);
+        if self.generation == 0 {
+            // Stamps from 2^32 - 1 searches ago would match again
+            self.stamps.fill(0);
+            self.generation = 1;
+        }
     }
 
     /// Mark a node as visited.
@@ -94,16 +122,12 @@ impl VisitedFilter {
             return false;
         }
 
-        let word_idx = idx >> 6;
-        let bit_idx = idx & 63;
-        let mask = 1u64 << bit_idx;
-
-        let word = unsafe { self.data.get_unchecked_mut(word_idx) };
+        let stamp = unsafe { self.stamps.get_unchecked_mut(idx) };
 
-        if *word & mask != 0 {
+        if *stamp == self.generation {
             false // Already visited (Return false to match old API)
         } else {
-            *word |= mask; // Mark as visited
+            *stamp = self.generation; // Mark as visited
             true // Newly visited (Success)
         }
     }
@@ -117,12 +141,393 @@ impl VisitedFilter {
             return false;
         }
 
-        let word_idx = idx >> 6;
-        let bit_idx = idx & 63;
-        let mask = 1u64 << bit_idx;
+        let stamp = unsafe { self.stamps.get_unchecked(idx) };
+        *stamp == self.generation
+    }
+
+    /// Heap bytes held by the stamps
+    fn heap_bytes(&self) -> usize {
+        self.stamps.capacity() * std::mem::size_of::<u32>()
+    }
+}
+
+/// Buffers reused across searches run one after another.
+///
+/// `search_batch` keeps one per thread so consecutive queries don't
+/// reallocate the visited filter and heaps.
+#[derive(Debug, Default)]
+pub(crate) struct SearchScratch {
+    visited: VisitedFilter,
+    candidates: BinaryHeap<Reverse<SearchResult>>,
+    results: BinaryHeap<SearchResult>,
+
+    /// Results of the last layer search, nearest first
+    output: Vec<SearchResult>,
+}
+
+impl SearchScratch {
+    /// Heap bytes held by the filter and heaps
+    fn heap_bytes(&self) -> usize {
+        let heaps = self.candidates.capacity() + self.results.capacity() + self.output.capacity();
+        self.visited.heap_bytes() + heaps * std::mem::size_of::<SearchResult>()
+    }
+}
+
+/// Scratch buffers left by finished searches, owned by the graph.
+///
+/// Each search takes one (or starts an empty one when all are in use) and
+/// returns it when done, so repeated searches do not allocate. The pool holds
+/// at most one scratch per concurrent search.
+#[derive(Debug, Default)]
+pub(crate) struct ScratchPool(Mutex<Vec<SearchScratch>>);
+
+impl ScratchPool {
+    fn take(&self) -> SearchScratch {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).pop().unwrap_or_default()
+    }
+
+    fn put(&self, scratch: SearchScratch) {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).push(scratch);
+    }
+
+    /// Drop all pooled buffers
+    pub(crate) fn clear(&self) {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+
+    /// Heap bytes held by pooled buffers
+    pub(crate) fn heap_bytes(&self) -> usize {
+        let pool = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        pool.iter().map(SearchScratch::heap_bytes).sum()
+    }
+}
+
+/// Copy as many of `results` as fit into `ids` and `distances`, returning
+/// how many were copied
+pub(crate) fn write_results(
+    results: &[SearchResult],
+    ids: &mut [u64],
+    distances: &mut [f32],
+) -> usize {
+    let slots = ids.iter_mut().zip(distances.iter_mut());
+    let mut written = 0;
+    for (result, (id, distance)) in results.iter().zip(slots) {
+        *id = result.id;
+        *distance = result.distance;
+        written += 1;
+    }
+    written
+}
+
+/// Opt-in rules that end a base-layer search before the candidate queue is exhausted
+///
+/// The standard search stops once the nearest unexpanded candidate is farther
+/// than the worst of the `ef` results. On easy queries the results settle long
+/// before that, and the remaining expansions only confirm them. Both rules
+/// apply once `ef` results have been found:
+///
+/// - `patience`: stop after this many consecutive expansions that added
+///   nothing to the results (`0` disables the rule).
+/// - `distance_ratio`: stop once the nearest candidate is farther than
+///   `distance_ratio` times the worst result. `1.0` matches the standard rule;
+///   smaller values stop earlier.
+///
+/// Either rule can drop true neighbors that sit behind a far candidate, so
+/// measure recall (see `chassis_core::eval`) before enabling them. On 20k
+/// clustered 64-dim vectors with `ef = 64`, `k = 10`, patience 8 saved 14% of
+/// search time for 0.3 points of recall, patience 4 saved 32% for 0.9 points,
+/// and a ratio of 0.95 saved 40% for 3 points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EarlyTermination {
+    /// Consecutive non-improving expansions before stopping (`0` = off)
+    pub patience: usize,
+
+    /// Stop when `candidate > distance_ratio * worst result` (`1.0` = off)
+    pub distance_ratio: f32,
+}
+
+impl Default for EarlyTermination {
+    fn default() -> Self {
+        Self { patience: 6, distance_ratio: 1.0 }
+    }
+}
+
+impl EarlyTermination {
+    /// Whether to stop before expanding a candidate at `candidate` distance
+    #[inline]
+    fn should_stop(&self, candidate: f32, worst: f32, stale: usize) -> bool {
+        (self.patience > 0 && stale >= self.patience) || candidate > worst * self.distance_ratio
+    }
+}
+
+/// Hard cap on the work of one search, for latency limits on slow cores
+///
+/// Once the budget is spent the search stops expanding candidates and returns
+/// the best results found so far. A tight budget lowers recall and can return
+/// fewer than `k` results; the search always gets as far as the distance to
+/// the entry point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchBudget {
+    /// Wall-clock microseconds since the search started, checked before each
+    /// candidate expansion. Never runs out on wasm, where no clock is available.
+    Micros(u64),
+
+    /// Distances computed between the query and stored vectors, on all layers
+    DistanceComputations(u64),
+}
+
+/// Budget spent by one running search
+pub(crate) struct BudgetMeter {
+    budget: Option<SearchBudget>,
+
+    /// Checked before each candidate expansion
+    cancel: Option<CancelToken>,
+
+    /// Distances computed so far
+    computations: u64,
+
+    /// Node reached on the layer above and its distance, which the next
+    /// layer starts from without computing it again
+    carried: Option<(NodeId, f32)>,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    start: Instant,
+}
+
+impl BudgetMeter {
+    pub(crate) fn new(budget: Option<SearchBudget>) -> Self {
+        Self {
+            budget,
+            cancel: None,
+            computations: 0,
+            carried: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            start: Instant::now(),
+        }
+    }
+
+    /// A meter that never runs out
+    pub(crate) fn unlimited() -> Self {
+        Self::new(None)
+    }
+
+    /// Stop the search with `Cancelled` once `cancel` is cancelled
+    pub(crate) fn with_cancel(mut self, cancel: Option<CancelToken>) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Fail with `Cancelled` if the search's token was cancelled
+    #[inline]
+    pub(crate) fn check_cancelled(&self) -> Result<()> {
+        match &self.cancel {
+            Some(cancel) => cancel.check("Search"),
+            None => Ok(()),
+        }
+    }
+
+    /// Record one distance computation
+    #[inline]
+    pub(crate) fn spend(&mut self) {
+        self.computations += 1;
+    }
+
+    /// Distance from the query to `entry`, the start of a layer search
+    ///
+    /// Reuses the distance of the node carried down from the layer above, so
+    /// descending does not pay for it twice.
+    #[inline]
+    pub(crate) fn entry_distance(
+        &mut self,
+        entry: NodeId,
+        compute: impl FnOnce() -> Result<f32>,
+    ) -> Result<f32> {
+        match self.carried {
+            Some((node, distance)) if node == entry => Ok(distance),
+            _ => {
+                let distance = compute()?;
+                self.spend();
+                Ok(distance)
+            }
+        }
+    }
+
+    /// Record the node a layer search ended at, for `entry_distance()`
+    #[inline]
+    pub(crate) fn carry(&mut self, node: NodeId, distance: f32) {
+        self.carried = Some((node, distance));
+    }
+
+    /// Whether another distance computation fits the budget (cheap: no clock read)
+    #[inline]
+    pub(crate) fn can_compute(&self) -> bool {
+        match self.budget {
+            Some(SearchBudget::DistanceComputations(max)) => self.computations < max,
+            _ => true,
+        }
+    }
+
+    /// Whether the search must stop expanding candidates
+    #[inline]
+    pub(crate) fn exhausted(&self) -> bool {
+        match self.budget {
+            None => false,
+            Some(SearchBudget::DistanceComputations(max)) => self.computations >= max,
+            #[cfg(not(target_arch = "wasm32"))]
+            Some(SearchBudget::Micros(max)) => self.start.elapsed().as_micros() >= u128::from(max),
+            #[cfg(target_arch = "wasm32")]
+            Some(SearchBudget::Micros(_)) => false,
+        }
+    }
+}
+
+/// Largest `ef` searched with `SmallFrontier` instead of two heaps.
+///
+/// Typical "top 3 suggestions" queries use `ef` well below this; at this size
+/// shifting a few array slots is cheaper than heap bookkeeping.
+const SMALL_EF: usize = 16;
+
+/// The `ef` nearest nodes found so far, sorted nearest first, each marked
+/// once its neighbors have been expanded.
+///
+/// Replaces both heaps of `search_layer_bounded`: a node dropped from the
+/// array is farther than `ef` found nodes, so the heap search would stop
+/// before expanding it anyway. This only holds when every visited node is
+/// eligible as a result (`ResultFilter::ALL`).
+struct SmallFrontier {
+    /// `(id, distance, expanded)`; only the first `len` entries are live
+    entries: [(NodeId, f32, bool); SMALL_EF],
+    len: usize,
+    ef: usize,
+}
+
+impl SmallFrontier {
+    fn new(ef: usize) -> Self {
+        debug_assert!((1..=SMALL_EF).contains(&ef));
+        Self { entries: [(0, 0.0, false); SMALL_EF], len: 0, ef }
+    }
+
+    /// Insert a node if it is among the `ef` nearest, dropping the farthest
+    ///
+    /// Returns whether the node was kept.
+    fn admit(&mut self, id: NodeId, distance: f32) -> bool {
+        let position = self.entries[..self.len]
+            .iter()
+            .position(|entry| distance.total_cmp(&entry.1) == std::cmp::Ordering::Less)
+            .unwrap_or(self.len);
+        if position == self.ef {
+            return false;
+        }
+
+        let end = self.len.min(self.ef - 1);
+        self.entries.copy_within(position..end, position + 1);
+        self.entries[position] = (id, distance, false);
+        self.len = end + 1;
+        true
+    }
+
+    /// Nearest node not yet expanded, marked as expanded
+    fn next_unexpanded(&mut self) -> Option<(NodeId, f32)> {
+        let entry = self.entries[..self.len].iter_mut().find(|entry| !entry.2)?;
+        entry.2 = true;
+        Some((entry.0, entry.1))
+    }
+
+    /// Distance of the farthest kept node, once `ef` nodes are kept
+    fn full_worst(&self) -> Option<f32> {
+        (self.len == self.ef).then(|| self.entries[self.len - 1].1)
+    }
+
+    /// Replace the contents of `out` with the frontier, nearest first
+    fn write_results(&self, out: &mut Vec<SearchResult>) {
+        out.clear();
+        out.extend(
+            self.entries[..self.len].iter().map(|&(id, distance, _)| SearchResult { id, distance }),
+        );
+    }
+}
+
+/// Which visited nodes a search may return.
+///
+/// Filtered-out nodes are still traversed: they may be the only route to the
+/// nodes that are returned.
+#[derive(Clone, Copy)]
+pub(crate) struct ResultFilter<'a> {
+    /// Only nodes with `id < id_limit` are returned
+    pub id_limit: NodeId,
+
+    /// Skip nodes marked deleted
+    pub skip_deleted: bool,
+
+    /// Only nodes the predicate accepts are returned (such as a tag filter)
+    pub predicate: Option<&'a dyn Fn(NodeId) -> bool>,
+}
+
+impl ResultFilter<'_> {
+    /// Every node, deleted or not (graph construction still links to deleted nodes)
+    pub const ALL: Self = Self { id_limit: NodeId::MAX, skip_deleted: false, predicate: None };
+
+    fn accepts_all(&self) -> bool {
+        self.id_limit == NodeId::MAX && !self.skip_deleted && self.predicate.is_none()
+    }
+}
+
+/// Base-layer exploration state for range search.
+struct RangeFrontier {
+    radius: f32,
+    ef: usize,
+
+    /// Nodes to expand, nearest first
+    candidates: BinaryHeap<Reverse<SearchResult>>,
+
+    /// The `ef` closest out-of-range nodes seen (max-heap, worst on top)
+    stepping_stones: BinaryHeap<SearchResult>,
+
+    /// In-range nodes found so far
+    results: Vec<SearchResult>,
+}
+
+impl RangeFrontier {
+    fn new(radius: f32, ef: usize) -> Self {
+        Self {
+            radius,
+            ef,
+            candidates: BinaryHeap::new(),
+            stepping_stones: BinaryHeap::new(),
+            results: Vec::new(),
+        }
+    }
+
+    /// Record a newly visited node and queue it for expansion if it is in
+    /// range or among the `ef` closest out-of-range nodes. Only `eligible`
+    /// nodes become results.
+    fn admit(&mut self, id: NodeId, distance: f32, eligible: bool) {
+        let node = SearchResult { id, distance };
+
+        if distance <= self.radius {
+            if eligible {
+                self.results.push(node.clone());
+            }
+        } else if self.stepping_stones.len() < self.ef
+            || self.stepping_stones.peek().is_some_and(|worst| node < *worst)
+        {
+            self.stepping_stones.push(node.clone());
+            if self.stepping_stones.len() > self.ef {
+                self.stepping_stones.pop();
+            }
+        } else {
+            return;
+        }
 
-        let word = unsafe { self.data.get_unchecked(word_idx) };
-        (*word & mask) != 0
+        self.candidates.push(Reverse(node));
+    }
+
+    /// `true` once `candidate` is outside the radius and farther than every
+    /// retained stepping stone: nothing closer remains to expand.
+    fn exhausted_at(&self, candidate: &SearchResult) -> bool {
+        candidate.distance > self.radius
+            && self.stepping_stones.len() >= self.ef
+            && self.stepping_stones.peek().is_some_and(|worst| *candidate > *worst)
     }
 }
 
@@ -148,29 +553,277 @@ impl HnswGraph {
     /// - O(node_count) space for visited filter
     /// - Zero allocations in hot path (after setup)
     pub fn search(&self, query: &[f32], k: usize, ef: usize) -> Result<Vec<SearchResult>> {
+        self.search_bounded(query, k, ef, NodeId::MAX)
+    }
+
+    /// Search for k nearest neighbors among nodes with `id < id_limit`.
+    ///
+    /// Nodes at or above the limit are still traversed (they may be the only
+    /// route to older nodes) but never returned. Used to hide nodes that have
+    /// not been flushed yet. Deleted nodes are never returned either.
+    pub fn search_bounded(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef: usize,
+        id_limit: NodeId,
+    ) -> Result<Vec<SearchResult>> {
+        self.search_adaptive(query, k, ef, id_limit, None)
+    }
+
+    /// `search_bounded` with optional early termination of the base layer.
+    ///
+    /// See `EarlyTermination`; `None` gives the standard search.
+    pub fn search_adaptive(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef: usize,
+        id_limit: NodeId,
+        termination: Option<EarlyTermination>,
+    ) -> Result<Vec<SearchResult>> {
+        self.with_scratch(|scratch| {
+            let meter = BudgetMeter::unlimited();
+            self.search_adaptive_in(scratch, query, k, ef, id_limit, termination, meter)
+        })
+    }
+
+    /// `search_adaptive` that stops expanding candidates once the budget of
+    /// `meter` is spent, and fails once its token is cancelled. Only nodes
+    /// `predicate` accepts, if given, are returned.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn search_budgeted(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef: usize,
+        id_limit: NodeId,
+        predicate: Option<&dyn Fn(NodeId) -> bool>,
+        termination: Option<EarlyTermination>,
+        mut meter: BudgetMeter,
+    ) -> Result<Vec<SearchResult>> {
+        let query = &Query::new(query, self.metric());
+        let filter = ResultFilter { predicate, ..self.result_filter(id_limit) };
+        self.with_scratch(|scratch| {
+            self.search_filtered_in(scratch, query, k, ef, filter, termination, &mut meter)?;
+            Ok(self.reported(std::mem::take(&mut scratch.output)))
+        })
+    }
+
+    /// Run `search` with a scratch from the pool, returning it afterwards
+    fn with_scratch<T>(&self, search: impl FnOnce(&mut SearchScratch) -> T) -> T {
+        let mut scratch = self.scratch_pool.take();
+        let result = search(&mut scratch);
+        self.scratch_pool.put(scratch);
+        result
+    }
+
+    /// `search_budgeted` using the buffers in `scratch`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn search_adaptive_in(
+        &self,
+        scratch: &mut SearchScratch,
+        query: &[f32],
+        k: usize,
+        ef: usize,
+        id_limit: NodeId,
+        termination: Option<EarlyTermination>,
+        mut meter: BudgetMeter,
+    ) -> Result<Vec<SearchResult>> {
+        let query = &Query::new(query, self.metric());
+        let filter = self.result_filter(id_limit);
+        self.search_filtered_in(scratch, query, k, ef, filter, termination, &mut meter)?;
+        Ok(self.reported(std::mem::take(&mut scratch.output)))
+    }
+
+    /// `search_bounded` writing the `ids.len()` nearest results (at most) to
+    /// `ids` and `distances` instead of a new vector
+    ///
+    /// Reuses a pooled scratch, so once the pool is warm a search allocates
+    /// nothing. Returns the number of results written.
+    pub(crate) fn search_into(
+        &self,
+        query: &[f32],
+        ef: usize,
+        id_limit: NodeId,
+        ids: &mut [u64],
+        distances: &mut [f32],
+    ) -> Result<usize> {
+        let query = &Query::new(query, self.metric());
+        let k = ids.len().min(distances.len());
+        self.with_scratch(|scratch| {
+            let filter = self.result_filter(id_limit);
+            let mut meter = BudgetMeter::unlimited();
+            self.search_filtered_in(scratch, query, k, ef, filter, None, &mut meter)?;
+            let count = write_results(&scratch.output, ids, distances);
+            let metric = self.metric();
+            distances[..count].iter_mut().for_each(|d| *d = metric.reported(*d));
+            Ok(count)
+        })
+    }
+
+    /// `search_bounded` that only returns nodes accepted by `predicate`.
+    ///
+    /// The predicate is applied during the base-layer traversal, so the
+    /// search keeps exploring until it has `ef` accepted nodes (or runs out of
+    /// graph) instead of filtering a fixed candidate list afterwards. Rejected
+    /// nodes are still expanded as routes to accepted ones.
+    pub(crate) fn search_with_predicate(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef: usize,
+        id_limit: NodeId,
+        predicate: &dyn Fn(NodeId) -> bool,
+    ) -> Result<Vec<SearchResult>> {
+        let query = &Query::new(query, self.metric());
+        let filter = ResultFilter { predicate: Some(predicate), ..self.result_filter(id_limit) };
+        self.with_scratch(|scratch| {
+            let mut meter = BudgetMeter::unlimited();
+            self.search_filtered_in(scratch, query, k, ef, filter, None, &mut meter)?;
+            Ok(self.reported(std::mem::take(&mut scratch.output)))
+        })
+    }
+
+    /// `search_adaptive_in` with an explicit result filter, leaving the
+    /// results in `scratch.output`
+    #[allow(clippy::too_many_arguments)]
+    fn search_filtered_in(
+        &self,
+        scratch: &mut SearchScratch,
+        query: &Query<'_>,
+        k: usize,
+        ef: usize,
+        filter: ResultFilter,
+        termination: Option<EarlyTermination>,
+        meter: &mut BudgetMeter,
+    ) -> Result<()> {
         if self.entry_point.is_none() {
-            return Ok(Vec::new());
+            scratch.output.clear();
+            return Ok(());
         }
 
         // Enforce ef >= k silently (no error, no surprise)
         let ef = ef.max(k);
 
         let entry = self.entry_point.unwrap();
-        let mut current_layer = self.max_layer;
+        let current = self.descend_in(&mut scratch.visited, query, entry, meter)?;
 
-        // Greedy search from top layer to layer 1
-        let mut current = entry;
-        while current_layer > 0 {
-            current = self.search_layer_greedy(query, current, current_layer)?;
-            current_layer -= 1;
-        }
+        // A ratio of distances is a ratio of ranking distances squared
+        let termination = termination.map(|t| EarlyTermination {
+            distance_ratio: self.metric().ranked(t.distance_ratio),
+            ..t
+        });
 
         // Search base layer with ef candidates
-        let mut candidates = self.search_layer_optimized(query, current, ef, 0)?;
+        let entries = std::slice::from_ref(&current);
+        self.search_layer_bounded(scratch, query, entries, ef, 0, filter, termination, meter)?;
 
         // Return top k
-        candidates.truncate(k);
-        Ok(candidates)
+        scratch.output.truncate(k);
+        Ok(())
+    }
+
+    /// Find every node within `radius` of the query.
+    ///
+    /// See `search_range_bounded`.
+    pub fn search_range(&self, query: &[f32], radius: f32, ef: usize) -> Result<Vec<SearchResult>> {
+        self.search_range_bounded(query, radius, ef, NodeId::MAX)
+    }
+
+    /// Find every node with `id < id_limit` within `radius` of the query.
+    ///
+    /// # Termination
+    ///
+    /// After the usual greedy descent, the base layer is explored best-first
+    /// with an expanding frontier:
+    ///
+    /// - Every node inside the radius is expanded, so the frontier grows with
+    ///   the size of the in-range region rather than a fixed `k`.
+    /// - Nodes outside the radius are kept as stepping stones only while they
+    ///   are among the `ef` closest such nodes seen; they bridge gaps between
+    ///   in-range regions.
+    /// - The search stops once the next candidate is outside the radius and
+    ///   farther than all `ef` retained stepping stones.
+    ///
+    /// Like k-NN search this is approximate: larger `ef` finds more of the
+    /// in-range nodes at higher cost.
+    ///
+    /// # Returns
+    ///
+    /// All found nodes with `distance <= radius`, nearest first.
+    pub fn search_range_bounded(
+        &self,
+        query: &[f32],
+        radius: f32,
+        ef: usize,
+        id_limit: NodeId,
+    ) -> Result<Vec<SearchResult>> {
+        self.search_range_filtered(query, radius, ef, id_limit, None)
+    }
+
+    /// `search_range_bounded` that only returns nodes `predicate` accepts, if given
+    pub(crate) fn search_range_filtered(
+        &self,
+        query: &[f32],
+        radius: f32,
+        ef: usize,
+        id_limit: NodeId,
+        predicate: Option<&dyn Fn(NodeId) -> bool>,
+    ) -> Result<Vec<SearchResult>> {
+        let Some(entry) = self.entry_point else {
+            return Ok(Vec::new());
+        };
+        let ef = ef.max(1);
+        let query = &Query::new(query, self.metric());
+        let filter = ResultFilter { predicate, ..self.result_filter(id_limit) };
+        self.with_scratch(|scratch| {
+            self.search_range_in(&mut scratch.visited, query, entry, radius, ef, filter)
+        })
+    }
+
+    /// `search_range_filtered` from `entry`, using `visited`
+    fn search_range_in(
+        &self,
+        visited: &mut VisitedFilter,
+        query: &Query<'_>,
+        entry: NodeId,
+        radius: f32,
+        ef: usize,
+        filter: ResultFilter,
+    ) -> Result<Vec<SearchResult>> {
+        let current = self.descend_in(visited, query, entry, &mut BudgetMeter::unlimited())?;
+
+        visited.reset(self.node_count as usize);
+        let mut frontier = RangeFrontier::new(self.metric().ranked(radius), ef);
+
+        visited.visit(current);
+        let dist = self.query_distance(query, current)?;
+        frontier.admit(current, dist, self.accepts(filter, current)?);
+
+        while let Some(Reverse(candidate)) = frontier.candidates.pop() {
+            if frontier.exhausted_at(&candidate) {
+                break;
+            }
+
+            for neighbor_id in self.live_neighbors(candidate.id, 0)? {
+                if visited.visit(neighbor_id) {
+                    let dist = self.query_distance(query, neighbor_id)?;
+                    frontier.admit(neighbor_id, dist, self.accepts(filter, neighbor_id)?);
+                }
+            }
+        }
+
+        let mut results = frontier.results;
+        results.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        Ok(self.reported(results))
+    }
+
+    /// `results` with the distances callers see instead of ranking distances
+    pub(crate) fn reported(&self, mut results: Vec<SearchResult>) -> Vec<SearchResult> {
+        let metric = self.metric();
+        results.iter_mut().for_each(|r| r.distance = metric.reported(r.distance));
+        results
     }
 
     /// Greedy search for a single best node (used for layer descent).
@@ -185,20 +838,94 @@ impl HnswGraph {
         query: &[f32],
         entry: NodeId,
         layer: usize,
+    ) -> Result<NodeId> {
+        let query = &Query::new(query, self.metric());
+        self.with_scratch(|scratch| {
+            let mut meter = BudgetMeter::unlimited();
+            let visited = &mut scratch.visited;
+            self.search_layer_greedy_in(
+                visited,
+                query,
+                entry,
+                std::iter::empty(),
+                layer,
+                &mut meter,
+            )
+        })
+    }
+
+    /// Greedy search from the top layer to layer 1, returning the node to
+    /// start the base layer search from.
+    ///
+    /// Each layer is entered at whichever is closest of the node reached on
+    /// the layer above and the entry candidates whose top layer it is, so a
+    /// query far from `entry` (another cluster of the data) can switch to a
+    /// better route early in the descent.
+    fn descend_in(
+        &self,
+        visited: &mut VisitedFilter,
+        query: &Query<'_>,
+        entry: NodeId,
+        meter: &mut BudgetMeter,
+    ) -> Result<NodeId> {
+        let mut current = entry;
+        for layer in (1..=self.max_layer).rev() {
+            let starts = self
+                .entry_candidates
+                .iter()
+                .filter(|&&(_, top_layer)| top_layer == layer)
+                .map(|&(node_id, _)| node_id);
+            current = self.search_layer_greedy_in(visited, query, current, starts, layer, meter)?;
+        }
+        Ok(current)
+    }
+
+    /// `search_layer_greedy` that resets and reuses `visited`, starting from
+    /// the closest of `entry` and `starts` and stopping at the best node so
+    /// far once `meter` runs out.
+    fn search_layer_greedy_in(
+        &self,
+        visited: &mut VisitedFilter,
+        query: &Query<'_>,
+        entry: NodeId,
+        starts: impl Iterator<Item = NodeId>,
+        layer: usize,
+        meter: &mut BudgetMeter,
     ) -> Result<NodeId> {
         let mut best_id = entry;
-        let mut best_dist = self.compute_distance_zero_copy(query, entry)?;
+        let mut best_dist = meter.entry_distance(entry, || self.query_distance(query, entry))?;
 
-        let mut visited = VisitedFilter::new(self.node_count as usize);
+        visited.reset(self.node_count as usize);
         visited.visit(entry);
 
+        for start in starts {
+            meter.check_cancelled()?;
+            if meter.exhausted() {
+                break;
+            }
+            if visited.visit(start) {
+                let dist = self.query_distance(query, start)?;
+                meter.spend();
+
+                if dist.total_cmp(&best_dist) == std::cmp::Ordering::Less {
+                    best_id = start;
+                    best_dist = dist;
+                }
+            }
+        }
+
         let mut changed = true;
-        while changed {
+        while changed && !meter.exhausted() {
+            meter.check_cancelled()?;
             changed = false;
 
-            for neighbor_id in self.neighbors_iter_from_mmap(best_id, layer)? {
+            for neighbor_id in self.live_neighbors(best_id, layer)? {
+                if !meter.can_compute() {
+                    break;
+                }
                 if visited.visit(neighbor_id) {
-                    let dist = self.compute_distance_zero_copy(query, neighbor_id)?;
+                    let dist = self.query_distance(query, neighbor_id)?;
+                    meter.spend();
 
                     if dist.total_cmp(&best_dist) == std::cmp::Ordering::Less {
                         best_id = neighbor_id;
@@ -209,11 +936,15 @@ impl HnswGraph {
             }
         }
 
+        meter.carry(best_id, best_dist);
         Ok(best_id)
     }
 
     /// Search within a single layer using zero-allocation optimizations.
     ///
+    /// Like the other layer searches, this returns ranking distances: squared
+    /// L2 for `Distance::Euclidean`, where `search()` returns L2.
+    ///
     /// # Optimizations Applied
     ///
     /// 1. **Dense visited filter**: O(1) array access instead of HashSet hashing
@@ -224,9 +955,10 @@ impl HnswGraph {
     ///    - No `Vec<NodeId>` allocation per node
     ///    - ~100ns vs ~400ns per node
     ///
-    /// 3. **Zero-copy distance computation**: `compute_distance_zero_copy()`
+    /// 3. **Zero-copy distance computation**: `query_distance()`
     ///    - No `Vec<f32>` allocation per distance calculation
     ///    - Direct mmap reads
+    ///    - Query binarized once per search, not per binary vector
     ///
     /// 4. **NaN-safe ordering**: `f32::total_cmp`
     ///    - No panics on NaN
@@ -239,6 +971,10 @@ impl HnswGraph {
     /// - ✓ No `Vec<NodeId>` for neighbors
     /// - ✓ No `Vec<f32>` for vectors
     /// - ✓ No HashSet operations
+    ///
+    /// The returned vector is the one allocation per call once the scratch
+    /// pool is warm; `search_layer_into()` reuses a caller-owned buffer
+    /// instead. Both are checked with the `alloc-audit` feature.
     pub fn search_layer_optimized(
         &self,
         query: &[f32],
@@ -246,34 +982,172 @@ impl HnswGraph {
         ef: usize,
         layer: usize,
     ) -> Result<Vec<SearchResult>> {
-        // Dense visited filter: O(n) space, O(1) time per check
-        let mut visited = VisitedFilter::new(self.node_count as usize);
+        self.search_layer_from(query, std::slice::from_ref(&entry), ef, layer)
+    }
+
+    /// `search_layer_optimized` writing the results to `output` instead of a
+    /// new vector
+    ///
+    /// `output` is cleared first. Once the scratch pool is warm and `output`
+    /// has room for `ef` results, this allocates nothing (checked by the
+    /// `alloc_audit` tests).
+    ///
+    /// # Errors
+    ///
+    /// Returns `OutOfBounds` if `entry` is not a node of the graph.
+    pub fn search_layer_into(
+        &self,
+        query: &[f32],
+        entry: NodeId,
+        ef: usize,
+        layer: usize,
+        output: &mut Vec<SearchResult>,
+    ) -> Result<()> {
+        if entry >= self.node_count {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!("Node {} does not exist (node count is {})", entry, self.node_count)
+            ));
+        }
+        let query = &Query::new(query, self.metric());
+        self.with_scratch(|scratch| {
+            let entries = std::slice::from_ref(&entry);
+            let filter = ResultFilter::ALL;
+            let mut meter = BudgetMeter::unlimited();
+            self.search_layer_bounded(
+                scratch, query, entries, ef, layer, filter, None, &mut meter,
+            )?;
+            output.clear();
+            output.extend_from_slice(&scratch.output);
+            Ok(())
+        })
+    }
+
+    /// Search one layer starting from every node in `entries`.
+    ///
+    /// The building block of `search()`, for experiments with other routing:
+    /// the caller chooses the layer and the starting set, for example the
+    /// nodes of `entry_points()` or the result of its own descent. Returns up
+    /// to `ef` nodes nearest first, deleted ones included.
+    ///
+    /// # Errors
+    ///
+    /// Returns `OutOfBounds` if an entry is not a node of the graph.
+    pub fn search_layer_from(
+        &self,
+        query: &[f32],
+        entries: &[NodeId],
+        ef: usize,
+        layer: usize,
+    ) -> Result<Vec<SearchResult>> {
+        if let Some(&entry) = entries.iter().find(|&&entry| entry >= self.node_count) {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!("Node {} does not exist (node count is {})", entry, self.node_count)
+            ));
+        }
+        let query = &Query::new(query, self.metric());
+        self.with_scratch(|scratch| {
+            let filter = ResultFilter::ALL;
+            let mut meter = BudgetMeter::unlimited();
+            self.search_layer_bounded(
+                scratch, query, entries, ef, layer, filter, None, &mut meter,
+            )?;
+            Ok(std::mem::take(&mut scratch.output))
+        })
+    }
+
+    /// Filter for user-facing searches: below `id_limit` and not deleted
+    fn result_filter(&self, id_limit: NodeId) -> ResultFilter<'static> {
+        ResultFilter { id_limit, skip_deleted: self.deleted_count() > 0, predicate: None }
+    }
 
-        let mut candidates = BinaryHeap::new();
-        let mut results = BinaryHeap::new();
+    /// `search_layer_optimized` that only admits nodes accepted by `filter` as results.
+    ///
+    /// Excluded nodes are still expanded as candidates so the search can pass
+    /// through them.
+    #[allow(clippy::too_many_arguments)]
+    fn search_layer_bounded(
+        &self,
+        scratch: &mut SearchScratch,
+        query: &Query<'_>,
+        entries: &[NodeId],
+        ef: usize,
+        layer: usize,
+        filter: ResultFilter,
+        termination: Option<EarlyTermination>,
+        meter: &mut BudgetMeter,
+    ) -> Result<()> {
+        if (1..=SMALL_EF).contains(&ef) && filter.accepts_all() {
+            return self.search_layer_small(
+                &mut scratch.visited,
+                &mut scratch.output,
+                query,
+                entries,
+                ef,
+                layer,
+                termination,
+                meter,
+            );
+        }
+
+        // Dense visited filter: O(n) space, O(1) time per check
+        let SearchScratch { visited, candidates, results, output } = scratch;
+        visited.reset(self.node_count as usize);
+        candidates.clear();
+        results.clear();
 
         // Zero-copy distance computation
-        let entry_dist = self.compute_distance_zero_copy(query, entry)?;
-        candidates.push(Reverse(SearchResult { id: entry, distance: entry_dist }));
-        results.push(SearchResult { id: entry, distance: entry_dist });
-        visited.visit(entry);
+        for &entry in entries {
+            if !visited.visit(entry) {
+                continue;
+            }
+            let entry_dist = meter.entry_distance(entry, || self.query_distance(query, entry))?;
+            candidates.push(Reverse(SearchResult { id: entry, distance: entry_dist }));
+            if self.accepts(filter, entry)? {
+                results.push(SearchResult { id: entry, distance: entry_dist });
+                if results.len() > ef {
+                    results.pop();
+                }
+            }
+        }
+
+        // Consecutive expansions that added no result (for `termination`)
+        let mut stale = 0;
 
         while let Some(Reverse(current)) = candidates.pop() {
+            meter.check_cancelled()?;
+            if meter.exhausted() {
+                break;
+            }
+
             // Early termination: current is further than worst result
             if results.len() >= ef
                 && let Some(worst) = results.peek()
-                && current.distance.total_cmp(&worst.distance) == std::cmp::Ordering::Greater
             {
-                break;
+                if current.distance.total_cmp(&worst.distance) == std::cmp::Ordering::Greater {
+                    break;
+                }
+                if let Some(termination) = &termination
+                    && termination.should_stop(current.distance, worst.distance, stale)
+                {
+                    break;
+                }
             }
 
+            let mut improved = false;
+
             // Zero-allocation neighbor iteration
             // Uses mmap-based iteration (~100ns) instead of Vec allocation (~400ns)
-            for neighbor_id in self.neighbors_iter_from_mmap(current.id, layer)? {
+            for neighbor_id in self.live_neighbors(current.id, layer)? {
+                if !meter.can_compute() {
+                    break;
+                }
                 if visited.visit(neighbor_id) {
                     // Zero-copy distance computation
                     // Reads directly from mmap instead of allocating Vec<f32>
-                    let dist = self.compute_distance_zero_copy(query, neighbor_id)?;
+                    let dist = self.query_distance(query, neighbor_id)?;
+                    meter.spend();
 
                     let should_add = if results.len() < ef {
                         true
@@ -285,19 +1159,99 @@ impl HnswGraph {
 
                     if should_add {
                         candidates.push(Reverse(SearchResult { id: neighbor_id, distance: dist }));
-                        results.push(SearchResult { id: neighbor_id, distance: dist });
 
-                        if results.len() > ef {
-                            results.pop();
+                        if self.accepts(filter, neighbor_id)? {
+                            results.push(SearchResult { id: neighbor_id, distance: dist });
+                            improved = true;
+
+                            if results.len() > ef {
+                                results.pop();
+                            }
                         }
                     }
                 }
             }
+
+            stale = if improved { 0 } else { stale + 1 };
+        }
+
+        output.clear();
+        output.extend(results.drain());
+        output.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        Ok(())
+    }
+
+    /// `search_layer_optimized` for `ef <= SMALL_EF`, without heaps.
+    ///
+    /// Expands the same nodes as the heap search (up to ties between equal
+    /// distances); see `SmallFrontier`.
+    #[allow(clippy::too_many_arguments)]
+    fn search_layer_small(
+        &self,
+        visited: &mut VisitedFilter,
+        out: &mut Vec<SearchResult>,
+        query: &Query<'_>,
+        entries: &[NodeId],
+        ef: usize,
+        layer: usize,
+        termination: Option<EarlyTermination>,
+        meter: &mut BudgetMeter,
+    ) -> Result<()> {
+        visited.reset(self.node_count as usize);
+        let mut frontier = SmallFrontier::new(ef);
+
+        for &entry in entries {
+            if visited.visit(entry) {
+                let distance = meter.entry_distance(entry, || self.query_distance(query, entry))?;
+                frontier.admit(entry, distance);
+            }
+        }
+
+        let mut stale = 0;
+        while let Some((current, distance)) = frontier.next_unexpanded() {
+            meter.check_cancelled()?;
+            if meter.exhausted() {
+                break;
+            }
+            if let Some(termination) = &termination
+                && let Some(worst) = frontier.full_worst()
+                && termination.should_stop(distance, worst, stale)
+            {
+                break;
+            }
+
+            let mut improved = false;
+            for neighbor_id in self.live_neighbors(current, layer)? {
+                if !meter.can_compute() {
+                    break;
+                }
+                if visited.visit(neighbor_id) {
+                    let dist = self.query_distance(query, neighbor_id)?;
+                    meter.spend();
+                    improved |= frontier.admit(neighbor_id, dist);
+                }
+            }
+            stale = if improved { 0 } else { stale + 1 };
         }
 
-        let mut sorted: Vec<_> = results.into_iter().collect();
-        sorted.sort_by(|a, b| a.distance.total_cmp(&b.distance));
-        Ok(sorted)
+        frontier.write_results(out);
+        Ok(())
+    }
+
+    /// Neighbors of a node that exist in the graph
+    ///
+    /// Skips links to IDs at or past `node_count`, which are left behind when
+    /// ghost nodes are rolled back on open.
+    fn live_neighbors(
+        &self,
+        node_id: NodeId,
+        layer: usize,
+    ) -> Result<impl Iterator<Item = NodeId> + '_> {
+        instrument::record_hop(|| {
+            instrument::page_key(self.node_offset(node_id), self.storage.has_graph_file())
+        });
+        let limit = self.node_count;
+        Ok(self.neighbors_iter_from_mmap(node_id, layer)?.filter(move |&id| id < limit))
     }
 }
 
@@ -346,6 +1300,27 @@ mod tests {
         assert!(!results[1].distance.is_nan());
     }
 
+    #[test]
+    fn test_small_frontier_keeps_nearest_sorted() {
+        let mut frontier = SmallFrontier::new(3);
+        for (id, distance) in [(0, 5.0), (1, 2.0), (2, 9.0), (3, 1.0), (4, 7.0), (5, 2.0)] {
+            frontier.admit(id, distance);
+        }
+        assert_eq!(frontier.next_unexpanded(), Some((3, 1.0)));
+        assert_eq!(frontier.full_worst(), Some(2.0));
+
+        // Closer nodes admitted later are expanded next
+        frontier.admit(6, 0.5);
+        assert_eq!(frontier.next_unexpanded(), Some((6, 0.5)));
+        assert_eq!(frontier.next_unexpanded(), Some((1, 2.0)));
+        assert_eq!(frontier.next_unexpanded(), None);
+
+        let mut results = Vec::new();
+        frontier.write_results(&mut results);
+        let ids: Vec<_> = results.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![6, 3, 1]);
+    }
+
     #[test]
     fn test_visited_filter() {
         let mut filter = VisitedFilter::new(10);
@@ -377,6 +1352,27 @@ mod tests {
         assert!(!filter.visit(100));
     }
 
+    #[test]
+    fn test_visited_filter_reset() {
+        let mut filter = VisitedFilter::new(4);
+        assert!(filter.visit(2));
+
+        filter.reset(8);
+        assert!(!filter.is_visited(2));
+        assert!(filter.visit(2));
+        assert!(filter.visit(7));
+
+        // Generation wraparound clears stale stamps
+        filter.generation = u32::MAX;
+        filter.stamps.fill(1);
+        filter.reset(8);
+        assert_eq!(filter.generation, 1);
+        assert!(filter.visit(3));
+
+        filter.reset(2);
+        assert!(!filter.visit(7));
+    }
+
     #[test]
     fn test_ef_less_than_k_correction() {
         // This is a behavioral test - we can't easily test the internal
@@ -0,0 +1,99 @@
+//! Node records held in memory between commits
+//!
+//! Building a graph rewrites the record of every node it links, so an insert
+//! dirties the pages of a new record plus a few dozen scattered neighbors.
+//! Written straight to the mapping, a page can be written back by the OS
+//! several times before the next commit, which wears out flash storage such as
+//! SD cards. Buffered records instead reach the mapping once per commit, in
+//! file order, so each page is written back at most once per commit and the
+//! writes come out mostly sequential.
+
+use super::node::NodeId;
+use std::collections::BTreeMap;
+
+/// A record in the graph zone, ordered as in the file: compact (or
+/// fixed-width) records by node ID, then overflow records by slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum RecordSlot {
+    Node(NodeId),
+    Overflow(u64),
+}
+
+/// Dirty records waiting for the next commit
+#[derive(Debug, Clone, Default)]
+pub(crate) struct WriteBuffer {
+    records: BTreeMap<RecordSlot, Box<[u8]>>,
+
+    /// Records held before they are written out without waiting for a commit;
+    /// 0 disables buffering
+    capacity: usize,
+}
+
+impl WriteBuffer {
+    #[inline]
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    #[inline]
+    pub(crate) fn is_full(&self) -> bool {
+        self.records.len() >= self.capacity
+    }
+
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Latest bytes of `slot`, if it was written since the last drain
+    #[inline]
+    pub(crate) fn get(&self, slot: RecordSlot) -> Option<&[u8]> {
+        if self.records.is_empty() {
+            return None;
+        }
+        self.records.get(&slot).map(|bytes| &bytes[..])
+    }
+
+    #[inline]
+    pub(crate) fn get_mut(&mut self, slot: RecordSlot) -> Option<&mut [u8]> {
+        self.records.get_mut(&slot).map(|bytes| &mut bytes[..])
+    }
+
+    pub(crate) fn insert(&mut self, slot: RecordSlot, bytes: Box<[u8]>) {
+        self.records.insert(slot, bytes);
+    }
+
+    /// Take every buffered record, in file order
+    pub(crate) fn take(&mut self) -> BTreeMap<RecordSlot, Box<[u8]>> {
+        std::mem::take(&mut self.records)
+    }
+
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_come_out_in_file_order() {
+        let mut buffer = WriteBuffer::default();
+        buffer.set_capacity(8);
+        buffer.insert(RecordSlot::Overflow(0), Box::new([3]));
+        buffer.insert(RecordSlot::Node(7), Box::new([2]));
+        buffer.insert(RecordSlot::Node(2), Box::new([1]));
+        buffer.insert(RecordSlot::Node(7), Box::new([4]));
+
+        assert_eq!(buffer.get(RecordSlot::Node(7)), Some(&[4][..]));
+        let order: Vec<_> = buffer.take().into_keys().collect();
+        assert_eq!(order, [RecordSlot::Node(2), RecordSlot::Node(7), RecordSlot::Overflow(0)]);
+        assert!(buffer.is_empty());
+    }
+}
@@ -0,0 +1,210 @@
+//! Hooks for exporting operation metrics to application telemetry.
+//!
+//! An application that wants search latency, graph hops or page access counts
+//! in its own metrics system implements `Instrumentation` and attaches it with
+//! `VectorIndex::set_instrumentation()`. Chassis calls the hooks around each
+//! search, insert and flush, and takes no metrics dependency itself.
+//!
+//! Search counters are collected in a per-thread trace that is only active
+//! while an instrumented search runs, so an index without instrumentation
+//! pays one thread-local increment (for `VectorIndex::metrics()`) and flag
+//! check per distance computation.
+
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Receiver of per-operation events from a `VectorIndex`
+///
+/// Every method has an empty default, so implementations only override the
+/// events they export. Hooks run synchronously on the thread performing the
+/// operation (searches may run on several threads at once) and should return
+/// quickly, for example by updating atomic counters or pushing to a channel.
+pub trait Instrumentation: Send + Sync {
+    /// Called before a search starts, with the requested `k` (0 for radius searches)
+    fn on_search_start(&self, k: usize) {
+        let _ = k;
+    }
+
+    /// Called after a search completed successfully
+    fn on_search_end(&self, event: &SearchEvent) {
+        let _ = event;
+    }
+
+    /// Called after a vector was added
+    fn on_add(&self, event: &AddEvent) {
+        let _ = event;
+    }
+
+    /// Called after `flush()` completed, or after `flush_async()` handed its
+    /// work to the background thread
+    fn on_flush(&self, event: &FlushEvent) {
+        let _ = event;
+    }
+}
+
+/// Work done by one search
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchEvent {
+    /// Requested number of neighbors (0 for radius searches)
+    pub k: usize,
+
+    /// Number of results returned
+    pub results: usize,
+
+    /// Whether the search scanned every vector instead of the graph
+    pub exact: bool,
+
+    /// Graph nodes whose neighbor lists were read, on all layers
+    pub hops: u64,
+
+    /// Distances computed between the query and stored vectors
+    pub distance_computations: u64,
+
+    /// Distinct 4 KiB pages of the index (and graph) file read, resident or not
+    pub pages_touched: u64,
+
+    /// Wall-clock duration; `None` where no clock is available (wasm)
+    pub duration: Option<Duration>,
+}
+
+/// An insert, reported by `add()` and the other add methods
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddEvent {
+    /// ID assigned to the vector
+    pub id: u64,
+
+    /// Wall-clock duration, including neighbor selection; `None` on wasm
+    pub duration: Option<Duration>,
+}
+
+/// A flush, reported by `flush()` and `flush_async()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlushEvent {
+    /// Vectors in the index when the flush started
+    pub vectors: u64,
+
+    /// `true` for `flush_async()`, whose `fsync` was still running
+    pub background: bool,
+
+    /// Wall-clock duration of the call; `None` on wasm
+    pub duration: Option<Duration>,
+}
+
+/// The instrumentation attached to an index, if any
+#[derive(Clone, Default)]
+pub(crate) struct Hooks(pub(crate) Option<Arc<dyn Instrumentation>>);
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.0.is_some() { "Hooks(Some(..))" } else { "Hooks(None)" })
+    }
+}
+
+/// Counters of the search running on this thread
+#[derive(Debug, Default)]
+pub(crate) struct Trace {
+    pub(crate) hops: u64,
+    pub(crate) distance_computations: u64,
+
+    /// Page of every read, tagged with its file (see `page_key`); deduplicated
+    /// when the trace ends
+    pages: Vec<u64>,
+}
+
+impl Trace {
+    /// Distinct pages read
+    pub(crate) fn pages_touched(&mut self) -> u64 {
+        self.pages.sort_unstable();
+        self.pages.dedup();
+        self.pages.len() as u64
+    }
+}
+
+thread_local! {
+    /// Whether a trace is being collected on this thread
+    static ACTIVE: Cell<bool> = const { Cell::new(false) };
+
+    static TRACE: RefCell<Trace> = RefCell::new(Trace::default());
+
+    /// Distances computed on this thread, traced or not
+    static DISTANCES: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Distances computed on this thread so far; the difference of two readings
+/// counts the computations in between
+pub(crate) fn distances_on_thread() -> u64 {
+    DISTANCES.with(Cell::get)
+}
+
+/// Start collecting a trace on this thread
+pub(crate) fn begin() {
+    TRACE.with(|trace| *trace.borrow_mut() = Trace::default());
+    ACTIVE.with(|active| active.set(true));
+}
+
+/// Stop collecting and return the trace
+pub(crate) fn end() -> Trace {
+    ACTIVE.with(|active| active.set(false));
+    TRACE.with(|trace| std::mem::take(&mut *trace.borrow_mut()))
+}
+
+/// Identifies the page at byte `offset` of the index file, or of the graph
+/// file if `graph_file` is set
+#[inline]
+pub(crate) fn page_key(offset: u64, graph_file: bool) -> u64 {
+    (offset / crate::storage::PAGE_SIZE as u64) << 1 | u64::from(graph_file)
+}
+
+/// Record a distance computation reading the page identified by `page`
+#[inline]
+pub(crate) fn record_distance(page: impl FnOnce() -> u64) {
+    DISTANCES.with(|distances| distances.set(distances.get().wrapping_add(1)));
+    if ACTIVE.with(Cell::get) {
+        TRACE.with(|trace| {
+            let mut trace = trace.borrow_mut();
+            trace.distance_computations += 1;
+            trace.pages.push(page());
+        });
+    }
+}
+
+/// Record a hop reading the neighbor list on the page identified by `page`
+#[inline]
+pub(crate) fn record_hop(page: impl FnOnce() -> u64) {
+    if ACTIVE.with(Cell::get) {
+        TRACE.with(|trace| {
+            let mut trace = trace.borrow_mut();
+            trace.hops += 1;
+            trace.pages.push(page());
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_only_collects_while_active() {
+        record_distance(|| 0);
+
+        begin();
+        record_distance(|| page_key(100, false));
+        record_distance(|| page_key(4000, false));
+        record_distance(|| page_key(4096, false));
+        record_hop(|| page_key(100, true));
+        let mut trace = end();
+        assert_eq!(trace.distance_computations, 3);
+        assert_eq!(trace.hops, 1);
+        assert_eq!(trace.pages_touched(), 3);
+
+        record_hop(|| 0);
+        assert_eq!(end().hops, 0);
+
+        let before = distances_on_thread();
+        record_distance(|| 0);
+        assert_eq!(distances_on_thread() - before, 1);
+    }
+}
@@ -0,0 +1,431 @@
+//! Conversion to and from other ANN library file formats.
+//!
+//! Lets users migrating from hnswlib or FAISS bring their indexes over without
+//! re-embedding their data:
+//!
+//! - `export_hnswlib()` writes a Chassis index as an hnswlib `HierarchicalNSW`
+//!   file, graph included, loadable with `hnswlib.Index(space="l2", dim=..)`.
+//! - `import_hnswlib()` and `import_faiss_flat()` read the vectors of an
+//!   hnswlib or FAISS `IndexFlat` file and insert them into a new Chassis index.
+//!
+//! Imports rebuild the graph with Chassis' own parameters rather than copying
+//! foreign links. Chassis always ranks by Euclidean distance; vectors from an
+//! inner-product index only rank the same way if they are normalized.
+//!
+//! All formats are read and written little-endian with 64-bit `size_t`, as
+//! produced by both libraries on x86_64 and aarch64.
+
+use crate::error::{ErrorKind, Tagged};
+use crate::hnsw::node::NodeId;
+use crate::{Distance, IndexOptions, VectorIndex};
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// hnswlib flag bit marking an element as deleted (third byte of its level-0 list)
+const HNSWLIB_DELETE_MARK: u8 = 0x01;
+
+/// Result of `import_hnswlib()`
+#[derive(Debug)]
+pub struct HnswlibImport {
+    /// The new Chassis index
+    pub index: VectorIndex,
+
+    /// hnswlib label of each imported vector, indexed by Chassis ID
+    pub labels: Vec<u64>,
+}
+
+/// Write `index` as an hnswlib `HierarchicalNSW` file at `path`
+///
+/// The Chassis graph is written as-is, so hnswlib can search the file without
+/// rebuilding it. Each vector's label is its Chassis ID, and deleted vectors
+/// carry hnswlib's delete mark. Open the file with
+/// `hnswlib.Index(space="l2", dim=index.dimensions())` and `load_index()`.
+///
+/// # Errors
+///
+/// Returns an error if the index holds more than `u32::MAX` vectors (hnswlib's
+/// ID limit), uses a distance other than Euclidean, holds vectors not yet
+/// linked into the graph (see `VectorIndex::link_graph()`), or if the file
+/// cannot be written.
+pub fn export_hnswlib<P: AsRef<Path>>(index: &VectorIndex, path: P) -> Result<()> {
+    let path = path.as_ref();
+    let graph = &index.graph;
+
+    // The graph's edges only suit hnswlib's "l2" space if they were chosen by L2
+    let distance = index.distance();
+    if distance != Distance::Euclidean {
+        anyhow::bail!(Tagged::new(
+            ErrorKind::InvalidArgument,
+            format!(
+                "Cannot export an index built with {} to hnswlib",
+                Distance::describe(distance.name())
+            )
+        ));
+    }
+
+    let unlinked = index.unlinked_len();
+    if unlinked > 0 {
+        anyhow::bail!(Tagged::new(
+            ErrorKind::InvalidArgument,
+            format!("Cannot export {} vectors not yet linked into the graph", unlinked)
+        ));
+    }
+
+    let count = graph.node_count();
+    if count > u64::from(u32::MAX) {
+        anyhow::bail!(Tagged::new(
+            ErrorKind::InvalidArgument,
+            format!("hnswlib indexes hold at most {} vectors, this one has {}", u32::MAX, count)
+        ));
+    }
+
+    let max_m0 = graph.record_params.max_neighbors(0);
+    let max_m = graph.record_params.max_neighbors(1);
+    let data_size = index.dimensions() as usize * std::mem::size_of::<f32>();
+    let size_links_level0 = max_m0 * 4 + 4;
+    let size_links_upper = max_m * 4 + 4;
+    let size_data_per_element = size_links_level0 + data_size + 8;
+
+    let (max_level, entry_point) = match graph.entry_point {
+        Some(entry) => (graph.max_layer as i32, entry as u32),
+        None => (-1, u32::MAX),
+    };
+
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create hnswlib index {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+
+    let write = |out: &mut BufWriter<File>| -> std::io::Result<()> {
+        // Header, in the field order of HierarchicalNSW::saveIndex
+        for value in [0, count, count, size_data_per_element as u64] {
+            out.write_all(&value.to_le_bytes())?;
+        }
+        out.write_all(&((size_links_level0 + data_size) as u64).to_le_bytes())?;
+        out.write_all(&(size_links_level0 as u64).to_le_bytes())?;
+        out.write_all(&max_level.to_le_bytes())?;
+        out.write_all(&entry_point.to_le_bytes())?;
+        for value in [max_m, max_m0, max_m] {
+            out.write_all(&(value as u64).to_le_bytes())?;
+        }
+        out.write_all(&(1.0 / (max_m as f64).ln()).to_le_bytes())?;
+        out.write_all(&(index.options.ef_construction as u64).to_le_bytes())?;
+        Ok(())
+    };
+    write(&mut out).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    // Level 0: link count, fixed link slots, vector, label
+    let mut layer_counts = Vec::with_capacity(count as usize);
+    for id in 0..count {
+        let record = graph.read_node_record(id)?;
+        layer_counts.push(record.header.layer_count.max(1) as usize);
+
+        let links = live_links(&record.get_neighbors(0), count);
+        let vector = index.decoded_vector(id)?;
+        let mark = if graph.is_deleted(id)? { HNSWLIB_DELETE_MARK } else { 0 };
+        write_hnswlib_links(&mut out, &links, max_m0, mark)
+            .and_then(|()| {
+                for value in vector {
+                    out.write_all(&value.to_le_bytes())?;
+                }
+                out.write_all(&id.to_le_bytes())
+            })
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+
+    // Upper levels: per element, the byte size of its lists and then the lists
+    for (id, &layers) in layer_counts.iter().enumerate() {
+        let record = graph.read_node_record(id as NodeId)?;
+        let size = (size_links_upper * (layers - 1)) as u32;
+        out.write_all(&size.to_le_bytes())
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        for layer in 1..layers {
+            let links = live_links(&record.get_neighbors(layer), count);
+            write_hnswlib_links(&mut out, &links, max_m, 0)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+    }
+
+    out.into_inner()
+        .map_err(|e| e.into_error())
+        .and_then(|file| file.sync_all())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Build a Chassis index at `dst` from the vectors of the hnswlib index at `src`
+///
+/// Vectors are inserted in hnswlib's internal order; elements marked deleted
+/// are skipped. The hnswlib label of each vector is returned alongside the
+/// index, since Chassis assigns its own sequential IDs.
+///
+/// # Errors
+///
+/// Returns an error if `src` is not a valid hnswlib index, cannot be read, or
+/// if the Chassis index cannot be created or written.
+pub fn import_hnswlib<P: AsRef<Path>, Q: AsRef<Path>>(
+    src: P,
+    dst: Q,
+    options: IndexOptions,
+) -> Result<HnswlibImport> {
+    let src = src.as_ref();
+    let file = File::open(src)
+        .with_context(|| format!("Failed to open hnswlib index {}", src.display()))?;
+    let mut reader = BufReader::new(file);
+    let read_err = || format!("Failed to read hnswlib index {}", src.display());
+
+    let mut header = [0u64; 6];
+    for value in &mut header {
+        *value = read_u64(&mut reader).with_context(read_err)?;
+    }
+    let [offset_level0, _max_elements, count, size_data_per_element, label_offset, offset_data] =
+        header;
+
+    // maxlevel, enterpoint, maxM, maxM0, M, mult and ef_construction
+    let mut rest = [0u8; 4 + 4 + 8 * 3 + 8 + 8];
+    reader.read_exact(&mut rest).with_context(read_err)?;
+    let max_m0 = u64::from_le_bytes(rest[16..24].try_into().expect("slice is eight bytes"));
+
+    let data_size = label_offset.saturating_sub(offset_data);
+    if offset_level0 != 0
+        || offset_data != max_m0.saturating_mul(4).saturating_add(4)
+        || data_size == 0
+        || data_size % 4 != 0
+        || size_data_per_element != label_offset.saturating_add(8)
+    {
+        anyhow::bail!(Tagged::new(
+            ErrorKind::Corrupted,
+            format!("{} is not an hnswlib index with f32 vectors", src.display())
+        ));
+    }
+    let dims = u32::try_from(data_size / 4).context("hnswlib dimensions out of range")?;
+
+    let mut index = VectorIndex::open(dst, dims, options)?;
+    let mut labels = Vec::with_capacity(usize::try_from(count).unwrap_or(0));
+    let mut element = vec![0u8; size_data_per_element as usize];
+    let mut vector = vec![0.0f32; dims as usize];
+    for _ in 0..count {
+        reader.read_exact(&mut element).with_context(read_err)?;
+        if element[2] & HNSWLIB_DELETE_MARK != 0 {
+            continue;
+        }
+
+        let data = &element[offset_data as usize..label_offset as usize];
+        decode_f32s(data, &mut vector);
+        index.add(&vector)?;
+
+        let label = &element[label_offset as usize..];
+        labels.push(u64::from_le_bytes(label.try_into().expect("label is eight bytes")));
+    }
+
+    index.flush()?;
+    Ok(HnswlibImport { index, labels })
+}
+
+/// Build a Chassis index at `dst` from a FAISS `IndexFlat` file at `src`
+///
+/// Accepts files written by `faiss.write_index()` for `IndexFlatL2`,
+/// `IndexFlatIP` and plain `IndexFlat`. Vectors keep their FAISS IDs, which
+/// are sequential for flat indexes.
+///
+/// # Errors
+///
+/// Returns an error if `src` is not a FAISS flat index, cannot be read, or if
+/// the Chassis index cannot be created or written.
+pub fn import_faiss_flat<P: AsRef<Path>, Q: AsRef<Path>>(
+    src: P,
+    dst: Q,
+    options: IndexOptions,
+) -> Result<VectorIndex> {
+    let src = src.as_ref();
+    let file =
+        File::open(src).with_context(|| format!("Failed to open FAISS index {}", src.display()))?;
+    let mut reader = BufReader::new(file);
+    let read_err = || format!("Failed to read FAISS index {}", src.display());
+
+    let mut fourcc = [0u8; 4];
+    reader.read_exact(&mut fourcc).with_context(read_err)?;
+    if !matches!(&fourcc, b"IxF2" | b"IxFI" | b"IxFl") {
+        anyhow::bail!(Tagged::new(
+            ErrorKind::Corrupted,
+            format!(
+                "{} is not a FAISS flat index (found {:?})",
+                src.display(),
+                String::from_utf8_lossy(&fourcc)
+            )
+        ));
+    }
+
+    // Index header: d, ntotal, two unused fields, is_trained, metric_type
+    let dims = read_u32(&mut reader).with_context(read_err)?;
+    let count = read_u64(&mut reader).with_context(read_err)?;
+    let mut skipped = [0u8; 8 + 8 + 1];
+    reader.read_exact(&mut skipped).with_context(read_err)?;
+    let metric = read_u32(&mut reader).with_context(read_err)?;
+    if metric > 1 {
+        // metric_arg, only present for metrics other than inner product and L2
+        read_u32(&mut reader).with_context(read_err)?;
+    }
+
+    let floats = read_u64(&mut reader).with_context(read_err)?;
+    if dims == 0 || floats != count.saturating_mul(u64::from(dims)) {
+        anyhow::bail!(Tagged::new(
+            ErrorKind::Corrupted,
+            format!(
+                "FAISS index {} holds {} floats, expected {} vectors of {} dimensions",
+                src.display(),
+                floats,
+                count,
+                dims
+            )
+        ));
+    }
+
+    let mut index = VectorIndex::open(dst, dims, options)?;
+    let mut raw = vec![0u8; dims as usize * std::mem::size_of::<f32>()];
+    let mut vector = vec![0.0f32; dims as usize];
+    for _ in 0..count {
+        reader.read_exact(&mut raw).with_context(read_err)?;
+        decode_f32s(&raw, &mut vector);
+        index.add(&vector)?;
+    }
+
+    index.flush()?;
+    Ok(index)
+}
+
+/// Keep links to nodes that exist, in hnswlib's 32-bit ID space
+fn live_links(neighbors: &[NodeId], count: u64) -> Vec<u32> {
+    neighbors.iter().filter(|&&id| id < count).map(|&id| id as u32).collect()
+}
+
+/// Write one hnswlib link list: the count and `flags` (third byte), then `slots`
+/// IDs padded with zeros
+fn write_hnswlib_links<W: Write>(
+    out: &mut W,
+    links: &[u32],
+    slots: usize,
+    flags: u8,
+) -> std::io::Result<()> {
+    out.write_all(&(links.len() as u32 | u32::from(flags) << 16).to_le_bytes())?;
+    for slot in 0..slots {
+        out.write_all(&links.get(slot).copied().unwrap_or(0).to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Decode packed little-endian `f32` values into `dst`
+fn decode_f32s(src: &[u8], dst: &mut [f32]) {
+    let (values, _) = src.as_chunks::<4>();
+    for (value, bytes) in dst.iter_mut().zip(values) {
+        *value = f32::from_le_bytes(*bytes);
+    }
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> std::io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> std::io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Size of the hnswlib header written by `export_hnswlib()`
+    const HNSWLIB_HEADER_SIZE: usize = 6 * 8 + 4 + 4 + 3 * 8 + 8 + 8;
+
+    #[test]
+    fn test_hnswlib_roundtrip_keeps_labels_and_skips_deleted() {
+        let dir = tempdir().unwrap();
+        let mut index =
+            VectorIndex::open(dir.path().join("a.chassis"), 8, IndexOptions::default()).unwrap();
+        for i in 0..100 {
+            index.add(&[i as f32; 8]).unwrap();
+        }
+
+        let exported = dir.path().join("a.hnsw");
+        export_hnswlib(&index, &exported).unwrap();
+
+        // Level-0 element size: link count + 2M slots, 8 floats, label
+        let mut bytes = std::fs::read(&exported).unwrap();
+        let max_m0 = index.graph.record_params.max_neighbors(0);
+        let element = max_m0 * 4 + 4 + 8 * 4 + 8;
+        assert_eq!(u64::from_le_bytes(bytes[16..24].try_into().unwrap()), 100);
+        assert_eq!(u64::from_le_bytes(bytes[24..32].try_into().unwrap()), element as u64);
+
+        // Mark element 3 deleted, as hnswlib's markDelete() does
+        bytes[HNSWLIB_HEADER_SIZE + 3 * element + 2] |= HNSWLIB_DELETE_MARK;
+        std::fs::write(&exported, &bytes).unwrap();
+
+        let imported =
+            import_hnswlib(&exported, dir.path().join("b.chassis"), IndexOptions::default())
+                .unwrap();
+        assert_eq!(imported.index.len(), 99);
+        assert_eq!(imported.labels.len(), 99);
+        assert_eq!(&imported.labels[..4], &[0, 1, 2, 4]);
+
+        let hit = imported.index.search(&[50.0; 8], 1).unwrap().remove(0);
+        assert_eq!(imported.labels[hit.id as usize], 50);
+        assert_eq!(hit.distance, 0.0);
+    }
+
+    #[test]
+    fn test_hnswlib_export_marks_deleted() {
+        let dir = tempdir().unwrap();
+        let mut index =
+            VectorIndex::open(dir.path().join("a.chassis"), 4, IndexOptions::default()).unwrap();
+        for i in 0..10 {
+            index.add(&[i as f32; 4]).unwrap();
+        }
+        index.delete(7).unwrap();
+
+        let exported = dir.path().join("a.hnsw");
+        export_hnswlib(&index, &exported).unwrap();
+
+        let imported =
+            import_hnswlib(&exported, dir.path().join("b.chassis"), IndexOptions::default())
+                .unwrap();
+        assert_eq!(imported.labels, [0, 1, 2, 3, 4, 5, 6, 8, 9]);
+    }
+
+    #[test]
+    fn test_import_faiss_flat() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("flat.faiss");
+
+        let (dims, count) = (4u32, 10u64);
+        let mut bytes = b"IxF2".to_vec();
+        bytes.extend_from_slice(&dims.to_le_bytes());
+        bytes.extend_from_slice(&count.to_le_bytes());
+        bytes.extend_from_slice(&(1u64 << 20).to_le_bytes());
+        bytes.extend_from_slice(&(1u64 << 20).to_le_bytes());
+        bytes.push(1); // is_trained
+        bytes.extend_from_slice(&1u32.to_le_bytes()); // METRIC_L2
+        bytes.extend_from_slice(&(count * u64::from(dims)).to_le_bytes());
+        for i in 0..count * u64::from(dims) {
+            bytes.extend_from_slice(&((i / 4) as f32).to_le_bytes());
+        }
+        std::fs::write(&src, &bytes).unwrap();
+
+        let index =
+            import_faiss_flat(&src, dir.path().join("a.chassis"), IndexOptions::default()).unwrap();
+        assert_eq!(index.len(), 10);
+        assert_eq!(index.dimensions(), 4);
+        assert_eq!(index.search(&[7.0; 4], 1).unwrap()[0].id, 7);
+
+        // Anything but a flat index is refused
+        bytes[..4].copy_from_slice(b"IHNf");
+        std::fs::write(&src, &bytes).unwrap();
+        let err = import_faiss_flat(&src, dir.path().join("b.chassis"), IndexOptions::default())
+            .unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Corrupted);
+    }
+}
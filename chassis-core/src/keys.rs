@@ -0,0 +1,261 @@
+//! Application keys mapped to vector IDs.
+//!
+//! Vector IDs are dense integers assigned by the index. Applications that
+//! identify documents by their own keys (UUIDs, paths, `"doc:123"`) can attach
+//! one key per vector with `VectorIndex::add_with_key()` instead of keeping a
+//! separate ID translation table.
+//!
+//! The map is held in memory and persisted as a metadata section on flush:
+//!
+//! ```text
+//! Offset  Size  Field
+//! ------  ----  -----
+//! 0       8     count: u64
+//! 8       ...   entries: [id: u64][len: u32][key: len bytes of UTF-8]
+//! ```
+//!
+//! The IDs whose key changed since the last flush go to a journal record in
+//! the same format, where an entry with `len` 0 removes the key of its ID.
+
+use crate::error::{ErrorKind, Tagged};
+use crate::metadata::{Changes, JournaledMap, SectionTag};
+use anyhow::{Context, Result};
+use std::collections::{BTreeSet, HashMap};
+
+/// Metadata section tag for the persisted key map.
+pub(crate) const KEYS_SECTION: &SectionTag = b"KEYS\0\0\0\0";
+
+/// Maximum length of a key in bytes (UTF-8).
+pub const MAX_KEY_LEN: usize = 1024;
+
+/// Size of the serialized entry header (`id: u64` + `len: u32`).
+const ENTRY_HEADER_SIZE: usize = 12;
+
+/// Bidirectional map between keys and vector IDs.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct KeyMap {
+    ids: HashMap<String, u64>,
+    keys: HashMap<u64, String>,
+
+    /// IDs whose key changed since it was last written to the file
+    changes: Changes,
+}
+
+impl KeyMap {
+    /// Check that `key` can be stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidArgument` if the key is empty or longer than `MAX_KEY_LEN` bytes.
+    pub(crate) fn validate(key: &str) -> Result<()> {
+        if key.is_empty() || key.len() > MAX_KEY_LEN {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!("Keys must be 1 to {} bytes, got {}", MAX_KEY_LEN, key.len())
+            ));
+        }
+        Ok(())
+    }
+
+    /// ID mapped to `key`, if any
+    pub(crate) fn id(&self, key: &str) -> Option<u64> {
+        self.ids.get(key).copied()
+    }
+
+    /// Key mapped to `id`, if any
+    pub(crate) fn key(&self, id: u64) -> Option<&str> {
+        self.keys.get(&id).map(String::as_str)
+    }
+
+    /// Map `key` to `id`; neither may be mapped yet
+    pub(crate) fn insert(&mut self, key: &str, id: u64) {
+        debug_assert!(!self.ids.contains_key(key) && !self.keys.contains_key(&id));
+        self.ids.insert(key.to_owned(), id);
+        self.keys.insert(id, key.to_owned());
+        self.changes.touch(id);
+    }
+
+    /// Remove the key of `id`, returning it
+    pub(crate) fn remove_id(&mut self, id: u64) -> Option<String> {
+        let key = self.keys.remove(&id)?;
+        self.ids.remove(&key);
+        self.changes.touch(id);
+        Some(key)
+    }
+
+    /// Drop keys of IDs at or past `count` (vectors rolled back on open)
+    pub(crate) fn truncate(&mut self, count: u64) {
+        let changes = &mut self.changes;
+        self.keys.retain(|&id, _| {
+            if id >= count {
+                changes.touch(id);
+            }
+            id < count
+        });
+        self.ids.retain(|_, &mut id| id < count);
+    }
+
+    /// Heap bytes held by the keys and hash tables (approximate)
+    pub(crate) fn heap_bytes(&self) -> usize {
+        let entry = std::mem::size_of::<(String, u64)>() + 1;
+        let strings: usize = self.keys.values().map(String::capacity).sum();
+        (self.ids.capacity() + self.keys.capacity()) * entry + 2 * strings
+    }
+
+    /// Map each key of `entries`, none of which may be mapped yet
+    fn insert_entries(&mut self, entries: &[(u64, &str)]) -> Result<()> {
+        for &(id, key) in entries {
+            if self.ids.contains_key(key) || self.keys.contains_key(&id) {
+                anyhow::bail!(Tagged::new(ErrorKind::Corrupted, "Key map maps a key or ID twice"));
+            }
+            self.insert(key, id);
+        }
+        Ok(())
+    }
+}
+
+/// Parse the entries of the section format
+fn parse_entries(bytes: &[u8]) -> Result<Vec<(u64, &str)>> {
+    let corrupted = |msg: &str| Tagged::new(ErrorKind::Corrupted, format!("Key map {}", msg));
+
+    let count = bytes.get(..8).ok_or_else(|| corrupted("section too small"))?;
+    let count = u64::from_le_bytes(count.try_into()?);
+
+    let mut entries = Vec::new();
+    let mut offset = 8;
+    for _ in 0..count {
+        let header = bytes
+            .get(offset..offset + ENTRY_HEADER_SIZE)
+            .ok_or_else(|| corrupted("entry extends beyond section"))?;
+        let id = u64::from_le_bytes(header[..8].try_into()?);
+        let len = u32::from_le_bytes(header[8..].try_into()?) as usize;
+
+        let start = offset + ENTRY_HEADER_SIZE;
+        let end = start.checked_add(len).context("Key map entry length overflow")?;
+        let key = bytes.get(start..end).ok_or_else(|| corrupted("key extends beyond section"))?;
+        let key = std::str::from_utf8(key).map_err(|_| corrupted("key is not UTF-8"))?;
+
+        entries.push((id, key));
+        offset = end;
+    }
+
+    if offset != bytes.len() {
+        anyhow::bail!(corrupted("has trailing bytes"));
+    }
+    Ok(entries)
+}
+
+/// Serialize `entries` in the section format
+fn entries_bytes(entries: &[(u64, &str)]) -> Vec<u8> {
+    let size = 8 + entries.iter().map(|(_, key)| ENTRY_HEADER_SIZE + key.len()).sum::<usize>();
+    let mut bytes = Vec::with_capacity(size);
+    bytes.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+    for (id, key) in entries {
+        bytes.extend_from_slice(&id.to_le_bytes());
+        bytes.extend_from_slice(&(key.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(key.as_bytes());
+    }
+    bytes
+}
+
+impl JournaledMap for KeyMap {
+    const TAG: &'static SectionTag = KEYS_SECTION;
+
+    fn changes(&self) -> &Changes {
+        &self.changes
+    }
+
+    fn changes_mut(&mut self) -> &mut Changes {
+        &mut self.changes
+    }
+
+    /// Serialize to the on-disk section format, in ID order.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut entries: Vec<_> = self.keys.iter().map(|(&id, key)| (id, key.as_str())).collect();
+        entries.sort_unstable_by_key(|&(id, _)| id);
+        entries_bytes(&entries)
+    }
+
+    /// Deserialize from the on-disk section format.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Corrupted` if the payload is truncated, holds invalid UTF-8, or
+    /// maps a key or ID twice.
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut map = Self::default();
+        map.insert_entries(&parse_entries(bytes)?)?;
+        map.changes = Changes::default();
+        Ok(map)
+    }
+
+    fn delta_bytes(&self, ids: &BTreeSet<u64>) -> Vec<u8> {
+        let entries: Vec<_> = ids.iter().map(|&id| (id, self.key(id).unwrap_or(""))).collect();
+        entries_bytes(&entries)
+    }
+
+    /// Entries replace the key of their ID; an empty key removes it. Keys
+    /// may move between IDs within a record, so all are removed first.
+    fn apply_delta(&mut self, bytes: &[u8]) -> Result<()> {
+        let mut entries = parse_entries(bytes)?;
+        for &(id, _) in &entries {
+            self.remove_id(id);
+        }
+        entries.retain(|(_, key)| !key.is_empty());
+        self.insert_entries(&entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_map_roundtrip() {
+        let mut map = KeyMap::default();
+        map.insert("doc:1", 4);
+        map.insert("ключ", 0);
+        map.insert("doc:2", 9);
+        map.remove_id(9);
+        assert!(!map.changes().is_empty());
+
+        let bytes = map.to_bytes();
+
+        let restored = KeyMap::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.id("doc:1"), Some(4));
+        assert_eq!(restored.key(0), Some("ключ"));
+        assert_eq!(restored.id("doc:2"), None);
+        assert!(restored.changes().is_empty());
+
+        assert!(KeyMap::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_key_map_truncate() {
+        let mut map = KeyMap::default();
+        map.insert("kept", 1);
+        map.insert("ghost", 5);
+        let mut restored = KeyMap::from_bytes(&map.to_bytes()).unwrap();
+        map.changes_mut().rewritten(0);
+
+        map.truncate(5);
+        map.insert("ghost", 2);
+        map.remove_id(1);
+        map.insert("kept", 3);
+        assert_eq!(map.id("kept"), Some(3));
+        assert_eq!(map.key(5), None);
+
+        // The journal record replays the removals and the moved keys
+        restored.apply_delta(&map.delta_bytes(map.changes().ids().unwrap())).unwrap();
+        assert_eq!(restored.to_bytes(), map.to_bytes());
+        assert_eq!(restored.id("ghost"), Some(2));
+        assert_eq!(restored.key(1), None);
+    }
+
+    #[test]
+    fn test_validate_key() {
+        assert!(KeyMap::validate("doc:123").is_ok());
+        assert!(KeyMap::validate("").is_err());
+        assert!(KeyMap::validate(&"x".repeat(MAX_KEY_LEN + 1)).is_err());
+    }
+}
@@ -46,42 +46,708 @@
 //! These concerns are left to the application layer. Chassis is a storage
 //! primitive, like SQLite for relational data.
 
+/// Returns early with an error at a failpoint armed by a crash test (no-op
+/// without the `failpoints` feature)
+macro_rules! fail_point {
+    ($name:expr) => {
+        #[cfg(feature = "failpoints")]
+        $crate::failpoint::hit($name)?;
+    };
+}
+
+/// Embed an index file in the binary, aligned for `VectorIndex::open_from_bytes()`
+///
+/// Expands to a `&'static [u8]` of the file's contents that starts on a
+/// 4 KiB boundary, as the index header requires. The path is resolved like
+/// `include_bytes!`, relative to the current source file.
+#[macro_export]
+macro_rules! include_index {
+    ($path:expr) => {{
+        #[repr(C, align(4096))]
+        struct Aligned<T: ?Sized>(T);
+        static ALIGNED: &Aligned<[u8]> = &Aligned(*include_bytes!($path));
+        &ALIGNED.0
+    }};
+}
+
+#[cfg(feature = "alloc-audit")]
+pub mod alloc_audit;
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+mod async_index;
+mod cancel;
+#[cfg(not(target_arch = "wasm32"))]
+mod collections;
+mod dirty;
 pub mod distance;
+mod element;
+mod embed;
+#[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+mod encrypted;
+mod error;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod eval;
+mod expiry;
+#[cfg(all(feature = "io-formats", not(target_arch = "wasm32")))]
+mod export;
+#[cfg(feature = "failpoints")]
+pub mod failpoint;
+#[cfg(not(target_arch = "wasm32"))]
+mod fork;
+mod free_ids;
+mod groups;
+mod handle;
 mod header;
 mod hnsw;
+mod instrument;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod interop;
+mod keys;
+mod mapping;
+#[cfg(not(target_arch = "wasm32"))]
+mod merge;
+#[cfg(not(target_arch = "wasm32"))]
+mod merger;
+mod metadata;
+mod metrics;
+mod migrate;
+#[cfg(all(feature = "io-formats", not(target_arch = "wasm32")))]
+mod npy;
+mod pin;
+mod preset;
+mod profile;
+mod progress;
+#[cfg(not(target_arch = "wasm32"))]
+mod quantize;
+#[cfg(feature = "linalg")]
+mod rotation;
 mod storage;
+mod tags;
+mod tiered;
+#[cfg(not(target_arch = "wasm32"))]
+mod writer;
 
 #[cfg(feature = "internals")]
 pub use hnsw::*;
 
-pub use distance::{DistanceMetric, cosine_distance, euclidean_distance};
-pub use header::{HEADER_SIZE, Header, MAGIC, VERSION};
-pub use hnsw::{HnswBuilder, HnswGraph, HnswParams, SearchResult};
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+pub use async_index::AsyncVectorIndex;
+pub use cancel::CancelToken;
+#[cfg(not(target_arch = "wasm32"))]
+pub use collections::{COLLECTIONS_MAGIC, Collections, MAX_COLLECTIONS, MAX_NAME_LEN};
+pub use distance::{
+    CustomDistance, Distance, MAX_DISTANCE_NAME_LEN, cosine_distance, dot_product,
+    euclidean_distance, manhattan_distance, squared_euclidean,
+};
+pub use element::ElementType;
+pub use embed::Embedder;
+#[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+pub use encrypted::EncryptionKey;
+pub use error::{ErrorKind, FileStolen};
+#[cfg(all(feature = "io-formats", not(target_arch = "wasm32")))]
+pub use export::ExportFormat;
+#[cfg(not(target_arch = "wasm32"))]
+pub use fork::IndexFork;
+pub use handle::{ReadHandle, WriteHandle};
+pub use header::{
+    HEADER_SIZE, Header, LibraryVersion, MAGIC, MAX_PAGE_SIZE, MAX_USER_META_LEN, MIN_VERSION,
+    VERSION,
+};
+pub use hnsw::{
+    BuildProgress, EarlyTermination, HnswBuilder, HnswGraph, HnswParams, MAX_CONNECTIONS_0,
+    SearchBudget, SearchResult,
+};
+pub use instrument::{AddEvent, FlushEvent, Instrumentation, SearchEvent};
+pub use keys::MAX_KEY_LEN;
+pub use mapping::MemoryMode;
+#[cfg(not(target_arch = "wasm32"))]
+pub use merge::MergedIndex;
+#[cfg(not(target_arch = "wasm32"))]
+pub use merger::MergingTieredIndex;
+pub use metrics::IndexMetrics;
+#[cfg(not(target_arch = "wasm32"))]
+pub use migrate::migrate_file;
+pub use pin::GraphPin;
+pub use preset::Preset;
+pub use profile::WorkloadProfile;
+pub use progress::{ProgressHandler, ProgressStage};
+#[cfg(feature = "linalg")]
+pub use rotation::Rotation;
 pub use storage::Storage;
+#[cfg(not(target_arch = "wasm32"))]
+pub use storage::graph_file_path;
+pub use tags::MAX_TAG;
+pub use tiered::{TieredIndex, TieredOptions};
+#[cfg(not(target_arch = "wasm32"))]
+pub use writer::{IndexWriter, InsertPriority, Pending};
 
 use anyhow::Result;
-use hnsw::layer_from_uniform;
+use element::Query;
+use embed::EmbedderSlot;
+use error::Tagged;
+use expiry::ExpiryMap;
+use free_ids::FreeIds;
+use groups::GroupMap;
+use hnsw::{BudgetMeter, SearchScratch, adaptive_ef, layer_from_uniform, write_results};
+use instrument::Hooks;
+use keys::KeyMap;
+use profile::{Timer, WorkloadStats};
+use progress::ProgressSlot;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
+#[cfg(not(target_arch = "wasm32"))]
 use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::{PoisonError, RwLock};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+use std::time::SystemTime;
+use tags::TagMap;
 
 /// Maximum candidates to pass to diversity heuristic (cache limit)
 const MAX_CANDIDATES_FOR_HEURISTIC: usize = 33;
 
+/// Most recent node records checked on open after an unclean shutdown
+const TAIL_RECORDS_CHECKED: u64 = 1024;
+
+/// Pending vectors `add()` and `flush()` link past `flat_threshold`
+const LINK_STEP: u64 = 16;
+
 /// Configuration options for VectorIndex
 #[derive(Debug, Clone)]
 pub struct IndexOptions {
-    /// Maximum connections per node (M parameter)
+    /// Maximum connections per node (M parameter).
+    ///
+    /// Stored in the file: only used when creating an index. An existing index
+    /// keeps the value it was created with.
     pub max_connections: u16,
 
-    /// Construction quality parameter (efConstruction)
+    /// Maximum connections per node on layer 0 (M0 parameter). Default:
+    /// `None`, which means `2 * max_connections`
+    ///
+    /// Layer 0 holds every vector and does most of the routing. Some datasets
+    /// reach the same recall at a lower `ef_search` with `3 * max_connections`,
+    /// at a cost of 8 bytes per extra link in every node record. Must be
+    /// between `max_connections` and `MAX_CONNECTIONS_0`. Stored in the file
+    /// like `max_connections`.
+    pub max_connections_0: Option<u16>,
+
+    /// Construction quality parameter (efConstruction).
+    ///
+    /// Stored in the file like `max_connections`. Indexes written before it
+    /// was stored take it from the options once, and keep it from then on.
     pub ef_construction: usize,
 
     /// Search quality parameter (efSearch)
     pub ef_search: usize,
+
+    /// Scale the construction beam with the index size. Default: `false`
+    ///
+    /// Inserts search with `ef_construction` scaled by the logarithm of the
+    /// node count, from the layer-0 connection count on an empty index to the
+    /// full `ef_construction` at 1,000,000 nodes (half of it at 1,000 nodes and
+    /// two thirds at 10,000). Small graphs are navigable with a narrow beam, so
+    /// bulk loads into a new index run faster with little loss of recall. Not
+    /// stored in the file.
+    pub adaptive_ef_construction: bool,
+
+    /// Dimensionality of incoming vectors for Matryoshka-style truncation.
+    ///
+    /// When set to `Some(d)`, `add()` and `search()` accept `d`-dimensional
+    /// vectors and keep only the first `dims` components (the dimensionality
+    /// passed to `open()`). Must be `>= dims`. `None` stores vectors as given.
+    pub input_dimensions: Option<u32>,
+
+    /// How to treat files last written by a newer library version
+    pub version_policy: VersionPolicy,
+
+    /// Paging advice for the mapped file (`madvise` on Unix)
+    pub memory_mode: MemoryMode,
+
+    /// On-disk encoding of vector components.
+    ///
+    /// Applied while the index is empty; an index that already holds vectors
+    /// keeps the type it was created with (see `VectorIndex::element_type()`).
+    /// `F16` and `BF16` halve the vector zone at some cost in precision.
+    /// `Binary` keeps only the sign of each component and compares vectors by
+    /// Hamming distance; it rules out `normalize` and custom distances.
+    pub element_type: ElementType,
+
+    /// Maximum number of graph layers (1..=16).
+    ///
+    /// Fixes the size of every node record, so like `max_connections` it is
+    /// stored in the file and only used when creating an index. Fewer layers
+    /// shrink the graph zone; nodes drawn for a higher layer are placed on the
+    /// top one.
+    pub max_layers: u8,
+
+    /// Minimum bytes the file grows by when it runs out of room.
+    ///
+    /// Rounded up to whole pages (see `Storage::set_growth_chunk()`). Not
+    /// stored in the file.
+    pub growth_chunk: usize,
+
+    /// Size the index file may not grow past, including its graph file.
+    /// Default: `None` (no limit)
+    ///
+    /// An `add()` that needs more room fails with `QuotaExceeded` and leaves
+    /// the index as it was, so apps can enforce a storage budget instead of
+    /// running out of disk space. Not stored in the file.
+    pub max_file_bytes: Option<u64>,
+
+    /// Node records to keep in memory between flushes. Default: `0` (none)
+    ///
+    /// An insert rewrites its own record and those of the neighbors it links
+    /// to, dirtying pages all over the graph zone. Buffered records are
+    /// written to the file together on `flush()`, in file order, or earlier
+    /// once this many are waiting, so each page is written back once per
+    /// flush instead of whenever the OS gets to it: fewer, mostly sequential
+    /// writes, which spares flash storage such as SD cards. Costs up to this
+    /// many node records of memory (272 bytes each with the default
+    /// parameters). Not stored in the file.
+    pub write_buffer_records: usize,
+
+    /// Answer searches by scanning every vector while the index holds at most
+    /// this many.
+    ///
+    /// Below a few thousand vectors a linear scan is about as fast as the
+    /// graph and always exact. `0` (the default) always uses the graph; see
+    /// also `VectorIndex::search_exact()`.
+    pub exact_search_threshold: u64,
+
+    /// Insert vectors without linking them into the graph while the index
+    /// holds fewer than this many.
+    ///
+    /// Until then `add()` skips neighbor selection, the bulk of its cost, and
+    /// searches scan every vector, so small personal datasets get fast inserts
+    /// and exact results. Past the threshold, each `add()` and `flush()` first
+    /// links up to 16 of the pending vectors, so the graph catches up over
+    /// the following inserts without one long pause, and searches walk it
+    /// once no vector is pending. `link_graph()` or `link_graph_batch()` link
+    /// them sooner, at an idle moment, and an `IndexWriter` links them between
+    /// writes. Pending vectors are recorded in the file, so they stay pending
+    /// whatever the option is on reopen. `0` (the default) links every vector
+    /// on insert.
+    pub flat_threshold: u64,
+
+    /// When to start a `flush_async()` without being asked. Default: `Manual`
+    pub flush_policy: FlushPolicy,
+
+    /// Distance function for construction and search. Default: `Euclidean`
+    ///
+    /// Recorded in the file when the index is created: reopening it with a
+    /// different distance (including the default, for an index built with a
+    /// custom one) fails with `ErrorKind::InvalidArgument`.
+    pub distance: Distance,
+
+    /// Scale vectors to unit length on `add()` and compare them by cosine distance.
+    ///
+    /// Queries are normalized too, and Euclidean distance is replaced by
+    /// `1 - dot product`, which costs less than L2 on unit vectors. Zero
+    /// vectors are rejected. Stored in the file like `max_connections`: an
+    /// index holding vectors keeps the value it was created with. With a
+    /// custom `distance`, vectors are normalized but compared by that function.
+    pub normalize: bool,
+
+    /// Keep the graph in a second file next to the index (`<path>-graph`).
+    ///
+    /// The single-file layout leaves a sparse gap between the vector and graph
+    /// zones, which some backup tools copy in full and filesystems without
+    /// sparse files (FAT32 SD cards) allocate. With a graph file both files
+    /// grow densely and are always opened, snapshotted and flushed together;
+    /// move or delete them together too. Only applied when the index is
+    /// created: an existing index keeps its layout. Not supported for
+    /// in-memory indexes or collections.
+    pub graph_file: bool,
+
+    /// Page size the file's zones are aligned to, in bytes. Default: 4096
+    ///
+    /// A power of two up to `MAX_PAGE_SIZE`. Larger pages suit flash with
+    /// 16 KiB erase blocks or mappings backed by 2 MiB huge pages: the vector
+    /// zone starts one page into the file, and the graph zone, the metadata
+    /// zone and every growth of the file fall on page boundaries. Stored in
+    /// the file and only applied when the index is created; collections use
+    /// 4 KiB pages.
+    pub page_size: usize,
+
+    /// Whether `add()` reuses the IDs of deleted vectors. Default: `AppendOnly`
+    ///
+    /// Not stored in the file: deleted IDs are tracked either way, so an index
+    /// can be reopened with another policy.
+    pub id_reuse: IdReuse,
+
+    /// Overwrite the stored vector with zeros when it is deleted. Default: `false`
+    ///
+    /// Deletion otherwise only flags the vector, and its bytes stay in the
+    /// file until the slot is reused, which matters for personal embeddings
+    /// in a file that may be copied off the device. The group, tags and
+    /// expiry go with it (the key is always dropped). The node keeps routing
+    /// searches from the zero vector, which can cost some recall until its
+    /// slot is reused. Older versions of the metadata zone, and in an
+    /// encrypted file the previous ciphertext of each block, stay in the file
+    /// until overwritten. Not stored in the file.
+    pub scrub_deleted: bool,
+
+    /// What `add()` and `search()` do with NaN or infinite components.
+    /// Default: `Reject`
+    ///
+    /// Not stored in the file: it only governs incoming vectors.
+    pub nan_policy: NanPolicy,
 }
 
 impl Default for IndexOptions {
     fn default() -> Self {
-        Self { max_connections: 16, ef_construction: 200, ef_search: 50 }
+        Self {
+            max_connections: 16,
+            max_connections_0: None,
+            ef_construction: 200,
+            ef_search: 50,
+            adaptive_ef_construction: false,
+            input_dimensions: None,
+            version_policy: VersionPolicy::default(),
+            memory_mode: MemoryMode::default(),
+            element_type: ElementType::default(),
+            max_layers: 16,
+            growth_chunk: storage::PAGE_SIZE,
+            max_file_bytes: None,
+            write_buffer_records: 0,
+            exact_search_threshold: 0,
+            flat_threshold: 0,
+            flush_policy: FlushPolicy::default(),
+            distance: Distance::default(),
+            normalize: false,
+            graph_file: false,
+            page_size: storage::PAGE_SIZE,
+            id_reuse: IdReuse::default(),
+            scrub_deleted: false,
+            nan_policy: NanPolicy::default(),
+        }
+    }
+}
+
+/// When a `VectorIndex` flushes on its own
+///
+/// Automatic flushes run `flush_async()` after an `add()`, so the writer only
+/// pays for handing dirty pages to the OS; the sync happens in the background.
+/// Time is only checked on inserts: an idle index is not flushed. Ignored on
+/// `wasm32`, which has no background threads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlushPolicy {
+    /// Only flush when `flush()` or `flush_async()` is called
+    #[default]
+    Manual,
+
+    /// Flush after this many inserts since the last flush
+    EveryInserts(u64),
+
+    /// Flush on the first insert at least this many milliseconds after the last flush
+    EveryMillis(u64),
+}
+
+/// Where `add()` puts new vectors once some have been deleted
+///
+/// Deletion only flags a vector, so an index under a steady stream of
+/// deletes and adds keeps growing unless IDs are reused.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IdReuse {
+    /// Always append under a new ID; deleted IDs are never handed out again
+    #[default]
+    AppendOnly,
+
+    /// Store new vectors in the slots of deleted ones, most recently deleted
+    /// first, and append only when none is left
+    ///
+    /// The vector and node record are overwritten in place and the node is
+    /// linked anew, keeping its layer count. The file keeps the slot deleted
+    /// until the next `flush()` has synced the new vector, so a crash before
+    /// then leaves it deleted, never holding a torn vector, and
+    /// `SearchConsistency::DurableOnly` searches skip it until then. With
+    /// reused slots pending, `flush_async()` flushes synchronously.
+    /// Applications that hold on to IDs of deleted vectors should drop them
+    /// before the IDs come back.
+    ReuseDeleted,
+}
+
+/// How incoming vectors with NaN or infinite components are handled
+///
+/// A single NaN makes every distance to the vector NaN, which sorts after
+/// all real distances: the vector is never found and, once linked, wastes
+/// its neighbors' slots. Infinities do the same to any vector they meet.
+/// The check covers the components that are stored or searched, before
+/// normalization, and costs one SIMD pass over them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NanPolicy {
+    /// Fail with `ErrorKind::InvalidArgument`
+    #[default]
+    Reject,
+
+    /// Replace each NaN or infinite component with `0.0` and go on
+    ///
+    /// For pipelines that emit the odd broken component and prefer a
+    /// degraded vector to a failed batch. A vector that is all zero after
+    /// the replacement is still rejected by `normalize`.
+    ReplaceWithZero,
+}
+
+/// Compatibility policy for files written by other library versions
+///
+/// Every flush records the writing library version in the file header (see
+/// `VectorIndex::written_by()`). Older libraries may not understand data that
+/// newer ones add, so applications that ship mixed versions can refuse such
+/// files instead of risking silent misinterpretation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VersionPolicy {
+    /// Open files written by any library version (advisory only)
+    #[default]
+    Allow,
+
+    /// Refuse files last written by a library with a newer `major.minor`
+    RefuseNewerMinor,
+}
+
+impl VersionPolicy {
+    /// Check the recorded writer version of a file against this library
+    fn check(self, written_by: Option<LibraryVersion>) -> Result<()> {
+        if self == Self::RefuseNewerMinor
+            && let Some(writer) = written_by
+            && writer.is_newer_minor_than(&LibraryVersion::CURRENT)
+        {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::IncompatibleVersion,
+                format!(
+                    "Index was written by Chassis {}, which is newer than this library ({})",
+                    writer,
+                    LibraryVersion::CURRENT
+                )
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Order of exact search results: by distance, then by ID
+fn exact_order(a: &SearchResult, b: &SearchResult) -> std::cmp::Ordering {
+    a.distance.total_cmp(&b.distance).then(a.id.cmp(&b.id))
+}
+
+/// What a search returns, as reported to instrumentation
+trait SearchOutput {
+    /// Number of results found
+    fn result_count(&self) -> usize;
+}
+
+impl SearchOutput for Vec<SearchResult> {
+    fn result_count(&self) -> usize {
+        self.len()
+    }
+}
+
+/// Results written to caller buffers (`search_into()`)
+impl SearchOutput for usize {
+    fn result_count(&self) -> usize {
+        *self
+    }
+}
+
+/// The `k` nearest of `results`, in `exact_order`
+fn nearest(mut results: Vec<SearchResult>, k: usize) -> Vec<SearchResult> {
+    if k < results.len() {
+        results.select_nth_unstable_by(k, exact_order);
+        results.truncate(k);
+    }
+    results.sort_unstable_by(exact_order);
+    results
+}
+
+/// Which vectors a search may return
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchConsistency {
+    /// Return every inserted vector, including ones not yet flushed (read-your-writes)
+    #[default]
+    IncludeUnflushed,
+
+    /// Return only vectors that were durable at the last `flush()` (or at open)
+    DurableOnly,
+}
+
+/// Per-search options for `VectorIndex::search_with_options`
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// Visibility of unflushed vectors. Default: `IncludeUnflushed`
+    pub consistency: SearchConsistency,
+
+    /// Adaptive early termination for k-NN searches. Default: `None`
+    ///
+    /// Trades a little recall for fewer distance evaluations on easy queries;
+    /// see `EarlyTermination`. Ignored by exact scans and `search_within()`.
+    pub early_termination: Option<EarlyTermination>,
+
+    /// Hard cap on the work of a k-NN search. Default: `None`
+    ///
+    /// See `SearchBudget` and `VectorIndex::search_with_budget()`. Exact scans
+    /// stop at the budget too; ignored by `search_within()`.
+    pub budget: Option<SearchBudget>,
+
+    /// Abandons the search once cancelled. Default: `None`
+    ///
+    /// Checked before each candidate expansion (every 64 vectors of an exact
+    /// scan); the search then fails with `ErrorKind::Cancelled`. For
+    /// interactive search, cancel the previous query's token when a new one
+    /// starts.
+    pub cancel: Option<CancelToken>,
+}
+
+/// Search result with a copy of the stored vector, from `VectorIndex::search_with_vectors()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorResult {
+    /// Vector ID
+    pub id: u64,
+
+    /// Distance from the query
+    pub distance: f32,
+
+    /// Stored vector, decoded to `f32` (`dimensions()` components)
+    pub vector: Vec<f32>,
+}
+
+/// Search result with the key its vector was added under, from `VectorIndex::search_with_keys()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyedResult {
+    /// Vector ID
+    pub id: u64,
+
+    /// Key passed to `add_with_key()`, or `None` for vectors added without one
+    pub key: Option<String>,
+
+    /// Distance from the query
+    pub distance: f32,
+}
+
+/// Best hits of one group, from `VectorIndex::search_grouped()`
+#[derive(Debug, Clone)]
+pub struct GroupedResult {
+    /// Group ID, or `None` for a vector without a group (a group of its own)
+    pub group: Option<u64>,
+
+    /// Hits from the group, nearest first (at most `group_size`)
+    pub hits: Vec<SearchResult>,
+}
+
+/// Approximate memory used by an open index, from `VectorIndex::memory_footprint()`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryFootprint {
+    /// Size of the mapping (address space, not necessarily in RAM)
+    pub mapped_bytes: u64,
+
+    /// Mapped bytes currently in RAM, or `None` where the platform cannot
+    /// report it. For files this is page cache the OS may reclaim under
+    /// pressure, and shrinks after `release_memory()` only once it does.
+    pub resident_bytes: Option<u64>,
+
+    /// Heap held for the lifetime of the index (caches such as the rotation,
+    /// search buffers pooled for reuse)
+    pub heap_bytes: u64,
+
+    /// Scratch used by one search or insert (visited set, heaps)
+    pub scratch_bytes: u64,
+}
+
+impl MemoryFootprint {
+    /// Estimated total: resident (or, if unknown, mapped) bytes plus heap and scratch
+    pub fn total(&self) -> u64 {
+        self.resident_bytes.unwrap_or(self.mapped_bytes) + self.heap_bytes + self.scratch_bytes
+    }
+}
+
+/// Shape and size of an index, from `VectorIndex::stats()`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IndexStats {
+    /// Vectors in the index, including deleted ones
+    pub node_count: u64,
+
+    /// Vectors marked deleted; their space is not reclaimed
+    pub deleted_count: u64,
+
+    /// Highest graph layer in use
+    pub max_layer: usize,
+
+    /// Nodes on each layer, from layer 0 up to `max_layer` (empty for an empty index)
+    pub layer_node_counts: Vec<u64>,
+
+    /// Mean number of layer 0 neighbors per node
+    pub avg_out_degree: f64,
+
+    /// Size of the index image: the file, its window in a collection, or the buffer
+    pub file_bytes: u64,
+
+    /// Bytes of stored vectors
+    pub vector_bytes: u64,
+
+    /// Bytes of the graph zone: its headers and node records
+    pub graph_bytes: u64,
+}
+
+impl IndexStats {
+    /// Fraction of vectors that are deleted, from 0.0 to 1.0
+    pub fn deleted_fraction(&self) -> f64 {
+        if self.node_count == 0 {
+            return 0.0;
+        }
+        self.deleted_count as f64 / self.node_count as f64
+    }
+}
+
+/// Recovery actions taken while opening an index, from `VectorIndex::open_with_report()`
+///
+/// Chassis keeps no write-ahead log: a vector is durable once a flush covers
+/// it, and whatever a crashed writer added after its last flush is rolled
+/// back on open. The rolled back vectors are the only data an application
+/// can lose, so a report with a non-empty `reclaimed_ids` is worth surfacing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpenReport {
+    /// IDs of vectors found in the file past the last flushed graph header
+    /// (ghost vectors), together with their keys, groups and tags. Their
+    /// space and IDs are reused by the next inserts; a shared reader only
+    /// hides them.
+    pub reclaimed_ids: Range<u64>,
+
+    /// Whether a graph stored at the old fixed offset was moved into the
+    /// current layout
+    pub legacy_layout_compacted: bool,
+
+    /// Upgrades of an older file layout performed in memory, in the order
+    /// they ran (see `migrate_file()`); written to the file by the next flush
+    pub migrations: Vec<&'static str>,
+
+    /// Whether the last writer closed the file with every write flushed
+    ///
+    /// Then open trusts the counts in the headers and does no further work.
+    /// Otherwise (a crash, or a process that exited after writing without a
+    /// flush) a writer also recounts the deleted flags and checks the most
+    /// recent node records before using the file.
+    pub clean_shutdown: bool,
+
+    /// Whether the deleted count stored in the graph header disagreed with
+    /// the node flags and was corrected (only after an unclean shutdown)
+    pub deleted_count_repaired: bool,
+
+    /// Number of commits made to the file when it was opened (see
+    /// `VectorIndex::commit_epoch()`)
+    pub commit_epoch: u64,
+}
+
+impl OpenReport {
+    /// Number of ghost vectors rolled back
+    pub fn ghosts_reclaimed(&self) -> u64 {
+        self.reclaimed_ids.end - self.reclaimed_ids.start
+    }
+
+    /// Returns `true` if open found the file as the last flush left it
+    pub fn is_clean(&self) -> bool {
+        self.reclaimed_ids.is_empty() && self.migrations.is_empty() && !self.deleted_count_repaired
     }
 }
 
@@ -100,6 +766,49 @@ pub struct VectorIndex {
 
     /// Layer multiplier cache: 1.0 / ln(M)
     ml: f32,
+
+    /// Node count as of the last completed flush (or open); IDs below this
+    /// are durable. Shared with the thread running a `flush_async()`.
+    durable_count: Arc<AtomicU64>,
+
+    /// Inserts since the last flush, for `FlushPolicy::EveryInserts`
+    #[cfg(not(target_arch = "wasm32"))]
+    unflushed_inserts: u64,
+
+    /// Start of the last flush, for `FlushPolicy::EveryMillis`
+    #[cfg(not(target_arch = "wasm32"))]
+    last_flush: Instant,
+
+    /// Trained PCA rotation, if one has been persisted in the file
+    #[cfg(feature = "linalg")]
+    rotation: Option<Rotation>,
+
+    /// Application keys of vectors added with `add_with_key()`
+    keys: KeyMap,
+
+    /// Group IDs for `search_grouped()`
+    groups: GroupMap,
+
+    /// Tag sets for `search_with_tags()`
+    tags: TagMap,
+
+    /// Deadlines for `purge_expired()`
+    expiry: ExpiryMap,
+
+    /// Deleted IDs for `IdReuse::ReuseDeleted`
+    free_ids: FreeIds,
+
+    /// Usage counters for `export_workload_profile()`
+    stats: WorkloadStats,
+
+    /// Instrumentation attached with `set_instrumentation()`
+    hooks: Hooks,
+
+    /// Embedder attached with `set_embedder()`
+    embedder: EmbedderSlot,
+
+    /// Progress handler attached with `set_progress_handler()`
+    progress: ProgressSlot,
 }
 
 impl VectorIndex {
@@ -111,6 +820,10 @@ impl VectorIndex {
     /// * `dims` - Number of dimensions per vector
     /// * `options` - Index configuration options
     ///
+    /// If the file already holds an index, its graph parameters
+    /// (`max_connections`, `ef_construction`, `max_layers`) are read from the
+    /// file and override those in `options`; see `options()`.
+    ///
     /// # Crash Consistency
     ///
     /// This method handles ghost nodes (vectors written but not indexed due to crash):
@@ -118,146 +831,2046 @@ impl VectorIndex {
     /// - If `storage.count() > graph.node_count()`: Ghost vectors are ignored
     /// - If `storage.count() == graph.node_count()`: Success
     ///
+    /// Use `open_with_report()` to learn whether ghost vectors were rolled back.
+    ///
     /// # Errors
     ///
     /// Returns an error if:
     /// - The file cannot be opened or created
     /// - The file is corrupted
     /// - Dimension mismatch with existing index
+    /// - `options.input_dimensions` is smaller than `dims`
     /// - Graph references non-existent vectors
+    /// - `options.version_policy` refuses the library version that wrote the file
+    #[cfg(not(target_arch = "wasm32"))]
     pub fn open<P: AsRef<Path>>(path: P, dims: u32, options: IndexOptions) -> Result<Self> {
+        Self::open_with_report(path, dims, options).map(|(index, _)| index)
+    }
+
+    /// Open or create a vector index like `open()`, and report the recovery
+    /// actions open took
+    ///
+    /// Applications can log the `OpenReport` or tell their users when vectors
+    /// added after the last flush were lost to a crash.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for the same reasons as `open()`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_with_report<P: AsRef<Path>>(
+        path: P,
+        dims: u32,
+        options: IndexOptions,
+    ) -> Result<(Self, OpenReport)> {
+        Self::check_input_dimensions(dims, &options)?;
+
         // Open storage
         let storage = Storage::open(path, dims)?;
 
-        // Compute layer multiplier
-        let ml = 1.0 / (options.max_connections as f32).ln();
+        Self::from_storage_with_report(storage, options)
+    }
 
-        // Create HNSW params from options
-        let params = HnswParams {
-            max_connections: options.max_connections,
-            ef_construction: options.ef_construction,
-            ef_search: options.ef_search,
-            ml,
-            max_layers: 16, // Fixed for now
-        };
+    /// Open an existing index without knowing its shape
+    ///
+    /// Reads the dimensions from the file header and, like `open()`, the graph
+    /// parameters from the graph header. Settings that are not stored in the
+    /// file, such as `ef_search`, are taken from `options`. Unlike `open()`,
+    /// this never creates a file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or is not a Chassis index,
+    /// or for the same reasons as `open()`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_existing<P: AsRef<Path>>(path: P, options: IndexOptions) -> Result<Self> {
+        let storage = Storage::open_existing(path)?;
+        Self::check_input_dimensions(storage.dimensions(), &options)?;
+        Self::from_storage(storage, options)
+    }
 
-        // Open graph
-        let mut graph = HnswGraph::open(storage, params)?;
+    /// Open an existing index for reading, shared with other processes
+    ///
+    /// Any number of processes (or handles) can hold a shared reader at once.
+    /// While one does, `open()` fails with a lock error, and a shared open fails
+    /// while a writer holds the file. This maps to `LockFileEx` shared locks and
+    /// a copy-on-write file mapping object on Windows, and to `flock(LOCK_SH)`
+    /// with a `MAP_PRIVATE` mapping on Unix.
+    ///
+    /// The returned index rejects `add()`, `flush()` and other mutations. Ghost
+    /// vectors left by a crashed writer are hidden in memory only; the next
+    /// writer reclaims them as usual.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The file does not exist, is corrupted, or has different dimensions
+    /// - A writer currently holds the file
+    /// - The file has no graph zone (it was never opened for writing)
+    /// - `options.input_dimensions` is smaller than `dims`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_shared<P: AsRef<Path>>(path: P, dims: u32, options: IndexOptions) -> Result<Self> {
+        Self::check_input_dimensions(dims, &options)?;
+        Self::from_storage(Storage::open_shared(path, dims)?, options)
+    }
 
-        // Consistency check: Ghost node handling
-        let storage_count = graph.storage.count();
-        let graph_node_count = graph.node_count();
+    /// Open a finished index that is never written at runtime
+    ///
+    /// Meant for pre-built indexes shipped inside app bundles or on read-only
+    /// media. Unlike `open_shared()`, the file is mapped without write access
+    /// (`PROT_READ`) and not locked at all, and open performs no ghost vector
+    /// rollback. The file must therefore be complete, as left by `flush()`
+    /// after the last insert, and must not change while the index is open.
+    /// Dimensions and graph parameters are read from the file; settings not
+    /// stored there, such as `ef_search`, come from `options`.
+    ///
+    /// The returned index is `Send + Sync`: share it between threads behind
+    /// an `Arc` and search from all of them. Mutations fail with `ReadOnly`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The file (or its graph file) does not exist or is not a Chassis index
+    /// - The file has no graph zone, or vectors left unindexed by a crash
+    /// - `options.input_dimensions` is smaller than the file's dimensions
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_sealed<P: AsRef<Path>>(path: P, options: IndexOptions) -> Result<Self> {
+        let storage = Storage::open_sealed(path)?;
+        Self::check_input_dimensions(storage.dimensions(), &options)?;
+        Self::from_storage(storage, options)
+    }
 
-        if storage_count < graph_node_count {
-            // Graph references vectors that don't exist = corruption
-            anyhow::bail!(
-                "Index corruption detected: graph has {} nodes but storage has only {} vectors",
-                graph_node_count,
-                storage_count
-            );
-        } else if storage_count > graph_node_count {
-            // GHOST NODE RECOVERY
-            // Storage is ahead of Graph (crash during write).
-            // We must rollback Storage to match Graph so the next insert
-            // reclaims the 'ghost' ID instead of appending after it.
-            graph.storage.truncate_logical(graph_node_count);
+    /// Open a finished index image held in memory for the life of the program
+    ///
+    /// For indexes compiled into the binary with `include_index!`, or loaded
+    /// once from an asset store (Android `AAssetManager`, a bundle resource)
+    /// where no file path exists. The image is read in place, without a copy
+    /// or a mapping, and behaves like `open_sealed()`: it must be complete,
+    /// with its graph in the same image, and every mutation fails with
+    /// `ReadOnly`. `bytes` must start on a 4 KiB boundary, which
+    /// `include_index!` guarantees; copy a buffer from elsewhere into an
+    /// aligned allocation and leak it first.
+    ///
+    /// ```ignore
+    /// static INDEX: &[u8] = chassis_core::include_index!("../assets/faq.chassis");
+    ///
+    /// let index = VectorIndex::open_from_bytes(INDEX, IndexOptions::default())?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - `bytes` is not 4 KiB-aligned or is not a Chassis index
+    /// - The image has no graph zone, keeps its graph in a separate file, or
+    ///   holds vectors left unindexed by a crash
+    /// - `options.input_dimensions` is smaller than the image's dimensions
+    pub fn open_from_bytes(bytes: &'static [u8], options: IndexOptions) -> Result<Self> {
+        let storage = Storage::from_static(bytes)?;
+        Self::check_input_dimensions(storage.dimensions(), &options)?;
+        Self::from_storage(storage, options)
+    }
+
+    /// Number of flushes made to the file since commits were first counted
+    ///
+    /// Every flush increases it, so comparing the epoch of a new open (or its
+    /// `OpenReport::commit_epoch`) with one seen earlier tells whether the
+    /// file was written in between.
+    pub fn commit_epoch(&self) -> u64 {
+        self.graph.storage.commit_epoch()
+    }
+
+    /// Check if this index was opened with `open_sealed()` or `open_from_bytes()`
+    pub fn is_sealed(&self) -> bool {
+        self.graph.storage.is_sealed()
+    }
+
+    /// Open or create an index whose file is encrypted with `key`
+    ///
+    /// For apps that keep sensitive embeddings on user devices. Vector and
+    /// graph pages are encrypted and authenticated with AES-256-GCM in 4 KiB
+    /// blocks before they reach the disk, and `flush()` re-encrypts only the
+    /// blocks written since the last flush. A block is decrypted into
+    /// anonymous memory the first time it is read or written, where searches
+    /// read vectors zero-copy as usual: plaintext never touches the file, and
+    /// RAM follows the pages the index uses (which may be swapped out like
+    /// any other memory).
+    ///
+    /// Otherwise the index behaves like one opened with `open()`, including
+    /// ghost rollback after a crash, and `snapshot_to()` copies the encrypted
+    /// file. `options.graph_file` is not supported, nor are `reattach()`,
+    /// shared readers and sealed opens. Requires the `encryption` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for the same reasons as `open()`, and if:
+    /// - The file exists but is not an encrypted index (`Corrupted`)
+    /// - `key` is not the key the file was created with (`InvalidArgument`)
+    /// - A block it reads was modified outside the library (`Corrupted`);
+    ///   later blocks fail the same way when first accessed
+    /// - `options.graph_file` is set (`InvalidArgument`)
+    #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+    pub fn open_encrypted<P: AsRef<Path>>(
+        path: P,
+        dims: u32,
+        key: &EncryptionKey,
+        options: IndexOptions,
+    ) -> Result<Self> {
+        Self::check_input_dimensions(dims, &options)?;
+        if options.graph_file {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Encrypted indexes keep the graph in the index file; unset graph_file"
+            ));
+        }
+        Self::from_storage(Storage::open_encrypted(path, dims, key)?, options)
+    }
+
+    /// Check if this index was opened with `open_encrypted()`
+    pub fn is_encrypted(&self) -> bool {
+        self.graph.storage.is_encrypted()
+    }
+
+    /// Rebind the index to `path` after its file was moved, replaced, or deleted
+    ///
+    /// Use this after `flush()` fails with `FileStolen`. If the file was moved
+    /// to `path`, it is adopted; otherwise the current contents (including
+    /// unflushed vectors) are written to `path`, overwriting any file there.
+    /// Call `flush()` afterwards to make them durable.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index is not file-backed or is a shared reader,
+    /// or if `path` cannot be created, locked, or written.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn reattach<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.graph.storage.reattach(path)
+    }
+
+    /// Get the version of the library that last flushed this index file
+    ///
+    /// Returns `None` for new files that have not been flushed yet and for
+    /// files last written before versions were recorded. After a successful
+    /// `flush()` this is always the running library's version.
+    pub fn written_by(&self) -> Option<LibraryVersion> {
+        self.graph.storage.writer_version()
+    }
+
+    /// Get the time this index file was created
+    ///
+    /// Returns `None` for files created before creation times were recorded
+    /// and for indexes created in the browser, which has no clock.
+    pub fn created_at(&self) -> Option<SystemTime> {
+        let secs = self.graph.storage.created_at()?;
+        Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs))
+    }
+
+    /// Get the version of the library that created this index file
+    ///
+    /// Returns `None` for files created before versions were recorded.
+    pub fn created_by(&self) -> Option<LibraryVersion> {
+        self.graph.storage.created_by()
+    }
+
+    /// Store up to `MAX_USER_META_LEN` bytes of application metadata in the header
+    ///
+    /// Chassis never interprets the bytes. Use them to record what the index
+    /// was built with, such as the embedding model name, and check it with
+    /// `get_meta()` before searching. Replaces any previous metadata; the
+    /// change is durable after the next `flush()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `meta` is too long or the index is read-only.
+    pub fn set_meta(&mut self, meta: &[u8]) -> Result<()> {
+        self.graph.storage.set_user_meta(meta)
+    }
+
+    /// Get the application metadata stored with `set_meta()`, empty if none
+    pub fn get_meta(&self) -> &[u8] {
+        self.graph.storage.user_meta()
+    }
+
+    /// Get the progress of the last `HnswBuilder` bulk build into this file
+    ///
+    /// Returns `None` if no build was recorded. Counts only vectors that were
+    /// durable at open or the last flush.
+    pub fn build_progress(&self) -> Option<BuildProgress> {
+        let (start, total) = self.graph.storage.build_checkpoint()?;
+        let vectors_done = self.durable_len().saturating_sub(start).min(total);
+        let vector_bytes = u64::from(self.dimensions()) * std::mem::size_of::<f32>() as u64;
+        Some(BuildProgress { input_offset: vectors_done * vector_bytes, vectors_done, total })
+    }
+
+    /// Record the start of a bulk build of `total` vectors at the current length
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn begin_build(&mut self, total: u64) {
+        let start = self.len();
+        self.graph.storage.set_build_checkpoint(start, total);
+    }
+
+    /// Change the paging advice for the mapped file
+    ///
+    /// For example, switch to `MemoryMode::Sequential` for a bulk build and
+    /// back to `MemoryMode::Random` for querying.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the OS rejects the advice.
+    pub fn set_memory_mode(&mut self, mode: MemoryMode) -> Result<()> {
+        self.graph.storage.set_memory_mode(mode)?;
+        self.options.memory_mode = mode;
+        Ok(())
+    }
+
+    /// Change the size the index file may not grow past; see
+    /// `IndexOptions::max_file_bytes`
+    ///
+    /// Takes effect with the next growth. Lowering it below the current size
+    /// never shrinks the file, but stops it from growing.
+    pub fn set_max_file_bytes(&mut self, max_file_bytes: Option<u64>) {
+        self.graph.storage.set_max_file_bytes(max_file_bytes);
+        self.options.max_file_bytes = max_file_bytes;
+    }
+
+    /// Warm the pages holding the given vectors and their graph records
+    ///
+    /// Issues `MADV_WILLNEED` so a following burst of queries around these IDs
+    /// does not stall on page faults. The call returns immediately; reading
+    /// happens in the background. No-op for in-memory indexes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an ID is not in the index.
+    pub fn prefetch(&self, ids: &[u64]) -> Result<()> {
+        for &id in ids {
+            self.graph.prefetch_node(id)?;
+        }
+        Ok(())
+    }
+
+    /// Attach `instrumentation` to receive an event for every search, insert
+    /// and flush, or detach it with `None`
+    ///
+    /// Search events count graph hops, distance computations and distinct
+    /// file pages read, so applications can export them to their own
+    /// telemetry. The counters are only collected while instrumentation is
+    /// attached. Attach it before `into_handles()` to instrument the handles.
+    pub fn set_instrumentation(&mut self, instrumentation: Option<Arc<dyn Instrumentation>>) {
+        self.hooks = Hooks(instrumentation);
+    }
+
+    /// Attach `embedder` to turn text into vectors for `add_text()` and
+    /// `search_text()`, or detach it with `None`
+    ///
+    /// The embedder is not stored in the file; attach it again after every
+    /// open. Attach it before `into_handles()` to use it through the handles.
+    pub fn set_embedder(&mut self, embedder: Option<Arc<dyn Embedder>>) {
+        self.embedder = EmbedderSlot(embedder);
+    }
+
+    /// Attach `handler` to follow, and cancel, long-running operations, or
+    /// detach it with `None`
+    ///
+    /// `optimize()`, `import_npy()` and `import_npz()` call the handler with
+    /// their `ProgressStage`, the units of work done and the total, at the
+    /// start, every few dozen units and at the end. It runs on the thread doing
+    /// the work, with the index borrowed, so it must not call back into the
+    /// index. Returning `false` stops the operation with `ErrorKind::Cancelled`
+    /// and keeps what was done up to then.
+    pub fn set_progress_handler(&mut self, handler: Option<Arc<ProgressHandler>>) {
+        self.progress = ProgressSlot(handler);
+    }
+
+    /// The attached embedder, or an `InvalidArgument` error naming `operation`
+    pub(crate) fn embedder(&self, operation: &str) -> Result<Arc<dyn Embedder>> {
+        let embedder = self.embedder.0.clone().ok_or_else(|| {
+            Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!("{}() needs an embedder; attach one with set_embedder()", operation),
+            )
+        })?;
+        Ok(embedder)
+    }
+
+    /// Summarize this index and how it has been used, without any vector data
+    ///
+    /// The profile holds the index shape (dimensions, size, HNSW parameters)
+    /// and counters since the index was opened: inserts, query rate, and
+    /// histograms of `k`, effective `ef` and search latency. It contains no
+    /// vectors, queries, IDs or paths; attach `to_json()` to bug reports so
+    /// performance issues can be reproduced with synthetic data.
+    pub fn export_workload_profile(&self) -> WorkloadProfile {
+        self.stats.profile(self)
+    }
+
+    /// Estimate the memory used by this index
+    ///
+    /// Use this to attribute memory in app diagnostics or to react before the
+    /// OS enforces limits. `resident_bytes` counts a page once however many
+    /// processes map it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the OS rejects the residency query.
+    pub fn memory_footprint(&self) -> Result<MemoryFootprint> {
+        let storage = &self.graph.storage;
+
+        let mut heap_bytes = std::mem::size_of::<Self>();
+        #[cfg(feature = "linalg")]
+        if let Some(rotation) = &self.rotation {
+            heap_bytes += rotation.heap_bytes();
+        }
+        heap_bytes += self.keys.heap_bytes() + self.groups.heap_bytes() + self.tags.heap_bytes();
+        heap_bytes += self.expiry.heap_bytes() + self.free_ids.heap_bytes();
+        heap_bytes += self.graph.scratch_pool.heap_bytes();
+
+        // A search holds a u32 stamp per node plus candidate and result heaps of up to ef entries
+        let ef = self.options.ef_search.max(self.options.ef_construction);
+        let visited = self.len() * 4;
+        let heaps = (2 * ef * std::mem::size_of::<SearchResult>()) as u64;
+
+        Ok(MemoryFootprint {
+            mapped_bytes: storage.mapped_len() as u64,
+            resident_bytes: storage.resident_bytes()?.map(|bytes| bytes as u64),
+            heap_bytes: heap_bytes as u64,
+            scratch_bytes: visited + heaps,
+        })
+    }
+
+    /// Report the graph shape and zone sizes of the index
+    ///
+    /// Reads every node record to count layers and edges, so the cost is
+    /// linear in the index size; call it for dashboards and maintenance
+    /// decisions, not per query.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a node record is corrupted.
+    pub fn stats(&self) -> Result<IndexStats> {
+        let storage = &self.graph.storage;
+        let (layer_node_counts, base_edges) = self.graph.layer_census()?;
+        let node_count = self.graph.node_count();
+        let avg_out_degree =
+            if node_count == 0 { 0.0 } else { base_edges as f64 / node_count as f64 };
+
+        Ok(IndexStats {
+            node_count,
+            deleted_count: self.graph.deleted_count(),
+            max_layer: self.graph.max_layer,
+            layer_node_counts,
+            avg_out_degree,
+            file_bytes: storage.mapped_len() as u64,
+            vector_bytes: (storage.vector_end()? - storage.vector_end_for_count(0)?) as u64,
+            graph_bytes: self.graph.zone_bytes()?,
+        })
+    }
+
+    /// Let the OS reclaim the memory holding cached index pages
+    ///
+    /// Issues `MADV_DONTNEED` over the mapping and frees the pooled search
+    /// buffers. No data is lost, including unflushed vectors; pages are read
+    /// back on next access. Useful when a mobile app moves to the background.
+    /// The mapping is left alone for shared readers.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the OS rejects the advice.
+    pub fn release_memory(&self) -> Result<()> {
+        self.graph.scratch_pool.clear();
+        self.graph.storage.release_memory()
+    }
+
+    /// Write a crash-consistent copy of the index to `path` without closing it
+    ///
+    /// Flushes first (unless this is a shared reader), then copies the file
+    /// while this handle still holds its lock, so no writer can change it
+    /// mid-copy. The copy is staged in a temporary file and renamed into place:
+    /// `path` never holds a partial snapshot. Where the filesystem supports it
+    /// (Btrfs, XFS, APFS) the copy is a reflink and costs no extra space until
+    /// the index changes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the flush fails, the index is in memory, `path` is
+    /// the index's own file, or the copy cannot be written.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn snapshot_to<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        if !self.is_shared_reader() {
+            self.flush()?;
+        }
+        self.graph.storage.snapshot_to(path)
+    }
+
+    /// Write a read-only copy of the index with a compressed graph to `path`
+    ///
+    /// Node records in the index keep a fixed-width slot for every possible
+    /// neighbor so they can be rewritten in place. The copy stores each
+    /// neighbor list sorted, as the gaps between IDs in varints, which
+    /// typically shrinks the graph zone by half or more; searches decode the
+    /// lists as they go, at a small cost in speed. Meant for indexes that are
+    /// built once and shipped: open the copy with `open_sealed()`,
+    /// `open_shared()` or `open_from_bytes()`, while `open()` refuses it with
+    /// `ReadOnly`. Vectors and ID maps are copied as they are.
+    ///
+    /// Flushes first, then writes the copy under a temporary name next to
+    /// `path`, syncs it and renames it into place.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the flush fails, the index is encrypted or a
+    /// collection, `path` is the index's own file, or the copy cannot be
+    /// written.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn snapshot_compressed_to<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        if !self.is_shared_reader() {
+            self.flush()?;
+        }
+        let graph = &self.graph;
+        let offsets = graph.compressed_offsets()?;
+        graph.storage.write_compressed_copy(
+            graph.node_count(),
+            HnswGraph::compressed_zone_len(&offsets),
+            |out| graph.write_compressed_zone(&offsets, out),
+            path.as_ref(),
+        )
+    }
+
+    /// Check if this index lives in memory (`in_memory()`, `open_from_bytes()`,
+    /// or `open_in_memory()` with the `wasm` feature) rather than in a file
+    pub fn is_in_memory(&self) -> bool {
+        self.graph.storage.is_in_memory()
+    }
+
+    /// Check if this index was opened with `open_shared()` or `open_sealed()` and is read-only
+    pub fn is_shared_reader(&self) -> bool {
+        self.graph.storage.is_shared_reader()
+    }
+
+    /// Create an empty index backed by anonymous memory instead of a file
+    ///
+    /// The index has the full `VectorIndex` API and is built exactly like a
+    /// file-backed one, but takes no lock and never touches the disk:
+    /// `flush()` only finalizes the in-memory image, and everything is gone
+    /// when the index is dropped. Meant for tests, short-lived sessions, and
+    /// building an index in memory before writing it out with `persist_to()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `options.input_dimensions` is smaller than `dims`,
+    /// `options.graph_file` is set, or the OS refuses the mapping.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn in_memory(dims: u32, options: IndexOptions) -> Result<Self> {
+        Self::check_input_dimensions(dims, &options)?;
+        Self::from_storage(Storage::open_anonymous(dims)?, options)
+    }
+
+    /// Write an in-memory index to `path` as an index file
+    ///
+    /// Flushes the in-memory image, then writes it to a temporary file next
+    /// to `path`, syncs it and renames it into place, replacing any file at
+    /// `path`. `open()` loads the result like any other index. The in-memory
+    /// index stays usable; later changes are not written unless `persist_to()`
+    /// is called again.
+    ///
+    /// The file is packed: the graph follows the vectors and the metadata
+    /// follows the graph, without the room an index file keeps for growth,
+    /// so the file is not much larger than its contents. The first insert
+    /// after reopening it moves the graph to make room again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index is file-backed (use `snapshot_to()`),
+    /// or the file cannot be written.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn persist_to<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        if !self.is_in_memory() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Only in-memory indexes can be persisted; use snapshot_to() for file-backed ones"
+            ));
+        }
+        self.flush()?;
+        let graph_len = self.graph.zone_bytes()?;
+        self.graph.storage.persist_packed_to(self.graph.node_count(), graph_len, path.as_ref())
+    }
+
+    /// Create an empty index held entirely in memory
+    ///
+    /// This is the browser (`wasm32-unknown-unknown`) entry point. Nothing is
+    /// written anywhere until the caller persists `as_bytes()`, for example to
+    /// an OPFS file, and reloads it with `from_bytes()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `options.input_dimensions` is smaller than `dims`.
+    #[cfg(feature = "wasm")]
+    pub fn open_in_memory(dims: u32, options: IndexOptions) -> Result<Self> {
+        Self::check_input_dimensions(dims, &options)?;
+        Self::from_storage(Storage::open_in_memory(dims), options)
+    }
+
+    /// Load an in-memory index from a serialized image
+    ///
+    /// `bytes` can come from `as_bytes()` or be the contents of an index file
+    /// written by `open()`. Ghost nodes are recovered exactly as in `open()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image is corrupted, has different dimensions,
+    /// or `options.input_dimensions` is smaller than `dims`.
+    #[cfg(feature = "wasm")]
+    pub fn from_bytes(bytes: &[u8], dims: u32, options: IndexOptions) -> Result<Self> {
+        Self::check_input_dimensions(dims, &options)?;
+        Self::from_storage(Storage::from_bytes(bytes, dims)?, options)
+    }
+
+    /// Serialized index image, byte-for-byte identical to the on-disk format
+    ///
+    /// Call `flush()` first: the graph header is only written on flush, so an
+    /// unflushed image reloads without the most recent inserts.
+    #[cfg(feature = "wasm")]
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.graph.storage.as_bytes()
+    }
+
+    /// Reject `input_dimensions` smaller than the stored dimensionality
+    fn check_input_dimensions(dims: u32, options: &IndexOptions) -> Result<()> {
+        if let Some(input_dims) = options.input_dimensions
+            && input_dims < dims
+        {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "Input dimensions ({}) must be >= stored dimensions ({})",
+                    input_dims, dims
+                )
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Reject `max_layers` outside the range a node record supports
+    fn check_max_layers(options: &IndexOptions) -> Result<()> {
+        if !(1..=16).contains(&options.max_layers) {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!("max_layers must be between 1 and 16, got {}", options.max_layers)
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Reject a `max_connections_0` below `max_connections` or above `MAX_CONNECTIONS_0`
+    fn check_max_connections_0(options: &IndexOptions) -> Result<()> {
+        if let Some(m0) = options.max_connections_0
+            && !(options.max_connections..=MAX_CONNECTIONS_0).contains(&m0)
+        {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "max_connections_0 must be between max_connections ({}) and {}, got {}",
+                    options.max_connections, MAX_CONNECTIONS_0, m0
+                )
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Build the index on top of opened storage, recovering ghost nodes
+    fn from_storage(storage: Storage, options: IndexOptions) -> Result<Self> {
+        Self::from_storage_with_report(storage, options).map(|(index, _)| index)
+    }
+
+    /// `from_storage`, also reporting what recovery did
+    fn from_storage_with_report(
+        mut storage: Storage,
+        mut options: IndexOptions,
+    ) -> Result<(Self, OpenReport)> {
+        // Refuse before the graph or recovery touch a file we may not understand
+        options.version_policy.check(storage.writer_version())?;
+
+        // Read before this open marks the file as held by a writer
+        let clean_shutdown = !storage.writer_open();
+        let commit_epoch = storage.commit_epoch();
+
+        // Bring a file from an older release up to the current layout
+        let migrations = migrate::run(&mut storage)?;
+
+        // An existing graph fixes its own parameters; the options only shape new ones
+        if let Some(header) = HnswGraph::stored_header(&storage) {
+            options.max_connections = header.m;
+            options.max_connections_0 = Some(header.m0);
+            options.max_layers = header.max_layers;
+            if header.ef_construction != 0 {
+                options.ef_construction = header.ef_construction as usize;
+            }
+        } else {
+            Self::check_max_connections_0(&options)?;
+        }
+
+        Self::check_max_layers(&options)?;
+
+        // Only a new index can choose its page size; the growth chunk is rounded to it
+        if options.page_size != storage.page_size()
+            && !storage.is_shared_reader()
+            && storage.graph_offset().is_none()
+            && storage.count() == 0
+        {
+            storage.set_page_size(options.page_size)?;
+        }
+        options.page_size = storage.page_size();
+
+        storage.set_memory_mode(options.memory_mode)?;
+        storage.set_growth_chunk(options.growth_chunk);
+        storage.set_max_file_bytes(options.max_file_bytes);
+
+        // A new (or still empty) index takes the requested encoding
+        if storage.count() == 0
+            && !storage.is_shared_reader()
+            && storage.element_type() != options.element_type
+        {
+            storage.set_element_type(options.element_type)?;
+        }
+
+        // Only a new index can choose where its graph lives
+        if options.graph_file
+            && !storage.is_shared_reader()
+            && !storage.has_graph_file()
+            && storage.graph_offset().is_none()
+            && storage.count() == 0
+        {
+            storage.use_graph_file()?;
+        }
+
+        // Compute layer multiplier
+        let ml = 1.0 / (options.max_connections as f32).ln();
+
+        // Create HNSW params from options
+        let params = HnswParams {
+            max_connections: options.max_connections,
+            max_connections_0: options.max_connections_0,
+            ef_construction: options.ef_construction,
+            ef_search: options.ef_search,
+            ml,
+            max_layers: options.max_layers,
+            distance: options.distance,
+            normalize: options.normalize,
+        };
+
+        let mut report = OpenReport {
+            legacy_layout_compacted: migrations.contains(&"legacy-graph-zone"),
+            migrations,
+            clean_shutdown,
+            commit_epoch,
+            ..OpenReport::default()
+        };
+
+        // Open graph
+        let mut graph = HnswGraph::open(storage, params)?;
+        options.normalize = graph.normalizes();
+        graph.set_write_buffer(options.write_buffer_records)?;
+
+        // Consistency check: Ghost node handling
+        let storage_count = graph.storage.count();
+        let graph_node_count = graph.node_count();
+
+        if storage_count < graph_node_count {
+            // Graph references vectors that don't exist = corruption
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                format!(
+                    "Index corruption detected: graph has {} nodes but storage has only {} vectors",
+                    graph_node_count, storage_count
+                )
+            ));
+        } else if storage_count > graph_node_count && graph.storage.is_sealed() {
+            // A sealed file is never adjusted, not even in memory
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                format!(
+                    "Sealed index has {} vectors missing from its graph; open it for writing once to recover them",
+                    storage_count - graph_node_count
+                )
+            ));
+        } else if storage_count > graph_node_count {
+            // GHOST NODE RECOVERY
+            // Storage is ahead of Graph (crash during write).
+            // We must rollback Storage to match Graph so the next insert
+            // reclaims the 'ghost' ID instead of appending after it.
+            graph.storage.truncate_logical(graph_node_count);
+            report.reclaimed_ids = graph_node_count..storage_count;
+        }
+
+        // After a clean shutdown the header counts are exact; otherwise pages
+        // may have reached the disk in any order since the last flush
+        if !clean_shutdown && !graph.storage.is_shared_reader() {
+            report.deleted_count_repaired = graph.recount_deleted()?;
+            graph.verify_tail_records(TAIL_RECORDS_CHECKED)?;
+        }
+
+        #[cfg(feature = "linalg")]
+        let rotation = graph
+            .storage
+            .metadata_section(rotation::ROTATION_SECTION)?
+            .map(Rotation::from_bytes)
+            .transpose()?;
+
+        let mut keys: KeyMap = graph.storage.read_map()?;
+        keys.truncate(graph.node_count());
+
+        let mut groups: GroupMap = graph.storage.read_map()?;
+        groups.truncate(graph.node_count());
+
+        let mut tags: TagMap = graph.storage.read_map()?;
+        tags.truncate(graph.node_count());
+
+        let mut expiry: ExpiryMap = graph.storage.read_map()?;
+        expiry.truncate(graph.node_count());
+
+        let free_ids = Self::load_free_ids(&graph)?;
+
+        let durable_count = Arc::new(AtomicU64::new(graph.node_count()));
+
+        let mut index = Self {
+            graph,
+            options,
+            ml,
+            durable_count,
+            #[cfg(not(target_arch = "wasm32"))]
+            unflushed_inserts: 0,
+            #[cfg(not(target_arch = "wasm32"))]
+            last_flush: Instant::now(),
+            #[cfg(feature = "linalg")]
+            rotation,
+            keys,
+            groups,
+            tags,
+            expiry,
+            free_ids,
+            stats: WorkloadStats::new(),
+            hooks: Hooks::default(),
+            embedder: EmbedderSlot::default(),
+            progress: ProgressSlot::default(),
+        };
+        if !index.graph.storage.is_shared_reader() {
+            index.graph.storage.repair_metadata_zone()?;
+        }
+        index.graph.storage.mark_writer_open()?;
+        Ok((index, report))
+    }
+
+    /// Read the persisted free list, checked against the node flags
+    ///
+    /// IDs past the node count or no longer deleted are dropped. If deletions
+    /// are missing (a file written before the list was stored, or deletions
+    /// that reached the disk without a flush), the list is rebuilt by a scan.
+    fn load_free_ids(graph: &HnswGraph) -> Result<FreeIds> {
+        let mut free_ids: FreeIds = graph.storage.read_map()?;
+        free_ids.retain(|id| Ok(id < graph.node_count() && graph.is_deleted(id)?))?;
+
+        if free_ids.len() as u64 != graph.deleted_count() {
+            let mut ids = Vec::new();
+            for id in 0..graph.node_count() {
+                if graph.is_deleted(id)? {
+                    ids.push(id);
+                }
+            }
+            free_ids = FreeIds::rebuilt(ids);
+        }
+        Ok(free_ids)
+    }
+
+    /// Add a vector to the index
+    ///
+    /// # Arguments
+    ///
+    /// * `vector` - Vector to add (must match `input_dimensions()`)
+    ///
+    /// # Returns
+    ///
+    /// Returns the ID of the inserted vector
+    ///
+    /// # Crash Consistency Protocol (ADR-005)
+    ///
+    /// This method follows a strict 3-phase protocol:
+    /// 1. **Persist Vector**: Write vector data to storage
+    /// 2. **Write Node**: Write HNSW node with links (invisible to readers)
+    /// 3. **Publish**: Update in-memory counters (visible to readers)
+    ///
+    /// If a crash occurs during step 2, we have a "ghost node" (vector without
+    /// graph entry). The next add() will reclaim this space.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Vector dimensions don't match index dimensions
+    /// - Storage write fails
+    /// - Graph write fails
+    pub fn add(&mut self, vector: &[f32]) -> Result<u64> {
+        // Validate dimensions and keep the stored prefix
+        let vector = self.stored_prefix(vector, "Vector")?;
+        self.insert_stored(&vector)
+    }
+
+    /// Make room for `additional` more vectors before adding them
+    ///
+    /// Grows the file once to fit `len() + additional` vectors and their graph
+    /// records, and asks the filesystem to allocate the space up front. Bulk
+    /// loads then avoid the repeated grow-and-remap cycles of on-demand growth,
+    /// and the file is less fragmented on flash storage such as SD cards.
+    /// Reserving no more than is already available does not grow the file.
+    ///
+    /// The reserved space is kept on close and counts towards the file size.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReadOnly` for a shared reader, or an error if the disk cannot
+    /// hold the reserved size.
+    pub fn reserve(&mut self, additional: u64) -> Result<()> {
+        self.graph.storage.ensure_writable("reserve")?;
+
+        let count = self.len().checked_add(additional).ok_or_else(|| {
+            Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!("Cannot reserve {} more vectors", additional),
+            )
+        })?;
+        self.graph.reserve(count)
+    }
+
+    /// Insert a vector that already has the stored dimensionality
+    ///
+    /// If `flush_policy` triggers, the vector is inserted before the flush
+    /// starts; an error from the flush does not undo the insert.
+    pub(crate) fn insert_stored(&mut self, vector: &[f32]) -> Result<u64> {
+        let id = self.insert_node(vector)?;
+        self.apply_flush_policy()?;
+        Ok(id)
+    }
+
+    /// `insert_stored()` under a new ID, never in a deleted vector's slot
+    ///
+    /// For callers that promised the ID in advance (`len()` at insert time),
+    /// whatever `id_reuse` says.
+    pub(crate) fn append_stored(&mut self, vector: &[f32]) -> Result<u64> {
+        let id = self.insert_node_with(vector, IdReuse::AppendOnly)?;
+        self.apply_flush_policy()?;
+        Ok(id)
+    }
+
+    /// Add a vector under an application key and return its ID
+    ///
+    /// The key identifies the vector in `id_for_key()`, `delete_by_key()` and
+    /// `search_with_keys()`, so applications with their own document keys
+    /// need no separate table to translate IDs. Keys are stored in the file
+    /// and become durable with the vector on the next `flush()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidArgument` if the key is empty, longer than
+    /// `MAX_KEY_LEN` bytes or already in use, and the errors of `add()`
+    pub fn add_with_key(&mut self, key: &str, vector: &[f32]) -> Result<u64> {
+        KeyMap::validate(key)?;
+        if let Some(id) = self.keys.id(key) {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!("Key {:?} is already used by vector {}", key, id)
+            ));
+        }
+
+        let vector = self.stored_prefix(vector, "Vector")?;
+        let id = self.insert_node(&vector)?;
+        self.keys.insert(key, id);
+        self.apply_flush_policy()?;
+        Ok(id)
+    }
+
+    /// Get the ID of the vector added under `key`
+    ///
+    /// Returns `None` if no vector has the key, or its vector was deleted.
+    pub fn id_for_key(&self, key: &str) -> Option<u64> {
+        self.keys.id(key)
+    }
+
+    /// Get the key vector `id` was added under, if any
+    pub fn key_for_id(&self, id: u64) -> Option<&str> {
+        self.keys.key(id)
+    }
+
+    /// Embed `text` with the attached embedder and add the vector; returns its ID
+    ///
+    /// The text itself is not stored: keep it in the application, looked up
+    /// by the returned ID, or add the vector with `add_with_key()` instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidArgument` if no embedder is attached, the embedder's
+    /// error if it fails, and the errors of `add()`
+    pub fn add_text(&mut self, text: &str) -> Result<u64> {
+        let vector = self.embedder("add_text")?.embed(text)?;
+        self.add(&vector)
+    }
+
+    /// Add a vector to a group and return its ID
+    ///
+    /// Vectors that belong together, such as the chunks of one document,
+    /// share a group ID of the application's choosing; `search_grouped()`
+    /// then returns one entry per group. Groups are stored in the file and
+    /// become durable with the vector on the next `flush()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidArgument` if `group` is `u64::MAX`, and the errors of `add()`
+    pub fn add_to_group(&mut self, group: u64, vector: &[f32]) -> Result<u64> {
+        GroupMap::validate(group)?;
+
+        let vector = self.stored_prefix(vector, "Vector")?;
+        let id = self.insert_node(&vector)?;
+        self.groups.set(id, Some(group));
+        self.apply_flush_policy()?;
+        Ok(id)
+    }
+
+    /// Set or clear the group of vector `id`
+    ///
+    /// # Errors
+    ///
+    /// Returns `OutOfBounds` if there is no vector `id`, `InvalidArgument` if
+    /// `group` is `Some(u64::MAX)`, or `ReadOnly` for a shared reader
+    pub fn set_group(&mut self, id: u64, group: Option<u64>) -> Result<()> {
+        self.graph.storage.ensure_writable("set group")?;
+        if let Some(group) = group {
+            GroupMap::validate(group)?;
+        }
+        if id >= self.len() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!("Vector {} out of bounds (count: {})", id, self.len())
+            ));
+        }
+
+        self.groups.set(id, group);
+        Ok(())
+    }
+
+    /// Get the group of vector `id`, if it has one
+    pub fn group_of(&self, id: u64) -> Option<u64> {
+        self.groups.get(id)
+    }
+
+    /// Add a vector carrying a set of tags and return its ID
+    ///
+    /// Tags are application-chosen IDs from 0 to `MAX_TAG`, such as a user,
+    /// folder or label; `search_with_tags()` restricts a search by them. Tags
+    /// are stored in the file and become durable with the vector on the next
+    /// `flush()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidArgument` if a tag exceeds `MAX_TAG`, and the errors of `add()`
+    pub fn add_with_tags(&mut self, vector: &[f32], tags: &[u32]) -> Result<u64> {
+        TagMap::validate(tags)?;
+
+        let vector = self.stored_prefix(vector, "Vector")?;
+        let id = self.insert_node(&vector)?;
+        self.tags.set(id, tags);
+        self.apply_flush_policy()?;
+        Ok(id)
+    }
+
+    /// Replace the tags of vector `id`; an empty slice clears them
+    ///
+    /// # Errors
+    ///
+    /// Returns `OutOfBounds` if there is no vector `id`, `InvalidArgument` if
+    /// a tag exceeds `MAX_TAG`, or `ReadOnly` for a shared reader
+    pub fn set_tags(&mut self, id: u64, tags: &[u32]) -> Result<()> {
+        self.graph.storage.ensure_writable("set tags")?;
+        TagMap::validate(tags)?;
+        if id >= self.len() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!("Vector {} out of bounds (count: {})", id, self.len())
+            ));
+        }
+
+        self.tags.set(id, tags);
+        Ok(())
+    }
+
+    /// Get the tags of vector `id`, in ascending order
+    pub fn tags_of(&self, id: u64) -> Vec<u32> {
+        self.tags.get(id)
+    }
+
+    /// Add a vector that `purge_expired()` deletes once `expires_at` has passed
+    ///
+    /// Expired vectors stay searchable until they are purged, so call
+    /// `purge_expired()` periodically or before searches that must not see
+    /// them. Deadlines are kept to the second and become durable with the
+    /// vector on the next `flush()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `add()`
+    pub fn add_with_expiry(&mut self, vector: &[f32], expires_at: SystemTime) -> Result<u64> {
+        let vector = self.stored_prefix(vector, "Vector")?;
+        let id = self.insert_node(&vector)?;
+        self.expiry.set(id, Some(expires_at));
+        self.apply_flush_policy()?;
+        Ok(id)
+    }
+
+    /// Set or clear the expiration time of vector `id`
+    ///
+    /// # Errors
+    ///
+    /// Returns `OutOfBounds` if there is no vector `id`, or `ReadOnly` for a
+    /// shared reader
+    pub fn set_expiry(&mut self, id: u64, expires_at: Option<SystemTime>) -> Result<()> {
+        self.graph.storage.ensure_writable("set expiry")?;
+        if id >= self.len() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!("Vector {} out of bounds (count: {})", id, self.len())
+            ));
+        }
+
+        self.expiry.set(id, expires_at);
+        Ok(())
+    }
+
+    /// Get the expiration time of vector `id`, if it has one
+    pub fn expiry_of(&self, id: u64) -> Option<SystemTime> {
+        self.expiry.get(id)
+    }
+
+    /// Delete every vector whose expiration time is at or before `now`
+    ///
+    /// Returns the number of vectors deleted. `now` is passed in rather than
+    /// read from the clock so callers can purge on their own schedule, and
+    /// tests can use fixed times. Deletions behave like `delete()` and become
+    /// durable on the next `flush()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReadOnly` for a shared reader, or an error if a node record
+    /// cannot be written
+    pub fn purge_expired(&mut self, now: SystemTime) -> Result<u64> {
+        self.graph.storage.ensure_writable("purge expired vectors")?;
+
+        let mut purged = 0;
+        for id in self.expiry.expired(now) {
+            if self.delete(id)? {
+                purged += 1;
+            }
+            self.expiry.set(id, None);
+        }
+        Ok(purged)
+    }
+
+    /// Delete a vector so that searches no longer return it
+    ///
+    /// The vector keeps its ID and its place in the graph, which searches
+    /// still pass through. Its ID and space are only reclaimed with
+    /// `IdReuse::ReuseDeleted`, by a later insert. Its key, if any, is
+    /// released for reuse. The deletion becomes durable on
+    /// the next `flush()`.
+    ///
+    /// Returns `false` if the vector was already deleted.
+    ///
+    /// # Errors
+    ///
+    /// Returns `OutOfBounds` if there is no vector `id`, or `ReadOnly` for a
+    /// shared reader
+    pub fn delete(&mut self, id: u64) -> Result<bool> {
+        self.graph.storage.ensure_writable("delete")?;
+
+        let deleted = self.graph.mark_deleted(id)?;
+        if deleted {
+            self.free_ids.push(id);
+            self.scrub_if_enabled(id)?;
+        }
+        self.keys.remove_id(id);
+        Ok(deleted)
+    }
+
+    /// With `scrub_deleted`, zero the slot of deleted vector `id` and drop
+    /// its group, tags and expiry
+    fn scrub_if_enabled(&mut self, id: u64) -> Result<()> {
+        if !self.options.scrub_deleted {
+            return Ok(());
+        }
+        self.graph.storage.scrub(id)?;
+        self.groups.set(id, None);
+        self.tags.set(id, &[]);
+        self.expiry.set(id, None);
+        Ok(())
+    }
+
+    /// Delete the vector added under `key`
+    ///
+    /// Returns the ID of the deleted vector, or `None` if no vector has the key.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReadOnly` for a shared reader
+    pub fn delete_by_key(&mut self, key: &str) -> Result<Option<u64>> {
+        self.graph.storage.ensure_writable("delete")?;
+
+        let Some(id) = self.keys.id(key) else {
+            return Ok(None);
+        };
+        self.delete(id)?;
+        Ok(Some(id))
+    }
+
+    /// Replace vector `id` with `vector` and return the replacement's ID
+    ///
+    /// The graph has no in-place updates: the new vector is added under a new
+    /// ID, takes over the key, group, tags and expiry of `id`, and `id` is
+    /// deleted. Both changes become durable on the next `flush()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `OutOfBounds` if there is no vector `id` or it was deleted, and
+    /// the errors of `add()`
+    pub fn update(&mut self, id: u64, vector: &[f32]) -> Result<u64> {
+        self.graph.storage.ensure_writable("update")?;
+        if self.is_deleted(id)? {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!("Vector {} was deleted", id)
+            ));
+        }
+
+        let vector = self.stored_prefix(vector, "Vector")?;
+        let new_id = self.insert_node(&vector)?;
+        if let Some(key) = self.keys.remove_id(id) {
+            self.keys.insert(&key, new_id);
+        }
+        self.groups.set(new_id, self.groups.get(id));
+        self.tags.set(new_id, &self.tags.get(id));
+        self.expiry.set(new_id, self.expiry.get(id));
+        if self.graph.mark_deleted(id)? {
+            self.free_ids.push(id);
+            self.scrub_if_enabled(id)?;
+        }
+        self.apply_flush_policy()?;
+        Ok(new_id)
+    }
+
+    /// Get the stored vector `id`, decoded to `f32`
+    ///
+    /// This is the vector as stored: truncated to `dimensions()` and, with
+    /// `normalize`, scaled to unit length. A quantized copy written with a
+    /// rotation (see `quantize_to()`) rotates it back, except for `Binary`
+    /// vectors, which only keep the signs of the rotated vector.
+    ///
+    /// # Errors
+    ///
+    /// Returns `OutOfBounds` if there is no vector `id` or it was deleted
+    pub fn get_vector(&self, id: u64) -> Result<Vec<f32>> {
+        if self.is_deleted(id)? {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!("Vector {} was deleted", id)
+            ));
+        }
+        self.decoded_vector(id)
+    }
+
+    /// Check whether vector `id` has been deleted
+    ///
+    /// # Errors
+    ///
+    /// Returns `OutOfBounds` if there is no vector `id`
+    pub fn is_deleted(&self, id: u64) -> Result<bool> {
+        if id >= self.len() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!("Vector {} out of bounds (count: {})", id, self.len())
+            ));
+        }
+        self.graph.is_deleted(id)
+    }
+
+    /// Pin vector `id` in the neighbor lists of the graph, or unpin it
+    ///
+    /// Neighbor selection takes pinned vectors ahead of the diversity
+    /// heuristic, so a node linked to a pinned vector keeps that link through
+    /// later pruning, and new vectors close to it link to it. Pin a few
+    /// anchors that must stay well connected, such as category centroids: a
+    /// neighbor list full of pinned vectors has no room left for diverse
+    /// links. Unrelated to `pin_graph()`, which keeps graph pages in RAM.
+    /// Durable on the next `flush()`.
+    ///
+    /// Returns `false` if the vector already was in that state.
+    ///
+    /// # Errors
+    ///
+    /// Returns `OutOfBounds` if there is no vector `id`, or `ReadOnly` for a
+    /// shared reader
+    pub fn set_pinned(&mut self, id: u64, pinned: bool) -> Result<bool> {
+        self.graph.storage.ensure_writable("set_pinned")?;
+        self.graph.set_pinned(id, pinned)
+    }
+
+    /// Check whether vector `id` is pinned (see `set_pinned()`)
+    ///
+    /// # Errors
+    ///
+    /// Returns `OutOfBounds` if there is no vector `id`
+    pub fn is_pinned(&self, id: u64) -> Result<bool> {
+        if id >= self.len() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!("Vector {} out of bounds (count: {})", id, self.len())
+            ));
+        }
+        self.graph.is_pinned(id)
+    }
+
+    /// Get the number of deleted vectors
+    pub fn deleted_count(&self) -> u64 {
+        self.graph.deleted_count()
+    }
+
+    /// Start a flush if `flush_policy` is due after an insert
+    ///
+    /// An error from the flush does not undo the insert.
+    fn apply_flush_policy(&mut self) -> Result<()> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.unflushed_inserts += 1;
+            let due = match self.options.flush_policy {
+                FlushPolicy::Manual => false,
+                FlushPolicy::EveryInserts(n) => self.unflushed_inserts >= n,
+                FlushPolicy::EveryMillis(ms) => {
+                    self.last_flush.elapsed().as_millis() >= u128::from(ms)
+                }
+            };
+            if due {
+                self.flush_async()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Insert a vector and its graph node (the crash consistency protocol of `add()`)
+    fn insert_node(&mut self, vector: &[f32]) -> Result<u64> {
+        self.insert_node_with(vector, self.options.id_reuse)
+    }
+
+    /// `insert_node()` with `reuse` in place of the index's `id_reuse`
+    fn insert_node_with(&mut self, vector: &[f32], reuse: IdReuse) -> Result<u64> {
+        let result = self.try_insert_node(vector, reuse);
+        if result.is_err() {
+            // Roll back a vector persisted without its node, as reopening
+            // after a crash would, so the next insert reclaims its ID
+            let published = self.graph.node_count();
+            if self.graph.storage.count() > published && !self.graph.storage.is_shared_reader() {
+                self.graph.storage.truncate_logical(published);
+            }
+        }
+        result
+    }
+
+    /// `insert_node_with()` without the rollback on failure
+    fn try_insert_node(&mut self, vector: &[f32], reuse: IdReuse) -> Result<u64> {
+        self.graph.storage.ensure_writable("add")?;
+        self.link_step()?;
+        let timer = self.stats.timer();
+
+        if reuse == IdReuse::ReuseDeleted
+            && let Some(id) = self.free_ids.next()
+        {
+            return self.reuse_node(id, vector, timer);
+        }
+
+        // Behind pending vectors the graph is linked in ID order, later
+        let flat =
+            self.len() < self.options.flat_threshold || self.graph.storage.linked_count().is_some();
+
+        // Relocate graph zone if the next vector append would overlap it
+        self.graph.prepare_for_vector_insert()?;
+
+        // STEP 1: Persist vector (reclaims ghost node space if any)
+        let new_id = self.graph.storage.insert(vector)?;
+        fail_point!(crate::failpoint::AFTER_VECTOR_PERSIST);
+
+        // STEP 2: Determine layer for new node
+        let layer = self.select_layer();
+        let layer_count = layer + 1;
+
+        // STEP 3: Handle empty graph and flat region cases
+        if flat {
+            // Linked by `link_graph()` later; publishing still counts the node.
+            // A count past `new_id` was left by nodes rolled back on open
+            if self.graph.storage.linked_count().is_none_or(|linked| linked > new_id) {
+                self.graph.storage.set_linked_count(Some(new_id));
+            }
+            self.graph.write_node_and_backlinks(new_id, layer_count, &vec![vec![]; layer_count])?;
+            self.graph.publish_node(new_id, layer_count)?;
+            self.record_insert(new_id, timer);
+            return Ok(new_id);
+        }
+        if self.graph.node_count() == 0 {
+            // Empty graph - just publish the node
+            self.graph.write_node_and_backlinks(new_id, layer_count, &vec![vec![]; layer_count])?;
+            self.graph.publish_node(new_id, layer_count)?;
+            self.record_insert(new_id, timer);
+            return Ok(new_id);
+        }
+
+        // STEP 4: Neighbor selection (in-memory phase)
+        let neighbors = self.select_neighbors(vector, new_id, layer)?;
+
+        // STEP 5: Atomic write (disk phase)
+        // Node is written but invisible (node_count not incremented)
+        self.graph.write_node_and_backlinks(new_id, layer_count, &neighbors)?;
+
+        // STEP 6: Publish (commit phase)
+        // Node becomes visible to readers
+        self.graph.publish_node(new_id, layer_count)?;
+        self.record_insert(new_id, timer);
+
+        Ok(new_id)
+    }
+
+    /// Store `vector` in the slot of deleted vector `id` and link it anew
+    fn reuse_node(&mut self, id: u64, vector: &[f32], timer: Timer) -> Result<u64> {
+        // The new vector must be in place before neighbors are chosen by distance to it
+        self.graph.storage.overwrite(id, vector)?;
+
+        let layer_count = self.graph.read_node_record(id)?.header.layer_count as usize;
+        let neighbors = if self.is_linked(id) {
+            self.select_neighbors(vector, id, layer_count - 1)?
+        } else {
+            vec![vec![]; layer_count]
+        };
+        if let Err(err) = self.graph.relink_deleted_node(id, &neighbors) {
+            // Deleted and free again, as the file still has it
+            self.graph.mark_deleted(id)?;
+            return Err(err);
+        }
+        self.free_ids.take_next();
+
+        // The deleted vector's key was released on delete; its group, tags and
+        // expiry go now
+        self.groups.set(id, None);
+        self.tags.set(id, &[]);
+        self.expiry.set(id, None);
+        self.record_insert(id, timer);
+        Ok(id)
+    }
+
+    /// Count a completed insert and report it to the attached instrumentation
+    fn record_insert(&self, id: u64, timer: Timer) {
+        self.stats.record_insert();
+        if let Some(hooks) = &self.hooks.0 {
+            hooks.on_add(&AddEvent { id, duration: timer.elapsed() });
+        }
+    }
+
+    /// Search for k nearest neighbors
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Query vector (must match `input_dimensions()`)
+    /// * `k` - Number of nearest neighbors to return
+    ///
+    /// # Returns
+    ///
+    /// Returns a vector of search results, sorted by distance (ascending)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions
+    pub fn search(&self, query: &[f32], k: usize) -> Result<Vec<SearchResult>> {
+        self.search_with_options(query, k, &SearchOptions::default())
+    }
+
+    /// Embed `text` with the attached embedder and search for its k nearest
+    /// neighbors; see `search()`
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidArgument` if no embedder is attached, the embedder's
+    /// error if it fails, and the errors of `search()`
+    pub fn search_text(&self, text: &str, k: usize) -> Result<Vec<SearchResult>> {
+        let query = self.embedder("search_text")?.embed(text)?;
+        self.search(&query, k)
+    }
+
+    /// Search for k nearest neighbors with per-search options
+    ///
+    /// With `SearchConsistency::DurableOnly`, vectors added after the last
+    /// `flush()` are excluded from the results, so callers never show data
+    /// that could disappear after a crash.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions, or
+    /// `Cancelled` if `options.cancel` was cancelled before the search finished
+    pub fn search_with_options(
+        &self,
+        query: &[f32],
+        k: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchResult>> {
+        // Validate dimensions and keep the stored prefix
+        let query = self.stored_prefix(query, "Query")?;
+
+        // Delegate to graph search with configured ef_search
+        let visible = |id| self.is_visible(options, id);
+        let predicate = self.hides_reused(options).then_some(&visible as &dyn Fn(u64) -> bool);
+
+        let timer = self.stats.timer();
+        let exact = self.prefers_exact();
+        let results = self.observe_search(k, exact, || {
+            let meter = BudgetMeter::new(options.budget).with_cancel(options.cancel.clone());
+            if exact {
+                let mut hits = self.scan_budgeted(&query, self.id_limit(options), meter)?;
+                hits.retain(|hit| visible(hit.id));
+                Ok(nearest(hits, k))
+            } else {
+                self.graph.search_budgeted(
+                    &query,
+                    k,
+                    self.options.ef_search,
+                    self.id_limit(options),
+                    predicate,
+                    options.early_termination,
+                    meter,
+                )
+            }
+        })?;
+        self.stats.record_query(timer, k, self.options.ef_search);
+        Ok(results)
+    }
+
+    /// Search for k nearest neighbors, writing them to caller buffers
+    ///
+    /// Like `search()`, but the IDs and distances of the results, nearest
+    /// first, go to `ids[..n]` and `distances[..n]`, where `n` is the returned
+    /// count, instead of a new `Vec<SearchResult>`. The graph search reuses
+    /// pooled buffers, so once warm a query allocates nothing (normalized
+    /// indexes still copy the query). The same layout as `chassis_search` in
+    /// the C API.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions, or
+    /// `ids` or `distances` holds fewer than `k` elements
+    pub fn search_into(
+        &self,
+        query: &[f32],
+        k: usize,
+        ids: &mut [u64],
+        distances: &mut [f32],
+    ) -> Result<usize> {
+        if ids.len() < k || distances.len() < k {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "Output buffers hold {} IDs and {} distances, need {}",
+                    ids.len(),
+                    distances.len(),
+                    k
+                )
+            ));
+        }
+        let query = self.stored_prefix(query, "Query")?;
+
+        let (ids, distances) = (&mut ids[..k], &mut distances[..k]);
+        let timer = self.stats.timer();
+        let exact = self.prefers_exact();
+        let count = self.observe_search(k, exact, || {
+            if exact {
+                let results = self.scan_nearest(&query, k, u64::MAX)?;
+                Ok(write_results(&results, ids, distances))
+            } else {
+                self.graph.search_into(&query, self.options.ef_search, u64::MAX, ids, distances)
+            }
+        })?;
+        self.stats.record_query(timer, k, self.options.ef_search);
+        Ok(count)
+    }
+
+    /// Search for k nearest neighbors within a hard work budget
+    ///
+    /// Stops expanding candidates once `budget` is spent, in microseconds or
+    /// distance computations, and returns the best results found so far. For
+    /// latency limits on slow cores, where an unlucky query could otherwise
+    /// take several times the median. Shorthand for `search_with_options()`
+    /// with `SearchOptions::budget`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions
+    pub fn search_with_budget(
+        &self,
+        query: &[f32],
+        k: usize,
+        budget: SearchBudget,
+    ) -> Result<Vec<SearchResult>> {
+        let options = SearchOptions { budget: Some(budget), ..SearchOptions::default() };
+        self.search_with_options(query, k, &options)
+    }
+
+    /// Search for the k nearest neighbors of each query in `queries`
+    ///
+    /// Returns one result list per query, in order, each equal to what
+    /// `search()` returns for that query. The visited filter and heaps are
+    /// allocated once and reused between queries instead of per search. With
+    /// the `rayon` feature the queries are spread over the rayon thread pool,
+    /// with one set of buffers per worker.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any query's dimensions don't match index dimensions
+    pub fn search_batch(&self, queries: &[&[f32]], k: usize) -> Result<Vec<Vec<SearchResult>>> {
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            queries
+                .par_iter()
+                .map_init(SearchScratch::default, |scratch, query| {
+                    self.search_in(scratch, query, k)
+                })
+                .collect()
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            let mut scratch = SearchScratch::default();
+            queries.iter().map(|query| self.search_in(&mut scratch, query, k)).collect()
+        }
+    }
+
+    /// `search()` using the buffers in `scratch`
+    fn search_in(
+        &self,
+        scratch: &mut SearchScratch,
+        query: &[f32],
+        k: usize,
+    ) -> Result<Vec<SearchResult>> {
+        let query = self.stored_prefix(query, "Query")?;
+
+        let timer = self.stats.timer();
+        let exact = self.prefers_exact();
+        let results = self.observe_search(k, exact, || {
+            if exact {
+                self.scan_nearest(&query, k, u64::MAX)
+            } else {
+                self.graph.search_adaptive_in(
+                    scratch,
+                    &query,
+                    k,
+                    self.options.ef_search,
+                    u64::MAX,
+                    None,
+                    BudgetMeter::unlimited(),
+                )
+            }
+        })?;
+        self.stats.record_query(timer, k, self.options.ef_search);
+        Ok(results)
+    }
+
+    /// Search for k nearest neighbors and return their stored vectors too
+    ///
+    /// For callers that rerank results with the full vectors. The vectors are
+    /// read right after the search, while their pages are still resident, and
+    /// decoded to `f32` whatever the element type. Truncated indexes return
+    /// the stored `dimensions()` prefix.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions
+    pub fn search_with_vectors(&self, query: &[f32], k: usize) -> Result<Vec<VectorResult>> {
+        self.search(query, k)?
+            .into_iter()
+            .map(|r| {
+                let vector = self.decoded_vector(r.id)?;
+                Ok(VectorResult { id: r.id, distance: r.distance, vector })
+            })
+            .collect()
+    }
+
+    /// Search for k nearest neighbors and return the keys they were added under
+    ///
+    /// Vectors added with `add()` rather than `add_with_key()` have no key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions
+    pub fn search_with_keys(&self, query: &[f32], k: usize) -> Result<Vec<KeyedResult>> {
+        Ok(self
+            .search(query, k)?
+            .into_iter()
+            .map(|r| KeyedResult {
+                id: r.id,
+                key: self.keys.key(r.id).map(str::to_owned),
+                distance: r.distance,
+            })
+            .collect())
+    }
+
+    /// Search for the k nearest groups, with up to `group_size` hits each
+    ///
+    /// Hits that share a group (see `add_to_group()`) are collapsed into one
+    /// entry, so a document stored as many chunks takes one slot instead of
+    /// crowding out other documents. Groups are ordered by their nearest hit;
+    /// vectors without a group count as groups of their own.
+    ///
+    /// The search fetches more neighbors until it has found `k` groups or
+    /// the whole index, so groups may hold fewer than `group_size` hits when
+    /// their other members are far from the query.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions, or
+    /// `InvalidArgument` if `group_size` is 0
+    pub fn search_grouped(
+        &self,
+        query: &[f32],
+        k: usize,
+        group_size: usize,
+    ) -> Result<Vec<GroupedResult>> {
+        if group_size == 0 {
+            anyhow::bail!(Tagged::new(ErrorKind::InvalidArgument, "group_size must be > 0"));
+        }
+        if k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let live = usize::try_from(self.len() - self.deleted_count()).unwrap_or(usize::MAX);
+        let mut fetch = k.saturating_mul(group_size);
+        loop {
+            let hits = self.search(query, fetch)?;
+            let exhausted = hits.len() < fetch || fetch >= live;
+            let groups = self.group_hits(hits, k, group_size);
+            if groups.len() >= k || exhausted {
+                return Ok(groups);
+            }
+            fetch = fetch.saturating_mul(2);
+        }
+    }
+
+    /// Search for k neighbors that are near the query but not near each other
+    ///
+    /// Maximal marginal relevance: the search fetches `max(ef_search, k)`
+    /// nearest neighbors, then picks `k` of them one at a time, each time the
+    /// candidate minimizing
+    /// `lambda * distance(query, candidate) - (1 - lambda) * distance(candidate, nearest pick)`.
+    /// With `lambda = 1.0` the result is that of `search()`; lower values
+    /// trade closeness for variety, so a page of results is not ten
+    /// near-duplicates. The first pick is always the nearest neighbor.
+    ///
+    /// Results are in the order they were picked, with their distance to the
+    /// query. Picking costs one distance per candidate and result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions, or
+    /// `InvalidArgument` if `lambda` is not between 0 and 1
+    pub fn search_diverse(
+        &self,
+        query: &[f32],
+        k: usize,
+        lambda: f32,
+    ) -> Result<Vec<SearchResult>> {
+        if !(0.0..=1.0).contains(&lambda) {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!("lambda must be between 0 and 1, got {}", lambda)
+            ));
+        }
+        if k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut candidates = self.search(query, self.options.ef_search.max(k))?;
+        // Distance from each candidate to the nearest picked result
+        let mut spread = vec![f32::INFINITY; candidates.len()];
+        let mut picked = Vec::with_capacity(k.min(candidates.len()));
+        while picked.len() < k && !candidates.is_empty() {
+            let score = |i: usize| {
+                let diversity = if picked.is_empty() { 0.0 } else { spread[i] };
+                lambda * candidates[i].distance - (1.0 - lambda) * diversity
+            };
+            // Ties go to the nearer candidate, so the first pick is the nearest
+            let best = (1..candidates.len())
+                .fold(0, |best, i| if score(i) < score(best) { i } else { best });
+            let result = candidates.remove(best);
+            spread.remove(best);
+
+            let vector = self.graph.storage.get_vector(result.id)?;
+            let metric = self.graph.metric();
+            let vector = Query::new(&vector, metric);
+            for (candidate, nearest) in candidates.iter().zip(&mut spread) {
+                let distance = self.graph.query_distance(&vector, candidate.id)?;
+                *nearest = nearest.min(metric.reported(distance));
+            }
+            picked.push(result);
+        }
+        Ok(picked)
+    }
+
+    /// Search for the k nearest neighbors whose tags match
+    ///
+    /// A vector matches if it has at least one of `include` (any vector, if
+    /// `include` is empty) and none of `exclude`. The tags are checked while
+    /// the graph is traversed: non-matching vectors still serve as routes but
+    /// are never returned, and the search continues until it has found
+    /// `ef_search` matching vectors or explored all it can reach. Restrictive
+    /// filters therefore cost more than a plain search, but do not come back
+    /// empty-handed because the nearest vectors happened to be filtered out.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions, or
+    /// `InvalidArgument` if a tag exceeds `MAX_TAG`
+    pub fn search_with_tags(
+        &self,
+        query: &[f32],
+        k: usize,
+        include: &[u32],
+        exclude: &[u32],
+    ) -> Result<Vec<SearchResult>> {
+        TagMap::validate(include)?;
+        TagMap::validate(exclude)?;
+        let query = self.stored_prefix(query, "Query")?;
+
+        let filter = self.tags.filter(include, exclude);
+        let matches = |id: u64| self.tags.matches(&filter, id);
+
+        let timer = self.stats.timer();
+        let exact = self.prefers_exact();
+        let results = self.observe_search(k, exact, || {
+            if exact {
+                let mut hits = self.scan(&query, u64::MAX)?;
+                hits.retain(|hit| matches(hit.id));
+                Ok(nearest(hits, k))
+            } else {
+                let ef = self.options.ef_search;
+                self.graph.search_with_predicate(&query, k, ef, u64::MAX, &matches)
+            }
+        })?;
+        self.stats.record_query(timer, k, self.options.ef_search);
+        Ok(results)
+    }
+
+    /// Collapse `hits` (nearest first) into at most `k` groups of `group_size`
+    fn group_hits(
+        &self,
+        hits: Vec<SearchResult>,
+        k: usize,
+        group_size: usize,
+    ) -> Vec<GroupedResult> {
+        let mut groups: Vec<GroupedResult> = Vec::with_capacity(k);
+        let mut positions: HashMap<u64, usize> = HashMap::new();
+
+        for hit in hits {
+            let group = self.groups.get(hit.id);
+            let position = group.and_then(|group| positions.get(&group).copied());
+            match position {
+                Some(position) => {
+                    let hits = &mut groups[position].hits;
+                    if hits.len() < group_size {
+                        hits.push(hit);
+                    }
+                }
+                None if groups.len() < k => {
+                    if let Some(group) = group {
+                        positions.insert(group, groups.len());
+                    }
+                    groups.push(GroupedResult { group, hits: vec![hit] });
+                }
+                None => {}
+            }
+        }
+        groups
+    }
+
+    /// Search for k nearest neighbors and borrow their stored vectors
+    ///
+    /// Like `search_with_vectors()`, but each vector is a slice of the mapped
+    /// file rather than a copy. The slices borrow the index, so it cannot be
+    /// modified while they are alive.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions, or
+    /// `InvalidArgument` if vectors are not stored as `F32`
+    pub fn search_with_vector_slices(
+        &self,
+        query: &[f32],
+        k: usize,
+    ) -> Result<Vec<(SearchResult, &[f32])>> {
+        let storage = &self.graph.storage;
+        if storage.element_type() != ElementType::F32 {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "Vectors are stored as {}; use search_with_vectors() instead",
+                    storage.element_type()
+                )
+            ));
         }
 
-        Ok(Self { graph, options, ml })
+        self.search(query, k)?
+            .into_iter()
+            .map(|r| {
+                let vector = storage.get_vector_slice(r.id)?;
+                Ok((r, vector))
+            })
+            .collect()
     }
 
-    /// Add a vector to the index
+    /// Find all vectors within `max_distance` of the query
     ///
-    /// # Arguments
+    /// Unlike `search()`, the number of results is not fixed: this returns
+    /// every neighbor the graph traversal finds inside the radius, which suits
+    /// deduplication ("anything closer than 0.1 is a duplicate") and
+    /// clustering. Distances use the same scale as `search()`: with
+    /// `Distance::DotProduct`, a negative radius `-s` finds inner products of
+    /// at least `s`.
     ///
-    /// * `vector` - Vector to add (must match index dimensions)
+    /// The traversal is approximate in the same way as `search()`; raising
+    /// `ef_search` finds more of the in-range vectors in sparse regions.
     ///
     /// # Returns
     ///
-    /// Returns the ID of the inserted vector
-    ///
-    /// # Crash Consistency Protocol (ADR-005)
+    /// All found vectors with `distance <= max_distance`, sorted by distance
+    /// (ascending)
     ///
-    /// This method follows a strict 3-phase protocol:
-    /// 1. **Persist Vector**: Write vector data to storage
-    /// 2. **Write Node**: Write HNSW node with links (invisible to readers)
-    /// 3. **Publish**: Update in-memory counters (visible to readers)
+    /// # Errors
     ///
-    /// If a crash occurs during step 2, we have a "ghost node" (vector without
-    /// graph entry). The next add() will reclaim this space.
+    /// Returns an error if query dimensions don't match index dimensions, or
+    /// if `max_distance` is NaN, or negative for a distance that cannot be
+    pub fn search_within(&self, query: &[f32], max_distance: f32) -> Result<Vec<SearchResult>> {
+        self.search_within_with_options(query, max_distance, &SearchOptions::default())
+    }
+
+    /// Find all vectors within `max_distance` of the query, with per-search options
     ///
     /// # Errors
     ///
-    /// Returns an error if:
-    /// - Vector dimensions don't match index dimensions
-    /// - Storage write fails
-    /// - Graph write fails
-    pub fn add(&mut self, vector: &[f32]) -> Result<u64> {
-        // Validate dimensions
-        let dims = self.graph.storage.dimensions() as usize;
-        if vector.len() != dims {
-            anyhow::bail!("Vector dimension mismatch: expected {}, got {}", dims, vector.len());
-        }
-
-        // Relocate graph zone if the next vector append would overlap it
-        self.graph.prepare_for_vector_insert()?;
-
-        // STEP 1: Persist vector (reclaims ghost node space if any)
-        let new_id = self.graph.storage.insert(vector)?;
-
-        // STEP 2: Determine layer for new node
-        let layer = self.select_layer();
-        let layer_count = layer + 1;
-
-        // STEP 3: Handle empty graph case
-        if self.graph.node_count() == 0 {
-            // Empty graph - just publish the node
-            self.graph.write_node_and_backlinks(new_id, layer_count, &vec![vec![]; layer_count])?;
-            self.graph.publish_node(new_id, layer_count)?;
-            return Ok(new_id);
+    /// Returns an error if query dimensions don't match index dimensions, or
+    /// if `max_distance` is NaN, or negative for a distance that cannot be
+    pub fn search_within_with_options(
+        &self,
+        query: &[f32],
+        max_distance: f32,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchResult>> {
+        let query = self.stored_prefix(query, "Query")?;
+
+        if max_distance.is_nan() || (max_distance < 0.0 && !self.distance().can_be_negative()) {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!("max_distance must be non-negative, got {}", max_distance)
+            ));
         }
 
-        // STEP 4: Neighbor selection (in-memory phase)
-        let neighbors = self.select_neighbors(vector, new_id, layer)?;
-
-        // STEP 5: Atomic write (disk phase)
-        // Node is written but invisible (node_count not incremented)
-        self.graph.write_node_and_backlinks(new_id, layer_count, &neighbors)?;
-
-        // STEP 6: Publish (commit phase)
-        // Node becomes visible to readers
-        self.graph.publish_node(new_id, layer_count)?;
-
-        Ok(new_id)
+        let id_limit = self.id_limit(options);
+        let visible = |id| self.is_visible(options, id);
+        let predicate = self.hides_reused(options).then_some(&visible as &dyn Fn(u64) -> bool);
+
+        let timer = self.stats.timer();
+        let exact = self.prefers_exact();
+        let results = self.observe_search(0, exact, || {
+            if exact {
+                let mut results = self.scan(&query, id_limit)?;
+                results.retain(|r| r.distance <= max_distance && visible(r.id));
+                results.sort_unstable_by(exact_order);
+                Ok(results)
+            } else {
+                let ef = self.options.ef_search;
+                self.graph.search_range_filtered(&query, max_distance, ef, id_limit, predicate)
+            }
+        })?;
+        self.stats.record_range_query(timer);
+        Ok(results)
     }
 
-    /// Search for k nearest neighbors
+    /// Find the exact k nearest neighbors by scanning every vector
     ///
-    /// # Arguments
+    /// Unlike `search()`, the result never misses a neighbor, at a cost linear
+    /// in the number of vectors. Distances are computed with the same SIMD
+    /// kernels as graph search. Ties are broken by ID.
     ///
-    /// * `query` - Query vector (must match index dimensions)
-    /// * `k` - Number of nearest neighbors to return
+    /// # Errors
     ///
-    /// # Returns
+    /// Returns an error if query dimensions don't match index dimensions
+    pub fn search_exact(&self, query: &[f32], k: usize) -> Result<Vec<SearchResult>> {
+        let query = self.stored_prefix(query, "Query")?;
+
+        let timer = self.stats.timer();
+        let results = self.observe_search(k, true, || self.scan_nearest(&query, k, u64::MAX))?;
+        self.stats.record_query(timer, k, self.options.ef_search);
+        Ok(results)
+    }
+
+    /// Compute the exact distances from `query` to the vectors `ids`
     ///
-    /// Returns a vector of search results, sorted by distance (ascending)
+    /// A CPU fallback for bulk rescoring, for example of candidates gathered
+    /// by several searches or by an application-side filter, with the same
+    /// distance and SIMD kernels as `search()`. Deleted vectors are skipped;
+    /// the rest are returned sorted by distance, ties by ID. For a GPU or
+    /// BLAS library, take the raw vectors from `vector_matrix_slice()`
+    /// instead.
     ///
     /// # Errors
     ///
-    /// Returns an error if query dimensions don't match index dimensions
-    pub fn search(&self, query: &[f32], k: usize) -> Result<Vec<SearchResult>> {
-        // Validate dimensions
-        let dims = self.graph.storage.dimensions() as usize;
-        if query.len() != dims {
-            anyhow::bail!("Query dimension mismatch: expected {}, got {}", dims, query.len());
+    /// Returns `DimensionMismatch` if the query dimensions don't match, or
+    /// `OutOfBounds` if an ID is not below `len()`
+    pub fn rescore(&self, ids: &[u64], query: &[f32]) -> Result<Vec<SearchResult>> {
+        let query = self.stored_prefix(query, "Query")?;
+        let storage = &self.graph.storage;
+        let query = Query::new(&query, self.graph.metric());
+        let metric = self.graph.metric();
+
+        let mut results = Vec::with_capacity(ids.len());
+        for &id in ids {
+            if self.is_deleted(id)? {
+                continue;
+            }
+            instrument::record_distance(|| self.graph.vector_page(id));
+            let distance = storage.stored_vector(id)?.distance_to(&query, metric);
+            results.push(SearchResult { id, distance: metric.reported(distance) });
         }
+        results.sort_unstable_by(exact_order);
+        Ok(results)
+    }
 
-        // Delegate to graph search with configured ef_search
-        self.graph.search(query, k, self.options.ef_search)
+    /// Borrow the vectors with IDs in `range` as one row-major `f32` matrix
+    ///
+    /// See `Storage::vector_matrix_slice()`. Rows of deleted vectors are
+    /// included; check `is_deleted()` before using them.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidArgument` if vectors are not stored as `f32`, or
+    /// `OutOfBounds` if the range extends past `len()`
+    pub fn vector_matrix_slice(&self, range: Range<u64>) -> Result<&[f32]> {
+        self.graph.storage.vector_matrix_slice(range)
     }
 
     /// Flush all changes to disk
     ///
+    /// Before writing anything, the index checks that its path still refers
+    /// to the file it opened. If the file was deleted or replaced (for example
+    /// by a sync tool), a `FileStolen` error is returned and nothing is
+    /// flushed; see `reattach()`.
+    ///
     /// This method ensures durability by:
     /// 1. Flushing vector data to disk
     /// 2. Flushing graph metadata to disk
@@ -271,16 +2884,160 @@ impl VectorIndex {
     ///
     /// Returns an error if the flush fails
     pub fn flush(&mut self) -> Result<()> {
-        // Flush vector storage first
+        let timer = self.stats.timer();
+        self.link_step()?;
+        self.write_id_maps()?;
+
+        // Flush vector storage and node records first
+        self.graph.drain_write_buffer()?;
         self.graph.storage.commit()?;
 
-        // Then flush graph metadata
+        // Reused slots turn live in the file only now that their vectors are
+        // synced; then flush graph metadata
+        self.graph.clear_revived_flags()?;
         self.graph.commit()?;
 
+        self.durable_count.store(self.graph.node_count(), Ordering::Release);
+        self.reset_flush_policy();
+        self.report_flush(timer, false);
+        Ok(())
+    }
+
+    /// Start a flush that completes on a background thread
+    ///
+    /// Checks the file like `flush()`, hands the pages written since the last
+    /// flush to the OS for write-back and returns; `fsync` and the graph
+    /// header update run on a background thread. The writer can keep adding
+    /// vectors meanwhile. `durable_len()` advances once the background flush
+    /// has completed.
+    ///
+    /// Only one flush runs at a time: `flush()`, the next `flush_async()` and
+    /// `wait_for_flush()` wait for the previous one and report its error.
+    /// Dropping the index also waits for it. Indexes inside a `Collections`
+    /// file flush synchronously.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the previous background flush failed, the file was
+    /// moved or replaced (`FileStolen`), or the flush thread cannot start.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn flush_async(&mut self) -> Result<()> {
+        // Reused slots must not turn live in the file before their vectors
+        // are synced, which only `flush()` orders
+        if self.graph.has_revived() {
+            return self.flush();
+        }
+
+        let timer = self.stats.timer();
+        self.link_step()?;
+        self.write_id_maps()?;
+        self.commit_in_background()?;
+        self.reset_flush_policy();
+        self.report_flush(timer, true);
+        Ok(())
+    }
+
+    /// Flush an index shared behind a lock, without holding the lock across `fsync`
+    ///
+    /// For wrappers that share the index between threads, like `WriteHandle`.
+    /// The lock is held exclusively while the changes are handed to the OS
+    /// and again to check the result, but searches keep running while the
+    /// file syncs. Returns once the changes are durable, like `flush()`;
+    /// reused slots (`IdReuse::ReuseDeleted`) need a second sync, also
+    /// waited for outside the lock.
+    ///
+    /// The caller must keep other writers out until this returns: the lock
+    /// alone does not, since it is released halfway.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for the same reasons as `flush()`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn flush_locked(index: &RwLock<Self>) -> Result<()> {
+        let lock = || index.write().unwrap_or_else(PoisonError::into_inner);
+
+        let mut guard = lock();
+        let timer = guard.stats.timer();
+        guard.link_step()?;
+        guard.write_id_maps()?;
+        guard.reset_flush_policy();
+        loop {
+            guard.commit_in_background()?;
+            if let Some(signal) = guard.graph.storage.background_signal() {
+                drop(guard);
+                signal.wait();
+                guard = lock();
+            }
+            guard.wait_for_flush()?;
+
+            // Reused slots turn live in the file only now that their vectors
+            // are synced
+            if !guard.graph.has_revived() {
+                break;
+            }
+            guard.graph.clear_revived_flags()?;
+        }
+        guard.report_flush(timer, false);
         Ok(())
     }
 
+    /// Commit on a background thread, advancing `durable_len()` once the commit is durable
+    #[cfg(not(target_arch = "wasm32"))]
+    fn commit_in_background(&mut self) -> Result<()> {
+        let node_count = self.graph.node_count();
+        let durable_count = Arc::clone(&self.durable_count);
+        self.graph.commit_in_background(move || {
+            durable_count.fetch_max(node_count, Ordering::Release);
+        })
+    }
+
+    /// Wait for a `flush_async()` in progress, if any
+    ///
+    /// # Errors
+    ///
+    /// Returns the error the background flush failed with.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn wait_for_flush(&mut self) -> Result<()> {
+        self.graph.storage.wait_for_background()
+    }
+
+    /// Write the changes to the ID maps into the file ahead of a commit
+    fn write_id_maps(&mut self) -> Result<()> {
+        let storage = &mut self.graph.storage;
+        storage.write_map(&mut self.keys)?;
+        storage.write_map(&mut self.groups)?;
+        storage.write_map(&mut self.tags)?;
+        storage.write_map(&mut self.expiry)?;
+        storage.write_map(&mut self.free_ids)
+    }
+
+    /// Count a completed flush and pass it to the attached instrumentation, if any
+    fn report_flush(&self, timer: Timer, background: bool) {
+        self.stats.record_flush();
+        if let Some(hooks) = &self.hooks.0 {
+            let vectors = self.graph.node_count();
+            hooks.on_flush(&FlushEvent { vectors, background, duration: timer.elapsed() });
+        }
+    }
+
+    /// Restart the `flush_policy` counters after a flush
+    fn reset_flush_policy(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.unflushed_inserts = 0;
+            self.last_flush = Instant::now();
+        }
+    }
+
+    /// Get the number of vectors that were durable at the last completed flush (or at open)
+    pub fn durable_len(&self) -> u64 {
+        self.durable_count.load(Ordering::Acquire)
+    }
+
     /// Get the number of vectors in the index
+    ///
+    /// Deleted vectors are included: IDs run from 0 to `len() - 1` whether or
+    /// not they are deleted.
     pub fn len(&self) -> u64 {
         self.graph.node_count()
     }
@@ -290,13 +3047,500 @@ impl VectorIndex {
         self.graph.node_count() == 0
     }
 
+    /// Get the options in effect
+    ///
+    /// The graph parameters are those stored in the file, which may differ
+    /// from the options passed to `open()` for an existing index.
+    pub fn options(&self) -> &IndexOptions {
+        &self.options
+    }
+
+    /// Get the HNSW graph, for traversal experiments
+    ///
+    /// Graph node IDs are vector IDs. The graph sees unflushed inserts and
+    /// deleted nodes; only `search()` and the other `VectorIndex` searches
+    /// apply the index's filters and rotation.
+    #[cfg(feature = "internals")]
+    pub fn graph(&self) -> &HnswGraph {
+        &self.graph
+    }
+
     /// Get the dimensionality of vectors in this index
     pub fn dimensions(&self) -> u32 {
         self.graph.storage.dimensions()
     }
 
+    /// Get the on-disk encoding of vector components
+    pub fn element_type(&self) -> ElementType {
+        self.graph.storage.element_type()
+    }
+
+    /// Distance function the index is built and searched with
+    pub fn distance(&self) -> Distance {
+        self.graph.distance()
+    }
+
+    /// Train a PCA rotation over the stored vectors and persist it in the file.
+    ///
+    /// At most `max_samples` vectors are used, spread evenly across the index.
+    /// The rotation replaces any previously trained one and becomes durable on
+    /// the next `flush()`. Vectors stored as `f32` are exact, so searches of
+    /// this index are unaffected: the rotation is applied before the vectors
+    /// are quantized, in the copies `quantize_to()` writes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidArgument` if the index does not store `f32` vectors or
+    /// fewer than two vectors are available for training, or an error if the
+    /// rotation cannot be written to the file.
+    #[cfg(feature = "linalg")]
+    pub fn train_rotation(&mut self, max_samples: usize) -> Result<&Rotation> {
+        if self.element_type() != ElementType::F32 {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "Rotations are trained on f32 vectors, not {}; train on the full-precision \
+                     index and quantize it with quantize_to()",
+                    self.element_type()
+                )
+            ));
+        }
+        let count = self.len();
+        let samples = usize::try_from(count).unwrap_or(usize::MAX).min(max_samples) as u64;
+        if samples < 2 {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!("Rotation training requires at least 2 samples, got {}", samples)
+            ));
+        }
+
+        let storage = &self.graph.storage;
+        let ids = (0..samples).map(|i| i * count / samples);
+        let vectors = ids.map(|id| storage.get_vector(id)).collect::<Result<Vec<_>>>()?;
+        let rotation =
+            Rotation::train(vectors.iter().map(Vec::as_slice), self.dimensions() as usize)?;
+        self.put_rotation(rotation)
+    }
+
+    /// Returns the persisted PCA rotation, if one has been trained.
+    ///
+    /// In an index storing quantized vectors, it is the rotation every vector
+    /// and query is put through first.
+    #[cfg(feature = "linalg")]
+    pub fn rotation(&self) -> Option<&Rotation> {
+        self.rotation.as_ref()
+    }
+
+    /// Persist `rotation` in the file and use it from now on
+    #[cfg(feature = "linalg")]
+    pub(crate) fn put_rotation(&mut self, rotation: Rotation) -> Result<&Rotation> {
+        self.graph
+            .storage
+            .put_metadata_section(rotation::ROTATION_SECTION, &rotation.to_bytes())?;
+        Ok(self.rotation.insert(rotation))
+    }
+
+    /// Rotation applied to vectors before they are encoded: only indexes that
+    /// quantize their vectors apply the one they hold
+    #[cfg(feature = "linalg")]
+    pub(crate) fn applied_rotation(&self) -> Option<&Rotation> {
+        self.rotation.as_ref().filter(|_| self.element_type() != ElementType::F32)
+    }
+
+    /// Stored vector `id` decoded to `f32`, rotated back if the index applies a
+    /// rotation; `Binary` vectors keep only the signs of the rotated vector
+    pub(crate) fn decoded_vector(&self, id: u64) -> Result<Vec<f32>> {
+        let vector = self.graph.storage.get_vector(id)?;
+        #[cfg(feature = "linalg")]
+        if let Some(rotation) = self.applied_rotation()
+            && self.element_type() != ElementType::Binary
+        {
+            let mut original = vec![0.0; vector.len()];
+            rotation.invert_into(&vector, &mut original);
+            return Ok(original);
+        }
+        Ok(vector)
+    }
+
+    /// Get the dimensionality `add()` and `search()` expect from callers
+    ///
+    /// Equals `dimensions()` unless the index truncates Matryoshka-style inputs.
+    pub fn input_dimensions(&self) -> u32 {
+        self.options.input_dimensions.unwrap_or_else(|| self.dimensions())
+    }
+
     // Private helper methods
 
+    /// Validate an incoming vector and return the prefix that is stored/searched,
+    /// normalized if the index normalizes vectors and rotated if it applies a
+    /// rotation
+    pub(crate) fn stored_prefix<'a>(
+        &self,
+        vector: &'a [f32],
+        kind: &str,
+    ) -> Result<Cow<'a, [f32]>> {
+        let input_dims = self.input_dimensions() as usize;
+        if vector.len() != input_dims {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::DimensionMismatch,
+                format!(
+                    "{} dimension mismatch: expected {}, got {}",
+                    kind,
+                    input_dims,
+                    vector.len()
+                )
+            ));
+        }
+
+        let prefix = &vector[..self.dimensions() as usize];
+        let mut prefix = Cow::Borrowed(prefix);
+        if !distance::all_finite(&prefix) {
+            match self.options.nan_policy {
+                NanPolicy::Reject => anyhow::bail!(Tagged::new(
+                    ErrorKind::InvalidArgument,
+                    format!("{} contains NaN or infinite components", kind)
+                )),
+                NanPolicy::ReplaceWithZero => {
+                    prefix.to_mut().iter_mut().filter(|x| !x.is_finite()).for_each(|x| *x = 0.0)
+                }
+            }
+        }
+        if self.graph.normalizes() {
+            let mut normalized = prefix.into_owned();
+            if !distance::normalize(&mut normalized) {
+                anyhow::bail!(Tagged::new(
+                    ErrorKind::InvalidArgument,
+                    format!("{} cannot be normalized: it has zero or non-finite length", kind)
+                ));
+            }
+            prefix = Cow::Owned(normalized);
+        }
+        #[cfg(feature = "linalg")]
+        if let Some(rotation) = self.applied_rotation() {
+            prefix = Cow::Owned(rotation.apply(&prefix));
+        }
+        Ok(prefix)
+    }
+
+    /// Whether searches should scan instead of walking the graph
+    fn prefers_exact(&self) -> bool {
+        (self.options.exact_search_threshold > 0
+            && self.len() <= self.options.exact_search_threshold)
+            || self.unlinked_len() > 0
+    }
+
+    /// Number of vectors inserted under `flat_threshold` and not yet linked
+    /// into the graph
+    ///
+    /// Searches scan every vector while this is not zero.
+    pub fn unlinked_len(&self) -> u64 {
+        let linked = self.graph.storage.linked_count().unwrap_or(u64::MAX);
+        self.len().saturating_sub(linked)
+    }
+
+    /// Whether vector `id` is linked into the graph
+    fn is_linked(&self, id: u64) -> bool {
+        self.graph.storage.linked_count().is_none_or(|linked| id < linked)
+    }
+
+    /// Whether vectors are pending past `flat_threshold`, for `add()`,
+    /// `flush()` or an idle writer to link
+    pub(crate) fn wants_linking(&self) -> bool {
+        self.unlinked_len() > 0
+            && self.len() >= self.options.flat_threshold
+            && !self.graph.storage.is_shared_reader()
+    }
+
+    /// Link the next `LINK_STEP` pending vectors, if the index is past `flat_threshold`
+    fn link_step(&mut self) -> Result<()> {
+        if self.wants_linking() {
+            self.link_pending(LINK_STEP)?;
+        }
+        Ok(())
+    }
+
+    /// `link_graph_batch()` without progress reports, for the steps taken
+    /// alongside other work, which a progress handler must not cancel
+    pub(crate) fn link_pending(&mut self, max: u64) -> Result<u64> {
+        self.link_reporting(max, &ProgressSlot::default())
+    }
+
+    /// Link the vectors inserted under `flat_threshold` into the graph
+    ///
+    /// The pending vectors are linked in ID order, as if they were added
+    /// now, and searches walk the graph again afterwards. Past the threshold
+    /// `add()` and `flush()` do this a few vectors at a time; calling it moves
+    /// the rest of the work to a convenient moment, and `link_graph_batch()`
+    /// spreads it. Durable on the next `flush()`. No-op if no vector is
+    /// pending. Progress is reported as for `link_graph_batch()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReadOnly` for a shared reader, `Cancelled` if the progress
+    /// handler stopped it (the vectors linked so far stay linked), or an
+    /// error if the graph cannot be written.
+    pub fn link_graph(&mut self) -> Result<()> {
+        self.link_graph_batch(u64::MAX).map(|_| ())
+    }
+
+    /// Link up to `max` of the pending vectors into the graph and return the
+    /// number still pending
+    ///
+    /// Like `link_graph()`, oldest first, in steps short enough to run
+    /// between other work. Searches keep scanning every vector until none is
+    /// pending, so results stay exact while the graph is built. Progress is
+    /// reported to the handler of `set_progress_handler()` as
+    /// `ProgressStage::Link`, one unit per vector; the steps `add()` and
+    /// `flush()` take are not reported.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReadOnly` for a shared reader, `Cancelled` if the progress
+    /// handler stopped it, or an error if the graph cannot be written.
+    /// Vectors linked before the error stay linked.
+    pub fn link_graph_batch(&mut self, max: u64) -> Result<u64> {
+        let progress = self.progress.clone();
+        self.link_reporting(max, &progress)
+    }
+
+    /// Link up to `max` pending vectors, reporting to `progress`
+    fn link_reporting(&mut self, max: u64, progress: &ProgressSlot) -> Result<u64> {
+        let Some(linked) = self.graph.storage.linked_count() else {
+            return Ok(0);
+        };
+        self.graph.storage.ensure_writable("link_graph")?;
+
+        // Unlinked nodes were published as entry points; promote the linked
+        // ones again in ID order so neighbor searches only start from those
+        let graph = &self.graph;
+        if graph.entry_point.is_none_or(|entry| entry >= linked)
+            || graph.entry_candidates.iter().any(|&(id, _)| id >= linked)
+        {
+            self.graph.reset_entry_points();
+            for id in 0..linked {
+                let layer_count = self.graph.node_layer_count(id)?;
+                self.graph.promote_entry(id, layer_count);
+            }
+        }
+
+        let end = linked.saturating_add(max).min(self.len());
+        let progress = progress.start(ProgressStage::Link, end - linked);
+        for id in linked..end {
+            progress.report(id - linked)?;
+
+            // A deleted node has nothing linking to it, so it must not become
+            // an entry point
+            if !self.graph.is_deleted(id)? {
+                let layer_count = self.graph.node_layer_count(id)?;
+                if self.graph.entry_point.is_some() {
+                    let vector = self.graph.storage.get_vector(id)?;
+                    let neighbors = self.select_neighbors(&vector, id, layer_count - 1)?;
+                    self.graph.link_unlinked_node(id, &neighbors)?;
+                }
+                self.graph.promote_entry(id, layer_count);
+            }
+            self.graph.storage.set_linked_count(Some(id + 1));
+        }
+
+        let pending = self.len() - end;
+        if pending == 0 {
+            self.graph.storage.set_linked_count(None);
+        }
+        progress.report(end - linked)?;
+        Ok(pending)
+    }
+
+    /// Improve the graph by selecting every node's neighbors again
+    ///
+    /// Nodes linked early chose their neighbors among the few vectors present
+    /// at the time, which leaves sequentially built graphs with poor
+    /// long-range edges. Each of the `iterations` passes visits the live
+    /// nodes in ID order, searches the current graph for each one's
+    /// candidates as `add()` would, and picks new neighbors among those and
+    /// its current ones. Changed records are rewritten in place, so recall on
+    /// an old index improves without a rebuild; passes after the first
+    /// usually change far fewer nodes. Vectors pending under
+    /// `flat_threshold` are linked first.
+    ///
+    /// A pass costs about as much as inserting every vector again, so run it
+    /// at an idle moment. Durable on the next `flush()`; a crash in between
+    /// leaves each record with either its old or its new neighbors.
+    ///
+    /// Returns the number of node records that changed. Progress is reported
+    /// to the handler of `set_progress_handler()`, one unit per node and pass.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReadOnly` for a shared reader, `Cancelled` if the progress
+    /// handler stopped the passes (the records rewritten so far stay), or an
+    /// error if the graph cannot be read or written.
+    pub fn optimize(&mut self, iterations: usize) -> Result<u64> {
+        self.graph.storage.ensure_writable("optimize")?;
+        self.link_graph()?;
+
+        let len = self.len();
+        let progress = self.progress.start(ProgressStage::Optimize, iterations as u64 * len);
+
+        let mut changed = 0;
+        for pass in 0..iterations as u64 {
+            for id in 0..len {
+                progress.report(pass * len + id)?;
+                if self.graph.is_deleted(id)? {
+                    continue;
+                }
+
+                let vector = self.graph.storage.get_vector(id)?;
+                let record = self.graph.read_node_record(id)?;
+                let layer_count = record.header.layer_count as usize;
+                let candidates = self.neighbor_candidates(&vector, id, layer_count - 1)?;
+                let prepared = Query::new(&vector, self.graph.metric());
+
+                let mut neighbors = Vec::with_capacity(layer_count);
+                for (layer, mut pool) in candidates.into_iter().enumerate() {
+                    // Current neighbors compete with the search results
+                    for neighbor in record.get_neighbors(layer) {
+                        if neighbor != id && pool.iter().all(|r| r.id != neighbor) {
+                            let distance = self.graph.query_distance(&prepared, neighbor)?;
+                            pool.push(SearchResult { id: neighbor, distance });
+                        }
+                    }
+                    pool.sort_unstable_by(exact_order);
+
+                    // Only the nearest candidates reach the heuristic; pinned ones must
+                    let mut pinned = Vec::new();
+                    for (position, result) in pool.iter().enumerate() {
+                        if self.graph.is_pinned(result.id)? {
+                            pinned.push(position);
+                        }
+                    }
+                    for (front, position) in pinned.into_iter().enumerate() {
+                        pool[front..=position].rotate_right(1);
+                    }
+
+                    let pool_ids: Vec<u64> = pool.iter().map(|r| r.id).collect();
+                    let max_neighbors = self.max_neighbors(layer);
+                    neighbors.push(self.select_diverse_subset(
+                        id,
+                        &pool_ids,
+                        layer,
+                        max_neighbors,
+                    )?);
+                }
+
+                if self.graph.replace_neighbors(id, &neighbors)? {
+                    changed += 1;
+                }
+            }
+        }
+        progress.report(iterations as u64 * len)?;
+        Ok(changed)
+    }
+
+    /// First ID a search with `options` must not return
+    fn id_limit(&self, options: &SearchOptions) -> u64 {
+        match options.consistency {
+            SearchConsistency::IncludeUnflushed => u64::MAX,
+            SearchConsistency::DurableOnly => self.durable_len(),
+        }
+    }
+
+    /// Whether a search with `options` must skip IDs below `id_limit()` too:
+    /// deleted slots reused since the last flush, which the file has deleted
+    fn hides_reused(&self, options: &SearchOptions) -> bool {
+        options.consistency == SearchConsistency::DurableOnly && self.graph.has_revived()
+    }
+
+    /// Whether a search with `options` may return vector `id` below `id_limit()`
+    fn is_visible(&self, options: &SearchOptions, id: u64) -> bool {
+        !(options.consistency == SearchConsistency::DurableOnly && self.graph.is_revived(id))
+    }
+
+    /// Distances from `query` (already truncated) to every live vector below
+    /// `id_limit`, as returned to callers
+    fn scan(&self, query: &[f32], id_limit: u64) -> Result<Vec<SearchResult>> {
+        self.scan_budgeted(query, id_limit, BudgetMeter::unlimited())
+    }
+
+    /// `scan` that stops once the budget of `meter` is spent, and fails once
+    /// its token is cancelled
+    fn scan_budgeted(
+        &self,
+        query: &[f32],
+        id_limit: u64,
+        mut meter: BudgetMeter,
+    ) -> Result<Vec<SearchResult>> {
+        let storage = &self.graph.storage;
+        let count = storage.count().min(id_limit);
+        let metric = self.graph.metric();
+        let query = &Query::new(query, metric);
+
+        let mut results = Vec::with_capacity(count as usize);
+        for id in 0..count {
+            if id % 64 == 0 {
+                meter.check_cancelled()?;
+            }
+            // Clock reads are amortized over blocks of vectors
+            if id % 64 == 0 && meter.exhausted() || !meter.can_compute() {
+                break;
+            }
+            if self.graph.is_deleted(id)? {
+                continue;
+            }
+            meter.spend();
+            instrument::record_distance(|| self.graph.vector_page(id));
+            let distance = storage.stored_vector(id)?.distance_to(query, metric);
+            results.push(SearchResult { id, distance: metric.reported(distance) });
+        }
+        Ok(results)
+    }
+
+    /// Run `search`, counting its distance computations and reporting it to
+    /// the attached instrumentation, if any
+    fn observe_search<T: SearchOutput>(
+        &self,
+        k: usize,
+        exact: bool,
+        search: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        let distances = instrument::distances_on_thread();
+        let counted = || {
+            let results = search();
+            self.stats.record_distances(instrument::distances_on_thread() - distances);
+            results
+        };
+        let Some(hooks) = &self.hooks.0 else {
+            return counted();
+        };
+
+        hooks.on_search_start(k);
+        let timer = self.stats.timer();
+        instrument::begin();
+        let results = counted();
+        let mut trace = instrument::end();
+        let results = results?;
+
+        hooks.on_search_end(&SearchEvent {
+            k,
+            results: results.result_count(),
+            exact,
+            hops: trace.hops,
+            distance_computations: trace.distance_computations,
+            pages_touched: trace.pages_touched(),
+            duration: timer.elapsed(),
+        });
+        Ok(results)
+    }
+
+    /// Exact k nearest neighbors of `query` (already truncated) below `id_limit`
+    pub(crate) fn scan_nearest(
+        &self,
+        query: &[f32],
+        k: usize,
+        id_limit: u64,
+    ) -> Result<Vec<SearchResult>> {
+        Ok(nearest(self.scan(query, id_limit)?, k))
+    }
+
     /// Select layer for a new node using exponential decay
     fn select_layer(&self) -> usize {
         let uniform: f32 = rand::random();
@@ -309,12 +3553,32 @@ impl VectorIndex {
     /// - Phase 1 (Zoom): Greedy descent from entry point to target layer
     /// - Phase 2 (Construction): Select diverse neighbors at each layer
     fn select_neighbors(
-        &mut self,
+        &self,
         vector: &[f32],
         new_id: u64,
         target_layer: usize,
     ) -> Result<Vec<Vec<u64>>> {
-        let mut neighbors = vec![Vec::new(); target_layer + 1];
+        let candidates = self.neighbor_candidates(vector, new_id, target_layer)?;
+        candidates
+            .iter()
+            .enumerate()
+            .map(|(layer, candidates)| {
+                let candidate_ids: Vec<u64> = candidates.iter().map(|r| r.id).collect();
+                self.select_diverse_subset(new_id, &candidate_ids, layer, self.max_neighbors(layer))
+            })
+            .collect()
+    }
+
+    /// Candidate neighbors of `vector` at each layer up to `target_layer`,
+    /// nearest first and without `node_id` itself (the search phases of
+    /// `select_neighbors()`)
+    fn neighbor_candidates(
+        &self,
+        vector: &[f32],
+        node_id: u64,
+        target_layer: usize,
+    ) -> Result<Vec<Vec<SearchResult>>> {
+        let mut candidates_per_layer = vec![Vec::new(); target_layer + 1];
 
         let entry_point = self.graph.entry_point.expect("Graph should have entry point");
         let max_layer = self.graph.max_layer;
@@ -325,39 +3589,40 @@ impl VectorIndex {
             curr = self.graph.search_layer_greedy(vector, curr, layer)?;
         }
 
-        // Phase 2: Construction - select neighbors at each layer
+        // Phase 2: Construction - search for candidates at each layer
+        let ef = self.construction_ef();
         for layer in (0..=target_layer.min(max_layer)).rev() {
-            // Search for candidates
-            let candidates = self.graph.search_layer_optimized(
-                vector,
-                curr,
-                self.options.ef_construction,
-                layer,
-            )?;
-
-            // Extract candidate IDs
-            let candidate_ids: Vec<u64> = candidates.iter().map(|r| r.id).collect();
-
-            // Determine max neighbors for this layer
-            let max_neighbors = if layer == 0 {
-                self.options.max_connections as usize * 2
-            } else {
-                self.options.max_connections as usize
-            };
-
-            // Select diverse neighbors using unified heuristic
-            let selected =
-                self.select_diverse_subset(new_id, &candidate_ids, layer, max_neighbors)?;
-
-            neighbors[layer] = selected;
+            let candidates = self.graph.search_layer_optimized(vector, curr, ef, layer)?;
 
             // Update curr to closest candidate for next layer
             if !candidates.is_empty() {
                 curr = candidates[0].id;
             }
+
+            // A reused or optimized node finds its own slot
+            candidates_per_layer[layer] =
+                candidates.into_iter().filter(|r| r.id != node_id).collect();
+        }
+
+        Ok(candidates_per_layer)
+    }
+
+    /// Beam width of the construction searches, see `adaptive_ef_construction`
+    fn construction_ef(&self) -> usize {
+        if self.options.adaptive_ef_construction {
+            adaptive_ef(
+                self.options.ef_construction,
+                self.graph.node_count(),
+                self.max_neighbors(0),
+            )
+        } else {
+            self.options.ef_construction
         }
+    }
 
-        Ok(neighbors)
+    /// Most neighbors a node keeps on `layer`
+    fn max_neighbors(&self, layer: usize) -> usize {
+        self.graph.record_params.max_neighbors(layer)
     }
 
     /// Select a diverse subset of neighbors using the diversity heuristic
@@ -367,9 +3632,10 @@ impl VectorIndex {
     ///
     /// # Truncation for Cache Safety
     ///
-    /// The diversity heuristic uses a stack-allocated cache sized for MAX_M+1 (33) nodes.
-    /// Since search results from `ef_construction` can be much larger (e.g., 200 nodes),
-    /// we truncate to the top 33 closest candidates. This is safe because:
+    /// The diversity heuristic uses a stack-allocated cache sized for 33 nodes (65 for
+    /// layer-0 lists above the default M0 of 32). Since search results from
+    /// `ef_construction` can be much larger (e.g., 200 nodes), we truncate to the
+    /// closest 33, or `max_count + 1` if that is more. This is safe because:
     /// 1. Search results are already sorted by distance (ascending)
     /// 2. The best diverse neighbors are likely among the closest candidates
     /// 3. We maintain O(1) stack allocation for the distance cache
@@ -384,10 +3650,10 @@ impl VectorIndex {
             return Ok(Vec::new());
         }
 
-        // Truncate candidates to fit in cache (33 = MAX_M + 1)
+        // Truncate candidates to fit in cache (33 = M0 + 1 by default)
         // Search results are already sorted by distance, so we keep the closest
-        let truncated_candidates: Vec<u64> =
-            candidates.iter().take(MAX_CANDIDATES_FOR_HEURISTIC).copied().collect();
+        let limit = MAX_CANDIDATES_FOR_HEURISTIC.max(max_count + 1);
+        let truncated_candidates: Vec<u64> = candidates.iter().take(limit).copied().collect();
 
         // Delegate to unified heuristic (no priority for forward linking)
         self.graph.select_neighbors_heuristic(
@@ -510,10 +3776,13 @@ mod tests {
 
         // Reopen with VectorIndex - should handle ghost node
         {
-            let mut index = VectorIndex::open(&path, 128, IndexOptions::default()).unwrap();
+            let (mut index, report) =
+                VectorIndex::open_with_report(&path, 128, IndexOptions::default()).unwrap();
 
-            // Ghost node should be ignored (not counted)
+            // Ghost node should be ignored (not counted) and reported
             assert_eq!(index.len(), 0);
+            assert_eq!(report.reclaimed_ids, 0..1);
+            assert!(!report.is_clean());
 
             // Next add should reclaim the ghost node space
             let vector = vec![2.0; 128];
@@ -0,0 +1,508 @@
+//! Byte-addressable backing for `Storage`.
+//!
+//! Native builds map the index file with `memmap2`, or a private mapping for
+//! indexes that live only in memory. With the `wasm` feature the same layout
+//! can live in a page-aligned heap buffer instead, which is what browsers (no
+//! `mmap`, no file locks) require. All variants expose the file image as one
+//! contiguous `[u8]`, so every layer above `Storage` is unchanged. An index
+//! compiled into the binary is read in place from its `&'static [u8]`.
+
+#[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+use memmap2::MmapRaw;
+#[cfg(not(target_arch = "wasm32"))]
+use memmap2::{Mmap, MmapMut};
+use std::ops::{Deref, DerefMut};
+
+#[cfg(all(target_arch = "wasm32", not(feature = "wasm")))]
+compile_error!("chassis-core requires the `wasm` feature on wasm32 targets");
+
+/// Page size used for buffer alignment (matches the on-disk page size).
+#[cfg(feature = "wasm")]
+const PAGE_SIZE: usize = 4096;
+
+/// One page of the in-memory image, aligned like an mmap'd page.
+#[cfg(feature = "wasm")]
+#[repr(C, align(4096))]
+#[derive(Clone, Copy)]
+struct Page([u8; PAGE_SIZE]);
+
+/// Paging behaviour requested from the OS for the mapped index file
+///
+/// Applied with `madvise` on Unix. Memory-backed indexes and other platforms
+/// accept every mode and ignore it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MemoryMode {
+    /// Leave paging to the OS defaults (`MADV_NORMAL`)
+    #[default]
+    Normal,
+
+    /// Queries touch pages in no particular order: disable readahead
+    /// (`MADV_RANDOM`). Avoids thrashing when the file is larger than RAM.
+    Random,
+
+    /// Pages are read front to back, as in bulk builds and full scans:
+    /// read ahead aggressively (`MADV_SEQUENTIAL`)
+    Sequential,
+
+    /// Keep the whole file in RAM: start reading every page in now
+    /// (`MADV_WILLNEED`), and again whenever the file grows
+    Resident,
+}
+
+#[cfg(unix)]
+impl MemoryMode {
+    fn advice(self) -> memmap2::Advice {
+        match self {
+            Self::Normal => memmap2::Advice::Normal,
+            Self::Random => memmap2::Advice::Random,
+            Self::Sequential => memmap2::Advice::Sequential,
+            Self::Resident => memmap2::Advice::WillNeed,
+        }
+    }
+}
+
+/// Active view of the index image.
+#[derive(Debug)]
+pub(crate) enum Mapping {
+    /// Memory-mapped file
+    #[cfg(not(target_arch = "wasm32"))]
+    File(MmapMut),
+
+    /// Read-only (`PROT_READ`) mapping of a sealed file; never written
+    #[cfg(not(target_arch = "wasm32"))]
+    Sealed(Mmap),
+
+    /// Private mapping that is never written back: anonymous memory of a
+    /// memory-only index (`VectorIndex::in_memory()`), or a copy-on-write
+    /// view of another index's file (`VectorIndex::fork_in_memory()`)
+    #[cfg(not(target_arch = "wasm32"))]
+    Private(MmapMut),
+
+    /// Anonymous memory holding the plaintext of an encrypted index; blocks
+    /// are decrypted into it through a raw pointer as they are first accessed
+    #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+    Decrypted(MmapRaw),
+
+    /// Page-aligned heap buffer (browser / in-memory storage)
+    #[cfg(feature = "wasm")]
+    Memory(PageBuffer),
+
+    /// Page-aligned image embedded in the binary (`VectorIndex::open_from_bytes()`);
+    /// never written, like a sealed file
+    Static(&'static [u8]),
+}
+
+impl Mapping {
+    /// Whether this is the read-only mapping of a sealed file
+    pub(crate) fn is_sealed(&self) -> bool {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Sealed(_) => true,
+            Self::Static(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Flush writes to `offset..offset + len` and wait for them (no-op for memory buffers).
+    #[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+    pub(crate) fn flush_range(&self, offset: usize, len: usize) -> std::io::Result<()> {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::File(mmap) => mmap.flush_range(offset, len),
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Sealed(_) | Self::Private(_) => Ok(()),
+            #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+            Self::Decrypted(_) => Ok(()),
+            #[cfg(feature = "wasm")]
+            Self::Memory(_) => Ok(()),
+            Self::Static(_) => Ok(()),
+        }
+    }
+
+    /// Start writing back `offset..offset + len` without waiting (`MS_ASYNC`).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn flush_async_range(&self, offset: usize, len: usize) -> std::io::Result<()> {
+        match self {
+            Self::File(mmap) => mmap.flush_async_range(offset, len),
+            Self::Sealed(_) | Self::Private(_) | Self::Static(_) => Ok(()),
+            #[cfg(feature = "encryption")]
+            Self::Decrypted(_) => Ok(()),
+            #[cfg(feature = "wasm")]
+            Self::Memory(_) => Ok(()),
+        }
+    }
+
+    /// Apply `mode` to the whole mapping.
+    #[cfg_attr(not(unix), allow(unused_variables))]
+    pub(crate) fn advise(&self, mode: MemoryMode) -> std::io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Self::File(mmap) => mmap.advise(mode.advice()),
+            #[cfg(unix)]
+            Self::Sealed(mmap) => mmap.advise(mode.advice()),
+            #[cfg(unix)]
+            Self::Private(mmap) => mmap.advise(mode.advice()),
+            #[cfg(all(not(unix), not(target_arch = "wasm32")))]
+            Self::File(_) | Self::Sealed(_) | Self::Private(_) => Ok(()),
+            // Anonymous memory has no readahead to tune
+            #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+            Self::Decrypted(_) => Ok(()),
+            #[cfg(feature = "wasm")]
+            Self::Memory(_) => Ok(()),
+            Self::Static(_) => Ok(()),
+        }
+    }
+
+    /// Ask the OS to start reading `offset..offset + len` into memory.
+    #[cfg_attr(not(unix), allow(unused_variables))]
+    pub(crate) fn will_need(&self, offset: usize, len: usize) -> std::io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Self::File(mmap) => mmap.advise_range(memmap2::Advice::WillNeed, offset, len),
+            #[cfg(unix)]
+            Self::Sealed(mmap) => mmap.advise_range(memmap2::Advice::WillNeed, offset, len),
+            #[cfg(unix)]
+            Self::Private(mmap) => mmap.advise_range(memmap2::Advice::WillNeed, offset, len),
+            #[cfg(all(not(unix), not(target_arch = "wasm32")))]
+            Self::File(_) | Self::Sealed(_) | Self::Private(_) => Ok(()),
+            // Anonymous memory has no readahead to tune
+            #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+            Self::Decrypted(_) => Ok(()),
+            #[cfg(feature = "wasm")]
+            Self::Memory(_) => Ok(()),
+            Self::Static(_) => Ok(()),
+        }
+    }
+
+    /// Let the OS drop the resident pages of the whole mapping (`MADV_DONTNEED`).
+    ///
+    /// # Safety
+    ///
+    /// The mapping must be a shared or read-only file mapping. On a private
+    /// (copy-on-write) mapping this discards every page modified since it was
+    /// mapped.
+    pub(crate) unsafe fn dont_need(&self) -> std::io::Result<()> {
+        match self {
+            // SAFETY: Upheld by the caller; shared pages are refetched from the page cache.
+            #[cfg(unix)]
+            Self::File(mmap) => unsafe {
+                mmap.unchecked_advise(memmap2::UncheckedAdvice::DontNeed)
+            },
+            // SAFETY: Read-only pages hold no changes and are refetched from the file.
+            #[cfg(unix)]
+            Self::Sealed(mmap) => unsafe {
+                mmap.unchecked_advise(memmap2::UncheckedAdvice::DontNeed)
+            },
+            #[cfg(all(not(unix), not(target_arch = "wasm32")))]
+            Self::File(_) | Self::Sealed(_) => Ok(()),
+            // Private pages hold the only copy of the changes: never discard them
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Private(_) => Ok(()),
+            #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+            Self::Decrypted(_) => Ok(()),
+            #[cfg(feature = "wasm")]
+            Self::Memory(_) => Ok(()),
+            // Pages of the binary are left to the loader
+            Self::Static(_) => Ok(()),
+        }
+    }
+
+    /// Lock the pages of `offset..offset + len` in RAM (`mlock`).
+    ///
+    /// Fails with the OS error when the lock would exceed `RLIMIT_MEMLOCK`, and
+    /// with `Unsupported` on platforms without `mlock`. Memory buffers are
+    /// always resident and accept every range.
+    #[cfg_attr(not(unix), allow(unused_variables))]
+    pub(crate) fn lock(&self, offset: usize, len: usize) -> std::io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Self::File(mmap) => lock_range(&mmap[offset..offset + len]),
+            #[cfg(unix)]
+            Self::Sealed(mmap) => lock_range(&mmap[offset..offset + len]),
+            #[cfg(unix)]
+            Self::Private(mmap) => lock_range(&mmap[offset..offset + len]),
+            #[cfg(all(feature = "encryption", unix))]
+            Self::Decrypted(_) => lock_range(&self[offset..offset + len]),
+            #[cfg(all(not(unix), not(target_arch = "wasm32")))]
+            Self::File(_) | Self::Sealed(_) | Self::Private(_) => {
+                Err(std::io::ErrorKind::Unsupported.into())
+            }
+            #[cfg(all(feature = "encryption", not(unix), not(target_arch = "wasm32")))]
+            Self::Decrypted(_) => Err(std::io::ErrorKind::Unsupported.into()),
+            #[cfg(feature = "wasm")]
+            Self::Memory(_) => Ok(()),
+            #[cfg(unix)]
+            Self::Static(bytes) => lock_range(&bytes[offset..offset + len]),
+            #[cfg(not(unix))]
+            Self::Static(_) => Err(std::io::ErrorKind::Unsupported.into()),
+        }
+    }
+
+    /// Unlock every page of the mapping (`munlock`); pages that were not
+    /// locked are unaffected.
+    pub(crate) fn unlock(&self) -> std::io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Self::File(mmap) => mmap.unlock(),
+            #[cfg(unix)]
+            Self::Sealed(mmap) => mmap.unlock(),
+            #[cfg(unix)]
+            Self::Private(mmap) => mmap.unlock(),
+            #[cfg(all(not(unix), not(target_arch = "wasm32")))]
+            Self::File(_) | Self::Sealed(_) | Self::Private(_) => Ok(()),
+            #[cfg(all(feature = "encryption", unix))]
+            Self::Decrypted(mmap) => mmap.unlock(),
+            #[cfg(all(feature = "encryption", not(unix), not(target_arch = "wasm32")))]
+            Self::Decrypted(_) => Ok(()),
+            #[cfg(feature = "wasm")]
+            Self::Memory(_) => Ok(()),
+            #[cfg(unix)]
+            Self::Static(bytes) => unlock_range(bytes),
+            #[cfg(not(unix))]
+            Self::Static(_) => Ok(()),
+        }
+    }
+
+    /// Bytes of the mapping currently held in RAM, if the platform reports it.
+    ///
+    /// For file mappings this counts pages present in the OS page cache
+    /// (`mincore`), which the OS may reclaim under pressure. Memory buffers are
+    /// always fully resident.
+    pub(crate) fn resident_bytes(&self) -> std::io::Result<Option<usize>> {
+        match self {
+            #[cfg(unix)]
+            Self::File(mmap) => resident_bytes(mmap).map(Some),
+            #[cfg(unix)]
+            Self::Sealed(mmap) => resident_bytes(mmap).map(Some),
+            #[cfg(unix)]
+            Self::Private(mmap) => resident_bytes(mmap).map(Some),
+            #[cfg(all(feature = "encryption", unix))]
+            Self::Decrypted(_) => resident_bytes(self).map(Some),
+            #[cfg(all(not(unix), not(target_arch = "wasm32")))]
+            Self::File(_) | Self::Sealed(_) | Self::Private(_) => Ok(None),
+            #[cfg(all(feature = "encryption", not(unix), not(target_arch = "wasm32")))]
+            Self::Decrypted(_) => Ok(None),
+            #[cfg(feature = "wasm")]
+            Self::Memory(buffer) => Ok(Some(buffer.as_bytes().len())),
+            #[cfg(unix)]
+            Self::Static(bytes) => resident_bytes(bytes).map(Some),
+            #[cfg(not(unix))]
+            Self::Static(_) => Ok(None),
+        }
+    }
+}
+
+/// Size of the OS memory pages, which `mlock` and `mincore` work in
+pub(crate) fn os_page_size() -> usize {
+    // SAFETY: `sysconf` has no preconditions.
+    #[cfg(unix)]
+    let size = usize::try_from(unsafe { libc::sysconf(libc::_SC_PAGESIZE) }).ok();
+    #[cfg(not(unix))]
+    let size = None;
+    size.filter(|&size| size > 0).unwrap_or(4096)
+}
+
+/// Lock the pages spanned by `range`, a slice of a live mapping (`mlock`).
+#[cfg(unix)]
+fn lock_range(range: &[u8]) -> std::io::Result<()> {
+    if range.is_empty() {
+        return Ok(());
+    }
+
+    // SAFETY: `range` lies within a live mapping; `mlock` rounds it out to
+    // whole pages, which belong to the same mapping.
+    if unsafe { libc::mlock(range.as_ptr().cast(), range.len()) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Unlock the pages spanned by `range`, a slice of a live mapping (`munlock`).
+#[cfg(unix)]
+fn unlock_range(range: &[u8]) -> std::io::Result<()> {
+    if range.is_empty() {
+        return Ok(());
+    }
+
+    // SAFETY: As in `lock_range`.
+    if unsafe { libc::munlock(range.as_ptr().cast(), range.len()) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Bytes this process may lock in RAM (the soft `RLIMIT_MEMLOCK`), or `None`
+/// if unlimited or unknown
+// `rlim_t` is 32 bits wide on some targets
+#[allow(clippy::unnecessary_cast)]
+pub(crate) fn memlock_limit() -> Option<u64> {
+    #[cfg(unix)]
+    {
+        let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+        // SAFETY: `limit` is a valid, writable `rlimit`.
+        if unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut limit) } != 0
+            || limit.rlim_cur == libc::RLIM_INFINITY
+        {
+            return None;
+        }
+        Some(limit.rlim_cur as u64)
+    }
+    #[cfg(not(unix))]
+    None
+}
+
+/// Count the bytes of a page-aligned mapping that are resident (`mincore`).
+#[cfg(unix)]
+fn resident_bytes(mapping: &[u8]) -> std::io::Result<usize> {
+    if mapping.is_empty() {
+        return Ok(0);
+    }
+
+    let page_size = os_page_size();
+    let mut pages = vec![0u8; mapping.len().div_ceil(page_size)];
+
+    // SAFETY: `mapping` is a live mapping starting on a page boundary, and
+    // `pages` holds one status byte per page it spans.
+    let rc = unsafe {
+        libc::mincore(mapping.as_ptr().cast_mut().cast(), mapping.len(), pages.as_mut_ptr().cast())
+    };
+    if rc != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let resident = pages.iter().filter(|&&status| status & 1 != 0).count();
+    Ok((resident * page_size).min(mapping.len()))
+}
+
+impl Deref for Mapping {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::File(mmap) => mmap,
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Sealed(mmap) => mmap,
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Private(mmap) => mmap,
+            // SAFETY: The mapping is live for `&self`; `Storage` decrypts a
+            // block before it hands out references into it
+            #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+            Self::Decrypted(mmap) => unsafe {
+                std::slice::from_raw_parts(mmap.as_ptr(), mmap.len())
+            },
+            #[cfg(feature = "wasm")]
+            Self::Memory(buffer) => buffer.as_bytes(),
+            Self::Static(bytes) => bytes,
+        }
+    }
+}
+
+impl DerefMut for Mapping {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::File(mmap) => mmap,
+            // Sealed storage refuses every write before it reaches the mapping
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Sealed(_) => panic!("sealed index mapping is read-only"),
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Private(mmap) => mmap,
+            // SAFETY: As in `deref()`, with exclusive access for `&mut self`
+            #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+            Self::Decrypted(mmap) => unsafe {
+                std::slice::from_raw_parts_mut(mmap.as_mut_ptr(), mmap.len())
+            },
+            #[cfg(feature = "wasm")]
+            Self::Memory(buffer) => buffer.as_bytes_mut(),
+            Self::Static(_) => panic!("embedded index image is read-only"),
+        }
+    }
+}
+
+/// Growable heap buffer whose start is 4KB-aligned.
+///
+/// The header is cast in place to `&Header` (`align(4096)`) and vectors are
+/// read as `&[f32]`, so the buffer must offer the same alignment as an mmap.
+#[cfg(feature = "wasm")]
+pub(crate) struct PageBuffer {
+    pages: Vec<Page>,
+}
+
+#[cfg(feature = "wasm")]
+impl PageBuffer {
+    /// Create a zeroed buffer of at least `len` bytes (rounded up to whole pages).
+    pub(crate) fn zeroed(len: usize) -> Self {
+        Self { pages: vec![Page([0; PAGE_SIZE]); len.div_ceil(PAGE_SIZE)] }
+    }
+
+    /// Copy `bytes` into a new buffer, zero-padding the final page.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Self {
+        let mut buffer = Self::zeroed(bytes.len());
+        buffer.as_bytes_mut()[..bytes.len()].copy_from_slice(bytes);
+        buffer
+    }
+
+    /// Resize to `len` bytes (rounded up to whole pages); new pages are zeroed.
+    pub(crate) fn resize(&mut self, len: usize) {
+        self.pages.resize(len.div_ceil(PAGE_SIZE), Page([0; PAGE_SIZE]));
+    }
+
+    #[inline]
+    fn as_bytes(&self) -> &[u8] {
+        // SAFETY: `Page` is `repr(C)` around `[u8; PAGE_SIZE]` with no padding,
+        // so the page vector is a contiguous run of initialized bytes.
+        unsafe {
+            std::slice::from_raw_parts(
+                self.pages.as_ptr().cast::<u8>(),
+                self.pages.len() * PAGE_SIZE,
+            )
+        }
+    }
+
+    #[inline]
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        // SAFETY: See `as_bytes`; `&mut self` guarantees exclusive access.
+        unsafe {
+            std::slice::from_raw_parts_mut(
+                self.pages.as_mut_ptr().cast::<u8>(),
+                self.pages.len() * PAGE_SIZE,
+            )
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl std::fmt::Debug for PageBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PageBuffer").field("len", &(self.pages.len() * PAGE_SIZE)).finish()
+    }
+}
+
+#[cfg(all(test, feature = "wasm"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_buffer_is_page_aligned() {
+        let mut buffer = PageBuffer::zeroed(1);
+        assert_eq!(buffer.as_bytes().len(), PAGE_SIZE);
+        assert_eq!(buffer.as_bytes().as_ptr() as usize % PAGE_SIZE, 0);
+
+        buffer.as_bytes_mut()[10] = 7;
+        buffer.resize(3 * PAGE_SIZE);
+        assert_eq!(buffer.as_bytes().len(), 3 * PAGE_SIZE);
+        assert_eq!(buffer.as_bytes()[10], 7);
+        assert_eq!(buffer.as_bytes().as_ptr() as usize % PAGE_SIZE, 0);
+    }
+
+    #[test]
+    fn test_page_buffer_from_bytes_pads_final_page() {
+        let buffer = PageBuffer::from_bytes(&[1u8; 5000]);
+        assert_eq!(buffer.as_bytes().len(), 2 * PAGE_SIZE);
+        assert!(buffer.as_bytes()[..5000].iter().all(|&b| b == 1));
+        assert!(buffer.as_bytes()[5000..].iter().all(|&b| b == 0));
+    }
+}
@@ -0,0 +1,182 @@
+//! Merging several index files into one.
+//!
+//! Devices that build an index in shards, such as one file per day, can
+//! consolidate them with `VectorIndex::merge()`. The vectors of every shard
+//! are inserted into a new index, which assigns them fresh sequential IDs and
+//! builds its graph from scratch; links are never copied between files, since
+//! they are only meaningful within the graph they were chosen for.
+
+use crate::error::{ErrorKind, Tagged};
+use crate::{IndexOptions, Storage, VectorIndex};
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Result of `VectorIndex::merge()`
+#[derive(Debug)]
+pub struct MergedIndex {
+    /// The new, flushed index
+    pub index: VectorIndex,
+
+    /// Source position (in the `sources` slice) and source ID of each merged
+    /// vector, indexed by its new ID
+    pub origins: Vec<(usize, u64)>,
+}
+
+impl VectorIndex {
+    /// Merge the indexes at `sources` into a new index at `output`
+    ///
+    /// The sources must agree on dimensions, element type, distance and
+    /// normalization. Their vectors are inserted in order, source by source,
+    /// and get new sequential IDs; `MergedIndex::origins` maps each new ID
+    /// back to its source. Deleted vectors are left out. Keys, groups, tags
+    /// and expiration times are carried over. Sources that apply a rotation
+    /// (see `quantize_to()`) must apply the same one, which the merged index
+    /// keeps; rotations trained on `f32` sources are not carried over.
+    ///
+    /// The graph is rebuilt with the first source's graph parameters
+    /// (`max_connections`, `ef_construction`, `max_layers`). Settings that are
+    /// not stored in the files, such as `ef_search` and a custom distance, come
+    /// from `options`, which is also used to open the sources. The sources are
+    /// opened as shared readers: they are left untouched, and fail to open
+    /// while a writer holds them. The merged index is flushed before it is
+    /// returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - `sources` is empty, or two sources use the same key (`InvalidArgument`)
+    /// - The sources differ in dimensions (`DimensionMismatch`), element type,
+    ///   distance, normalization or applied rotation (`InvalidArgument`)
+    /// - A source cannot be opened, or `output` already holds vectors
+    /// - An insert or the final flush fails
+    ///
+    /// A failed merge can leave a partly filled index at `output`; remove it
+    /// before trying again.
+    pub fn merge<P: AsRef<Path>>(
+        sources: &[&Path],
+        output: P,
+        options: IndexOptions,
+    ) -> Result<MergedIndex> {
+        let Some((&first_path, _)) = sources.split_first() else {
+            anyhow::bail!(Tagged::new(ErrorKind::InvalidArgument, "No indexes to merge"));
+        };
+
+        let mut indexes = Vec::with_capacity(sources.len());
+        for &path in sources {
+            let index = Storage::open_shared_existing(path)
+                .and_then(|storage| {
+                    Self::check_input_dimensions(storage.dimensions(), &options)?;
+                    Self::from_storage(storage, options.clone())
+                })
+                .with_context(|| format!("Failed to open {} for merging", path.display()))?;
+            indexes.push(index);
+        }
+
+        let first = &indexes[0];
+        for (index, &path) in indexes.iter().zip(sources).skip(1) {
+            Self::check_mergeable(first, index, first_path, path)?;
+        }
+
+        let output = output.as_ref();
+        let merged_options =
+            IndexOptions { element_type: first.element_type(), ..first.options().clone() };
+        let mut merged = VectorIndex::open(output, first.dimensions(), merged_options)?;
+        if !merged.is_empty() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!("Merge output {} already holds vectors", output.display())
+            ));
+        }
+        #[cfg(feature = "linalg")]
+        if let Some(rotation) = first.applied_rotation() {
+            merged.put_rotation(rotation.clone())?;
+        }
+
+        let live: u64 = indexes.iter().map(|index| index.len() - index.deleted_count()).sum();
+        merged.reserve(live)?;
+
+        let mut origins = Vec::with_capacity(live as usize);
+        for (source, index) in indexes.iter().enumerate() {
+            for id in 0..index.len() {
+                if index.is_deleted(id)? {
+                    continue;
+                }
+
+                let key = index.key_for_id(id);
+                if let Some(key) = key
+                    && merged.keys.id(key).is_some()
+                {
+                    anyhow::bail!(Tagged::new(
+                        ErrorKind::InvalidArgument,
+                        format!(
+                            "Key {:?} is used in more than one source (again in {})",
+                            key,
+                            sources[source].display()
+                        )
+                    ));
+                }
+
+                // Stored vectors are already truncated and normalized
+                let vector = index.graph.storage.get_vector(id)?;
+                let new_id = merged.insert_stored(&vector)?;
+                if let Some(key) = key {
+                    merged.keys.insert(key, new_id);
+                }
+                merged.groups.set(new_id, index.group_of(id));
+                merged.tags.set(new_id, &index.tags_of(id));
+                merged.expiry.set(new_id, index.expiry_of(id));
+                origins.push((source, id));
+            }
+        }
+
+        merged.flush()?;
+        Ok(MergedIndex { index: merged, origins })
+    }
+
+    /// Check that `other` can be merged into an index built like `first`
+    fn check_mergeable(
+        first: &VectorIndex,
+        other: &VectorIndex,
+        first_path: &Path,
+        path: &Path,
+    ) -> Result<()> {
+        if other.dimensions() != first.dimensions() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::DimensionMismatch,
+                format!(
+                    "Cannot merge {} ({} dimensions) with {} ({} dimensions)",
+                    path.display(),
+                    other.dimensions(),
+                    first_path.display(),
+                    first.dimensions()
+                )
+            ));
+        }
+
+        let mismatch = if other.element_type() != first.element_type() {
+            Some("element type")
+        } else if other.distance().name() != first.distance().name() {
+            Some("distance")
+        } else if other.options().normalize != first.options().normalize {
+            Some("normalization")
+        } else {
+            None
+        };
+        #[cfg(feature = "linalg")]
+        let mismatch = mismatch.or_else(|| {
+            (other.applied_rotation() != first.applied_rotation()).then_some("applied rotation")
+        });
+        if let Some(property) = mismatch {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "Cannot merge {} with {}: the indexes differ in {}",
+                    path.display(),
+                    first_path.display(),
+                    property
+                )
+            ));
+        }
+        Ok(())
+    }
+}
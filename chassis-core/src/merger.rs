@@ -0,0 +1,288 @@
+//! Background merging of a tiered index's recent tier into its graph.
+//!
+//! `TieredIndex` keeps new vectors in an exact in-memory tier, but leaves
+//! moving them into the graph to the caller's `migrate()` calls. For heavy
+//! ingest, `TieredIndex::merge_in_background()` hands the index to a merger
+//! thread instead, much like an LSM tree flushes its memtable: `add()` only
+//! appends to the recent tier, and every `interval` the merger moves whatever
+//! has accumulated into the graph.
+//!
+//! # Synchronization
+//!
+//! The index sits behind a reader-writer lock. The merger holds it
+//! exclusively for one graph insert at a time, so an `add()` or search waits
+//! for at most one insert, not for the whole backlog. A vector leaves the
+//! recent tier in the same critical section that inserts it into the graph,
+//! so searches never see it twice or miss it.
+
+use crate::error::{ErrorKind, Tagged};
+use crate::{SearchOptions, SearchResult, TieredIndex};
+use anyhow::Result;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, RwLock, RwLockWriteGuard};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// A `TieredIndex` whose recent tier is merged into the graph by a background thread
+///
+/// Methods take `&self`, so the index can be shared between threads, for
+/// example in an `Arc`. Dropping it stops the merger; vectors still in the
+/// recent tier are lost unless `flush()` or `into_inner()` ran first.
+#[derive(Debug)]
+pub struct MergingTieredIndex {
+    shared: Arc<Shared>,
+    worker: Option<JoinHandle<()>>,
+}
+
+/// State shared between a `MergingTieredIndex` and its merger thread
+#[derive(Debug)]
+struct Shared {
+    index: RwLock<TieredIndex>,
+
+    /// Set once the merger should exit; the merger sleeps on `wake`
+    closed: Mutex<bool>,
+    wake: Condvar,
+
+    /// Why the merger stopped, until a call reports it
+    error: Mutex<Option<anyhow::Error>>,
+}
+
+impl TieredIndex {
+    /// Move the index behind a merger thread that empties the recent tier every `interval`
+    ///
+    /// The merger migrates the oldest vectors first, so IDs returned by
+    /// `add()` stay valid. If ingest outpaces it, the `max_recent` backpressure
+    /// of `add()` still applies.
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use chassis_core::{IndexOptions, TieredIndex, TieredOptions, VectorIndex};
+    /// use std::time::Duration;
+    ///
+    /// let index = VectorIndex::open("embeddings.chassis", 4, IndexOptions::default())?;
+    /// let tiered = TieredIndex::new(index, TieredOptions::default())?;
+    /// let merging = tiered.merge_in_background(Duration::from_millis(100))?;
+    ///
+    /// let id = merging.add(&[1.0; 4])?; // no graph insert on this thread
+    /// assert_eq!(merging.search(&[1.0; 4], 1)?[0].id, id);
+    /// merging.flush()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the thread cannot be spawned
+    pub fn merge_in_background(self, interval: Duration) -> Result<MergingTieredIndex> {
+        let shared = Arc::new(Shared {
+            index: RwLock::new(self),
+            closed: Mutex::new(false),
+            wake: Condvar::new(),
+            error: Mutex::new(None),
+        });
+        let worker = std::thread::Builder::new()
+            .name("chassis-merger".into())
+            .spawn({
+                let shared = Arc::clone(&shared);
+                move || shared.run(interval)
+            })
+            .map_err(|e| Tagged::new(ErrorKind::Io, format!("Failed to spawn merger: {}", e)))?;
+
+        Ok(MergingTieredIndex { shared, worker: Some(worker) })
+    }
+}
+
+impl MergingTieredIndex {
+    /// Add a vector to the recent tier; see `TieredIndex::add()`
+    ///
+    /// # Errors
+    ///
+    /// Returns the error a background merge failed with, once, instead of
+    /// adding the vector; otherwise the errors of `TieredIndex::add()`.
+    pub fn add(&self, vector: &[f32]) -> Result<u64> {
+        self.shared.check()?;
+        self.shared.write().add(vector)
+    }
+
+    /// Search both tiers; see `TieredIndex::search()`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions
+    pub fn search(&self, query: &[f32], k: usize) -> Result<Vec<SearchResult>> {
+        self.search_with_options(query, k, &SearchOptions::default())
+    }
+
+    /// Search both tiers with per-search options; see `TieredIndex::search_with_options()`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions
+    pub fn search_with_options(
+        &self,
+        query: &[f32],
+        k: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchResult>> {
+        let index = self.shared.index.read().unwrap_or_else(PoisonError::into_inner);
+        index.search_with_options(query, k, options)
+    }
+
+    /// Migrate the whole recent tier on this thread and flush the graph to disk
+    ///
+    /// Searches and adds wait until the flush completes.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error a background merge failed with, once, or an error
+    /// if migration or the flush fails
+    pub fn flush(&self) -> Result<()> {
+        self.shared.check()?;
+        self.shared.write().flush()
+    }
+
+    /// Get the number of vectors waiting in the recent tier
+    pub fn recent_len(&self) -> usize {
+        self.shared.index.read().unwrap_or_else(PoisonError::into_inner).recent_len()
+    }
+
+    /// Get the total number of vectors across both tiers
+    pub fn len(&self) -> u64 {
+        self.shared.index.read().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    /// Check if both tiers are empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Stop the merger and return the index, recent tier included
+    ///
+    /// A merge in progress finishes first. A merge error that was not
+    /// reported yet is dropped; the vector it failed on is still in the
+    /// recent tier.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the merger thread panicked
+    pub fn into_inner(mut self) -> Result<TieredIndex> {
+        self.stop()?;
+        let shared = Arc::clone(&self.shared);
+        drop(self);
+        let shared = Arc::try_unwrap(shared).expect("the merger thread has exited");
+        Ok(shared.index.into_inner().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Ask the merger to exit and wait for it
+    fn stop(&mut self) -> Result<()> {
+        let Some(worker) = self.worker.take() else {
+            return Ok(());
+        };
+        *self.shared.lock_closed() = true;
+        self.shared.wake.notify_one();
+        worker.join().map_err(|_| Tagged::new(ErrorKind::Other, "Merger thread panicked").into())
+    }
+}
+
+impl Drop for MergingTieredIndex {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+impl Shared {
+    /// Lock for writing, ignoring poisoning like `ReadHandle` and `WriteHandle`
+    fn write(&self) -> RwLockWriteGuard<'_, TieredIndex> {
+        self.index.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn lock_closed(&self) -> MutexGuard<'_, bool> {
+        self.closed.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Report the error the merger stopped with, if it was not reported yet
+    fn check(&self) -> Result<()> {
+        match self.error.lock().unwrap_or_else(PoisonError::into_inner).take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Merger thread body: sleep for `interval`, then empty the recent tier
+    fn run(&self, interval: Duration) {
+        loop {
+            let closed = self.lock_closed();
+            let (closed, _) = self
+                .wake
+                .wait_timeout_while(closed, interval, |closed| !*closed)
+                .unwrap_or_else(PoisonError::into_inner);
+            if *closed {
+                return;
+            }
+            drop(closed);
+
+            // One insert per lock hold, so adds and searches interleave
+            while !*self.lock_closed() {
+                match self.write().migrate_up_to(1) {
+                    Ok(0) => break,
+                    Ok(_) => {}
+                    Err(e) => {
+                        *self.error.lock().unwrap_or_else(PoisonError::into_inner) = Some(e);
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IndexOptions, TieredOptions, VectorIndex};
+    use std::time::Instant;
+    use tempfile::NamedTempFile;
+
+    fn open_tiered(temp_file: &NamedTempFile) -> TieredIndex {
+        let main = VectorIndex::open(temp_file.path(), 8, IndexOptions::default()).unwrap();
+        TieredIndex::new(main, TieredOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_merger_moves_recent_tier_into_graph() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let merging =
+            open_tiered(&temp_file).merge_in_background(Duration::from_millis(1)).unwrap();
+        for i in 0..200 {
+            assert_eq!(merging.add(&[i as f32; 8]).unwrap(), i);
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while merging.recent_len() > 0 {
+            assert!(Instant::now() < deadline, "merger did not empty the recent tier");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        for i in [0, 77, 199] {
+            assert_eq!(merging.search(&[i as f32; 8], 1).unwrap()[0].id, i);
+        }
+
+        let tiered = merging.into_inner().unwrap();
+        assert_eq!(tiered.main().len(), 200);
+    }
+
+    #[test]
+    fn test_into_inner_keeps_unmerged_vectors() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let merging =
+            open_tiered(&temp_file).merge_in_background(Duration::from_secs(3600)).unwrap();
+        for i in 0..10 {
+            merging.add(&[i as f32; 8]).unwrap();
+        }
+        let err = merging.add(&[0.0; 3]).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::DimensionMismatch);
+
+        // Stopping does not wait for the interval to elapse
+        let tiered = merging.into_inner().unwrap();
+        assert_eq!(tiered.recent_len(), 10);
+        assert_eq!(tiered.main().len(), 0);
+    }
+}
@@ -0,0 +1,473 @@
+//! Tagged metadata sections stored in a relocatable zone at the end of the file.
+//!
+//! # Layout
+//!
+//! ```text
+//! Offset  Size  Field
+//! ------  ----  -----
+//! 0       8     magic: b"CHMETA\0\0"
+//! 8       4     section_count: u32
+//! 12      4     checksum: u32
+//! 16      ...   sections
+//! ```
+//!
+//! Each section is `[tag: 8 bytes][len: u64][payload padded to 8 bytes]`.
+//! The zone is rewritten in full whenever a section changes, so it is meant for
+//! small-to-medium blobs that change rarely (trained models, settings).
+//!
+//! Maps that change on every write (keys, groups, tags, deadlines, the free
+//! list) append their changes after the sections instead, as journal records
+//! `[len: u32][crc: u32][tag: 8 bytes][payload padded to 8 bytes]`, where
+//! `len` is the payload length and `crc` the CRC-32 of tag and payload. A
+//! reader applies a section's records to it in order and stops at the first
+//! record that fails its checksum (torn by a crash). A map is written as a
+//! full section again, dropping its records, once they outgrow the section
+//! (see `Changes`).
+//!
+//! The checksum is the CRC-32 of the zone up to the end of its last section,
+//! with the checksum field itself read as zero; `0` means the zone was written
+//! before zones were checksummed. A new zone is written next to the one the
+//! last commit left, never over it, and the header keeps pointing at that one
+//! as a fallback, so a zone torn by a crash fails its checksum and the
+//! previous version is read instead.
+
+use crate::error::{ErrorKind, Tagged};
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+
+/// Magic bytes identifying the metadata zone.
+const METADATA_MAGIC: &[u8; 8] = b"CHMETA\0\0";
+
+/// Size of the metadata zone header in bytes.
+const ZONE_HEADER_SIZE: usize = 16;
+
+/// Size of a section header (`tag` + `len`) in bytes.
+const SECTION_HEADER_SIZE: usize = 16;
+
+/// Position of the checksum in the zone header.
+const CHECKSUM_RANGE: std::ops::Range<usize> = 12..16;
+
+/// Size of a journal record header (`len` + `crc` + `tag`) in bytes.
+const RECORD_HEADER_SIZE: usize = 16;
+
+/// Journal bytes a map may accumulate before it is rewritten, however small
+/// its section.
+const MIN_JOURNAL_LEN: usize = 4096;
+
+/// Tag identifying a metadata section.
+pub(crate) type SectionTag = [u8; 8];
+
+/// Sections or journal records of a zone, as tags and payloads.
+pub(crate) type Entries<'a> = Vec<(SectionTag, &'a [u8])>;
+
+/// An in-memory map persisted as a section plus journal records of changes
+pub(crate) trait JournaledMap: Default {
+    /// Tag of the section and of its journal records
+    const TAG: &'static SectionTag;
+
+    /// Changes since the map was last written
+    fn changes(&self) -> &Changes;
+
+    /// Changes since the map was last written, for updating
+    fn changes_mut(&mut self) -> &mut Changes;
+
+    /// Serialize the whole map to its section format.
+    fn to_bytes(&self) -> Vec<u8>;
+
+    /// Deserialize from the section format.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Corrupted` if the payload is malformed.
+    fn from_bytes(bytes: &[u8]) -> Result<Self>;
+
+    /// Serialize the entries at `ids` (and the current length) as a journal
+    /// record payload.
+    fn delta_bytes(&self, ids: &BTreeSet<u64>) -> Vec<u8>;
+
+    /// Apply a payload written by `delta_bytes()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Corrupted` if the payload is malformed.
+    fn apply_delta(&mut self, bytes: &[u8]) -> Result<()>;
+}
+
+/// What changed in a `JournaledMap` since it was last written to the file
+///
+/// Changes to known IDs go to the journal; a map is written as a full section
+/// when a change cannot be listed by ID or when its journal records would
+/// outgrow its last section, so every byte of change is rewritten a bounded
+/// number of times.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Changes {
+    /// IDs (or positions) whose entries changed
+    ids: BTreeSet<u64>,
+
+    /// The whole map changed
+    all: bool,
+
+    /// Bytes of the section as last written or read
+    section_len: usize,
+
+    /// Bytes of journal records written after it
+    journal_len: usize,
+}
+
+impl Changes {
+    /// No changes, for a map read from a `section_len`-byte section followed
+    /// by `journal_len` bytes of records
+    pub(crate) fn loaded(section_len: usize, journal_len: usize) -> Self {
+        Self { section_len, journal_len, ..Self::default() }
+    }
+
+    /// Record a change to the entry at `id`
+    pub(crate) fn touch(&mut self, id: u64) {
+        if !self.all {
+            self.ids.insert(id);
+        }
+    }
+
+    /// Record changes to the entries at `ids`
+    pub(crate) fn touch_range(&mut self, ids: std::ops::Range<u64>) {
+        if !self.all {
+            self.ids.extend(ids);
+        }
+    }
+
+    /// Record a change that only a full section can hold
+    pub(crate) fn touch_all(&mut self) {
+        self.all = true;
+        self.ids.clear();
+    }
+
+    /// Returns `true` if nothing changed
+    pub(crate) fn is_empty(&self) -> bool {
+        !self.all && self.ids.is_empty()
+    }
+
+    /// Changed IDs, or `None` if the map needs a full section
+    pub(crate) fn ids(&self) -> Option<&BTreeSet<u64>> {
+        (!self.all).then_some(&self.ids)
+    }
+
+    /// Returns `true` if a `record_len`-byte record should be a full section instead
+    pub(crate) fn wants_section(&self, record_len: usize) -> bool {
+        self.journal_len + record_len > self.section_len.max(MIN_JOURNAL_LEN)
+    }
+
+    /// Record that the changes were appended as a `record_len`-byte record
+    pub(crate) fn appended(&mut self, record_len: usize) {
+        self.journal_len += record_len;
+        self.ids.clear();
+    }
+
+    /// Record that the map was written as a `section_len`-byte section
+    pub(crate) fn rewritten(&mut self, section_len: usize) {
+        *self = Self::loaded(section_len, 0);
+    }
+}
+
+/// Returns the payload of `tag` inside a serialized metadata zone, if present.
+///
+/// # Errors
+///
+/// Returns an error if the zone is truncated or has an invalid magic.
+pub(crate) fn find_section<'a>(zone: &'a [u8], tag: &SectionTag) -> Result<Option<&'a [u8]>> {
+    for (section_tag, payload) in parse_sections(zone)? {
+        if section_tag == *tag {
+            return Ok(Some(payload));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Parses all sections of a serialized metadata zone.
+///
+/// # Errors
+///
+/// Returns an error if the zone is truncated, has an invalid magic, or
+/// fails its checksum.
+pub(crate) fn parse_sections(zone: &[u8]) -> Result<Vec<(SectionTag, &[u8])>> {
+    parse_zone(zone).map(|(sections, _)| sections)
+}
+
+/// Parses all sections of a serialized metadata zone and returns them with
+/// the offset where the journal starts.
+fn parse_zone(zone: &[u8]) -> Result<(Entries<'_>, usize)> {
+    if zone.len() < ZONE_HEADER_SIZE || &zone[..8] != METADATA_MAGIC {
+        anyhow::bail!(Tagged::new(ErrorKind::Corrupted, "Invalid metadata zone header"));
+    }
+
+    let count = u32::from_le_bytes(zone[8..12].try_into()?) as usize;
+    let mut sections = Vec::with_capacity(count);
+    let mut offset = ZONE_HEADER_SIZE;
+
+    for _ in 0..count {
+        let payload_start =
+            offset.checked_add(SECTION_HEADER_SIZE).context("Metadata section offset overflow")?;
+        if payload_start > zone.len() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                "Metadata section header extends beyond zone"
+            ));
+        }
+
+        let mut tag = [0u8; 8];
+        tag.copy_from_slice(&zone[offset..offset + 8]);
+        let len = usize::try_from(u64::from_le_bytes(zone[offset + 8..payload_start].try_into()?))
+            .context("Metadata section too large for this platform")?;
+
+        let payload_end =
+            payload_start.checked_add(len).context("Metadata section length overflow")?;
+        if payload_end > zone.len() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                "Metadata section payload extends beyond zone"
+            ));
+        }
+
+        sections.push((tag, &zone[payload_start..payload_end]));
+        offset = payload_start + padded_len(len);
+    }
+
+    let stored = u32::from_le_bytes(zone[CHECKSUM_RANGE].try_into()?);
+    if stored != 0 && zone.get(..offset).map(checksum) != Some(stored) {
+        anyhow::bail!(Tagged::new(ErrorKind::Corrupted, "Metadata zone checksum mismatch"));
+    }
+
+    Ok((sections, offset))
+}
+
+/// Parses the journal records of a serialized metadata zone, in the order
+/// they were appended, and returns them with the offset where the valid
+/// journal ends.
+///
+/// Records from the first one that is truncated or fails its checksum on are
+/// left out.
+///
+/// # Errors
+///
+/// Returns an error if the sections in front of the journal do not parse
+/// (see `parse_sections()`).
+pub(crate) fn parse_journal(zone: &[u8]) -> Result<(Entries<'_>, usize)> {
+    let (_, mut offset) = parse_zone(zone)?;
+    let mut records = Vec::new();
+
+    while let Some(header) = zone.get(offset..offset + RECORD_HEADER_SIZE) {
+        let len = u32::from_le_bytes(header[..4].try_into()?) as usize;
+        let crc = u32::from_le_bytes(header[4..8].try_into()?);
+        let end = offset + RECORD_HEADER_SIZE + len;
+        let Some(body) = zone.get(offset + 8..end) else {
+            break;
+        };
+        if crc32fast::hash(body) != crc {
+            break;
+        }
+
+        let mut tag = [0u8; 8];
+        tag.copy_from_slice(&body[..8]);
+        records.push((tag, &body[8..]));
+        offset += record_len(len);
+    }
+
+    Ok((records, offset.min(zone.len())))
+}
+
+/// Serializes a journal record carrying `payload` for section `tag`.
+pub(crate) fn serialize_record(tag: &SectionTag, payload: &[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(8 + payload.len());
+    body.extend_from_slice(tag);
+    body.extend_from_slice(payload);
+
+    let mut bytes = Vec::with_capacity(record_len(payload.len()));
+    bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&crc32fast::hash(&body).to_le_bytes());
+    bytes.extend_from_slice(&body);
+    bytes.resize(record_len(payload.len()), 0);
+    bytes
+}
+
+/// Size of a journal record with a `len`-byte payload, padding included
+#[inline]
+pub(crate) const fn record_len(len: usize) -> usize {
+    RECORD_HEADER_SIZE + padded_len(len)
+}
+
+/// Serializes the entries at `ids` of a `u64` array whose tail past its
+/// current length was dropped: `[len: u64][count: u64]` and `count` pairs
+/// `[index: u64][value: u64]`
+pub(crate) fn array_delta(values: &[u64], ids: &BTreeSet<u64>) -> Vec<u8> {
+    let changed: Vec<_> = ids.range(..values.len() as u64).collect();
+    let mut bytes = Vec::with_capacity(16 * (changed.len() + 1));
+    bytes.extend_from_slice(&(values.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&(changed.len() as u64).to_le_bytes());
+    for &id in changed {
+        bytes.extend_from_slice(&id.to_le_bytes());
+        bytes.extend_from_slice(&values[id as usize].to_le_bytes());
+    }
+    bytes
+}
+
+/// Applies a payload written by `array_delta()`, filling new positions with `fill`.
+///
+/// # Errors
+///
+/// Returns `Corrupted` if the payload is malformed or sets an index past its length.
+pub(crate) fn apply_array_delta(
+    values: &mut Vec<u64>,
+    fill: u64,
+    bytes: &[u8],
+    what: &str,
+) -> Result<()> {
+    let corrupted =
+        || Tagged::new(ErrorKind::Corrupted, format!("{} journal record is malformed", what));
+
+    let (chunks, rest) = bytes.as_chunks::<8>();
+    let [len, count, pairs @ ..] = chunks else {
+        anyhow::bail!(corrupted());
+    };
+    let len = usize::try_from(u64::from_le_bytes(*len)).map_err(|_| corrupted())?;
+    let count = u64::from_le_bytes(*count);
+    if !rest.is_empty() || count.checked_mul(2) != Some(pairs.len() as u64) {
+        anyhow::bail!(corrupted());
+    }
+
+    values.resize(len, fill);
+    for pair in pairs.as_chunks::<2>().0 {
+        let index = u64::from_le_bytes(pair[0]);
+        let slot = usize::try_from(index).ok().and_then(|index| values.get_mut(index));
+        *slot.ok_or_else(corrupted)? = u64::from_le_bytes(pair[1]);
+    }
+    Ok(())
+}
+
+/// Serializes sections into a metadata zone, followed by journal `records`.
+pub(crate) fn serialize_zone(
+    sections: &[(SectionTag, &[u8])],
+    records: &[(SectionTag, &[u8])],
+) -> Vec<u8> {
+    let mut bytes = serialize_sections(sections);
+    for (tag, payload) in records {
+        bytes.extend_from_slice(&serialize_record(tag, payload));
+    }
+    bytes
+}
+
+/// Serializes sections into a metadata zone.
+pub(crate) fn serialize_sections(sections: &[(SectionTag, &[u8])]) -> Vec<u8> {
+    let total = ZONE_HEADER_SIZE
+        + sections
+            .iter()
+            .map(|(_, payload)| SECTION_HEADER_SIZE + padded_len(payload.len()))
+            .sum::<usize>();
+
+    let mut bytes = Vec::with_capacity(total);
+    bytes.extend_from_slice(METADATA_MAGIC);
+    bytes.extend_from_slice(&(sections.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+
+    for (tag, payload) in sections {
+        bytes.extend_from_slice(tag);
+        bytes.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(payload);
+        bytes.resize(bytes.len() + padded_len(payload.len()) - payload.len(), 0);
+    }
+
+    let checksum = checksum(&bytes);
+    bytes[CHECKSUM_RANGE].copy_from_slice(&checksum.to_le_bytes());
+    bytes
+}
+
+/// CRC-32 of a serialized zone, reading its checksum field as zero
+fn checksum(zone: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&zone[..CHECKSUM_RANGE.start]);
+    hasher.update(&[0; 4]);
+    hasher.update(&zone[CHECKSUM_RANGE.end..]);
+    hasher.finalize()
+}
+
+/// Rounds a payload length up to the next 8-byte boundary.
+#[inline]
+const fn padded_len(len: usize) -> usize {
+    (len + 7) & !7
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sections_roundtrip() {
+        let bytes = serialize_sections(&[(*b"FIRST\0\0\0", b"abc"), (*b"SECOND\0\0", &[7u8; 16])]);
+        assert_eq!(bytes.len() % 8, 0);
+
+        let sections = parse_sections(&bytes).unwrap();
+        assert_eq!(sections.len(), 2);
+        assert_eq!(find_section(&bytes, b"FIRST\0\0\0").unwrap(), Some(&b"abc"[..]));
+        assert_eq!(find_section(&bytes, b"SECOND\0\0").unwrap(), Some(&[7u8; 16][..]));
+        assert_eq!(find_section(&bytes, b"MISSING\0").unwrap(), None);
+    }
+
+    #[test]
+    fn test_truncated_zone_is_rejected() {
+        let bytes = serialize_sections(&[(*b"FIRST\0\0\0", &[1u8; 32])]);
+        assert!(parse_sections(&bytes[..bytes.len() - 8]).is_err());
+        assert!(parse_sections(&[0u8; 4]).is_err());
+    }
+
+    #[test]
+    fn test_journal_stops_at_torn_record() {
+        let first = serialize_record(b"FIRST\0\0\0", b"one");
+        let mut zone = serialize_zone(&[(*b"FIRST\0\0\0", b"base")], &[(*b"SECOND\0\0", b"x")]);
+        let valid = zone.len() + first.len();
+        zone.extend_from_slice(&first);
+        zone.extend_from_slice(&serialize_record(b"FIRST\0\0\0", b"two"));
+        let torn = zone.len() - 8;
+        zone[torn] ^= 1;
+
+        let (records, end) = parse_journal(&zone).unwrap();
+        assert_eq!(records, [(*b"SECOND\0\0", &b"x"[..]), (*b"FIRST\0\0\0", &b"one"[..])]);
+        assert_eq!(end, valid);
+        assert_eq!(find_section(&zone, b"FIRST\0\0\0").unwrap(), Some(&b"base"[..]));
+
+        // A record cut short by the end of the zone is dropped as well
+        let (records, end) = parse_journal(&zone[..valid - 8]).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(end, valid - first.len());
+    }
+
+    #[test]
+    fn test_array_delta_roundtrip() {
+        let mut values = vec![1, 2, 3, 4];
+        let delta = array_delta(&[1, 9, 3], &BTreeSet::from([1, 5]));
+
+        apply_array_delta(&mut values, u64::MAX, &delta, "Test").unwrap();
+        assert_eq!(values, [1, 9, 3]);
+        assert!(apply_array_delta(&mut values, 0, &delta[..delta.len() - 8], "Test").is_err());
+
+        let mut changes = Changes::default();
+        changes.touch_range(0..3);
+        assert_eq!(changes.ids().unwrap().len(), 3);
+        changes.touch_all();
+        assert!(changes.ids().is_none());
+        changes.rewritten(64);
+        assert!(changes.is_empty());
+        assert!(!changes.wants_section(MIN_JOURNAL_LEN));
+        assert!(changes.wants_section(MIN_JOURNAL_LEN + 8));
+    }
+
+    #[test]
+    fn test_damaged_zone_fails_its_checksum() {
+        let mut bytes = serialize_sections(&[(*b"FIRST\0\0\0", &[1u8; 32])]);
+        bytes[ZONE_HEADER_SIZE + SECTION_HEADER_SIZE + 3] ^= 1;
+        let err = parse_sections(&bytes).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Corrupted);
+
+        // Zones written before checksums are taken as they are
+        bytes[CHECKSUM_RANGE].fill(0);
+        assert_eq!(find_section(&bytes, b"FIRST\0\0\0").unwrap().unwrap()[3], 0);
+    }
+}
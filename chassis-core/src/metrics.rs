@@ -0,0 +1,107 @@
+//! Cumulative operation counters for application debug endpoints.
+//!
+//! `VectorIndex::metrics()` snapshots counters kept since the index was
+//! opened: searches, adds, flushes, distance computations and file remaps.
+//! `IndexMetrics::to_prometheus()` renders them in the Prometheus text
+//! exposition format, so an app can serve them from its own `/metrics`
+//! handler without a metrics dependency in Chassis. For per-operation events,
+//! attach `Instrumentation` instead.
+
+use crate::VectorIndex;
+use std::fmt::Write;
+
+/// Counters of an index since it was opened, from `VectorIndex::metrics()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexMetrics {
+    /// Vectors in the index, including unflushed ones
+    pub vectors: u64,
+
+    /// k-NN searches
+    pub searches: u64,
+
+    /// Radius searches (`search_within`)
+    pub range_searches: u64,
+
+    /// Vectors added
+    pub adds: u64,
+
+    /// Completed `flush()` and started `flush_async()` calls
+    pub flushes: u64,
+
+    /// Distances computed between queries and stored vectors by searches
+    pub distance_computations: u64,
+
+    /// Times the file was grown and mapped again
+    pub remaps: u64,
+
+    /// Major page faults of the whole process since it started, an estimate
+    /// of page-cache misses; `None` off Linux and Android
+    ///
+    /// Read from `/proc/self/stat`, so faults outside the index are counted
+    /// too. The difference between two snapshots taken around a burst of
+    /// searches approximates the index pages read from disk.
+    pub major_page_faults: Option<u64>,
+}
+
+impl IndexMetrics {
+    /// Render the counters in the Prometheus text exposition format
+    ///
+    /// Metric names start with `chassis_`; counters end in `_total` and the
+    /// vector count is a gauge. `major_page_faults` is left out when unknown.
+    pub fn to_prometheus(&self) -> String {
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: u64| {
+            let _ = writeln!(text, "# HELP chassis_{} {}", name, help);
+            let _ = writeln!(text, "# TYPE chassis_{} {}", name, kind);
+            let _ = writeln!(text, "chassis_{} {}", name, value);
+        };
+
+        metric("vectors", "gauge", "Vectors in the index.", self.vectors);
+        metric("searches_total", "counter", "k-NN searches.", self.searches);
+        metric("range_searches_total", "counter", "Radius searches.", self.range_searches);
+        metric("adds_total", "counter", "Vectors added.", self.adds);
+        metric("flushes_total", "counter", "Flushes.", self.flushes);
+        metric(
+            "distance_computations_total",
+            "counter",
+            "Distances computed by searches.",
+            self.distance_computations,
+        );
+        metric("remaps_total", "counter", "File growths that remapped the index.", self.remaps);
+        if let Some(faults) = self.major_page_faults {
+            metric(
+                "process_major_page_faults_total",
+                "counter",
+                "Major page faults of the process.",
+                faults,
+            );
+        }
+        text
+    }
+}
+
+impl VectorIndex {
+    /// Snapshot the operation counters kept since the index was opened
+    ///
+    /// Counting is always on and costs a few relaxed atomic increments per
+    /// operation. Evaluation searches (`eval`) are not counted.
+    pub fn metrics(&self) -> IndexMetrics {
+        self.stats.metrics(self)
+    }
+}
+
+/// Major page faults of this process (`majflt` in `/proc/self/stat`)
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn major_page_faults() -> Option<u64> {
+    let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+    // The command name in parentheses may contain spaces; `majflt` is the
+    // tenth field after it
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(9)?.parse().ok()
+}
+
+/// Major page faults of this process; not reported on this platform
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn major_page_faults() -> Option<u64> {
+    None
+}
@@ -0,0 +1,100 @@
+//! In-place upgrades of index files written by older releases.
+//!
+//! The format version in the header only says which library can read a
+//! file (see `Header::is_compatible()`); most layout changes are additive,
+//! and older files open as they are. The few that are not are listed in
+//! `MIGRATIONS`, oldest first. Opening an index for writing detects the
+//! steps a file needs and runs them before the graph is loaded; like any
+//! other change they reach the disk with the next flush. Shared readers and
+//! sealed opens cannot write, so they refuse such files with
+//! `IncompatibleVersion` until `migrate_file()` (or a writable open and a
+//! flush) has upgraded them.
+//!
+//! A new step goes at the end of `MIGRATIONS`. It must recognize files that
+//! need it from their contents alone, leave them in a layout the current
+//! library reads, and be safe to run again if a crash drops the upgrade
+//! before it was flushed.
+
+use crate::Storage;
+use crate::error::{ErrorKind, Tagged};
+use crate::hnsw::HnswGraph;
+use anyhow::Result;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+/// One upgrade step of the file layout
+pub(crate) struct Migration {
+    /// Short name, reported in `OpenReport::migrations`
+    pub(crate) name: &'static str,
+
+    /// Returns `true` if the file in `storage` needs this step
+    needed: fn(&Storage) -> bool,
+
+    /// Upgrades the file in `storage` in place
+    run: fn(&mut Storage) -> Result<()>,
+}
+
+/// Every upgrade step, oldest first
+pub(crate) const MIGRATIONS: &[Migration] = &[Migration {
+    // Before 0.6.0 the graph zone started at a fixed 1 GiB offset, leaving
+    // a sparse gap after the vectors, and its offset was not recorded
+    name: "legacy-graph-zone",
+    needed: |storage| {
+        storage.graph_offset().is_none() && HnswGraph::stored_header(storage).is_some()
+    },
+    run: HnswGraph::compact_legacy_graph_zone,
+}];
+
+/// Names of the steps the file in `storage` needs, in the order they run
+pub(crate) fn pending(storage: &Storage) -> Vec<&'static str> {
+    MIGRATIONS.iter().filter(|step| (step.needed)(storage)).map(|step| step.name).collect()
+}
+
+/// Runs every step the file in `storage` needs, returning their names
+///
+/// # Errors
+///
+/// Returns `IncompatibleVersion` if the file needs upgrading but `storage`
+/// is a shared reader or sealed, and any error of a failed step.
+pub(crate) fn run(storage: &mut Storage) -> Result<Vec<&'static str>> {
+    let pending = pending(storage);
+    if !pending.is_empty() && (storage.is_shared_reader() || storage.is_sealed()) {
+        anyhow::bail!(Tagged::new(
+            ErrorKind::IncompatibleVersion,
+            format!(
+                "Index file was written by an older Chassis and needs upgrading ({}); open it for writing once or call migrate_file()",
+                pending.join(", ")
+            )
+        ));
+    }
+
+    for step in MIGRATIONS {
+        if (step.needed)(storage) {
+            (step.run)(storage)?;
+        }
+    }
+    Ok(pending)
+}
+
+/// Upgrades the index file at `path` to the current layout and syncs it
+///
+/// Opening an index for writing performs the same upgrades, so this is only
+/// needed to prepare files for `open_shared()` or `open_sealed()`, which
+/// refuse files that still need them. Returns the names of the steps that
+/// ran, empty if the file was already current.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The file cannot be opened, or is locked by another process
+/// - The file is not a Chassis index, or is in a newer format (`IncompatibleVersion`)
+/// - An upgrade step or the final sync fails
+#[cfg(not(target_arch = "wasm32"))]
+pub fn migrate_file<P: AsRef<Path>>(path: P) -> Result<Vec<&'static str>> {
+    let mut storage = Storage::open_existing(path)?;
+    let applied = run(&mut storage)?;
+    if !applied.is_empty() {
+        storage.commit()?;
+    }
+    Ok(applied)
+}
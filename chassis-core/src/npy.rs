@@ -0,0 +1,551 @@
+//! Bulk import from NumPy `.npy` and `.npz` files.
+//!
+//! Embeddings computed in Python usually reach disk through `np.save()` or
+//! `np.savez()`. `VectorIndex::import_npy()` maps such a file read-only and
+//! inserts its rows in order, so the array is never loaded into memory as a
+//! whole.
+//!
+//! Supported arrays are two-dimensional, C-ordered, and hold `float16`,
+//! `float32` or `float64` values of either byte order. `.npz` archives must be
+//! written uncompressed (`np.savez()`, not `np.savez_compressed()`), which is
+//! what lets their members be mapped in place.
+
+use crate::element::f16_to_f32;
+use crate::error::{ErrorKind, Tagged};
+use crate::{ProgressStage, VectorIndex};
+use anyhow::{Context, Result};
+use memmap2::Mmap;
+use std::fs::File;
+use std::ops::Range;
+use std::path::Path;
+
+/// Magic string at the start of every `.npy` file
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Signature of a zip local file header (the start of every `.npz` file)
+const ZIP_LOCAL_HEADER: u32 = 0x0403_4b50;
+
+/// Zip central directory signatures
+const ZIP_END_OF_DIRECTORY: u32 = 0x0605_4b50;
+const ZIP64_END_OF_DIRECTORY: u32 = 0x0606_4b50;
+const ZIP64_END_LOCATOR: u32 = 0x0706_4b50;
+const ZIP_DIRECTORY_ENTRY: u32 = 0x0201_4b50;
+
+/// Zip extra field holding 64-bit sizes and offsets
+const ZIP64_EXTRA: u16 = 0x0001;
+
+/// Zip compression method for members stored as-is
+const ZIP_STORED: u16 = 0;
+
+/// Element type of a NumPy array
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dtype {
+    F16,
+    F32,
+    F64,
+}
+
+impl Dtype {
+    /// Parse a NumPy type string such as `<f4`; returns the type and whether it is big-endian
+    fn parse(descr: &str) -> Option<(Self, bool)> {
+        let (order, kind) = descr.split_at_checked(1)?;
+        let big_endian = match order {
+            "<" | "|" => false,
+            ">" => true,
+            "=" => cfg!(target_endian = "big"),
+            _ => return None,
+        };
+        let dtype = match kind {
+            "f2" => Self::F16,
+            "f4" => Self::F32,
+            "f8" => Self::F64,
+            _ => return None,
+        };
+        Some((dtype, big_endian))
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::F16 => 2,
+            Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+}
+
+/// Layout of the array in a `.npy` image
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct NpyArray {
+    dtype: Dtype,
+    big_endian: bool,
+    rows: usize,
+    cols: usize,
+
+    /// Offset of the first element within the image
+    data_offset: usize,
+}
+
+impl NpyArray {
+    /// Parse the header of the `.npy` image `bytes`
+    fn parse(bytes: &[u8]) -> Result<Self> {
+        let corrupted =
+            |what: &str| Tagged::new(ErrorKind::Corrupted, format!("Invalid .npy data: {}", what));
+
+        if !bytes.starts_with(NPY_MAGIC) {
+            anyhow::bail!(corrupted("missing magic string"));
+        }
+        let (header_len, header_start) = match bytes.get(6) {
+            Some(1) => (usize::from(u16::from_le_bytes(le(bytes, 8).unwrap_or_default())), 10),
+            Some(2 | 3) => (u32::from_le_bytes(le(bytes, 8).unwrap_or_default()) as usize, 12),
+            _ => anyhow::bail!(corrupted("unsupported format version")),
+        };
+        let data_offset = header_start + header_len;
+        let header = bytes
+            .get(header_start..data_offset)
+            .and_then(|header| std::str::from_utf8(header).ok())
+            .ok_or_else(|| corrupted("truncated header"))?;
+
+        let descr = dict_value(header, "descr").ok_or_else(|| corrupted("no 'descr'"))?;
+        let descr = descr.trim_matches(['\'', '"']);
+        let (dtype, big_endian) = Dtype::parse(descr).ok_or_else(|| {
+            Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!("Unsupported dtype '{}': expected float16, float32 or float64", descr),
+            )
+        })?;
+
+        if dict_value(header, "fortran_order") != Some("False") {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Fortran-ordered arrays are not supported; save a C-ordered copy"
+            ));
+        }
+
+        let shape = dict_value(header, "shape").ok_or_else(|| corrupted("no 'shape'"))?;
+        let dims: Vec<usize> = shape
+            .trim_start_matches('(')
+            .trim_end_matches(')')
+            .split(',')
+            .map(str::trim)
+            .filter(|dim| !dim.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map_err(|_| corrupted("malformed 'shape'"))?;
+        let [rows, cols] = dims[..] else {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!("Expected a 2-D array of vectors, got shape {}", shape)
+            ));
+        };
+
+        let data_len = rows
+            .checked_mul(cols)
+            .and_then(|count| count.checked_mul(dtype.size()))
+            .ok_or_else(|| corrupted("shape overflows"))?;
+        if bytes.len().saturating_sub(data_offset) < data_len {
+            anyhow::bail!(corrupted(&format!("data is shorter than shape {}", shape)));
+        }
+
+        Ok(Self { dtype, big_endian, rows, cols, data_offset })
+    }
+
+    /// Decode row `row` of the image `bytes` into `dst`
+    fn read_row(&self, bytes: &[u8], row: usize, dst: &mut [f32]) {
+        let row_len = self.cols * self.dtype.size();
+        let start = self.data_offset + row * row_len;
+        let src = &bytes[start..start + row_len];
+
+        match (self.dtype, self.big_endian) {
+            (Dtype::F16, false) => decode(src, dst, |b| f16_to_f32(u16::from_le_bytes(b))),
+            (Dtype::F16, true) => decode(src, dst, |b| f16_to_f32(u16::from_be_bytes(b))),
+            (Dtype::F32, false) => decode(src, dst, f32::from_le_bytes),
+            (Dtype::F32, true) => decode(src, dst, f32::from_be_bytes),
+            (Dtype::F64, false) => decode(src, dst, |b| f64::from_le_bytes(b) as f32),
+            (Dtype::F64, true) => decode(src, dst, |b| f64::from_be_bytes(b) as f32),
+        }
+    }
+}
+
+impl VectorIndex {
+    /// Insert every row of a NumPy array file, in order
+    ///
+    /// `path` is a `.npy` file, or an uncompressed `.npz` archive holding a
+    /// single array (see `import_npz()` for archives with several). The file
+    /// is memory-mapped and decoded one row at a time. Each row must have
+    /// `input_dimensions()` values; `float16` and `float64` rows are converted
+    /// to `f32`.
+    ///
+    /// Like `add()`, this does not flush. If a row fails to insert, the rows
+    /// before it stay in the index. Progress is reported to the handler of
+    /// `set_progress_handler()`, one unit per row; cancelling keeps the rows
+    /// inserted so far.
+    ///
+    /// # Returns
+    ///
+    /// The IDs assigned to the rows, in row order
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, is not a valid `.npy` or
+    /// `.npz` file, holds an unsupported array (dtype, order, shape or
+    /// compression), or has the wrong number of columns (`DimensionMismatch`).
+    pub fn import_npy<P: AsRef<Path>>(&mut self, path: P) -> Result<Range<u64>> {
+        self.import_numpy(path.as_ref(), None)
+    }
+
+    /// Insert every row of the array `name` in an uncompressed `.npz` archive
+    ///
+    /// `name` is the keyword the array was saved under with `np.savez()`
+    /// (`arr_0` for the first positional array), with or without the `.npy`
+    /// suffix of the archive member. Otherwise behaves like `import_npy()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive has no array `name`, or for the same
+    /// reasons as `import_npy()`.
+    pub fn import_npz<P: AsRef<Path>>(&mut self, path: P, name: &str) -> Result<Range<u64>> {
+        self.import_numpy(path.as_ref(), Some(name))
+    }
+
+    fn import_numpy(&mut self, path: &Path, name: Option<&str>) -> Result<Range<u64>> {
+        let file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        // SAFETY: The mapping is read-only and only copied out of; the caller
+        // must not truncate the file while it is being imported.
+        let map = unsafe { Mmap::map(&file) }
+            .with_context(|| format!("Failed to map {}", path.display()))?;
+        #[cfg(unix)]
+        let _ = map.advise(memmap2::Advice::Sequential);
+
+        let image = if map.starts_with(NPY_MAGIC) && name.is_none() {
+            &map[..]
+        } else if le(&map, 0).map(u32::from_le_bytes) == Some(ZIP_LOCAL_HEADER) {
+            &map[npz_member(&map, name)?]
+        } else {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                format!(
+                    "{} is not a NumPy {} file",
+                    path.display(),
+                    if name.is_some() { ".npz" } else { ".npy or .npz" }
+                )
+            ));
+        };
+
+        let array = NpyArray::parse(image)
+            .with_context(|| format!("Failed to import {}", path.display()))?;
+        let dims = self.input_dimensions() as usize;
+        if array.cols != dims {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::DimensionMismatch,
+                format!("{} has {} columns, expected {}", path.display(), array.cols, dims)
+            ));
+        }
+
+        let first = self.len();
+        let progress = self.progress.start(ProgressStage::Import, array.rows as u64);
+        let mut row = vec![0.0f32; dims];
+        for i in 0..array.rows {
+            progress.report(i as u64)?;
+            array.read_row(image, i, &mut row);
+            self.add(&row)?;
+        }
+        progress.report(array.rows as u64)?;
+        Ok(first..self.len())
+    }
+}
+
+/// Byte range of the `.npy` member `name` (or of the only member) in a zip image
+fn npz_member(zip: &[u8], name: Option<&str>) -> Result<Range<usize>> {
+    let corrupted =
+        |what: &str| Tagged::new(ErrorKind::Corrupted, format!("Invalid .npz: {}", what));
+    let u16_at = |at: usize| le(zip, at).map(u16::from_le_bytes);
+    let u32_at = |at: usize| le(zip, at).map(u32::from_le_bytes);
+    let u64_at = |at: usize| le(zip, at).map(u64::from_le_bytes);
+
+    // The end-of-directory record ends the file, followed by a comment of at most 64 KiB
+    let search_from = zip.len().saturating_sub(22 + usize::from(u16::MAX));
+    let eocd = (search_from..zip.len().saturating_sub(21))
+        .rev()
+        .find(|&at| u32_at(at) == Some(ZIP_END_OF_DIRECTORY))
+        .ok_or_else(|| corrupted("no end of central directory"))?;
+    let mut entries = u64::from(u16_at(eocd + 10).unwrap_or_default());
+    let mut directory = u64::from(u32_at(eocd + 16).unwrap_or_default());
+
+    // Zip64 archives keep the real values in a second record
+    if entries == u64::from(u16::MAX) || directory == u64::from(u32::MAX) {
+        let locator = eocd.checked_sub(20).filter(|&at| u32_at(at) == Some(ZIP64_END_LOCATOR));
+        let record = locator
+            .and_then(|at| u64_at(at + 8))
+            .and_then(|at| usize::try_from(at).ok())
+            .filter(|&at| u32_at(at) == Some(ZIP64_END_OF_DIRECTORY))
+            .ok_or_else(|| corrupted("no zip64 end of central directory"))?;
+        entries = u64_at(record + 32).unwrap_or_default();
+        directory = u64_at(record + 48).unwrap_or_default();
+    }
+
+    let mut at = usize::try_from(directory).map_err(|_| corrupted("directory out of range"))?;
+    let mut names = Vec::new();
+    for _ in 0..entries {
+        if u32_at(at) != Some(ZIP_DIRECTORY_ENTRY) {
+            anyhow::bail!(corrupted("bad central directory entry"));
+        }
+        let method = u16_at(at + 10).unwrap_or_default();
+        let mut size = u64::from(u32_at(at + 20).unwrap_or_default());
+        let name_len = usize::from(u16_at(at + 28).unwrap_or_default());
+        let extra_len = usize::from(u16_at(at + 30).unwrap_or_default());
+        let comment_len = usize::from(u16_at(at + 32).unwrap_or_default());
+        let mut local = u64::from(u32_at(at + 42).unwrap_or_default());
+        let uncompressed = u32_at(at + 24).unwrap_or_default();
+
+        let member = zip
+            .get(at + 46..at + 46 + name_len)
+            .and_then(|name| std::str::from_utf8(name).ok())
+            .ok_or_else(|| corrupted("bad member name"))?;
+        let member = member.strip_suffix(".npy").unwrap_or(member);
+
+        // The zip64 extra field holds 64-bit values for the 32-bit fields set
+        // to u32::MAX: uncompressed size, compressed size, local header offset
+        let mut extra = at + 46 + name_len;
+        let extra_end = extra + extra_len;
+        while extra + 4 <= extra_end {
+            let len = usize::from(u16_at(extra + 2).unwrap_or_default());
+            if u16_at(extra) == Some(ZIP64_EXTRA) {
+                let mut wide = (extra + 4..extra + 4 + len).step_by(8).filter_map(&u64_at);
+                if uncompressed == u32::MAX {
+                    wide.next();
+                }
+                if size == u64::from(u32::MAX) {
+                    size = wide.next().ok_or_else(|| corrupted("bad zip64 extra field"))?;
+                }
+                if local == u64::from(u32::MAX) {
+                    local = wide.next().ok_or_else(|| corrupted("bad zip64 extra field"))?;
+                }
+            }
+            extra += 4 + len;
+        }
+        at = extra_end + comment_len;
+
+        let wanted = match name {
+            Some(name) => name.strip_suffix(".npy").unwrap_or(name) == member,
+            None => entries == 1,
+        };
+        if !wanted {
+            names.push(member.to_owned());
+            continue;
+        }
+
+        if method != ZIP_STORED {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "Array '{}' is compressed; save it with np.savez() instead of np.savez_compressed()",
+                    member
+                )
+            ));
+        }
+
+        let local = usize::try_from(local).map_err(|_| corrupted("member out of range"))?;
+        if u32_at(local) != Some(ZIP_LOCAL_HEADER) {
+            anyhow::bail!(corrupted("bad local file header"));
+        }
+        let start = local
+            + 30
+            + usize::from(u16_at(local + 26).unwrap_or_default())
+            + usize::from(u16_at(local + 28).unwrap_or_default());
+        let end = usize::try_from(size)
+            .ok()
+            .and_then(|size| start.checked_add(size))
+            .filter(|&end| end <= zip.len())
+            .ok_or_else(|| corrupted("member extends past the end of the file"))?;
+        return Ok(start..end);
+    }
+
+    let message = match name {
+        Some(name) => format!("No array '{}' in .npz archive (found: {})", name, names.join(", ")),
+        None => format!(
+            "The .npz archive holds {} arrays ({}); use import_npz() to pick one",
+            names.len(),
+            names.join(", ")
+        ),
+    };
+    anyhow::bail!(Tagged::new(ErrorKind::InvalidArgument, message))
+}
+
+/// Value of `key` in the Python dict literal of a `.npy` header
+fn dict_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let start = header.find(&format!("'{}':", key))? + key.len() + 3;
+    let rest = header[start..].trim_start();
+    let end = if rest.starts_with('(') { rest.find(')')? + 1 } else { rest.find([',', '}'])? };
+    Some(rest[..end].trim())
+}
+
+/// The `N` bytes of `bytes` at `at`, if in bounds
+fn le<const N: usize>(bytes: &[u8], at: usize) -> Option<[u8; N]> {
+    bytes.get(at..at.checked_add(N)?)?.try_into().ok()
+}
+
+/// Decode packed `N`-byte values from `src` into `dst`
+fn decode<const N: usize>(src: &[u8], dst: &mut [f32], value: impl Fn([u8; N]) -> f32) {
+    let (values, _) = src.as_chunks::<N>();
+    for (dst, bytes) in dst.iter_mut().zip(values) {
+        *dst = value(*bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IndexOptions;
+    use tempfile::TempDir;
+
+    /// A version 1.0 `.npy` image as `np.save()` writes it
+    fn npy(descr: &str, fortran_order: bool, shape: &str, data: &[u8]) -> Vec<u8> {
+        let order = if fortran_order { "True" } else { "False" };
+        let mut header =
+            format!("{{'descr': '{}', 'fortran_order': {}, 'shape': {}, }}", descr, order, shape);
+        while (10 + header.len() + 1) % 64 != 0 {
+            header.push(' ');
+        }
+        header.push('\n');
+
+        let mut bytes = NPY_MAGIC.to_vec();
+        bytes.extend_from_slice(&[1, 0]);
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    /// An `.npz` image with the given `(name, compression method, contents)` members
+    fn npz(members: &[(&str, u16, &[u8])]) -> Vec<u8> {
+        let mut zip = Vec::new();
+        let mut directory = Vec::new();
+        for &(name, method, contents) in members {
+            let local = zip.len() as u32;
+            let sizes = [contents.len() as u32; 2];
+            zip.extend_from_slice(&ZIP_LOCAL_HEADER.to_le_bytes());
+            zip.extend_from_slice(&[20, 0, 0, 0]);
+            zip.extend_from_slice(&method.to_le_bytes());
+            zip.extend_from_slice(&[0; 8]);
+            sizes.iter().for_each(|size| zip.extend_from_slice(&size.to_le_bytes()));
+            zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            zip.extend_from_slice(&[0, 0]);
+            zip.extend_from_slice(name.as_bytes());
+            zip.extend_from_slice(contents);
+
+            directory.extend_from_slice(&ZIP_DIRECTORY_ENTRY.to_le_bytes());
+            directory.extend_from_slice(&[20, 0, 20, 0, 0, 0]);
+            directory.extend_from_slice(&method.to_le_bytes());
+            directory.extend_from_slice(&[0; 8]);
+            sizes.iter().for_each(|size| directory.extend_from_slice(&size.to_le_bytes()));
+            directory.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            directory.extend_from_slice(&[0; 12]);
+            directory.extend_from_slice(&local.to_le_bytes());
+            directory.extend_from_slice(name.as_bytes());
+        }
+
+        let offset = zip.len() as u32;
+        zip.extend_from_slice(&directory);
+        zip.extend_from_slice(&ZIP_END_OF_DIRECTORY.to_le_bytes());
+        zip.extend_from_slice(&[0; 4]);
+        zip.extend_from_slice(&[members.len() as u8, 0, members.len() as u8, 0]);
+        zip.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&offset.to_le_bytes());
+        zip.extend_from_slice(&[0, 0]);
+        zip
+    }
+
+    fn f32_rows(rows: &[[f32; 4]]) -> Vec<u8> {
+        rows.iter().flatten().flat_map(|x| x.to_le_bytes()).collect()
+    }
+
+    fn open(dir: &TempDir) -> VectorIndex {
+        VectorIndex::open(dir.path().join("index.chassis"), 4, IndexOptions::default()).unwrap()
+    }
+
+    fn write(dir: &TempDir, name: &str, bytes: &[u8]) -> std::path::PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_import_npy_inserts_rows_in_order() {
+        let dir = TempDir::new().unwrap();
+        let rows: Vec<[f32; 4]> = (0..50).map(|i| [i as f32, 1.0, 2.0, 3.0]).collect();
+        let path = write(&dir, "rows.npy", &npy("<f4", false, "(50, 4)", &f32_rows(&rows)));
+
+        let mut index = open(&dir);
+        index.add(&[-1.0; 4]).unwrap();
+        assert_eq!(index.import_npy(&path).unwrap(), 1..51);
+
+        let hit = &index.search_with_vectors(&[20.0, 1.0, 2.0, 3.0], 1).unwrap()[0];
+        assert_eq!(hit.id, 21);
+        assert_eq!(hit.vector, rows[20]);
+    }
+
+    #[test]
+    fn test_import_npy_converts_other_float_types() {
+        let dir = TempDir::new().unwrap();
+        let f64_be: Vec<u8> =
+            [0.5f64, -1.0, 2.0, 4.0].iter().flat_map(|x| x.to_be_bytes()).collect();
+        // 1.0, -2.0, 0.5, 0.0 in half precision
+        let f16_le: Vec<u8> =
+            [0x3c00u16, 0xc000, 0x3800, 0].iter().flat_map(|x| x.to_le_bytes()).collect();
+
+        let mut index = open(&dir);
+        index.import_npy(write(&dir, "f8.npy", &npy(">f8", false, "(1, 4)", &f64_be))).unwrap();
+        index.import_npy(write(&dir, "f2.npy", &npy("<f2", false, "(1, 4)", &f16_le))).unwrap();
+
+        let hit = &index.search_with_vectors(&[0.5, -1.0, 2.0, 4.0], 1).unwrap()[0];
+        assert_eq!((hit.id, hit.vector.as_slice()), (0, &[0.5, -1.0, 2.0, 4.0][..]));
+        let hit = &index.search_with_vectors(&[1.0, -2.0, 0.5, 0.0], 1).unwrap()[0];
+        assert_eq!((hit.id, hit.vector.as_slice()), (1, &[1.0, -2.0, 0.5, 0.0][..]));
+    }
+
+    #[test]
+    fn test_import_npy_rejects_unsupported_arrays() {
+        let dir = TempDir::new().unwrap();
+        let data = f32_rows(&[[0.0; 4]; 2]);
+        let mut index = open(&dir);
+
+        for (name, bytes, kind) in [
+            ("fortran.npy", npy("<f4", true, "(2, 4)", &data), ErrorKind::InvalidArgument),
+            ("flat.npy", npy("<f4", false, "(8,)", &data), ErrorKind::InvalidArgument),
+            ("ints.npy", npy("<i4", false, "(2, 4)", &data), ErrorKind::InvalidArgument),
+            ("wide.npy", npy("<f4", false, "(1, 8)", &data), ErrorKind::DimensionMismatch),
+            ("short.npy", npy("<f4", false, "(3, 4)", &data), ErrorKind::Corrupted),
+            ("text.npy", b"not numpy".to_vec(), ErrorKind::Corrupted),
+        ] {
+            let err = index.import_npy(write(&dir, name, &bytes)).unwrap_err();
+            assert_eq!(ErrorKind::of(&err), kind, "{}: {:#}", name, err);
+        }
+        assert!(index.is_empty());
+    }
+
+    #[test]
+    fn test_import_npz_members() {
+        let dir = TempDir::new().unwrap();
+        let a = npy("<f4", false, "(1, 4)", &f32_rows(&[[1.0; 4]]));
+        let b = npy("<f4", false, "(2, 4)", &f32_rows(&[[2.0; 4], [3.0; 4]]));
+        let mut index = open(&dir);
+
+        let single = write(&dir, "single.npz", &npz(&[("arr_0.npy", ZIP_STORED, &a)]));
+        assert_eq!(index.import_npy(&single).unwrap(), 0..1);
+
+        let pair = write(&dir, "pair.npz", &npz(&[("a.npy", ZIP_STORED, &a), ("b.npy", 0, &b)]));
+        let err = index.import_npy(&pair).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+        assert_eq!(index.import_npz(&pair, "b").unwrap(), 1..3);
+        assert_eq!(index.import_npz(&pair, "a.npy").unwrap(), 3..4);
+        let err = index.import_npz(&pair, "c").unwrap_err();
+        assert!(format!("{:#}", err).contains("found: a, b"), "{:#}", err);
+
+        // Deflated members cannot be mapped
+        let deflated = write(&dir, "deflated.npz", &npz(&[("arr_0.npy", 8, &a)]));
+        let err = index.import_npy(&deflated).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+        assert_eq!(index.len(), 4);
+    }
+}
@@ -0,0 +1,119 @@
+//! Locking the hot part of the graph in RAM.
+//!
+//! Every search starts at the entry point and descends through the upper
+//! layers, so the few nodes above layer 0 are read by every query. When the
+//! kernel evicts their pages on a memory-pressured device, the next search
+//! waits on a disk read per hop, a multi-millisecond spike.
+//! `VectorIndex::pin_graph()` locks (`mlock`) the graph headers and the
+//! records of upper-layer nodes, highest layer first, within a byte budget.
+//! In two-tier graphs the upper layers of all those nodes sit together in the
+//! overflow region, so the pinned pages are mostly theirs.
+
+use crate::VectorIndex;
+use crate::error::ErrorKind;
+use crate::mapping;
+use anyhow::Result;
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+/// What `VectorIndex::pin_graph()` locked in RAM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GraphPin {
+    /// Bytes of the pinned pages, in whole OS pages
+    pub pinned_bytes: u64,
+
+    /// Upper-layer nodes whose records are pinned
+    pub pinned_nodes: u64,
+
+    /// Nodes above layer 0 in the graph
+    pub upper_layer_nodes: u64,
+
+    /// The OS limit on locked memory (`RLIMIT_MEMLOCK`) cut the pin short, or
+    /// the platform cannot lock memory
+    pub os_limited: bool,
+}
+
+impl VectorIndex {
+    /// Lock the graph pages every search reads in RAM, up to `bytes_budget`
+    ///
+    /// Pins the graph headers, then the records of upper-layer nodes from the
+    /// highest layer down, starting with the entry point, until the budget
+    /// or the nodes run out. Layer-0-only nodes are never pinned. The budget
+    /// is lowered to the process's `RLIMIT_MEMLOCK`; when the OS still refuses
+    /// a lock, the pin keeps what was locked so far and reports
+    /// `GraphPin::os_limited` rather than failing. Platforms without `mlock`
+    /// pin nothing and report the same.
+    ///
+    /// Pins hold until `unpin_graph()` or until the mapping is replaced, which
+    /// happens when inserts grow the file or move the graph zone: call again
+    /// after a batch of inserts. Scans the node headers once, so the call is
+    /// linear in the node count.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a node record cannot be read.
+    pub fn pin_graph(&self, bytes_budget: u64) -> Result<GraphPin> {
+        let page_size = mapping::os_page_size() as u64;
+        let limit = mapping::memlock_limit();
+        let budget = bytes_budget.min(limit.unwrap_or(u64::MAX));
+        let capped = budget < bytes_budget;
+
+        let nodes = self.graph.upper_layer_nodes()?;
+        let mut pin = GraphPin {
+            pinned_bytes: 0,
+            pinned_nodes: 0,
+            upper_layer_nodes: nodes.len() as u64,
+            os_limited: false,
+        };
+
+        let mut pages = BTreeSet::new();
+        let mut pin_ranges = |ranges: &[Range<usize>], pin: &mut GraphPin| -> Result<bool> {
+            let new: BTreeSet<u64> = ranges
+                .iter()
+                .flat_map(|range| {
+                    range.start as u64 / page_size..(range.end as u64).div_ceil(page_size)
+                })
+                .filter(|page| !pages.contains(page))
+                .collect();
+            if (pages.len() + new.len()) as u64 * page_size > budget {
+                pin.os_limited = capped;
+                return Ok(false);
+            }
+
+            for range in ranges {
+                if let Err(e) = self.graph.storage.pin_graph(range.start, range.len()) {
+                    if ErrorKind::of(&e) != ErrorKind::Io {
+                        return Err(e);
+                    }
+                    pin.os_limited = true;
+                    return Ok(false);
+                }
+            }
+            pages.extend(new);
+            pin.pinned_bytes = pages.len() as u64 * page_size;
+            Ok(true)
+        };
+
+        if !pin_ranges(&[self.graph.header_range()], &mut pin)? {
+            return Ok(pin);
+        }
+        for (node_id, _) in nodes {
+            let (record, overflow) = self.graph.record_ranges(node_id)?;
+            let ranges: Vec<_> = std::iter::once(record).chain(overflow).collect();
+            if !pin_ranges(&ranges, &mut pin)? {
+                break;
+            }
+            pin.pinned_nodes += 1;
+        }
+        Ok(pin)
+    }
+
+    /// Unlock the pages locked by `pin_graph()`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the OS rejects the call.
+    pub fn unpin_graph(&self) -> Result<()> {
+        self.graph.storage.unpin_graph()
+    }
+}
@@ -0,0 +1,164 @@
+//! Parameter presets for users who do not want to tune HNSW by hand.
+//!
+//! `IndexOptions::preset()` picks `max_connections`, `ef_construction`,
+//! `ef_search`, `max_layers` and `growth_chunk` from a goal and two facts about
+//! the data: dimensionality and expected number of vectors.
+//!
+//! - `M` starts from the goal and grows by half for embeddings of 512 or more
+//!   dimensions, which need denser graphs for the same recall.
+//! - `ef_construction` and `ef_search` start from the goal and double past one
+//!   million vectors; neither exceeds the dataset size (plus `M`), where a
+//!   larger beam stops helping.
+//! - `max_layers` covers the expected top layer, `log_M(n)`, plus a margin.
+//!   Every node record reserves room for all layers, so small datasets get
+//!   noticeably smaller graphs.
+//! - `growth_chunk` is about 1/64 of the expected vector zone, between one
+//!   page and 64 MiB, so bulk loads remap the file a few dozen times instead
+//!   of once per page.
+//!
+//! `max_layers` is part of the file format: reopen an index with the same
+//! preset arguments (or the same explicit options) it was created with.
+
+use crate::IndexOptions;
+use crate::storage::PAGE_SIZE;
+
+/// Largest growth step a preset chooses
+const MAX_GROWTH_CHUNK: usize = 64 * 1024 * 1024;
+
+/// Datasets above this size get twice the search and construction beam
+const LARGE_DATASET: u64 = 1_000_000;
+
+/// Embeddings with at least this many dimensions get a denser graph
+const HIGH_DIMENSIONS: u32 = 512;
+
+/// Goal for `IndexOptions::preset()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Preset {
+    /// Quick inserts and searches, moderate recall
+    Fast,
+
+    /// Good recall at reasonable speed (the defaults, adjusted to the data)
+    #[default]
+    Balanced,
+
+    /// Highest recall, slower inserts and searches, larger graph
+    HighRecall,
+
+    /// Smallest graph and file, for mobile and embedded use
+    TinyFootprint,
+}
+
+impl Preset {
+    /// Base `(M, ef_construction, ef_search)` before adjusting to the data
+    const fn base(self) -> (u16, usize, usize) {
+        match self {
+            Self::Fast => (12, 100, 32),
+            Self::Balanced => (16, 200, 64),
+            Self::HighRecall => (32, 400, 200),
+            Self::TinyFootprint => (8, 100, 40),
+        }
+    }
+}
+
+impl IndexOptions {
+    /// Options for `preset`, derived from the vector dimensionality and the
+    /// number of vectors the index is expected to hold
+    ///
+    /// Fields not covered by presets keep their defaults, so they can be
+    /// overridden with struct update syntax:
+    ///
+    /// ```
+    /// use chassis_core::{ElementType, IndexOptions, Preset};
+    ///
+    /// let options = IndexOptions {
+    ///     element_type: ElementType::F16,
+    ///     ..IndexOptions::preset(Preset::TinyFootprint, 384, 50_000)
+    /// };
+    /// assert_eq!(options.max_connections, 8);
+    /// ```
+    #[must_use]
+    pub fn preset(preset: Preset, dims: u32, expected_count: u64) -> Self {
+        let (mut m, mut ef_construction, mut ef_search) = preset.base();
+        let expected_count = expected_count.max(1);
+
+        if dims >= HIGH_DIMENSIONS && preset != Preset::TinyFootprint {
+            m += m / 2;
+        }
+
+        if expected_count > LARGE_DATASET {
+            ef_construction *= 2;
+            ef_search *= 2;
+        }
+
+        // A beam wider than the dataset visits every vector anyway
+        let beam_cap =
+            usize::try_from(expected_count).unwrap_or(usize::MAX).saturating_add(m as usize);
+        ef_construction = ef_construction.min(beam_cap);
+        ef_search = ef_search.min(beam_cap);
+
+        // Expected top layer is log_M(n); keep two spare layers (one when tiny)
+        let margin = if preset == Preset::TinyFootprint { 1.0 } else { 2.0 };
+        let top_layer = (expected_count as f64).ln() / f64::from(m).ln();
+        let max_layers = (top_layer.ceil() + margin).clamp(2.0, 16.0) as u8;
+
+        let growth_chunk = if preset == Preset::TinyFootprint {
+            PAGE_SIZE
+        } else {
+            let vector_bytes = dims as usize * std::mem::size_of::<f32>();
+            let expected_bytes =
+                usize::try_from(expected_count).unwrap_or(usize::MAX).saturating_mul(vector_bytes);
+            (expected_bytes / 64).clamp(PAGE_SIZE, MAX_GROWTH_CHUNK).next_multiple_of(PAGE_SIZE)
+        };
+
+        Self {
+            max_connections: m,
+            ef_construction,
+            ef_search,
+            max_layers,
+            growth_chunk,
+            ..Self::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_scale_with_data() {
+        let balanced = IndexOptions::preset(Preset::Balanced, 128, 100_000);
+        assert_eq!(balanced.max_connections, 16);
+        assert_eq!(balanced.ef_construction, 200);
+        assert_eq!(balanced.ef_search, 64);
+        // log_16(100k) ~ 4.15 -> 5 + 2 spare
+        assert_eq!(balanced.max_layers, 7);
+        assert_eq!(balanced.growth_chunk, 196 * PAGE_SIZE);
+
+        let wide = IndexOptions::preset(Preset::Balanced, 1536, 10_000_000);
+        assert_eq!(wide.max_connections, 24);
+        assert_eq!(wide.ef_search, 128);
+        assert_eq!(wide.growth_chunk, MAX_GROWTH_CHUNK);
+
+        let small = IndexOptions::preset(Preset::HighRecall, 64, 50);
+        assert_eq!(small.ef_construction, 82);
+        assert_eq!(small.ef_search, 82);
+        assert_eq!(small.max_layers, 4);
+        assert_eq!(small.growth_chunk, PAGE_SIZE);
+
+        let tiny = IndexOptions::preset(Preset::TinyFootprint, 384, 0);
+        assert_eq!(tiny.max_connections, 8);
+        assert_eq!(tiny.max_layers, 2);
+        assert_eq!(tiny.growth_chunk, PAGE_SIZE);
+
+        // Everything stays within what an index accepts
+        for preset in [Preset::Fast, Preset::Balanced, Preset::HighRecall, Preset::TinyFootprint] {
+            for count in [0, 1, 1_000, u64::MAX] {
+                let options = IndexOptions::preset(preset, 4096, count);
+                assert!((1..=16).contains(&options.max_layers));
+                assert!(options.ef_search >= 1 && options.ef_construction >= 1);
+                assert!(options.growth_chunk.is_multiple_of(PAGE_SIZE));
+            }
+        }
+    }
+}
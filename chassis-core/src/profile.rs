@@ -0,0 +1,299 @@
+//! Anonymized workload profiles for bug reports.
+//!
+//! Every `VectorIndex` keeps a few counters and power-of-two histograms about
+//! how it is used: inserts, queries, `k`, effective `ef` and search latency.
+//! `VectorIndex::export_workload_profile()` combines them with the index shape
+//! (dimensions, size, HNSW parameters) into a `WorkloadProfile`.
+//!
+//! A profile never contains vectors, query contents, IDs, distances or file
+//! paths, so it can be attached to a public issue as-is. Recording is a handful
+//! of relaxed atomic increments per operation.
+
+use crate::VectorIndex;
+use crate::element::ElementType;
+use crate::header::LibraryVersion;
+use crate::metrics::{self, IndexMetrics};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+/// Number of histogram buckets; the last one is open-ended
+const BUCKETS: usize = 32;
+
+/// Summary of an index and its workload, safe to share
+///
+/// Histograms are lists of `(upper bound, count)` for non-empty buckets. Bucket
+/// bounds are powers of two, and each bucket counts values above the previous
+/// bound up to and including its own; the last bucket (`2^31`) also counts
+/// everything larger.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkloadProfile {
+    /// Version of the library producing the profile
+    pub library_version: LibraryVersion,
+
+    /// Operating system (`std::env::consts::OS`)
+    pub os: &'static str,
+
+    /// CPU architecture (`std::env::consts::ARCH`)
+    pub arch: &'static str,
+
+    /// Stored dimensions per vector
+    pub dimensions: u32,
+
+    /// On-disk encoding of vector components
+    pub element_type: ElementType,
+
+    /// Vectors in the index, including unflushed ones
+    pub vectors: u64,
+
+    /// HNSW `M` parameter
+    pub max_connections: u16,
+
+    /// HNSW `efConstruction` parameter
+    pub ef_construction: usize,
+
+    /// Configured `efSearch`
+    pub ef_search: usize,
+
+    /// Vectors inserted since the index was opened
+    pub inserts: u64,
+
+    /// k-NN searches since the index was opened
+    pub queries: u64,
+
+    /// Radius searches (`search_within`) since the index was opened
+    pub range_queries: u64,
+
+    /// Time since the index was opened; `None` where no clock is available (wasm)
+    pub uptime: Option<Duration>,
+
+    /// Requested `k` of k-NN searches
+    pub k_histogram: Vec<(u64, u64)>,
+
+    /// Effective `ef` (`max(ef_search, k)`) of k-NN searches
+    pub ef_histogram: Vec<(u64, u64)>,
+
+    /// Latency of all searches, in microseconds; empty where no clock is available
+    pub latency_histogram_us: Vec<(u64, u64)>,
+}
+
+impl WorkloadProfile {
+    /// Average searches per second since the index was opened
+    pub fn query_rate(&self) -> Option<f64> {
+        let seconds = self.uptime?.as_secs_f64();
+        (seconds > 0.0).then(|| (self.queries + self.range_queries) as f64 / seconds)
+    }
+
+    /// Renders the profile as a JSON object
+    pub fn to_json(&self) -> String {
+        fn histogram(buckets: &[(u64, u64)]) -> String {
+            let entries: Vec<String> =
+                buckets.iter().map(|(le, count)| format!("[{},{}]", le, count)).collect();
+            format!("[{}]", entries.join(","))
+        }
+
+        let mut json = String::from("{");
+        let _ = write!(json, "\"library_version\":\"{}\",", self.library_version);
+        let _ = write!(json, "\"os\":\"{}\",\"arch\":\"{}\",", self.os, self.arch);
+        let _ = write!(json, "\"dimensions\":{},", self.dimensions);
+        let _ = write!(json, "\"element_type\":\"{}\",", self.element_type);
+        let _ = write!(json, "\"vectors\":{},", self.vectors);
+        let _ = write!(json, "\"max_connections\":{},", self.max_connections);
+        let _ = write!(json, "\"ef_construction\":{},", self.ef_construction);
+        let _ = write!(json, "\"ef_search\":{},", self.ef_search);
+        let _ = write!(json, "\"inserts\":{},", self.inserts);
+        let _ = write!(json, "\"queries\":{},", self.queries);
+        let _ = write!(json, "\"range_queries\":{},", self.range_queries);
+        match self.uptime {
+            Some(uptime) => {
+                let _ = write!(json, "\"uptime_secs\":{:.3},", uptime.as_secs_f64());
+            }
+            None => json.push_str("\"uptime_secs\":null,"),
+        }
+        match self.query_rate() {
+            Some(rate) => {
+                let _ = write!(json, "\"query_rate\":{:.3},", rate);
+            }
+            None => json.push_str("\"query_rate\":null,"),
+        }
+        let _ = write!(json, "\"k_histogram\":{},", histogram(&self.k_histogram));
+        let _ = write!(json, "\"ef_histogram\":{},", histogram(&self.ef_histogram));
+        let _ = write!(json, "\"latency_histogram_us\":{}", histogram(&self.latency_histogram_us));
+        json.push('}');
+        json
+    }
+}
+
+/// Power-of-two histogram with lock-free recording
+#[derive(Debug)]
+struct Histogram([AtomicU64; BUCKETS]);
+
+impl Histogram {
+    fn new() -> Self {
+        Self(std::array::from_fn(|_| AtomicU64::new(0)))
+    }
+
+    fn record(&self, value: u64) {
+        // Bucket i holds (2^(i-1), 2^i]; 0 and 1 share bucket 0
+        let bucket = (u64::BITS - value.saturating_sub(1).leading_zeros()) as usize;
+        self.0[bucket.min(BUCKETS - 1)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> Vec<(u64, u64)> {
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(i, count)| (1 << i, count.load(Ordering::Relaxed)))
+            .filter(|&(_, count)| count > 0)
+            .collect()
+    }
+}
+
+/// Usage counters kept by a `VectorIndex`
+#[derive(Debug)]
+pub(crate) struct WorkloadStats {
+    #[cfg(not(target_arch = "wasm32"))]
+    opened: Instant,
+    inserts: AtomicU64,
+    queries: AtomicU64,
+    range_queries: AtomicU64,
+    flushes: AtomicU64,
+    distance_computations: AtomicU64,
+    k: Histogram,
+    ef: Histogram,
+    latency_us: Histogram,
+}
+
+/// Start time of an operation, if a clock is available
+pub(crate) struct Timer {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: Instant,
+}
+
+impl Timer {
+    /// Elapsed time; `None` on wasm, where `Instant` is unavailable
+    pub(crate) fn elapsed(&self) -> Option<Duration> {
+        #[cfg(not(target_arch = "wasm32"))]
+        return Some(self.start.elapsed());
+
+        #[cfg(target_arch = "wasm32")]
+        None
+    }
+
+    /// Elapsed microseconds; `None` on wasm
+    fn elapsed_us(&self) -> Option<u64> {
+        self.elapsed().map(|elapsed| u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX))
+    }
+}
+
+impl WorkloadStats {
+    pub(crate) fn new() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            opened: Instant::now(),
+            inserts: AtomicU64::new(0),
+            queries: AtomicU64::new(0),
+            range_queries: AtomicU64::new(0),
+            flushes: AtomicU64::new(0),
+            distance_computations: AtomicU64::new(0),
+            k: Histogram::new(),
+            ef: Histogram::new(),
+            latency_us: Histogram::new(),
+        }
+    }
+
+    pub(crate) fn timer(&self) -> Timer {
+        Timer {
+            #[cfg(not(target_arch = "wasm32"))]
+            start: Instant::now(),
+        }
+    }
+
+    pub(crate) fn record_insert(&self) {
+        self.inserts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_query(&self, timer: Timer, k: usize, ef: usize) {
+        self.queries.fetch_add(1, Ordering::Relaxed);
+        self.k.record(k as u64);
+        self.ef.record(ef.max(k) as u64);
+        self.record_latency(timer);
+    }
+
+    pub(crate) fn record_range_query(&self, timer: Timer) {
+        self.range_queries.fetch_add(1, Ordering::Relaxed);
+        self.record_latency(timer);
+    }
+
+    pub(crate) fn record_flush(&self) {
+        self.flushes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_distances(&self, count: u64) {
+        self.distance_computations.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn record_latency(&self, timer: Timer) {
+        if let Some(us) = timer.elapsed_us() {
+            self.latency_us.record(us);
+        }
+    }
+
+    /// Combines the counters with the shape of `index`
+    pub(crate) fn profile(&self, index: &VectorIndex) -> WorkloadProfile {
+        WorkloadProfile {
+            library_version: LibraryVersion::CURRENT,
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            dimensions: index.dimensions(),
+            element_type: index.element_type(),
+            vectors: index.len(),
+            max_connections: index.options.max_connections,
+            ef_construction: index.options.ef_construction,
+            ef_search: index.options.ef_search,
+            inserts: self.inserts.load(Ordering::Relaxed),
+            queries: self.queries.load(Ordering::Relaxed),
+            range_queries: self.range_queries.load(Ordering::Relaxed),
+            #[cfg(not(target_arch = "wasm32"))]
+            uptime: Some(self.opened.elapsed()),
+            #[cfg(target_arch = "wasm32")]
+            uptime: None,
+            k_histogram: self.k.snapshot(),
+            ef_histogram: self.ef.snapshot(),
+            latency_histogram_us: self.latency_us.snapshot(),
+        }
+    }
+
+    /// Snapshot of the counters for `VectorIndex::metrics()`
+    pub(crate) fn metrics(&self, index: &VectorIndex) -> IndexMetrics {
+        IndexMetrics {
+            vectors: index.len(),
+            searches: self.queries.load(Ordering::Relaxed),
+            range_searches: self.range_queries.load(Ordering::Relaxed),
+            adds: self.inserts.load(Ordering::Relaxed),
+            flushes: self.flushes.load(Ordering::Relaxed),
+            distance_computations: self.distance_computations.load(Ordering::Relaxed),
+            remaps: index.graph.storage.remaps(),
+            major_page_faults: metrics::major_page_faults(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_buckets_are_powers_of_two() {
+        let histogram = Histogram::new();
+        for value in [0, 1, 2, 3, 4, 5, 1000, u64::MAX] {
+            histogram.record(value);
+        }
+        assert_eq!(
+            histogram.snapshot(),
+            vec![(1, 2), (2, 1), (4, 2), (8, 1), (1024, 1), (1 << 31, 1)]
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! Progress reports from long-running operations.
+//!
+//! `optimize()`, `link_graph()` and the NumPy imports can run for minutes on a
+//! large index. An application that shows a progress bar, or lets the user
+//! cancel, attaches a handler with `VectorIndex::set_progress_handler()`. The
+//! handler runs on the thread performing the operation, every
+//! `REPORT_INTERVAL` units of work and once the work is done, and cancels the
+//! operation by returning `false`.
+
+use crate::error::{ErrorKind, Tagged};
+use anyhow::Result;
+use std::fmt;
+use std::sync::Arc;
+
+/// Units of work (vectors or nodes) between two reports
+const REPORT_INTERVAL: u64 = 64;
+
+/// Long-running operation reported to a progress handler
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ProgressStage {
+    /// Inserting the rows of `import_npy()` or `import_npz()`; one unit per row
+    Import,
+
+    /// Selecting neighbors again in `optimize()`; one unit per node and pass
+    Optimize,
+
+    /// Linking the vectors pending under `flat_threshold` in `link_graph()`
+    /// or `link_graph_batch()`; one unit per vector
+    Link,
+}
+
+impl ProgressStage {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Import => "Import",
+            Self::Optimize => "Optimize",
+            Self::Link => "Link",
+        }
+    }
+}
+
+/// Receives `(stage, done, total)` and returns whether to continue
+///
+/// `done` starts at 0 and reaches `total` when the operation completes.
+/// Returning `false` stops the operation with `ErrorKind::Cancelled`; the
+/// work done up to then is kept. The final report, with `done == total`,
+/// comes after the work and cannot cancel it.
+pub type ProgressHandler = dyn Fn(ProgressStage, u64, u64) -> bool + Send + Sync;
+
+/// The progress handler attached to an index, if any
+#[derive(Clone, Default)]
+pub(crate) struct ProgressSlot(pub(crate) Option<Arc<ProgressHandler>>);
+
+impl fmt::Debug for ProgressSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.0.is_some() { "Progress(Some(..))" } else { "Progress(None)" })
+    }
+}
+
+impl ProgressSlot {
+    /// Reporter for one run of `stage` over `total` units
+    pub(crate) fn start(&self, stage: ProgressStage, total: u64) -> Reporter {
+        Reporter { handler: self.0.clone(), stage, total }
+    }
+}
+
+/// Reports one operation's progress to the attached handler
+pub(crate) struct Reporter {
+    handler: Option<Arc<ProgressHandler>>,
+    stage: ProgressStage,
+    total: u64,
+}
+
+impl Reporter {
+    /// Report `done` units if a report is due, failing with `Cancelled` if the
+    /// handler asks to stop before the work is complete
+    pub(crate) fn report(&self, done: u64) -> Result<()> {
+        let Some(handler) = &self.handler else {
+            return Ok(());
+        };
+        if !done.is_multiple_of(REPORT_INTERVAL) && done != self.total {
+            return Ok(());
+        }
+
+        if !handler(self.stage, done, self.total) && done < self.total {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Cancelled,
+                format!("{} cancelled after {} of {}", self.stage.name(), done, self.total)
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_reporter_throttles_and_cancels() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorded = seen.clone();
+        let slot = ProgressSlot(Some(Arc::new(move |stage, done, total| {
+            recorded.lock().unwrap().push((stage, done, total));
+            done < 128
+        })));
+
+        let reporter = slot.start(ProgressStage::Import, 100);
+        for done in 0..=100 {
+            reporter.report(done).unwrap();
+        }
+        let import = ProgressStage::Import;
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![(import, 0, 100), (import, 64, 100), (import, 100, 100)]
+        );
+
+        let reporter = slot.start(ProgressStage::Optimize, 1000);
+        reporter.report(64).unwrap();
+        let err = reporter.report(128).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Cancelled);
+        assert_eq!(err.to_string(), "Optimize cancelled after 128 of 1000");
+
+        // Finished work is not undone
+        slot.start(ProgressStage::Optimize, 200).report(200).unwrap();
+
+        // Without a handler nothing is reported or cancelled
+        ProgressSlot::default().start(ProgressStage::Import, 10).report(0).unwrap();
+    }
+}
@@ -0,0 +1,91 @@
+//! Quantized copies of an index.
+//!
+//! An index built with `f32` vectors can be copied into a smaller element
+//! type once its data is known, for example to ship a `Binary` index to a
+//! device. With the `linalg` feature, a rotation trained on the original (see
+//! `VectorIndex::train_rotation()`) is applied to every vector before it is
+//! quantized, and persisted in the copy, which puts every added vector and
+//! query through it as well.
+
+use crate::element::ElementType;
+use crate::error::{ErrorKind, Tagged};
+use crate::{IndexOptions, VectorIndex};
+use anyhow::Result;
+use std::path::Path;
+
+impl VectorIndex {
+    /// Write a copy of this index that stores its vectors as `element_type`
+    ///
+    /// Vectors keep their IDs, along with their keys, groups, tags,
+    /// expiration times and deletions, and the copy's graph is built with this
+    /// index's graph parameters. If the index holds a trained rotation, every
+    /// vector is rotated before it is quantized, and the copy keeps the
+    /// rotation to apply to the vectors and queries it is given later. The
+    /// copy is flushed before it is returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - This index does not store `f32` vectors (`InvalidArgument`)
+    /// - `output` cannot be opened or already holds vectors
+    /// - An insert or the final flush fails
+    ///
+    /// A failed copy can leave a partly filled index at `output`; remove it
+    /// before trying again.
+    pub fn quantize_to<P: AsRef<Path>>(
+        &self,
+        output: P,
+        element_type: ElementType,
+    ) -> Result<VectorIndex> {
+        if self.element_type() != ElementType::F32 {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!("Only f32 indexes can be quantized, not {}", self.element_type())
+            ));
+        }
+
+        let output = output.as_ref();
+        let options = IndexOptions { element_type, ..self.options().clone() };
+        let mut copy = VectorIndex::open(output, self.dimensions(), options)?;
+        if !copy.is_empty() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!("Quantization output {} already holds vectors", output.display())
+            ));
+        }
+
+        #[cfg(feature = "linalg")]
+        if let Some(rotation) = &self.rotation {
+            copy.put_rotation(rotation.clone())?;
+        }
+        copy.reserve(self.len())?;
+
+        // Deleted vectors are copied too, then deleted, so IDs stay aligned
+        for id in 0..self.len() {
+            // Stored vectors are already truncated and normalized
+            let vector = self.graph.storage.get_vector(id)?;
+            #[cfg(feature = "linalg")]
+            let vector = match copy.applied_rotation() {
+                Some(rotation) => rotation.apply(&vector),
+                None => vector,
+            };
+            let new_id = copy.append_stored(&vector)?;
+            debug_assert_eq!(new_id, id);
+
+            if let Some(key) = self.key_for_id(id) {
+                copy.keys.insert(key, id);
+            }
+            copy.groups.set(id, self.group_of(id));
+            copy.tags.set(id, &self.tags_of(id));
+            copy.expiry.set(id, self.expiry_of(id));
+        }
+        for id in 0..self.len() {
+            if self.is_deleted(id)? {
+                copy.delete(id)?;
+            }
+        }
+
+        copy.flush()?;
+        Ok(copy)
+    }
+}
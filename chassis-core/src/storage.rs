@@ -1,32 +1,314 @@
-use crate::header::{HEADER_SIZE, Header, MAGIC};
+use crate::dirty::DirtyPages;
+use crate::element::{ElementType, MAX_BINARY_WORDS, StoredVector, binarize, binary_words};
+#[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+use crate::encrypted::{EncryptedFile, EncryptionKey};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::error::FileStolen;
+use crate::error::{ErrorKind, Tagged};
+use crate::header::{
+    HEADER_SIZE, Header, LibraryVersion, MAGIC, MAX_PAGE_SIZE, MAX_USER_META_LEN, VERSION,
+    is_valid_page_size,
+};
+#[cfg(feature = "wasm")]
+use crate::mapping::PageBuffer;
+use crate::mapping::{Mapping, MemoryMode};
+use crate::metadata::{self, Changes, JournaledMap, SectionTag};
 use anyhow::{Context, Result};
+#[cfg(not(target_arch = "wasm32"))]
 use fs2::FileExt;
-use memmap2::MmapMut;
-use std::fs::{File, OpenOptions};
-use std::path::Path;
-
-/// Page size for file alignment (4KB)
-const PAGE_SIZE: usize = 4096;
+#[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+use memmap2::MmapRaw;
+#[cfg(not(target_arch = "wasm32"))]
+use memmap2::{Mmap, MmapMut, MmapOptions};
+#[cfg(not(target_arch = "wasm32"))]
+use same_file::Handle;
+use std::fs::File;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs::OpenOptions;
+use std::ops::Range;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread::JoinHandle;
+
+/// OS page size assumed for file alignment (4KB), and the default index page size
+pub(crate) const PAGE_SIZE: usize = 4096;
+
+/// Extra room left in front of the metadata zone so graph growth does not move it on every insert.
+const METADATA_ZONE_SLACK: usize = 1024 * 1024;
 
 /// Storage engine for on-disk vector data
 #[derive(Debug)]
 pub struct Storage {
-    /// File handle (owns the file lock)
-    #[allow(dead_code)]
-    file: File,
+    /// File handle (owns the file lock); `None` for in-memory storage
+    file: Option<File>,
+
+    /// Mapped view of the file (`None` only transiently during resize on Windows).
+    mmap: Option<Mapping>,
+
+    /// Opened with `open_shared`: private copy-on-write view, no growth or sync
+    shared_reader: bool,
+
+    /// Paging advice applied to the mapping (re-applied after every remap)
+    memory_mode: MemoryMode,
+
+    /// Minimum bytes added per growth (a multiple of the page size)
+    growth_chunk: usize,
+
+    /// Size the index file (plus its graph file) may not grow past
+    max_file_bytes: Option<u64>,
+
+    /// Times the mapping (or the graph file's) was replaced to grow it
+    remaps: u64,
+
+    /// Where the writable file was opened, checked before every commit
+    #[cfg(not(target_arch = "wasm32"))]
+    origin: Option<FileOrigin>,
+
+    /// Region of a collection file holding this image; `None` for a whole file
+    #[cfg(not(target_arch = "wasm32"))]
+    window: Option<Window>,
+
+    /// Pages written since the last commit
+    dirty: DirtyPages,
+
+    /// File sync started by `commit_in_background()` and not yet joined
+    #[cfg(not(target_arch = "wasm32"))]
+    background: Option<BackgroundSync>,
+
+    /// Separate file holding the graph zone; `None` when it shares this file
+    #[cfg(not(target_arch = "wasm32"))]
+    graph_file: Option<GraphFile>,
+
+    /// Encrypted file the private image is sealed into on commit
+    #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+    encrypted: Option<EncryptedFile>,
+}
+
+/// A file sync running on a background thread, see `Storage::commit_in_background()`
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+struct BackgroundSync {
+    thread: JoinHandle<Result<()>>,
+    done: SyncSignal,
+}
+
+/// Tells whether a background sync has finished, without access to the storage
+///
+/// Lets a caller that shares the index behind a lock wait for the sync with
+/// the lock released. The storage still joins the sync, and reports its
+/// error, before it next touches the file.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SyncSignal(Arc<(Mutex<bool>, Condvar)>);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SyncSignal {
+    /// Block until the sync has finished, whether or not it succeeded
+    pub(crate) fn wait(&self) {
+        let (done, finished) = &*self.0;
+        let mut done = done.lock().unwrap_or_else(PoisonError::into_inner);
+        while !*done {
+            done = finished.wait(done).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+}
+
+/// Raises a `SyncSignal` when the sync thread exits, even by panicking
+#[cfg(not(target_arch = "wasm32"))]
+struct RaiseOnExit(SyncSignal);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for RaiseOnExit {
+    fn drop(&mut self) {
+        let (done, finished) = &*(self.0).0;
+        *done.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        finished.notify_all();
+    }
+}
+
+/// Second file holding the graph zone of an index, at `graph_file_path()`
+///
+/// Keeping the graph out of the index file avoids the sparse gap in front of
+/// the graph zone, which backup tools copy in full and filesystems without
+/// sparse files (FAT32) allocate. The graph zone starts at offset 0 of this
+/// file and the index header records that the file exists.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+struct GraphFile {
+    /// File handle (owns its own lock, taken like the index file's); `None`
+    /// for the copy-on-write view of a fork, which never writes the file
+    file: Option<File>,
+
+    /// Mapped view of the file (`None` only transiently during resize)
+    mmap: Option<Mapping>,
+
+    /// Pages written since the last commit
+    dirty: DirtyPages,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl GraphFile {
+    /// Opens the graph file at `path`, creating (or emptying) it with `create`
+    fn open(path: &Path, create: bool) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(create)
+            .truncate(create)
+            .open(path)
+            .with_context(|| format!("Failed to open graph file: {}", path.display()))?;
+        file.try_lock_exclusive().context(Tagged::new(
+            ErrorKind::Locked,
+            "Chassis graph file is already open by another process",
+        ))?;
+        if create {
+            file.set_len(PAGE_SIZE as u64)?;
+        }
+
+        let mmap = unsafe { MmapMut::map_mut(&file)? };
+        Ok(Self { file: Some(file), mmap: Some(Mapping::File(mmap)), dirty: DirtyPages::default() })
+    }
+
+    /// Opens the graph file at `path` for a shared reader
+    fn open_shared(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .open(path)
+            .with_context(|| format!("Failed to open graph file: {}", path.display()))?;
+        file.try_lock_shared().context(Tagged::new(
+            ErrorKind::Locked,
+            "Chassis graph file is open for writing by another process",
+        ))?;
+
+        // SAFETY: The shared lock excludes writers for the lifetime of the mapping.
+        let mmap = unsafe { MmapOptions::new().map_copy(&file)? };
+        Ok(Self { file: Some(file), mmap: Some(Mapping::File(mmap)), dirty: DirtyPages::default() })
+    }
+
+    /// Opens the graph file at `path` of a sealed index, read-only and unlocked
+    fn open_sealed(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .open(path)
+            .with_context(|| format!("Failed to open graph file: {}", path.display()))?;
+
+        // SAFETY: A sealed file is never written while mapped (see `Storage::open_sealed`).
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(Self {
+            file: Some(file),
+            mmap: Some(Mapping::Sealed(mmap)),
+            dirty: DirtyPages::default(),
+        })
+    }
 
-    /// Memory-mapped view of the file (`None` only transiently during resize on Windows).
-    mmap: Option<MmapMut>,
+    /// Private view of this graph file for a fork (see `Storage::fork()`)
+    fn fork(&self) -> Result<Self> {
+        let mmap = fork_mapping(self.file.as_ref(), self.mapped())?;
+        Ok(Self { file: None, mmap: Some(mmap), dirty: DirtyPages::default() })
+    }
+
+    fn mapped(&self) -> &Mapping {
+        self.mmap.as_ref().expect("graph file must hold an active mmap")
+    }
+
+    /// Synchronously flushes the pages written since the last commit
+    fn flush_dirty(&self) -> Result<()> {
+        let mapped = self.mapped();
+        for range in self.dirty.ranges() {
+            let end = range.end.min(mapped.len());
+            mapped.flush_range(range.start, end.saturating_sub(range.start))?;
+        }
+        Ok(())
+    }
+
+    /// Resizes the file to `new_len` bytes and remaps it
+    fn resize(&mut self, new_len: usize) -> Result<()> {
+        self.flush_dirty()?;
+
+        let Some(file) = &self.file else {
+            let grown = private_copy(self.mapped(), new_len)?;
+            self.mmap = Some(Mapping::Private(grown));
+            return Ok(());
+        };
+
+        // Unmap first, as for the index file (Windows refuses to resize mapped files)
+        drop(self.mmap.take());
+        let resized = file.set_len(new_len as u64);
+        self.mmap = Some(Mapping::File(unsafe { MmapMut::map_mut(file)? }));
+        resized.context("Failed to resize graph file")
+    }
+
+    /// Flushes the dirty pages and syncs the file to disk
+    fn commit(&mut self) -> Result<()> {
+        self.flush_dirty()?;
+        self.dirty.clear();
+        if let Some(file) = &self.file {
+            file.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+/// Path of the graph file kept next to the index file at `path`
+///
+/// The file name gets a `-graph` suffix: `notes.chassis` keeps its graph in
+/// `notes.chassis-graph`.
+#[cfg(not(target_arch = "wasm32"))]
+#[must_use]
+pub fn graph_file_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push("-graph");
+    PathBuf::from(name)
+}
+
+/// Placement of an index image inside a collection file
+///
+/// The image is mapped from `base`; the file reserves `capacity` bytes there.
+/// When the image outgrows the reservation it is copied to a new, larger
+/// region at the end of the file. The old region is left untouched, so it
+/// stays the durable copy until `commit()` publishes the new placement in the
+/// catalog slot.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Window {
+    /// File offset of this image's `(base, capacity, len)` catalog fields
+    pub(crate) slot: u64,
+
+    /// File offset of the image (page-aligned)
+    pub(crate) base: u64,
+
+    /// Bytes reserved for the image at `base`
+    pub(crate) capacity: u64,
+}
+
+/// Path and identity (device + inode, or volume + file index) of an opened file
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+struct FileOrigin {
+    path: PathBuf,
+    handle: Handle,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileOrigin {
+    fn new(path: &Path, file: &File) -> Result<Self> {
+        let handle =
+            Handle::from_file(file.try_clone()?).context("Failed to read index file identity")?;
+        Ok(Self { path: path.to_path_buf(), handle })
+    }
 }
 
 impl Storage {
     #[inline]
-    fn mapped(&self) -> &MmapMut {
+    fn mapped(&self) -> &Mapping {
         self.mmap.as_ref().expect("storage must hold an active mmap")
     }
 
     #[inline]
-    fn mapped_mut(&mut self) -> &mut MmapMut {
+    fn mapped_mut(&mut self) -> &mut Mapping {
         self.mmap.as_mut().expect("storage must hold an active mmap")
     }
 
@@ -44,25 +326,55 @@ impl Storage {
     /// - The file is already locked by another process
     /// - The file exists but has different dimensions
     /// - The file is corrupted
+    #[cfg(not(target_arch = "wasm32"))]
     pub fn open<P: AsRef<Path>>(path: P, dimensions: u32) -> Result<Self> {
-        let path = path.as_ref();
+        Self::open_file(path.as_ref(), Some(dimensions))
+    }
 
+    /// Opens an existing Chassis index file, taking its dimensions from the header
+    ///
+    /// Like `open()`, but never creates a file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The file does not exist or cannot be opened
+    /// - The file is already locked by another process
+    /// - The file is not a valid Chassis index
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_existing<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_file(path.as_ref(), None)
+    }
+
+    /// Opens a writable index file; creates it only if `dimensions` is given
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_file(path: &Path, dimensions: Option<u32>) -> Result<Self> {
         let file = OpenOptions::new()
             .read(true)
             .write(true)
-            .create(true)
+            .create(dimensions.is_some())
             .truncate(false)
             .open(path)
             .with_context(|| format!("Failed to open chassis file: {}", path.display()))?;
 
         // CRITICAL: Exclusive file locking prevents concurrent access corruption
-        file.try_lock_exclusive().context("Chassis file is already open by another process")?;
+        file.try_lock_exclusive().context(Tagged::new(
+            ErrorKind::Locked,
+            "Chassis file is already open by another process",
+        ))?;
 
         let needs_init = file.metadata().map(|m| m.len() < HEADER_SIZE as u64).unwrap_or(true);
 
         if needs_init {
+            let Some(dimensions) = dimensions else {
+                anyhow::bail!(Tagged::new(
+                    ErrorKind::Corrupted,
+                    "File is not a valid Chassis index"
+                ));
+            };
+
             // Initialize new file with header
-            let header = Header::new(dimensions);
+            let header = new_header(dimensions);
             file.set_len(HEADER_SIZE as u64)?;
 
             unsafe {
@@ -76,25 +388,1007 @@ impl Storage {
         let mmap = unsafe { MmapMut::map_mut(&file)? };
 
         // Validate file header
-        if mmap.len() < MAGIC.len() || &mmap[..MAGIC.len()] != MAGIC {
-            anyhow::bail!("File is not a valid Chassis index");
+        let dimensions = match dimensions {
+            Some(dimensions) => dimensions,
+            None => Self::read_image_header(&mmap)?.dimensions,
+        };
+        Self::validate_image(&mmap, dimensions)?;
+
+        let origin = FileOrigin::new(path, &file)?;
+        let graph_file = Self::read_image_header(&mmap)?
+            .graph_file()
+            .then(|| GraphFile::open(&graph_file_path(path), false))
+            .transpose()?;
+
+        Ok(Self {
+            file: Some(file),
+            mmap: Some(Mapping::File(mmap)),
+            shared_reader: false,
+            memory_mode: MemoryMode::Normal,
+            growth_chunk: PAGE_SIZE,
+            max_file_bytes: None,
+            remaps: 0,
+            origin: Some(origin),
+            window: None,
+            dirty: DirtyPages::default(),
+            background: None,
+            graph_file,
+            #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+            encrypted: None,
+        })
+    }
+
+    /// Opens an existing Chassis index file for reading alongside other processes
+    ///
+    /// Takes a shared lock (`LockFileEx` without `LOCKFILE_EXCLUSIVE_LOCK` on
+    /// Windows, `flock(LOCK_SH)` on Unix), so any number of processes can open
+    /// the file this way while `open()` (exclusive) is refused, and vice versa.
+    ///
+    /// The file handle is read-only and is mapped copy-on-write (a
+    /// `FILE_MAP_COPY` view on Windows, `MAP_PRIVATE` on Unix). In-memory
+    /// adjustments made while opening, such as ghost node rollback, stay private
+    /// to this process and never reach the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The file does not exist or cannot be opened
+    /// - A writer holds the exclusive lock
+    /// - The file has different dimensions or is corrupted
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_shared<P: AsRef<Path>>(path: P, dimensions: u32) -> Result<Self> {
+        Self::open_shared_file(path.as_ref(), Some(dimensions))
+    }
+
+    /// Opens an index file like `open_shared()`, taking its dimensions from the header
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn open_shared_existing(path: &Path) -> Result<Self> {
+        Self::open_shared_file(path, None)
+    }
+
+    /// Opens a shared reader, checking the dimensions if they are given
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_shared_file(path: &Path, dimensions: Option<u32>) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .open(path)
+            .with_context(|| format!("Failed to open chassis file: {}", path.display()))?;
+
+        file.try_lock_shared().context(Tagged::new(
+            ErrorKind::Locked,
+            "Chassis file is open for writing by another process",
+        ))?;
+
+        // SAFETY: The shared lock excludes writers for the lifetime of the mapping.
+        let mmap = unsafe { MmapOptions::new().map_copy(&file)? };
+
+        // Validate file header
+        let dimensions = match dimensions {
+            Some(dimensions) => dimensions,
+            None => Self::read_image_header(&mmap)?.dimensions,
+        };
+        Self::validate_image(&mmap, dimensions)?;
+        let graph_file = Self::read_image_header(&mmap)?
+            .graph_file()
+            .then(|| GraphFile::open_shared(&graph_file_path(path)))
+            .transpose()?;
+
+        Ok(Self {
+            file: Some(file),
+            mmap: Some(Mapping::File(mmap)),
+            shared_reader: true,
+            memory_mode: MemoryMode::Normal,
+            growth_chunk: PAGE_SIZE,
+            max_file_bytes: None,
+            remaps: 0,
+            origin: None,
+            window: None,
+            dirty: DirtyPages::default(),
+            background: None,
+            graph_file,
+            #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+            encrypted: None,
+        })
+    }
+
+    /// Opens a finished index file that is never written, such as one shipped
+    /// inside an app bundle
+    ///
+    /// The file is opened read-only and mapped without write access
+    /// (`PROT_READ` on Unix, a `FILE_MAP_READ` view on Windows). No lock is
+    /// taken, so any number of processes and handles can map the file, but
+    /// it must not be modified while mapped. Nothing is adjusted in memory
+    /// either: the file must hold a complete graph without ghost vectors,
+    /// as left by a `flush()` after the last insert. Every write is refused
+    /// with `ReadOnly`, as for `open_shared()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file (or its graph file) does not exist or is
+    /// not a Chassis index, or if it has no graph zone.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_sealed<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+
+        let file = OpenOptions::new()
+            .read(true)
+            .open(path)
+            .with_context(|| format!("Failed to open chassis file: {}", path.display()))?;
+
+        // SAFETY: Sealed files are immutable by contract; see above.
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        let header = Self::read_image_header(&mmap)?;
+        if header.graph_offset().is_none() && !header.graph_file() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                "Sealed index has no graph zone (it was never flushed by a writer)"
+            ));
+        }
+        let graph_file = header
+            .graph_file()
+            .then(|| GraphFile::open_sealed(&graph_file_path(path)))
+            .transpose()?;
+
+        Ok(Self {
+            file: Some(file),
+            mmap: Some(Mapping::Sealed(mmap)),
+            shared_reader: true,
+            memory_mode: MemoryMode::Normal,
+            growth_chunk: PAGE_SIZE,
+            max_file_bytes: None,
+            remaps: 0,
+            origin: None,
+            window: None,
+            dirty: DirtyPages::default(),
+            background: None,
+            graph_file,
+            #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+            encrypted: None,
+        })
+    }
+
+    /// Reads a finished index image in place, such as one compiled into the
+    /// binary with `include_index!`
+    ///
+    /// The sealed counterpart for images without a file: nothing is copied
+    /// or mapped, and every write is refused with `ReadOnly`. The image is
+    /// read through `&Header` and `&[f32]` casts, so it must start on a
+    /// 4 KiB boundary, as `include_index!` guarantees.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `bytes` is not 4 KiB-aligned, is not a Chassis
+    /// index, has no graph zone, or keeps its graph in a separate file.
+    pub fn from_static(bytes: &'static [u8]) -> Result<Self> {
+        if !bytes.as_ptr().cast::<Header>().is_aligned() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Embedded index image must start on a 4 KiB boundary; embed it with include_index!"
+            ));
         }
 
-        let header = unsafe { &*(mmap.as_ptr() as *const Header) };
+        let header = Self::read_image_header(bytes)?;
+        if header.graph_file() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Embedded index keeps its graph in a separate file"
+            ));
+        }
+        if header.graph_offset().is_none() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                "Embedded index has no graph zone (it was never flushed by a writer)"
+            ));
+        }
 
-        if !header.is_valid() {
-            anyhow::bail!("Corrupted or incompatible Chassis file at {}", path.display());
+        Ok(Self {
+            file: None,
+            mmap: Some(Mapping::Static(bytes)),
+            shared_reader: true,
+            memory_mode: MemoryMode::Normal,
+            growth_chunk: PAGE_SIZE,
+            max_file_bytes: None,
+            remaps: 0,
+            #[cfg(not(target_arch = "wasm32"))]
+            origin: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            window: None,
+            dirty: DirtyPages::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            background: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            graph_file: None,
+            #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+            encrypted: None,
+        })
+    }
+
+    /// Maps the index image stored in a region of a collection file
+    ///
+    /// `file` must be the collection file, opened for writing and locked by
+    /// the caller; this storage uses a clone of the handle and never locks or
+    /// unlocks it. The image spans `len` bytes at `window.base`. With `create`,
+    /// a fresh header is written first; the caller must have extended the file
+    /// to cover the window.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the window lies outside the file, the image is not
+    /// a valid index with `dimensions`, or the mapping fails.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn open_window(
+        path: &Path,
+        file: &File,
+        window: Window,
+        len: u64,
+        dimensions: u32,
+        create: bool,
+    ) -> Result<Self> {
+        let file = file.try_clone().context("Failed to clone collection file handle")?;
+        let end = window.base.checked_add(window.capacity).context("Window end overflow")?;
+        if len > window.capacity || end > file.metadata()?.len() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                format!(
+                    "Collection window out of bounds: base={}, capacity={}, len={}",
+                    window.base, window.capacity, len
+                )
+            ));
+        }
+
+        let len = if create {
+            let header = new_header(dimensions);
+            let mut mmap = map_window(&file, window.base, HEADER_SIZE)?;
+            mmap[..HEADER_SIZE].copy_from_slice(header.as_bytes());
+            mmap.flush()?;
+            HEADER_SIZE
+        } else {
+            usize::try_from(len).context("Collection too large for this platform")?
+        };
+
+        let mmap = map_window(&file, window.base, len)?;
+        Self::validate_image(&mmap, dimensions)?;
+
+        let origin = FileOrigin::new(path, &file)?;
+        Ok(Self {
+            file: Some(file),
+            mmap: Some(Mapping::File(mmap)),
+            shared_reader: false,
+            memory_mode: MemoryMode::Normal,
+            growth_chunk: PAGE_SIZE,
+            max_file_bytes: None,
+            remaps: 0,
+            origin: Some(origin),
+            window: Some(window),
+            dirty: DirtyPages::default(),
+            background: None,
+            graph_file: None,
+            #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+            encrypted: None,
+        })
+    }
+
+    /// Fails with a descriptive error if this storage lives in a collection file
+    #[cfg(not(target_arch = "wasm32"))]
+    fn ensure_whole_file(&self, action: &str) -> Result<()> {
+        if self.window.is_some() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!("Cannot {} a single collection; use the collection file instead", action)
+            ));
+        }
+        Ok(())
+    }
+
+    /// Keeps the graph zone of this new index in a separate file
+    ///
+    /// Creates the graph file at `graph_file_path()` (replacing any file left
+    /// there) and records it in the header. Only possible before the graph
+    /// zone is placed; from then on the index opens with its graph file, and
+    /// `commit()` syncs the index file before the graph file, so the graph
+    /// never references vectors that are not durable.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidArgument` for in-memory storage, collections and
+    /// indexes that already have a graph zone, or an error if the graph file
+    /// cannot be created or locked.
+    pub fn use_graph_file(&mut self) -> Result<()> {
+        self.ensure_writable("create a graph file")?;
+        if self.graph_offset().is_some() || self.count() > 0 {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Only a new index can move its graph to a separate file"
+            ));
         }
 
+        if self.is_encrypted() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Encrypted indexes keep the graph in the index file"
+            ));
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(origin) = &self.origin
+            && self.window.is_none()
+        {
+            let graph_file = GraphFile::open(&graph_file_path(&origin.path), true)?;
+            graph_file.mapped().advise(self.memory_mode).context("Failed to apply memory mode")?;
+            self.graph_file = Some(graph_file);
+            self.header_mut().set_graph_file();
+            return Ok(());
+        }
+
+        anyhow::bail!(Tagged::new(
+            ErrorKind::InvalidArgument,
+            "Only standalone index files can keep the graph in a separate file"
+        ))
+    }
+
+    /// Returns true if the graph zone is stored in a separate graph file
+    pub fn has_graph_file(&self) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.graph_file.is_some();
+        #[cfg(target_arch = "wasm32")]
+        false
+    }
+
+    /// Returns true if this storage was opened with `open_shared` or `open_sealed`
+    pub fn is_shared_reader(&self) -> bool {
+        self.shared_reader
+    }
+
+    /// Returns true if this storage was opened with `open_sealed`
+    pub fn is_sealed(&self) -> bool {
+        self.mmap.as_ref().is_some_and(Mapping::is_sealed)
+    }
+
+    /// Returns true if this storage lives in memory rather than in a file
+    pub fn is_in_memory(&self) -> bool {
+        self.file.is_none() && !self.is_encrypted()
+    }
+
+    /// Returns true if this storage was opened with `open_encrypted`
+    pub fn is_encrypted(&self) -> bool {
+        #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+        return self.encrypted.is_some();
+        #[cfg(not(all(feature = "encryption", not(target_arch = "wasm32"))))]
+        false
+    }
+
+    /// Checks that the opened path still refers to the file this storage holds
+    ///
+    /// # Errors
+    ///
+    /// Returns a `FileStolen` error if the file was deleted or replaced.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn verify_origin(&self) -> Result<()> {
+        let Some(origin) = &self.origin else {
+            return Ok(());
+        };
+
+        match Handle::from_path(&origin.path) {
+            Ok(handle) if handle == origin.handle => Ok(()),
+            Ok(_) => Err(FileStolen { path: origin.path.clone(), deleted: false }.into()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(FileStolen { path: origin.path.clone(), deleted: true }.into())
+            }
+            Err(e) => Err(e).context("Failed to check index file identity"),
+        }
+    }
+
+    /// Rebinds this storage to `path` after its file was moved, replaced, or deleted
+    ///
+    /// If `path` is the file this storage already holds (it was moved there),
+    /// it is adopted as-is. Otherwise the current in-memory image is written to
+    /// `path`, overwriting whatever is there, and the lock on the old file is
+    /// released. Call `commit()` afterwards to make the image durable.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the storage is not file-backed or writable, or if
+    /// `path` cannot be created, locked, or written.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn reattach<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.ensure_writable("reattach")?;
+        self.ensure_whole_file("reattach")?;
+        if self.is_encrypted() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Encrypted indexes cannot be reattached"
+            ));
+        }
+        if self.graph_file.is_some() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Indexes with a separate graph file cannot be reattached"
+            ));
+        }
+        self.wait_for_background()?;
+        let Some(origin) = &self.origin else {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Only file-backed storage can be reattached"
+            ));
+        };
+        let path = path.as_ref();
+
+        if let Ok(handle) = Handle::from_path(path)
+            && handle == origin.handle
+        {
+            self.origin = Some(FileOrigin { path: path.to_path_buf(), handle });
+            return Ok(());
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("Failed to open chassis file: {}", path.display()))?;
+        file.try_lock_exclusive().context(Tagged::new(
+            ErrorKind::Locked,
+            "Chassis file is already open by another process",
+        ))?;
+
+        let image = self.mapped();
+        file.set_len(image.len() as u64)?;
+        let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+        mmap.copy_from_slice(image);
+        self.dirty.mark(0, mmap.len());
+
+        let origin = FileOrigin::new(path, &file)?;
+        self.mmap = Some(Mapping::File(mmap));
+        if let Some(old) = self.file.replace(file) {
+            let _ = old.unlock();
+        }
+        self.origin = Some(origin);
+
+        self.apply_memory_mode()
+    }
+
+    /// Writes a copy of the file image to `path`
+    ///
+    /// The copy is written to a temporary file next to `path`, synced, and
+    /// renamed into place, so `path` holds either the previous file or a
+    /// complete snapshot, even across a crash. On filesystems that support it
+    /// the copy is a reflink (`FICLONE` on Linux, `clonefile` on Apple
+    /// platforms) that shares blocks with the original until either changes.
+    ///
+    /// The caller must `commit()` first for writable storage; the snapshot is
+    /// exactly what a reopen of the file would see. A graph file is copied
+    /// next to `path` first (see `graph_file_path()`), then the index file.
+    /// Encrypted storage copies its file as is, so the snapshot opens with
+    /// the same key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the storage is not file-backed, `path` is this
+    /// storage's own file, or the copy cannot be written.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn snapshot_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.ensure_whole_file("snapshot")?;
+        #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+        if let Some(encrypted) = &self.encrypted {
+            let path = path.as_ref();
+            if let Ok(handle) = Handle::from_path(path)
+                && handle == Handle::from_file(encrypted.file().try_clone()?)?
+            {
+                anyhow::bail!(Tagged::new(
+                    ErrorKind::InvalidArgument,
+                    "Cannot snapshot an index onto its own file"
+                ));
+            }
+            return Self::write_snapshot(Some(encrypted.file()), &encrypted.read_all()?, path);
+        }
+
+        let Some(file) = &self.file else {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Only file-backed storage can be snapshotted"
+            ));
+        };
+        let path = path.as_ref();
+
+        if let Ok(handle) = Handle::from_path(path)
+            && handle == Handle::from_file(file.try_clone()?)?
+        {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Cannot snapshot an index onto its own file"
+            ));
+        }
+
+        // The index file is renamed last: it records that the graph file exists
+        if let Some(graph_file) = &self.graph_file {
+            let graph_path = graph_file_path(path);
+            Self::write_snapshot(graph_file.file.as_ref(), graph_file.mapped(), &graph_path)?;
+        }
+        Self::write_snapshot(Some(file), self.mapped(), path)
+    }
+
+    /// Writes the image of in-memory storage to a new index file at `path`
+    ///
+    /// The caller must `commit()` first. Like `snapshot_to()`, the file is
+    /// staged under a temporary name and renamed into place, replacing any
+    /// file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the storage is file-backed or the file cannot be
+    /// written.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn persist_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        if !self.is_in_memory() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Only in-memory storage can be persisted; snapshot file-backed storage instead"
+            ));
+        }
+        let path = path.as_ref();
+
+        // The index file is renamed last: it records that the graph file exists
+        if let Some(graph_file) = &self.graph_file {
+            Self::write_snapshot(None, graph_file.mapped(), &graph_file_path(path))?;
+        }
+        Self::write_snapshot(None, self.mapped(), path)
+    }
+
+    /// Creates a private, writable copy of this storage that never writes back
+    ///
+    /// File-backed storage (and its graph file) is mapped again copy-on-write:
+    /// the fork shares the file's pages until it writes them, and moves into
+    /// anonymous memory once it grows. In-memory storage is copied. The fork
+    /// takes no lock; the caller must keep the file unchanged while the fork
+    /// is alive, since pages the fork has not written yet still show the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for collection images or if the OS refuses the mapping.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn fork(&self) -> Result<Self> {
+        self.ensure_whole_file("fork")?;
+        self.unseal(0..self.mapped().len())?;
+        let graph_file = self.graph_file.as_ref().map(GraphFile::fork).transpose()?;
+        Ok(Self {
+            file: None,
+            mmap: Some(fork_mapping(self.file.as_ref(), self.mapped())?),
+            shared_reader: false,
+            memory_mode: self.memory_mode,
+            growth_chunk: self.growth_chunk,
+            max_file_bytes: self.max_file_bytes,
+            remaps: 0,
+            origin: None,
+            window: None,
+            dirty: DirtyPages::default(),
+            background: None,
+            graph_file,
+            #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+            encrypted: None,
+        })
+    }
+
+    /// Writes a read-only copy of this index with a graph zone of
+    /// `graph_len` bytes written by `write_graph` to `path`
+    ///
+    /// The copy holds the first `count` vectors, the graph right after them,
+    /// and the metadata zone after the graph, each starting on a page; the
+    /// header records a compressed graph in the index file. Like
+    /// `snapshot_to()`, the file is staged under a temporary name and renamed
+    /// into place.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the storage is encrypted or a collection image,
+    /// `path` is this storage's own file, `write_graph` fails or writes
+    /// another length, or the file cannot be written.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn write_compressed_copy(
+        &self,
+        count: u64,
+        graph_len: u64,
+        write_graph: impl FnOnce(&mut dyn std::io::Write) -> Result<()>,
+        path: &Path,
+    ) -> Result<()> {
+        self.ensure_whole_file("compress")?;
+        #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+        if self.encrypted.is_some() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Cannot write a compressed copy of an encrypted index"
+            ));
+        }
+        if let Some(file) = &self.file
+            && let Ok(handle) = Handle::from_path(path)
+            && handle == Handle::from_file(file.try_clone()?)?
+        {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Cannot write a compressed copy of an index onto its own file"
+            ));
+        }
+
+        self.write_packed_copy(count, graph_len, true, write_graph, path)
+    }
+
+    /// Writes in-memory storage to `path` with its zones packed, for an index
+    /// whose graph zone holds `graph_len` bytes
+    ///
+    /// Unlike `persist_to()`, which writes the image as it is, the file leaves
+    /// no room for growth: the graph zone follows the first `count` vectors
+    /// and the metadata zone follows the graph, each starting on the next
+    /// page. The first insert after reopening moves the graph zone out of the
+    /// way. Storage with a graph file is written as it is.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the storage is file-backed or the file cannot be
+    /// written.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn persist_packed_to(&self, count: u64, graph_len: u64, path: &Path) -> Result<()> {
+        let Some(graph_offset) = self.graph_offset().filter(|_| !self.has_graph_file()) else {
+            return self.persist_to(path);
+        };
+        if !self.is_in_memory() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Only in-memory storage can be persisted; snapshot file-backed storage instead"
+            ));
+        }
+
+        let graph_offset = usize::try_from(graph_offset).context("Graph offset too large")?;
+        let graph_end = usize::try_from(graph_len)
+            .ok()
+            .and_then(|len| graph_offset.checked_add(len))
+            .context("Graph zone end overflow")?;
+        let graph =
+            self.mapped().get(graph_offset..graph_end).context("Graph zone out of bounds")?;
+        self.write_packed_copy(count, graph_len, false, |out| Ok(out.write_all(graph)?), path)
+    }
+
+    /// Writes a copy holding the first `count` vectors, a graph zone of
+    /// `graph_len` bytes written by `write_graph`, and the metadata zone,
+    /// each starting on the page after the previous one
+    #[cfg(not(target_arch = "wasm32"))]
+    fn write_packed_copy(
+        &self,
+        count: u64,
+        graph_len: u64,
+        compressed: bool,
+        write_graph: impl FnOnce(&mut dyn std::io::Write) -> Result<()>,
+        path: &Path,
+    ) -> Result<()> {
+        use std::io::{BufWriter, Read, Seek, Write};
+
+        let vector_end = self.vector_end_for_count(count)?;
+        let graph_offset = self.zone_align(vector_end);
+        let graph_len = usize::try_from(graph_len).context("Graph too large for this platform")?;
+        let graph_end = graph_offset.checked_add(graph_len).context("Graph zone end overflow")?;
+        let metadata_offset = self.zone_align(graph_end);
+        let metadata = self.metadata_zone_bytes()?;
+
+        let mut header = Header::new(self.dimensions());
+        header.copy_from(self.header());
+        header.count = count;
+        header.set_graph_offset(graph_offset as u64);
+        if compressed {
+            header.set_compressed_graph();
+        }
+        header.set_writer_open(false);
+        if let Some(zone) = metadata {
+            header.set_metadata_zone(metadata_offset as u64, zone.len() as u64);
+            header.set_previous_metadata_zone(None);
+            header.set_metadata_floor(metadata_offset as u64);
+            header.set_metadata_epoch(0);
+        }
+
+        let zeros = |out: &mut BufWriter<File>, len: usize| -> Result<()> {
+            std::io::copy(&mut std::io::repeat(0).take(len as u64), out)?;
+            Ok(())
+        };
+        Self::stage_snapshot(path, |tmp| {
+            let file = OpenOptions::new().write(true).create(true).truncate(true).open(tmp)?;
+            let mut out = BufWriter::new(file);
+            out.write_all(header.as_bytes())?;
+            out.write_all(&self.mapped()[HEADER_SIZE..vector_end])?;
+            zeros(&mut out, graph_offset - vector_end)?;
+            write_graph(&mut out)?;
+            if out.stream_position()? != graph_end as u64 {
+                anyhow::bail!("Graph zone does not have the expected {} bytes", graph_len);
+            }
+            if let Some(zone) = metadata {
+                zeros(&mut out, metadata_offset - graph_end)?;
+                out.write_all(zone)?;
+            }
+            out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+            Ok(())
+        })
+    }
+
+    /// Copies `image` (the mapping of `file`, if any) to a temporary file,
+    /// syncs it and renames it to `path`
+    #[cfg(not(target_arch = "wasm32"))]
+    fn write_snapshot(file: Option<&File>, image: &[u8], path: &Path) -> Result<()> {
+        Self::stage_snapshot(path, |tmp| Self::write_snapshot_image(file, image, tmp))
+    }
+
+    /// Has `write` create and sync a temporary file next to `path`, then
+    /// renames it to `path`
+    #[cfg(not(target_arch = "wasm32"))]
+    fn stage_snapshot(path: &Path, write: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+        let Some(name) = path.file_name() else {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!("Snapshot path has no file name: {}", path.display())
+            ));
+        };
+        let mut tmp_name = std::ffi::OsString::from(".");
+        tmp_name.push(name);
+        tmp_name.push(".snapshot-tmp");
+        let tmp = path.with_file_name(tmp_name);
+
+        let result = write(&tmp).and_then(|()| {
+            std::fs::rename(&tmp, path)?;
+            sync_parent_dir(path)
+        });
+        if result.is_err() {
+            let _ = std::fs::remove_file(&tmp);
+        }
+        result.with_context(|| format!("Failed to write snapshot: {}", path.display()))
+    }
+
+    /// Writes and syncs the image at `tmp`, by reflink of `file` if possible
+    #[cfg(not(target_arch = "wasm32"))]
+    fn write_snapshot_image(file: Option<&File>, image: &[u8], tmp: &Path) -> Result<()> {
+        use std::io::Write;
+
+        let _ = std::fs::remove_file(tmp);
+        let out = if file.is_some_and(|file| reflink(file, tmp)) {
+            OpenOptions::new().write(true).open(tmp)?
+        } else {
+            let mut out = OpenOptions::new().write(true).create(true).truncate(true).open(tmp)?;
+            out.write_all(image)?;
+            out
+        };
+        out.sync_all()?;
+        Ok(())
+    }
+
+    /// Fails with a descriptive error if this storage is a shared reader
+    pub(crate) fn ensure_writable(&self, action: &str) -> Result<()> {
+        if self.shared_reader {
+            let mode = if self.is_sealed() { "sealed" } else { "a shared reader" };
+            anyhow::bail!(Tagged::new(
+                ErrorKind::ReadOnly,
+                format!("Cannot {}: index is opened as {}", action, mode)
+            ));
+        }
+        Ok(())
+    }
+
+    /// Creates an empty index image in a heap buffer instead of a file
+    ///
+    /// This is the storage backend for browsers (`wasm32-unknown-unknown`),
+    /// where neither `mmap` nor file locks exist. The layout is byte-for-byte
+    /// identical to the on-disk format, so `as_bytes()` can be persisted (for
+    /// example to OPFS) and reloaded with `from_bytes()`.
+    #[cfg(feature = "wasm")]
+    #[must_use]
+    pub fn open_in_memory(dimensions: u32) -> Self {
+        let header = new_header(dimensions);
+        let buffer = PageBuffer::from_bytes(header.as_bytes());
+        Self {
+            file: None,
+            mmap: Some(Mapping::Memory(buffer)),
+            shared_reader: false,
+            memory_mode: MemoryMode::Normal,
+            growth_chunk: PAGE_SIZE,
+            max_file_bytes: None,
+            remaps: 0,
+            #[cfg(not(target_arch = "wasm32"))]
+            origin: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            window: None,
+            dirty: DirtyPages::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            background: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            graph_file: None,
+            #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+            encrypted: None,
+        }
+    }
+
+    /// Creates an empty index image in an anonymous memory mapping
+    ///
+    /// The storage behaves like a file that is never written: it has no lock
+    /// and nothing to sync, and its contents are lost when it is dropped
+    /// unless `persist_to()` writes them to a file first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the OS refuses the mapping.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_anonymous(dimensions: u32) -> Result<Self> {
+        let header = new_header(dimensions);
+        let bytes = header.as_bytes();
+        let mmap = private_copy(bytes, Self::page_align(bytes.len()))?;
+        Ok(Self {
+            file: None,
+            mmap: Some(Mapping::Private(mmap)),
+            shared_reader: false,
+            memory_mode: MemoryMode::Normal,
+            growth_chunk: PAGE_SIZE,
+            max_file_bytes: None,
+            remaps: 0,
+            origin: None,
+            window: None,
+            dirty: DirtyPages::default(),
+            background: None,
+            graph_file: None,
+            #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+            encrypted: None,
+        })
+    }
+
+    /// Opens or creates an index file encrypted with `key`
+    ///
+    /// The storage works on a plaintext image in anonymous memory, like a
+    /// memory-only index. Opening decrypts only the header; every other block
+    /// is decrypted the first time it is read or written, so memory use
+    /// follows the part of the index that is used. `commit()` encrypts the
+    /// pages written since the last commit into the file and syncs it. The
+    /// file is locked like one opened with `open()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The file cannot be opened, or is already locked
+    /// - The file is not an encrypted index, or a block fails authentication
+    /// - `key` is not the key the file was created with (`InvalidArgument`)
+    /// - The file has different dimensions
+    #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+    pub fn open_encrypted<P: AsRef<Path>>(
+        path: P,
+        dimensions: u32,
+        key: &EncryptionKey,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let encrypted = EncryptedFile::open(path, key)?;
+        let created = encrypted.image_len() == 0;
+
+        let len = if created { HEADER_SIZE } else { encrypted.image_len() };
+        let mmap = MmapRaw::from(
+            MmapMut::map_anon(Self::page_align(len)).context("Failed to map anonymous memory")?,
+        );
+        if created {
+            // SAFETY: The mapping is at least a header long and not shared yet
+            unsafe { std::slice::from_raw_parts_mut(mmap.as_mut_ptr(), HEADER_SIZE) }
+                .copy_from_slice(new_header(dimensions).as_bytes());
+        } else {
+            // SAFETY: The mapping is `mmap.len()` bytes long and not shared yet
+            unsafe { encrypted.unseal(mmap.as_mut_ptr(), mmap.len(), 0..HEADER_SIZE)? };
+        }
+        // SAFETY: The header block was written or decrypted above
+        let header = unsafe { std::slice::from_raw_parts(mmap.as_ptr(), HEADER_SIZE) };
+        Self::validate_image(header, dimensions)?;
+
+        let origin = FileOrigin::new(path, encrypted.file())?;
+        let mut storage = Self {
+            file: None,
+            mmap: Some(Mapping::Decrypted(mmap)),
+            shared_reader: false,
+            memory_mode: MemoryMode::Normal,
+            growth_chunk: PAGE_SIZE,
+            max_file_bytes: None,
+            remaps: 0,
+            origin: Some(origin),
+            window: None,
+            dirty: DirtyPages::default(),
+            background: None,
+            graph_file: None,
+            encrypted: Some(encrypted),
+        };
+        if created {
+            storage.commit()?;
+        }
+        Ok(storage)
+    }
+
+    /// Loads an index image previously produced by `as_bytes()` (or read from an index file)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image is not a valid Chassis index or has
+    /// different dimensions.
+    #[cfg(feature = "wasm")]
+    pub fn from_bytes(bytes: &[u8], dimensions: u32) -> Result<Self> {
+        Self::validate_image(bytes, dimensions)?;
+        Ok(Self {
+            file: None,
+            mmap: Some(Mapping::Memory(PageBuffer::from_bytes(bytes))),
+            shared_reader: false,
+            memory_mode: MemoryMode::Normal,
+            growth_chunk: PAGE_SIZE,
+            max_file_bytes: None,
+            remaps: 0,
+            #[cfg(not(target_arch = "wasm32"))]
+            origin: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            window: None,
+            dirty: DirtyPages::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            background: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            graph_file: None,
+            #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+            encrypted: None,
+        })
+    }
+
+    /// Returns the full index image (header, vectors, graph and metadata)
+    ///
+    /// For in-memory storage this is the only way to persist the index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the storage is encrypted and a block of its file fails
+    /// authentication.
+    #[cfg(feature = "wasm")]
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.unseal(0..self.mapped().len()).expect("encrypted blocks must authenticate");
+        self.mapped()
+    }
+
+    /// Validates the header at the start of an index image
+    fn validate_image(image: &[u8], dimensions: u32) -> Result<()> {
+        let header = Self::read_image_header(image)?;
+
         if header.dimensions != dimensions {
-            anyhow::bail!(
-                "Dimension mismatch: file has {}, requested {}",
-                header.dimensions,
-                dimensions
-            );
+            anyhow::bail!(Tagged::new(
+                ErrorKind::DimensionMismatch,
+                format!(
+                    "Dimension mismatch: file has {}, requested {}",
+                    header.dimensions, dimensions
+                )
+            ));
         }
 
-        Ok(Self { file, mmap: Some(mmap) })
+        Ok(())
+    }
+
+    /// Reads and checks the header at the start of an index image
+    fn read_image_header(image: &[u8]) -> Result<Header> {
+        if image.len() < HEADER_SIZE || &image[..MAGIC.len()] != MAGIC {
+            anyhow::bail!(Tagged::new(ErrorKind::Corrupted, "File is not a valid Chassis index"));
+        }
+
+        // SAFETY: length checked above; `read_unaligned` does not require the
+        // caller's buffer to honour `Header`'s 4096-byte alignment.
+        let header = unsafe { std::ptr::read_unaligned(image.as_ptr().cast::<Header>()) };
+
+        if header.version > VERSION {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::IncompatibleVersion,
+                format!(
+                    "Index file format version {} is newer than this library reads ({}); upgrade Chassis to open it",
+                    header.version, VERSION
+                )
+            ));
+        }
+
+        if !header.is_valid() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                "Corrupted or incompatible Chassis file"
+            ));
+        }
+
+        Ok(header)
     }
 
     /// Inserts a vector into the storage
@@ -118,54 +1412,349 @@ impl Storage {
     /// This method does NOT guarantee durability. Call `commit()` to ensure
     /// data is written to disk.
     pub fn insert(&mut self, vector: &[f32]) -> Result<u64> {
+        self.ensure_writable("insert")?;
+
         let dims = self.header().dimensions as usize;
 
         if vector.len() != dims {
-            anyhow::bail!("Vector dimension mismatch: expected {}, got {}", dims, vector.len());
+            anyhow::bail!(Tagged::new(
+                ErrorKind::DimensionMismatch,
+                format!("Vector dimension mismatch: expected {}, got {}", dims, vector.len())
+            ));
         }
 
         let current_count = self.header().count;
-        let vector_bytes = dims * std::mem::size_of::<f32>();
-        let offset = HEADER_SIZE + (current_count as usize * vector_bytes);
+        let element_type = self.element_type();
+        let vector_bytes = element_type.vector_bytes(dims);
+        let offset = self.vector_zone_start() + (current_count as usize * vector_bytes);
         let required_size = offset + vector_bytes;
 
         // Ensure file has enough capacity (may remap)
         self.ensure_capacity(required_size)?;
 
         // Write vector data first (data-before-header invariant)
-        unsafe {
-            let dst = self.mapped_mut().as_mut_ptr().add(offset) as *mut f32;
-            std::ptr::copy_nonoverlapping(vector.as_ptr(), dst, dims);
-        }
+        self.write_vector_at(offset, vector)?;
 
         // Update header count only after data is written
         self.header_mut().count = current_count + 1;
 
-        Ok(current_count)
+        Ok(current_count)
+    }
+
+    /// Overwrites the vector at `index` in place
+    ///
+    /// Used to reuse the slot of a deleted vector. Like `insert()`, the new
+    /// data is durable only after the next `commit()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the dimensions don't match or there is no vector `index`
+    pub fn overwrite(&mut self, index: u64, vector: &[f32]) -> Result<()> {
+        self.ensure_writable("overwrite")?;
+
+        let dims = self.header().dimensions as usize;
+        if vector.len() != dims {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::DimensionMismatch,
+                format!("Vector dimension mismatch: expected {}, got {}", dims, vector.len())
+            ));
+        }
+
+        let offset = self.vector_offset(index)?;
+        self.write_vector_at(offset, vector)
+    }
+
+    /// Overwrites the slot of vector `index` with zeros
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no vector `index`
+    pub(crate) fn scrub(&mut self, index: u64) -> Result<()> {
+        self.ensure_writable("scrub")?;
+
+        let offset = self.vector_offset(index)?;
+        let len = self.element_type().vector_bytes(self.dimensions() as usize);
+        self.unseal(offset..offset + len)?;
+        self.dirty.mark(offset, len);
+        self.mapped_mut()[offset..offset + len].fill(0);
+        Ok(())
+    }
+
+    /// Encodes `vector` into the mapped vector slot at `offset`
+    fn write_vector_at(&mut self, offset: usize, vector: &[f32]) -> Result<()> {
+        let dims = vector.len();
+        let element_type = self.element_type();
+        let vector_bytes = element_type.vector_bytes(dims);
+
+        self.unseal(offset..offset + vector_bytes)?;
+        self.dirty.mark(offset, vector_bytes);
+        match element_type {
+            ElementType::F32 => unsafe {
+                let dst = self.mapped_mut().as_mut_ptr().add(offset) as *mut f32;
+                std::ptr::copy_nonoverlapping(vector.as_ptr(), dst, dims);
+            },
+            ElementType::F16 | ElementType::BF16 => {
+                let dst = &mut self.mapped_mut()[offset..offset + vector_bytes];
+                for (dst, &value) in dst.as_chunks_mut::<2>().0.iter_mut().zip(vector) {
+                    *dst = element_type.encode(value).to_ne_bytes();
+                }
+            }
+            ElementType::Binary => {
+                let mut buffer = [0_u64; MAX_BINARY_WORDS];
+                let words = &mut buffer[..binary_words(dims)];
+                binarize(vector, words);
+                let dst = &mut self.mapped_mut()[offset..offset + vector_bytes];
+                for (dst, word) in dst.as_chunks_mut::<8>().0.iter_mut().zip(words) {
+                    *dst = word.to_ne_bytes();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Decrypts the blocks of an encrypted image that overlap `range`, before
+    /// they are first read or written; other storage has nothing to do
+    #[inline]
+    fn unseal(&self, range: Range<usize>) -> Result<()> {
+        #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+        if let (Some(encrypted), Some(Mapping::Decrypted(mmap))) = (&self.encrypted, &self.mmap) {
+            // SAFETY: The mapping is `mmap.len()` bytes long, and nothing reads
+            // or writes a block before it is unsealed here
+            return unsafe { encrypted.unseal(mmap.as_mut_ptr(), mmap.len(), range) };
+        }
+        let _ = range;
+        Ok(())
+    }
+
+    /// Commits all pending changes to disk
+    ///
+    /// This method flushes the pages written since the last commit (tracked by
+    /// `DirtyPages`, always including the header page after an insert) to the
+    /// kernel page cache and then forces a physical write to disk via fsync.
+    /// This guarantees durability even in the event of power loss, and the
+    /// flush cost follows the size of the change rather than of the file.
+    ///
+    /// # Performance
+    ///
+    /// This operation is expensive (1-50ms depending on storage device).
+    /// For batch inserts, insert many vectors and call commit() once.
+    pub fn commit(&mut self) -> Result<()> {
+        self.ensure_writable("commit")?;
+
+        // Let an earlier background sync finish (and report its failure) first
+        #[cfg(not(target_arch = "wasm32"))]
+        self.wait_for_background()?;
+
+        // Never flush into a file that is no longer reachable at its path
+        #[cfg(not(target_arch = "wasm32"))]
+        self.verify_origin()?;
+
+        // Record which library version last wrote the file
+        if self.header().writer_version() != Some(LibraryVersion::CURRENT) {
+            self.header_mut().set_writer_version(LibraryVersion::CURRENT);
+        }
+        self.bump_commit_epoch();
+
+        // Flush the pages written since the last commit to the kernel page cache
+        self.flush_dirty()?;
+
+        // Encrypted storage seals those pages into its file and syncs it
+        #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+        if let Some(encrypted) = &mut self.encrypted {
+            let image = self.mmap.as_deref().expect("storage must hold an active mmap");
+            encrypted.commit(image, self.dirty.ranges())?;
+        }
+        self.dirty.clear();
+
+        // In-memory storage has nothing further to make durable
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+
+        // Force kernel to flush to physical device
+        // On Linux: fdatasync() - flushes data but not metadata
+        file.sync_data()?;
+
+        // Additional barrier: sync_all() flushes metadata too
+        // This is slower but guarantees file size is durable
+        file.sync_all()?;
+
+        // The graph file goes second: its nodes may only reference durable vectors
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(graph_file) = &mut self.graph_file {
+            graph_file.commit()?;
+        }
+
+        // Only now point the catalog at the synced image
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(window) = self.window {
+            publish_window(file, window, self.mapped().len() as u64)?;
+        }
+
+        Ok(())
+    }
+
+    /// Commits like `commit()` plus a final write, syncing the file on a background thread
+    ///
+    /// The pages written since the last commit are handed to the OS for
+    /// write-back (`MS_ASYNC`) and the call returns. A background thread then
+    /// syncs the file, writes `tail` (the graph header) at `tail_offset`,
+    /// syncs again and calls `on_durable`. Writing the tail only after the
+    /// data is durable keeps a crash mid-sync from publishing nodes whose
+    /// records never reached the disk.
+    ///
+    /// Until the sync is joined, callers must not move the bytes at
+    /// `tail_offset`; `move_graph_zone()`, `commit()` and `reattach()` join it
+    /// first. Storage inside a collection file, with a graph file, or in
+    /// memory commits synchronously instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a previous background sync failed, the file was
+    /// stolen, or the sync thread cannot be started.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn commit_in_background(
+        &mut self,
+        tail_offset: usize,
+        tail: Vec<u8>,
+        on_durable: impl FnOnce() + Send + 'static,
+    ) -> Result<()> {
+        self.ensure_writable("commit")?;
+        self.wait_for_background()?;
+
+        if self.file.is_none() || self.window.is_some() || self.graph_file.is_some() {
+            self.commit()?;
+            self.graph_zone_mut(tail_offset, tail.len())?.copy_from_slice(&tail);
+            self.commit()?;
+            on_durable();
+            return Ok(());
+        }
+
+        self.verify_origin()?;
+        if self.header().writer_version() != Some(LibraryVersion::CURRENT) {
+            self.header_mut().set_writer_version(LibraryVersion::CURRENT);
+        }
+        self.bump_commit_epoch();
+
+        for range in self.dirty.ranges() {
+            let end = range.end.min(self.mapped().len());
+            self.mapped().flush_async_range(range.start, end.saturating_sub(range.start))?;
+        }
+        self.dirty.clear();
+
+        let file = self.file.as_ref().context("File-backed storage lost its file handle")?;
+        let file = file.try_clone().context("Failed to clone index file handle")?;
+        let done = SyncSignal::default();
+        let raise = RaiseOnExit(done.clone());
+        let thread = std::thread::Builder::new()
+            .name("chassis-flush".into())
+            .spawn(move || {
+                use std::io::{Seek, SeekFrom, Write};
+                let _raise = raise;
+
+                file.sync_data()?;
+                file.sync_all()?;
+
+                let mut out = &file;
+                out.seek(SeekFrom::Start(tail_offset as u64))?;
+                out.write_all(&tail)?;
+                file.sync_data()?;
+
+                on_durable();
+                Ok(())
+            })
+            .context("Failed to start background flush")?;
+        self.background = Some(BackgroundSync { thread, done });
+        Ok(())
+    }
+
+    /// Signal for the sync started by `commit_in_background()`, if one is running
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn background_signal(&self) -> Option<SyncSignal> {
+        self.background.as_ref().map(|background| background.done.clone())
+    }
+
+    /// Count a commit in the header
+    fn bump_commit_epoch(&mut self) {
+        let epoch = self.header().commit_epoch().wrapping_add(1);
+        self.header_mut().set_commit_epoch(epoch);
+    }
+
+    /// Number of commits made to this file (0 for files written before
+    /// commits were counted)
+    ///
+    /// Increases with every `commit()`, so a reader can tell whether the file
+    /// changed since it last looked.
+    pub fn commit_epoch(&self) -> u64 {
+        self.header().commit_epoch()
+    }
+
+    /// Returns `true` if the last writer did not close the file with every
+    /// write committed: it crashed, was killed after writing without a commit,
+    /// or still has the file open
+    pub fn writer_open(&self) -> bool {
+        self.header().writer_open()
     }
 
-    /// Commits all pending changes to disk
+    /// Records in the file that a writer has it open
     ///
-    /// This method flushes the memory map to the kernel page cache and then
-    /// forces a physical write to disk via fsync. This guarantees durability
-    /// even in the event of power loss.
+    /// The bit is synced at once, so a crash at any later point leaves it set
+    /// for the next open to find; `Drop` clears it again if every write was
+    /// committed. Only standalone, writable index files track it.
     ///
-    /// # Performance
+    /// # Errors
     ///
-    /// This operation is expensive (1-50ms depending on storage device).
-    /// For batch inserts, insert many vectors and call commit() once.
-    pub fn commit(&mut self) -> Result<()> {
-        // Flush mmap to kernel page cache
-        self.mapped_mut().flush()?;
+    /// Returns an error if the header cannot be synced.
+    pub(crate) fn mark_writer_open(&mut self) -> Result<()> {
+        if !self.tracks_writer_open() || self.header().writer_open() {
+            return Ok(());
+        }
+        self.set_writer_open_synced(true)
+    }
 
-        // Force kernel to flush to physical device
-        // On Linux: fdatasync() - flushes data but not metadata
-        self.file.sync_data()?;
+    /// Whether this storage records open writers in its header
+    fn tracks_writer_open(&self) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.file.is_some() && self.window.is_none() && !self.shared_reader;
+        #[cfg(target_arch = "wasm32")]
+        false
+    }
 
-        // Additional barrier: sync_all() flushes metadata too
-        // This is slower but guarantees file size is durable
-        self.file.sync_all()?;
+    /// Set the writer-open bit and sync the header page alone
+    ///
+    /// The header is written without marking it dirty: the bit is not part of
+    /// any commit, and leaving the page clean lets `Drop` see whether anything
+    /// else was written.
+    fn set_writer_open_synced(&mut self, open: bool) -> Result<()> {
+        // SAFETY: As `header_mut()`; the mapping always starts with the header.
+        let header = unsafe { &mut *(self.mapped_mut().as_mut_ptr() as *mut Header) };
+        header.set_writer_open(open);
+        self.mapped().flush_range(0, HEADER_SIZE).context("Failed to sync index header")
+    }
+
+    /// Waits for the sync started by `commit_in_background()`, if any
+    ///
+    /// # Errors
+    ///
+    /// Returns the error the background sync failed with.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn wait_for_background(&mut self) -> Result<()> {
+        let Some(background) = self.background.take() else {
+            return Ok(());
+        };
+        match background.thread.join() {
+            Ok(result) => result.context("Background flush failed"),
+            Err(_) => anyhow::bail!("Background flush panicked"),
+        }
+    }
 
+    /// Synchronously flushes the pages written since the last commit
+    fn flush_dirty(&self) -> Result<()> {
+        let mapped = self.mapped();
+        for range in self.dirty.ranges() {
+            let end = range.end.min(mapped.len());
+            mapped.flush_range(range.start, end.saturating_sub(range.start))?;
+        }
         Ok(())
     }
 
@@ -214,15 +1803,141 @@ impl Storage {
     /// # }
     /// ```
     pub fn get_vector_slice(&self, index: u64) -> Result<&[f32]> {
+        let element_type = self.element_type();
+        if element_type != ElementType::F32 {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!("Vectors are stored as {}; use get_vector() instead", element_type)
+            ));
+        }
+
+        let offset = self.vector_offset(index)?;
+        let dims = self.header().dimensions as usize;
+        self.unseal(offset..offset + dims * 4)?;
+
+        // SAFETY:
+        // - offset is bounds-checked by vector_offset() with overflow protection
+        // - the vector zone starts at a page boundary, so it is 4-byte aligned
+        // - vector_bytes is dims * 4, so 4-byte aligned
+        // - Therefore offset is 4-byte aligned (required for f32)
+        // - dims is the correct length for the slice
+        // - Lifetime is tied to &self, preventing use after remap
+        unsafe {
+            let ptr = self.mapped().as_ptr().add(offset) as *const f32;
+            Ok(std::slice::from_raw_parts(ptr, dims))
+        }
+    }
+
+    /// Returns the vectors with IDs in `range` as one contiguous row-major
+    /// `f32` matrix (zero-copy)
+    ///
+    /// Row `i` is vector `range.start + i`, `dimensions` components wide, so
+    /// the slice can be handed to GPU or BLAS libraries for bulk rescoring
+    /// without copying vector by vector. An empty range returns an empty
+    /// slice. The slice covers the mapped vectors as stored, including any
+    /// deleted ones.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidArgument` if vectors are not stored as `f32`, or
+    /// `OutOfBounds` if the range is reversed or extends past `count()`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use chassis_core::Storage;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let storage = Storage::open("vectors.chassis", 128)?;
+    /// let matrix = storage.vector_matrix_slice(0..storage.count())?;
+    ///
+    /// for row in matrix.chunks_exact(128) {
+    ///     // score each row, or upload `matrix` as a whole
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn vector_matrix_slice(&self, range: Range<u64>) -> Result<&[f32]> {
+        let element_type = self.element_type();
+        if element_type != ElementType::F32 {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!("Vectors are stored as {}; use get_vector() instead", element_type)
+            ));
+        }
+
+        let count = self.header().count;
+        if range.start > range.end || range.end > count {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!("Vector range {:?} out of bounds (count is {})", range, count)
+            ));
+        }
+        if range.is_empty() {
+            return Ok(&[]);
+        }
+
+        let start = self.vector_offset(range.start)?;
+        let end = self.vector_offset(range.end - 1)?
+            + element_type.vector_bytes(self.dimensions() as usize);
+        self.unseal(start..end)?;
+
+        // SAFETY: both ends are bounds-checked by vector_offset() and f32-aligned
+        // as in get_vector_slice(); vectors are stored back to back in between
+        unsafe {
+            let ptr = self.mapped().as_ptr().add(start) as *const f32;
+            Ok(std::slice::from_raw_parts(ptr, (end - start) / 4))
+        }
+    }
+
+    /// Borrows a vector in its stored encoding (zero-copy for every element type)
+    pub(crate) fn stored_vector(&self, index: u64) -> Result<StoredVector<'_>> {
+        let offset = self.vector_offset(index)?;
+        let dims = self.header().dimensions as usize;
+        self.unseal(offset..offset + self.element_type().vector_bytes(dims))?;
+
+        // SAFETY: as in get_vector_slice(); half-width strides keep the offset
+        // 2-byte aligned, as required for u16, and binary strides (whole words)
+        // 8-byte aligned, as required for u64
+        unsafe {
+            let ptr = self.mapped().as_ptr().add(offset);
+            Ok(match self.element_type() {
+                ElementType::F32 => {
+                    StoredVector::F32(std::slice::from_raw_parts(ptr.cast::<f32>(), dims))
+                }
+                ElementType::F16 => {
+                    StoredVector::F16(std::slice::from_raw_parts(ptr.cast::<u16>(), dims))
+                }
+                ElementType::BF16 => {
+                    StoredVector::BF16(std::slice::from_raw_parts(ptr.cast::<u16>(), dims))
+                }
+                ElementType::Binary => StoredVector::Binary {
+                    words: std::slice::from_raw_parts(ptr.cast::<u64>(), binary_words(dims)),
+                    dims,
+                },
+            })
+        }
+    }
+
+    /// Byte offset of the vector at `index`, without bounds checks
+    pub(crate) fn vector_position(&self, index: u64) -> u64 {
+        let dims = self.header().dimensions as usize;
+        self.vector_zone_start() as u64 + index * self.element_type().vector_bytes(dims) as u64
+    }
+
+    /// Returns the bounds-checked byte offset of the vector at `index`
+    fn vector_offset(&self, index: u64) -> Result<usize> {
         let count = self.header().count;
 
         // Bounds check: Ensure index is within valid range
         if index >= count {
-            anyhow::bail!("Index out of bounds: {} (count is {})", index, count);
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!("Index out of bounds: {} (count is {})", index, count)
+            ));
         }
 
         let dims = self.header().dimensions as usize;
-        let vector_bytes = dims * std::mem::size_of::<f32>();
+        let vector_bytes = self.element_type().vector_bytes(dims);
 
         // Use checked arithmetic to prevent overflow
         let index_usize = usize::try_from(index).context("Index too large for this platform")?;
@@ -230,33 +1945,29 @@ impl Storage {
         let byte_offset =
             index_usize.checked_mul(vector_bytes).context("Vector offset calculation overflow")?;
 
-        let offset = HEADER_SIZE.checked_add(byte_offset).context("Offset calculation overflow")?;
+        let offset = self
+            .vector_zone_start()
+            .checked_add(byte_offset)
+            .context("Offset calculation overflow")?;
 
         // Bounds check: Ensure the calculated offset + vector data fits within mmap
         let end_offset =
             offset.checked_add(vector_bytes).context("End offset calculation overflow")?;
 
         if end_offset > self.mapped().len() {
-            anyhow::bail!(
-                "Vector at index {} extends beyond mmap bounds (offset: {}, size: {}, mmap len: {})",
-                index,
-                offset,
-                vector_bytes,
-                self.mapped().len()
-            );
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!(
+                    "Vector at index {} extends beyond mmap bounds (offset: {}, size: {}, mmap len: {})",
+                    index,
+                    offset,
+                    vector_bytes,
+                    self.mapped().len()
+                )
+            ));
         }
 
-        // SAFETY:
-        // - offset is bounds-checked above with overflow protection
-        // - HEADER_SIZE (4096) is 4-byte aligned
-        // - vector_bytes is dims * 4, so 4-byte aligned
-        // - Therefore offset is 4-byte aligned (required for f32)
-        // - dims is the correct length for the slice
-        // - Lifetime is tied to &self, preventing use after remap
-        unsafe {
-            let ptr = self.mapped().as_ptr().add(offset) as *const f32;
-            Ok(std::slice::from_raw_parts(ptr, dims))
-        }
+        Ok(offset)
     }
 
     /// Retrieves a vector by index
@@ -276,8 +1987,7 @@ impl Storage {
     ///
     /// Returns an error if the index is out of bounds
     pub fn get_vector(&self, index: u64) -> Result<Vec<f32>> {
-        let slice = self.get_vector_slice(index)?;
-        Ok(slice.to_vec())
+        Ok(self.stored_vector(index)?.to_vec())
     }
 
     /// Returns the current vector count
@@ -290,6 +2000,162 @@ impl Storage {
         self.header().dimensions
     }
 
+    /// Returns the encoding of stored vector components
+    pub fn element_type(&self) -> ElementType {
+        // Unknown element types are rejected when the image is opened
+        self.header().element_type().unwrap_or_default()
+    }
+
+    /// Changes the encoding of stored vector components; only valid while empty
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the storage already holds vectors or is read-only.
+    pub fn set_element_type(&mut self, element_type: ElementType) -> Result<()> {
+        self.ensure_writable("change the element type")?;
+        if self.header().count != 0 && element_type != self.element_type() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "Cannot store vectors as {}: index already holds {} vectors as {}",
+                    element_type,
+                    self.header().count,
+                    self.element_type()
+                )
+            ));
+        }
+        self.header_mut().set_element_type(element_type);
+        Ok(())
+    }
+
+    /// Returns the name of the custom distance function recorded in the file
+    ///
+    /// `None` means the index uses Euclidean distance.
+    pub fn distance_name(&self) -> Option<&str> {
+        self.header().distance_name()
+    }
+
+    /// Records the custom distance function name; only valid while empty
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the storage already holds vectors or is read-only.
+    pub fn set_distance_name(&mut self, name: Option<&str>) -> Result<()> {
+        self.ensure_writable("change the distance function")?;
+        if self.header().count != 0 && name != self.distance_name() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "Cannot change the distance function: index already holds {} vectors",
+                    self.header().count
+                )
+            ));
+        }
+        self.header_mut().set_distance_name(name);
+        Ok(())
+    }
+
+    /// Returns `true` if vectors are normalized to unit length on insert
+    pub fn normalized(&self) -> bool {
+        self.header().normalized()
+    }
+
+    /// Records whether vectors are normalized on insert; only valid while empty
+    ///
+    /// Normalization itself is done by `VectorIndex`; `insert()` stores
+    /// vectors as given.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the storage already holds vectors or is read-only.
+    pub fn set_normalized(&mut self, normalized: bool) -> Result<()> {
+        self.ensure_writable("change normalization")?;
+        if self.header().count != 0 && normalized != self.normalized() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "Cannot change normalization: index already holds {} vectors",
+                    self.header().count
+                )
+            ));
+        }
+        self.header_mut().set_normalized(normalized);
+        Ok(())
+    }
+
+    /// Records that the graph stores two-tier node records, raising the
+    /// format version so older libraries refuse the file
+    pub(crate) fn set_two_tier_graph(&mut self) -> Result<()> {
+        self.ensure_writable("record the graph layout")?;
+        self.header_mut().set_two_tier_graph();
+        Ok(())
+    }
+
+    /// Returns the version of the library that last committed this file, if recorded
+    pub fn writer_version(&self) -> Option<LibraryVersion> {
+        self.header().writer_version()
+    }
+
+    /// Returns when the file was created, in seconds since the Unix epoch, if recorded
+    pub fn created_at(&self) -> Option<u64> {
+        self.header().created_at()
+    }
+
+    /// Returns the version of the library that created this file, if recorded
+    pub fn created_by(&self) -> Option<LibraryVersion> {
+        self.header().created_by()
+    }
+
+    /// Returns the application metadata stored in the header
+    pub fn user_meta(&self) -> &[u8] {
+        self.header().user_meta()
+    }
+
+    /// Replaces the application metadata; persisted by the next commit
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `meta` is longer than `MAX_USER_META_LEN` bytes or
+    /// the storage is read-only.
+    pub fn set_user_meta(&mut self, meta: &[u8]) -> Result<()> {
+        self.ensure_writable("set metadata")?;
+        if meta.len() > MAX_USER_META_LEN {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "Metadata is {} bytes; at most {} fit in the header",
+                    meta.len(),
+                    MAX_USER_META_LEN
+                )
+            ));
+        }
+        if self.user_meta() != meta {
+            self.header_mut().set_user_meta(meta);
+        }
+        Ok(())
+    }
+
+    /// Returns the recorded bulk build as `(start count, total vectors)`, if any
+    pub(crate) fn build_checkpoint(&self) -> Option<(u64, u64)> {
+        self.header().build_checkpoint()
+    }
+
+    /// Records a bulk build in the file header; persisted by the next commit
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn set_build_checkpoint(&mut self, start: u64, total: u64) {
+        self.header_mut().set_build_checkpoint(start, total);
+    }
+
+    /// Returns the number of nodes linked into the graph, if later ones are not
+    pub(crate) fn linked_count(&self) -> Option<u64> {
+        self.header().linked_count()
+    }
+
+    /// Records how many nodes are linked into the graph; persisted by the next commit
+    pub(crate) fn set_linked_count(&mut self, linked: Option<u64>) {
+        self.header_mut().set_linked_count(linked);
+    }
+
     /// Returns the byte offset immediately after the current logical vector data.
     pub(crate) fn vector_end(&self) -> Result<usize> {
         self.vector_end_for_count(self.header().count)
@@ -298,58 +2164,478 @@ impl Storage {
     /// Returns the byte offset immediately after `count` vectors.
     pub(crate) fn vector_end_for_count(&self, count: u64) -> Result<usize> {
         let dims = self.header().dimensions as usize;
-        let vector_bytes = dims
-            .checked_mul(std::mem::size_of::<f32>())
-            .context("Vector byte size calculation overflow")?;
+        let vector_bytes = self.element_type().vector_bytes(dims);
         let count = usize::try_from(count).context("Vector count too large for this platform")?;
         let vector_data_bytes =
             count.checked_mul(vector_bytes).context("Vector zone size calculation overflow")?;
 
-        HEADER_SIZE.checked_add(vector_data_bytes).context("Vector end calculation overflow")
+        self.vector_zone_start()
+            .checked_add(vector_data_bytes)
+            .context("Vector end calculation overflow")
+    }
+
+    /// Byte offset of the first vector: one page past the start of the file
+    #[inline]
+    fn vector_zone_start(&self) -> usize {
+        self.page_size()
+    }
+
+    /// Returns the page size the zones of this index are aligned to
+    ///
+    /// 4 KiB unless the index was created with a larger one (see
+    /// `set_page_size()`).
+    pub fn page_size(&self) -> usize {
+        self.header().page_size()
+    }
+
+    /// Lays the zones of this new index out on `page_size`-byte pages
+    ///
+    /// Moves the vector zone to start one page into the file and aligns the
+    /// graph and metadata zones and every file growth to the page size, for
+    /// flash whose erase blocks exceed 4 KiB or mappings backed by huge
+    /// pages. Only possible before the first vector or graph zone is placed.
+    /// The growth chunk is raised to at least one page.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidArgument` if `page_size` is not a power of two from
+    /// 4 KiB to `MAX_PAGE_SIZE`, the index already holds vectors or a graph
+    /// zone, or the storage is a collection image.
+    pub fn set_page_size(&mut self, page_size: usize) -> Result<()> {
+        self.ensure_writable("change the page size")?;
+        if !is_valid_page_size(page_size) {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "Page size must be a power of two from {} to {} bytes, got {}",
+                    HEADER_SIZE, MAX_PAGE_SIZE, page_size
+                )
+            ));
+        }
+        if self.graph_offset().is_some() || self.count() > 0 {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Only a new index can change its page size"
+            ));
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.window.is_some() && page_size != PAGE_SIZE {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "Collection indexes use 4 KiB pages"
+            ));
+        }
+
+        self.header_mut().set_page_size(page_size as u32);
+        self.set_growth_chunk(self.growth_chunk);
+        Ok(())
+    }
+
+    /// Returns the persisted graph zone offset, if present.
+    ///
+    /// The graph zone of an index with a graph file starts that file.
+    pub(crate) fn graph_offset(&self) -> Option<u64> {
+        if self.has_graph_file() {
+            return Some(0);
+        }
+        self.header().graph_offset()
+    }
+
+    /// Persists the graph zone offset in the file header.
+    pub(crate) fn set_graph_offset(&mut self, offset: u64) {
+        self.header_mut().set_graph_offset(offset);
+    }
+
+    /// Align a byte count to the next OS page boundary.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[inline]
+    pub(crate) const fn page_align(size: usize) -> usize {
+        (size + PAGE_SIZE - 1) & !(PAGE_SIZE - 1)
+    }
+
+    /// Align a byte count to the next boundary of this index's page size
+    #[inline]
+    pub(crate) fn zone_align(&self, size: usize) -> usize {
+        size.next_multiple_of(self.page_size())
+    }
+
+    /// Ensures file has enough capacity, growing if necessary
+    ///
+    /// File growth is aligned to the index's page size (4KB by default) to optimize for:
+    /// - SSD write amplification
+    /// - Kernel page cache efficiency
+    /// - Hardware block alignment
+    ///
+    /// # Warning
+    ///
+    /// This method invalidates all existing pointers into the mmap.
+    /// Do not hold references across calls to this method.
+    fn ensure_capacity(&mut self, required_size: usize) -> Result<()> {
+        self.relocate_metadata_zone_if_overlapped(required_size)?;
+        self.grow_to(required_size)
+    }
+
+    /// Grows the file and remaps it so at least `required_size` bytes are mapped.
+    fn grow_to(&mut self, required_size: usize) -> Result<()> {
+        if self.mapped().len() >= required_size {
+            return Ok(());
+        }
+
+        let new_len = self.growth_target(self.mapped().len(), required_size)?;
+        self.resize_mapping(new_len)
+    }
+
+    /// Length to grow a file of `current_len` bytes to so that it holds
+    /// `required_size`: at least one chunk more, rounded up to the next page
+    /// boundary, but short of the quota where a smaller step fits
+    ///
+    /// # Errors
+    ///
+    /// Returns `QuotaExceeded` if `required_size` does not fit the quota.
+    fn growth_target(&self, current_len: usize, required_size: usize) -> Result<usize> {
+        let chunked = current_len.saturating_add(self.growth_chunk);
+        let target = self.zone_align(required_size.max(chunked));
+        let Some(max_file_bytes) = self.max_file_bytes else {
+            return Ok(target);
+        };
+
+        // The other file of the index counts against the same quota
+        let others = (self.mapped_len() - current_len) as u64;
+        let needed = self.zone_align(required_size);
+        let allowed = max_file_bytes.saturating_sub(others);
+        if needed as u64 > allowed {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::QuotaExceeded,
+                format!(
+                    "Growing the index to {} bytes would exceed its {} byte quota",
+                    others + needed as u64,
+                    max_file_bytes
+                )
+            ));
+        }
+        let allowed = usize::try_from(allowed).unwrap_or(usize::MAX);
+        Ok(target.min(allowed - allowed % self.page_size()).max(needed))
+    }
+
+    /// Resizes the backing file (or buffer) to `new_len` bytes and refreshes the mapping.
+    fn resize_mapping(&mut self, new_len: usize) -> Result<()> {
+        self.ensure_writable("grow index file")?;
+
+        self.flush_dirty()?;
+        self.remaps += 1;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(window) = self.window {
+            return self.resize_window(window, new_len);
+        }
+
+        match self.mmap.take() {
+            #[cfg(feature = "wasm")]
+            Some(Mapping::Memory(mut buffer)) => {
+                buffer.resize(new_len);
+                self.mmap = Some(Mapping::Memory(buffer));
+            }
+            #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+            Some(Mapping::Decrypted(old)) => {
+                let grown = match MmapMut::map_anon(new_len) {
+                    Ok(grown) => grown,
+                    Err(e) => {
+                        self.mmap = Some(Mapping::Decrypted(old));
+                        return Err(e).context("Failed to map anonymous memory");
+                    }
+                };
+                let grown = MmapRaw::from(grown);
+                let kept = old.len().min(new_len);
+                let encrypted =
+                    self.encrypted.as_mut().context("Decrypted image without its file")?;
+                // Sealed blocks stay untouched in the new mapping, as in the old
+                for start in
+                    (0..kept).step_by(PAGE_SIZE).filter(|&start| !encrypted.is_sealed(start))
+                {
+                    // SAFETY: Both mappings are live and at least `kept` bytes long
+                    unsafe {
+                        std::ptr::copy_nonoverlapping(
+                            old.as_ptr().add(start),
+                            grown.as_mut_ptr().add(start),
+                            PAGE_SIZE.min(kept - start),
+                        );
+                    }
+                }
+                encrypted.discard(kept)?;
+                self.mmap = Some(Mapping::Decrypted(grown));
+                // Blocks the image grows back into must not keep their old
+                // contents in the file
+                if new_len > kept {
+                    self.dirty.mark(kept, new_len - kept);
+                }
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Some(Mapping::Private(old)) => match private_copy(&old, new_len) {
+                Ok(grown) => self.mmap = Some(Mapping::Private(grown)),
+                Err(e) => {
+                    // Keep the old view, as a failed file resize does
+                    self.mmap = Some(Mapping::Private(old));
+                    return Err(e);
+                }
+            },
+            #[cfg(not(target_arch = "wasm32"))]
+            old => {
+                // Unmap before resizing: `SetEndOfFile` is only specified for
+                // files without mapped views (and fails outright with
+                // ERROR_USER_MAPPED_FILE when shrinking one). The view is shared,
+                // so its pages are already in the file.
+                drop(old);
+                let file =
+                    self.file.as_ref().context("File-backed storage lost its file handle")?;
+                let resized = file.set_len(new_len as u64);
+
+                // Map whatever size the file has now, so a failed resize still
+                // leaves a valid view of the old contents
+                self.mmap = Some(Mapping::File(unsafe { MmapMut::map_mut(file)? }));
+                resized.context("Failed to resize index file")?;
+            }
+            #[cfg(target_arch = "wasm32")]
+            None => unreachable!("storage must hold an active mmap"),
+        }
+
+        self.apply_memory_mode()
+    }
+
+    /// Remaps a collection image at `new_len` bytes, moving it to the end of
+    /// the file if it no longer fits its reserved region.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn resize_window(&mut self, mut window: Window, new_len: usize) -> Result<()> {
+        let file = self.file.as_ref().context("Collection storage lost its file handle")?;
+
+        let mmap = if new_len as u64 <= window.capacity {
+            map_window(file, window.base, new_len)?
+        } else {
+            // Double the reservation so repeated growth moves the image rarely.
+            // Other collections keep their views mapped meanwhile; Windows
+            // allows extending a file with mapped views, never shrinking it.
+            let capacity = Self::page_align(new_len.max(2 * window.capacity as usize)) as u64;
+            let base = Self::page_align(file.metadata()?.len() as usize) as u64;
+            file.set_len(base + capacity)?;
+
+            let mut mmap = map_window(file, base, new_len)?;
+            copy_nonzero_pages(self.mapped(), &mut mmap);
+            self.dirty.mark(0, new_len);
+            window = Window { base, capacity, ..window };
+            mmap
+        };
+
+        self.mmap = Some(Mapping::File(mmap));
+        self.window = Some(window);
+        self.apply_memory_mode()
+    }
+
+    /// Sets the paging advice for the mapping and applies it immediately
+    ///
+    /// The advice is re-applied whenever the file grows and is remapped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the OS rejects the advice.
+    pub fn set_memory_mode(&mut self, mode: MemoryMode) -> Result<()> {
+        self.memory_mode = mode;
+        self.apply_memory_mode()
+    }
+
+    /// Returns the paging advice in effect
+    pub fn memory_mode(&self) -> MemoryMode {
+        self.memory_mode
+    }
+
+    /// Sets the minimum number of bytes the file grows by when it runs out of room
+    ///
+    /// Rounded up to whole pages of the index's page size; the default is one
+    /// page. Larger chunks mean
+    /// fewer resizes and remaps during bulk inserts, at the cost of up to one
+    /// chunk of unused (sparse where supported) space at the end of the file.
+    pub fn set_growth_chunk(&mut self, bytes: usize) {
+        let page_size = self.page_size();
+        self.growth_chunk = self.zone_align(bytes.clamp(page_size, usize::MAX - page_size));
+    }
+
+    /// Returns the minimum growth step in bytes
+    pub fn growth_chunk(&self) -> usize {
+        self.growth_chunk
+    }
+
+    /// Sets the size the index may not grow past, or `None` for no limit
+    ///
+    /// Counts the index file and its graph file together (for in-memory
+    /// storage, the buffer). Growth that would pass the limit fails with
+    /// `QuotaExceeded` before the file is touched; growth steps are cut short
+    /// to stay within it. A file already larger than the limit stays as it is
+    /// but cannot grow.
+    pub fn set_max_file_bytes(&mut self, max_file_bytes: Option<u64>) {
+        self.max_file_bytes = max_file_bytes;
+    }
+
+    /// Returns the size limit set with `set_max_file_bytes()`
+    pub fn max_file_bytes(&self) -> Option<u64> {
+        self.max_file_bytes
+    }
+
+    fn apply_memory_mode(&self) -> Result<()> {
+        self.mapped().advise(self.memory_mode).context("Failed to apply memory mode")?;
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(graph_file) = &self.graph_file {
+            graph_file.mapped().advise(self.memory_mode).context("Failed to apply memory mode")?;
+        }
+        Ok(())
+    }
+
+    /// Asks the OS to start reading `offset..offset + len` into memory
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the range is outside the mapping.
+    pub(crate) fn prefetch(&self, offset: usize, len: usize) -> Result<()> {
+        let mapped_len = self.mapped().len();
+        if offset.checked_add(len).is_none_or(|end| end > mapped_len) {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!(
+                    "Prefetch range out of bounds (offset: {}, len: {}, mmap len: {})",
+                    offset, len, mapped_len
+                )
+            ));
+        }
+
+        self.mapped().will_need(offset, len).context("Failed to prefetch index pages")
+    }
+
+    /// `prefetch()` for a range of the graph zone
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the range is outside the graph zone's mapping.
+    pub(crate) fn prefetch_graph(&self, offset: usize, len: usize) -> Result<()> {
+        let mapped = self.graph_mapped();
+        if offset.checked_add(len).is_none_or(|end| end > mapped.len()) {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!(
+                    "Prefetch range out of bounds (offset: {}, len: {}, mmap len: {})",
+                    offset,
+                    len,
+                    mapped.len()
+                )
+            ));
+        }
+
+        mapped.will_need(offset, len).context("Failed to prefetch index pages")
+    }
+
+    /// Locks `offset..offset + len` of the graph zone's mapping in RAM
+    ///
+    /// The lock lasts until `unpin_graph()`, or until the mapping is replaced
+    /// when the file grows or the graph zone moves.
+    ///
+    /// # Errors
+    ///
+    /// Returns `OutOfBounds` if the range is outside the mapping, and an `Io`
+    /// error if the OS refuses the lock.
+    pub(crate) fn pin_graph(&self, offset: usize, len: usize) -> Result<()> {
+        let mapped = self.graph_mapped();
+        if offset.checked_add(len).is_none_or(|end| end > mapped.len()) {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!(
+                    "Pin range out of bounds (offset: {}, len: {}, mmap len: {})",
+                    offset,
+                    len,
+                    mapped.len()
+                )
+            ));
+        }
+
+        mapped.lock(offset, len).context("Failed to pin graph pages")
     }
 
-    /// Returns the persisted graph zone offset, if present.
-    pub(crate) fn graph_offset(&self) -> Option<u64> {
-        self.header().graph_offset()
+    /// Unlocks every page of the graph zone's mapping locked by `pin_graph()`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the OS rejects the call.
+    pub(crate) fn unpin_graph(&self) -> Result<()> {
+        self.graph_mapped().unlock().context("Failed to unpin graph pages")
     }
 
-    /// Persists the graph zone offset in the file header.
-    pub(crate) fn set_graph_offset(&mut self, offset: u64) {
-        self.header_mut().set_graph_offset(offset);
-    }
+    /// Asks the OS to start reading the vector at `index` into memory
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index is out of bounds.
+    pub(crate) fn prefetch_vector(&self, index: u64) -> Result<()> {
+        let count = self.header().count;
+        if index >= count {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!("Index out of bounds: {} (count is {})", index, count)
+            ));
+        }
 
-    /// Align a byte count to the next page boundary.
-    #[inline]
-    pub(crate) const fn page_align(size: usize) -> usize {
-        (size + PAGE_SIZE - 1) & !(PAGE_SIZE - 1)
+        let start = self.vector_end_for_count(index)?;
+        let end = self.vector_end_for_count(index + 1)?;
+        self.prefetch(start, end - start)
     }
 
-    /// Ensures file has enough capacity, growing if necessary
+    /// Lets the OS drop resident pages of the mapping (`MADV_DONTNEED`)
     ///
-    /// File growth is page-aligned (4KB boundaries) to optimize for:
-    /// - SSD write amplification
-    /// - Kernel page cache efficiency
-    /// - Hardware block alignment
+    /// Nothing is lost: pages are read back from the page cache or the file on
+    /// next access. Use this when the app is backgrounded or under memory
+    /// pressure. No-op for shared readers (whose private mapping may hold
+    /// in-memory changes), except sealed ones, and for in-memory storage.
     ///
-    /// # Warning
+    /// # Errors
     ///
-    /// This method invalidates all existing pointers into the mmap.
-    /// Do not hold references across calls to this method.
-    fn ensure_capacity(&mut self, required_size: usize) -> Result<()> {
-        if self.mapped().len() >= required_size {
+    /// Returns an error if the OS rejects the advice.
+    pub fn release_memory(&self) -> Result<()> {
+        if self.shared_reader && !self.is_sealed() {
             return Ok(());
         }
 
-        // Round up to next page boundary (4KB)
-        let new_size = Self::page_align(required_size);
+        // SAFETY: Writable file storage is always a shared (`MAP_SHARED`) mapping,
+        // and sealed storage a read-only one.
+        unsafe { self.mapped().dont_need() }.context("Failed to release index pages")?;
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(graph_file) = &self.graph_file {
+            // SAFETY: As above, the graph file is mapped shared or read-only.
+            unsafe { graph_file.mapped().dont_need() }.context("Failed to release graph pages")?;
+        }
+        Ok(())
+    }
 
-        // Windows: cannot change file size while a mapping of this file exists (ERROR_USER_MAPPED_FILE).
-        self.mapped_mut().flush()?;
-        self.mmap.take();
-        self.file.set_len(new_size as u64)?;
-        self.mmap = Some(unsafe { MmapMut::map_mut(&self.file)? });
+    /// Returns how many times the file was grown and remapped since it was opened
+    pub(crate) fn remaps(&self) -> u64 {
+        self.remaps
+    }
 
-        Ok(())
+    /// Returns the size of the active mapping in bytes, including the graph file's
+    pub(crate) fn mapped_len(&self) -> usize {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(graph_file) = &self.graph_file {
+            return self.mapped().len() + graph_file.mapped().len();
+        }
+        self.mapped().len()
+    }
+
+    /// Returns the bytes of the mapping held in RAM, if the platform reports it
+    pub(crate) fn resident_bytes(&self) -> Result<Option<usize>> {
+        let resident =
+            self.mapped().resident_bytes().context("Failed to query resident index pages")?;
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(graph_file) = &self.graph_file {
+            let graph = graph_file
+                .mapped()
+                .resident_bytes()
+                .context("Failed to query resident graph pages")?;
+            return Ok(resident.zip(graph).map(|(index, graph)| index + graph));
+        }
+        Ok(resident)
     }
 
     /// Returns a reference to the header
@@ -359,14 +2645,36 @@ impl Storage {
 
     /// Returns a mutable reference to the header
     fn header_mut(&mut self) -> &mut Header {
+        self.dirty.mark(0, HEADER_SIZE);
         unsafe { &mut *(self.mapped_mut().as_mut_ptr() as *mut Header) }
     }
 
+    /// Mapping holding the graph zone: the graph file's, or this file's own
+    #[inline]
+    fn graph_mapped(&self) -> &Mapping {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(graph_file) = &self.graph_file {
+            return graph_file.mapped();
+        }
+        self.mapped()
+    }
+
+    /// Mutable graph zone mapping and the dirty tracker for its pages
+    #[inline]
+    fn graph_mapped_mut(&mut self) -> (&mut Mapping, &mut DirtyPages) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(graph_file) = &mut self.graph_file {
+            let mapped = graph_file.mmap.as_mut().expect("graph file must hold an active mmap");
+            return (mapped, &mut graph_file.dirty);
+        }
+        (self.mmap.as_mut().expect("storage must hold an active mmap"), &mut self.dirty)
+    }
+
     /// Get immutable mmap slice for graph zone
     ///
     /// # Arguments
     ///
-    /// * `offset` - Byte offset from the start of the file
+    /// * `offset` - Byte offset from the start of the graph zone's file
     /// * `len` - Number of bytes to return
     ///
     /// # Returns
@@ -378,24 +2686,29 @@ impl Storage {
     /// Returns an error if the requested range is out of bounds
     pub fn graph_zone(&self, offset: usize, len: usize) -> Result<&[u8]> {
         let end = offset.checked_add(len).context("Graph zone end offset overflow")?;
-
-        if end > self.mapped().len() {
-            anyhow::bail!(
-                "Graph zone access out of bounds: offset={}, len={}, mmap_len={}",
-                offset,
-                len,
-                self.mapped().len()
-            );
+        let mapped = self.graph_mapped();
+
+        if end > mapped.len() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!(
+                    "Graph zone access out of bounds: offset={}, len={}, mmap_len={}",
+                    offset,
+                    len,
+                    mapped.len()
+                )
+            ));
         }
 
-        Ok(&self.mapped()[offset..end])
+        self.unseal(offset..end)?;
+        Ok(&mapped[offset..end])
     }
 
     /// Get mutable mmap slice for graph zone
     ///
     /// # Arguments
     ///
-    /// * `offset` - Byte offset from the start of the file
+    /// * `offset` - Byte offset from the start of the graph zone's file
     /// * `len` - Number of bytes to return
     ///
     /// # Returns
@@ -407,17 +2720,23 @@ impl Storage {
     /// Returns an error if the requested range is out of bounds
     pub fn graph_zone_mut(&mut self, offset: usize, len: usize) -> Result<&mut [u8]> {
         let end = offset.checked_add(len).context("Graph zone end offset overflow")?;
-
-        if end > self.mapped().len() {
-            anyhow::bail!(
-                "Graph zone access out of bounds: offset={}, len={}, mmap_len={}",
-                offset,
-                len,
-                self.mapped().len()
-            );
+        self.unseal(offset..end)?;
+        let (mapped, dirty) = self.graph_mapped_mut();
+
+        if end > mapped.len() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::OutOfBounds,
+                format!(
+                    "Graph zone access out of bounds: offset={}, len={}, mmap_len={}",
+                    offset,
+                    len,
+                    mapped.len()
+                )
+            ));
         }
 
-        Ok(&mut self.mapped_mut()[offset..end])
+        dirty.mark(offset, len);
+        Ok(&mut mapped[offset..end])
     }
 
     /// Ensure graph zone has enough capacity
@@ -434,9 +2753,69 @@ impl Storage {
     /// This method invalidates all existing pointers into the mmap.
     /// Do not hold references across calls to this method.
     pub fn ensure_graph_capacity(&mut self, required_size: usize) -> Result<()> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(len) = self.graph_file.as_ref().map(|graph_file| graph_file.mapped().len()) {
+            if len >= required_size {
+                return Ok(());
+            }
+            self.ensure_writable("grow graph file")?;
+
+            let new_len = self.growth_target(len, required_size)?;
+            if let Some(graph_file) = &mut self.graph_file {
+                graph_file.resize(new_len)?;
+            }
+            self.remaps += 1;
+            return self.apply_memory_mode();
+        }
         self.ensure_capacity(required_size)
     }
 
+    /// Grow the file to at least `required_size` bytes and allocate its blocks
+    ///
+    /// Unlike growth on demand, which leaves the new space sparse where the
+    /// filesystem supports it, this asks the filesystem to allocate every block
+    /// now, so later writes do not fragment the file. Collection images and
+    /// in-memory storage are only grown.
+    ///
+    /// # Warning
+    ///
+    /// This method invalidates all existing pointers into the mmap.
+    pub(crate) fn preallocate(&mut self, required_size: usize) -> Result<()> {
+        self.ensure_writable("reserve space")?;
+        self.ensure_capacity(required_size)?;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(file) = &self.file
+            && self.window.is_none()
+        {
+            file.allocate(self.mapped().len() as u64)
+                .context("Failed to preallocate index file")?;
+        }
+        Ok(())
+    }
+
+    /// `preallocate()` for the graph zone: grows and allocates the graph file,
+    /// or the index file when it holds the graph
+    ///
+    /// # Warning
+    ///
+    /// This method invalidates all existing pointers into the mmap.
+    pub(crate) fn preallocate_graph(&mut self, required_size: usize) -> Result<()> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.graph_file.is_some() {
+            self.ensure_writable("reserve space")?;
+            self.ensure_graph_capacity(required_size)?;
+            if let Some(graph_file) = &self.graph_file
+                && let Some(file) = &graph_file.file
+            {
+                let len = graph_file.mapped().len() as u64;
+                file.allocate(len).context("Failed to preallocate graph file")?;
+            }
+            return Ok(());
+        }
+        self.preallocate(required_size)
+    }
+
     /// Move the graph zone to a new offset and update the persisted offset.
     ///
     /// The copy uses memmove semantics so overlapping source and destination ranges are safe.
@@ -447,6 +2826,7 @@ impl Storage {
         new_offset: usize,
         len: usize,
     ) -> Result<()> {
+        debug_assert!(!self.has_graph_file(), "a graph file never moves its graph zone");
         if len == 0 {
             self.set_graph_offset(new_offset as u64);
             return Ok(());
@@ -455,19 +2835,333 @@ impl Storage {
         let old_end = old_offset.checked_add(len).context("Old graph zone end overflow")?;
         let new_end = new_offset.checked_add(len).context("New graph zone end overflow")?;
 
+        // A background sync still writes the graph header at the old offset
+        #[cfg(not(target_arch = "wasm32"))]
+        self.wait_for_background()?;
+
         self.ensure_capacity(old_end.max(new_end))?;
+        self.unseal(old_offset..old_end)?;
+        self.unseal(new_offset..new_end)?;
         self.mapped_mut().copy_within(old_offset..old_end, new_offset);
+        self.dirty.mark(new_offset, len);
         self.set_graph_offset(new_offset as u64);
 
-        let new_file_len = Self::page_align(new_end);
-        self.mapped_mut().flush()?;
-        self.mmap.take();
-        self.file.set_len(new_file_len as u64)?;
-        self.mmap = Some(unsafe { MmapMut::map_mut(&self.file)? });
+        // The metadata zone lives past the graph zone and would be cut off by the resize below.
+        let metadata = self.metadata_zone_bytes()?.map(<[u8]>::to_vec);
+
+        self.resize_mapping(self.zone_align(new_end))?;
+
+        if let Some(zone) = metadata {
+            self.write_metadata_zone(&zone, self.metadata_zone_start(new_end)?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the payload of a metadata section, if it has been written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the metadata zone is out of bounds or corrupted.
+    #[cfg_attr(not(feature = "linalg"), allow(dead_code))]
+    pub(crate) fn metadata_section(&self, tag: &SectionTag) -> Result<Option<&[u8]>> {
+        match self.metadata_zone_bytes()? {
+            Some(zone) => metadata::find_section(zone, tag),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads a journaled map: its section, with its journal records applied.
+    ///
+    /// Returns an empty map if the file holds neither.
+    pub(crate) fn read_map<M: JournaledMap>(&self) -> Result<M> {
+        let Some(zone) = self.metadata_zone_bytes()? else {
+            return Ok(M::default());
+        };
+
+        let section = metadata::find_section(zone, M::TAG)?;
+        let mut map = section.map(M::from_bytes).transpose()?.unwrap_or_default();
+        let mut journal_len = 0;
+        for (_, payload) in metadata::parse_journal(zone)?.0.iter().filter(|(tag, _)| tag == M::TAG)
+        {
+            map.apply_delta(payload)?;
+            journal_len += metadata::record_len(payload.len());
+        }
+
+        *map.changes_mut() = Changes::loaded(section.map_or(0, <[u8]>::len), journal_len);
+        Ok(map)
+    }
+
+    /// Writes the changes to a journaled map, if any.
+    ///
+    /// Changes to known IDs are appended to the metadata zone as a journal
+    /// record; otherwise, or once the map's records would outgrow its section,
+    /// the map is written as a full section. Call `commit()` to make the
+    /// change durable.
+    ///
+    /// # Warning
+    ///
+    /// This method may remap the file and invalidates all existing pointers into the mmap.
+    pub(crate) fn write_map<M: JournaledMap>(&mut self, map: &mut M) -> Result<()> {
+        let changes = map.changes();
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(ids) = changes.ids() {
+            let record = metadata::serialize_record(M::TAG, &map.delta_bytes(ids));
+            if !changes.wants_section(record.len()) && self.append_metadata_record(&record)? {
+                map.changes_mut().appended(record.len());
+                return Ok(());
+            }
+        }
+
+        let bytes = map.to_bytes();
+        self.put_metadata_section(M::TAG, &bytes)?;
+        map.changes_mut().rewritten(bytes.len());
+        Ok(())
+    }
+
+    /// Appends a serialized journal record to the metadata zone in place
+    ///
+    /// Returns `false` without writing if there is no zone yet or the record
+    /// would land on the durable zone (see `durable_metadata_zone()`); the
+    /// caller then rewrites the zone instead. The durable zone is a prefix of
+    /// the grown one or lies elsewhere, so it stays the fallback.
+    fn append_metadata_record(&mut self, record: &[u8]) -> Result<bool> {
+        self.ensure_writable("write metadata")?;
+
+        let Some((offset, len)) = self.header().metadata_zone() else {
+            return Ok(false);
+        };
+        let start = offset.checked_add(len).context("Metadata zone end offset overflow")?;
+        let end = start + record.len() as u64;
+
+        let durable = self.durable_metadata_zone();
+        if let Some((durable_offset, durable_len)) = durable
+            && durable_offset != offset
+            && durable_offset < end
+            && start < durable_offset.saturating_add(durable_len)
+        {
+            return Ok(false);
+        }
+
+        let start =
+            usize::try_from(start).context("Metadata offset too large for this platform")?;
+        let end = start + record.len();
+        self.grow_to(end)?;
+        self.unseal(start..end)?;
+        self.mapped_mut()[start..end].copy_from_slice(record);
+        self.dirty.mark(start, record.len());
+
+        let epoch = self.header().commit_epoch().wrapping_add(1);
+        let header = self.header_mut();
+        header.set_metadata_zone(offset, len + record.len() as u64);
+        header.set_previous_metadata_zone(durable);
+        header.set_metadata_epoch(epoch);
+        Ok(true)
+    }
+
+    /// Points the header at the metadata zone readers actually use
+    ///
+    /// After a crash the current zone may be torn, so reads fall back to the
+    /// previous zone, or its journal may end in a torn record. A writer makes
+    /// the fallback the current zone and cuts the journal back to its valid
+    /// records, so that appends land after them.
+    pub(crate) fn repair_metadata_zone(&mut self) -> Result<()> {
+        let Some(current) = self.header().metadata_zone() else {
+            return Ok(());
+        };
+        let parses =
+            |zone| self.zone_bytes(zone).is_ok_and(|bytes| metadata::parse_sections(bytes).is_ok());
+        let used = match self.header().previous_metadata_zone() {
+            _ if parses(current) => current,
+            Some(previous) if parses(previous) => previous,
+            _ => return Ok(()),
+        };
+
+        let (_, end) = metadata::parse_journal(self.zone_bytes(used)?)?;
+        let repaired = (used.0, end as u64);
+        if repaired == current {
+            return Ok(());
+        }
+
+        self.ensure_writable("repair metadata")?;
+        let header = self.header_mut();
+        header.set_metadata_zone(repaired.0, repaired.1);
+        header.set_previous_metadata_zone(None);
+        header.set_metadata_epoch(0);
+        Ok(())
+    }
+
+    /// Writes (or replaces) a metadata section.
+    ///
+    /// The whole metadata zone is rewritten, next to the zone the last commit
+    /// left rather than over it (see `write_metadata_zone()`). Journal records
+    /// of other sections are carried over; those of `tag` are dropped, since
+    /// `payload` supersedes them. Call `commit()` to make the change durable.
+    ///
+    /// # Warning
+    ///
+    /// This method may remap the file and invalidates all existing pointers into the mmap.
+    pub(crate) fn put_metadata_section(&mut self, tag: &SectionTag, payload: &[u8]) -> Result<()> {
+        self.ensure_writable("write metadata")?;
+
+        let existing = self.metadata_zone_bytes()?;
+        let zone = {
+            let (mut sections, mut records) = match existing {
+                Some(zone) => (metadata::parse_sections(zone)?, metadata::parse_journal(zone)?.0),
+                None => (Vec::new(), Vec::new()),
+            };
+            match sections.iter_mut().find(|(section_tag, _)| section_tag == tag) {
+                Some(section) => section.1 = payload,
+                None => sections.push((*tag, payload)),
+            }
+            records.retain(|(record_tag, _)| record_tag != tag);
+            metadata::serialize_zone(&sections, &records)
+        };
+
+        let floor = match self.metadata_floor() {
+            Some(floor) => {
+                usize::try_from(floor).context("Metadata offset too large for this platform")?
+            }
+            None => self.metadata_zone_start(self.mapped().len())?,
+        };
+
+        self.write_metadata_zone(&zone, floor)
+    }
+
+    /// Start of the area past the data that holds the metadata zones, if any
+    fn metadata_floor(&self) -> Option<u64> {
+        let header = self.header();
+        let (offset, _) = header.metadata_zone()?;
+        let previous = header.previous_metadata_zone().map_or(offset, |(previous, _)| previous);
+        Some(header.metadata_floor().unwrap_or(offset.min(previous)))
+    }
+
+    /// Returns the raw bytes of the metadata zone, if present.
+    ///
+    /// A zone that fails validation (torn by a crash during its commit) gives
+    /// way to the previous one the header records.
+    fn metadata_zone_bytes(&self) -> Result<Option<&[u8]>> {
+        let Some(zone) = self.header().metadata_zone() else {
+            return Ok(None);
+        };
+
+        let current = self.zone_bytes(zone);
+        if let Ok(bytes) = current
+            && metadata::parse_sections(bytes).is_ok()
+        {
+            return current.map(Some);
+        }
+        if let Some(previous) = self.header().previous_metadata_zone()
+            && let Ok(bytes) = self.zone_bytes(previous)
+            && metadata::parse_sections(bytes).is_ok()
+        {
+            return Ok(Some(bytes));
+        }
+        current.map(Some)
+    }
+
+    /// Bytes of the metadata zone at `(offset, len)`
+    fn zone_bytes(&self, (offset, len): (u64, u64)) -> Result<&[u8]> {
+        let offset =
+            usize::try_from(offset).context("Metadata offset too large for this platform")?;
+        let len = usize::try_from(len).context("Metadata length too large for this platform")?;
+        let end = offset.checked_add(len).context("Metadata zone end offset overflow")?;
+
+        if end > self.mapped().len() {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                format!(
+                    "Metadata zone out of bounds: offset={}, len={}, mmap_len={}",
+                    offset,
+                    len,
+                    self.mapped().len()
+                )
+            ));
+        }
+
+        self.unseal(offset..end)?;
+        Ok(&self.mapped()[offset..end])
+    }
+
+    /// The metadata zone the last commit left in the header
+    ///
+    /// The current zone, unless it was written since that commit: then the
+    /// previous one, which the file still names if the commit does not
+    /// complete.
+    fn durable_metadata_zone(&self) -> Option<(u64, u64)> {
+        let header = self.header();
+        if header.metadata_epoch() == header.commit_epoch().wrapping_add(1) {
+            header.previous_metadata_zone()
+        } else {
+            header.metadata_zone()
+        }
+    }
+
+    /// Copies a serialized metadata zone to `floor` or past it and records its
+    /// location in the header
+    ///
+    /// The zone never overlaps the durable zone (see `durable_metadata_zone()`);
+    /// if it would at `floor`, it goes right after it, so zones alternate
+    /// between the two places. The header keeps the durable zone as the
+    /// fallback until the next commit, so a zone torn by a crash mid-commit
+    /// costs only the change it carried. A durable zone below `floor` (data
+    /// grows over it) or cut off by a shrink is dropped.
+    fn write_metadata_zone(&mut self, zone: &[u8], floor: usize) -> Result<()> {
+        let mapped_len = self.mapped().len() as u64;
+        let durable = self.durable_metadata_zone().filter(|&(offset, len)| {
+            offset >= floor as u64 && offset.saturating_add(len) <= mapped_len
+        });
+
+        let mut offset = floor;
+        if let Some((durable_offset, durable_len)) = durable {
+            let durable_offset = usize::try_from(durable_offset)
+                .context("Metadata offset too large for this platform")?;
+            let durable_end = durable_offset + durable_len as usize;
+            if offset < durable_end && durable_offset < offset + zone.len() {
+                offset = self.zone_align(durable_end);
+            }
+        }
 
+        let end = offset.checked_add(zone.len()).context("Metadata zone end offset overflow")?;
+        self.grow_to(end)?;
+        self.unseal(offset..end)?;
+        self.mapped_mut()[offset..end].copy_from_slice(zone);
+        self.dirty.mark(offset, zone.len());
+
+        let epoch = self.header().commit_epoch().wrapping_add(1);
+        let header = self.header_mut();
+        header.set_metadata_zone(offset as u64, zone.len() as u64);
+        header.set_previous_metadata_zone(durable);
+        header.set_metadata_epoch(epoch);
+        header.set_metadata_floor(floor as u64);
         Ok(())
     }
 
+    /// Moves the metadata zone out of the way if growing to `required_size` would overlap it.
+    fn relocate_metadata_zone_if_overlapped(&mut self, required_size: usize) -> Result<()> {
+        let Some(floor) = self.metadata_floor() else {
+            return Ok(());
+        };
+
+        if (required_size as u64) <= floor {
+            return Ok(());
+        }
+
+        let zone = self.metadata_zone_bytes()?.map(<[u8]>::to_vec).unwrap_or_default();
+        self.write_metadata_zone(&zone, self.metadata_zone_start(required_size)?)
+    }
+
+    /// Chooses where to place the metadata zone given the end of the data in front of it.
+    fn metadata_zone_start(&self, data_end: usize) -> Result<usize> {
+        let start = data_end
+            .checked_add(METADATA_ZONE_SLACK)
+            .context("Metadata offset calculation overflow")?;
+        Ok(self.zone_align(start))
+    }
+
     /// Truncate the logical count of vectors to handle ghost node recovery.
     ///
     /// This method is used during index opening to recover from crashes where
@@ -523,16 +3217,187 @@ impl Storage {
     }
 }
 
+/// Header for a new index, stamped with the creation time and library version
+///
+/// `wasm32-unknown-unknown` has no clock, so browser indexes record no time.
+fn new_header(dimensions: u32) -> Header {
+    #[cfg(not(target_arch = "wasm32"))]
+    let created_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    #[cfg(target_arch = "wasm32")]
+    let created_at = 0;
+
+    let mut header = Header::new(dimensions);
+    header.set_created(created_at, LibraryVersion::CURRENT);
+    header
+}
+
+/// Anonymous mapping of `len` bytes that starts with a copy of `image`
+#[cfg(not(target_arch = "wasm32"))]
+fn private_copy(image: &[u8], len: usize) -> Result<MmapMut> {
+    let mut mmap = MmapMut::map_anon(len).context("Failed to map anonymous memory")?;
+    let kept = image.len().min(len);
+    mmap[..kept].copy_from_slice(&image[..kept]);
+    Ok(mmap)
+}
+
+/// Private view of an image for a fork: a copy-on-write mapping of `file`,
+/// or a copy of `image` when there is no file
+#[cfg(not(target_arch = "wasm32"))]
+fn fork_mapping(file: Option<&File>, image: &[u8]) -> Result<Mapping> {
+    let mmap = match file {
+        // SAFETY: The owner of the fork keeps the file unchanged while it is
+        // mapped (see `Storage::fork`); the fork's own writes stay private.
+        Some(file) => unsafe { MmapOptions::new().map_copy(file) }
+            .context("Failed to map index file copy-on-write")?,
+        None => private_copy(image, image.len())?,
+    };
+    Ok(Mapping::Private(mmap))
+}
+
+/// Maps `len` bytes of `file` starting at the page-aligned offset `base`
+#[cfg(not(target_arch = "wasm32"))]
+fn map_window(file: &File, base: u64, len: usize) -> Result<MmapMut> {
+    // SAFETY: The collection file is exclusively locked by its owner, and each
+    // window is mapped by exactly one storage.
+    unsafe { MmapOptions::new().offset(base).len(len).map_mut(file) }
+        .context("Failed to map collection")
+}
+
+/// Copies `src` into the start of `dst`, skipping all-zero pages
+///
+/// `dst` is a fresh region of a sparse file that already reads as zeros;
+/// skipping keeps untouched slack space from being allocated on disk.
+#[cfg(not(target_arch = "wasm32"))]
+fn copy_nonzero_pages(src: &[u8], dst: &mut [u8]) {
+    for (src, dst) in src.chunks(PAGE_SIZE).zip(dst.chunks_mut(PAGE_SIZE)) {
+        if src.iter().any(|&byte| byte != 0) {
+            dst[..src.len()].copy_from_slice(src);
+        }
+    }
+}
+
+/// Writes a window's placement into its catalog slot and syncs it
+#[cfg(not(target_arch = "wasm32"))]
+fn publish_window(file: &File, window: Window, len: u64) -> Result<()> {
+    use std::io::{Seek, SeekFrom, Write};
+
+    let mut slot = [0u8; 24];
+    slot[0..8].copy_from_slice(&window.base.to_le_bytes());
+    slot[8..16].copy_from_slice(&window.capacity.to_le_bytes());
+    slot[16..24].copy_from_slice(&len.to_le_bytes());
+
+    let mut file = file;
+    file.seek(SeekFrom::Start(window.slot))?;
+    file.write_all(&slot)?;
+    file.sync_data().context("Failed to update collection catalog")
+}
+
+/// Creates `dst` as a copy-on-write clone of `src`; returns `false` if unsupported
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn reflink(src: &File, dst: &Path) -> bool {
+    use std::os::fd::AsRawFd;
+
+    let Ok(out) = OpenOptions::new().write(true).create_new(true).open(dst) else {
+        return false;
+    };
+    // SAFETY: Both descriptors are open for the duration of the call.
+    unsafe { libc::ioctl(out.as_raw_fd(), libc::FICLONE, src.as_raw_fd()) == 0 }
+}
+
+/// Creates `dst` as a copy-on-write clone of `src`; returns `false` if unsupported
+#[cfg(target_vendor = "apple")]
+fn reflink(src: &File, dst: &Path) -> bool {
+    use std::os::fd::AsRawFd;
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(dst) = std::ffi::CString::new(dst.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: `dst` is NUL-terminated and the source descriptor is open.
+    unsafe { libc::fclonefileat(src.as_raw_fd(), libc::AT_FDCWD, dst.as_ptr(), 0) == 0 }
+}
+
+/// Creates `dst` as a copy-on-write clone of `src`; returns `false` if unsupported
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_vendor = "apple",
+    target_arch = "wasm32"
+)))]
+fn reflink(_src: &File, _dst: &Path) -> bool {
+    false
+}
+
+/// Makes a rename into `path`'s directory durable
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> Result<()> {
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    File::open(parent)?.sync_all()?;
+    Ok(())
+}
+
+/// Makes a rename into `path`'s directory durable (Windows renames are not
+/// synced through directory handles)
+#[cfg(all(not(unix), not(target_arch = "wasm32")))]
+fn sync_parent_dir(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
 impl Drop for Storage {
     fn drop(&mut self) {
+        // Finish a background sync before the lock is released
+        #[cfg(not(target_arch = "wasm32"))]
+        let synced = self.wait_for_background().is_ok();
+        #[cfg(target_arch = "wasm32")]
+        let synced = true;
+
+        // Nothing was written since the last commit: record a clean close
+        #[cfg(not(target_arch = "wasm32"))]
+        let graph_clean =
+            self.graph_file.as_ref().is_none_or(|graph_file| graph_file.dirty.ranges().is_empty());
+        #[cfg(target_arch = "wasm32")]
+        let graph_clean = true;
+        if synced
+            && graph_clean
+            && self.dirty.ranges().is_empty()
+            && self.tracks_writer_open()
+            && self.header().writer_open()
+        {
+            let _ = self.set_writer_open_synced(false);
+        }
+
+        // Sealed files were never locked
+        if self.is_sealed() {
+            return;
+        }
+
         // Explicitly unlock the file (happens automatically, but being explicit)
-        let _ = self.file.unlock();
+        // Collection storages share the collection file's lock and must not release it
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(file) = &self.file
+            && self.window.is_none()
+        {
+            let _ = file.unlock();
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(file) = self.graph_file.as_ref().and_then(|graph_file| graph_file.file.as_ref())
+        {
+            let _ = file.unlock();
+        }
+        #[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+        if let Some(encrypted) = &self.encrypted {
+            let _ = encrypted.file().unlock();
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::distance::Metric;
+    use crate::element::Query;
 
     #[test]
     fn test_truncate_logical() {
@@ -572,6 +3437,30 @@ mod tests {
         storage.truncate_logical(5);
     }
 
+    #[test]
+    fn test_commit_syncs_only_written_pages() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        let mut storage = Storage::open(temp_file.path(), 128).unwrap();
+        for i in 0..2000 {
+            storage.insert(&vec![i as f32; 128]).unwrap();
+        }
+        storage.commit().unwrap();
+        assert!(storage.dirty.ranges().is_empty());
+
+        // One more vector dirties the header page and the pages it lands on
+        storage.insert(&vec![1.0; 128]).unwrap();
+        let offset = HEADER_SIZE + 2000 * 128 * 4;
+        let vector_pages =
+            offset - offset % PAGE_SIZE..(offset + 128 * 4).next_multiple_of(PAGE_SIZE);
+        assert_eq!(
+            storage.dirty.ranges(),
+            [0..HEADER_SIZE.next_multiple_of(PAGE_SIZE), vector_pages]
+        );
+
+        storage.commit().unwrap();
+        assert!(storage.dirty.ranges().is_empty());
+    }
+
     #[test]
     fn test_vector_end_and_graph_offset_helpers() {
         let temp_file = tempfile::NamedTempFile::new().unwrap();
@@ -587,6 +3476,30 @@ mod tests {
         assert_eq!(storage.graph_offset(), Some(8192));
     }
 
+    #[test]
+    fn test_half_width_vectors_use_two_bytes_per_component() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        let mut storage = Storage::open(temp_file.path(), 128).unwrap();
+        storage.set_element_type(ElementType::F16).unwrap();
+
+        storage.insert(&vec![1.5; 128]).unwrap();
+        storage.insert(&vec![-0.25; 128]).unwrap();
+        assert_eq!(storage.vector_end().unwrap(), HEADER_SIZE + 2 * 128 * 2);
+        assert_eq!(storage.get_vector(1).unwrap(), vec![-0.25; 128]);
+        let query = Query::new(&[1.5; 128], Metric::Euclidean);
+        assert_eq!(storage.stored_vector(0).unwrap().distance_to(&query, Metric::Euclidean), 0.0);
+
+        // The encoding is fixed once vectors are stored
+        let err = storage.set_element_type(ElementType::BF16).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+
+        storage.commit().unwrap();
+        drop(storage);
+        let storage = Storage::open(temp_file.path(), 128).unwrap();
+        assert_eq!(storage.element_type(), ElementType::F16);
+        assert_eq!(storage.get_vector(0).unwrap(), vec![1.5; 128]);
+    }
+
     #[test]
     fn test_move_graph_zone_compacts_file() {
         let temp_file = tempfile::NamedTempFile::new().unwrap();
@@ -604,8 +3517,170 @@ mod tests {
         assert_eq!(storage.graph_offset(), Some(new_offset as u64));
         assert_eq!(storage.graph_zone(new_offset, graph_bytes.len()).unwrap(), graph_bytes);
         assert_eq!(
-            storage.file.metadata().unwrap().len(),
+            storage.file.as_ref().unwrap().metadata().unwrap().len(),
             Storage::page_align(new_offset + graph_bytes.len()) as u64
         );
     }
+
+    #[test]
+    fn test_metadata_section_survives_growth_and_reopen() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+
+        {
+            let mut storage = Storage::open(temp_file.path(), 128).unwrap();
+            storage.put_metadata_section(b"TESTSECT", b"payload-one").unwrap();
+            storage.put_metadata_section(b"OTHERSEC", &[9u8; 40]).unwrap();
+            storage.put_metadata_section(b"TESTSECT", b"payload-two").unwrap();
+
+            // Grow well past the original metadata location
+            let (offset, _) = storage.header().metadata_zone().unwrap();
+            storage.ensure_graph_capacity(offset as usize + 4096).unwrap();
+            assert!(storage.header().metadata_zone().unwrap().0 > offset);
+
+            storage.commit().unwrap();
+        }
+
+        let storage = Storage::open(temp_file.path(), 128).unwrap();
+        assert_eq!(storage.metadata_section(b"TESTSECT").unwrap(), Some(&b"payload-two"[..]));
+        assert_eq!(storage.metadata_section(b"OTHERSEC").unwrap(), Some(&[9u8; 40][..]));
+        assert_eq!(storage.metadata_section(b"MISSING\0").unwrap(), None);
+    }
+
+    #[test]
+    fn test_torn_metadata_zone_falls_back_to_previous() {
+        use std::io::{Seek, SeekFrom, Write};
+
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+
+        {
+            let mut storage = Storage::open(temp_file.path(), 128).unwrap();
+            storage.put_metadata_section(b"TESTSECT", b"payload-one").unwrap();
+            storage.commit().unwrap();
+
+            // The committed zone stays untouched until the next commit lands
+            let (offset, len) = storage.header().metadata_zone().unwrap();
+            let committed = storage.zone_bytes((offset, len)).unwrap().to_vec();
+            storage.put_metadata_section(b"TESTSECT", b"payload-two").unwrap();
+            assert_ne!(storage.header().metadata_zone().unwrap().0, offset);
+            assert_eq!(storage.zone_bytes((offset, len)).unwrap(), committed);
+            storage.commit().unwrap();
+        }
+
+        // Tear the newest zone as a crash mid-write would
+        let (offset, len) = {
+            let storage = Storage::open(temp_file.path(), 128).unwrap();
+            storage.header().metadata_zone().unwrap()
+        };
+        let mut file = std::fs::OpenOptions::new().write(true).open(temp_file.path()).unwrap();
+        file.seek(SeekFrom::Start(offset + len - 4)).unwrap();
+        file.write_all(&[0xFF; 4]).unwrap();
+        drop(file);
+
+        let storage = Storage::open(temp_file.path(), 128).unwrap();
+        assert_eq!(storage.metadata_section(b"TESTSECT").unwrap(), Some(&b"payload-one"[..]));
+    }
+
+    #[test]
+    fn test_map_changes_append_to_the_journal() {
+        use crate::groups::GroupMap;
+
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+
+        let len = {
+            let mut storage = Storage::open(temp_file.path(), 128).unwrap();
+            storage.put_metadata_section(b"TESTSECT", b"kept").unwrap();
+            let mut groups = GroupMap::default();
+            for id in 0..100 {
+                groups.set(id, Some(id % 7));
+            }
+            storage.write_map(&mut groups).unwrap();
+            storage.commit().unwrap();
+            let (offset, len) = storage.header().metadata_zone().unwrap();
+
+            // A small change is appended after the committed bytes
+            groups.set(3, None);
+            groups.set(120, Some(1));
+            storage.write_map(&mut groups).unwrap();
+            storage.commit().unwrap();
+            let (grown_offset, grown_len) = storage.header().metadata_zone().unwrap();
+            assert_eq!(grown_offset, offset);
+            assert!(grown_len > len && grown_len < len + 512);
+            grown_len
+        };
+
+        let mut storage = Storage::open(temp_file.path(), 128).unwrap();
+        let mut groups: GroupMap = storage.read_map().unwrap();
+        assert_eq!(groups.get(3), None);
+        assert_eq!(groups.get(4), Some(4));
+        assert_eq!(groups.get(110), None);
+        assert_eq!(groups.get(120), Some(1));
+        assert_eq!(storage.metadata_section(b"TESTSECT").unwrap(), Some(&b"kept"[..]));
+
+        // Records that outgrow the section are folded back into it
+        for round in 0..200 {
+            groups.set(round % 100, Some(round));
+            storage.write_map(&mut groups).unwrap();
+        }
+        let (_, compacted_len) = storage.header().metadata_zone().unwrap();
+        assert!(compacted_len < len + 4096 + 512);
+        assert_eq!(storage.read_map::<GroupMap>().unwrap().to_bytes(), groups.to_bytes());
+        assert_eq!(storage.metadata_section(b"TESTSECT").unwrap(), Some(&b"kept"[..]));
+    }
+
+    #[test]
+    fn test_torn_journal_record_is_dropped_and_overwritten() {
+        use crate::groups::GroupMap;
+        use std::io::{Seek, SeekFrom, Write};
+
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+
+        {
+            let mut storage = Storage::open(temp_file.path(), 128).unwrap();
+            let mut groups = GroupMap::default();
+            groups.set(0, Some(1));
+            storage.write_map(&mut groups).unwrap();
+            groups.set(1, Some(2));
+            storage.write_map(&mut groups).unwrap();
+            groups.set(2, Some(3));
+            storage.write_map(&mut groups).unwrap();
+            storage.commit().unwrap();
+        }
+
+        // Tear the last record as a crash mid-write would
+        let (offset, len) = {
+            let storage = Storage::open(temp_file.path(), 128).unwrap();
+            storage.header().metadata_zone().unwrap()
+        };
+        let mut file = std::fs::OpenOptions::new().write(true).open(temp_file.path()).unwrap();
+        file.seek(SeekFrom::Start(offset + len - 8)).unwrap();
+        file.write_all(&[0xFF; 4]).unwrap();
+        drop(file);
+
+        let mut storage = Storage::open(temp_file.path(), 128).unwrap();
+        let mut groups: GroupMap = storage.read_map().unwrap();
+        assert_eq!(groups.get(1), Some(2));
+        assert_eq!(groups.get(2), None);
+
+        // The next record replaces the torn one rather than landing behind it
+        storage.repair_metadata_zone().unwrap();
+        assert!(storage.header().metadata_zone().unwrap().1 < len);
+        groups.set(5, Some(6));
+        storage.write_map(&mut groups).unwrap();
+        let restored: GroupMap = storage.read_map().unwrap();
+        assert_eq!(restored.get(1), Some(2));
+        assert_eq!(restored.get(5), Some(6));
+    }
+
+    #[test]
+    fn test_move_graph_zone_preserves_metadata() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        let mut storage = Storage::open(temp_file.path(), 128).unwrap();
+
+        let old_offset = 64 * 1024;
+        storage.ensure_graph_capacity(old_offset + 64).unwrap();
+        storage.put_metadata_section(b"TESTSECT", b"kept").unwrap();
+
+        storage.move_graph_zone(old_offset, 8 * 1024, 64).unwrap();
+        assert_eq!(storage.metadata_section(b"TESTSECT").unwrap(), Some(&b"kept"[..]));
+    }
 }
@@ -0,0 +1,335 @@
+//! Tag sets attached to vectors, for filtered search.
+//!
+//! Local apps often scope a search to one user, folder or label. Each vector
+//! carries a set of small tag IDs (`0..=MAX_TAG`) chosen by the application,
+//! set with `VectorIndex::add_with_tags()` or `set_tags()`, and
+//! `VectorIndex::search_with_tags()` returns only vectors whose tags match.
+//!
+//! The sets are bitmaps of `words` 64-bit words per vector, indexed by vector
+//! ID, so a tag test is one load and a mask. The width grows to fit the
+//! highest tag used. The map is persisted as a metadata section on flush:
+//!
+//! ```text
+//! Offset  Size               Field
+//! ------  ----               -----
+//! 0       8                  count: u64
+//! 8       8                  words: u64 (per vector)
+//! 16      8 * count * words  tag bitmap of each vector ID: u64 words, tag t
+//!                            in bit t % 64 of word t / 64
+//! ```
+//!
+//! The bitmaps changed by a flush go to a journal record instead: `count`,
+//! `words` and a `u64` number of entries, each a vector ID followed by its
+//! `words` bitmap words. Widening changes every bitmap, so it is written as a
+//! full section.
+
+use crate::error::{ErrorKind, Tagged};
+use crate::metadata::{Changes, JournaledMap, SectionTag};
+use anyhow::Result;
+use std::collections::BTreeSet;
+
+/// Metadata section tag for the persisted tag map.
+pub(crate) const TAGS_SECTION: &SectionTag = b"TAGS\0\0\0\0";
+
+/// Highest tag ID a vector can carry (bitmaps are at most 128 bytes per vector)
+pub const MAX_TAG: u32 = 1023;
+
+/// Tag bitmap of each vector, indexed by vector ID.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TagMap {
+    /// `words` bitmap words per vector; may cover fewer vectors than the index
+    bits: Vec<u64>,
+
+    /// Bitmap words per vector (0 while no vector has a tag)
+    words: usize,
+
+    /// IDs whose bitmap changed since it was last written to the file
+    changes: Changes,
+}
+
+/// Tags a search requires and rejects, as bitmaps
+#[derive(Debug)]
+pub(crate) struct TagFilter {
+    /// A vector needs at least one of these tags (empty: no requirement)
+    include: Vec<u64>,
+
+    /// A vector must have none of these tags
+    exclude: Vec<u64>,
+}
+
+/// Bitmap words needed to hold `tag`
+fn words_for(tag: u32) -> usize {
+    tag as usize / 64 + 1
+}
+
+/// Bitmap of `tags`, `words` words wide (tags must fit)
+fn bitmap(tags: &[u32], words: usize) -> Vec<u64> {
+    let mut bits = vec![0; words];
+    for &tag in tags {
+        bits[tag as usize / 64] |= 1 << (tag % 64);
+    }
+    bits
+}
+
+impl TagMap {
+    /// Check that every tag in `tags` can be stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidArgument` for tags above `MAX_TAG`.
+    pub(crate) fn validate(tags: &[u32]) -> Result<()> {
+        if let Some(&tag) = tags.iter().find(|&&tag| tag > MAX_TAG) {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                format!("Tag {} out of range (tags are 0 to {})", tag, MAX_TAG)
+            ));
+        }
+        Ok(())
+    }
+
+    /// Tags of vector `id`, in ascending order
+    pub(crate) fn get(&self, id: u64) -> Vec<u32> {
+        let Some(bits) = self.bitmap(id) else {
+            return Vec::new();
+        };
+        let mut tags = Vec::new();
+        for (word_index, &word) in bits.iter().enumerate() {
+            let mut word = word;
+            while word != 0 {
+                tags.push(word_index as u32 * 64 + word.trailing_zeros());
+                word &= word - 1;
+            }
+        }
+        tags
+    }
+
+    /// Replace the tags of vector `id` (already validated)
+    pub(crate) fn set(&mut self, id: u64, tags: &[u32]) {
+        let id = id as usize;
+        let words = tags.iter().map(|&tag| words_for(tag)).max().unwrap_or(0);
+        if words > self.words {
+            self.widen(words);
+        }
+        if self.words == 0 {
+            return;
+        }
+
+        let end = (id + 1) * self.words;
+        if end > self.bits.len() {
+            if tags.is_empty() {
+                return;
+            }
+            self.changes.touch_range(self.len() as u64..id as u64);
+            self.bits.resize(end, 0);
+        }
+        self.bits[id * self.words..end].copy_from_slice(&bitmap(tags, self.words));
+        self.changes.touch(id as u64);
+    }
+
+    /// Re-lay the bitmaps out with `words` words per vector
+    fn widen(&mut self, words: usize) {
+        let count = self.len();
+        let mut bits = vec![0; count * words];
+        for id in 0..count {
+            let old = &self.bits[id * self.words..(id + 1) * self.words];
+            bits[id * words..id * words + self.words].copy_from_slice(old);
+        }
+        self.bits = bits;
+        self.words = words;
+        self.changes.touch_all();
+    }
+
+    /// Vectors covered by the map
+    fn len(&self) -> usize {
+        self.bits.len().checked_div(self.words).unwrap_or(0)
+    }
+
+    fn bitmap(&self, id: u64) -> Option<&[u64]> {
+        let start = usize::try_from(id).ok()?.checked_mul(self.words)?;
+        self.bits.get(start..start + self.words)
+    }
+
+    /// Build the filter for a search requiring any of `include` (unless
+    /// empty) and none of `exclude` (both already validated)
+    pub(crate) fn filter(&self, include: &[u32], exclude: &[u32]) -> TagFilter {
+        let words = include.iter().chain(exclude).map(|&tag| words_for(tag)).max().unwrap_or(0);
+        TagFilter { include: bitmap(include, words), exclude: bitmap(exclude, words) }
+    }
+
+    /// Returns `true` if the tags of vector `id` pass `filter`
+    #[inline]
+    pub(crate) fn matches(&self, filter: &TagFilter, id: u64) -> bool {
+        let bits = self.bitmap(id).unwrap_or(&[]);
+        let word = |index: usize| bits.get(index).copied().unwrap_or(0);
+
+        let excluded =
+            filter.exclude.iter().enumerate().any(|(index, &mask)| word(index) & mask != 0);
+        let included = filter.include.iter().all(|&mask| mask == 0)
+            || filter.include.iter().enumerate().any(|(index, &mask)| word(index) & mask != 0);
+        included && !excluded
+    }
+
+    /// Drop tags of IDs at or past `count` (vectors rolled back on open)
+    pub(crate) fn truncate(&mut self, count: u64) {
+        let len = usize::try_from(count).unwrap_or(usize::MAX).saturating_mul(self.words);
+        if self.bits.len() > len {
+            self.changes.touch_range(count..self.len() as u64);
+            self.bits.truncate(len);
+        }
+    }
+
+    /// Heap bytes held by the map
+    pub(crate) fn heap_bytes(&self) -> usize {
+        self.bits.capacity() * std::mem::size_of::<u64>()
+    }
+}
+
+impl JournaledMap for TagMap {
+    const TAG: &'static SectionTag = TAGS_SECTION;
+
+    fn changes(&self) -> &Changes {
+        &self.changes
+    }
+
+    fn changes_mut(&mut self) -> &mut Changes {
+        &mut self.changes
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 * (self.bits.len() + 2));
+        bytes.extend_from_slice(&(self.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.words as u64).to_le_bytes());
+        for word in &self.bits {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (chunks, rest) = bytes.as_chunks::<8>();
+        let field = |index: usize| chunks.get(index).map(|chunk| u64::from_le_bytes(*chunk));
+        let size = match (field(0), field(1)) {
+            (Some(count), Some(words)) if words <= words_for(MAX_TAG) as u64 => {
+                count.checked_mul(words).and_then(|len| len.checked_add(2))
+            }
+            _ => None,
+        };
+        if !rest.is_empty() || size != Some(chunks.len() as u64) {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::Corrupted,
+                format!("Tag section size mismatch: {} bytes", bytes.len())
+            ));
+        }
+
+        let words = field(1).unwrap_or(0) as usize;
+        let bits = chunks[2..].iter().map(|chunk| u64::from_le_bytes(*chunk)).collect();
+        Ok(Self { bits, words, changes: Changes::default() })
+    }
+
+    fn delta_bytes(&self, ids: &BTreeSet<u64>) -> Vec<u8> {
+        let changed: Vec<_> = ids.range(..self.len() as u64).collect();
+        let mut bytes = Vec::with_capacity(8 * (3 + changed.len() * (self.words + 1)));
+        bytes.extend_from_slice(&(self.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.words as u64).to_le_bytes());
+        bytes.extend_from_slice(&(changed.len() as u64).to_le_bytes());
+        for &id in changed {
+            bytes.extend_from_slice(&id.to_le_bytes());
+            for word in self.bitmap(id).unwrap_or(&[]) {
+                bytes.extend_from_slice(&word.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    fn apply_delta(&mut self, bytes: &[u8]) -> Result<()> {
+        let corrupted = || Tagged::new(ErrorKind::Corrupted, "Tag journal record is malformed");
+
+        let (chunks, rest) = bytes.as_chunks::<8>();
+        let [count, words, entries, bitmaps @ ..] = chunks else {
+            anyhow::bail!(corrupted());
+        };
+        let count = usize::try_from(u64::from_le_bytes(*count)).map_err(|_| corrupted())?;
+        let words = u64::from_le_bytes(*words);
+        let entries = u64::from_le_bytes(*entries);
+        let size = entries.checked_mul(words + 1);
+        if !rest.is_empty() || words != self.words as u64 || size != Some(bitmaps.len() as u64) {
+            anyhow::bail!(corrupted());
+        }
+        if self.words == 0 {
+            return Ok(());
+        }
+
+        self.bits.resize(count.checked_mul(self.words).ok_or_else(corrupted)?, 0);
+        for entry in bitmaps.chunks_exact(self.words + 1) {
+            let id = u64::from_le_bytes(entry[0]);
+            let start = usize::try_from(id).ok().and_then(|id| id.checked_mul(self.words));
+            let bits = start.and_then(|start| self.bits.get_mut(start..start + self.words));
+            for (word, chunk) in bits.ok_or_else(corrupted)?.iter_mut().zip(&entry[1..]) {
+                *word = u64::from_le_bytes(*chunk);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_map_roundtrip_and_widening() {
+        let mut map = TagMap::default();
+        map.set(2, &[]);
+        assert!(map.changes().is_empty());
+
+        map.set(1, &[3, 5]);
+        map.set(4, &[0]);
+        assert_eq!(map.get(1), vec![3, 5]);
+
+        // A high tag widens every bitmap without losing the others
+        map.set(2, &[700, 3]);
+        assert_eq!(map.get(1), vec![3, 5]);
+        assert_eq!(map.get(2), vec![3, 700]);
+        assert_eq!(map.get(4), vec![0]);
+        assert_eq!(map.get(9), Vec::<u32>::new());
+
+        let restored = TagMap::from_bytes(&map.to_bytes()).unwrap();
+        assert_eq!(restored.get(2), vec![3, 700]);
+        assert!(restored.changes().is_empty());
+
+        let bytes = map.to_bytes();
+        assert!(TagMap::from_bytes(&bytes[..bytes.len() - 8]).is_err());
+        assert!(TagMap::from_bytes(&[]).is_err());
+
+        let mut restored = restored;
+        map.changes_mut().rewritten(0);
+        map.truncate(2);
+        map.set(3, &[64]);
+        assert_eq!(map.get(2), Vec::<u32>::new());
+        assert_eq!(map.get(1), vec![3, 5]);
+
+        restored.apply_delta(&map.delta_bytes(map.changes().ids().unwrap())).unwrap();
+        assert_eq!(restored.to_bytes(), map.to_bytes());
+        assert!(TagMap::default().apply_delta(&map.delta_bytes(&BTreeSet::new())).is_err());
+    }
+
+    #[test]
+    fn test_tag_filter() {
+        let mut map = TagMap::default();
+        map.set(0, &[1]);
+        map.set(1, &[1, 2]);
+        map.set(2, &[3]);
+
+        let matching = |include: &[u32], exclude: &[u32]| -> Vec<u64> {
+            let filter = map.filter(include, exclude);
+            (0..4).filter(|&id| map.matches(&filter, id)).collect()
+        };
+        assert_eq!(matching(&[1], &[]), vec![0, 1]);
+        assert_eq!(matching(&[1, 3], &[2]), vec![0, 2]);
+        assert_eq!(matching(&[], &[1]), vec![2, 3]);
+        assert_eq!(matching(&[], &[]), vec![0, 1, 2, 3]);
+        assert_eq!(matching(&[900], &[]), Vec::<u64>::new());
+        assert!(TagMap::validate(&[MAX_TAG]).is_ok());
+        assert!(TagMap::validate(&[MAX_TAG + 1]).is_err());
+    }
+}
@@ -0,0 +1,406 @@
+//! Two-tier index: an exact in-memory recent tier in front of the HNSW graph.
+//!
+//! Inserting into the graph costs a neighbor search plus backlink writes per
+//! vector. `TieredIndex` defers that work: new vectors land in a flat,
+//! in-memory tier that is searched exactly and merged with the graph results,
+//! so they are searchable immediately. Vectors are later moved into the graph
+//! in batches via `migrate()`, oldest first.
+//!
+//! # IDs
+//!
+//! Migration preserves insertion order, so a vector's final graph ID is known
+//! at insert time (`graph.len() + position in the recent tier`). IDs returned
+//! by `add()` never change. Migration always appends, so with
+//! `IdReuse::ReuseDeleted` the slots of deleted vectors are reused only by
+//! inserts made directly on the main index.
+//!
+//! # Durability
+//!
+//! The recent tier lives only in memory. Vectors in it are lost on crash until
+//! they have been migrated and flushed; `flush()` migrates everything first.
+
+use crate::error::{ErrorKind, Tagged};
+use crate::{
+    MemoryFootprint, SearchConsistency, SearchOptions, SearchResult, VectorIndex, WorkloadProfile,
+};
+use anyhow::Result;
+
+/// Configuration for `TieredIndex`
+#[derive(Debug, Clone)]
+pub struct TieredOptions {
+    /// Maximum number of vectors held in the recent tier.
+    ///
+    /// When `add()` would exceed this, one batch is migrated synchronously
+    /// first. Bounds both memory use and the cost of the exact scan.
+    pub max_recent: usize,
+
+    /// Number of vectors moved into the graph per `migrate()` call
+    pub migration_batch: usize,
+}
+
+impl Default for TieredOptions {
+    fn default() -> Self {
+        Self { max_recent: 4096, migration_batch: 256 }
+    }
+}
+
+/// Vector index with an exact in-memory tier for recent inserts
+#[derive(Debug)]
+pub struct TieredIndex {
+    /// On-disk HNSW index (main tier)
+    main: VectorIndex,
+
+    /// Recent vectors, contiguous with stride `main.dimensions()`
+    recent: Vec<f32>,
+
+    /// Tier configuration
+    options: TieredOptions,
+}
+
+impl TieredIndex {
+    /// Wrap an opened index with an empty recent tier
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `max_recent` or `migration_batch` is zero.
+    pub fn new(main: VectorIndex, options: TieredOptions) -> Result<Self> {
+        if options.max_recent == 0 || options.migration_batch == 0 {
+            anyhow::bail!(Tagged::new(
+                ErrorKind::InvalidArgument,
+                "max_recent and migration_batch must be greater than zero"
+            ));
+        }
+
+        Ok(Self { main, recent: Vec::new(), options })
+    }
+
+    /// Add a vector to the recent tier
+    ///
+    /// The vector is searchable immediately. If the recent tier is full, one
+    /// batch is migrated into the graph before the vector is accepted.
+    ///
+    /// # Returns
+    ///
+    /// The ID the vector will keep after migration
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the vector dimensions don't match or the forced
+    /// migration fails.
+    pub fn add(&mut self, vector: &[f32]) -> Result<u64> {
+        let vector = self.main.stored_prefix(vector, "Vector")?;
+
+        if self.recent_len() >= self.options.max_recent {
+            self.migrate()?;
+        }
+
+        let id = self.main.len() + self.recent_len() as u64;
+        self.recent.extend_from_slice(&vector);
+        Ok(id)
+    }
+
+    /// Search both tiers and merge the results
+    ///
+    /// The graph is searched approximately, the recent tier exactly.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions
+    pub fn search(&self, query: &[f32], k: usize) -> Result<Vec<SearchResult>> {
+        self.search_with_options(query, k, &SearchOptions::default())
+    }
+
+    /// Search both tiers with per-search options
+    ///
+    /// `SearchConsistency::DurableOnly` skips the recent tier entirely, since
+    /// none of it has been flushed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions
+    pub fn search_with_options(
+        &self,
+        query: &[f32],
+        k: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchResult>> {
+        let mut results = self.main.search_with_options(query, k, options)?;
+        if options.consistency == SearchConsistency::DurableOnly {
+            return Ok(results);
+        }
+
+        let query = self.main.stored_prefix(query, "Query")?;
+        let metric = self.main.graph.metric();
+
+        let base = self.main.len();
+        results.extend(self.recent_vectors().enumerate().map(|(i, v)| SearchResult {
+            id: base + i as u64,
+            distance: metric.reported(metric.compute(&query, v)),
+        }));
+
+        results.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        results.truncate(k);
+        Ok(results)
+    }
+
+    /// Find all vectors within `max_distance` of the query in both tiers
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions, or
+    /// if `max_distance` is negative or NaN
+    pub fn search_within(&self, query: &[f32], max_distance: f32) -> Result<Vec<SearchResult>> {
+        self.search_within_with_options(query, max_distance, &SearchOptions::default())
+    }
+
+    /// Find all vectors within `max_distance` of the query, with per-search options
+    ///
+    /// As with `search_with_options()`, `DurableOnly` skips the recent tier.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if query dimensions don't match index dimensions, or
+    /// if `max_distance` is negative or NaN
+    pub fn search_within_with_options(
+        &self,
+        query: &[f32],
+        max_distance: f32,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchResult>> {
+        let mut results = self.main.search_within_with_options(query, max_distance, options)?;
+        if options.consistency == SearchConsistency::DurableOnly {
+            return Ok(results);
+        }
+
+        let query = self.main.stored_prefix(query, "Query")?;
+        let metric = self.main.graph.metric();
+
+        let base = self.main.len();
+        results.extend(
+            self.recent_vectors()
+                .enumerate()
+                .map(|(i, v)| SearchResult {
+                    id: base + i as u64,
+                    distance: metric.reported(metric.compute(&query, v)),
+                })
+                .filter(|result| result.distance <= max_distance),
+        );
+
+        results.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        Ok(results)
+    }
+
+    /// Move up to `migration_batch` of the oldest recent vectors into the graph
+    ///
+    /// Call this from idle time or a maintenance loop to keep the recent tier
+    /// small. Each moved vector pays the normal graph insert cost, but searches
+    /// stay correct between calls.
+    ///
+    /// # Returns
+    ///
+    /// The number of vectors migrated
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a graph insert fails. Vectors inserted before the
+    /// failure are removed from the recent tier; the rest stay there.
+    pub fn migrate(&mut self) -> Result<usize> {
+        self.migrate_up_to(self.options.migration_batch)
+    }
+
+    /// Move up to `batch` of the oldest recent vectors into the graph; see `migrate()`
+    pub(crate) fn migrate_up_to(&mut self, batch: usize) -> Result<usize> {
+        let batch = batch.min(self.recent_len());
+        let dims = self.main.dimensions() as usize;
+
+        let mut migrated = 0;
+        let mut result = Ok(());
+        for vector in self.recent.chunks_exact(dims).take(batch) {
+            if let Err(e) = self.main.append_stored(vector) {
+                result = Err(e);
+                break;
+            }
+            migrated += 1;
+        }
+
+        // Drop only what reached the graph so IDs stay aligned with positions
+        self.recent.drain(..migrated * dims);
+        result.map(|()| migrated)
+    }
+
+    /// Migrate the whole recent tier into the graph
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a graph insert fails
+    pub fn migrate_all(&mut self) -> Result<()> {
+        while !self.recent.is_empty() {
+            self.migrate()?;
+        }
+        Ok(())
+    }
+
+    /// Migrate the recent tier and flush the graph to disk
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if migration or the flush fails
+    pub fn flush(&mut self) -> Result<()> {
+        self.migrate_all()?;
+        self.main.flush()
+    }
+
+    /// Migrate the recent tier and return the underlying index
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if migration fails
+    pub fn into_inner(mut self) -> Result<VectorIndex> {
+        self.migrate_all()?;
+        Ok(self.main)
+    }
+
+    /// Estimate the memory used by both tiers
+    ///
+    /// The recent tier is counted in `heap_bytes`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the OS rejects the residency query.
+    pub fn memory_footprint(&self) -> Result<MemoryFootprint> {
+        let mut footprint = self.main.memory_footprint()?;
+        footprint.heap_bytes += (self.recent.capacity() * std::mem::size_of::<f32>()) as u64;
+        Ok(footprint)
+    }
+
+    /// Summarize both tiers and their workload, without any vector data
+    ///
+    /// `vectors` includes the recent tier; `inserts` counts vectors once they
+    /// are migrated into the graph.
+    pub fn export_workload_profile(&self) -> WorkloadProfile {
+        let mut profile = self.main.export_workload_profile();
+        profile.vectors = self.len();
+        profile
+    }
+
+    /// Get the main (graph) tier
+    pub fn main(&self) -> &VectorIndex {
+        &self.main
+    }
+
+    /// Get the number of vectors waiting in the recent tier
+    pub fn recent_len(&self) -> usize {
+        self.recent.len() / self.main.dimensions() as usize
+    }
+
+    /// Get the total number of vectors across both tiers
+    pub fn len(&self) -> u64 {
+        self.main.len() + self.recent_len() as u64
+    }
+
+    /// Check if both tiers are empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate over recent vectors, oldest first
+    fn recent_vectors(&self) -> impl Iterator<Item = &[f32]> {
+        self.recent.chunks_exact(self.main.dimensions() as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IndexOptions;
+    use tempfile::NamedTempFile;
+
+    fn create_tiered(options: TieredOptions) -> (TieredIndex, NamedTempFile) {
+        let temp_file = NamedTempFile::new().unwrap();
+        let main = VectorIndex::open(temp_file.path(), 8, IndexOptions::default()).unwrap();
+        (TieredIndex::new(main, options).unwrap(), temp_file)
+    }
+
+    #[test]
+    fn test_recent_vectors_are_searchable_before_migration() {
+        let (mut index, _temp) = create_tiered(TieredOptions::default());
+        for i in 0..10 {
+            assert_eq!(index.add(&[i as f32; 8]).unwrap(), i);
+        }
+
+        assert_eq!(index.main().len(), 0);
+        assert_eq!(index.recent_len(), 10);
+
+        let results = index.search(&[3.0; 8], 2).unwrap();
+        assert_eq!(results[0].id, 3);
+        assert_eq!(results[0].distance, 0.0);
+        assert_eq!(results.len(), 2);
+    }
+
+    #[test]
+    fn test_migration_preserves_ids() {
+        let (mut index, _temp) =
+            create_tiered(TieredOptions { max_recent: 16, migration_batch: 5 });
+        for i in 0..40 {
+            assert_eq!(index.add(&[i as f32; 8]).unwrap(), i);
+        }
+        assert!(index.recent_len() <= 16);
+        assert_eq!(index.len(), 40);
+
+        assert_eq!(index.migrate().unwrap(), 5);
+        for i in 0..40 {
+            assert_eq!(index.search(&[i as f32; 8], 1).unwrap()[0].id, i);
+        }
+
+        let main = index.into_inner().unwrap();
+        assert_eq!(main.len(), 40);
+        assert_eq!(main.search(&[17.0; 8], 1).unwrap()[0].id, 17);
+    }
+
+    #[test]
+    fn test_search_within_merges_both_tiers() {
+        let (mut index, _temp) =
+            create_tiered(TieredOptions { max_recent: 64, migration_batch: 10 });
+        for i in 0..20 {
+            index.add(&[i as f32; 8]).unwrap();
+        }
+        index.migrate().unwrap();
+
+        // Ids 8..=12 straddle the graph (0..10) and the recent tier (10..20)
+        let results = index.search_within(&[10.0; 8], 6.0).unwrap();
+        let mut ids: Vec<u64> = results.iter().map(|r| r.id).collect();
+        assert_eq!(ids[0], 10);
+        ids.sort_unstable();
+        assert_eq!(ids, vec![8, 9, 10, 11, 12]);
+    }
+
+    #[test]
+    fn test_migration_keeps_ids_with_id_reuse() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let options = IndexOptions { id_reuse: crate::IdReuse::ReuseDeleted, ..Default::default() };
+        let mut main = VectorIndex::open(temp_file.path(), 8, options).unwrap();
+        for i in 0..4 {
+            main.add(&[i as f32; 8]).unwrap();
+        }
+        main.delete(1).unwrap();
+
+        let mut index = TieredIndex::new(main, TieredOptions::default()).unwrap();
+        assert_eq!(index.add(&[40.0; 8]).unwrap(), 4);
+        index.migrate_all().unwrap();
+
+        // The deleted slot stays free instead of taking the promised ID's vector
+        assert_eq!(index.search(&[40.0; 8], 1).unwrap()[0].id, 4);
+        assert_eq!(index.len(), 5);
+        let mut main = index.into_inner().unwrap();
+        assert_eq!(main.add(&[50.0; 8]).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_invalid_options_rejected() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let main = VectorIndex::open(temp_file.path(), 8, IndexOptions::default()).unwrap();
+        assert!(
+            TieredIndex::new(main, TieredOptions { max_recent: 0, migration_batch: 1 }).is_err()
+        );
+    }
+}
@@ -0,0 +1,330 @@
+//! Background writer thread with prioritized insert queues.
+//!
+//! Chassis allows a single writer (see ADR-0003). `IndexWriter` moves that
+//! writer onto its own thread so callers on other threads can submit inserts
+//! without holding `&mut VectorIndex`. Work is queued in two classes:
+//!
+//! - `InsertPriority::Interactive`: user-facing writes that should land fast
+//! - `InsertPriority::Background`: backfills and migrations
+//!
+//! The writer always drains interactive work first, so a single interactive
+//! insert waits for at most one in-flight background insert, not for the whole
+//! backfill queue. Within a class, work runs in submission order.
+//!
+//! When both queues are empty and the index has grown past its
+//! `flat_threshold` with vectors still unlinked, the writer links them into
+//! the graph `LINK_BATCH` at a time, so queued work waits for at most one
+//! batch.
+
+use crate::VectorIndex;
+use crate::error::{ErrorKind, Tagged};
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, SyncSender, sync_channel};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+/// Pending vectors the writer links between queued writes
+const LINK_BATCH: u64 = 64;
+
+/// Queue class for work submitted to an `IndexWriter`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum InsertPriority {
+    /// Runs before any queued background work
+    Interactive,
+
+    /// Runs when no interactive work is queued
+    #[default]
+    Background,
+}
+
+/// Result of work queued on an `IndexWriter`
+#[derive(Debug)]
+#[must_use = "dropping a Pending does not cancel the work, but discards its result"]
+pub struct Pending<T> {
+    reply: Receiver<Result<T>>,
+}
+
+impl<T> Pending<T> {
+    /// Block until the writer has run the work and return its result
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the insert or flush, or an error if the writer
+    /// thread panicked before running it.
+    pub fn wait(self) -> Result<T> {
+        self.reply.recv().unwrap_or_else(|_| {
+            Err(Tagged::new(ErrorKind::Other, "Writer thread exited before running the write")
+                .into())
+        })
+    }
+}
+
+/// Owns a `VectorIndex` on a writer thread and applies queued writes by priority
+#[derive(Debug)]
+pub struct IndexWriter {
+    shared: Arc<Shared>,
+    worker: Option<JoinHandle<VectorIndex>>,
+}
+
+impl IndexWriter {
+    /// Move `index` onto a new writer thread
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the thread cannot be spawned
+    pub fn spawn(index: VectorIndex) -> Result<Self> {
+        let shared = Arc::new(Shared::default());
+        shared.unlinked.store(index.unlinked_len(), Ordering::Relaxed);
+        let worker = std::thread::Builder::new()
+            .name("chassis-writer".into())
+            .spawn({
+                let shared = Arc::clone(&shared);
+                move || shared.run(index)
+            })
+            .map_err(|e| Tagged::new(ErrorKind::Io, format!("Failed to spawn writer: {}", e)))?;
+
+        Ok(Self { shared, worker: Some(worker) })
+    }
+
+    /// Queue a vector for insertion
+    ///
+    /// `Pending::wait()` returns the vector's ID once it is inserted (but not
+    /// yet flushed).
+    pub fn insert(&self, vector: Vec<f32>, priority: InsertPriority) -> Pending<u64> {
+        self.shared.submit(priority, |reply| Job::Insert { vector, reply })
+    }
+
+    /// Queue a flush of everything inserted before it
+    ///
+    /// An interactive flush covers interactive inserts queued before it and any
+    /// background inserts that already ran; queued background inserts may still
+    /// be pending when it runs.
+    pub fn flush(&self, priority: InsertPriority) -> Pending<()> {
+        self.shared.submit(priority, |reply| Job::Flush { reply })
+    }
+
+    /// Number of queued writes as `(interactive, background)`
+    pub fn queued(&self) -> (usize, usize) {
+        let queues = self.shared.lock();
+        (queues.interactive.len(), queues.background.len())
+    }
+
+    /// Number of vectors not yet linked into the graph, as of the last write
+    /// or linking step (see `IndexOptions::flat_threshold`)
+    pub fn unlinked_len(&self) -> u64 {
+        self.shared.unlinked.load(Ordering::Relaxed)
+    }
+
+    /// Run all queued writes, stop the writer thread, and return the index
+    ///
+    /// The index is not flushed; queue a flush first or call
+    /// `VectorIndex::flush()` on the result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the writer thread panicked
+    pub fn into_inner(mut self) -> Result<VectorIndex> {
+        self.shared.close();
+        let worker = self.worker.take().expect("writer thread is joined only once");
+        worker.join().map_err(|_| Tagged::new(ErrorKind::Other, "Writer thread panicked").into())
+    }
+}
+
+impl Drop for IndexWriter {
+    fn drop(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            self.shared.close();
+            let _ = worker.join();
+        }
+    }
+}
+
+/// A queued write and the channel for its result
+enum Job {
+    Insert { vector: Vec<f32>, reply: SyncSender<Result<u64>> },
+    Flush { reply: SyncSender<Result<()>> },
+}
+
+/// What the writer thread does next
+enum Next {
+    Job(Job),
+    /// Both queues are empty and the index has vectors to link
+    Link,
+    Closed,
+}
+
+#[derive(Default)]
+struct Queues {
+    interactive: VecDeque<Job>,
+    background: VecDeque<Job>,
+    closed: bool,
+}
+
+/// State shared between `IndexWriter` handles and the writer thread
+#[derive(Default)]
+struct Shared {
+    queues: Mutex<Queues>,
+    ready: Condvar,
+    unlinked: AtomicU64,
+}
+
+impl std::fmt::Debug for Shared {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Shared").finish_non_exhaustive()
+    }
+}
+
+impl Shared {
+    /// Lock the queues, ignoring poisoning (jobs run outside the lock)
+    fn lock(&self) -> std::sync::MutexGuard<'_, Queues> {
+        self.queues.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn submit<T>(
+        &self,
+        priority: InsertPriority,
+        job: impl FnOnce(SyncSender<Result<T>>) -> Job,
+    ) -> Pending<T> {
+        let (reply, pending) = sync_channel(1);
+        let mut queues = self.lock();
+        match priority {
+            InsertPriority::Interactive => queues.interactive.push_back(job(reply)),
+            InsertPriority::Background => queues.background.push_back(job(reply)),
+        }
+        drop(queues);
+
+        self.ready.notify_one();
+        Pending { reply: pending }
+    }
+
+    fn close(&self) {
+        self.lock().closed = true;
+        self.ready.notify_one();
+    }
+
+    /// Next job, interactive first, then linking if `link` is set;
+    /// `Closed` once closed and drained
+    fn next(&self, link: bool) -> Next {
+        let mut queues = self.lock();
+        loop {
+            if let Some(job) = queues.interactive.pop_front() {
+                return Next::Job(job);
+            }
+            if let Some(job) = queues.background.pop_front() {
+                return Next::Job(job);
+            }
+            if queues.closed {
+                return Next::Closed;
+            }
+            if link {
+                return Next::Link;
+            }
+            queues = self.ready.wait(queues).unwrap_or_else(std::sync::PoisonError::into_inner);
+        }
+    }
+
+    /// Writer thread body
+    fn run(&self, mut index: VectorIndex) -> VectorIndex {
+        // A failed linking step is retried only after the next write
+        let mut link_failed = false;
+        loop {
+            match self.next(!link_failed && index.wants_linking()) {
+                // Callers may have dropped their `Pending`; that is not an error
+                Next::Job(Job::Insert { vector, reply }) => {
+                    let _ = reply.send(index.add(&vector));
+                    link_failed = false;
+                }
+                Next::Job(Job::Flush { reply }) => {
+                    let _ = reply.send(index.flush());
+                    link_failed = false;
+                }
+                Next::Link => link_failed = index.link_pending(LINK_BATCH).is_err(),
+                Next::Closed => return index,
+            }
+            self.unlinked.store(index.unlinked_len(), Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IndexOptions;
+    use tempfile::NamedTempFile;
+
+    fn open_index(temp_file: &NamedTempFile) -> VectorIndex {
+        VectorIndex::open(temp_file.path(), 8, IndexOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_interactive_inserts_jump_ahead_of_background() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let writer = IndexWriter::spawn(open_index(&temp_file)).unwrap();
+
+        // Queue a backfill and one interactive insert while holding the lock, so
+        // the writer sees all of them at once
+        let mut queues = writer.shared.lock();
+        let mut background = Vec::new();
+        for i in 0..20 {
+            let (reply, pending) = sync_channel(1);
+            queues.background.push_back(Job::Insert { vector: vec![i as f32; 8], reply });
+            background.push(Pending { reply: pending });
+        }
+        let (reply, pending) = sync_channel(1);
+        queues.interactive.push_back(Job::Insert { vector: vec![100.0; 8], reply });
+        let interactive = Pending { reply: pending };
+        drop(queues);
+        writer.shared.ready.notify_one();
+
+        assert_eq!(interactive.wait().unwrap(), 0);
+        let ids: Vec<u64> = background.into_iter().map(|p| p.wait().unwrap()).collect();
+        assert_eq!(ids, (1..=20).collect::<Vec<_>>());
+
+        writer.flush(InsertPriority::Interactive).wait().unwrap();
+        let index = writer.into_inner().unwrap();
+        assert_eq!(index.durable_len(), 21);
+        assert_eq!(index.search(&[100.0; 8], 1).unwrap()[0].id, 0);
+    }
+
+    #[test]
+    fn test_idle_writer_links_vectors_past_flat_threshold() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let options = IndexOptions { flat_threshold: 20, ..IndexOptions::default() };
+        let index = VectorIndex::open(temp_file.path(), 8, options).unwrap();
+        let writer = IndexWriter::spawn(index).unwrap();
+
+        let pending: Vec<_> = (0..300)
+            .map(|i| writer.insert(vec![i as f32; 8], InsertPriority::Background))
+            .collect();
+        for (id, pending) in (0..).zip(pending) {
+            assert_eq!(pending.wait().unwrap(), id);
+        }
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(30);
+        while writer.unlinked_len() > 0 && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        let index = writer.into_inner().unwrap();
+        assert_eq!(index.unlinked_len(), 0);
+        assert_eq!(index.search(&[123.0; 8], 1).unwrap()[0].id, 123);
+    }
+
+    #[test]
+    fn test_into_inner_drains_queues_and_reports_errors() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let writer = IndexWriter::spawn(open_index(&temp_file)).unwrap();
+
+        let bad = writer.insert(vec![1.0; 3], InsertPriority::Interactive);
+        for i in 0..50 {
+            let _ = writer.insert(vec![i as f32; 8], InsertPriority::Background);
+        }
+
+        let err = bad.wait().unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::DimensionMismatch);
+
+        let index = writer.into_inner().unwrap();
+        assert_eq!(index.len(), 50);
+    }
+}
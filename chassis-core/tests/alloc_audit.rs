@@ -0,0 +1,90 @@
+//! Allocation audit of the search hot path
+//!
+//! Installs the counting allocator from the `alloc-audit` feature and checks
+//! that, once the scratch pool is warm, searches allocate nothing beyond the
+//! vector they return. Guards the zero-allocation claim of
+//! `search_layer_optimized()` against regressions.
+
+use chassis_core::alloc_audit::{CountingAllocator, count_allocations};
+use chassis_core::{IndexOptions, SearchResult, VectorIndex};
+use tempfile::NamedTempFile;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const DIMS: usize = 32;
+const COUNT: usize = 500;
+const EF: usize = 64;
+
+fn vector(i: usize) -> Vec<f32> {
+    (0..DIMS).map(|j| ((i * DIMS + j) as f32 * 0.37).sin()).collect()
+}
+
+fn build_index() -> (VectorIndex, NamedTempFile) {
+    let file = NamedTempFile::new().unwrap();
+    let mut index = VectorIndex::open(file.path(), DIMS as u32, IndexOptions::default()).unwrap();
+    for i in 0..COUNT {
+        index.add(&vector(i)).unwrap();
+    }
+    index.flush().unwrap();
+    (index, file)
+}
+
+#[test]
+fn test_counter_sees_allocations() {
+    let (boxed, allocations) = count_allocations(|| Box::new([0u8; 64]));
+    assert_eq!(allocations, 1);
+    drop(boxed);
+
+    let ((), allocations) = count_allocations(|| {});
+    assert_eq!(allocations, 0);
+}
+
+#[test]
+fn test_search_layer_allocates_nothing_after_setup() {
+    let (index, _file) = build_index();
+    let graph = index.graph();
+    let (entry, _) = graph.entry_points().next().unwrap();
+    let queries: Vec<Vec<f32>> = (0..20).map(|i| vector(i * 7 + 3)).collect();
+
+    // Setup: warm the pooled scratch and size the output buffer
+    let mut output: Vec<SearchResult> = Vec::with_capacity(EF);
+    for query in &queries {
+        graph.search_layer_into(query, entry, EF, 0, &mut output).unwrap();
+    }
+
+    for query in &queries {
+        let (result, allocations) =
+            count_allocations(|| graph.search_layer_into(query, entry, EF, 0, &mut output));
+        result.unwrap();
+        assert!(!output.is_empty());
+        assert_eq!(allocations, 0, "search_layer_into allocated {allocations} times");
+    }
+
+    // The returned vector (the scratch's output buffer, replaced on the next
+    // search) is the only allocation
+    for query in &queries {
+        let (results, allocations) =
+            count_allocations(|| graph.search_layer_optimized(query, entry, EF, 0));
+        assert!(!results.unwrap().is_empty());
+        assert!(allocations <= 1, "search_layer_optimized allocated {allocations} times");
+    }
+}
+
+#[test]
+fn test_search_into_allocates_nothing_after_setup() {
+    let (index, _file) = build_index();
+    let (mut ids, mut distances) = ([0u64; 10], [0f32; 10]);
+    for i in 0..20 {
+        index.search_into(&vector(i), 10, &mut ids, &mut distances).unwrap();
+    }
+
+    for i in 0..20 {
+        let query = vector(i);
+        let (count, allocations) =
+            count_allocations(|| index.search_into(&query, 10, &mut ids, &mut distances));
+        assert_eq!(count.unwrap(), 10);
+        assert_eq!(ids[0], i as u64);
+        assert_eq!(allocations, 0, "search_into allocated {allocations} times");
+    }
+}
@@ -0,0 +1,167 @@
+//! Contention tests for the single-writer, multi-reader model (ADR-0003)
+//!
+//! Reader threads search through `ReadHandle`s while one `WriteHandle` keeps
+//! inserting, flushing and remapping the file underneath them. Vector `i` is
+//! `[i; DIMS]`, so every returned vector must be uniform and equal to its ID:
+//! a search that overlapped a write, or read a record half-written, would
+//! return a torn vector, a distance that does not match it, or miss the last
+//! published vector.
+
+use chassis_core::{IndexOptions, ReadHandle, VectorIndex, WriteHandle};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tempfile::NamedTempFile;
+
+const DIMS: usize = 8;
+const READERS: usize = 4;
+
+/// Small build beam: the graph only needs to stay navigable, and inserts run
+/// while four readers compete for the lock
+fn options() -> IndexOptions {
+    IndexOptions { ef_construction: 32, ..IndexOptions::default() }
+}
+
+fn vector(id: u64) -> [f32; DIMS] {
+    [id as f32; DIMS]
+}
+
+/// Searches until `done`, checking every result against the published length,
+/// which must always be a whole number of `batch` inserts
+///
+/// Returns the number of searches run.
+fn check_reads(reader: &ReadHandle, done: &AtomicBool, batch: u64) -> u64 {
+    let mut searches = 0;
+    let mut last_len = 0;
+    let mut last_durable = 0;
+
+    while !done.load(Ordering::Acquire) || searches == 0 {
+        let len = reader.len();
+        let durable = reader.durable_len();
+        assert!(len >= last_len, "length went backwards: {last_len} -> {len}");
+        assert!(durable >= last_durable, "durable length went backwards");
+        assert_eq!(len % batch, 0, "saw part of a batch");
+        last_len = len;
+        last_durable = durable;
+        if len == 0 {
+            std::thread::yield_now();
+            continue;
+        }
+
+        // The newest published vector is reachable as soon as it is counted
+        let newest = len - 1;
+        let results = reader.search_with_vectors(&vector(newest), 3).unwrap();
+        assert_eq!(results[0].id, newest, "published vector {newest} not found");
+        assert_eq!(results[0].distance, 0.0);
+
+        let after = reader.len();
+        for result in &results {
+            assert!(result.id < after, "vector {} returned before it was published", result.id);
+            assert!(
+                result.vector.iter().all(|&x| x == result.id as f32),
+                "torn read of vector {}: {:?}",
+                result.id,
+                result.vector
+            );
+            let expected = (result.id as f32 - newest as f32).abs() * (DIMS as f32).sqrt();
+            assert!((result.distance - expected).abs() <= expected * 1e-4 + 1e-4);
+        }
+        searches += 1;
+        // Leave the writer a turn on machines with few cores
+        std::thread::yield_now();
+    }
+    searches
+}
+
+/// Runs `READERS` checking threads while `write` inserts through the writer
+/// in batches of `batch` vectors
+fn run_contended(
+    options: IndexOptions,
+    batch: u64,
+    write: impl FnOnce(&mut WriteHandle),
+) -> ReadHandle {
+    let temp_file = NamedTempFile::new().unwrap();
+    let index = VectorIndex::open(temp_file.path(), DIMS as u32, options).unwrap();
+    let (mut writer, reader) = index.into_handles();
+    let done = AtomicBool::new(false);
+
+    std::thread::scope(|scope| {
+        let threads: Vec<_> = (0..READERS)
+            .map(|_| {
+                let reader = reader.clone();
+                let done = &done;
+                scope.spawn(move || check_reads(&reader, done, batch))
+            })
+            .collect();
+
+        write(&mut writer);
+        done.store(true, Ordering::Release);
+        for thread in threads {
+            assert!(thread.join().unwrap() > 0);
+        }
+    });
+
+    reader
+}
+
+#[test]
+fn test_readers_never_see_torn_vectors_while_writer_inserts() {
+    // Enough vectors to grow and remap the file several times
+    let reader = run_contended(options(), 1, |writer| {
+        for i in 0..500 {
+            assert_eq!(writer.add(&vector(i)).unwrap(), i);
+        }
+    });
+
+    assert_eq!(reader.len(), 500);
+    assert_eq!(reader.search(&vector(377), 1).unwrap()[0].id, 377);
+}
+
+#[test]
+fn test_readers_see_batched_writes_whole() {
+    // A batch holds the lock for all its inserts: readers see none or all
+    let reader = run_contended(options(), 20, |writer| {
+        for batch in 0..20 {
+            writer.write(|index| {
+                for i in 0..20 {
+                    index.add(&vector(batch * 20 + i)).unwrap();
+                }
+                assert_eq!(index.len() % 20, 0);
+            });
+            assert_eq!(writer.len(), (batch + 1) * 20);
+        }
+    });
+
+    assert_eq!(reader.len(), 400);
+}
+
+#[test]
+fn test_readers_search_through_background_flushes() {
+    let reader = run_contended(options(), 1, |writer| {
+        for i in 0..400 {
+            writer.add(&vector(i)).unwrap();
+            if i % 50 == 49 {
+                writer.flush_async().unwrap();
+            }
+        }
+        writer.flush().unwrap();
+    });
+
+    assert_eq!(reader.durable_len(), 400);
+}
+
+#[test]
+fn test_readers_see_buffered_records() {
+    // Node records wait in the write buffer until a flush; searches must read
+    // the buffered copies, not the stale mapping
+    let options = IndexOptions { write_buffer_records: 64, ..options() };
+    let reader = run_contended(options, 1, |writer| {
+        for i in 0..400 {
+            writer.add(&vector(i)).unwrap();
+            if i % 150 == 149 {
+                writer.flush().unwrap();
+            }
+        }
+    });
+
+    assert_eq!(reader.len(), 400);
+    assert_eq!(reader.search(&vector(399), 1).unwrap()[0].id, 399);
+}
@@ -0,0 +1,257 @@
+//! Conformance tests against golden index files
+//!
+//! `tests/golden/` holds tiny index files, one per on-disk variant, each with
+//! a `.expected` manifest of what reading it must produce: header fields,
+//! every stored vector, keys, and the results of fixed queries. Any
+//! implementation or binding of the format can check itself against the same
+//! files (see `tests/golden/README.md`); this module checks this library.
+//!
+//! The files are never rewritten: a file written by an older release must keep
+//! reading the same way. When the format gains a variant, add a fixture to
+//! `FIXTURES` and run
+//! `CHASSIS_GOLDEN_GENERATE=1 cargo test -p chassis-core --test conformance_tests`,
+//! which writes only the fixtures that do not exist yet.
+
+use chassis_core::{ElementType, IndexOptions, VectorIndex};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+const GENERATE_VAR: &str = "CHASSIS_GOLDEN_GENERATE";
+
+const DIMS: usize = 8;
+const COUNT: u64 = 40;
+
+/// Deleted from every fixture, so readers must skip it
+const DELETED_ID: u64 = 7;
+
+/// Seed of the queries recorded in the manifests
+const QUERY_SEED: u64 = 0x601d;
+const QUERIES: usize = 4;
+const K: usize = 5;
+
+/// How one golden file is written
+struct Fixture {
+    name: &'static str,
+    options: fn() -> IndexOptions,
+
+    /// Vectors are added under the keys `doc-<id>`
+    keyed: bool,
+
+    /// Written with `snapshot_compressed_to()` instead of `persist_to()`
+    compressed: bool,
+}
+
+const FIXTURES: &[Fixture] = &[
+    Fixture { name: "f32-euclidean", options: default_options, keyed: false, compressed: false },
+    Fixture { name: "f16", options: f16_options, keyed: false, compressed: false },
+    Fixture { name: "normalized", options: normalized_options, keyed: false, compressed: false },
+    Fixture { name: "keyed", options: default_options, keyed: true, compressed: false },
+    Fixture { name: "compressed", options: small_options, keyed: false, compressed: true },
+];
+
+/// A quota shrinks the room left for vectors in front of the graph zone of
+/// the source file, which the compressed copy does not keep either
+fn small_options() -> IndexOptions {
+    IndexOptions { max_file_bytes: Some(2 * 1024 * 1024), ..IndexOptions::default() }
+}
+
+fn default_options() -> IndexOptions {
+    IndexOptions::default()
+}
+
+fn f16_options() -> IndexOptions {
+    IndexOptions { element_type: ElementType::F16, ..IndexOptions::default() }
+}
+
+fn normalized_options() -> IndexOptions {
+    IndexOptions { normalize: true, ..IndexOptions::default() }
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+fn vector(id: u64) -> Vec<f32> {
+    (0..DIMS).map(|j| ((id as usize * DIMS + j) as f32 * 0.37).sin()).collect()
+}
+
+fn queries() -> Vec<Vec<f32>> {
+    let mut rng = StdRng::seed_from_u64(QUERY_SEED);
+    (0..QUERIES).map(|_| (0..DIMS).map(|_| rng.random_range(-1.0..1.0)).collect()).collect()
+}
+
+/// The format version field of the main header: a little-endian `u32` at
+/// offset 8
+fn format_version(path: &Path) -> u32 {
+    let bytes = std::fs::read(path).unwrap();
+    u32::from_le_bytes(bytes[8..12].try_into().unwrap())
+}
+
+/// Write the fixture's file with no room for growth, so it stays small:
+/// `persist_to()` packs the zones of an in-memory index
+fn write_fixture(fixture: &Fixture, path: &Path) {
+    let temp = tempfile::tempdir().unwrap();
+    let mut index = if fixture.compressed {
+        let source = temp.path().join("source.chassis");
+        VectorIndex::open(source, DIMS as u32, (fixture.options)()).unwrap()
+    } else {
+        VectorIndex::in_memory(DIMS as u32, (fixture.options)()).unwrap()
+    };
+    for id in 0..COUNT {
+        if fixture.keyed {
+            index.add_with_key(&format!("doc-{id}"), &vector(id)).unwrap();
+        } else {
+            index.add(&vector(id)).unwrap();
+        }
+    }
+    index.delete(DELETED_ID).unwrap();
+    if fixture.compressed {
+        index.snapshot_compressed_to(path).unwrap();
+    } else {
+        index.persist_to(path).unwrap();
+    }
+}
+
+/// Describe `path` as this library reads it, in the manifest format
+fn describe(path: &Path) -> String {
+    let index = VectorIndex::open_sealed(path, IndexOptions::default()).unwrap();
+    let mut out = String::new();
+    let file_name = path.file_name().unwrap().to_string_lossy();
+    writeln!(out, "# Expected contents of {file_name}; see README.md").unwrap();
+    writeln!(out, "format_version {}", format_version(path)).unwrap();
+    writeln!(out, "dimensions {}", index.dimensions()).unwrap();
+    writeln!(out, "count {}", index.len()).unwrap();
+    writeln!(out, "deleted_count {}", index.deleted_count()).unwrap();
+
+    for id in 0..index.len() {
+        if index.is_deleted(id).unwrap() {
+            writeln!(out, "deleted {id}").unwrap();
+            continue;
+        }
+        write!(out, "vector {id}").unwrap();
+        for x in index.get_vector(id).unwrap() {
+            write!(out, " {x:?}").unwrap();
+        }
+        writeln!(out).unwrap();
+        if let Some(key) = index.key_for_id(id) {
+            writeln!(out, "key {key} {id}").unwrap();
+        }
+    }
+
+    for (i, query) in queries().iter().enumerate() {
+        write!(out, "query {i} {K}").unwrap();
+        for x in query {
+            write!(out, " {x:?}").unwrap();
+        }
+        writeln!(out).unwrap();
+        for result in index.search(query, K).unwrap() {
+            writeln!(out, "result {i} {} {:?}", result.id, result.distance).unwrap();
+        }
+    }
+    out
+}
+
+fn parse<T: std::str::FromStr>(field: &str) -> T
+where
+    T::Err: std::fmt::Debug,
+{
+    field.parse().unwrap()
+}
+
+/// Check `path` against its manifest, line by line
+fn check(path: &Path, manifest: &str) {
+    let name = path.file_name().unwrap().to_string_lossy().into_owned();
+    let index = VectorIndex::open_sealed(path, IndexOptions::default())
+        .unwrap_or_else(|e| panic!("{name}: cannot open: {e:#}"));
+    let mut queries: Vec<Vec<f32>> = Vec::new();
+    let mut results: Vec<Vec<(u64, f32)>> = Vec::new();
+
+    for (number, line) in manifest.lines().enumerate() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let at = format!("{name}.expected:{}", number + 1);
+        match fields.as_slice() {
+            [] => {}
+            [comment, ..] if comment.starts_with('#') => {}
+            ["format_version", version] => {
+                assert_eq!(format_version(path), parse::<u32>(version), "{at}");
+            }
+            ["dimensions", dims] => assert_eq!(index.dimensions(), parse::<u32>(dims), "{at}"),
+            ["count", count] => assert_eq!(index.len(), parse::<u64>(count), "{at}"),
+            ["deleted_count", count] => {
+                assert_eq!(index.deleted_count(), parse::<u64>(count), "{at}");
+            }
+            ["deleted", id] => assert!(index.is_deleted(parse(id)).unwrap(), "{at}"),
+            ["vector", id, components @ ..] => {
+                let expected: Vec<f32> = components.iter().map(|x| parse(x)).collect();
+                assert_eq!(index.get_vector(parse(id)).unwrap(), expected, "{at}");
+            }
+            ["key", key, id] => assert_eq!(index.id_for_key(key), Some(parse(id)), "{at}"),
+            ["query", i, k, components @ ..] => {
+                assert_eq!(parse::<usize>(i), queries.len(), "{at}: queries out of order");
+                let query: Vec<f32> = components.iter().map(|x| parse(x)).collect();
+                results.push(
+                    index
+                        .search(&query, parse(k))
+                        .unwrap()
+                        .iter()
+                        .map(|r| (r.id, r.distance))
+                        .collect(),
+                );
+                queries.push(query);
+            }
+            ["result", i, id, distance] => {
+                let found = &mut results[parse::<usize>(i)];
+                assert!(!found.is_empty(), "{at}: fewer results than expected");
+                let (found_id, found_distance) = found.remove(0);
+                let distance: f32 = parse(distance);
+                assert_eq!(found_id, parse::<u64>(id), "{at}");
+                assert!(
+                    (found_distance - distance).abs() <= 1e-5 * distance.abs().max(1.0),
+                    "{at}: distance {found_distance}, expected {distance}"
+                );
+            }
+            _ => panic!("{at}: unknown line {line:?}"),
+        }
+    }
+
+    for (i, extra) in results.iter().enumerate() {
+        assert!(extra.is_empty(), "{name}: query {i} returned more results than expected");
+    }
+}
+
+#[test]
+fn test_golden_files_read_identically() {
+    let dir = golden_dir();
+    let generate = std::env::var_os(GENERATE_VAR).is_some();
+
+    for fixture in FIXTURES {
+        let path = dir.join(format!("{}.chassis", fixture.name));
+        let manifest = dir.join(format!("{}.expected", fixture.name));
+        if generate && !path.exists() {
+            write_fixture(fixture, &path);
+            std::fs::write(&manifest, describe(&path)).unwrap();
+        }
+
+        assert!(path.exists(), "missing {}; run with {GENERATE_VAR}=1", path.display());
+        check(&path, &std::fs::read_to_string(&manifest).unwrap());
+    }
+}
+
+#[test]
+fn test_every_golden_file_has_a_fixture() {
+    // Files dropped into the directory by hand must be listed, or nothing reads them
+    for entry in std::fs::read_dir(golden_dir()).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|ext| ext == "chassis") {
+            let stem = path.file_stem().unwrap().to_string_lossy();
+            assert!(
+                FIXTURES.iter().any(|f| f.name == stem),
+                "{} is not in FIXTURES",
+                path.display()
+            );
+            assert!(path.with_extension("expected").exists(), "{} has no manifest", path.display());
+        }
+    }
+}
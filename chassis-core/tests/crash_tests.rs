@@ -0,0 +1,258 @@
+//! Crash-consistency tests driven by failpoints
+//!
+//! Each test kills a write at a failpoint, drops the index without flushing
+//! (the file is left as a killed process would leave it) and checks that
+//! `open()` recovers to the last flushed state, that every recovered vector is
+//! found by search, and that the index keeps accepting writes.
+
+use std::path::Path;
+
+use chassis_core::{ErrorKind, IdReuse, IndexOptions, VectorIndex, failpoint};
+use tempfile::NamedTempFile;
+
+const DIMS: usize = 16;
+const FLUSHED: usize = 40;
+const UNFLUSHED: usize = 10;
+const TOTAL: usize = 80;
+
+fn vector(i: usize) -> Vec<f32> {
+    (0..DIMS).map(|j| ((i * DIMS + j) as f32 * 0.37).sin()).collect()
+}
+
+/// Adds `FLUSHED` vectors and flushes, then adds `UNFLUSHED` more
+fn populate(path: &Path) -> VectorIndex {
+    let mut index = VectorIndex::open(path, DIMS as u32, IndexOptions::default()).unwrap();
+    for i in 0..FLUSHED {
+        index.add(&vector(i)).unwrap();
+    }
+    index.flush().unwrap();
+    for i in FLUSHED..FLUSHED + UNFLUSHED {
+        index.add(&vector(i)).unwrap();
+    }
+    index
+}
+
+fn assert_searchable(index: &VectorIndex) {
+    for id in 0..index.len() {
+        let results = index.search(&vector(id as usize), 1).unwrap();
+        assert_eq!(results[0].id, id);
+        assert_eq!(results[0].distance, 0.0);
+    }
+}
+
+/// Reopens the crashed file and checks it recovers to the flushed vectors and
+/// keeps working
+fn assert_recovers(path: &Path) {
+    let mut index = VectorIndex::open(path, DIMS as u32, IndexOptions::default()).unwrap();
+    assert_eq!(index.len(), FLUSHED as u64);
+    assert_searchable(&index);
+
+    // The rolled back IDs are reused and the graph grows past them
+    for i in FLUSHED..TOTAL {
+        assert_eq!(index.add(&vector(i)).unwrap(), i as u64);
+    }
+    index.flush().unwrap();
+    drop(index);
+
+    let index = VectorIndex::open(path, DIMS as u32, IndexOptions::default()).unwrap();
+    assert_eq!(index.len(), TOTAL as u64);
+    assert_searchable(&index);
+}
+
+#[test]
+fn test_crash_after_vector_persist() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = populate(temp_file.path());
+
+    failpoint::arm(failpoint::AFTER_VECTOR_PERSIST, 0);
+    let err = index.add(&vector(FLUSHED + UNFLUSHED)).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::Io);
+    drop(index);
+
+    assert_recovers(temp_file.path());
+}
+
+#[test]
+fn test_crash_mid_backlink_update() {
+    for skip in [0, 1, 3, 7] {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut index = populate(temp_file.path());
+
+        failpoint::arm(failpoint::MID_BACKLINK_UPDATE, skip);
+        let err = index.add(&vector(FLUSHED + UNFLUSHED)).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Io, "skip {}", skip);
+        drop(index);
+
+        assert_recovers(temp_file.path());
+    }
+}
+
+#[test]
+fn test_crash_before_header_write() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = populate(temp_file.path());
+
+    // Vectors and node records are synced, but the graph header still
+    // describes the previous flush
+    failpoint::arm(failpoint::BEFORE_HEADER_WRITE, 0);
+    let err = index.flush().unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::Io);
+    drop(index);
+
+    assert_recovers(temp_file.path());
+}
+
+#[test]
+fn test_crash_before_first_flush() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index =
+        VectorIndex::open(temp_file.path(), DIMS as u32, IndexOptions::default()).unwrap();
+    for i in 0..UNFLUSHED {
+        index.add(&vector(i)).unwrap();
+    }
+
+    failpoint::arm(failpoint::MID_BACKLINK_UPDATE, 2);
+    assert!(index.add(&vector(UNFLUSHED)).is_err());
+    drop(index);
+
+    let mut index =
+        VectorIndex::open(temp_file.path(), DIMS as u32, IndexOptions::default()).unwrap();
+    assert!(index.is_empty());
+    for i in 0..UNFLUSHED {
+        assert_eq!(index.add(&vector(i)).unwrap(), i as u64);
+    }
+    assert_searchable(&index);
+}
+
+#[test]
+fn test_crash_mid_delete_recounts_deletions() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index =
+        VectorIndex::open(temp_file.path(), DIMS as u32, IndexOptions::default()).unwrap();
+    for i in 0..FLUSHED {
+        index.add(&vector(i)).unwrap();
+    }
+    index.flush().unwrap();
+
+    // The flag reaches the file, the count in the graph header does not
+    failpoint::arm(failpoint::MID_DELETE, 0);
+    let err = index.delete(7).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::Io);
+    drop(index);
+
+    let (mut index, report) =
+        VectorIndex::open_with_report(temp_file.path(), DIMS as u32, IndexOptions::default())
+            .unwrap();
+    assert!(!report.clean_shutdown);
+    assert!(report.deleted_count_repaired);
+    assert!(!report.is_clean());
+    assert_eq!(index.deleted_count(), 1);
+    assert!(index.is_deleted(7).unwrap());
+    assert_ne!(index.search(&vector(7), 1).unwrap()[0].id, 7);
+
+    // The repaired count is written by the next flush, after which the drop
+    // records a clean shutdown
+    index.flush().unwrap();
+    drop(index);
+    let (_, report) =
+        VectorIndex::open_with_report(temp_file.path(), DIMS as u32, IndexOptions::default())
+            .unwrap();
+    assert!(report.clean_shutdown);
+    assert!(report.is_clean());
+}
+
+#[test]
+fn test_crash_mid_reuse_leaves_the_slot_deleted() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let options = IndexOptions { id_reuse: IdReuse::ReuseDeleted, ..IndexOptions::default() };
+    let open =
+        || VectorIndex::open_with_report(temp_file.path(), DIMS as u32, options.clone()).unwrap();
+    let reused = vector(TOTAL);
+
+    let (mut index, _) = open();
+    for i in 0..FLUSHED {
+        index.add(&vector(i)).unwrap();
+    }
+    assert!(index.delete(7).unwrap());
+    assert!(index.delete(9).unwrap());
+    index.flush().unwrap();
+
+    // The slot holds the new vector and record, but the file has it deleted
+    failpoint::arm(failpoint::MID_REUSE, 0);
+    let err = index.add(&reused).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::Io);
+    assert!(index.is_deleted(9).unwrap());
+    drop(index);
+
+    let (mut index, report) = open();
+    assert!(!report.clean_shutdown);
+    assert!(!report.deleted_count_repaired);
+    assert_eq!(index.deleted_count(), 2);
+    assert!(index.is_deleted(9).unwrap());
+    assert!(index.search(&reused, 3).unwrap().iter().all(|r| r.id != 9));
+
+    // A completed reuse that was never flushed is lost the same way
+    assert_eq!(index.add(&reused).unwrap(), 9);
+    assert_eq!(index.deleted_count(), 1);
+    drop(index);
+
+    let (mut index, report) = open();
+    assert!(!report.deleted_count_repaired);
+    assert!(index.is_deleted(9).unwrap());
+    assert!(index.search(&reused, 3).unwrap().iter().all(|r| r.id != 9));
+
+    // Once flushed, the reused slot is live in the file
+    assert_eq!(index.add(&reused).unwrap(), 9);
+    index.flush().unwrap();
+    drop(index);
+
+    let (index, report) = open();
+    assert!(report.is_clean());
+    assert_eq!(index.deleted_count(), 1);
+    assert!(!index.is_deleted(9).unwrap());
+    let results = index.search(&reused, 1).unwrap();
+    assert_eq!((results[0].id, results[0].distance), (9, 0.0));
+    for id in (0..FLUSHED as u64).filter(|&id| id != 7 && id != 9) {
+        assert_eq!(index.search(&vector(id as usize), 1).unwrap()[0].id, id);
+    }
+}
+
+#[test]
+fn test_clean_shutdown_needs_every_write_flushed() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let index = populate(temp_file.path());
+    drop(index);
+
+    let (mut index, report) =
+        VectorIndex::open_with_report(temp_file.path(), DIMS as u32, IndexOptions::default())
+            .unwrap();
+    assert!(!report.clean_shutdown);
+    assert_eq!(report.ghosts_reclaimed(), UNFLUSHED as u64);
+    let epoch = report.commit_epoch;
+    assert_eq!(index.commit_epoch(), epoch);
+
+    index.add(&vector(FLUSHED)).unwrap();
+    index.flush().unwrap();
+    assert!(index.commit_epoch() > epoch);
+    drop(index);
+
+    let (index, report) =
+        VectorIndex::open_with_report(temp_file.path(), DIMS as u32, IndexOptions::default())
+            .unwrap();
+    assert!(report.clean_shutdown);
+    assert!(report.commit_epoch > epoch);
+    assert_eq!(index.len(), FLUSHED as u64 + 1);
+}
+
+#[test]
+fn test_disarmed_failpoints_do_not_trigger() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = populate(temp_file.path());
+
+    failpoint::arm(failpoint::AFTER_VECTOR_PERSIST, 0);
+    failpoint::arm(failpoint::BEFORE_HEADER_WRITE, 0);
+    failpoint::disarm_all();
+    index.add(&vector(FLUSHED + UNFLUSHED)).unwrap();
+    index.flush().unwrap();
+    assert_eq!(index.len(), (FLUSHED + UNFLUSHED + 1) as u64);
+}
@@ -0,0 +1,338 @@
+use chassis_core::{
+    ElementType, GraphHeader, HEADER_SIZE, Header, IndexOptions, LibraryVersion,
+    MAX_DISTANCE_NAME_LEN, MAX_PAGE_SIZE, NodeHeader, NodeRecord, NodeRecordParams, OverflowHeader,
+    VectorIndex,
+};
+use proptest::prelude::*;
+use tempfile::tempdir;
+
+const INVALID_NODE_ID: u64 = u64::MAX;
+
+// Strategies
+
+fn record_params() -> impl Strategy<Value = NodeRecordParams> {
+    (1u16..=64, 0u16..=64, 1u8..=16)
+        .prop_map(|(m, extra, max_layers)| NodeRecordParams::new(m, m + extra, max_layers))
+}
+
+/// A record for `params` with a random layer count and partly filled layers
+fn node_record() -> impl Strategy<Value = (NodeRecordParams, NodeRecord)> {
+    record_params().prop_flat_map(|params| {
+        let layers = (0..params.max_layers as usize)
+            .map(|layer| prop::collection::vec(0..INVALID_NODE_ID, 0..=params.max_neighbors(layer)))
+            .collect::<Vec<_>>();
+        (Just(params), 0..INVALID_NODE_ID, 1..=params.max_layers, layers).prop_map(
+            |(params, id, layer_count, layers)| {
+                let mut record = NodeRecord::new(id, layer_count, params);
+                for (layer, neighbors) in layers.iter().enumerate().take(layer_count as usize) {
+                    record.set_neighbors(layer, neighbors);
+                }
+                (params, record)
+            },
+        )
+    })
+}
+
+fn element_type() -> impl Strategy<Value = ElementType> {
+    prop_oneof![
+        Just(ElementType::F32),
+        Just(ElementType::F16),
+        Just(ElementType::BF16),
+        Just(ElementType::Binary),
+    ]
+}
+
+fn page_size() -> impl Strategy<Value = u32> {
+    let min = HEADER_SIZE.trailing_zeros();
+    let max = MAX_PAGE_SIZE.trailing_zeros();
+    (min..=max).prop_map(|shift| 1u32 << shift)
+}
+
+/// One step of an index workload
+#[derive(Debug, Clone)]
+enum Op {
+    Add(Vec<f32>),
+    Flush,
+    Reopen,
+}
+
+fn ops(dims: usize) -> impl Strategy<Value = Vec<Op>> {
+    let op = prop_oneof![
+        6 => prop::collection::vec(-1.0f32..1.0, dims).prop_map(Op::Add),
+        2 => Just(Op::Flush),
+        1 => Just(Op::Reopen),
+    ];
+    prop::collection::vec(op, 1..48)
+}
+
+/// Reads a header back from its on-disk bytes
+fn header_from_bytes(bytes: &[u8]) -> Box<Header> {
+    assert_eq!(bytes.len(), HEADER_SIZE);
+    let mut header = Box::new(Header::new(1));
+    unsafe {
+        std::ptr::copy_nonoverlapping(
+            bytes.as_ptr(),
+            (&mut *header as *mut Header).cast::<u8>(),
+            HEADER_SIZE,
+        );
+    }
+    header
+}
+
+// Node records
+
+proptest! {
+    #[test]
+    fn prop_node_record_roundtrip((params, record) in node_record()) {
+        let bytes = record.to_bytes();
+        prop_assert_eq!(bytes.len(), params.record_size());
+        prop_assert_eq!(bytes.len() % 8, 0);
+
+        let restored = NodeRecord::from_bytes(&bytes, params).unwrap();
+        prop_assert_eq!(restored.header.node_id, record.header.node_id);
+        prop_assert_eq!(restored.header.layer_count, record.header.layer_count);
+        prop_assert_eq!(restored.header.flags, record.header.flags);
+        prop_assert_eq!(&restored.neighbors, &record.neighbors);
+        for layer in 0..params.max_layers as usize {
+            prop_assert_eq!(restored.get_neighbors(layer), record.get_neighbors(layer));
+        }
+        prop_assert_eq!(restored.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn prop_record_sizes_cover_all_layers(params in record_params()) {
+        let total = params.total_max_neighbors();
+        prop_assert_eq!(
+            total,
+            params.m0 as usize + (params.max_layers as usize - 1) * params.m as usize
+        );
+        prop_assert!(params.record_size() >= NodeHeader::SIZE + total * 8);
+        prop_assert!(params.record_size() < NodeHeader::SIZE + total * 8 + 8);
+        prop_assert!(params.compact_record_size() >= NodeHeader::SIZE + params.m0 as usize * 8);
+        prop_assert!(params.compact_record_size() <= params.record_size());
+
+        let upper = params.record_size() - NodeHeader::SIZE - params.m0 as usize * 8;
+        prop_assert!(params.overflow_record_size() >= upper);
+
+        for layer in 0..params.max_layers as usize {
+            let offset = params.layer_offset(layer).unwrap();
+            prop_assert!(offset + params.max_neighbors(layer) * 8 <= params.record_size());
+        }
+        prop_assert!(params.layer_offset(params.max_layers as usize).is_none());
+    }
+
+    #[test]
+    fn prop_node_record_rejects_truncated_bytes((params, record) in node_record(), cut in 1usize..64) {
+        let bytes = record.to_bytes();
+        let len = bytes.len().saturating_sub(cut);
+        prop_assert!(NodeRecord::from_bytes(&bytes[..len], params).is_err());
+    }
+
+    #[test]
+    fn prop_node_record_rejects_excess_layers((params, record) in node_record()) {
+        let mut bytes = record.to_bytes();
+        bytes[8] = params.max_layers + 1;
+        prop_assert!(NodeRecord::from_bytes(&bytes, params).is_err());
+    }
+}
+
+// Graph headers
+
+proptest! {
+    #[test]
+    fn prop_graph_header_roundtrip(
+        params in record_params(),
+        entry_point in any::<u64>(),
+        node_count in any::<u64>(),
+        max_layer in any::<u32>(),
+        ef_construction in any::<u32>(),
+        deleted_count in any::<u64>(),
+        entry_candidates in any::<[u64; 2]>(),
+    ) {
+        let mut header = GraphHeader::new(params);
+        header.entry_point = entry_point;
+        header.node_count = node_count;
+        header.max_layer = max_layer;
+        header.ef_construction = ef_construction;
+        header.deleted_count = deleted_count;
+        header.entry_candidates = entry_candidates;
+
+        let bytes = header.to_bytes();
+        let restored = GraphHeader::from_bytes(&bytes).unwrap();
+        prop_assert!(restored.is_valid());
+        prop_assert!(restored.is_two_tier());
+        prop_assert_eq!(restored.entry_point, entry_point);
+        prop_assert_eq!(restored.node_count, node_count);
+        prop_assert_eq!(restored.max_layer, max_layer);
+        prop_assert_eq!(restored.ef_construction, ef_construction);
+        prop_assert_eq!(restored.deleted_count, deleted_count);
+        prop_assert_eq!(restored.entry_candidates, entry_candidates);
+        prop_assert_eq!(restored.to_record_params(), params);
+        prop_assert_eq!(restored.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn prop_graph_header_rejects_bad_magic(params in record_params(), byte in 0usize..4, value in any::<u8>()) {
+        let mut bytes = GraphHeader::new(params).to_bytes();
+        prop_assume!(bytes[byte] != value);
+        bytes[byte] = value;
+        prop_assert!(!GraphHeader::from_bytes(&bytes).unwrap().is_valid());
+    }
+
+    #[test]
+    fn prop_overflow_header_roundtrip(base_capacity in any::<u64>(), count in any::<u64>()) {
+        let header = OverflowHeader { base_capacity, count };
+        let restored = OverflowHeader::from_bytes(&header.to_bytes()).unwrap();
+        prop_assert_eq!(restored, header);
+    }
+}
+
+// File headers
+
+proptest! {
+    #[test]
+    fn prop_file_header_roundtrip(
+        dimensions in 1u32..=4096,
+        count in any::<u64>(),
+        graph_offset in 1u64..u64::MAX,
+        metadata_zone in (1u64..u64::MAX, any::<u64>()),
+        writer_version in (any::<u16>(), any::<u16>(), 1u16..u16::MAX),
+        build_checkpoint in (any::<u64>(), 1u64..u64::MAX),
+        element_type in element_type(),
+        distance_name in prop::option::of(
+            prop::collection::vec(b'a'..=b'z', 1..=MAX_DISTANCE_NAME_LEN)
+        ),
+        normalized in any::<bool>(),
+        graph_file in any::<bool>(),
+        two_tier in any::<bool>(),
+        page_size in page_size(),
+    ) {
+        let distance_name = distance_name.map(|name| String::from_utf8(name).unwrap());
+        let (major, minor, patch) = writer_version;
+        let writer_version = LibraryVersion { major, minor, patch };
+
+        let mut header = Box::new(Header::new(dimensions));
+        header.count = count;
+        header.set_graph_offset(graph_offset);
+        header.set_metadata_zone(metadata_zone.0, metadata_zone.1);
+        header.set_writer_version(writer_version);
+        header.set_build_checkpoint(build_checkpoint.0, build_checkpoint.1);
+        header.set_element_type(element_type);
+        header.set_distance_name(distance_name.as_deref());
+        header.set_normalized(normalized);
+        if graph_file {
+            header.set_graph_file();
+        }
+        if two_tier {
+            header.set_two_tier_graph();
+        }
+        header.set_page_size(page_size);
+        prop_assert!(header.is_valid());
+
+        let restored = header_from_bytes(header.as_bytes());
+        prop_assert!(restored.is_valid());
+        prop_assert_eq!(restored.version, header.version);
+        prop_assert_eq!(restored.dimensions, dimensions);
+        prop_assert_eq!(restored.count, count);
+        prop_assert_eq!(restored.graph_offset(), Some(graph_offset));
+        prop_assert_eq!(restored.metadata_zone(), Some(metadata_zone));
+        prop_assert_eq!(restored.writer_version(), Some(writer_version));
+        prop_assert_eq!(restored.build_checkpoint(), Some(build_checkpoint));
+        prop_assert_eq!(restored.element_type(), Some(element_type));
+        prop_assert_eq!(restored.distance_name(), distance_name.as_deref());
+        prop_assert_eq!(restored.normalized(), normalized);
+        prop_assert_eq!(restored.graph_file(), graph_file);
+        prop_assert_eq!(restored.two_tier_graph(), two_tier);
+        prop_assert_eq!(restored.page_size(), page_size as usize);
+        prop_assert_eq!(restored.as_bytes(), header.as_bytes());
+    }
+
+    #[test]
+    fn prop_file_header_version_tracks_features(
+        element_type in element_type(),
+        graph_file in any::<bool>(),
+        page_size in page_size(),
+    ) {
+        let mut header = Box::new(Header::new(8));
+        header.set_element_type(element_type);
+        if graph_file {
+            header.set_graph_file();
+        }
+        header.set_page_size(page_size);
+        let version = header.version;
+
+        // Setting features in another order records the same version
+        let mut reordered = Box::new(Header::new(8));
+        reordered.set_page_size(page_size);
+        if graph_file {
+            reordered.set_graph_file();
+        }
+        reordered.set_element_type(element_type);
+        prop_assert_eq!(reordered.version, version);
+        prop_assert!(version <= chassis_core::VERSION);
+    }
+}
+
+// Index files
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(24))]
+
+    #[test]
+    fn prop_reopen_after_interleaved_writes(
+        (dims, ops) in (1usize..=8).prop_flat_map(|dims| (Just(dims), ops(dims)))
+    ) {
+        check_interleaved_writes(dims, &ops)?;
+    }
+}
+
+fn check_interleaved_writes(dims: usize, ops: &[Op]) -> Result<(), TestCaseError> {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("index.chassis");
+    let open = || VectorIndex::open(&path, dims as u32, IndexOptions::default()).unwrap();
+
+    let mut index = open();
+    let mut written: Vec<Vec<f32>> = Vec::new();
+    let mut flushed = 0;
+
+    for op in ops {
+        match op {
+            Op::Add(vector) => {
+                let id = index.add(vector).unwrap();
+                prop_assert_eq!(id, written.len() as u64);
+                written.push(vector.clone());
+            }
+            Op::Flush => {
+                index.flush().unwrap();
+                flushed = written.len();
+            }
+            Op::Reopen => {
+                drop(index);
+                index = open();
+
+                // Unflushed vectors may or may not survive, but never partly
+                let len = index.len() as usize;
+                prop_assert!(len >= flushed && len <= written.len());
+                written.truncate(len);
+                flushed = len;
+            }
+        }
+
+        prop_assert_eq!(index.len() as usize, written.len());
+    }
+
+    index.flush().unwrap();
+    drop(index);
+    let index = open();
+
+    prop_assert_eq!(index.len() as usize, written.len());
+    for (id, vector) in written.iter().enumerate() {
+        prop_assert_eq!(&index.get_vector(id as u64).unwrap(), vector);
+    }
+    if let Some(query) = written.last() {
+        let results = index.search(query, 1).unwrap();
+        prop_assert_eq!(results.len(), 1);
+        prop_assert_eq!(results[0].distance, 0.0);
+    }
+    Ok(())
+}
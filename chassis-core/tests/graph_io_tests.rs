@@ -1,5 +1,7 @@
 use chassis_core::Storage;
-use chassis_core::{GraphHeader, HnswGraph, HnswParams, NodeRecord, NodeRecordParams};
+use chassis_core::{
+    GraphHeader, HnswGraph, HnswParams, IndexOptions, NodeRecord, NodeRecordParams, VectorIndex,
+};
 use tempfile::NamedTempFile;
 
 const ONE_GIB: u64 = 1024 * 1024 * 1024;
@@ -344,6 +346,7 @@ fn test_node_record_with_partial_neighbors() {
         ef_search: 50,
         ml: 1.0 / (4.0_f32).ln(),
         max_layers: 3,
+        ..HnswParams::default()
     };
 
     let storage = Storage::open(path, 128).unwrap();
@@ -397,6 +400,55 @@ fn test_legacy_graph_zone_compacts_on_open() {
     }
 }
 
+#[test]
+fn test_legacy_layout_needs_migration_before_read_only_opens() {
+    use chassis_core::{ErrorKind, migrate_file};
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let path = temp_file.path();
+    let params = HnswParams::default();
+    {
+        let mut storage = Storage::open(path, 128).unwrap();
+        let legacy_header = GraphHeader::new(params.to_record_params()).to_bytes();
+        storage.ensure_graph_capacity(ONE_GIB as usize + legacy_header.len()).unwrap();
+        storage
+            .graph_zone_mut(ONE_GIB as usize, legacy_header.len())
+            .unwrap()
+            .copy_from_slice(&legacy_header);
+    }
+
+    // A shared reader cannot move the graph, so it points at the upgrade
+    let err = VectorIndex::open_shared(path, 128, IndexOptions::default()).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::IncompatibleVersion);
+    assert!(err.to_string().contains("legacy-graph-zone"));
+
+    assert_eq!(migrate_file(path).unwrap(), ["legacy-graph-zone"]);
+    assert!(std::fs::metadata(path).unwrap().len() < ONE_GIB / 10);
+    assert!(migrate_file(path).unwrap().is_empty());
+
+    let index = VectorIndex::open_shared(path, 128, IndexOptions::default()).unwrap();
+    assert_eq!(index.len(), 0);
+    drop(index);
+    let (_, report) = VectorIndex::open_with_report(path, 128, IndexOptions::default()).unwrap();
+    assert!(report.migrations.is_empty() && report.is_clean());
+}
+
+#[test]
+fn test_newer_format_version_is_refused() {
+    use chassis_core::{ErrorKind, VERSION};
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let path = temp_file.path();
+    VectorIndex::open(path, 16, IndexOptions::default()).unwrap().flush().unwrap();
+
+    let mut bytes = std::fs::read(path).unwrap();
+    bytes[8..12].copy_from_slice(&(VERSION + 1).to_le_bytes());
+    std::fs::write(path, &bytes).unwrap();
+
+    let err = VectorIndex::open(path, 16, IndexOptions::default()).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::IncompatibleVersion);
+}
+
 #[test]
 fn test_10k_768d_layout_stays_under_100mb() {
     let temp_file = NamedTempFile::new().unwrap();
@@ -431,3 +483,34 @@ fn test_10k_768d_layout_stays_under_100mb() {
         assert_eq!(graph.read_node_record(9_999).unwrap().header.node_id, 9_999);
     }
 }
+
+#[test]
+fn test_traversal_primitives() {
+    let mut index = VectorIndex::in_memory(4, IndexOptions::default()).unwrap();
+    for i in 0..40 {
+        index.add(&[i as f32, 0.0, 0.0, 0.0]).unwrap();
+    }
+    let graph = index.graph();
+
+    let entry_points: Vec<_> = graph.entry_points().collect();
+    assert_eq!(entry_points[0], (graph.entry_point.unwrap(), graph.max_layer));
+    assert!(entry_points[1..].windows(2).all(|pair| pair[0].1 >= pair[1].1));
+
+    // Every link on every layer points to a node linked on that layer
+    for node_id in 0..40 {
+        let layers = graph.node_layer_count(node_id).unwrap();
+        for layer in 0..layers {
+            for neighbor in graph.neighbors_iter_from_mmap(node_id, layer).unwrap() {
+                assert!(graph.node_layer_count(neighbor).unwrap() > layer);
+            }
+        }
+    }
+    assert!(graph.node_layer_count(40).is_err());
+
+    let query = [20.2, 0.0, 0.0, 0.0];
+    let results = graph.search_layer_from(&query, &[0, 39, 0], 3, 0).unwrap();
+    let ids: Vec<_> = results.iter().map(|result| result.id).collect();
+    assert_eq!(ids, vec![20, 21, 19]);
+
+    assert!(graph.search_layer_from(&query, &[0, 40], 3, 0).is_err());
+}
@@ -2,7 +2,10 @@
 //!
 //! Tests cover the complete lifecycle: open -> add -> search -> flush
 
-use chassis_core::{IndexOptions, VectorIndex};
+use chassis_core::{
+    ElementType, ErrorKind, FileStolen, FlushPolicy, HEADER_SIZE, IndexOptions, SearchConsistency,
+    SearchOptions, VectorIndex,
+};
 use tempfile::NamedTempFile;
 
 #[test]
@@ -176,7 +179,12 @@ fn test_large_batch_insert() {
 fn test_custom_options() {
     let temp_file = NamedTempFile::new().unwrap();
 
-    let options = IndexOptions { max_connections: 8, ef_construction: 100, ef_search: 25 };
+    let options = IndexOptions {
+        max_connections: 8,
+        ef_construction: 100,
+        ef_search: 25,
+        ..Default::default()
+    };
 
     let mut index = VectorIndex::open(temp_file.path(), 128, options).unwrap();
 
@@ -400,3 +408,3087 @@ fn test_stress_sequential_adds() {
         assert!(results[i - 1].distance <= results[i].distance);
     }
 }
+
+#[test]
+fn test_trained_rotation_persists_across_reopen() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let path = temp_file.path().to_owned();
+
+    let expected = {
+        let mut index = VectorIndex::open(&path, 16, IndexOptions::default()).unwrap();
+        for i in 0..300 {
+            let t = i as f32 / 30.0;
+            let mut vec = vec![0.0; 16];
+            vec[0] = t;
+            vec[1] = t * 0.5;
+            vec[2] = ((i % 7) as f32) * 0.01;
+            index.add(&vec).unwrap();
+        }
+
+        let rotation = index.train_rotation(256).unwrap().clone();
+        assert_eq!(rotation.dimensions(), 16);
+
+        // Keep adding after training so the graph grows past the metadata zone
+        for i in 0..50 {
+            index.add(&[i as f32; 16]).unwrap();
+        }
+        index.flush().unwrap();
+        rotation
+    };
+
+    let index = VectorIndex::open(&path, 16, IndexOptions::default()).unwrap();
+    let rotation = index.rotation().expect("rotation should be persisted");
+    assert_eq!(*rotation, expected);
+
+    // Variance concentrates in the leading component
+    let variances = rotation.variances();
+    assert!(variances[0] > 100.0 * variances[2].max(1e-6));
+}
+
+#[test]
+fn test_quantized_copy_applies_trained_rotation() {
+    // Variance along a diagonal the sign bits of the raw axes cannot see
+    let vector = |i: u64| {
+        let mut x = i.wrapping_mul(2_654_435_761) ^ 0x5bd1;
+        let mut next = || {
+            x = x.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+            ((x >> 33) % 1000) as f32 / 500.0 - 1.0
+        };
+        let t = 4.0 * next();
+        (0..16).map(|d| if d < 4 { t } else { 0.0 } + 0.1 * next()).collect::<Vec<f32>>()
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let mut index =
+        VectorIndex::open(dir.path().join("full.chassis"), 16, IndexOptions::default()).unwrap();
+    for i in 0..300 {
+        index.add(&vector(i)).unwrap();
+    }
+    let rotation = index.train_rotation(300).unwrap().clone();
+
+    // Half-width copies store rotated vectors and rotate them back on read
+    let mut half = index.quantize_to(dir.path().join("f16.chassis"), ElementType::F16).unwrap();
+    assert_eq!(half.rotation(), Some(&rotation));
+    for i in [0, 150, 299] {
+        let restored = half.get_vector(i).unwrap();
+        assert!(restored.iter().zip(vector(i)).all(|(a, b)| (a - b).abs() < 1e-2));
+        assert_eq!(half.search(&vector(i), 1).unwrap()[0].id, i);
+    }
+    let err = half.train_rotation(100).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+
+    // Binary copies keep the signs of the rotated vectors, for added ones too
+    let signs =
+        |v: &[f32]| rotation.apply(v).iter().map(|&x| f32::from(u8::from(x > 0.0))).collect();
+    let binary = index.quantize_to(dir.path().join("binary.chassis"), ElementType::Binary).unwrap();
+    let expected: Vec<f32> = signs(&vector(7));
+    assert_eq!(binary.get_vector(7).unwrap(), expected);
+    drop(binary);
+
+    let mut binary = VectorIndex::open(
+        dir.path().join("binary.chassis"),
+        16,
+        IndexOptions { element_type: ElementType::Binary, ..IndexOptions::default() },
+    )
+    .unwrap();
+    assert_eq!(binary.rotation(), Some(&rotation));
+    let id = binary.add(&vector(1000)).unwrap();
+    let expected: Vec<f32> = signs(&vector(1000));
+    assert_eq!(binary.get_vector(id).unwrap(), expected);
+    assert_eq!(binary.search(&vector(1000), 1).unwrap()[0].distance, 0.0);
+}
+
+#[test]
+fn test_matryoshka_truncation_stores_prefix() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let path = temp_file.path().to_owned();
+    let options = IndexOptions { input_dimensions: Some(64), ..Default::default() };
+
+    {
+        let mut index = VectorIndex::open(&path, 16, options.clone()).unwrap();
+        assert_eq!(index.dimensions(), 16);
+        assert_eq!(index.input_dimensions(), 64);
+
+        for i in 0..20 {
+            // Prefix encodes the identity, the tail is noise that must be ignored
+            let mut vec = vec![i as f32; 64];
+            vec[16..].fill(-(i as f32) * 100.0);
+            index.add(&vec).unwrap();
+        }
+
+        // Already-truncated vectors are rejected
+        let err = index.add(&[0.0; 16]).unwrap_err();
+        assert!(err.to_string().contains("dimension mismatch"));
+        index.flush().unwrap();
+    }
+
+    let index = VectorIndex::open(&path, 16, options).unwrap();
+    let mut query = vec![7.0; 64];
+    query[16..].fill(1e6);
+    let results = index.search(&query, 1).unwrap();
+    assert_eq!(results[0].id, 7);
+    assert_eq!(results[0].distance, 0.0);
+}
+
+#[test]
+fn test_matryoshka_rejects_smaller_input_dimensions() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let options = IndexOptions { input_dimensions: Some(8), ..Default::default() };
+
+    let err = VectorIndex::open(temp_file.path(), 16, options).unwrap_err();
+    assert!(err.to_string().contains("must be >= stored dimensions"));
+}
+
+#[test]
+fn test_in_memory_index_roundtrips_through_bytes() {
+    let mut index = VectorIndex::open_in_memory(32, IndexOptions::default()).unwrap();
+    for i in 0..300 {
+        index.add(&[i as f32; 32]).unwrap();
+    }
+    index.flush().unwrap();
+
+    let bytes = index.as_bytes().to_vec();
+    let restored = VectorIndex::from_bytes(&bytes, 32, IndexOptions::default()).unwrap();
+    assert_eq!(restored.len(), 300);
+
+    let results = restored.search(&[42.0; 32], 1).unwrap();
+    assert_eq!(results[0].id, 42);
+
+    assert!(VectorIndex::from_bytes(&bytes, 16, IndexOptions::default()).is_err());
+    assert!(VectorIndex::from_bytes(&bytes[..100], 32, IndexOptions::default()).is_err());
+}
+
+#[test]
+fn test_in_memory_index_loads_native_file() {
+    let temp_file = NamedTempFile::new().unwrap();
+    {
+        let mut index = VectorIndex::open(temp_file.path(), 16, IndexOptions::default()).unwrap();
+        for i in 0..50 {
+            index.add(&[i as f32; 16]).unwrap();
+        }
+        index.flush().unwrap();
+    }
+
+    let bytes = std::fs::read(temp_file.path()).unwrap();
+    let mut index = VectorIndex::from_bytes(&bytes, 16, IndexOptions::default()).unwrap();
+    assert_eq!(index.len(), 50);
+    assert_eq!(index.add(&[99.0; 16]).unwrap(), 50);
+    assert_eq!(index.search(&[99.0; 16], 1).unwrap()[0].id, 50);
+}
+
+/// Copy `bytes` into a leaked 4 KiB-aligned buffer, as an embedded asset would be
+fn leak_aligned(bytes: &[u8]) -> &'static [u8] {
+    #[repr(C, align(4096))]
+    #[derive(Clone, Copy)]
+    struct Page([u8; 4096]);
+
+    let pages = Box::leak(vec![Page([0; 4096]); bytes.len().div_ceil(4096)].into_boxed_slice());
+    // SAFETY: `Page` is plain bytes without padding, so the pages are one
+    // contiguous initialized run.
+    let image = unsafe {
+        std::slice::from_raw_parts_mut(pages.as_mut_ptr().cast::<u8>(), pages.len() * 4096)
+    };
+    image[..bytes.len()].copy_from_slice(bytes);
+    &image[..bytes.len()]
+}
+
+#[test]
+fn test_open_from_bytes_reads_embedded_image_in_place() {
+    let temp_file = NamedTempFile::new().unwrap();
+    {
+        let mut index = VectorIndex::open(temp_file.path(), 16, IndexOptions::default()).unwrap();
+        for i in 0..50 {
+            index.add(&[i as f32; 16]).unwrap();
+        }
+        index.flush().unwrap();
+    }
+    let file = std::fs::read(temp_file.path()).unwrap();
+
+    let bytes = leak_aligned(&file);
+    let mut index = VectorIndex::open_from_bytes(bytes, IndexOptions::default()).unwrap();
+    assert!(index.is_sealed());
+    assert_eq!(index.dimensions(), 16);
+    assert_eq!(index.len(), 50);
+    assert_eq!(index.search(&[7.0; 16], 1).unwrap()[0].id, 7);
+    assert_eq!(index.get_vector(3).unwrap(), vec![3.0; 16]);
+
+    let err = index.add(&[1.0; 16]).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::ReadOnly);
+    index.release_memory().unwrap();
+    assert_eq!(bytes, &file[..]);
+
+    // Misaligned images are refused instead of read through unaligned casts
+    let misaligned = &leak_aligned(&[&[0u8][..], &file].concat())[1..];
+    let err = VectorIndex::open_from_bytes(misaligned, IndexOptions::default()).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+
+    let not_index: &'static [u8] = chassis_core::include_index!("index_integration.rs");
+    assert_eq!(not_index.as_ptr() as usize % 4096, 0);
+    let err = VectorIndex::open_from_bytes(not_index, IndexOptions::default()).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::Corrupted);
+}
+
+#[test]
+fn test_durable_only_search_excludes_unflushed_vectors() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let durable =
+        SearchOptions { consistency: SearchConsistency::DurableOnly, ..Default::default() };
+
+    let mut index = VectorIndex::open(temp_file.path(), 16, IndexOptions::default()).unwrap();
+    for i in 0..100 {
+        index.add(&[i as f32; 16]).unwrap();
+    }
+    assert_eq!(index.durable_len(), 0);
+    assert!(index.search_with_options(&[5.0; 16], 5, &durable).unwrap().is_empty());
+
+    index.flush().unwrap();
+    assert_eq!(index.durable_len(), 100);
+
+    // Unflushed vectors are visible by default but hidden from durable-only searches
+    for i in 100..150 {
+        index.add(&[i as f32; 16]).unwrap();
+    }
+    assert_eq!(index.search(&[120.0; 16], 1).unwrap()[0].id, 120);
+
+    let results = index.search_with_options(&[120.0; 16], 10, &durable).unwrap();
+    assert_eq!(results.len(), 10);
+    assert_eq!(results[0].id, 99);
+    assert!(results.iter().all(|r| r.id < 100));
+
+    drop(index);
+    let index = VectorIndex::open(temp_file.path(), 16, IndexOptions::default()).unwrap();
+    assert_eq!(index.durable_len(), index.len());
+}
+
+#[test]
+fn test_durable_only_search_excludes_reused_slots_until_flushed() {
+    use chassis_core::IdReuse;
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let durable =
+        SearchOptions { consistency: SearchConsistency::DurableOnly, ..Default::default() };
+    let options = IndexOptions { id_reuse: IdReuse::ReuseDeleted, ..Default::default() };
+
+    let mut index = VectorIndex::open(temp_file.path(), 16, options).unwrap();
+    for i in 0..100 {
+        index.add(&[i as f32; 16]).unwrap();
+    }
+    assert!(index.delete(40).unwrap());
+    index.flush().unwrap();
+
+    // The reused ID lies below the durable count, but its vector is not durable yet
+    assert_eq!(index.add(&[1000.0; 16]).unwrap(), 40);
+    assert_eq!(index.durable_len(), 100);
+    assert_eq!(index.search(&[1000.0; 16], 1).unwrap()[0].id, 40);
+    let results = index.search_with_options(&[1000.0; 16], 5, &durable).unwrap();
+    assert_eq!(results.len(), 5);
+    assert!(results.iter().all(|r| r.id != 40));
+    let within = index.search_within_with_options(&[1000.0; 16], 4000.0, &durable).unwrap();
+    assert!(!within.is_empty() && within.iter().all(|r| r.id != 40));
+
+    // Deleting it again before the flush frees the slot for the next insert
+    assert!(index.delete(40).unwrap());
+    assert_eq!(index.deleted_count(), 1);
+    assert_eq!(index.add(&[2000.0; 16]).unwrap(), 40);
+    assert_eq!(index.deleted_count(), 0);
+
+    index.flush().unwrap();
+    let results = index.search_with_options(&[2000.0; 16], 1, &durable).unwrap();
+    assert_eq!(results[0].id, 40);
+}
+
+#[test]
+fn test_flush_async_publishes_in_background() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let options = IndexOptions { ef_construction: 16, ..Default::default() };
+
+    // 16 KiB vectors: 600 of them outgrow the slack in front of the graph zone
+    let mut index = VectorIndex::open(temp_file.path(), 4096, options.clone()).unwrap();
+    for i in 0..100 {
+        index.add(&[i as f32; 4096]).unwrap();
+    }
+    index.flush_async().unwrap();
+
+    // The writer keeps going, including past a graph zone relocation
+    for i in 100..600 {
+        index.add(&[i as f32; 4096]).unwrap();
+    }
+    index.wait_for_flush().unwrap();
+    assert_eq!(index.durable_len(), 100);
+
+    index.flush_async().unwrap();
+    drop(index);
+
+    let index = VectorIndex::open(temp_file.path(), 4096, options).unwrap();
+    assert_eq!(index.len(), 600);
+    assert_eq!(index.search(&[500.0; 4096], 1).unwrap()[0].id, 500);
+}
+
+#[test]
+fn test_flush_policy_flushes_in_background() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let options =
+        IndexOptions { flush_policy: FlushPolicy::EveryInserts(10), ..Default::default() };
+
+    let mut index = VectorIndex::open(temp_file.path(), 16, options).unwrap();
+    for i in 0..25 {
+        index.add(&[i as f32; 16]).unwrap();
+    }
+    index.wait_for_flush().unwrap();
+    assert_eq!(index.durable_len(), 20);
+
+    // A manual flush restarts the count
+    index.flush().unwrap();
+    for i in 25..34 {
+        index.add(&[i as f32; 16]).unwrap();
+    }
+    index.wait_for_flush().unwrap();
+    assert_eq!(index.durable_len(), 25);
+    drop(index);
+
+    let options = IndexOptions { flush_policy: FlushPolicy::EveryMillis(0), ..Default::default() };
+    let mut index = VectorIndex::open(temp_file.path(), 16, options).unwrap();
+    assert_eq!(index.len(), 25);
+    index.add(&[40.0; 16]).unwrap();
+    index.wait_for_flush().unwrap();
+    assert_eq!(index.durable_len(), 26);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_flush_detects_deleted_file_and_reattach_recovers() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("index.chassis");
+
+    let mut index = VectorIndex::open(&path, 16, IndexOptions::default()).unwrap();
+    for i in 0..20 {
+        index.add(&[i as f32; 16]).unwrap();
+    }
+    index.flush().unwrap();
+    index.add(&[20.0; 16]).unwrap();
+
+    std::fs::remove_file(&path).unwrap();
+    let err = index.flush().unwrap_err();
+    let stolen = err.downcast_ref::<FileStolen>().expect("typed FileStolen error");
+    assert!(stolen.deleted);
+    assert_eq!(stolen.path, path);
+
+    index.reattach(&path).unwrap();
+    index.flush().unwrap();
+    drop(index);
+
+    let index = VectorIndex::open(&path, 16, IndexOptions::default()).unwrap();
+    assert_eq!(index.len(), 21);
+    assert_eq!(index.search(&[20.0; 16], 1).unwrap()[0].id, 20);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_flush_detects_replaced_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("index.chassis");
+    let replacement = dir.path().join("replacement");
+
+    let mut index = VectorIndex::open(&path, 16, IndexOptions::default()).unwrap();
+    index.add(&[1.0; 16]).unwrap();
+
+    std::fs::write(&replacement, b"synced copy").unwrap();
+    std::fs::rename(&replacement, &path).unwrap();
+
+    let err = index.flush().unwrap_err();
+    assert!(!err.downcast_ref::<FileStolen>().unwrap().deleted);
+    assert_eq!(std::fs::read(&path).unwrap(), b"synced copy");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_reattach_adopts_moved_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("index.chassis");
+    let moved = dir.path().join("moved.chassis");
+
+    let mut index = VectorIndex::open(&path, 16, IndexOptions::default()).unwrap();
+    index.add(&[1.0; 16]).unwrap();
+
+    std::fs::rename(&path, &moved).unwrap();
+    assert!(index.flush().unwrap_err().downcast_ref::<FileStolen>().is_some());
+
+    index.reattach(&moved).unwrap();
+    index.add(&[2.0; 16]).unwrap();
+    index.flush().unwrap();
+    drop(index);
+
+    assert!(!path.exists());
+    let index = VectorIndex::open(&moved, 16, IndexOptions::default()).unwrap();
+    assert_eq!(index.len(), 2);
+}
+
+#[test]
+fn test_version_policy_refuses_newer_minor_writer() {
+    use chassis_core::{ErrorKind, HEADER_SIZE, Header, LibraryVersion, VersionPolicy};
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let path = temp_file.path().to_owned();
+
+    {
+        let mut index = VectorIndex::open(&path, 16, IndexOptions::default()).unwrap();
+        assert_eq!(index.written_by(), None);
+        index.add(&[1.0; 16]).unwrap();
+        index.flush().unwrap();
+        assert_eq!(index.written_by(), Some(LibraryVersion::CURRENT));
+    }
+
+    // Pretend a newer library flushed the file
+    let current = LibraryVersion::CURRENT;
+    let newer = LibraryVersion { minor: current.minor + 1, patch: 0, ..current };
+    let mut bytes = std::fs::read(&path).unwrap();
+    let mut header = unsafe { std::ptr::read_unaligned(bytes.as_ptr().cast::<Header>()) };
+    header.set_writer_version(newer);
+    bytes[..HEADER_SIZE].copy_from_slice(header.as_bytes());
+    std::fs::write(&path, &bytes).unwrap();
+
+    let strict =
+        IndexOptions { version_policy: VersionPolicy::RefuseNewerMinor, ..Default::default() };
+    let err = VectorIndex::open(&path, 16, strict.clone()).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::IncompatibleVersion);
+    assert!(VectorIndex::open_shared(&path, 16, strict.clone()).is_err());
+    assert_eq!(std::fs::read(&path).unwrap(), bytes);
+
+    // The default policy is advisory: open, then the next flush restamps
+    let mut index = VectorIndex::open(&path, 16, IndexOptions::default()).unwrap();
+    assert_eq!(index.written_by(), Some(newer));
+    index.flush().unwrap();
+    assert_eq!(index.written_by(), Some(current));
+    drop(index);
+
+    assert!(VectorIndex::open(&path, 16, strict).is_ok());
+}
+
+#[test]
+fn test_creation_metadata_and_user_meta_persist() {
+    use chassis_core::{ErrorKind, LibraryVersion, MAX_USER_META_LEN};
+    use std::time::{Duration, SystemTime};
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let path = temp_file.path().to_owned();
+    let before = SystemTime::now() - Duration::from_secs(1);
+
+    {
+        let mut index = VectorIndex::open(&path, 8, IndexOptions::default()).unwrap();
+        let created_at = index.created_at().unwrap();
+        assert!(created_at >= before && created_at <= SystemTime::now());
+        assert_eq!(index.created_by(), Some(LibraryVersion::CURRENT));
+        assert_eq!(index.get_meta(), b"");
+
+        index.set_meta(b"model=text-embedding-3-small;dims=8").unwrap();
+        let err = index.set_meta(&[0; MAX_USER_META_LEN + 1]).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+        index.add(&[1.0; 8]).unwrap();
+        index.flush().unwrap();
+    }
+
+    let index = VectorIndex::open_shared(&path, 8, IndexOptions::default()).unwrap();
+    assert_eq!(index.get_meta(), b"model=text-embedding-3-small;dims=8");
+    assert_eq!(index.created_by(), Some(LibraryVersion::CURRENT));
+    assert!(index.created_at().unwrap() >= before);
+    drop(index);
+
+    let mut index = VectorIndex::open_shared(&path, 8, IndexOptions::default()).unwrap();
+    let err = index.set_meta(b"other").unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::ReadOnly);
+}
+
+#[test]
+fn test_max_file_bytes_stops_growth_without_corrupting() {
+    use chassis_core::{ErrorKind, graph_file_path};
+
+    const QUOTA: u64 = 256 * 1024;
+
+    for graph_file in [false, true] {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("quota.chassis");
+        let file_bytes = || {
+            let graph = std::fs::metadata(graph_file_path(&path)).map_or(0, |m| m.len());
+            std::fs::metadata(&path).unwrap().len() + graph
+        };
+        let options = IndexOptions {
+            max_file_bytes: Some(QUOTA),
+            growth_chunk: 64 * 1024,
+            graph_file,
+            ..Default::default()
+        };
+
+        let mut index = VectorIndex::open(&path, 64, options.clone()).unwrap();
+        let vector = |i: u64| [i as f32; 64];
+        let err = loop {
+            match index.add(&vector(index.len())) {
+                Ok(_) => assert!(file_bytes() <= QUOTA),
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(ErrorKind::of(&err), ErrorKind::QuotaExceeded);
+        assert!(file_bytes() <= QUOTA);
+
+        // The failed add left nothing behind; a higher limit lets the next one in
+        let len = index.len();
+        assert!(len > 100, "quota hit after only {} vectors", len);
+        assert_eq!(index.search(&vector(len - 1), 1).unwrap()[0].id, len - 1);
+        index.set_max_file_bytes(None);
+        assert_eq!(index.add(&vector(len)).unwrap(), len);
+        index.flush().unwrap();
+        drop(index);
+
+        let mut index = VectorIndex::open(&path, 64, options).unwrap();
+        assert_eq!(index.len(), len + 1);
+        assert_eq!(index.search(&vector(len), 1).unwrap()[0].id, len);
+
+        // The unlimited add grew the file past the quota; it fills but never grows again
+        let size = file_bytes();
+        let err = loop {
+            match index.add(&vector(index.len())) {
+                Ok(_) => assert_eq!(file_bytes(), size),
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(ErrorKind::of(&err), ErrorKind::QuotaExceeded);
+    }
+}
+
+#[test]
+fn test_memory_modes_prefetch_and_release() {
+    use chassis_core::{ErrorKind, MemoryMode};
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let options = IndexOptions { memory_mode: MemoryMode::Resident, ..Default::default() };
+    let mut index = VectorIndex::open(temp_file.path(), 32, options).unwrap();
+
+    // Growth remaps the file; the advice must survive it
+    for i in 0..500 {
+        index.add(&[i as f32; 32]).unwrap();
+    }
+
+    index.set_memory_mode(MemoryMode::Random).unwrap();
+    index.prefetch(&[0, 250, 499]).unwrap();
+    let err = index.prefetch(&[500]).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::OutOfBounds);
+
+    // Dropping resident pages must not lose unflushed vectors
+    index.release_memory().unwrap();
+    assert_eq!(index.search(&[321.0; 32], 1).unwrap()[0].id, 321);
+
+    index.set_memory_mode(MemoryMode::Sequential).unwrap();
+    index.flush().unwrap();
+    drop(index);
+
+    let index = VectorIndex::open(temp_file.path(), 32, IndexOptions::default()).unwrap();
+    assert_eq!(index.len(), 500);
+    assert_eq!(index.search(&[499.0; 32], 1).unwrap()[0].distance, 0.0);
+}
+
+#[test]
+fn test_build_from_reader_streams_vectors() {
+    use chassis_core::{ErrorKind, HnswBuilder, HnswParams};
+    use std::io::Cursor;
+
+    let dims = 8u32;
+    let bytes: Vec<u8> = (0..300)
+        .flat_map(|i| std::iter::repeat_n(i as f32, dims as usize))
+        .flat_map(f32::to_le_bytes)
+        .collect();
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let builder = HnswBuilder::new(HnswParams::default()).chunk_size(64).flush_interval(100);
+    let index =
+        builder.build_from_reader(temp_file.path(), Cursor::new(&bytes), dims, 300).unwrap();
+    assert_eq!(index.len(), 300);
+    assert_eq!(index.search(&[123.0; 8], 1).unwrap()[0].id, 123);
+    drop(index);
+
+    let index = VectorIndex::open(temp_file.path(), dims, IndexOptions::default()).unwrap();
+    assert_eq!(index.len(), 300);
+    drop(index);
+
+    // A short source fails in the last chunk; the flush at 256 vectors survives
+    let short = NamedTempFile::new().unwrap();
+    let err = builder.build_from_reader(short.path(), Cursor::new(&bytes), dims, 301).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::Io);
+    let index = VectorIndex::open(short.path(), dims, IndexOptions::default()).unwrap();
+    assert_eq!(index.len(), 256);
+}
+
+#[test]
+fn test_snapshot_to_copies_open_index() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("index.chassis");
+    let backup = dir.path().join("backup.chassis");
+
+    let mut index = VectorIndex::open(&path, 16, IndexOptions::default()).unwrap();
+    for i in 0..50 {
+        index.add(&[i as f32; 16]).unwrap();
+    }
+
+    // Unflushed vectors are included: the snapshot flushes first
+    index.snapshot_to(&backup).unwrap();
+    assert!(index.snapshot_to(&path).is_err());
+
+    // The source stays open and writable; later writes don't reach the snapshot
+    index.add(&[50.0; 16]).unwrap();
+    index.flush().unwrap();
+
+    let copy = VectorIndex::open(&backup, 16, IndexOptions::default()).unwrap();
+    assert_eq!(copy.len(), 50);
+    assert_eq!(copy.search(&[7.0; 16], 1).unwrap()[0].id, 7);
+    drop(copy);
+
+    // Overwrites an existing snapshot, and works from a shared reader too
+    drop(index);
+    let mut reader = VectorIndex::open_shared(&path, 16, IndexOptions::default()).unwrap();
+    reader.snapshot_to(&backup).unwrap();
+    drop(reader);
+    assert_eq!(VectorIndex::open(&backup, 16, IndexOptions::default()).unwrap().len(), 51);
+
+    let leftovers: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.ends_with(".snapshot-tmp"))
+        .collect();
+    assert!(leftovers.is_empty());
+}
+
+#[test]
+fn test_snapshot_compressed_to_shrinks_graph_and_stays_read_only() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("index.chassis");
+    let copy = dir.path().join("compressed.chassis");
+    let dims = 16;
+
+    let mut index = VectorIndex::open(&path, dims, IndexOptions::default()).unwrap();
+    let vector =
+        |i: u32| -> Vec<f32> { (0..dims).map(|d| ((i * dims + d) as f32 * 0.37).sin()).collect() };
+    for i in 0..2000 {
+        index.add(&vector(i)).unwrap();
+    }
+    index.delete(5).unwrap();
+
+    index.snapshot_compressed_to(&copy).unwrap();
+    assert!(index.snapshot_compressed_to(&path).is_err());
+
+    let sealed = VectorIndex::open_sealed(&copy, IndexOptions::default()).unwrap();
+    assert_eq!(sealed.len(), index.len());
+    let original = index.stats().unwrap().graph_bytes;
+    let compressed = sealed.stats().unwrap().graph_bytes;
+    assert!(compressed * 10 < original * 6, "{compressed} vs {original} bytes");
+
+    // Sorted neighbor lists change the order nodes are visited in, so results
+    // can differ at the margin, but not by much
+    let mut shared_hits = 0;
+    for i in (0..2000).step_by(97) {
+        let expected: Vec<_> = index.search(&vector(i), 10).unwrap().iter().map(|r| r.id).collect();
+        let results: Vec<_> = sealed.search(&vector(i), 10).unwrap().iter().map(|r| r.id).collect();
+        assert_eq!(results[0], expected[0]);
+        assert!(!results.contains(&5));
+        shared_hits += results.iter().filter(|id| expected.contains(id)).count();
+    }
+    assert!(shared_hits >= 190, "{shared_hits} of 210 results shared");
+    assert!(sealed.search(&vector(5), 1).unwrap()[0].id != 5);
+    drop(sealed);
+
+    // Writers refuse the copy; shared readers can search it
+    let err = VectorIndex::open(&copy, dims, IndexOptions::default()).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::ReadOnly);
+    let shared = VectorIndex::open_shared(&copy, dims, IndexOptions::default()).unwrap();
+    assert_eq!(shared.search(&vector(42), 1).unwrap()[0].id, 42);
+
+    let bytes = leak_aligned(&std::fs::read(&copy).unwrap());
+    let embedded = VectorIndex::open_from_bytes(bytes, IndexOptions::default()).unwrap();
+    assert_eq!(embedded.search(&vector(42), 1).unwrap()[0].id, 42);
+}
+
+#[test]
+fn test_search_within_returns_all_vectors_in_radius() {
+    use chassis_core::ErrorKind;
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let durable =
+        SearchOptions { consistency: SearchConsistency::DurableOnly, ..Default::default() };
+
+    let mut index = VectorIndex::open(temp_file.path(), 8, IndexOptions::default()).unwrap();
+    for i in 0..200 {
+        index.add(&[i as f32; 8]).unwrap();
+    }
+
+    // Neighbouring vectors are sqrt(8) apart, so a radius of 6 covers +/- 2
+    let results = index.search_within(&[100.0; 8], 6.0).unwrap();
+    let ids: Vec<u64> = results.iter().map(|r| r.id).collect();
+    assert_eq!(ids[0], 100);
+    let mut sorted = ids.clone();
+    sorted.sort_unstable();
+    assert_eq!(sorted, vec![98, 99, 100, 101, 102]);
+
+    // Radius wider than ef_search still returns every match
+    let wide = index.search_within(&[100.0; 8], 200.0).unwrap();
+    assert_eq!(wide.len(), 141);
+    assert!(wide.windows(2).all(|w| w[0].distance <= w[1].distance));
+
+    assert!(index.search_within(&[1000.0; 8], 1.0).unwrap().is_empty());
+    for bad in [-1.0, f32::NAN] {
+        let err = index.search_within(&[0.0; 8], bad).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+    }
+
+    assert!(index.search_within_with_options(&[100.0; 8], 6.0, &durable).unwrap().is_empty());
+    index.flush().unwrap();
+    assert_eq!(index.search_within_with_options(&[100.0; 8], 6.0, &durable).unwrap().len(), 5);
+}
+
+#[test]
+fn test_resume_from_reader_continues_interrupted_build() {
+    use chassis_core::{BuildProgress, ErrorKind, HnswBuilder, HnswParams};
+    use std::io::{Cursor, Read};
+
+    let dims = 8u32;
+    let bytes: Vec<u8> = (0..300)
+        .flat_map(|i| std::iter::repeat_n(i as f32, dims as usize))
+        .flat_map(f32::to_le_bytes)
+        .collect();
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let builder = HnswBuilder::new(HnswParams::default()).chunk_size(32).flush_interval(64);
+
+    // The source dies partway through the 5th flush interval
+    let truncated = Cursor::new(&bytes).take(290 * 32);
+    assert!(builder.build_from_reader(temp_file.path(), truncated, dims, 300).is_err());
+
+    let index = VectorIndex::open(temp_file.path(), dims, IndexOptions::default()).unwrap();
+    assert_eq!(
+        index.build_progress(),
+        Some(BuildProgress { input_offset: 256 * 32, vectors_done: 256, total: 300 })
+    );
+
+    // Links to the rolled-back vectors 256..288 must not break search
+    assert_eq!(index.search(&[255.0; 8], 1).unwrap()[0].id, 255);
+    drop(index);
+
+    let err =
+        builder.resume_from_reader(temp_file.path(), Cursor::new(&bytes), dims, 500).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+
+    let index =
+        builder.resume_from_reader(temp_file.path(), Cursor::new(&bytes), dims, 300).unwrap();
+    assert_eq!(index.len(), 300);
+    assert!(index.build_progress().unwrap().is_complete());
+    for i in [0, 255, 256, 299] {
+        assert_eq!(index.search(&[i as f32; 8], 1).unwrap()[0].id, i);
+    }
+    drop(index);
+
+    // Resuming a finished build reads nothing
+    let index =
+        builder.resume_from_reader(temp_file.path(), Cursor::new(Vec::new()), dims, 300).unwrap();
+    assert_eq!(index.len(), 300);
+}
+
+#[test]
+fn test_memory_footprint_reports_mapping_and_scratch() {
+    use chassis_core::{TieredIndex, TieredOptions};
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = VectorIndex::open(temp_file.path(), 64, IndexOptions::default()).unwrap();
+    let empty = index.memory_footprint().unwrap();
+
+    for i in 0..1000 {
+        index.add(&[i as f32; 64]).unwrap();
+    }
+    index.search(&[500.0; 64], 10).unwrap();
+
+    let footprint = index.memory_footprint().unwrap();
+    assert!(footprint.mapped_bytes >= 1000 * 64 * 4);
+    assert!(footprint.scratch_bytes > empty.scratch_bytes);
+    if cfg!(unix) {
+        // Pages just written are in RAM
+        let resident = footprint.resident_bytes.unwrap();
+        assert!(resident > 0 && resident <= footprint.mapped_bytes);
+    }
+    assert!(footprint.total() >= footprint.heap_bytes + footprint.scratch_bytes);
+
+    // Search buffers stay pooled until released
+    assert!(footprint.heap_bytes >= empty.heap_bytes + 1000 * 4);
+    index.release_memory().unwrap();
+    let released = index.memory_footprint().unwrap();
+    assert!(released.heap_bytes < footprint.heap_bytes);
+
+    // The recent tier of a TieredIndex is counted as heap
+    let mut tiered = TieredIndex::new(index, TieredOptions::default()).unwrap();
+    for _ in 0..100 {
+        tiered.add(&[0.0; 64]).unwrap();
+    }
+    let tiered_footprint = tiered.memory_footprint().unwrap();
+    assert!(tiered_footprint.heap_bytes >= released.heap_bytes + 100 * 64 * 4);
+}
+
+#[test]
+fn test_half_precision_indexes_roundtrip_and_search() {
+    use chassis_core::{ElementType, ErrorKind, Storage};
+
+    let dims = 64;
+    let vector = |i: usize| (0..dims).map(|j| ((i * dims + j) as f32 * 0.37).sin()).collect();
+    let vectors: Vec<Vec<f32>> = (0..300).map(vector).collect();
+
+    for element_type in [ElementType::F16, ElementType::BF16] {
+        let temp_file = NamedTempFile::new().unwrap();
+        let options = IndexOptions { element_type, ..Default::default() };
+        {
+            let mut index = VectorIndex::open(temp_file.path(), dims as u32, options).unwrap();
+            for v in &vectors {
+                index.add(v).unwrap();
+            }
+            index.flush().unwrap();
+        }
+
+        // The stored type wins over the options once the index holds vectors
+        let index =
+            VectorIndex::open(temp_file.path(), dims as u32, IndexOptions::default()).unwrap();
+        assert_eq!(index.element_type(), element_type);
+        assert_eq!(index.len(), 300);
+        for (i, v) in vectors.iter().enumerate().step_by(37) {
+            let results = index.search(v, 1).unwrap();
+            assert_eq!(results[0].id, i as u64);
+            assert!(results[0].distance < 0.05, "{}: {}", element_type, results[0].distance);
+        }
+        drop(index);
+
+        let storage = Storage::open(temp_file.path(), dims as u32).unwrap();
+        let err = storage.get_vector_slice(0).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+        let decoded = storage.get_vector(5).unwrap();
+        assert!(decoded.iter().zip(&vectors[5]).all(|(a, b)| (a - b).abs() < 1e-2));
+        assert_eq!(storage.element_type(), element_type);
+    }
+}
+
+#[test]
+fn test_binary_index_searches_by_hamming_distance() {
+    use chassis_core::{CustomDistance, Distance, ElementType, ErrorKind};
+
+    let dims = 256;
+    // Pseudo-random bits, so no two vectors are near duplicates
+    let bits = |i: usize| -> Vec<f32> {
+        (0..dims)
+            .map(|j| {
+                // splitmix64 finalizer, since a bare multiplicative hash leaves
+                // neighbouring vectors strongly correlated
+                let mut z = ((i * dims + j) as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                (z ^ (z >> 31)) >> 63
+            })
+            .map(|bit| bit as f32)
+            .collect()
+    };
+    let vectors: Vec<Vec<f32>> = (0..300).map(bits).collect();
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let options = IndexOptions { element_type: ElementType::Binary, ..Default::default() };
+    {
+        let mut index = VectorIndex::open(temp_file.path(), dims as u32, options.clone()).unwrap();
+        for v in &vectors {
+            index.add(v).unwrap();
+        }
+        index.flush().unwrap();
+
+        // 32 bytes per vector instead of 1 KiB
+        assert_eq!(index.stats().unwrap().vector_bytes, 300 * 32);
+    }
+
+    let index = VectorIndex::open(temp_file.path(), dims as u32, IndexOptions::default()).unwrap();
+    assert_eq!(index.element_type(), ElementType::Binary);
+    for i in (0..300).step_by(29) {
+        let mut query = vectors[i].clone();
+        for j in [3, 100, 255] {
+            query[j] = 1.0 - query[j];
+        }
+        let results = index.search(&query, 1).unwrap();
+        assert_eq!(results[0].id, i as u64);
+        assert_eq!(results[0].distance, 3.0);
+        assert_eq!(index.search_exact(&query, 1).unwrap()[0].distance, 3.0);
+    }
+    assert_eq!(index.search_with_vectors(&vectors[7], 1).unwrap()[0].vector, vectors[7]);
+    drop(index);
+
+    let invalid = [
+        IndexOptions { normalize: true, ..options.clone() },
+        IndexOptions {
+            distance: Distance::Custom(CustomDistance::new("l1", |a, b| {
+                a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum()
+            })),
+            ..options
+        },
+    ];
+    for options in invalid {
+        let temp_file = NamedTempFile::new().unwrap();
+        let err = VectorIndex::open(temp_file.path(), dims as u32, options).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+    }
+}
+
+#[test]
+fn test_workload_profile_summarizes_usage_without_vectors() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let options = IndexOptions { ef_search: 40, ..Default::default() };
+    let mut index = VectorIndex::open(temp_file.path(), 16, options).unwrap();
+
+    for i in 0..200 {
+        index.add(&[i as f32 * 0.123; 16]).unwrap();
+    }
+    for _ in 0..10 {
+        index.search(&[1.0; 16], 5).unwrap();
+    }
+    index.search(&[1.0; 16], 100).unwrap();
+    index.search_within(&[1.0; 16], 0.5).unwrap();
+
+    let profile = index.export_workload_profile();
+    assert_eq!(profile.dimensions, 16);
+    assert_eq!(profile.vectors, 200);
+    assert_eq!(profile.inserts, 200);
+    assert_eq!(profile.queries, 11);
+    assert_eq!(profile.range_queries, 1);
+    assert_eq!(profile.k_histogram, vec![(8, 10), (128, 1)]);
+    assert_eq!(profile.ef_histogram, vec![(64, 10), (128, 1)]);
+    assert_eq!(profile.latency_histogram_us.iter().map(|b| b.1).sum::<u64>(), 12);
+    assert!(profile.query_rate().unwrap() > 0.0);
+
+    let json = profile.to_json();
+    assert!(json.starts_with('{') && json.ends_with('}'));
+    assert!(json.contains("\"k_histogram\":[[8,10],[128,1]]"));
+    assert!(!json.contains(&*temp_file.path().to_string_lossy()));
+    assert!(!json.contains("0.123"));
+}
+
+#[test]
+fn test_preset_options_build_reopen_and_grow_in_chunks() {
+    use chassis_core::Preset;
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let options = IndexOptions::preset(Preset::Balanced, 64, 20_000);
+    assert!(options.max_layers < IndexOptions::default().max_layers);
+    assert!(options.growth_chunk > 4096);
+
+    {
+        let mut index = VectorIndex::open(temp_file.path(), 64, options.clone()).unwrap();
+        index.add(&[0.5; 64]).unwrap();
+        // One insert reserves a whole chunk instead of a page
+        let len = std::fs::metadata(temp_file.path()).unwrap().len() as usize;
+        assert!(len >= options.growth_chunk, "{} < {}", len, options.growth_chunk);
+
+        for i in 1..300 {
+            index.add(&[i as f32 * 0.01 + 0.003; 64]).unwrap();
+        }
+        index.flush().unwrap();
+    }
+
+    let index = VectorIndex::open(temp_file.path(), 64, options.clone()).unwrap();
+    assert_eq!(index.len(), 300);
+    assert_eq!(index.search(&[0.5; 64], 1).unwrap()[0].id, 0);
+    drop(index);
+
+    // max_layers sizes node records, so the stored value wins over the options
+    let index = VectorIndex::open(temp_file.path(), 64, IndexOptions::default()).unwrap();
+    assert_eq!(index.options().max_layers, options.max_layers);
+    assert_eq!(index.search(&[0.5; 64], 1).unwrap()[0].id, 0);
+    drop(index);
+
+    let invalid = IndexOptions { max_layers: 0, ..Default::default() };
+    let err = VectorIndex::open_in_memory(8, invalid).unwrap_err();
+    assert_eq!(chassis_core::ErrorKind::of(&err), chassis_core::ErrorKind::InvalidArgument);
+}
+
+#[test]
+fn test_adaptive_ef_construction_keeps_recall() {
+    let dims = 16;
+    let vector =
+        |i: u32| -> Vec<f32> { (0..dims).map(|d| ((i * dims + d) as f32 * 0.61).sin()).collect() };
+
+    let recall = |options: IndexOptions| {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut index = VectorIndex::open(temp_file.path(), dims, options).unwrap();
+        for i in 0..1000 {
+            index.add(&vector(i)).unwrap();
+        }
+
+        let mut found = 0;
+        for q in 0..50 {
+            let query: Vec<f32> = (0..dims).map(|d| ((q * 7 + d) as f32 * 1.3).cos()).collect();
+            let exact: Vec<_> =
+                index.search_exact(&query, 10).unwrap().iter().map(|r| r.id).collect();
+            let results = index.search(&query, 10).unwrap();
+            found += results.iter().filter(|r| exact.contains(&r.id)).count();
+        }
+        found as f64 / 500.0
+    };
+
+    let full = recall(IndexOptions::default());
+    let adaptive = recall(IndexOptions { adaptive_ef_construction: true, ..Default::default() });
+    assert!(adaptive >= 0.9, "adaptive recall {adaptive}");
+    assert!(adaptive >= full - 0.03, "adaptive recall {adaptive}, full beam {full}");
+}
+
+#[test]
+fn test_max_connections_0_persists_and_widens_layer_0() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let options = IndexOptions {
+        max_connections: 16,
+        max_connections_0: Some(48),
+        ef_construction: 100,
+        ..Default::default()
+    };
+
+    {
+        let mut index = VectorIndex::open(temp_file.path(), 8, options).unwrap();
+        let mut state = 0x9e37_79b9_u32;
+        for _ in 0..400 {
+            let vector: Vec<f32> = (0..8)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    (state % 1000) as f32 / 1000.0
+                })
+                .collect();
+            index.add(&vector).unwrap();
+        }
+        assert_eq!(index.graph().record_params().m0, 48);
+        // The default M0 of 32 would cap every list at 32
+        let widest = (0..400)
+            .map(|id| index.graph().neighbors_iter_from_mmap(id, 0).unwrap().count())
+            .max()
+            .unwrap();
+        assert!(widest > 32, "widest layer-0 list has {} links", widest);
+        index.flush().unwrap();
+    }
+
+    // M0 sizes node records, so the stored value wins over the options
+    let index = VectorIndex::open(temp_file.path(), 8, IndexOptions::default()).unwrap();
+    assert_eq!(index.options().max_connections_0, Some(48));
+    assert_eq!(index.graph().record_params().m0, 48);
+    drop(index);
+
+    for m0 in [8, chassis_core::MAX_CONNECTIONS_0 + 1] {
+        let invalid =
+            IndexOptions { max_connections: 16, max_connections_0: Some(m0), ..Default::default() };
+        let err = VectorIndex::open_in_memory(8, invalid).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+    }
+}
+
+#[test]
+fn test_write_buffer_holds_records_until_flush() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let options = IndexOptions { write_buffer_records: 256, ..Default::default() };
+
+    {
+        let mut index = VectorIndex::open(temp_file.path(), 4, options.clone()).unwrap();
+        for i in 0..300 {
+            index.add(&[i as f32, (i % 7) as f32, 0.0, 1.0]).unwrap();
+        }
+        assert!(index.delete(42).unwrap());
+        // Searches read buffered records before they reach the file
+        assert_eq!(index.search(&[100.0, 2.0, 0.0, 1.0], 1).unwrap()[0].id, 100);
+        assert_ne!(index.search(&[42.0, 0.0, 0.0, 1.0], 1).unwrap()[0].id, 42);
+        index.flush().unwrap();
+
+        for i in 300..400 {
+            index.add(&[i as f32, (i % 7) as f32, 0.0, 1.0]).unwrap();
+        }
+        index.flush_async().unwrap();
+        index.wait_for_flush().unwrap();
+
+        // Not flushed: rolled back on reopen
+        index.add(&[1000.0, 0.0, 0.0, 1.0]).unwrap();
+    }
+
+    let index = VectorIndex::open(temp_file.path(), 4, IndexOptions::default()).unwrap();
+    assert_eq!(index.len(), 400);
+    for id in [0, 100, 299, 350, 399] {
+        let query = [id as f32, (id % 7) as f32, 0.0, 1.0];
+        assert_eq!(index.search(&query, 1).unwrap()[0].id, id);
+    }
+    assert_ne!(index.search(&[42.0, 0.0, 0.0, 1.0], 1).unwrap()[0].id, 42);
+    let stats = index.stats().unwrap();
+    assert_eq!(stats.layer_node_counts[0], 400);
+    assert_eq!(stats.deleted_count, 1);
+}
+
+#[test]
+fn test_eval_measures_recall_against_exact_search() {
+    use chassis_core::eval::{evaluate_recall, evaluate_recall_sweep, exact_search, ground_truth};
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = VectorIndex::open(temp_file.path(), 8, IndexOptions::default()).unwrap();
+    // Deterministic pseudo-random components in [0, 1)
+    let mut state = 0x2545_f491_u32;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        (state >> 8) as f32 / (1 << 24) as f32
+    };
+    for _ in 0..500 {
+        let vector: Vec<f32> = (0..8).map(|_| next()).collect();
+        index.add(&vector).unwrap();
+    }
+
+    let queries: Vec<Vec<f32>> = (0..20).map(|_| (0..8).map(|_| next()).collect()).collect();
+
+    let exact = exact_search(&index, &queries[0], 5).unwrap();
+    assert_eq!(exact.len(), 5);
+    assert!(exact.windows(2).all(|w| w[0].distance <= w[1].distance));
+
+    let truth = ground_truth(&index, &queries, 10).unwrap();
+    assert_eq!(truth[0][..5], exact.iter().map(|r| r.id).collect::<Vec<_>>()[..]);
+
+    let report = evaluate_recall(&index, &queries, &truth, 10).unwrap();
+    assert_eq!(report.queries, 20);
+    assert_eq!(report.ef, 50);
+    assert!(report.recall > 0.9, "recall {}", report.recall);
+    assert!(report.latency_p50 <= report.latency_p99 && report.latency_p99 <= report.latency_max);
+
+    // An exhaustive beam finds everything
+    let sweep = evaluate_recall_sweep(&index, &queries, &truth, 10, &[10, 500]).unwrap();
+    assert_eq!(sweep.len(), 2);
+    assert_eq!(sweep[1].recall, 1.0);
+
+    // Evaluation does not show up in the workload profile
+    assert_eq!(index.export_workload_profile().queries, 0);
+    assert!(evaluate_recall(&index, &queries, &truth[..1], 10).is_err());
+}
+
+#[test]
+fn test_search_exact_and_threshold_fallback() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = VectorIndex::open(temp_file.path(), 4, IndexOptions::default()).unwrap();
+    for i in 0..150 {
+        index.add(&[i as f32, 0.0, 0.0, 0.0]).unwrap();
+    }
+
+    let exact = index.search_exact(&[10.2, 0.0, 0.0, 0.0], 3).unwrap();
+    assert_eq!(exact.iter().map(|r| r.id).collect::<Vec<_>>(), vec![10, 11, 9]);
+    assert!((exact[0].distance - 0.2).abs() < 1e-5);
+    assert!(index.search_exact(&[0.0; 3], 3).is_err());
+
+    // Below the threshold, search() and search_within() scan
+    let options = IndexOptions { exact_search_threshold: 100, ..Default::default() };
+    let mut small = VectorIndex::open_in_memory(4, options).unwrap();
+    for i in 0..60 {
+        small.add(&[i as f32, 0.0, 0.0, 0.0]).unwrap();
+    }
+    let results = small.search(&[59.9, 0.0, 0.0, 0.0], 2).unwrap();
+    assert_eq!(results.iter().map(|r| r.id).collect::<Vec<_>>(), vec![59, 58]);
+
+    let within = small.search_within(&[30.0, 0.0, 0.0, 0.0], 1.5).unwrap();
+    assert_eq!(within.iter().map(|r| r.id).collect::<Vec<_>>(), vec![30, 29, 31]);
+
+    // Nothing has been flushed yet
+    let durable =
+        SearchOptions { consistency: SearchConsistency::DurableOnly, ..Default::default() };
+    assert!(small.search_with_options(&[1.0, 0.0, 0.0, 0.0], 5, &durable).unwrap().is_empty());
+}
+
+#[test]
+fn test_early_termination_is_opt_in_and_keeps_results_sorted() {
+    use chassis_core::{EarlyTermination, SearchResult};
+
+    let ids = |results: Vec<SearchResult>| results.into_iter().map(|r| r.id).collect::<Vec<_>>();
+    let query = [0.3; 8];
+
+    // ef_search 8 takes the small-ef path, 50 the heap path
+    for ef_search in [8, 50] {
+        let options = IndexOptions { ef_search, ..Default::default() };
+        let mut index = VectorIndex::open_in_memory(8, options).unwrap();
+        for i in 0..400 {
+            let vector: Vec<f32> = (0..8).map(|d| ((i * 31 + d * 17) % 97) as f32 / 97.0).collect();
+            index.add(&vector).unwrap();
+        }
+
+        // Rules that can never fire leave the search unchanged
+        let inert = EarlyTermination { patience: 0, distance_ratio: 1.0 };
+        let options = SearchOptions { early_termination: Some(inert), ..Default::default() };
+        assert_eq!(
+            ids(index.search_with_options(&query, 5, &options).unwrap()),
+            ids(index.search(&query, 5).unwrap())
+        );
+
+        // Aggressive settings still return k sorted results
+        for termination in
+            [EarlyTermination::default(), EarlyTermination { patience: 1, distance_ratio: 0.5 }]
+        {
+            let options =
+                SearchOptions { early_termination: Some(termination), ..Default::default() };
+            let results = index.search_with_options(&query, 5, &options).unwrap();
+            assert_eq!(results.len(), 5, "ef_search={}", ef_search);
+            assert!(results.windows(2).all(|w| w[0].distance <= w[1].distance));
+        }
+    }
+}
+
+#[test]
+fn test_open_existing_reads_shape_from_file() {
+    use chassis_core::{ErrorKind, Preset};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("index.chassis");
+
+    let options = IndexOptions::preset(Preset::TinyFootprint, 48, 1_000);
+    {
+        let mut index = VectorIndex::open(&path, 48, options.clone()).unwrap();
+        for i in 0..50 {
+            index.add(&[i as f32; 48]).unwrap();
+        }
+        index.flush().unwrap();
+    }
+
+    // Defaults would not match the preset's M and layer count
+    let mut index = VectorIndex::open_existing(&path, IndexOptions::default()).unwrap();
+    assert_eq!(index.dimensions(), 48);
+    assert_eq!(index.len(), 50);
+    assert_eq!(index.search(&[20.0; 48], 1).unwrap()[0].id, 20);
+    index.add(&[50.0; 48]).unwrap();
+    index.flush().unwrap();
+    drop(index);
+
+    // The shape it reported reopens the file the usual way
+    let index = VectorIndex::open(&path, 48, options).unwrap();
+    assert_eq!(index.len(), 51);
+    drop(index);
+
+    // Never creates files
+    let missing = dir.path().join("missing.chassis");
+    assert!(VectorIndex::open_existing(&missing, IndexOptions::default()).is_err());
+    assert!(!missing.exists());
+
+    let empty = dir.path().join("empty.chassis");
+    std::fs::File::create(&empty).unwrap();
+    let err = VectorIndex::open_existing(&empty, IndexOptions::default()).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::Corrupted);
+}
+
+#[test]
+fn test_open_uses_stored_graph_parameters() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let created = IndexOptions {
+        max_connections: 8,
+        ef_construction: 64,
+        max_layers: 6,
+        ..Default::default()
+    };
+    {
+        let mut index = VectorIndex::open(temp_file.path(), 16, created).unwrap();
+        for i in 0..100 {
+            index.add(&[i as f32; 16]).unwrap();
+        }
+        index.flush().unwrap();
+    }
+
+    // Reopening with other graph parameters keeps the stored ones
+    let other = IndexOptions {
+        max_connections: 32,
+        ef_construction: 400,
+        max_layers: 16,
+        ef_search: 80,
+        ..Default::default()
+    };
+    let mut index = VectorIndex::open(temp_file.path(), 16, other.clone()).unwrap();
+    assert_eq!(index.options().max_connections, 8);
+    assert_eq!(index.options().ef_construction, 64);
+    assert_eq!(index.options().max_layers, 6);
+    assert_eq!(index.options().ef_search, 80);
+
+    index.add(&[100.0; 16]).unwrap();
+    index.flush().unwrap();
+    assert_eq!(index.search(&[42.0; 16], 1).unwrap()[0].id, 42);
+    assert_eq!(index.search(&[100.0; 16], 1).unwrap()[0].id, 100);
+
+    // A new file takes the caller's parameters
+    let new_file = NamedTempFile::new().unwrap();
+    let index = VectorIndex::open(new_file.path(), 16, other).unwrap();
+    assert_eq!(index.options().max_connections, 32);
+    assert_eq!(index.options().ef_construction, 400);
+}
+
+#[test]
+fn test_search_with_vectors_returns_stored_vectors() {
+    use chassis_core::{ElementType, ErrorKind};
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = VectorIndex::open(temp_file.path(), 8, IndexOptions::default()).unwrap();
+    for i in 0..50 {
+        index.add(&[i as f32; 8]).unwrap();
+    }
+
+    let plain = index.search(&[10.2; 8], 3).unwrap();
+    let with_vectors = index.search_with_vectors(&[10.2; 8], 3).unwrap();
+    assert_eq!(with_vectors.len(), 3);
+    for (r, v) in plain.iter().zip(&with_vectors) {
+        assert_eq!((r.id, r.distance), (v.id, v.distance));
+        assert_eq!(v.vector, vec![v.id as f32; 8]);
+    }
+
+    let slices = index.search_with_vector_slices(&[10.2; 8], 3).unwrap();
+    assert_eq!(slices[0].0.id, 10);
+    assert_eq!(slices[0].1, [10.0; 8]);
+
+    // Half-precision vectors are decoded, but cannot be borrowed as f32
+    let half_file = NamedTempFile::new().unwrap();
+    let options = IndexOptions { element_type: ElementType::F16, ..Default::default() };
+    let mut half = VectorIndex::open(half_file.path(), 8, options).unwrap();
+    half.add(&[0.5; 8]).unwrap();
+    assert_eq!(half.search_with_vectors(&[0.5; 8], 1).unwrap()[0].vector, vec![0.5; 8]);
+    let err = half.search_with_vector_slices(&[0.5; 8], 1).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+}
+
+/// L2 with the first component weighted 100x
+fn weighted_distance(a: &[f32], b: &[f32]) -> f32 {
+    let first = 100.0 * (a[0] - b[0]) * (a[0] - b[0]);
+    let rest: f32 = a[1..].iter().zip(&b[1..]).map(|(x, y)| (x - y) * (x - y)).sum();
+    (first + rest).sqrt()
+}
+
+#[test]
+fn test_custom_distance_builds_and_searches() {
+    use chassis_core::{CustomDistance, Distance, ElementType, ErrorKind};
+
+    let weighted = Distance::Custom(CustomDistance::new("weighted-l2", weighted_distance));
+    let temp_file = NamedTempFile::new().unwrap();
+    let options =
+        IndexOptions { distance: weighted, element_type: ElementType::F16, ..Default::default() };
+    {
+        let mut index = VectorIndex::open(temp_file.path(), 4, options.clone()).unwrap();
+        index.add(&[1.0, 0.0, 0.0, 0.0]).unwrap();
+        index.add(&[0.0, 5.0, 0.0, 0.0]).unwrap();
+        for i in 0..200 {
+            let x = i as f32 * 0.37;
+            index.add(&[x.sin() * 3.0, x.cos() * 9.0, x, -x]).unwrap();
+        }
+        index.flush().unwrap();
+
+        // Under L2 vector 0 is nearest; the weighted first component makes it vector 1
+        let results = index.search(&[0.0; 4], 1).unwrap();
+        assert_eq!(results[0].id, 1);
+        assert_eq!(results[0].distance, 5.0);
+        let query = [2.0, 1.0, 3.0, -3.0];
+        let ids = |results: Vec<chassis_core::SearchResult>| {
+            results.into_iter().map(|r| r.id).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            ids(index.search(&query, 5).unwrap()),
+            ids(index.search_exact(&query, 5).unwrap())
+        );
+    }
+
+    let index = VectorIndex::open(temp_file.path(), 4, options).unwrap();
+    assert_eq!(index.distance(), weighted);
+    assert_eq!(index.search(&[0.0; 4], 1).unwrap()[0].id, 1);
+    drop(index);
+
+    // Any other distance, including the default, is refused
+    let err = VectorIndex::open(temp_file.path(), 4, IndexOptions::default()).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+    assert!(err.to_string().contains("\"weighted-l2\""), "{}", err);
+
+    let renamed = Distance::Custom(CustomDistance::new("weighted-l2-v2", weighted_distance));
+    let options = IndexOptions { distance: renamed, ..Default::default() };
+    let err = VectorIndex::open(temp_file.path(), 4, options).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+
+    // A Euclidean index cannot be reopened with a custom distance either
+    let euclidean_file = NamedTempFile::new().unwrap();
+    {
+        let mut index =
+            VectorIndex::open(euclidean_file.path(), 4, IndexOptions::default()).unwrap();
+        index.add(&[1.0; 4]).unwrap();
+        index.flush().unwrap();
+    }
+    let options = IndexOptions { distance: weighted, ..Default::default() };
+    let err = VectorIndex::open(euclidean_file.path(), 4, options).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+
+    // Names must fit the header
+    let unnamed = Distance::Custom(CustomDistance::new("", weighted_distance));
+    let options = IndexOptions { distance: unnamed, ..Default::default() };
+    let err = VectorIndex::open(NamedTempFile::new().unwrap().path(), 4, options).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+}
+
+#[test]
+fn test_dot_product_finds_maximum_inner_products() {
+    use chassis_core::{CustomDistance, Distance, ErrorKind};
+
+    let dot = |a: &[f32], b: &[f32]| a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+    // Norms vary by 8x, so the largest inner products are not the L2 nearest
+    let vector = |i: usize| -> Vec<f32> {
+        let scale = 1.0 + (i % 8) as f32;
+        (0..16).map(|d| ((i * 16 + d) as f32 * 0.61).sin() * scale).collect()
+    };
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let options =
+        IndexOptions { distance: Distance::DotProduct, ef_search: 200, ..Default::default() };
+    let vectors: Vec<Vec<f32>> = (0..500).map(vector).collect();
+    {
+        let mut index = VectorIndex::open(temp_file.path(), 16, options.clone()).unwrap();
+        for v in &vectors {
+            index.add(v).unwrap();
+        }
+        index.flush().unwrap();
+
+        let mut found = 0;
+        for q in 0..10 {
+            let query: Vec<f32> = (0..16).map(|d| ((q * 7 + d) as f32 * 1.3).cos()).collect();
+            let mut expected: Vec<(u64, f32)> =
+                vectors.iter().enumerate().map(|(id, v)| (id as u64, dot(&query, v))).collect();
+            expected.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let expected: Vec<u64> = expected[..10].iter().map(|&(id, _)| id).collect();
+
+            let exact = index.search_exact(&query, 10).unwrap();
+            assert_eq!(exact.iter().map(|r| r.id).collect::<Vec<_>>(), expected);
+
+            let results = index.search(&query, 10).unwrap();
+            assert_eq!(results[0].id, expected[0], "query {}", q);
+            for r in &results {
+                let inner = dot(&query, &vectors[r.id as usize]);
+                assert!((r.distance + inner).abs() < 1e-3, "{} vs {}", r.distance, -inner);
+            }
+            found += results.iter().filter(|r| expected.contains(&r.id)).count();
+        }
+        assert!(found >= 95, "top-10 recall {}/100", found);
+
+        // A negative radius asks for inner products of at least its magnitude
+        let query = &vectors[7];
+        let threshold = dot(query, query) * 0.5;
+        let within = index.search_within(query, -threshold).unwrap();
+        assert!(!within.is_empty());
+        assert!(within.iter().all(|r| dot(query, &vectors[r.id as usize]) >= threshold - 1e-3));
+        assert!(index.search_within(query, f32::NAN).is_err());
+    }
+
+    let index = VectorIndex::open(temp_file.path(), 16, options).unwrap();
+    assert_eq!(index.distance(), Distance::DotProduct);
+    drop(index);
+
+    let err = VectorIndex::open(temp_file.path(), 16, IndexOptions::default()).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+    assert!(err.to_string().contains("dot product"), "{}", err);
+
+    // Custom distances cannot pass for the built-in one
+    let impostor =
+        CustomDistance::new("chassis:dot-product", |a, b| a.len() as f32 - b.len() as f32);
+    let options = IndexOptions { distance: Distance::Custom(impostor), ..Default::default() };
+    let err = VectorIndex::open(temp_file.path(), 16, options).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+
+    // Euclidean indexes still refuse negative radii
+    let index =
+        VectorIndex::open(NamedTempFile::new().unwrap().path(), 16, IndexOptions::default())
+            .unwrap();
+    assert!(index.search_within(&[0.0; 16], -1.0).is_err());
+}
+
+#[test]
+fn test_manhattan_and_squared_euclidean_distances() {
+    use chassis_core::{Distance, ErrorKind};
+
+    let vector = |i: usize| -> Vec<f32> {
+        (0..16).map(|d| ((i * 16 + d) as f32 * 0.37).sin() * (1.0 + (i % 5) as f32)).collect()
+    };
+    let vectors: Vec<Vec<f32>> = (0..400).map(vector).collect();
+    fn l1(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum()
+    }
+    fn l2sq(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+    }
+    fn l2(a: &[f32], b: &[f32]) -> f32 {
+        l2sq(a, b).sqrt()
+    }
+
+    type Reference = fn(&[f32], &[f32]) -> f32;
+    let cases: [(Distance, Reference, &str); 3] = [
+        (Distance::Manhattan, l1, "Manhattan"),
+        (Distance::SquaredEuclidean, l2sq, "squared Euclidean"),
+        (Distance::Euclidean, l2, "Euclidean"),
+    ];
+    for (distance, reference, name) in cases {
+        let temp_file = NamedTempFile::new().unwrap();
+        let options = IndexOptions { distance, ef_search: 200, ..Default::default() };
+        {
+            let mut index = VectorIndex::open(temp_file.path(), 16, options.clone()).unwrap();
+            for v in &vectors {
+                index.add(v).unwrap();
+            }
+            index.flush().unwrap();
+
+            let query: Vec<f32> = (0..16).map(|d| (d as f32 * 1.3).cos()).collect();
+            let mut expected: Vec<(u64, f32)> = vectors
+                .iter()
+                .enumerate()
+                .map(|(id, v)| (id as u64, reference(&query, v)))
+                .collect();
+            expected.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+            // Graph and exact searches report the distance, not the ranking key
+            for results in
+                [index.search(&query, 10).unwrap(), index.search_exact(&query, 10).unwrap()]
+            {
+                assert_eq!(results[0].id, expected[0].0, "{}", name);
+                for r in &results {
+                    let exact = reference(&query, &vectors[r.id as usize]);
+                    assert!((r.distance - exact).abs() < 1e-3 * exact.max(1.0), "{}", name);
+                }
+            }
+
+            let radius = expected[20].1;
+            let within = index.search_within(&query, radius).unwrap();
+            assert!(within.len() > 10 && within.len() <= 21, "{}: {}", name, within.len());
+            assert!(within.iter().all(|r| r.distance <= radius));
+        }
+
+        let index = VectorIndex::open(temp_file.path(), 16, options).unwrap();
+        assert_eq!(index.distance(), distance);
+        drop(index);
+
+        if distance != Distance::Euclidean {
+            let err = VectorIndex::open(temp_file.path(), 16, IndexOptions::default()).unwrap_err();
+            assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+            assert!(err.to_string().contains(name), "{}", err);
+        }
+    }
+}
+
+#[test]
+fn test_normalize_on_insert() {
+    use chassis_core::ErrorKind;
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let options = IndexOptions { normalize: true, ..Default::default() };
+    {
+        let mut index = VectorIndex::open(temp_file.path(), 2, options).unwrap();
+        index.add(&[1.0, 0.0]).unwrap();
+        index.add(&[0.0, 10.0]).unwrap();
+        index.add(&[-3.0, -3.0]).unwrap();
+
+        // Vectors are stored at unit length, compared by direction only
+        let hit = &index.search_with_vectors(&[0.0, 0.5], 1).unwrap()[0];
+        assert_eq!(hit.id, 1);
+        assert_eq!(hit.distance, 0.0);
+        assert_eq!(hit.vector, [0.0, 1.0]);
+        assert!((index.search(&[100.0, 0.0], 3).unwrap()[1].distance - 1.0).abs() < 1e-6);
+
+        let err = index.add(&[0.0, 0.0]).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+        assert_eq!(
+            ErrorKind::of(&index.search(&[0.0, 0.0], 1).unwrap_err()),
+            ErrorKind::InvalidArgument
+        );
+        index.flush().unwrap();
+    }
+
+    // The file's flag wins over the options once it holds vectors
+    let mut index = VectorIndex::open(temp_file.path(), 2, IndexOptions::default()).unwrap();
+    assert!(index.options().normalize);
+    index.add(&[5.0, 0.0]).unwrap();
+    let hits = index.search_with_vectors(&[2.0, 0.0], 2).unwrap();
+    assert_eq!(hits.iter().map(|hit| hit.distance).collect::<Vec<_>>(), [0.0, 0.0]);
+    assert!(hits.iter().all(|hit| hit.vector == [1.0, 0.0]));
+}
+
+#[test]
+fn test_delete_hides_vectors_from_search() {
+    let temp_file = NamedTempFile::new().unwrap();
+    {
+        let mut index = VectorIndex::open(temp_file.path(), 4, IndexOptions::default()).unwrap();
+        for i in 0..200 {
+            index.add(&[i as f32; 4]).unwrap();
+        }
+
+        assert!(index.delete(50).unwrap());
+        assert!(!index.delete(50).unwrap());
+        assert!(index.delete(500).is_err());
+        assert_eq!(index.deleted_count(), 1);
+        assert_eq!(index.len(), 200);
+
+        let ids: Vec<_> = index.search(&[50.0; 4], 2).unwrap().iter().map(|r| r.id).collect();
+        assert!(!ids.contains(&50));
+        assert!(ids.contains(&49) && ids.contains(&51));
+        assert_ne!(index.search_exact(&[50.0; 4], 1).unwrap()[0].id, 50);
+        assert!(index.search_within(&[50.0; 4], 0.5).unwrap().is_empty());
+        index.flush().unwrap();
+    }
+
+    let index = VectorIndex::open(temp_file.path(), 4, IndexOptions::default()).unwrap();
+    assert!(index.is_deleted(50).unwrap());
+    assert!(!index.is_deleted(51).unwrap());
+    assert_ne!(index.search(&[50.0; 4], 1).unwrap()[0].id, 50);
+}
+
+#[test]
+fn test_scrub_deleted_zeroes_the_vector_in_the_file() {
+    let secret = [1234.5_f32, -6789.25, 42.125, 0.5];
+    let secret_bytes: Vec<u8> = secret.iter().flat_map(|value| value.to_le_bytes()).collect();
+    let in_file = |path: &std::path::Path| {
+        std::fs::read(path).unwrap().windows(secret_bytes.len()).any(|w| w == secret_bytes)
+    };
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let options = IndexOptions { scrub_deleted: true, ..IndexOptions::default() };
+    let mut index = VectorIndex::open(temp_file.path(), 4, options).unwrap();
+    for i in 0..50 {
+        index.add(&[i as f32; 4]).unwrap();
+    }
+    let id = index.add_to_group(9, &secret).unwrap();
+    index.set_tags(id, &[3]).unwrap();
+    index.flush().unwrap();
+    assert!(in_file(temp_file.path()));
+
+    assert!(index.delete(id).unwrap());
+    index.flush().unwrap();
+    assert!(!in_file(temp_file.path()));
+    assert_eq!(index.group_of(id), None);
+    assert!(index.tags_of(id).is_empty());
+    assert_eq!(index.search(&[7.0; 4], 1).unwrap()[0].id, 7);
+}
+
+#[test]
+fn test_id_reuse_reclaims_deleted_slots() {
+    use chassis_core::IdReuse;
+
+    let point = |i: u64| [(i % 10) as f32, (i / 10 % 10) as f32, (i * 7 % 10) as f32, 0.0];
+    let temp_file = NamedTempFile::new().unwrap();
+
+    // Deleted under the default policy: IDs keep growing, but are tracked
+    {
+        let mut index = VectorIndex::open(temp_file.path(), 4, IndexOptions::default()).unwrap();
+        for i in 0..100 {
+            index.add(&point(i)).unwrap();
+        }
+        assert!(index.delete(10).unwrap());
+        assert!(index.delete(20).unwrap());
+        index.add_to_group(7, &point(100)).unwrap();
+        assert_eq!(index.len(), 101);
+        assert!(index.delete(100).unwrap());
+        index.flush().unwrap();
+    }
+
+    let options = IndexOptions { id_reuse: IdReuse::ReuseDeleted, ..IndexOptions::default() };
+    let mut index = VectorIndex::open(temp_file.path(), 4, options.clone()).unwrap();
+    let file_size = index.stats().unwrap().file_bytes;
+
+    // Most recently deleted first, then append once the list runs dry
+    let reused = [[4.5, 4.5, 4.5, 1.0], [2.5, 7.5, 0.5, 1.0], [8.5, 1.5, 6.5, 1.0]];
+    assert_eq!(index.add(&reused[0]).unwrap(), 100);
+    assert_eq!(index.group_of(100), None);
+    assert_eq!(index.add(&reused[1]).unwrap(), 20);
+    assert_eq!(index.add(&reused[2]).unwrap(), 10);
+    assert_eq!(index.deleted_count(), 0);
+    assert_eq!(index.len(), 101);
+    assert_eq!(index.stats().unwrap().file_bytes, file_size);
+    assert_eq!(index.add(&[5.0, 5.0, 5.0, 1.0]).unwrap(), 101);
+
+    assert_eq!(index.get_vector(20).unwrap(), reused[1]);
+    for (vector, id) in reused.iter().zip([100, 20, 10]) {
+        assert_eq!(index.search(vector, 1).unwrap()[0].id, id);
+    }
+    assert_eq!(index.search(&point(21), 1).unwrap()[0].id, 21);
+
+    // The free list survives a reopen
+    assert!(index.delete(50).unwrap());
+    index.flush().unwrap();
+    drop(index);
+
+    let mut index = VectorIndex::open(temp_file.path(), 4, options).unwrap();
+    assert_eq!(index.add(&[0.5, 0.5, 0.5, 1.0]).unwrap(), 50);
+    assert!(!index.is_deleted(50).unwrap());
+    assert_eq!(index.search(&[0.5, 0.5, 0.5, 1.0], 1).unwrap()[0].id, 50);
+    assert_eq!(index.search(&reused[0], 1).unwrap()[0].id, 100);
+}
+
+#[test]
+fn test_flat_threshold_defers_linking_until_linked() {
+    let point = |i: u64| [(i % 10) as f32, (i / 10 % 10) as f32, (i * 7 % 10) as f32, 0.0];
+    let format_version = |path: &std::path::Path| {
+        let bytes = std::fs::read(path).unwrap();
+        u32::from_le_bytes(bytes[8..12].try_into().unwrap())
+    };
+    let temp_file = NamedTempFile::new().unwrap();
+
+    // Below the threshold, vectors are stored unlinked and searches scan
+    {
+        let options = IndexOptions { flat_threshold: 50, ..IndexOptions::default() };
+        let mut index = VectorIndex::open(temp_file.path(), 4, options).unwrap();
+        for i in 0..49 {
+            index.add(&point(i)).unwrap();
+        }
+        assert_eq!(index.unlinked_len(), 49);
+        assert!(index.delete(5).unwrap());
+        assert_eq!(index.search(&point(15), 1).unwrap()[0].id, 15);
+        index.flush().unwrap();
+    }
+    assert_eq!(format_version(temp_file.path()), 7);
+
+    // Pending vectors are recorded in the file; an insert past the threshold
+    // links 16 of them first, and searches keep scanning until all are linked
+    let mut index = VectorIndex::open(temp_file.path(), 4, IndexOptions::default()).unwrap();
+    assert_eq!(index.unlinked_len(), 49);
+    assert_eq!(index.add(&point(49)).unwrap(), 49);
+    assert_eq!(index.unlinked_len(), 34);
+    assert_eq!(index.search(&point(27), 1).unwrap()[0].id, 27);
+
+    // Linking in batches serves searches in between
+    assert_eq!(index.link_graph_batch(20).unwrap(), 14);
+    assert_eq!(index.search(&point(41), 1).unwrap()[0].id, 41);
+    assert_eq!(index.link_graph_batch(10).unwrap(), 4);
+    index.link_graph().unwrap();
+    assert_eq!(index.unlinked_len(), 0);
+    for i in (0..50).filter(|&i| i != 5) {
+        assert_eq!(index.search(&point(i), 1).unwrap()[0].id, i);
+    }
+    assert!(index.search(&point(5), 3).unwrap().iter().all(|r| r.id != 5));
+    index.flush().unwrap();
+    drop(index);
+
+    // Back to the version of a two-tier graph once every vector is linked
+    assert_eq!(format_version(temp_file.path()), 5);
+    let index = VectorIndex::open(temp_file.path(), 4, IndexOptions::default()).unwrap();
+    assert_eq!(index.unlinked_len(), 0);
+    assert_eq!(index.search(&point(33), 1).unwrap()[0].id, 33);
+}
+
+#[test]
+fn test_flat_threshold_links_past_threshold_without_a_writer() {
+    let point = |i: u64| [(i % 13) as f32, (i / 13 % 13) as f32, (i * 7 % 11) as f32, 1.0];
+    let temp_file = NamedTempFile::new().unwrap();
+    let options = IndexOptions { flat_threshold: 100, ..IndexOptions::default() };
+    let mut index = VectorIndex::open(temp_file.path(), 4, options).unwrap();
+
+    for i in 0..100 {
+        index.add(&point(i)).unwrap();
+    }
+    assert_eq!(index.unlinked_len(), 100);
+
+    // Reaching the threshold, a flush links a batch as well
+    index.flush().unwrap();
+    assert_eq!(index.unlinked_len(), 84);
+
+    // Each insert links more than it adds, so the index stops scanning
+    let mut inserts = 0;
+    while index.unlinked_len() > 0 {
+        index.add(&point(100 + inserts)).unwrap();
+        inserts += 1;
+        assert!(inserts <= 6, "{} still pending", index.unlinked_len());
+    }
+    for i in (0..100 + inserts).step_by(7) {
+        assert_eq!(index.search(&point(i), 1).unwrap()[0].id, i);
+    }
+
+    // Later inserts are linked on the spot
+    index.add(&point(1000)).unwrap();
+    assert_eq!(index.unlinked_len(), 0);
+}
+
+#[test]
+fn test_link_graph_reports_progress_and_cancels() {
+    use chassis_core::{ErrorKind, ProgressStage};
+    use std::sync::{Arc, Mutex};
+
+    let point = |i: u64| [(i % 13) as f32, (i / 13 % 13) as f32, (i * 7 % 11) as f32, 1.0];
+    let options = IndexOptions { flat_threshold: 1000, ..IndexOptions::default() };
+    let mut index = VectorIndex::in_memory(4, options).unwrap();
+    for i in 0..300 {
+        index.add(&point(i)).unwrap();
+    }
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&reports);
+    index.set_progress_handler(Some(Arc::new(move |stage, done, total| {
+        recorded.lock().unwrap().push((stage, done, total));
+        done < 128
+    })));
+
+    // Cancelled at the report for 128 of 200, with the vectors before it linked
+    let err = index.link_graph_batch(200).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::Cancelled);
+    assert_eq!(err.to_string(), "Link cancelled after 128 of 200");
+    let link = ProgressStage::Link;
+    assert_eq!(*reports.lock().unwrap(), vec![(link, 0, 200), (link, 64, 200), (link, 128, 200)]);
+    assert_eq!(index.unlinked_len(), 172);
+
+    reports.lock().unwrap().clear();
+    let recorded = Arc::clone(&reports);
+    index.set_progress_handler(Some(Arc::new(move |stage, done, total| {
+        recorded.lock().unwrap().push((stage, done, total));
+        true
+    })));
+    index.link_graph().unwrap();
+    assert_eq!(reports.lock().unwrap().last(), Some(&(link, 172, 172)));
+    assert_eq!(index.unlinked_len(), 0);
+    for i in (0..300).step_by(7) {
+        assert_eq!(index.search(&point(i), 1).unwrap()[0].id, i);
+    }
+}
+
+#[test]
+fn test_optimize_improves_recall_in_place() {
+    let vector = |i: u64| {
+        let mut x = i.wrapping_mul(2_654_435_761) ^ 0x9e37;
+        (0..16)
+            .map(|_| {
+                x = x
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                ((x >> 33) % 1000) as f32 / 1000.0
+            })
+            .collect::<Vec<f32>>()
+    };
+    let recall = |index: &VectorIndex| {
+        let mut hits = 0;
+        for q in 0..100 {
+            let query = vector(100_000 + q);
+            let exact = index.search_exact(&query, 10).unwrap();
+            let found = index.search(&query, 10).unwrap();
+            hits += found.iter().filter(|r| exact.iter().any(|e| e.id == r.id)).count();
+        }
+        hits as f64 / 1000.0
+    };
+
+    // A sparse graph built with little search effort
+    let temp_file = NamedTempFile::new().unwrap();
+    let options = IndexOptions {
+        max_connections: 4,
+        ef_construction: 8,
+        ef_search: 10,
+        ..Default::default()
+    };
+    let mut index = VectorIndex::open(temp_file.path(), 16, options.clone()).unwrap();
+    for i in 0..1000 {
+        index.add(&vector(i)).unwrap();
+    }
+    assert!(index.delete(3).unwrap());
+    let before = recall(&index);
+
+    let first = index.optimize(1).unwrap();
+    assert!(first > 0);
+    assert!(index.optimize(1).unwrap() < first);
+    let after = recall(&index);
+    assert!(after > before, "recall {} -> {}", before, after);
+    assert!(index.search(&vector(3), 5).unwrap().iter().all(|r| r.id != 3));
+    index.flush().unwrap();
+    drop(index);
+
+    let index = VectorIndex::open(temp_file.path(), 16, options).unwrap();
+    assert_eq!(recall(&index), after);
+}
+
+#[test]
+fn test_pinned_nodes_survive_pruning() {
+    // An anchor far off the line of later points; with M = 2, pruning soon
+    // replaces links to it with links along the line
+    let anchor_links = |path: &std::path::Path, pin: bool| {
+        let options = IndexOptions { max_connections: 2, ..IndexOptions::default() };
+        let mut index = VectorIndex::open(path, 2, options).unwrap();
+        index.add(&[0.0, 30.0]).unwrap();
+        assert_eq!(index.set_pinned(0, pin).unwrap(), pin);
+        for i in 1..300 {
+            index.add(&[i as f32 / 10.0, 0.0]).unwrap();
+        }
+        index.flush().unwrap();
+
+        let graph = index.graph();
+        (1..300)
+            .filter(|&id| graph.neighbors_iter_from_mmap(id, 0).unwrap().any(|n| n == 0))
+            .count()
+    };
+
+    let unpinned_file = NamedTempFile::new().unwrap();
+    let pinned_file = NamedTempFile::new().unwrap();
+    let unpinned = anchor_links(unpinned_file.path(), false);
+    let pinned = anchor_links(pinned_file.path(), true);
+    assert!(pinned > unpinned, "{} links to the pinned anchor, {} unpinned", pinned, unpinned);
+
+    // The flag is stored in the node record
+    let mut index = VectorIndex::open(pinned_file.path(), 2, IndexOptions::default()).unwrap();
+    assert!(index.is_pinned(0).unwrap());
+    assert!(!index.is_pinned(1).unwrap());
+    assert!(!index.set_pinned(0, true).unwrap());
+    assert!(index.set_pinned(0, false).unwrap());
+    assert!(!index.is_pinned(0).unwrap());
+    assert!(index.set_pinned(300, true).is_err());
+}
+
+#[test]
+fn test_rescore_matches_exact_distances() {
+    use chassis_core::ElementType;
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = VectorIndex::open(temp_file.path(), 4, IndexOptions::default()).unwrap();
+    for i in 0..50 {
+        index.add(&[i as f32, 0.0, 1.0, 2.0]).unwrap();
+    }
+    index.delete(7).unwrap();
+
+    let query = [10.0, 0.0, 1.0, 2.0];
+    let results = index.rescore(&[3, 7, 12, 10, 40], &query).unwrap();
+    let ids: Vec<_> = results.iter().map(|r| r.id).collect();
+    assert_eq!(ids, vec![10, 12, 3, 40]);
+    for result in &results {
+        let exact = index.search_exact(&query, 50).unwrap();
+        let expected = exact.iter().find(|r| r.id == result.id).unwrap();
+        assert_eq!(result.distance, expected.distance);
+    }
+    assert!(index.rescore(&[50], &query).is_err());
+    assert!(index.rescore(&[1], &[0.0; 3]).is_err());
+
+    // The raw matrix holds deleted rows too
+    let matrix = index.vector_matrix_slice(5..9).unwrap();
+    assert_eq!(matrix.len(), 16);
+    assert_eq!(&matrix[8..12], &[7.0, 0.0, 1.0, 2.0]);
+
+    let options = IndexOptions { element_type: ElementType::F16, ..IndexOptions::default() };
+    let half = VectorIndex::open_in_memory(4, options).unwrap();
+    assert!(half.vector_matrix_slice(0..0).is_err());
+}
+
+#[test]
+fn test_keys_map_to_ids_and_persist() {
+    use chassis_core::ErrorKind;
+
+    let temp_file = NamedTempFile::new().unwrap();
+    {
+        let mut index = VectorIndex::open(temp_file.path(), 2, IndexOptions::default()).unwrap();
+        let a = index.add_with_key("doc:a", &[0.0, 0.0]).unwrap();
+        index.add(&[1.0, 0.0]).unwrap();
+        let c = index.add_with_key("doc:c", &[2.0, 0.0]).unwrap();
+
+        assert_eq!(index.id_for_key("doc:a"), Some(a));
+        assert_eq!(index.key_for_id(c), Some("doc:c"));
+        assert_eq!(index.key_for_id(1), None);
+
+        let err = index.add_with_key("doc:a", &[3.0, 0.0]).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+        let err = index.add_with_key("", &[3.0, 0.0]).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+        assert_eq!(index.len(), 3);
+
+        let results = index.search_with_keys(&[0.9, 0.0], 2).unwrap();
+        assert_eq!(results[0].key, None);
+        assert_eq!(results[1].key.as_deref(), Some("doc:a"));
+        index.flush().unwrap();
+    }
+
+    let mut index = VectorIndex::open(temp_file.path(), 2, IndexOptions::default()).unwrap();
+    assert_eq!(index.id_for_key("doc:c"), Some(2));
+
+    // Deleting by key frees the key for a new vector
+    assert_eq!(index.delete_by_key("doc:a").unwrap(), Some(0));
+    assert_eq!(index.delete_by_key("doc:a").unwrap(), None);
+    assert_eq!(index.id_for_key("doc:a"), None);
+    let replacement = index.add_with_key("doc:a", &[0.0, 0.5]).unwrap();
+    assert_eq!(index.search_with_keys(&[0.0, 0.0], 1).unwrap()[0].id, replacement);
+    index.flush().unwrap();
+    drop(index);
+
+    let index = VectorIndex::open(temp_file.path(), 2, IndexOptions::default()).unwrap();
+    assert_eq!(index.id_for_key("doc:a"), Some(replacement));
+    assert_eq!(index.key_for_id(0), None);
+}
+
+#[test]
+fn test_unflushed_keys_roll_back_with_their_vectors() {
+    let temp_file = NamedTempFile::new().unwrap();
+    {
+        let mut index = VectorIndex::open(temp_file.path(), 2, IndexOptions::default()).unwrap();
+        index.add_with_key("durable", &[0.0, 0.0]).unwrap();
+        index.flush().unwrap();
+        index.add_with_key("lost", &[1.0, 0.0]).unwrap();
+    }
+
+    let mut index = VectorIndex::open(temp_file.path(), 2, IndexOptions::default()).unwrap();
+    assert_eq!(index.len(), 1);
+    assert_eq!(index.id_for_key("durable"), Some(0));
+    assert_eq!(index.id_for_key("lost"), None);
+    assert_eq!(index.add_with_key("lost", &[1.0, 0.0]).unwrap(), 1);
+}
+
+#[test]
+fn test_search_grouped_collapses_chunks() {
+    use chassis_core::ErrorKind;
+
+    let temp_file = NamedTempFile::new().unwrap();
+    {
+        let mut index = VectorIndex::open(temp_file.path(), 2, IndexOptions::default()).unwrap();
+        // Document 7 has four chunks near the query, crowding out everything else
+        for x in [0.0, 0.1, 0.2, 0.3] {
+            index.add_to_group(7, &[x, 0.0]).unwrap();
+        }
+        let loose = index.add(&[1.0, 0.0]).unwrap();
+        index.add_to_group(3, &[2.0, 0.0]).unwrap();
+        index.add_to_group(3, &[2.5, 0.0]).unwrap();
+
+        let plain = index.search(&[0.0, 0.0], 3).unwrap();
+        assert!(plain.iter().all(|r| index.group_of(r.id) == Some(7)));
+
+        let grouped = index.search_grouped(&[0.0, 0.0], 3, 2).unwrap();
+        let groups: Vec<_> = grouped.iter().map(|g| g.group).collect();
+        assert_eq!(groups, vec![Some(7), None, Some(3)]);
+        let ids: Vec<_> = grouped[0].hits.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![0, 1]);
+        assert_eq!(grouped[1].hits[0].id, loose);
+        assert_eq!(grouped[2].hits[0].id, 5);
+
+        // Fewer groups than asked for when the index runs out
+        assert_eq!(index.search_grouped(&[0.0, 0.0], 10, 1).unwrap().len(), 3);
+
+        let err = index.search_grouped(&[0.0, 0.0], 3, 0).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+        let err = index.add_to_group(u64::MAX, &[0.0, 0.0]).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+
+        index.set_group(loose, Some(3)).unwrap();
+        index.set_group(6, None).unwrap();
+        index.flush().unwrap();
+    }
+
+    let index = VectorIndex::open(temp_file.path(), 2, IndexOptions::default()).unwrap();
+    assert_eq!(index.group_of(4), Some(3));
+    assert_eq!(index.group_of(6), None);
+    let grouped = index.search_grouped(&[0.0, 0.0], 2, 1).unwrap();
+    assert_eq!(grouped[1].group, Some(3));
+    assert_eq!(grouped[1].hits[0].id, 4);
+}
+
+#[test]
+fn test_search_diverse_spreads_results() {
+    use chassis_core::ErrorKind;
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = VectorIndex::open(temp_file.path(), 2, IndexOptions::default()).unwrap();
+    // Five near-duplicates at the query, and two vectors further away
+    for x in [0.0, 0.01, 0.02, 0.03, 0.04] {
+        index.add(&[x, 0.0]).unwrap();
+    }
+    let up = index.add(&[0.0, 0.6]).unwrap();
+    let left = index.add(&[-0.5, 0.0]).unwrap();
+
+    let ids = |results: Vec<chassis_core::SearchResult>| -> Vec<u64> {
+        results.iter().map(|r| r.id).collect()
+    };
+    let query = [0.0, 0.0];
+    assert_eq!(ids(index.search_diverse(&query, 3, 1.0).unwrap()), vec![0, 1, 2]);
+    assert_eq!(ids(index.search_diverse(&query, 3, 0.3).unwrap()), vec![0, up, left]);
+
+    // Distances are to the query, as from search()
+    let diverse = index.search_diverse(&query, 2, 0.3).unwrap();
+    let plain = index.search(&query, 7).unwrap();
+    assert_eq!(diverse[1].distance, plain.iter().find(|r| r.id == up).unwrap().distance);
+
+    assert_eq!(index.search_diverse(&query, 20, 0.5).unwrap().len(), 7);
+    assert!(index.search_diverse(&query, 0, 0.5).unwrap().is_empty());
+    for lambda in [-0.1, 1.5, f32::NAN] {
+        let err = index.search_diverse(&query, 3, lambda).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+    }
+}
+
+#[test]
+fn test_stats_describe_graph_and_zones() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = VectorIndex::open(temp_file.path(), 4, IndexOptions::default()).unwrap();
+
+    let stats = index.stats().unwrap();
+    assert_eq!(stats.node_count, 0);
+    assert!(stats.layer_node_counts.is_empty());
+    assert_eq!(stats.avg_out_degree, 0.0);
+    assert_eq!(stats.vector_bytes, 0);
+
+    for i in 0..300 {
+        index.add(&[i as f32, (i % 7) as f32, 1.0, 0.5]).unwrap();
+    }
+    index.delete(3).unwrap();
+    index.delete(4).unwrap();
+
+    let stats = index.stats().unwrap();
+    assert_eq!(stats.node_count, 300);
+    assert_eq!(stats.deleted_count, 2);
+    assert!((stats.deleted_fraction() - 2.0 / 300.0).abs() < 1e-9);
+    assert_eq!(stats.layer_node_counts.len(), stats.max_layer + 1);
+    assert_eq!(stats.layer_node_counts[0], 300);
+    assert!(stats.layer_node_counts.windows(2).all(|pair| pair[0] >= pair[1]));
+    assert!(stats.avg_out_degree > 1.0);
+    assert!(stats.avg_out_degree <= 2.0 * IndexOptions::default().max_connections as f64);
+    assert_eq!(stats.vector_bytes, 300 * 4 * 4);
+    assert!(stats.graph_bytes > 0);
+    let zones = chassis_core::HEADER_SIZE as u64 + stats.vector_bytes + stats.graph_bytes;
+    assert!(stats.file_bytes >= zones);
+}
+
+#[test]
+fn test_reserve_grows_file_once() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = VectorIndex::open(temp_file.path(), 8, IndexOptions::default()).unwrap();
+    index.add(&[0.0; 8]).unwrap();
+
+    index.reserve(2000).unwrap();
+    let reserved_len = std::fs::metadata(temp_file.path()).unwrap().len();
+
+    for i in 1..2001 {
+        index.add(&[i as f32; 8]).unwrap();
+    }
+    index.flush().unwrap();
+    assert_eq!(std::fs::metadata(temp_file.path()).unwrap().len(), reserved_len);
+
+    // Reserving what is already there changes nothing
+    index.reserve(0).unwrap();
+    assert_eq!(std::fs::metadata(temp_file.path()).unwrap().len(), reserved_len);
+    assert_eq!(index.search(&[1234.0; 8], 1).unwrap()[0].id, 1234);
+
+    drop(index);
+    let index = VectorIndex::open(temp_file.path(), 8, IndexOptions::default()).unwrap();
+    assert_eq!(index.len(), 2001);
+    assert_eq!(index.search(&[17.0; 8], 1).unwrap()[0].id, 17);
+}
+
+#[test]
+fn test_search_batch_matches_search() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = VectorIndex::open(temp_file.path(), 4, IndexOptions::default()).unwrap();
+    for i in 0..500 {
+        let x = i as f32;
+        index.add(&[x.sin(), x.cos(), (x * 0.3).sin(), (x * 0.7).cos()]).unwrap();
+    }
+    index.delete(10).unwrap();
+
+    let queries: Vec<Vec<f32>> =
+        (0..20).map(|i| vec![i as f32 * 0.1, 0.5, -0.2, i as f32 * -0.05]).collect();
+    let slices: Vec<&[f32]> = queries.iter().map(Vec::as_slice).collect();
+
+    let batch = index.search_batch(&slices, 5).unwrap();
+    assert_eq!(batch.len(), queries.len());
+    for (query, results) in queries.iter().zip(&batch) {
+        let single = index.search(query, 5).unwrap();
+        let ids: Vec<_> = results.iter().map(|r| r.id).collect();
+        let expected: Vec<_> = single.iter().map(|r| r.id).collect();
+        assert_eq!(ids, expected);
+    }
+
+    assert!(index.search_batch(&[], 5).unwrap().is_empty());
+    assert!(index.search_batch(&[&[0.0; 4], &[0.0; 3]], 5).is_err());
+}
+
+#[test]
+fn test_search_into_matches_search() {
+    use chassis_core::ErrorKind;
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = VectorIndex::open(temp_file.path(), 4, IndexOptions::default()).unwrap();
+    let (mut ids, mut distances) = ([0_u64; 8], [0_f32; 8]);
+    assert_eq!(index.search_into(&[0.0; 4], 5, &mut ids, &mut distances).unwrap(), 0);
+
+    for i in 0..300 {
+        let x = i as f32;
+        index.add(&[x.sin(), x.cos(), (x * 0.3).sin(), (x * 0.7).cos()]).unwrap();
+    }
+    index.delete(3).unwrap();
+
+    for i in 0..10 {
+        let query = [i as f32 * 0.1, 0.5, -0.2, i as f32 * -0.05];
+        let expected = index.search(&query, 5).unwrap();
+        let count = index.search_into(&query, 5, &mut ids, &mut distances).unwrap();
+        assert_eq!(count, 5);
+        for (i, result) in expected.iter().enumerate() {
+            assert_eq!((ids[i], distances[i]), (result.id, result.distance));
+        }
+    }
+    assert_eq!(index.metrics().searches, 21);
+
+    // Buffers may be longer than k, but not shorter
+    let err = index.search_into(&[0.0; 4], 9, &mut ids, &mut distances).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+    assert!(index.search_into(&[0.0; 3], 5, &mut ids, &mut distances).is_err());
+
+    // Exact scans fill the buffers the same way
+    index.flush().unwrap();
+    drop(index);
+    let options = IndexOptions { exact_search_threshold: 1000, ..Default::default() };
+    let index = VectorIndex::open(temp_file.path(), 4, options).unwrap();
+    let expected = index.search(&[0.3; 4], 8).unwrap();
+    assert_eq!(index.search_into(&[0.3; 4], 8, &mut ids, &mut distances).unwrap(), 8);
+    assert_eq!(ids.to_vec(), expected.iter().map(|r| r.id).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_graph_file_layout() {
+    use chassis_core::graph_file_path;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.chassis");
+    let graph_path = graph_file_path(&path);
+    let options = IndexOptions { graph_file: true, ..Default::default() };
+    {
+        let mut index = VectorIndex::open(&path, 8, options.clone()).unwrap();
+        for i in 0..300 {
+            index.add(&[i as f32; 8]).unwrap();
+        }
+        index.flush().unwrap();
+    }
+
+    // No sparse gap: the index file holds the header, vectors and slack only
+    assert!(graph_path.exists());
+    assert!(std::fs::metadata(&path).unwrap().len() < 1024 * 1024);
+    assert!(std::fs::metadata(&graph_path).unwrap().len() > 300 * 64);
+
+    // The layout is recorded in the file, whatever the options say
+    let mut index = VectorIndex::open(&path, 8, IndexOptions::default()).unwrap();
+    assert_eq!(index.len(), 300);
+    assert_eq!(index.search(&[42.0; 8], 1).unwrap()[0].id, 42);
+    index.add(&[1000.0; 8]).unwrap();
+    index.flush().unwrap();
+
+    let snapshot = dir.path().join("copy.chassis");
+    index.snapshot_to(&snapshot).unwrap();
+    drop(index);
+
+    let copy = VectorIndex::open_shared(&snapshot, 8, IndexOptions::default()).unwrap();
+    assert_eq!(copy.len(), 301);
+    assert_eq!(copy.search(&[999.0; 8], 1).unwrap()[0].id, 300);
+    drop(copy);
+
+    // An existing single-file index keeps its layout
+    let single = dir.path().join("single.chassis");
+    {
+        let mut index = VectorIndex::open(&single, 8, IndexOptions::default()).unwrap();
+        index.add(&[1.0; 8]).unwrap();
+        index.flush().unwrap();
+    }
+    let index = VectorIndex::open(&single, 8, options).unwrap();
+    assert_eq!(index.len(), 1);
+    assert!(!graph_file_path(&single).exists());
+    drop(index);
+
+    // The graph cannot be left behind
+    std::fs::remove_file(&graph_path).unwrap();
+    assert!(VectorIndex::open(&path, 8, IndexOptions::default()).is_err());
+    assert!(VectorIndex::open_shared(&path, 8, IndexOptions::default()).is_err());
+}
+
+#[test]
+fn test_page_size_aligns_zones() {
+    use chassis_core::{ErrorKind, MAX_PAGE_SIZE};
+
+    let dir = tempfile::tempdir().unwrap();
+    for page_size in [16 * 1024, MAX_PAGE_SIZE] {
+        let path = dir.path().join(format!("pages-{}.chassis", page_size));
+        let options = IndexOptions { page_size, ..Default::default() };
+        {
+            let mut index = VectorIndex::open(&path, 8, options).unwrap();
+            assert_eq!(index.options().page_size, page_size);
+            for i in 0..300 {
+                index.add(&[i as f32; 8]).unwrap();
+            }
+            index.set_tags(3, &[1]).unwrap();
+            index.flush().unwrap();
+        }
+
+        // Vectors start one page in, the file grows in whole pages, and
+        // pre-page-size libraries are refused by the format version
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(bytes.len() % page_size, 0);
+        assert_eq!(u32::from_le_bytes(bytes[8..12].try_into().unwrap()), 6);
+        assert_eq!(&bytes[page_size + 32..page_size + 36], 1.0_f32.to_ne_bytes());
+
+        // The page size is recorded in the file, whatever the options say
+        let mut index = VectorIndex::open(&path, 8, IndexOptions::default()).unwrap();
+        assert_eq!(index.options().page_size, page_size);
+        assert_eq!(index.search(&[42.0; 8], 1).unwrap()[0].id, 42);
+        assert_eq!(index.tags_of(3), vec![1]);
+        index.add(&[1000.0; 8]).unwrap();
+        index.flush().unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len() % page_size as u64, 0);
+    }
+
+    for page_size in [0, 12 * 1024, 2 * MAX_PAGE_SIZE] {
+        let options = IndexOptions { page_size, ..Default::default() };
+        let err = VectorIndex::open(dir.path().join("bad.chassis"), 8, options).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+    }
+}
+
+#[test]
+fn test_merge_combines_shards() {
+    use chassis_core::ErrorKind;
+
+    let dir = tempfile::tempdir().unwrap();
+    let dims = 8;
+    let vector =
+        |i: usize| -> Vec<f32> { (0..dims).map(|j| ((i * dims + j) as f32).sin()).collect() };
+    let shard = |day: usize| dir.path().join(format!("day-{}.chassis", day));
+
+    for day in 0..3 {
+        let mut index =
+            VectorIndex::open(shard(day), dims as u32, IndexOptions::default()).unwrap();
+        for i in 0..40 {
+            let n = day * 40 + i;
+            if i == 0 {
+                index.add_with_key(&format!("doc-{}", n), &vector(n)).unwrap();
+            } else {
+                index.add_to_group(day as u64, &vector(n)).unwrap();
+            }
+        }
+        index.delete(5).unwrap();
+        index.flush().unwrap();
+    }
+    let before = std::fs::read(shard(1)).unwrap();
+
+    let sources = [shard(0), shard(1), shard(2)];
+    let sources: Vec<&std::path::Path> = sources.iter().map(|p| p.as_path()).collect();
+    let output = dir.path().join("merged.chassis");
+    let merged = VectorIndex::merge(&sources, &output, IndexOptions::default()).unwrap();
+
+    // Deleted vectors are dropped and IDs are reassigned in source order
+    assert_eq!(merged.index.len(), 117);
+    assert_eq!(merged.origins.len(), 117);
+    assert_eq!(merged.origins[39], (1, 0));
+    assert_eq!(merged.origins[44], (1, 6));
+    for (new_id, &(source, id)) in merged.origins.iter().enumerate() {
+        let results = merged.index.search(&vector(source * 40 + id as usize), 1).unwrap();
+        assert_eq!(results[0].id, new_id as u64);
+    }
+    assert_eq!(merged.index.id_for_key("doc-80"), Some(78));
+    assert_eq!(merged.index.group_of(80), Some(2));
+    assert_eq!(std::fs::read(shard(1)).unwrap(), before);
+    drop(merged);
+
+    let reopened = VectorIndex::open(&output, dims as u32, IndexOptions::default()).unwrap();
+    assert_eq!(reopened.len(), 117);
+    assert_eq!(reopened.id_for_key("doc-40"), Some(39));
+    drop(reopened);
+
+    // The output must be new
+    let err = VectorIndex::merge(&sources[..1], &output, IndexOptions::default()).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+
+    // Keys must be unique across sources
+    let err = VectorIndex::merge(
+        &[sources[0], sources[0]],
+        dir.path().join("twice.chassis"),
+        IndexOptions::default(),
+    )
+    .unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+
+    // Shapes must match
+    let other = dir.path().join("other.chassis");
+    {
+        let mut index = VectorIndex::open(&other, 4, IndexOptions::default()).unwrap();
+        index.add(&[1.0; 4]).unwrap();
+        index.flush().unwrap();
+    }
+    let err = VectorIndex::merge(
+        &[sources[0], other.as_path()],
+        dir.path().join("mixed.chassis"),
+        IndexOptions::default(),
+    )
+    .unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::DimensionMismatch);
+
+    let err = VectorIndex::merge(&[], dir.path().join("none.chassis"), IndexOptions::default())
+        .unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+}
+
+#[test]
+fn test_search_with_tags_filters_during_traversal() {
+    use chassis_core::{ErrorKind, MAX_TAG};
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let point = |i: usize| [i as f32, (i % 7) as f32];
+    {
+        let mut index = VectorIndex::open(temp_file.path(), 2, IndexOptions::default()).unwrap();
+        for i in 0..500 {
+            // Tag 0/1/2 by residue; tag 900 marks a sparse "folder" far from the query
+            let mut tags = vec![(i % 3) as u32];
+            if i % 100 == 99 {
+                tags.push(900);
+            }
+            index.add_with_tags(&point(i), &tags).unwrap();
+        }
+
+        // The nearest vectors overall are not in the folder, yet all five are found
+        let hits = index.search_with_tags(&point(0), 5, &[900], &[]).unwrap();
+        let ids: Vec<_> = hits.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![99, 199, 299, 399, 499]);
+
+        let hits = index.search_with_tags(&point(250), 10, &[1, 2], &[900]).unwrap();
+        assert_eq!(hits.len(), 10);
+        assert!(hits.iter().all(|r| r.id % 3 != 0 && r.id % 100 != 99));
+        assert_eq!(hits[0].id, 250);
+
+        assert_eq!(index.search_with_tags(&point(0), 3, &[], &[]).unwrap()[0].id, 0);
+        assert!(index.search_with_tags(&point(0), 3, &[5], &[]).unwrap().is_empty());
+
+        let err = index.search_with_tags(&point(0), 3, &[MAX_TAG + 1], &[]).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+        let err = index.set_tags(500, &[1]).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::OutOfBounds);
+
+        index.set_tags(0, &[MAX_TAG, 4]).unwrap();
+        index.set_tags(1, &[]).unwrap();
+        index.flush().unwrap();
+    }
+
+    let index = VectorIndex::open(temp_file.path(), 2, IndexOptions::default()).unwrap();
+    assert_eq!(index.tags_of(0), vec![4, MAX_TAG]);
+    assert_eq!(index.tags_of(1), Vec::<u32>::new());
+    assert_eq!(index.tags_of(99), vec![0, 900]);
+    assert_eq!(index.search_with_tags(&point(300), 1, &[4], &[]).unwrap()[0].id, 0);
+}
+
+#[test]
+fn test_purge_expired_deletes_past_deadlines() {
+    use chassis_core::ErrorKind;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let at = |secs: u64| UNIX_EPOCH + Duration::from_secs(secs);
+    {
+        let mut index = VectorIndex::open(temp_file.path(), 2, IndexOptions::default()).unwrap();
+        for i in 0..10 {
+            // Vector i expires at 1000 + 100 * i; the last two never do
+            let vector = [i as f32, 0.0];
+            if i < 8 {
+                index.add_with_expiry(&vector, at(1000 + 100 * i)).unwrap();
+            } else {
+                index.add(&vector).unwrap();
+            }
+        }
+        assert_eq!(index.expiry_of(2), Some(at(1200)));
+        assert_eq!(index.expiry_of(9), None);
+
+        assert_eq!(index.purge_expired(at(999)).unwrap(), 0);
+        assert_eq!(index.purge_expired(at(1250)).unwrap(), 3);
+        assert_eq!(index.deleted_count(), 3);
+        let hits = index.search(&[0.0, 0.0], 3).unwrap();
+        assert_eq!(hits.iter().map(|r| r.id).collect::<Vec<_>>(), vec![3, 4, 5]);
+
+        // An update carries the deadline over; clearing one exempts the vector
+        let new_id = index.update(3, &[3.0, 1.0]).unwrap();
+        assert_eq!(index.expiry_of(new_id), Some(at(1300)));
+        index.set_expiry(4, None).unwrap();
+        index.set_expiry(9, Some(at(1500))).unwrap();
+        let err = index.set_expiry(100, None).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::OutOfBounds);
+        index.flush().unwrap();
+    }
+
+    let mut index = VectorIndex::open(temp_file.path(), 2, IndexOptions::default()).unwrap();
+    assert_eq!(index.expiry_of(5), Some(at(1500)));
+    assert_eq!(index.expiry_of(4), None);
+    // 3 was already deleted by the update; its replacement, 5 and 9 expire now
+    assert_eq!(index.purge_expired(at(1500)).unwrap(), 3);
+    assert!(!index.is_deleted(4).unwrap());
+    assert!(index.is_deleted(9).unwrap());
+    assert_eq!(index.purge_expired(at(1500)).unwrap(), 0);
+}
+
+#[test]
+fn test_instrumentation_reports_operations() {
+    use chassis_core::{AddEvent, FlushEvent, Instrumentation, SearchEvent};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Recorder {
+        starts: Mutex<Vec<usize>>,
+        searches: Mutex<Vec<SearchEvent>>,
+        adds: Mutex<Vec<u64>>,
+        flushes: Mutex<Vec<u64>>,
+    }
+
+    impl Instrumentation for Recorder {
+        fn on_search_start(&self, k: usize) {
+            self.starts.lock().unwrap().push(k);
+        }
+
+        fn on_search_end(&self, event: &SearchEvent) {
+            self.searches.lock().unwrap().push(event.clone());
+        }
+
+        fn on_add(&self, event: &AddEvent) {
+            self.adds.lock().unwrap().push(event.id);
+        }
+
+        fn on_flush(&self, event: &FlushEvent) {
+            assert!(!event.background);
+            self.flushes.lock().unwrap().push(event.vectors);
+        }
+    }
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = VectorIndex::open(temp_file.path(), 32, IndexOptions::default()).unwrap();
+    let vector = |i: usize| (0..32).map(|j| ((i * 32 + j) as f32 * 0.7).sin()).collect::<Vec<_>>();
+    index.add(&vector(0)).unwrap();
+
+    let recorder = Arc::new(Recorder::default());
+    index.set_instrumentation(Some(recorder.clone()));
+    for i in 1..299 {
+        index.add(&vector(i)).unwrap();
+    }
+    index.add_with_key("last", &vector(299)).unwrap();
+    index.flush().unwrap();
+
+    let results = index.search(&vector(7), 5).unwrap();
+    index.search_exact(&vector(7), 3).unwrap();
+    assert_eq!(results[0].id, 7);
+
+    assert_eq!(*recorder.adds.lock().unwrap(), (1..300).collect::<Vec<u64>>());
+    assert_eq!(*recorder.flushes.lock().unwrap(), vec![300]);
+    assert_eq!(*recorder.starts.lock().unwrap(), vec![5, 3]);
+
+    let searches = recorder.searches.lock().unwrap();
+    let graph = &searches[0];
+    assert_eq!((graph.k, graph.results, graph.exact), (5, 5, false));
+    assert!(graph.hops > 0 && graph.hops < graph.distance_computations);
+    assert!(graph.distance_computations < 300);
+    assert!(graph.pages_touched > 0);
+    assert!(graph.duration.is_some());
+
+    // A scan reads every vector: 300 * 128 bytes spread over 10 pages
+    let exact = &searches[1];
+    assert_eq!((exact.results, exact.exact, exact.hops), (3, true, 0));
+    assert_eq!(exact.distance_computations, 300);
+    assert_eq!(exact.pages_touched, 10);
+    drop(searches);
+
+    index.set_instrumentation(None);
+    index.search(&vector(1), 5).unwrap();
+    assert_eq!(recorder.searches.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn test_embedder_adds_and_searches_text() {
+    use chassis_core::Embedder;
+    use std::sync::Arc;
+
+    // Letter counts: texts sharing letters end up close together
+    let letters = |text: &str| {
+        let mut vector = vec![0.0f32; 26];
+        for c in text.bytes().filter(u8::is_ascii_lowercase) {
+            vector[(c - b'a') as usize] += 1.0;
+        }
+        vector
+    };
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = VectorIndex::open(temp_file.path(), 26, IndexOptions::default()).unwrap();
+    let err = index.add_text("no embedder yet").unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+
+    index.set_embedder(Some(Arc::new(letters)));
+    let texts = ["apple pie", "banana bread", "cherry tart", "plum jam"];
+    for (i, text) in texts.iter().enumerate() {
+        assert_eq!(index.add_text(text).unwrap(), i as u64);
+    }
+    assert_eq!(index.get_vector(2).unwrap(), letters("cherry tart"));
+    assert_eq!(index.search_text("banana bread", 1).unwrap()[0].id, 1);
+    assert_eq!(index.search_text("plum jams", 2).unwrap()[0].id, 3);
+
+    // Embedder errors come back unchanged, and nothing is added
+    struct Offline;
+    impl Embedder for Offline {
+        fn embed(&self, _text: &str) -> anyhow::Result<Vec<f32>> {
+            anyhow::bail!("model not loaded")
+        }
+    }
+    index.set_embedder(Some(Arc::new(Offline)));
+    let err = index.add_text("grape").unwrap_err();
+    assert_eq!(err.to_string(), "model not loaded");
+    assert_eq!(index.len(), 4);
+
+    // Wrong dimensions are rejected like any other vector
+    index.set_embedder(Some(Arc::new(|_: &str| vec![1.0; 3])));
+    let err = index.search_text("grape", 1).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::DimensionMismatch);
+
+    // The handles share the embedder
+    index.set_embedder(Some(Arc::new(letters)));
+    let (mut writer, reader) = index.into_handles();
+    assert_eq!(writer.add_text("grape jelly").unwrap(), 4);
+    assert_eq!(reader.search_text("grape jelly", 1).unwrap()[0].id, 4);
+}
+
+#[test]
+fn test_update_replaces_vector_and_carries_metadata() {
+    use chassis_core::ErrorKind;
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = VectorIndex::open(temp_file.path(), 2, IndexOptions::default()).unwrap();
+    let id = index.add_with_key("doc", &[1.0, 1.0]).unwrap();
+    index.set_group(id, Some(7)).unwrap();
+    index.set_tags(id, &[3]).unwrap();
+    index.add(&[5.0, 5.0]).unwrap();
+
+    let new_id = index.update(id, &[9.0, 9.0]).unwrap();
+    assert_eq!(new_id, 2);
+    assert!(index.is_deleted(id).unwrap());
+    assert_eq!(index.get_vector(new_id).unwrap(), vec![9.0, 9.0]);
+    assert_eq!(index.id_for_key("doc"), Some(new_id));
+    assert_eq!(index.group_of(new_id), Some(7));
+    assert_eq!(index.tags_of(new_id), vec![3]);
+    assert_eq!(index.search(&[1.0, 1.0], 1).unwrap()[0].id, 1);
+
+    let err = index.get_vector(id).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::OutOfBounds);
+    let err = index.update(id, &[0.0, 0.0]).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::OutOfBounds);
+    let err = index.update(new_id, &[0.0]).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::DimensionMismatch);
+}
+
+#[test]
+fn test_search_with_budget_caps_work() {
+    use chassis_core::{Instrumentation, SearchBudget, SearchEvent};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Computations(Mutex<Vec<u64>>);
+
+    impl Instrumentation for Computations {
+        fn on_search_end(&self, event: &SearchEvent) {
+            self.0.lock().unwrap().push(event.distance_computations);
+        }
+    }
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = VectorIndex::open(temp_file.path(), 16, IndexOptions::default()).unwrap();
+    let vector = |i: usize| (0..16).map(|j| ((i * 16 + j) as f32 * 0.37).sin()).collect::<Vec<_>>();
+    for i in 0..1000 {
+        index.add(&vector(i)).unwrap();
+    }
+    let computations = Arc::new(Computations::default());
+    index.set_instrumentation(Some(computations.clone()));
+
+    let unbounded = index.search(&vector(5), 10).unwrap();
+    let generous = index.search_with_budget(&vector(5), 10, SearchBudget::Micros(60_000_000));
+    assert_eq!(generous.unwrap(), unbounded);
+
+    let budgeted =
+        index.search_with_budget(&vector(5), 10, SearchBudget::DistanceComputations(40)).unwrap();
+    assert!(!budgeted.is_empty() && budgeted.len() <= 10);
+    assert!(budgeted.windows(2).all(|pair| pair[0].distance <= pair[1].distance));
+
+    // Out of time before the descent: only the entry point is evaluated
+    let instant = index.search_with_budget(&vector(5), 10, SearchBudget::Micros(0)).unwrap();
+    assert_eq!(instant.len(), 1);
+
+    let spent = computations.0.lock().unwrap().clone();
+    assert!(spent[0] > 40);
+    assert_eq!(spent[2], 40);
+    assert!(spent[3] <= index.stats().unwrap().max_layer as u64 + 1);
+    index.flush().unwrap();
+    drop(index);
+
+    // Exact scans stop at the budget too
+    let options = IndexOptions { exact_search_threshold: 10_000, ..IndexOptions::default() };
+    let index = VectorIndex::open(temp_file.path(), 16, options).unwrap();
+    let results =
+        index.search_with_budget(&vector(900), 3, SearchBudget::DistanceComputations(100)).unwrap();
+    assert!(results.iter().all(|r| r.id < 100));
+}
+
+#[test]
+fn test_cancel_token_stops_search() {
+    use chassis_core::CancelToken;
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = VectorIndex::open(temp_file.path(), 16, IndexOptions::default()).unwrap();
+    let vector = |i: usize| (0..16).map(|j| ((i * 16 + j) as f32 * 0.37).sin()).collect::<Vec<_>>();
+    for i in 0..500 {
+        index.add(&vector(i)).unwrap();
+    }
+
+    let token = CancelToken::new();
+    let options = SearchOptions { cancel: Some(token.clone()), ..SearchOptions::default() };
+    assert_eq!(
+        index.search_with_options(&vector(5), 10, &options).unwrap(),
+        index.search(&vector(5), 10).unwrap()
+    );
+
+    // The user typed again: the stale query fails instead of finishing
+    token.cancel();
+    let err = index.search_with_options(&vector(5), 10, &options).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::Cancelled);
+    index.flush().unwrap();
+    drop(index);
+
+    let options_exact = IndexOptions { exact_search_threshold: 10_000, ..IndexOptions::default() };
+    let index = VectorIndex::open(temp_file.path(), 16, options_exact).unwrap();
+    let err = index.search_with_options(&vector(5), 10, &options).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::Cancelled);
+}
+
+#[test]
+fn test_cancel_token_stops_build_resumably() {
+    use chassis_core::{CancelToken, HnswBuilder, HnswParams};
+    use std::io::{Cursor, Read};
+
+    /// Cancels `token` once `limit` bytes have been read
+    struct CancelAfter {
+        inner: Cursor<Vec<u8>>,
+        token: CancelToken,
+        limit: u64,
+    }
+
+    impl Read for CancelAfter {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let read = self.inner.read(buf)?;
+            if self.inner.position() >= self.limit {
+                self.token.cancel();
+            }
+            Ok(read)
+        }
+    }
+
+    let dims = 8u32;
+    let bytes: Vec<u8> = (0..300)
+        .flat_map(|i| std::iter::repeat_n(i as f32, dims as usize))
+        .flat_map(f32::to_le_bytes)
+        .collect();
+
+    // Cancelled while the 4th chunk (vectors 96..128) is read
+    let token = CancelToken::new();
+    let reader =
+        CancelAfter { inner: Cursor::new(bytes.clone()), token: token.clone(), limit: 3200 };
+    let temp_file = NamedTempFile::new().unwrap();
+    let builder = HnswBuilder::new(HnswParams::default()).chunk_size(32).flush_interval(256);
+    let err = builder
+        .cancel_token(token)
+        .build_from_reader(temp_file.path(), reader, dims, 300)
+        .unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::Cancelled);
+
+    // The inserted vectors were flushed, so the build resumes after them
+    let index = VectorIndex::open(temp_file.path(), dims, IndexOptions::default()).unwrap();
+    assert_eq!(index.build_progress().unwrap().vectors_done, 96);
+    drop(index);
+
+    let builder = HnswBuilder::new(HnswParams::default()).chunk_size(32);
+    let index =
+        builder.resume_from_reader(temp_file.path(), Cursor::new(&bytes), dims, 300).unwrap();
+    assert_eq!(index.len(), 300);
+    assert_eq!(index.search(&[200.0; 8], 1).unwrap()[0].id, 200);
+}
+
+#[test]
+fn test_open_with_report_describes_rolled_back_vectors() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let path = temp_file.path();
+    let vector = |i: usize| (0..8).map(|j| (i * 8 + j) as f32).collect::<Vec<_>>();
+
+    let (mut index, report) =
+        VectorIndex::open_with_report(path, 8, IndexOptions::default()).unwrap();
+    assert!(report.is_clean());
+    for i in 0..15 {
+        index.add(&vector(i)).unwrap();
+        if i == 9 {
+            index.flush().unwrap();
+        }
+    }
+    // Dropped like a crashed writer: the last five vectors were never flushed
+    drop(index);
+
+    let (mut index, report) =
+        VectorIndex::open_with_report(path, 8, IndexOptions::default()).unwrap();
+    assert_eq!(report.reclaimed_ids, 10..15);
+    assert_eq!(report.ghosts_reclaimed(), 5);
+    assert!(!report.legacy_layout_compacted);
+    assert_eq!(index.len(), 10);
+    assert_eq!(index.add(&vector(20)).unwrap(), 10);
+    index.flush().unwrap();
+    drop(index);
+
+    let (index, report) = VectorIndex::open_with_report(path, 8, IndexOptions::default()).unwrap();
+    assert!(report.is_clean());
+    assert_eq!(index.len(), 11);
+}
+
+#[test]
+fn test_in_memory_index_persists_to_file() {
+    use chassis_core::ErrorKind;
+
+    let vector = |i: usize| (0..16).map(|j| ((i * 16 + j) as f32 * 0.37).sin()).collect::<Vec<_>>();
+    let mut index = VectorIndex::in_memory(16, IndexOptions::default()).unwrap();
+    assert!(index.is_in_memory());
+    for i in 0..300 {
+        index.add_with_key(&format!("doc-{}", i), &vector(i)).unwrap();
+    }
+    let expected = index.search(&vector(42), 5).unwrap();
+    assert_eq!(expected[0].id, 42);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("built.chassis");
+    index.persist_to(&path).unwrap();
+
+    // The in-memory index keeps working after it was written out
+    index.add(&vector(300)).unwrap();
+    assert_eq!(index.len(), 301);
+    drop(index);
+
+    // The zones are packed: no slack in front of the graph or the metadata
+    assert!(std::fs::metadata(&path).unwrap().len() < 256 * 1024);
+
+    let mut reopened = VectorIndex::open(&path, 16, IndexOptions::default()).unwrap();
+    assert!(!reopened.is_in_memory());
+    assert_eq!(reopened.len(), 300);
+    assert_eq!(reopened.search(&vector(42), 5).unwrap(), expected);
+    assert_eq!(reopened.id_for_key("doc-7"), Some(7));
+
+    let err = reopened.persist_to(dir.path().join("copy.chassis")).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+
+    // The first insert moves the graph out of the way of the vectors
+    for i in 300..400 {
+        reopened.add_with_key(&format!("doc-{}", i), &vector(i)).unwrap();
+    }
+    reopened.flush().unwrap();
+    drop(reopened);
+    let reopened = VectorIndex::open(&path, 16, IndexOptions::default()).unwrap();
+    assert_eq!(reopened.search(&vector(42), 1).unwrap()[0].id, 42);
+    assert_eq!(reopened.search(&vector(350), 1).unwrap()[0].id, 350);
+    assert_eq!(reopened.id_for_key("doc-399"), Some(399));
+}
+
+#[test]
+fn test_fork_in_memory_leaves_original_untouched() {
+    let vector = |i: usize| (0..16).map(|j| ((i * 16 + j) as f32 * 0.37).sin()).collect::<Vec<_>>();
+    for graph_file in [false, true] {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("original.chassis");
+        let options = IndexOptions { graph_file, ..IndexOptions::default() };
+
+        let mut index = VectorIndex::open(&path, 16, options.clone()).unwrap();
+        for i in 0..200 {
+            index.add_with_key(&format!("doc-{}", i), &vector(i)).unwrap();
+        }
+        index.flush().unwrap();
+        let before = std::fs::read(&path).unwrap();
+        let expected = index.search(&vector(1000), 5).unwrap();
+
+        {
+            let mut fork = index.fork_in_memory().unwrap();
+            assert!(fork.is_in_memory());
+            assert_eq!(fork.len(), 200);
+            assert_eq!(fork.id_for_key("doc-3"), Some(3));
+
+            // Enough inserts to grow the fork past the end of the file
+            for i in 1000..1600 {
+                fork.add(&vector(i)).unwrap();
+            }
+            fork.delete(0).unwrap();
+            fork.flush().unwrap();
+            assert_eq!(fork.search(&vector(1000), 1).unwrap()[0].id, 200);
+            assert_eq!(index.search(&vector(1000), 5).unwrap(), expected);
+        }
+
+        assert_eq!(index.len(), 200);
+        assert!(!index.is_deleted(0).unwrap());
+        drop(index);
+        // The header differs only in the writer-open bit, cleared by the drop
+        let after = std::fs::read(&path).unwrap();
+        assert_eq!(after[HEADER_SIZE..], before[HEADER_SIZE..], "graph_file {}", graph_file);
+
+        let reopened = VectorIndex::open(&path, 16, options).unwrap();
+        assert_eq!(reopened.len(), 200);
+        assert_eq!(reopened.search(&vector(1000), 5).unwrap(), expected);
+    }
+}
+
+#[test]
+fn test_pin_graph_locks_upper_layers_within_budget() {
+    let vector = |i: usize| (0..16).map(|j| ((i * 16 + j) as f32 * 0.37).sin()).collect::<Vec<_>>();
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = VectorIndex::open(temp_file.path(), 16, IndexOptions::default()).unwrap();
+    for i in 0..500 {
+        index.add(&vector(i)).unwrap();
+    }
+    index.flush().unwrap();
+
+    let none = index.pin_graph(0).unwrap();
+    assert_eq!((none.pinned_bytes, none.pinned_nodes), (0, 0));
+    assert!(none.upper_layer_nodes > 0);
+
+    // A budget of one page holds the headers and perhaps a few records
+    let page = index.pin_graph(4096).unwrap();
+    assert!(page.pinned_bytes <= 4096);
+    assert!(page.pinned_nodes < page.upper_layer_nodes);
+
+    let pin = index.pin_graph(64 << 20).unwrap();
+    assert!(pin.pinned_bytes <= 64 << 20);
+    if !pin.os_limited {
+        assert_eq!(pin.pinned_nodes, pin.upper_layer_nodes);
+    }
+    assert_eq!(index.search(&vector(42), 1).unwrap()[0].id, 42);
+
+    index.unpin_graph().unwrap();
+    index.add(&vector(500)).unwrap();
+    assert_eq!(index.search(&vector(500), 1).unwrap()[0].id, 500);
+}
+
+#[test]
+fn test_metrics_count_operations() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = VectorIndex::open(temp_file.path(), 16, IndexOptions::default()).unwrap();
+    assert_eq!(index.metrics().adds, 0);
+
+    for i in 0..300 {
+        index.add(&[i as f32 * 0.123; 16]).unwrap();
+    }
+    index.flush().unwrap();
+    for _ in 0..5 {
+        index.search(&[1.0; 16], 5).unwrap();
+    }
+    index.search_within(&[1.0; 16], 0.5).unwrap();
+
+    let metrics = index.metrics();
+    assert_eq!(metrics.vectors, 300);
+    assert_eq!(metrics.adds, 300);
+    assert_eq!(metrics.flushes, 1);
+    assert_eq!((metrics.searches, metrics.range_searches), (5, 1));
+    assert!(metrics.distance_computations >= 5);
+    assert!(metrics.remaps > 0);
+    #[cfg(target_os = "linux")]
+    assert!(metrics.major_page_faults.is_some());
+
+    let text = metrics.to_prometheus();
+    assert!(text.contains("# TYPE chassis_searches_total counter\nchassis_searches_total 5\n"));
+    assert!(text.contains("chassis_vectors 300\n"));
+    assert_eq!(
+        text.lines().filter(|line| line.starts_with("# HELP")).count(),
+        7 + usize::from(metrics.major_page_faults.is_some())
+    );
+}
+
+#[test]
+fn test_nan_policy_rejects_or_zeroes_non_finite_components() {
+    use chassis_core::{ErrorKind, NanPolicy};
+
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut index = VectorIndex::open(temp_file.path(), 4, IndexOptions::default()).unwrap();
+    index.add(&[1.0, 2.0, 3.0, 4.0]).unwrap();
+
+    for bad in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+        let err = index.add(&[1.0, bad, 3.0, 4.0]).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+        let err = index.search(&[bad, 0.0, 0.0, 0.0], 1).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+    }
+    assert_eq!(index.len(), 1);
+    index.flush().unwrap();
+    drop(index);
+
+    let options =
+        IndexOptions { nan_policy: NanPolicy::ReplaceWithZero, ..IndexOptions::default() };
+    let mut index = VectorIndex::open(temp_file.path(), 4, options).unwrap();
+    let id = index.add(&[f32::NAN, 2.0, f32::INFINITY, 4.0]).unwrap();
+    assert_eq!(index.get_vector(id).unwrap(), [0.0, 2.0, 0.0, 4.0]);
+
+    let results = index.search(&[f32::NAN, 2.0, 0.0, 4.0], 2).unwrap();
+    assert_eq!(results[0].id, id);
+    assert_eq!(results[0].distance, 0.0);
+    assert!(results[1].distance.is_finite());
+}
+
+#[test]
+fn test_encrypted_index_keeps_plaintext_off_disk() {
+    use chassis_core::{EncryptionKey, ErrorKind};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("secret.chassis");
+    let key = EncryptionKey::from_bytes([42; 32]);
+    let point = |i: usize| -> Vec<f32> { (0..8).map(|d| (i * 8 + d) as f32 * 0.25).collect() };
+
+    let expected_ids: Vec<u64> = {
+        let mut index =
+            VectorIndex::open_encrypted(&path, 8, &key, IndexOptions::default()).unwrap();
+        assert!(index.is_encrypted() && !index.is_in_memory());
+        for i in 0..300 {
+            index.add(&point(i)).unwrap();
+        }
+        index.flush().unwrap();
+
+        // Unflushed vectors are rolled back on reopen, as with a plain file
+        index.add(&point(300)).unwrap();
+        index.search(&point(123), 5).unwrap().iter().map(|r| r.id).collect()
+    };
+
+    let bytes = std::fs::read(&path).unwrap();
+    let needle: Vec<u8> = point(123).iter().flat_map(|x| x.to_le_bytes()).collect();
+    assert!(!bytes.windows(needle.len()).any(|w| w == needle));
+
+    let err = VectorIndex::open(&path, 8, IndexOptions::default()).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::Corrupted);
+    let wrong = EncryptionKey::from_bytes([7; 32]);
+    let err = VectorIndex::open_encrypted(&path, 8, &wrong, IndexOptions::default()).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+
+    let mut index = VectorIndex::open_encrypted(&path, 8, &key, IndexOptions::default()).unwrap();
+    assert_eq!(index.len(), 300);
+    assert_eq!(index.get_vector(123).unwrap(), point(123));
+    let results = index.search(&point(123), 5).unwrap();
+    assert_eq!(results.iter().map(|r| r.id).collect::<Vec<_>>(), expected_ids);
+
+    let copy = dir.path().join("copy.chassis");
+    index.snapshot_to(&copy).unwrap();
+    assert!(index.reattach(dir.path().join("moved.chassis")).is_err());
+    drop(index);
+    let copy = VectorIndex::open_encrypted(&copy, 8, &key, IndexOptions::default()).unwrap();
+    assert_eq!(copy.len(), 300);
+
+    let options = IndexOptions { graph_file: true, ..IndexOptions::default() };
+    let other = dir.path().join("graph.chassis");
+    let err = VectorIndex::open_encrypted(&other, 8, &key, options).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::InvalidArgument);
+}
+
+#[cfg(all(feature = "encryption", target_os = "linux"))]
+#[test]
+fn test_encrypted_index_decrypts_blocks_on_first_access() {
+    use chassis_core::EncryptionKey;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("lazy.chassis");
+    let key = EncryptionKey::from_bytes([11; 32]);
+    let point = |i: usize| -> Vec<f32> { (0..64).map(|d| ((i * 64 + d) as f32).sin()).collect() };
+    {
+        let mut index =
+            VectorIndex::open_encrypted(&path, 64, &key, IndexOptions::default()).unwrap();
+        for i in 0..1000 {
+            index.add(&point(i)).unwrap();
+        }
+        index.flush().unwrap();
+    }
+
+    let mut index = VectorIndex::open_encrypted(&path, 64, &key, IndexOptions::default()).unwrap();
+    let footprint = index.memory_footprint().unwrap();
+    let resident = footprint.resident_bytes.unwrap();
+    assert!(
+        resident * 4 < footprint.mapped_bytes,
+        "{} of {} bytes resident",
+        resident,
+        footprint.mapped_bytes
+    );
+
+    assert_eq!(index.get_vector(567).unwrap(), point(567));
+    assert_eq!(index.search(&point(77), 1).unwrap()[0].id, 77);
+    let id = index.add(&point(1000)).unwrap();
+    index.flush().unwrap();
+    drop(index);
+
+    let index = VectorIndex::open_encrypted(&path, 64, &key, IndexOptions::default()).unwrap();
+    assert_eq!(index.get_vector(id).unwrap(), point(1000));
+    assert_eq!(index.search(&point(999), 1).unwrap()[0].id, 999);
+}
@@ -0,0 +1,184 @@
+//! Multi-process read tests for shared reader and sealed mode
+//!
+//! The cross-process test re-runs this test binary as child processes. Each
+//! child executes `shared_reader_child` with `CHASSIS_SHARED_READER_PATH` set,
+//! so the locks are exercised between real processes (`LockFileEx` on Windows,
+//! `flock` on Unix).
+
+use chassis_core::{ErrorKind, IndexOptions, VectorIndex};
+use std::path::Path;
+use std::process::Command;
+use std::sync::Arc;
+use tempfile::NamedTempFile;
+
+const DIMS: u32 = 16;
+const CHILD_PATH_VAR: &str = "CHASSIS_SHARED_READER_PATH";
+
+fn build_index(path: &Path, count: u64) {
+    let mut index = VectorIndex::open(path, DIMS, IndexOptions::default()).unwrap();
+    for i in 0..count {
+        index.add(&[i as f32; DIMS as usize]).unwrap();
+    }
+    index.flush().unwrap();
+}
+
+/// Child-process entry point; a no-op when run as a normal test.
+#[test]
+fn shared_reader_child() {
+    let Ok(path) = std::env::var(CHILD_PATH_VAR) else {
+        return;
+    };
+
+    let reader = VectorIndex::open_shared(&path, DIMS, IndexOptions::default()).unwrap();
+    assert_eq!(reader.len(), 100);
+    assert_eq!(reader.search(&[42.0; DIMS as usize], 1).unwrap()[0].id, 42);
+
+    // The parent also holds a shared lock, so a writer must be refused here
+    assert!(VectorIndex::open(&path, DIMS, IndexOptions::default()).is_err());
+}
+
+#[test]
+fn test_shared_readers_across_processes() {
+    let temp_file = NamedTempFile::new().unwrap();
+    build_index(temp_file.path(), 100);
+
+    let parent = VectorIndex::open_shared(temp_file.path(), DIMS, IndexOptions::default()).unwrap();
+
+    let exe = std::env::current_exe().unwrap();
+    let children: Vec<_> = (0..3)
+        .map(|_| {
+            Command::new(&exe)
+                .args(["--exact", "shared_reader_child", "--test-threads=1"])
+                .env(CHILD_PATH_VAR, temp_file.path())
+                .spawn()
+                .unwrap()
+        })
+        .collect();
+
+    for mut child in children {
+        assert!(child.wait().unwrap().success(), "child reader process failed");
+    }
+
+    assert_eq!(parent.search(&[7.0; DIMS as usize], 1).unwrap()[0].id, 7);
+    drop(parent);
+
+    // All shared locks released: a writer can open again
+    let mut writer = VectorIndex::open(temp_file.path(), DIMS, IndexOptions::default()).unwrap();
+    writer.add(&[100.0; DIMS as usize]).unwrap();
+}
+
+#[test]
+fn test_shared_readers_exclude_writer() {
+    let temp_file = NamedTempFile::new().unwrap();
+    build_index(temp_file.path(), 10);
+
+    {
+        let writer = VectorIndex::open(temp_file.path(), DIMS, IndexOptions::default()).unwrap();
+        let err =
+            VectorIndex::open_shared(temp_file.path(), DIMS, IndexOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("open for writing"));
+        drop(writer);
+    }
+
+    let first = VectorIndex::open_shared(temp_file.path(), DIMS, IndexOptions::default()).unwrap();
+    let second = VectorIndex::open_shared(temp_file.path(), DIMS, IndexOptions::default()).unwrap();
+    assert!(first.is_shared_reader());
+    assert_eq!(second.len(), 10);
+
+    assert!(VectorIndex::open(temp_file.path(), DIMS, IndexOptions::default()).is_err());
+}
+
+#[test]
+fn test_shared_reader_rejects_writes_and_leaves_file_untouched() {
+    let temp_file = NamedTempFile::new().unwrap();
+    build_index(temp_file.path(), 10);
+    let before = std::fs::read(temp_file.path()).unwrap();
+
+    {
+        let mut reader =
+            VectorIndex::open_shared(temp_file.path(), DIMS, IndexOptions::default()).unwrap();
+        let err = reader.add(&[1.0; DIMS as usize]).unwrap_err();
+        assert!(err.to_string().contains("shared reader"));
+        assert!(reader.flush().is_err());
+        assert_eq!(reader.len(), 10);
+    }
+
+    assert_eq!(std::fs::read(temp_file.path()).unwrap(), before);
+}
+
+#[test]
+fn test_shared_open_requires_existing_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("missing.chassis");
+
+    assert!(VectorIndex::open_shared(&path, DIMS, IndexOptions::default()).is_err());
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_sealed_index_is_searched_from_threads_without_locking() {
+    let temp_file = NamedTempFile::new().unwrap();
+    build_index(temp_file.path(), 100);
+    let before = std::fs::read(temp_file.path()).unwrap();
+
+    let sealed =
+        Arc::new(VectorIndex::open_sealed(temp_file.path(), IndexOptions::default()).unwrap());
+    assert!(sealed.is_sealed());
+    assert!(sealed.is_shared_reader());
+    assert_eq!(sealed.dimensions(), DIMS);
+
+    std::thread::scope(|scope| {
+        for t in 0..4 {
+            let sealed = Arc::clone(&sealed);
+            scope.spawn(move || {
+                for i in (t..100).step_by(4) {
+                    let results = sealed.search(&[i as f32; DIMS as usize], 1).unwrap();
+                    assert_eq!(results[0].id, i as u64);
+                }
+            });
+        }
+    });
+
+    // No lock is held: other readers and even a writer can open the file
+    let shared = VectorIndex::open_shared(temp_file.path(), DIMS, IndexOptions::default()).unwrap();
+    drop(shared);
+    let writer = VectorIndex::open(temp_file.path(), DIMS, IndexOptions::default()).unwrap();
+    drop(writer);
+
+    let mut sealed = Arc::into_inner(sealed).unwrap();
+    let err = sealed.add(&[1.0; DIMS as usize]).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::ReadOnly);
+    assert!(err.to_string().contains("sealed"));
+    assert!(sealed.flush().is_err());
+    sealed.release_memory().unwrap();
+    drop(sealed);
+
+    assert_eq!(std::fs::read(temp_file.path()).unwrap(), before);
+}
+
+#[test]
+fn test_sealed_open_refuses_unrecovered_files() {
+    let temp_file = NamedTempFile::new().unwrap();
+    build_index(temp_file.path(), 10);
+    {
+        // Crash after an insert: the vector is written, the graph header is not
+        let mut writer =
+            VectorIndex::open(temp_file.path(), DIMS, IndexOptions::default()).unwrap();
+        writer.add(&[10.0; DIMS as usize]).unwrap();
+    }
+
+    let err = VectorIndex::open_sealed(temp_file.path(), IndexOptions::default()).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::Corrupted);
+    let shared = VectorIndex::open_shared(temp_file.path(), DIMS, IndexOptions::default()).unwrap();
+    assert_eq!(shared.len(), 10);
+    drop(shared);
+
+    // A writer recovers the file, after which it can be sealed
+    VectorIndex::open(temp_file.path(), DIMS, IndexOptions::default()).unwrap().flush().unwrap();
+    let sealed = VectorIndex::open_sealed(temp_file.path(), IndexOptions::default()).unwrap();
+    assert_eq!(sealed.len(), 10);
+
+    // Files that never got a graph zone cannot be sealed
+    let empty = NamedTempFile::new().unwrap();
+    assert!(VectorIndex::open_sealed(empty.path(), IndexOptions::default()).is_err());
+}
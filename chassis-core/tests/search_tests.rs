@@ -344,3 +344,30 @@ fn test_search_correctness_on_small_graph() {
     assert!(results[0].distance <= results[1].distance);
     assert!(results[1].distance <= results[2].distance);
 }
+
+#[test]
+fn test_small_ef_matches_heap_search() {
+    let (mut graph, _temp) = create_test_graph(200, 16);
+    build_sequential_graph(&mut graph, 200);
+
+    // An id_limit above every node keeps the heap implementation, while the
+    // unbounded search uses the fixed-array fast path for ef <= 16
+    for ef in [1, 3, 4, 8, 16] {
+        for q in 0..20 {
+            let mut query = vec![0.0; 16];
+            query[0] = q as f32 / 20.0;
+            query[1] = (q % 7) as f32 / 7.0;
+
+            let k = ef.min(4);
+            let fast = graph.search(&query, k, ef).unwrap();
+            let heap = graph.search_bounded(&query, k, ef, 200).unwrap();
+            // Equal distances may come back in either order
+            let ids = |results: &[chassis_core::SearchResult]| {
+                let mut ids: Vec<_> = results.iter().map(|r| (r.distance, r.id)).collect();
+                ids.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+                ids.into_iter().map(|(_, id)| id).collect::<Vec<_>>()
+            };
+            assert_eq!(ids(&fast), ids(&heap), "ef={} q={}", ef, q);
+        }
+    }
+}
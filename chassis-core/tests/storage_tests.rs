@@ -347,6 +347,27 @@ fn test_get_vector_slice_multiple_vectors() {
     }
 }
 
+#[test]
+fn test_vector_matrix_slice_is_row_major() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let mut storage = Storage::open(temp_file.path(), 3).unwrap();
+    for i in 0..10 {
+        storage.insert(&[i as f32, i as f32 + 0.5, -(i as f32)]).unwrap();
+    }
+
+    let matrix = storage.vector_matrix_slice(2..7).unwrap();
+    assert_eq!(matrix.len(), 5 * 3);
+    for (row, id) in matrix.as_chunks::<3>().0.iter().zip(2..7) {
+        assert_eq!(&row[..], storage.get_vector_slice(id).unwrap());
+    }
+
+    assert_eq!(storage.vector_matrix_slice(0..10).unwrap().len(), 30);
+    assert!(storage.vector_matrix_slice(4..4).unwrap().is_empty());
+    assert!(storage.vector_matrix_slice(8..11).is_err());
+    let (start, end) = (5, 3);
+    assert!(storage.vector_matrix_slice(start..end).is_err());
+}
+
 #[test]
 fn test_get_vector_slice_after_reopen() {
     let temp_file = NamedTempFile::new().unwrap();
@@ -502,3 +523,34 @@ fn test_slice_then_read_only_operations() {
     assert_eq!(other_slice.len(), 128);
     assert_eq!(owned.len(), 128);
 }
+
+#[test]
+fn test_repeated_growth_remaps_without_losing_data() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let path = temp_file.path();
+
+    {
+        // One page per growth step: a remap every four inserts
+        let mut storage = Storage::open(path, 256).unwrap();
+        storage.set_growth_chunk(4096);
+        for i in 0..100 {
+            storage.insert(&vec![i as f32; 256]).unwrap();
+            if i % 25 == 0 {
+                storage.commit().unwrap();
+            }
+        }
+        storage.commit().unwrap();
+
+        // Still locked while remapped
+        assert!(Storage::open(path, 256).is_err());
+    }
+
+    let len = std::fs::metadata(path).unwrap().len();
+    assert_eq!(len % 4096, 0);
+
+    let storage = Storage::open(path, 256).unwrap();
+    assert_eq!(storage.count(), 100);
+    for i in [0, 1, 50, 99] {
+        assert_eq!(storage.get_vector(i).unwrap(), vec![i as f32; 256]);
+    }
+}
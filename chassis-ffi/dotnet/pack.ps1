@@ -0,0 +1,50 @@
+# Build the Chassis NuGet package on Windows; see pack.sh for the details.
+#
+# Usage: ./pack.ps1 [-Rids win-x64,win-arm64]
+
+param(
+    [string[]]$Rids = @("win-x64", "win-arm64")
+)
+
+$ErrorActionPreference = "Stop"
+
+$DotnetDir = $PSScriptRoot
+$Root = Resolve-Path (Join-Path $DotnetDir "../..")
+$Runtimes = Join-Path $DotnetDir "src/Chassis/runtimes"
+$Version = (Select-String -Path (Join-Path $Root "Cargo.toml") -Pattern '^version = "(.*)"' |
+    Select-Object -First 1).Matches[0].Groups[1].Value
+
+$Targets = @{
+    "win-x64"   = "x86_64-pc-windows-msvc"
+    "win-arm64" = "aarch64-pc-windows-msvc"
+}
+
+if (Test-Path $Runtimes) {
+    Remove-Item -Recurse -Force $Runtimes
+}
+foreach ($rid in $Rids) {
+    $target = $Targets[$rid]
+    if (-not $target) {
+        throw "unknown runtime identifier: $rid"
+    }
+
+    rustup target add $target
+    cargo build --manifest-path (Join-Path $Root "Cargo.toml") -p chassis-ffi --release --target $target
+    if ($LASTEXITCODE -ne 0) {
+        throw "cargo build failed for $target"
+    }
+    $native = Join-Path $Runtimes "$rid/native"
+    New-Item -ItemType Directory -Force $native | Out-Null
+    Copy-Item (Join-Path $Root "target/$target/release/chassis_ffi.dll") $native
+}
+
+Push-Location $DotnetDir
+try {
+    dotnet pack src/Chassis -c Release -o artifacts "-p:Version=$Version"
+    if ($LASTEXITCODE -ne 0) {
+        throw "dotnet pack failed"
+    }
+} finally {
+    Pop-Location
+}
+Write-Output "Built artifacts/Chassis.$Version.nupkg"
@@ -0,0 +1,49 @@
+#!/usr/bin/env bash
+# Build the Chassis NuGet package with native libraries for several platforms.
+#
+# Each Rust target is built as a shared library and staged under
+# src/Chassis/runtimes/<rid>/native/, where NuGet resolves it for the app's
+# runtime identifier. Cross targets need their linkers installed; pass the
+# runtime identifiers to build a subset. Requires rustup and the .NET 8 SDK.
+#
+# Usage: ./pack.sh [rid...]    e.g. ./pack.sh linux-x64 osx-arm64
+
+set -euo pipefail
+
+cd "$(dirname "$0")"
+DOTNET_DIR="$PWD"
+ROOT="$(cd ../.. && pwd)"
+RUNTIMES="$DOTNET_DIR/src/Chassis/runtimes"
+VERSION="$(sed -n 's/^version = "\(.*\)"/\1/p' "$ROOT/Cargo.toml" | head -n 1)"
+
+declare -A TARGETS=(
+    [win-x64]=x86_64-pc-windows-msvc
+    [win-arm64]=aarch64-pc-windows-msvc
+    [linux-x64]=x86_64-unknown-linux-gnu
+    [linux-arm64]=aarch64-unknown-linux-gnu
+    [osx-x64]=x86_64-apple-darwin
+    [osx-arm64]=aarch64-apple-darwin
+)
+
+RIDS=("$@")
+if [ ${#RIDS[@]} -eq 0 ]; then
+    RIDS=("${!TARGETS[@]}")
+fi
+
+rm -rf "$RUNTIMES"
+for rid in "${RIDS[@]}"; do
+    target="${TARGETS[$rid]:?unknown runtime identifier: $rid}"
+    case "$rid" in
+        win-*) lib=chassis_ffi.dll ;;
+        osx-*) lib=libchassis_ffi.dylib ;;
+        *) lib=libchassis_ffi.so ;;
+    esac
+
+    rustup target add "$target"
+    cargo build --manifest-path "$ROOT/Cargo.toml" -p chassis-ffi --release --target "$target"
+    mkdir -p "$RUNTIMES/$rid/native"
+    cp "$ROOT/target/$target/release/$lib" "$RUNTIMES/$rid/native/"
+done
+
+dotnet pack src/Chassis -c Release -o artifacts -p:Version="$VERSION"
+echo "Built artifacts/Chassis.$VERSION.nupkg"
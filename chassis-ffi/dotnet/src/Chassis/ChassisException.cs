@@ -0,0 +1,60 @@
+namespace Chassis;
+
+/// <summary>Category of a failure, mirroring <c>ChassisErrorCode</c> in <c>chassis.h</c></summary>
+public enum ChassisErrorCode : uint
+{
+    InvalidArgument = 1,
+    NullPointer = 2,
+    InvalidUtf8 = 3,
+    DimensionMismatch = 4,
+    Locked = 5,
+    Corrupted = 6,
+    Io = 7,
+    OutOfBounds = 8,
+    FileStolen = 9,
+    ReadOnly = 10,
+    Panic = 11,
+    Unknown = 12,
+    IncompatibleVersion = 13,
+    QuotaExceeded = 14,
+    Cancelled = 15,
+}
+
+/// <summary>An error reported by the Chassis library</summary>
+public sealed class ChassisException : Exception
+{
+    public ChassisException(ChassisErrorCode code, string message)
+        : base(message)
+    {
+        Code = code;
+    }
+
+    /// <summary>Category of the failure</summary>
+    public ChassisErrorCode Code { get; }
+
+    /// <summary>The error left by the last C call on this thread, if it failed</summary>
+    /// <remarks>
+    /// Errors are thread-local and overwritten by the next call, so this must
+    /// run right after the failing call, on the same thread. P/Invoke calls
+    /// do not switch threads, so that holds inside one method.
+    /// </remarks>
+    internal static unsafe ChassisException? Last()
+    {
+        var raw = NativeMethods.LastErrorCode();
+        if (raw == 0)
+        {
+            return null;
+        }
+
+        var message = NativeMethods.LastErrorMessage();
+        var text = message == null
+            ? "Unknown error"
+            : System.Runtime.InteropServices.Marshal.PtrToStringUTF8((nint)message) ?? "Unknown error";
+        var code = Enum.IsDefined(typeof(ChassisErrorCode), raw) ? (ChassisErrorCode)raw : ChassisErrorCode.Unknown;
+        return new ChassisException(code, text);
+    }
+
+    /// <summary>Like <see cref="Last"/>, for calls whose return value already signalled a failure</summary>
+    internal static ChassisException LastOrUnknown() =>
+        Last() ?? new ChassisException(ChassisErrorCode.Unknown, "Unknown error");
+}
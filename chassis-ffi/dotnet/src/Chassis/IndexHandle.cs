@@ -0,0 +1,25 @@
+using System.Runtime.InteropServices;
+
+namespace Chassis;
+
+/// <summary>Owns a <c>ChassisIndex*</c> and frees it exactly once</summary>
+/// <remarks>
+/// P/Invoke holds a reference to the handle for the length of every call, so
+/// disposing the index or finalizing it while another thread is inside a
+/// call defers <c>chassis_free</c> until that call returns.
+/// </remarks>
+internal sealed class IndexHandle : SafeHandle
+{
+    public IndexHandle()
+        : base(nint.Zero, ownsHandle: true)
+    {
+    }
+
+    public override bool IsInvalid => handle == nint.Zero;
+
+    protected override bool ReleaseHandle()
+    {
+        NativeMethods.Free(handle);
+        return true;
+    }
+}
@@ -0,0 +1,74 @@
+namespace Chassis;
+
+/// <summary>On-disk encoding of vector components</summary>
+public enum ElementType : uint
+{
+    /// <summary>32-bit floats</summary>
+    F32 = 0,
+
+    /// <summary>IEEE 754 half precision</summary>
+    F16 = 1,
+
+    /// <summary>bfloat16</summary>
+    BF16 = 2,
+
+    /// <summary>One sign bit per component, compared by Hamming distance</summary>
+    Binary = 3,
+}
+
+/// <summary>Configuration for a new index, mirroring <c>ChassisOptions</c> in <c>chassis.h</c></summary>
+/// <remarks>
+/// The graph parameters and element type of an existing file take precedence
+/// over these values.
+/// </remarks>
+public sealed record IndexOptions
+{
+    /// <summary>Maximum connections per node (M parameter, at most 65535)</summary>
+    public uint MaxConnections { get; init; } = 16;
+
+    /// <summary>Construction quality (efConstruction)</summary>
+    public uint EfConstruction { get; init; } = 200;
+
+    /// <summary>Search quality (efSearch)</summary>
+    public uint EfSearch { get; init; } = 50;
+
+    /// <summary>Maximum number of graph layers (1 to 16)</summary>
+    public uint MaxLayers { get; init; } = 16;
+
+    /// <summary>Encoding of stored vector components</summary>
+    public ElementType ElementType { get; init; } = ElementType.F32;
+
+    /// <summary>
+    /// Dimensionality of incoming vectors, truncated to the index dimensions on
+    /// add and search; 0 stores vectors as given
+    /// </summary>
+    public uint InputDimensions { get; init; }
+
+    /// <summary>
+    /// Scan every vector instead of searching the graph while the index holds
+    /// at most this many; 0 always uses the graph
+    /// </summary>
+    public ulong ExactSearchThreshold { get; init; }
+
+    /// <summary>Normalize vectors to unit length and compare them by cosine distance</summary>
+    public bool Normalize { get; init; }
+
+    /// <summary>Keep the graph in a second file next to the index</summary>
+    public bool GraphFile { get; init; }
+
+    /// <summary>These options over the library defaults, so fields added later keep their defaults</summary>
+    internal NativeOptions ToNative()
+    {
+        var options = NativeMethods.OptionsDefault();
+        options.MaxConnections = MaxConnections;
+        options.EfConstruction = EfConstruction;
+        options.EfSearch = EfSearch;
+        options.MaxLayers = MaxLayers;
+        options.ElementType = (uint)ElementType;
+        options.InputDimensions = InputDimensions;
+        options.ExactSearchThreshold = ExactSearchThreshold;
+        options.Normalize = Normalize ? (byte)1 : (byte)0;
+        options.GraphFile = GraphFile ? (byte)1 : (byte)0;
+        return options;
+    }
+}
@@ -0,0 +1,90 @@
+using System.Runtime.InteropServices;
+
+namespace Chassis;
+
+/// <summary>Field layout of <c>ChassisOptions</c> in <c>chassis.h</c></summary>
+[StructLayout(LayoutKind.Sequential)]
+internal struct NativeOptions
+{
+    public uint MaxConnections;
+    public uint EfConstruction;
+    public uint EfSearch;
+    public uint MaxLayers;
+    public uint ElementType;
+    public uint InputDimensions;
+    public ulong ExactSearchThreshold;
+    // C `bool`, kept as bytes so the struct stays blittable
+    public byte Normalize;
+    public byte GraphFile;
+}
+
+/// <summary>P/Invoke declarations for <c>chassis.h</c></summary>
+/// <remarks>
+/// Buffers are passed as pointers into pinned spans, so vectors cross the
+/// boundary without marshalling copies. The library resolves to
+/// <c>chassis_ffi.dll</c>, <c>libchassis_ffi.so</c> or <c>libchassis_ffi.dylib</c>.
+/// </remarks>
+internal static unsafe partial class NativeMethods
+{
+    private const string Library = "chassis_ffi";
+
+    /// <summary>Matches <c>CHASSIS_ABI_VERSION</c> in the header these declarations follow</summary>
+    internal const uint AbiVersion = 1;
+
+    [LibraryImport(Library, EntryPoint = "chassis_open")]
+    internal static partial IndexHandle Open(byte* path, uint dimensions);
+
+    [LibraryImport(Library, EntryPoint = "chassis_options_default")]
+    internal static partial NativeOptions OptionsDefault();
+
+    [LibraryImport(Library, EntryPoint = "chassis_open_with_config")]
+    internal static partial IndexHandle OpenWithConfig(byte* path, uint dimensions, NativeOptions* options);
+
+    [LibraryImport(Library, EntryPoint = "chassis_open_shared")]
+    internal static partial IndexHandle OpenShared(byte* path, uint dimensions);
+
+    [LibraryImport(Library, EntryPoint = "chassis_free")]
+    internal static partial void Free(nint index);
+
+    [LibraryImport(Library, EntryPoint = "chassis_add")]
+    internal static partial ulong Add(IndexHandle index, float* vector, nuint len);
+
+    [LibraryImport(Library, EntryPoint = "chassis_add_batch")]
+    internal static partial nuint AddBatch(IndexHandle index, float* vectors, nuint count, nuint dim, ulong* outIds);
+
+    [LibraryImport(Library, EntryPoint = "chassis_search")]
+    internal static partial nuint Search(IndexHandle index, float* query, nuint len, nuint k, ulong* outIds, float* outDists);
+
+    [LibraryImport(Library, EntryPoint = "chassis_flush")]
+    internal static partial int Flush(IndexHandle index);
+
+    [LibraryImport(Library, EntryPoint = "chassis_snapshot_to")]
+    internal static partial int SnapshotTo(IndexHandle index, byte* path);
+
+    [LibraryImport(Library, EntryPoint = "chassis_delete")]
+    internal static partial int Delete(IndexHandle index, ulong id);
+
+    [LibraryImport(Library, EntryPoint = "chassis_update")]
+    internal static partial ulong Update(IndexHandle index, ulong id, float* vector, nuint len);
+
+    [LibraryImport(Library, EntryPoint = "chassis_get_vector")]
+    internal static partial nuint GetVector(IndexHandle index, ulong id, float* outBuf, nuint len);
+
+    [LibraryImport(Library, EntryPoint = "chassis_len")]
+    internal static partial ulong Len(IndexHandle index);
+
+    [LibraryImport(Library, EntryPoint = "chassis_dimensions")]
+    internal static partial uint Dimensions(IndexHandle index);
+
+    [LibraryImport(Library, EntryPoint = "chassis_last_error_message")]
+    internal static partial byte* LastErrorMessage();
+
+    [LibraryImport(Library, EntryPoint = "chassis_last_error_code")]
+    internal static partial uint LastErrorCode();
+
+    [LibraryImport(Library, EntryPoint = "chassis_version")]
+    internal static partial byte* Version();
+
+    [LibraryImport(Library, EntryPoint = "chassis_abi_version")]
+    internal static partial uint LibraryAbiVersion();
+}
@@ -0,0 +1,6 @@
+namespace Chassis;
+
+/// <summary>A search result</summary>
+/// <param name="Id">Vector ID in the index</param>
+/// <param name="Distance">Distance to the query vector (lower is closer)</param>
+public readonly record struct SearchResult(ulong Id, float Distance);
@@ -0,0 +1,354 @@
+using System.Text;
+
+namespace Chassis;
+
+/// <summary>A Chassis vector index stored in one file</summary>
+/// <remarks>
+/// <para>
+/// Dispose the index to close the file and release its lock; the finalizer
+/// closes it otherwise. Instances are safe to share between threads: the
+/// native index runs searches concurrently and serializes <c>Add</c>,
+/// <c>Flush</c> and the other writes against them.
+/// </para>
+/// <para>
+/// Vectors and result buffers are passed to the library as pointers into the
+/// caller's spans, without marshalling copies.
+/// </para>
+/// <code>
+/// var dir = Environment.GetFolderPath(Environment.SpecialFolder.LocalApplicationData);
+/// using var index = new VectorIndex(Path.Combine(dir, "notes.chassis"), dimensions: 384);
+/// var id = index.Add(embedding);
+/// index.Flush();
+/// var nearest = index.Search(query, k: 10);
+/// </code>
+/// </remarks>
+public sealed unsafe class VectorIndex : IDisposable
+{
+    private readonly IndexHandle _handle;
+
+    /// <summary>Open or create the index at <paramref name="path"/> with default options</summary>
+    /// <exception cref="ChassisException">
+    /// The file cannot be opened, is locked by another handle, or holds
+    /// vectors of other dimensions.
+    /// </exception>
+    public VectorIndex(string path, int dimensions)
+        : this(Open(path, dimensions, null))
+    {
+    }
+
+    /// <summary>Open or create the index at <paramref name="path"/> with custom options</summary>
+    /// <exception cref="ChassisException">
+    /// An option is out of range, or for the same reasons as
+    /// <see cref="VectorIndex(string, int)"/>.
+    /// </exception>
+    public VectorIndex(string path, int dimensions, IndexOptions options)
+        : this(Open(path, dimensions, options ?? throw new ArgumentNullException(nameof(options))))
+    {
+    }
+
+    private VectorIndex(IndexHandle handle)
+    {
+        _handle = handle;
+    }
+
+    /// <summary>Open an existing index read-only, shared with other processes</summary>
+    /// <remarks>
+    /// Writes on the returned index throw with <see cref="ChassisErrorCode.ReadOnly"/>.
+    /// </remarks>
+    /// <exception cref="ChassisException">The file cannot be opened or a writer holds it.</exception>
+    public static VectorIndex OpenShared(string path, int dimensions)
+    {
+        var dims = CheckDimensions(dimensions);
+        IndexHandle handle;
+        fixed (byte* utf8 = Utf8Path(path))
+        {
+            handle = NativeMethods.OpenShared(utf8, dims);
+        }
+        return new VectorIndex(Checked(handle));
+    }
+
+    private static IndexHandle Open(string path, int dimensions, IndexOptions? options)
+    {
+        var dims = CheckDimensions(dimensions);
+        if (NativeMethods.LibraryAbiVersion() != NativeMethods.AbiVersion)
+        {
+            throw new ChassisException(
+                ChassisErrorCode.IncompatibleVersion,
+                $"Native library ABI version {NativeMethods.LibraryAbiVersion()}, expected {NativeMethods.AbiVersion}");
+        }
+
+        IndexHandle handle;
+        fixed (byte* utf8 = Utf8Path(path))
+        {
+            if (options is null)
+            {
+                handle = NativeMethods.Open(utf8, dims);
+            }
+            else
+            {
+                var native = options.ToNative();
+                handle = NativeMethods.OpenWithConfig(utf8, dims, &native);
+            }
+        }
+        return Checked(handle);
+    }
+
+    private static IndexHandle Checked(IndexHandle handle)
+    {
+        if (handle.IsInvalid)
+        {
+            var error = ChassisException.LastOrUnknown();
+            handle.Dispose();
+            throw error;
+        }
+        return handle;
+    }
+
+    private static uint CheckDimensions(int dimensions)
+    {
+        if (dimensions <= 0)
+        {
+            throw new ChassisException(
+                ChassisErrorCode.InvalidArgument,
+                $"Dimensions must be between 1 and {int.MaxValue}, got {dimensions}");
+        }
+        return (uint)dimensions;
+    }
+
+    private static byte[] Utf8Path(string path)
+    {
+        ArgumentNullException.ThrowIfNull(path);
+        var bytes = new byte[Encoding.UTF8.GetByteCount(path) + 1];
+        Encoding.UTF8.GetBytes(path, bytes);
+        return bytes;
+    }
+
+    /// <summary>Version of the Chassis library</summary>
+    public static string Version =>
+        System.Runtime.InteropServices.Marshal.PtrToStringUTF8((nint)NativeMethods.Version()) ?? string.Empty;
+
+    /// <summary>Number of vectors in the index</summary>
+    public long Count => (long)NativeMethods.Len(_handle);
+
+    /// <summary>Whether the index holds no vectors</summary>
+    public bool IsEmpty => Count == 0;
+
+    /// <summary>Number of values in each vector</summary>
+    public int Dimensions => (int)NativeMethods.Dimensions(_handle);
+
+    /// <summary>Add a vector and return its ID</summary>
+    /// <remarks>The vector is not durable until the next <see cref="Flush"/>.</remarks>
+    /// <exception cref="ChassisException">
+    /// <paramref name="vector"/> has the wrong length or the file cannot grow.
+    /// </exception>
+    public ulong Add(ReadOnlySpan<float> vector)
+    {
+        ulong id;
+        fixed (float* data = vector)
+        {
+            id = NativeMethods.Add(_handle, data, (nuint)vector.Length);
+        }
+        if (id == ulong.MaxValue)
+        {
+            throw ChassisException.LastOrUnknown();
+        }
+        return id;
+    }
+
+    /// <summary>Add vectors stored row after row in <paramref name="rows"/> and return their IDs, in order</summary>
+    /// <remarks>
+    /// Stops at the first vector that fails; the vectors before it stay in the index.
+    /// </remarks>
+    /// <exception cref="ChassisException">
+    /// The length of <paramref name="rows"/> is not a multiple of
+    /// <see cref="Dimensions"/>, or for the same reasons as <see cref="Add(ReadOnlySpan{float})"/>.
+    /// </exception>
+    public ulong[] AddBatch(ReadOnlySpan<float> rows)
+    {
+        var ids = new ulong[RowCount(rows)];
+        AddBatch(rows, ids);
+        return ids;
+    }
+
+    /// <summary>Add vectors stored row after row in <paramref name="rows"/>, writing their IDs to <paramref name="ids"/></summary>
+    /// <exception cref="ChassisException">
+    /// <paramref name="ids"/> does not have one slot per row, or for the same
+    /// reasons as <see cref="AddBatch(ReadOnlySpan{float})"/>.
+    /// </exception>
+    public void AddBatch(ReadOnlySpan<float> rows, Span<ulong> ids)
+    {
+        var count = RowCount(rows);
+        if (ids.Length != count)
+        {
+            throw new ChassisException(
+                ChassisErrorCode.InvalidArgument,
+                $"Batch of {count} vectors needs {count} ID slots, got {ids.Length}");
+        }
+        if (count == 0)
+        {
+            return;
+        }
+
+        nuint added;
+        fixed (float* data = rows)
+        fixed (ulong* outIds = ids)
+        {
+            added = NativeMethods.AddBatch(_handle, data, (nuint)count, (nuint)Dimensions, outIds);
+        }
+        if (added != (nuint)count)
+        {
+            throw ChassisException.LastOrUnknown();
+        }
+    }
+
+    private int RowCount(ReadOnlySpan<float> rows)
+    {
+        var dims = Dimensions;
+        if (rows.Length % dims != 0)
+        {
+            throw new ChassisException(
+                ChassisErrorCode.DimensionMismatch,
+                $"Batch of {rows.Length} values is not a whole number of {dims}-dimensional vectors");
+        }
+        return rows.Length / dims;
+    }
+
+    /// <summary>Find the <paramref name="k"/> nearest neighbors of <paramref name="query"/>, closest first</summary>
+    /// <exception cref="ChassisException"><paramref name="query"/> has the wrong length or <paramref name="k"/> is 0.</exception>
+    public SearchResult[] Search(ReadOnlySpan<float> query, int k)
+    {
+        if (k <= 0)
+        {
+            throw new ChassisException(ChassisErrorCode.InvalidArgument, "k must be > 0");
+        }
+
+        var ids = new ulong[k];
+        var distances = new float[k];
+        var found = Search(query, ids, distances);
+        var results = new SearchResult[found];
+        for (var i = 0; i < found; i++)
+        {
+            results[i] = new SearchResult(ids[i], distances[i]);
+        }
+        return results;
+    }
+
+    /// <summary>
+    /// Find up to <c>ids.Length</c> nearest neighbors of <paramref name="query"/>
+    /// without allocating, and return how many were written
+    /// </summary>
+    /// <exception cref="ChassisException">
+    /// <paramref name="query"/> has the wrong length, <paramref name="ids"/> is
+    /// empty, or <paramref name="distances"/> is shorter than <paramref name="ids"/>.
+    /// </exception>
+    public int Search(ReadOnlySpan<float> query, Span<ulong> ids, Span<float> distances)
+    {
+        if (distances.Length < ids.Length)
+        {
+            throw new ChassisException(
+                ChassisErrorCode.InvalidArgument,
+                $"Distance buffer holds {distances.Length} values, need {ids.Length}");
+        }
+
+        nuint found;
+        fixed (float* data = query)
+        fixed (ulong* outIds = ids)
+        fixed (float* outDists = distances)
+        {
+            found = NativeMethods.Search(_handle, data, (nuint)query.Length, (nuint)ids.Length, outIds, outDists);
+        }
+        // 0 results is also the answer for an empty index
+        if (found == 0 && ChassisException.Last() is { } error)
+        {
+            throw error;
+        }
+        return (int)found;
+    }
+
+    /// <summary>Delete a vector, returning <c>false</c> if it was already deleted</summary>
+    /// <exception cref="ChassisException"><paramref name="id"/> was never assigned or the index is read-only.</exception>
+    public bool Delete(ulong id)
+    {
+        var status = NativeMethods.Delete(_handle, id);
+        if (status < 0)
+        {
+            throw ChassisException.LastOrUnknown();
+        }
+        return status == 1;
+    }
+
+    /// <summary>Replace the vector stored under <paramref name="id"/> and return its new ID</summary>
+    /// <exception cref="ChassisException">
+    /// <paramref name="id"/> is missing or deleted, or for the same reasons as
+    /// <see cref="Add(ReadOnlySpan{float})"/>.
+    /// </exception>
+    public ulong Update(ulong id, ReadOnlySpan<float> vector)
+    {
+        ulong newId;
+        fixed (float* data = vector)
+        {
+            newId = NativeMethods.Update(_handle, id, data, (nuint)vector.Length);
+        }
+        if (newId == ulong.MaxValue)
+        {
+            throw ChassisException.LastOrUnknown();
+        }
+        return newId;
+    }
+
+    /// <summary>Copy of the vector stored under <paramref name="id"/></summary>
+    /// <exception cref="ChassisException"><paramref name="id"/> is missing or deleted.</exception>
+    public float[] GetVector(ulong id)
+    {
+        var vector = new float[Dimensions];
+        GetVector(id, vector);
+        return vector;
+    }
+
+    /// <summary>Write the vector stored under <paramref name="id"/> into <paramref name="destination"/></summary>
+    /// <exception cref="ChassisException">
+    /// <paramref name="id"/> is missing or deleted, or <paramref name="destination"/>
+    /// is shorter than <see cref="Dimensions"/>.
+    /// </exception>
+    public void GetVector(ulong id, Span<float> destination)
+    {
+        nuint written;
+        fixed (float* data = destination)
+        {
+            written = NativeMethods.GetVector(_handle, id, data, (nuint)destination.Length);
+        }
+        if (written == 0)
+        {
+            throw ChassisException.LastOrUnknown();
+        }
+    }
+
+    /// <summary>Make every added vector durable</summary>
+    /// <exception cref="ChassisException">The index is read-only or the write fails.</exception>
+    public void Flush()
+    {
+        if (NativeMethods.Flush(_handle) != 0)
+        {
+            throw ChassisException.LastOrUnknown();
+        }
+    }
+
+    /// <summary>Write a crash-consistent copy of the index to <paramref name="path"/> without closing it</summary>
+    /// <exception cref="ChassisException">The flush or the copy fails.</exception>
+    public void SnapshotTo(string path)
+    {
+        int status;
+        fixed (byte* utf8 = Utf8Path(path))
+        {
+            status = NativeMethods.SnapshotTo(_handle, utf8);
+        }
+        if (status != 0)
+        {
+            throw ChassisException.LastOrUnknown();
+        }
+    }
+
+    /// <summary>Close the index and release its file lock</summary>
+    /// <remarks>Call <see cref="Flush"/> first; closing does not make added vectors durable.</remarks>
+    public void Dispose() => _handle.Dispose();
+}
@@ -0,0 +1,136 @@
+using Chassis;
+using Xunit;
+
+namespace Chassis.Tests;
+
+public sealed class VectorIndexTests : IDisposable
+{
+    private readonly string _directory =
+        Directory.CreateDirectory(Path.Combine(Path.GetTempPath(), Guid.NewGuid().ToString())).FullName;
+
+    public void Dispose() => Directory.Delete(_directory, recursive: true);
+
+    private string PathOf(string name) => Path.Combine(_directory, name);
+
+    [Fact]
+    public void AddSearchAndReopen()
+    {
+        using (var index = new VectorIndex(PathOf("test.chassis"), 4))
+        {
+            Assert.Equal(0UL, index.Add([1, 0, 0, 0]));
+            Assert.Equal(new ulong[] { 1, 2 }, index.AddBatch([0, 1, 0, 0, 0, 0, 1, 0]));
+            Assert.Equal(1UL, index.Search([0, 1, 0, 0], 1)[0].Id);
+            index.Flush();
+        }
+
+        // Disposing closes the file and frees its lock
+        using var reopened = new VectorIndex(PathOf("test.chassis"), 4);
+        Assert.Equal(3, reopened.Count);
+        Assert.Equal(4, reopened.Dimensions);
+    }
+
+    [Fact]
+    public void SpanOverloadsWriteIntoCallerBuffers()
+    {
+        using var index = new VectorIndex(PathOf("spans.chassis"), 4);
+        Span<ulong> added = stackalloc ulong[3];
+        index.AddBatch([1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0], added);
+        Assert.Equal(new ulong[] { 0, 1, 2 }, added.ToArray());
+
+        Span<ulong> ids = stackalloc ulong[8];
+        Span<float> distances = stackalloc float[8];
+        var found = index.Search([3, 0, 0, 0], ids, distances);
+        Assert.Equal(3, found);
+        Assert.Equal(2UL, ids[0]);
+        Assert.Equal(0f, distances[0]);
+
+        Span<float> vector = stackalloc float[4];
+        index.GetVector(1, vector);
+        Assert.Equal(new float[] { 2, 0, 0, 0 }, vector.ToArray());
+    }
+
+    [Fact]
+    public void DeleteAndUpdate()
+    {
+        using var index = new VectorIndex(PathOf("edit.chassis"), 4);
+        var first = index.Add([1, 0, 0, 0]);
+        var second = index.Add([0, 1, 0, 0]);
+
+        Assert.True(index.Delete(first));
+        Assert.False(index.Delete(first));
+        Assert.DoesNotContain(index.Search([1, 0, 0, 0], 2), result => result.Id == first);
+
+        var updated = index.Update(second, [0, 0, 1, 0]);
+        Assert.Equal(new float[] { 0, 0, 1, 0 }, index.GetVector(updated));
+        var error = Assert.Throws<ChassisException>(() => index.GetVector(second));
+        Assert.Equal(ChassisErrorCode.OutOfBounds, error.Code);
+    }
+
+    [Fact]
+    public void OpenWithOptions()
+    {
+        var options = new IndexOptions { Normalize = true, EfSearch = 100 };
+        using var index = new VectorIndex(PathOf("options.chassis"), 4, options);
+        index.Add([3, 0, 0, 0]);
+        Assert.Equal(new float[] { 1, 0, 0, 0 }, index.GetVector(0));
+    }
+
+    [Fact]
+    public void SnapshotOpensShared()
+    {
+        using var index = new VectorIndex(PathOf("source.chassis"), 4);
+        index.Add([1, 0, 0, 0]);
+        index.SnapshotTo(PathOf("copy.chassis"));
+
+        using var copy = VectorIndex.OpenShared(PathOf("copy.chassis"), 4);
+        Assert.Equal(1, copy.Count);
+        var error = Assert.Throws<ChassisException>(() => copy.Add([0, 1, 0, 0]));
+        Assert.Equal(ChassisErrorCode.ReadOnly, error.Code);
+    }
+
+    [Fact]
+    public void ErrorsCarryCodes()
+    {
+        using var index = new VectorIndex(PathOf("errors.chassis"), 4);
+        Assert.Empty(index.Search([0, 0, 0, 0], 5));
+
+        Assert.Equal(
+            ChassisErrorCode.DimensionMismatch,
+            Assert.Throws<ChassisException>(() => index.Add([1, 2, 3])).Code);
+        Assert.Equal(
+            ChassisErrorCode.Locked,
+            Assert.Throws<ChassisException>(() => new VectorIndex(PathOf("errors.chassis"), 4)).Code);
+        Assert.Equal(
+            ChassisErrorCode.InvalidArgument,
+            Assert.Throws<ChassisException>(() => new VectorIndex(PathOf("zero.chassis"), 0)).Code);
+    }
+
+    [Fact]
+    public void ConcurrentSearches()
+    {
+        using var index = new VectorIndex(PathOf("threads.chassis"), 4);
+        for (var i = 0; i < 100; i++)
+        {
+            index.Add([i, 0, 0, 0]);
+        }
+
+        Parallel.For(0, 8, i =>
+        {
+            if (i == 0)
+            {
+                for (var j = 100; j < 200; j++)
+                {
+                    index.Add([j, 0, 0, 0]);
+                }
+            }
+            else
+            {
+                for (var n = 0; n < 50; n++)
+                {
+                    Assert.Equal(42UL, index.Search([42, 0, 0, 0], 1)[0].Id);
+                }
+            }
+        });
+        Assert.Equal(200, index.Count);
+    }
+}
@@ -1,10 +1,12 @@
 /* Chassis Vector Storage Engine - C API */
-/* Version 0.1.0 */
+/* Check chassis_abi_version() == CHASSIS_ABI_VERSION before use */
 
 /* Thread Safety: */
 /* - chassis_open, chassis_free: Thread-safe if called with different indices */
-/* - chassis_add, chassis_add_batch, chassis_flush: Single-writer (exclusive access required) */
-/* - chassis_search: Multi-reader (shared access allowed) */
+/* - chassis_add, chassis_add_batch, chassis_delete, chassis_update, chassis_flush, chassis_snapshot_to, chassis_optimize: Single-writer (exclusive access required) */
+/* - chassis_search, chassis_get_vector: Multi-reader (shared access allowed) */
+/* - ChassisWriter (chassis_writer_*): one handle per index, calls serialized internally */
+/* - ChassisReader (chassis_reader_*): any number of threads, synchronized against writer remaps */
 
 
 #ifndef CHASSIS_H
@@ -20,6 +22,131 @@
 #include <stdint.h>
 #include <stdlib.h>
 
+/**
+ * Version of the C ABI described by `chassis.h`
+ *
+ * Bumped whenever a function signature, struct layout or enum value changes
+ * incompatibly; additions keep the version. Bindings compare the value they
+ * were generated against with `chassis_abi_version()` when they load the
+ * library.
+ */
+#define CHASSIS_ABI_VERSION 1
+
+/**
+ * Stable error codes reported by `chassis_last_error_code()`
+ *
+ * Values are part of the ABI: existing codes never change meaning and new
+ * codes are only ever appended. Callers should treat unknown values like
+ * `Unknown`.
+ */
+typedef enum ChassisErrorCode {
+  /**
+   * The last call on this thread succeeded
+   */
+  CHASSIS_ERROR_CODE_OK = 0,
+  /**
+   * An argument was out of range (zero dimensions, zero k, ...)
+   */
+  CHASSIS_ERROR_CODE_INVALID_ARGUMENT = 1,
+  /**
+   * A required pointer argument was NULL
+   */
+  CHASSIS_ERROR_CODE_NULL_POINTER = 2,
+  /**
+   * A string argument was not valid UTF-8
+   */
+  CHASSIS_ERROR_CODE_INVALID_UTF8 = 3,
+  /**
+   * Vector, query, or file dimensions did not match
+   */
+  CHASSIS_ERROR_CODE_DIMENSION_MISMATCH = 4,
+  /**
+   * The file is locked by another process or handle
+   */
+  CHASSIS_ERROR_CODE_LOCKED = 5,
+  /**
+   * The file is not a Chassis index or is corrupted
+   */
+  CHASSIS_ERROR_CODE_CORRUPTED = 6,
+  /**
+   * An operating system I/O call failed
+   */
+  CHASSIS_ERROR_CODE_IO = 7,
+  /**
+   * A vector ID or internal offset was out of range
+   */
+  CHASSIS_ERROR_CODE_OUT_OF_BOUNDS = 8,
+  /**
+   * The index file was deleted or replaced while open
+   */
+  CHASSIS_ERROR_CODE_FILE_STOLEN = 9,
+  /**
+   * A write was attempted through a shared (read-only) handle
+   */
+  CHASSIS_ERROR_CODE_READ_ONLY = 10,
+  /**
+   * A Rust panic was caught at the FFI boundary
+   */
+  CHASSIS_ERROR_CODE_PANIC = 11,
+  /**
+   * Any other failure
+   */
+  CHASSIS_ERROR_CODE_UNKNOWN = 12,
+  /**
+   * The file was written by a library version the caller's policy refuses
+   */
+  CHASSIS_ERROR_CODE_INCOMPATIBLE_VERSION = 13,
+  /**
+   * The index would grow past the limit set with `chassis_set_max_file_bytes()`
+   */
+  CHASSIS_ERROR_CODE_QUOTA_EXCEEDED = 14,
+  /**
+   * The progress callback returned nonzero and the operation stopped early
+   */
+  CHASSIS_ERROR_CODE_CANCELLED = 15,
+} ChassisErrorCode;
+
+/**
+ * On-disk encoding of vector components, for `ChassisOptions::element_type`
+ *
+ * Values are part of the ABI, like `ChassisErrorCode`.
+ */
+typedef enum ChassisElementType {
+  /**
+   * 32-bit floats
+   */
+  CHASSIS_ELEMENT_TYPE_F32 = 0,
+  /**
+   * IEEE 754 half precision
+   */
+  CHASSIS_ELEMENT_TYPE_F16 = 1,
+  /**
+   * bfloat16
+   */
+  CHASSIS_ELEMENT_TYPE_BF16 = 2,
+  /**
+   * One sign bit per component, compared by Hamming distance
+   */
+  CHASSIS_ELEMENT_TYPE_BINARY = 3,
+} ChassisElementType;
+
+/**
+ * Long-running operation reported to a progress callback, passed as its
+ * `stage` argument
+ *
+ * Values are part of the ABI, like `ChassisErrorCode`.
+ */
+typedef enum ChassisProgressStage {
+  /**
+   * Inserting the vectors of `chassis_add_batch()`; one unit per vector
+   */
+  CHASSIS_PROGRESS_STAGE_IMPORT = 0,
+  /**
+   * Rewriting neighbor lists in `chassis_optimize()`; one unit per node and pass
+   */
+  CHASSIS_PROGRESS_STAGE_OPTIMIZE = 1,
+} ChassisProgressStage;
+
 /**
  * Opaque handle to a Chassis index (C-compatible)
  *
@@ -31,6 +158,85 @@ typedef struct ChassisIndex {
   uint8_t _private[0];
 } ChassisIndex;
 
+/**
+ * Index configuration for `chassis_open_with_config()`
+ *
+ * Start from `chassis_options_default()` and change the fields you need, so
+ * code keeps compiling with the defaults when fields are added in a later
+ * ABI version.
+ */
+typedef struct ChassisOptions {
+  /**
+   * Maximum connections per node (M parameter, at most 65535). Default: 16
+   */
+  uint32_t max_connections;
+  /**
+   * Construction quality (efConstruction). Default: 200
+   */
+  uint32_t ef_construction;
+  /**
+   * Search quality (efSearch). Default: 50
+   */
+  uint32_t ef_search;
+  /**
+   * Maximum number of graph layers (1 to 16). Default: 16
+   */
+  uint32_t max_layers;
+  /**
+   * A `ChassisElementType` value. Default: `CHASSIS_ELEMENT_TYPE_F32`
+   */
+  uint32_t element_type;
+  /**
+   * Dimensionality of incoming vectors, truncated to `dimensions` on add
+   * and search; 0 stores vectors as given. Default: 0
+   */
+  uint32_t input_dimensions;
+  /**
+   * Scan every vector instead of searching the graph while the index holds
+   * at most this many. Default: 0 (always use the graph)
+   */
+  uint64_t exact_search_threshold;
+  /**
+   * Normalize vectors to unit length and compare them by cosine distance.
+   * Default: false
+   */
+  bool normalize;
+  /**
+   * Keep the graph in a second file next to the index. Default: false
+   */
+  bool graph_file;
+} ChassisOptions;
+
+/**
+ * Progress callback for `chassis_set_progress_callback()`
+ *
+ * Called with a `ChassisProgressStage` value, the units of work done, the
+ * total, and the `user_data` given when it was set. Return 0 to continue, or
+ * nonzero to stop the operation, which then fails with
+ * `CHASSIS_ERROR_CODE_CANCELLED`.
+ */
+typedef int (*ChassisProgressFn)(uint32_t stage, uint64_t done, uint64_t total, void *user_data);
+
+/**
+ * Opaque handle through which one thread at a time modifies an index
+ *
+ * Returned by `chassis_open_writer()`. Only the `chassis_writer_*` functions
+ * accept it; searches go through a `ChassisReader`.
+ */
+typedef struct ChassisWriter {
+  uint8_t _private[0];
+} ChassisWriter;
+
+/**
+ * Opaque, thread-safe handle for searching an index held by a `ChassisWriter`
+ *
+ * Returned by `chassis_open_reader_from()`. Only the `chassis_reader_*`
+ * functions accept it, and any number of threads may use one at once.
+ */
+typedef struct ChassisReader {
+  uint8_t _private[0];
+} ChassisReader;
+
 #ifdef __cplusplus
 extern "C" {
 #endif // __cplusplus
@@ -51,7 +257,7 @@ extern "C" {
  * # Thread Safety
  *
  * - Safe to call from multiple threads with different paths
- * - The returned index requires exclusive access for writes
+ * - The returned index serializes writes against searches internally
  *
  * # Example (C)
  *
@@ -99,6 +305,63 @@ struct ChassisIndex *chassis_open(const char *path, uint32_t dimensions);
  */
 struct ChassisIndex *chassis_open_with_options(const char *path, uint32_t dimensions, uint32_t max_connections, uint32_t ef_construction, uint32_t ef_search);
 
+/**
+ * Default options for `chassis_open_with_config()`
+ *
+ * # Example (C)
+ *
+ * ```c
+ * ChassisOptions options = chassis_options_default();
+ * options.element_type = CHASSIS_ELEMENT_TYPE_F16;
+ * options.normalize = true;
+ * ChassisIndex* index = chassis_open_with_config("vectors.chassis", 768, &options);
+ * ```
+ */
+struct ChassisOptions chassis_options_default(void);
+
+/**
+ * Open or create a Chassis vector index with a `ChassisOptions` struct
+ *
+ * # Arguments
+ *
+ * - `path`: UTF-8 encoded path to the index file (must not be NULL)
+ * - `dimensions`: Number of dimensions per vector (must be > 0)
+ * - `options`: Options from `chassis_options_default()`, possibly modified
+ *   (must not be NULL)
+ *
+ * # Returns
+ *
+ * - Non-NULL pointer on success
+ * - NULL on failure (check `chassis_last_error_message()`); an out-of-range
+ *   field reports `CHASSIS_ERROR_CODE_INVALID_ARGUMENT`
+ *
+ * # Safety
+ *
+ * Same safety requirements as `chassis_open()`; `options` must point to a
+ * valid `ChassisOptions` for the duration of this call
+ */
+struct ChassisIndex *chassis_open_with_config(const char *path, uint32_t dimensions, const struct ChassisOptions *options);
+
+/**
+ * Open an existing Chassis index read-only, shared with other processes
+ *
+ * Any number of processes may hold a shared handle at once (shared
+ * `LockFileEx` lock on Windows, `flock(LOCK_SH)` on Unix). While one does,
+ * `chassis_open()` fails, and this call fails while a writer holds the file.
+ * The index must have been created with default options.
+ *
+ * # Returns
+ *
+ * - Non-NULL pointer on success; `chassis_add`, `chassis_add_batch` and
+ *   `chassis_flush` report an error on it
+ * - NULL on failure (check `chassis_last_error_message()`)
+ *
+ * # Safety
+ *
+ * Same safety requirements as `chassis_open()`
+ */
+struct ChassisIndex *chassis_open_shared(const char *path, uint32_t dimensions);
+
 /**
  * Free a Chassis index and release all resources
  *
@@ -112,6 +375,7 @@ struct ChassisIndex *chassis_open_with_options(const char *path, uint32_t dimens
  * - After this call, `ptr` is invalid and must not be used
  * - Safe to call with NULL (no-op)
  * - Must not be called more than once with the same non-NULL pointer
+ * - No other thread may use `ptr` during or after this call
  *
  * # Example (C)
  *
@@ -127,7 +391,7 @@ void chassis_free(struct ChassisIndex *ptr);
  *
  * # Arguments
  *
- * - `ptr`: Non-NULL pointer to index (requires exclusive access)
+ * - `ptr`: Non-NULL pointer to index
  * - `vector`: Pointer to f32 array (must not be NULL)
  * - `len`: Number of elements in vector (must match index dimensions)
  *
@@ -138,8 +402,8 @@ void chassis_free(struct ChassisIndex *ptr);
  *
  * # Thread Safety
  *
- * **SINGLE-WRITER**: Only one thread may call this function at a time for a
- * given index. Concurrent writes will cause data corruption.
+ * **SINGLE-WRITER**: Takes the index's exclusive lock. Calls from other
+ * threads, reads included, wait until the vector is inserted.
  *
  * # Performance Note
  *
@@ -161,7 +425,7 @@ void chassis_free(struct ChassisIndex *ptr);
  * - `ptr` must be non-NULL and valid
  * - `vector` must point to `len` valid f32 values
  * - `len` must match the dimensions specified in `chassis_open()`
- * - No other thread may access `ptr` during this call
+ * - `ptr` must not be freed during this call
  */
 uint64_t chassis_add(struct ChassisIndex *ptr, const float *vector, size_t len);
 
@@ -170,7 +434,7 @@ uint64_t chassis_add(struct ChassisIndex *ptr, const float *vector, size_t len);
  *
  * # Arguments
  *
- * - `ptr`: Non-NULL pointer to index (requires exclusive access)
+ * - `ptr`: Non-NULL pointer to index
  * - `vectors`: Contiguous `count * dim` floats: row `i` is
  *   `vectors[i*dim .. (i+1)*dim]`
  * - `count`: Number of vectors to insert
@@ -182,6 +446,8 @@ uint64_t chassis_add(struct ChassisIndex *ptr, const float *vector, size_t len);
  * - Number of vectors successfully inserted
  * - On first error, stops and returns the count inserted so far; use
  *   `chassis_last_error_message()` for the reason
+ * - If the progress callback asks to stop, returns the count inserted so far
+ *   with `CHASSIS_ERROR_CODE_CANCELLED`
  * - If `count == 0`, returns `0` and succeeds (pointers need not be valid)
  *
  * # Thread Safety
@@ -210,7 +476,7 @@ uint64_t chassis_add(struct ChassisIndex *ptr, const float *vector, size_t len);
  * - If `count > 0`, `vectors` and `out_ids` must be non-NULL; `vectors` must point
  *   to `count * dim` valid floats
  * - `dim` must match dimensions passed to `chassis_open()`
- * - No other thread may access `ptr` during this call
+ * - `ptr` must not be freed during this call
  */
 size_t chassis_add_batch(struct ChassisIndex *ptr, const float *vectors, size_t count, size_t dim, uint64_t *out_ids);
 
@@ -234,7 +500,8 @@ size_t chassis_add_batch(struct ChassisIndex *ptr, const float *vectors, size_t
  * # Thread Safety
  *
  * **MULTI-READER**: Multiple threads may call this function concurrently
- * on the same index. Reads do not block other reads.
+ * on the same index. Reads do not block other reads; they wait for a write
+ * in progress.
  *
  * # Output Format
  *
@@ -270,7 +537,7 @@ size_t chassis_search(const struct ChassisIndex *ptr, const float *query, size_t
  *
  * # Arguments
  *
- * - `ptr`: Non-NULL pointer to index (requires exclusive access)
+ * - `ptr`: Non-NULL pointer to index
  *
  * # Returns
  *
@@ -279,8 +546,8 @@ size_t chassis_search(const struct ChassisIndex *ptr, const float *query, size_t
  *
  * # Thread Safety
  *
- * **SINGLE-WRITER**: Only one thread may call this function at a time for a
- * given index. No other operations (read or write) may occur during flush.
+ * **SINGLE-WRITER**: Other writes wait for the flush to complete. Searches
+ * only wait while the changes are handed to the OS, not for the `fsync`.
  *
  * # Performance Warning
  *
@@ -304,10 +571,141 @@ size_t chassis_search(const struct ChassisIndex *ptr, const float *query, size_t
  * # Safety
  *
  * - `ptr` must be non-NULL and valid
- * - No other thread may access `ptr` during this call
+ * - `ptr` must not be freed during this call
  */
 int chassis_flush(struct ChassisIndex *ptr);
 
+/**
+ * Write a crash-consistent copy of the index to `path` without closing it
+ *
+ * Flushes first (unless the handle came from `chassis_open_shared`), then
+ * copies the file while the handle keeps its lock. The copy is staged in a
+ * temporary file and renamed into place, so `path` never holds a partial
+ * snapshot. Uses a reflink (copy-on-write clone) where the filesystem allows.
+ *
+ * # Arguments
+ *
+ * - `ptr`: Non-NULL pointer to index
+ * - `path`: Null-terminated UTF-8 destination path
+ *
+ * # Returns
+ *
+ * - 0 on success
+ * - -1 on failure (check `chassis_last_error_message()`)
+ *
+ * # Thread Safety
+ *
+ * **SINGLE-WRITER**: Same rules as `chassis_flush()`.
+ *
+ * # Example (C)
+ *
+ * ```c
+ * if (chassis_snapshot_to(index, "backup/vectors.chassis") != 0) {
+ *     fprintf(stderr, "Backup failed: %s\n", chassis_last_error_message());
+ * }
+ * ```
+ *
+ * # Safety
+ *
+ * - `ptr` must be non-NULL and valid
+ * - `path` must be a valid null-terminated C string
+ * - `ptr` must not be freed during this call
+ */
+int chassis_snapshot_to(struct ChassisIndex *ptr, const char *path);
+
+/**
+ * Delete a vector so that searches no longer return it
+ *
+ * # Arguments
+ *
+ * - `ptr`: Non-NULL pointer to index
+ * - `id`: ID of the vector to delete
+ *
+ * # Returns
+ *
+ * - `1` if the vector was deleted
+ * - `0` if it was already deleted
+ * - `-1` on failure (check `chassis_last_error_message()`); an unknown ID
+ *   reports `CHASSIS_ERROR_CODE_OUT_OF_BOUNDS`
+ *
+ * IDs are never reused. Like `chassis_add`, the deletion becomes durable on
+ * the next `chassis_flush()`.
+ *
+ * # Thread Safety
+ *
+ * **SINGLE-WRITER**: Takes the index's exclusive lock.
+ *
+ * # Safety
+ *
+ * - `ptr` must be non-NULL and valid
+ * - `ptr` must not be freed during this call
+ */
+int chassis_delete(struct ChassisIndex *ptr, uint64_t id);
+
+/**
+ * Replace a vector, returning the ID of the replacement
+ *
+ * # Arguments
+ *
+ * - `ptr`: Non-NULL pointer to index
+ * - `id`: ID of the vector to replace
+ * - `vector`: Pointer to f32 array (must not be NULL)
+ * - `len`: Number of elements in vector (must match index dimensions)
+ *
+ * # Returns
+ *
+ * - ID of the new vector on success; `id` is deleted
+ * - `UINT64_MAX` on failure (check `chassis_last_error_message()`); a
+ *   missing or deleted `id` reports `CHASSIS_ERROR_CODE_OUT_OF_BOUNDS`
+ *
+ * The graph has no in-place updates, so the replacement always gets a new
+ * ID. Durable on the next `chassis_flush()`.
+ *
+ * # Thread Safety
+ *
+ * **SINGLE-WRITER**: Takes the index's exclusive lock.
+ *
+ * # Safety
+ *
+ * - `ptr` must be non-NULL and valid
+ * - `vector` must point to `len` valid f32 values
+ * - `ptr` must not be freed during this call
+ */
+uint64_t chassis_update(struct ChassisIndex *ptr, uint64_t id, const float *vector, size_t len);
+
+/**
+ * Copy a stored vector into a caller-provided buffer
+ *
+ * # Arguments
+ *
+ * - `ptr`: Non-NULL pointer to index (shared access)
+ * - `id`: ID of the vector to read
+ * - `out_buf`: Output buffer (must not be NULL)
+ * - `len`: Capacity of `out_buf` in floats (at least `chassis_dimensions()`)
+ *
+ * # Returns
+ *
+ * - Number of floats written (the index dimensions) on success
+ * - `0` on failure (check `chassis_last_error_message()`); a missing or
+ *   deleted `id` reports `CHASSIS_ERROR_CODE_OUT_OF_BOUNDS`, a short buffer
+ *   `CHASSIS_ERROR_CODE_INVALID_ARGUMENT`
+ *
+ * The vector is returned as stored: truncated to the index dimensions and
+ * normalized if the index normalizes, decoded to f32 whatever the element
+ * type.
+ *
+ * # Thread Safety
+ *
+ * **MULTI-READER**: Runs concurrently with searches.
+ *
+ * # Safety
+ *
+ * - `ptr` must be non-NULL and valid
+ * - `out_buf` must have space for `len` f32 values
+ * - `ptr` must not be freed during this call
+ */
+size_t chassis_get_vector(const struct ChassisIndex *ptr, uint64_t id, float *out_buf, size_t len);
+
 /**
  * Get the number of vectors in the index
  *
@@ -363,6 +761,286 @@ int chassis_is_empty(const struct ChassisIndex *ptr);
  */
 uint32_t chassis_dimensions(const struct ChassisIndex *ptr);
 
+/**
+ * Limit the size the index file may grow to
+ *
+ * # Arguments
+ *
+ * - `ptr`: Non-NULL pointer to index
+ * - `max_file_bytes`: Limit in bytes for the index file and its graph file
+ *   together, or `0` for no limit (the default)
+ *
+ * # Returns
+ *
+ * - 0 on success
+ * - -1 if `ptr` is NULL
+ *
+ * Once set, an add that needs the file to grow past the limit fails with
+ * `CHASSIS_ERROR_CODE_QUOTA_EXCEEDED` and leaves the index unchanged, so
+ * apps can keep to a storage budget instead of running out of disk space.
+ *
+ * # Thread Safety
+ *
+ * **SINGLE-WRITER**: Takes the index's exclusive lock.
+ *
+ * # Safety
+ *
+ * - `ptr` must be non-NULL and valid
+ * - `ptr` must not be freed during this call
+ */
+int chassis_set_max_file_bytes(struct ChassisIndex *ptr, uint64_t max_file_bytes);
+
+/**
+ * Report the progress of long operations on this index, and let the host
+ * cancel them
+ *
+ * # Arguments
+ *
+ * - `ptr`: Non-NULL pointer to index
+ * - `callback`: Called with the stage, work done, total and `user_data`, or
+ *   NULL to remove the callback
+ * - `user_data`: Passed to every call unchanged; may be NULL
+ *
+ * # Returns
+ *
+ * - 0 on success
+ * - -1 if `ptr` is NULL
+ *
+ * `chassis_add_batch()` and `chassis_optimize()` call the callback when they
+ * start, every 64 units of work and when they finish, so GUI hosts can show
+ * a progress bar. Returning nonzero stops the operation before the next unit:
+ * it fails with `CHASSIS_ERROR_CODE_CANCELLED` and keeps the work done so far.
+ * The return value of the final call, made when the work is complete, is
+ * ignored.
+ *
+ * # Thread Safety
+ *
+ * **SINGLE-WRITER**: Takes the index's exclusive lock to set the callback.
+ * The callback runs on whichever thread performs the operation, while that
+ * thread holds the index lock: it must not call `chassis_*` functions on the
+ * same index, and should hand UI updates to the UI thread.
+ *
+ * # Example (C)
+ *
+ * ```c
+ * int on_progress(uint32_t stage, uint64_t done, uint64_t total, void *user_data) {
+ *     struct job *job = user_data;
+ *     atomic_store(&job->percent, total ? (int)(100 * done / total) : 100);
+ *     return atomic_load(&job->cancel_requested);
+ * }
+ *
+ * chassis_set_progress_callback(index, on_progress, &job);
+ * size_t added = chassis_add_batch(index, batch, n, 768, ids);
+ * if (added < n && chassis_last_error_code() == CHASSIS_ERROR_CODE_CANCELLED) {
+ *     // the first `added` vectors are in the index
+ * }
+ * ```
+ *
+ * # Safety
+ *
+ * - `ptr` must be non-NULL and valid
+ * - `callback` and `user_data` must stay valid, and be safe to use from any
+ *   thread that calls into the index, until the callback is replaced or the
+ *   index is freed
+ */
+int chassis_set_progress_callback(struct ChassisIndex *ptr, ChassisProgressFn callback, void *user_data);
+
+/**
+ * Improve search quality by selecting every node's neighbors again
+ *
+ * # Arguments
+ *
+ * - `ptr`: Non-NULL pointer to index
+ * - `iterations`: Passes over the graph; 1 is usually enough
+ *
+ * # Returns
+ *
+ * - Number of nodes whose neighbors changed
+ * - -1 on failure (check `chassis_last_error_message()`), including
+ *   `CHASSIS_ERROR_CODE_CANCELLED` if the progress callback stopped it
+ *
+ * Nodes added early chose their neighbors among the few vectors present
+ * then; a pass rewrites their neighbor lists against the whole index. A pass
+ * costs about as much as adding every vector again, so run it when the app
+ * is idle, with a progress callback for long runs, and call `chassis_flush()`
+ * afterwards. A cancelled pass keeps the lists it already rewrote.
+ *
+ * # Thread Safety
+ *
+ * **SINGLE-WRITER**: Takes the index's exclusive lock; searches wait until
+ * it returns.
+ *
+ * # Safety
+ *
+ * - `ptr` must be non-NULL and valid
+ * - `ptr` must not be freed during this call
+ */
+int64_t chassis_optimize(struct ChassisIndex *ptr, uint32_t iterations);
+
+/**
+ * Open or create an index behind a single-writer handle
+ *
+ * Unlike `chassis_open()`, the handle cannot be passed to searches: create
+ * readers with `chassis_open_reader_from()`. Writes take the index lock
+ * exclusively for one call, so readers never observe a remap, and writer
+ * calls from several threads are serialized.
+ *
+ * # Arguments
+ *
+ * - `path`: UTF-8 encoded path to the index file (must not be NULL)
+ * - `dimensions`: Number of dimensions per vector (must be > 0)
+ * - `options`: Options from `chassis_options_default()`, or NULL for the defaults
+ *
+ * # Returns
+ *
+ * - Non-NULL pointer on success; free it with `chassis_writer_free()`
+ * - NULL on failure (check `chassis_last_error_message()`)
+ *
+ * # Example (C)
+ *
+ * ```c
+ * ChassisWriter* writer = chassis_open_writer("vectors.chassis", 768, NULL);
+ * ChassisReader* reader = chassis_open_reader_from(writer);
+ * // Hand `reader` to search threads; keep `writer` on the ingest thread
+ * ```
+ *
+ * # Safety
+ *
+ * Same safety requirements as `chassis_open_with_config()`, except that
+ * `options` may be NULL
+ */
+struct ChassisWriter *chassis_open_writer(const char *path, uint32_t dimensions, const struct ChassisOptions *options);
+
+/**
+ * Create a reader for the index held by `writer`
+ *
+ * Each reader is independent: free it with `chassis_reader_free()`. The
+ * index stays open until the writer and every reader have been freed, so a
+ * reader outlives its writer safely (it then sees the final state).
+ *
+ * # Returns
+ *
+ * - Non-NULL pointer on success
+ * - NULL if `writer` is NULL
+ *
+ * # Safety
+ *
+ * - `writer` must be NULL or a valid pointer from `chassis_open_writer()`
+ */
+struct ChassisReader *chassis_open_reader_from(const struct ChassisWriter *writer);
+
+/**
+ * Add a vector through a writer; see `chassis_add()`
+ *
+ * # Returns
+ *
+ * - Vector ID on success; readers see it once this returns
+ * - `UINT64_MAX` on failure (check `chassis_last_error_message()`)
+ *
+ * # Safety
+ *
+ * - `writer` must be non-NULL and valid, and not freed during this call
+ * - `vector` must point to `len` valid f32 values
+ */
+uint64_t chassis_writer_add(struct ChassisWriter *writer, const float *vector, size_t len);
+
+/**
+ * Delete a vector through a writer; see `chassis_delete()`
+ *
+ * # Returns
+ *
+ * - `1` if the vector was deleted, `0` if it was already deleted
+ * - `-1` on failure (check `chassis_last_error_message()`)
+ *
+ * # Safety
+ *
+ * - `writer` must be non-NULL and valid, and not freed during this call
+ */
+int chassis_writer_delete(struct ChassisWriter *writer, uint64_t id);
+
+/**
+ * Flush all changes made through a writer to disk; see `chassis_flush()`
+ *
+ * # Returns
+ *
+ * - 0 on success
+ * - -1 on failure (check `chassis_last_error_message()`)
+ *
+ * # Safety
+ *
+ * - `writer` must be non-NULL and valid, and not freed during this call
+ */
+int chassis_writer_flush(struct ChassisWriter *writer);
+
+/**
+ * Free a writer
+ *
+ * Readers created from it stay valid; the index closes once they are freed
+ * too. Unflushed changes are not written: call `chassis_writer_flush()` first.
+ *
+ * # Safety
+ *
+ * - `writer` must be NULL or a valid pointer from `chassis_open_writer()`
+ * - No other thread may use `writer` during or after this call
+ */
+void chassis_writer_free(struct ChassisWriter *writer);
+
+/**
+ * Search through a reader; see `chassis_search()`
+ *
+ * # Thread Safety
+ *
+ * **MULTI-READER**: Any number of threads may search through one reader, or
+ * through several, while the writer inserts. A search waits for at most one
+ * write call in progress.
+ *
+ * # Safety
+ *
+ * - `reader` must be non-NULL and valid, and not freed during this call
+ * - Same buffer requirements as `chassis_search()`
+ */
+size_t chassis_reader_search(const struct ChassisReader *reader, const float *query, size_t len, size_t k, uint64_t *out_ids, float *out_dists);
+
+/**
+ * Copy a stored vector through a reader; see `chassis_get_vector()`
+ *
+ * # Safety
+ *
+ * - `reader` must be non-NULL and valid, and not freed during this call
+ * - `out_buf` must have space for `len` f32 values
+ */
+size_t chassis_reader_get_vector(const struct ChassisReader *reader, uint64_t id, float *out_buf, size_t len);
+
+/**
+ * Get the number of vectors visible to a reader, or 0 if `reader` is NULL
+ *
+ * Never waits for a write in progress.
+ *
+ * # Safety
+ *
+ * - `reader` must be NULL or valid
+ */
+uint64_t chassis_reader_len(const struct ChassisReader *reader);
+
+/**
+ * Get the dimensionality of vectors in a reader's index, or 0 if `reader` is NULL
+ *
+ * # Safety
+ *
+ * - `reader` must be NULL or valid
+ */
+uint32_t chassis_reader_dimensions(const struct ChassisReader *reader);
+
+/**
+ * Free a reader
+ *
+ * # Safety
+ *
+ * - `reader` must be NULL or a valid pointer from `chassis_open_reader_from()`
+ * - No other thread may use `reader` during or after this call
+ */
+void chassis_reader_free(struct ChassisReader *reader);
+
 /**
  * Get the last error message for the current thread
  *
@@ -397,6 +1075,30 @@ uint32_t chassis_dimensions(const struct ChassisIndex *ptr);
  */
 const char *chassis_last_error_message(void);
 
+/**
+ * Get the last error code for the current thread
+ *
+ * # Returns
+ *
+ * - `CHASSIS_ERROR_CODE_OK` if the last call on this thread succeeded
+ * - Otherwise the category of the failure; the message from
+ *   `chassis_last_error_message()` carries the details
+ *
+ * Codes are stable across releases, so callers can branch on them instead of
+ * parsing messages. Like the message, the code is per-thread and reflects the
+ * most recent failing or clearing call.
+ *
+ * # Example (C)
+ *
+ * ```c
+ * ChassisIndex* index = chassis_open("vectors.chassis", 768);
+ * if (index == NULL && chassis_last_error_code() == CHASSIS_ERROR_CODE_LOCKED) {
+ *     index = chassis_open_shared("vectors.chassis", 768);
+ * }
+ * ```
+ */
+enum ChassisErrorCode chassis_last_error_code(void);
+
 /**
  * Get the Chassis library version
  *
@@ -417,6 +1119,26 @@ const char *chassis_last_error_message(void);
  */
 const char *chassis_version(void);
 
+/**
+ * Get the version of the C ABI implemented by the loaded library
+ *
+ * # Returns
+ *
+ * `CHASSIS_ABI_VERSION` of the library build. Bindings should refuse to use
+ * a library whose ABI version differs from the `CHASSIS_ABI_VERSION` of the
+ * header they were built against.
+ *
+ * # Example (C)
+ *
+ * ```c
+ * if (chassis_abi_version() != CHASSIS_ABI_VERSION) {
+ *     fprintf(stderr, "libchassis_ffi ABI mismatch\n");
+ *     exit(1);
+ * }
+ * ```
+ */
+uint32_t chassis_abi_version(void);
+
 #ifdef __cplusplus
 }  // extern "C"
 #endif  // __cplusplus
@@ -13,29 +13,125 @@
 //! # Error Handling
 //!
 //! Errors are reported through:
-//! - Return values: `u64::MAX` for add, `size_t` insert count for `chassis_add_batch`
+//! - Return values: `u64::MAX` for add and update, `size_t` insert count for `chassis_add_batch`
 //!   (on partial failure, less than requested; on total failure of a non-empty batch, `0`),
-//!   `0` for search, `-1` for flush
+//!   `0` for search and get_vector, `-1` for flush and delete
 //! - Thread-local error message: `chassis_last_error_message()`
+//! - Thread-local error code: `chassis_last_error_code()` (stable `ChassisErrorCode` values)
 //!
 //! # Thread Safety
 //!
-//! - Single-writer: `chassis_add`, `chassis_add_batch`, `chassis_flush` require exclusive access
-//! - Multi-reader: `chassis_search` allows concurrent readers
+//! - Every function except `chassis_free` may be called from any thread at any time
+//! - Single-writer: `chassis_add`, `chassis_add_batch`, `chassis_delete`, `chassis_update`,
+//!   `chassis_flush`, `chassis_snapshot_to`, `chassis_optimize` take an exclusive lock,
+//!   waiting for in-flight searches (which may hold slices of a mapping the write grows and
+//!   remaps) and blocking new ones until done
+//! - Reads are not lock-free: a search waits for the whole write in progress, such as all
+//!   of `chassis_add_batch` or `chassis_optimize` (but not `chassis_flush`'s `fsync`), and
+//!   whether new searches queue behind a waiting writer depends on the platform's
+//!   reader-writer lock. `ChassisReader`/`ChassisWriter` block the same way, one write call
+//!   at a time
+//! - Progress callbacks run on the thread performing the operation, inside its lock
+//! - Multi-reader: `chassis_search`, `chassis_get_vector` and the accessors share the lock
+//!   and run concurrently
 //! - Each thread has its own error message storage
-
-use chassis_core::{IndexOptions, VectorIndex};
-use libc::{c_char, c_float, c_int, size_t};
-use std::cell::RefCell;
+//! - `chassis_open_writer` and `chassis_open_reader_from` hand out distinct `ChassisWriter`
+//!   and `ChassisReader` types instead, so the single-writer/multi-reader split is checked by
+//!   the C compiler rather than by convention
+
+use chassis_core::{
+    ElementType, ErrorKind, IndexOptions, ProgressHandler, ProgressStage, ReadHandle, VectorIndex,
+    WriteHandle,
+};
+use libc::{c_char, c_float, c_int, c_void, size_t};
+use std::cell::{Cell, RefCell};
 use std::ffi::{CStr, CString};
 use std::ptr;
 use std::slice;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 /// Internal state holder (not exposed to C)
 ///
-/// This holds the actual VectorIndex and is purely Rust-internal.
+/// This holds the actual VectorIndex and is purely Rust-internal. Writers take
+/// the lock exclusively, so a write that grows and remaps the file waits for
+/// in-flight searches to drop their slices of the old mapping.
 struct ChassisIndexState {
-    inner: VectorIndex,
+    inner: RwLock<VectorIndex>,
+
+    /// Held by every write for its whole duration, so no other write runs
+    /// while `chassis_flush` syncs with `inner` released
+    writer: Mutex<()>,
+
+    /// Callback set with `chassis_set_progress_callback()`, for the loops that
+    /// run here rather than in the core (`chassis_add_batch`)
+    progress: Mutex<Option<ProgressCallback>>,
+}
+
+impl ChassisIndexState {
+    fn new(index: VectorIndex) -> Self {
+        Self { inner: RwLock::new(index), writer: Mutex::new(()), progress: Mutex::new(None) }
+    }
+
+    fn progress(&self) -> Option<ProgressCallback> {
+        *self.progress.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Shared access for searches and accessors
+    ///
+    /// A panic caught while a writer held the lock leaves the index in a state
+    /// its own consistency protocol tolerates, so poisoning is ignored.
+    fn read(&self) -> RwLockReadGuard<'_, VectorIndex> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Exclusive access for writes
+    fn write(&self) -> WriteGuard<'_> {
+        let writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        WriteGuard {
+            index: self.inner.write().unwrap_or_else(PoisonError::into_inner),
+            _writer: writer,
+        }
+    }
+
+    /// Flush without holding the index lock across `fsync`, so searches run meanwhile
+    fn flush(&self) -> anyhow::Result<()> {
+        let _writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        VectorIndex::flush_locked(&self.inner)
+    }
+}
+
+/// Exclusive access to the index, keeping other writes out
+struct WriteGuard<'a> {
+    index: RwLockWriteGuard<'a, VectorIndex>,
+    _writer: MutexGuard<'a, ()>,
+}
+
+impl std::ops::Deref for WriteGuard<'_> {
+    type Target = VectorIndex;
+
+    fn deref(&self) -> &VectorIndex {
+        &self.index
+    }
+}
+
+impl std::ops::DerefMut for WriteGuard<'_> {
+    fn deref_mut(&mut self) -> &mut VectorIndex {
+        &mut self.index
+    }
+}
+
+/// Internal state behind a `ChassisWriter`
+///
+/// The mutex serializes writer calls made from several threads; readers never
+/// take it, only the index lock inside the `WriteHandle`.
+struct ChassisWriterState {
+    inner: Mutex<WriteHandle>,
+}
+
+impl ChassisWriterState {
+    fn lock(&self) -> MutexGuard<'_, WriteHandle> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
 }
 
 /// Opaque handle to a Chassis index (C-compatible)
@@ -48,35 +144,297 @@ pub struct ChassisIndex {
     _private: [u8; 0],
 }
 
+/// Opaque handle through which one thread at a time modifies an index
+///
+/// Returned by `chassis_open_writer()`. Only the `chassis_writer_*` functions
+/// accept it; searches go through a `ChassisReader`.
+#[repr(C)]
+pub struct ChassisWriter {
+    _private: [u8; 0],
+}
+
+/// Opaque, thread-safe handle for searching an index held by a `ChassisWriter`
+///
+/// Returned by `chassis_open_reader_from()`. Only the `chassis_reader_*`
+/// functions accept it, and any number of threads may use one at once.
+#[repr(C)]
+pub struct ChassisReader {
+    _private: [u8; 0],
+}
+
+/// Stable error codes reported by `chassis_last_error_code()`
+///
+/// Values are part of the ABI: existing codes never change meaning and new
+/// codes are only ever appended. Callers should treat unknown values like
+/// `Unknown`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChassisErrorCode {
+    /// The last call on this thread succeeded
+    Ok = 0,
+
+    /// An argument was out of range (zero dimensions, zero k, ...)
+    InvalidArgument = 1,
+
+    /// A required pointer argument was NULL
+    NullPointer = 2,
+
+    /// A string argument was not valid UTF-8
+    InvalidUtf8 = 3,
+
+    /// Vector, query, or file dimensions did not match
+    DimensionMismatch = 4,
+
+    /// The file is locked by another process or handle
+    Locked = 5,
+
+    /// The file is not a Chassis index or is corrupted
+    Corrupted = 6,
+
+    /// An operating system I/O call failed
+    Io = 7,
+
+    /// A vector ID or internal offset was out of range
+    OutOfBounds = 8,
+
+    /// The index file was deleted or replaced while open
+    FileStolen = 9,
+
+    /// A write was attempted through a shared (read-only) handle
+    ReadOnly = 10,
+
+    /// A Rust panic was caught at the FFI boundary
+    Panic = 11,
+
+    /// Any other failure
+    Unknown = 12,
+
+    /// The file was written by a library version the caller's policy refuses
+    IncompatibleVersion = 13,
+
+    /// The index would grow past the limit set with `chassis_set_max_file_bytes()`
+    QuotaExceeded = 14,
+
+    /// The progress callback returned nonzero and the operation stopped early
+    Cancelled = 15,
+}
+
+/// Version of the C ABI described by `chassis.h`
+///
+/// Bumped whenever a function signature, struct layout or enum value changes
+/// incompatibly; additions keep the version. Bindings compare the value they
+/// were generated against with `chassis_abi_version()` when they load the
+/// library.
+pub const CHASSIS_ABI_VERSION: u32 = 1;
+
+/// On-disk encoding of vector components, for `ChassisOptions::element_type`
+///
+/// Values are part of the ABI, like `ChassisErrorCode`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChassisElementType {
+    /// 32-bit floats
+    F32 = 0,
+
+    /// IEEE 754 half precision
+    F16 = 1,
+
+    /// bfloat16
+    Bf16 = 2,
+
+    /// One sign bit per component, compared by Hamming distance
+    Binary = 3,
+}
+
+/// Long-running operation reported to a progress callback, passed as its
+/// `stage` argument
+///
+/// Values are part of the ABI, like `ChassisErrorCode`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChassisProgressStage {
+    /// Inserting the vectors of `chassis_add_batch()`; one unit per vector
+    Import = 0,
+
+    /// Rewriting neighbor lists in `chassis_optimize()`; one unit per node and pass
+    Optimize = 1,
+}
+
+impl ChassisProgressStage {
+    fn from_core(stage: ProgressStage) -> Option<Self> {
+        match stage {
+            ProgressStage::Import => Some(Self::Import),
+            ProgressStage::Optimize => Some(Self::Optimize),
+            _ => None,
+        }
+    }
+}
+
+/// Progress callback for `chassis_set_progress_callback()`
+///
+/// Called with a `ChassisProgressStage` value, the units of work done, the
+/// total, and the `user_data` given when it was set. Return 0 to continue, or
+/// nonzero to stop the operation, which then fails with
+/// `CHASSIS_ERROR_CODE_CANCELLED`.
+pub type ChassisProgressFn = Option<
+    unsafe extern "C" fn(stage: u32, done: u64, total: u64, user_data: *mut c_void) -> c_int,
+>;
+
+/// Non-NULL `ChassisProgressFn`
+type ProgressFnPtr =
+    unsafe extern "C" fn(stage: u32, done: u64, total: u64, user_data: *mut c_void) -> c_int;
+
+/// Units of work between two calls of a progress callback, as in the core
+const PROGRESS_INTERVAL: u64 = 64;
+
+/// A progress callback with its user data
+#[derive(Clone, Copy)]
+struct ProgressCallback {
+    callback: ProgressFnPtr,
+    user_data: *mut c_void,
+}
+
+// SAFETY: `chassis_set_progress_callback()` requires the callback and its
+// user data to be usable from any thread that calls into the index.
+unsafe impl Send for ProgressCallback {}
+unsafe impl Sync for ProgressCallback {}
+
+impl ProgressCallback {
+    /// Call the callback; returns `false` if it asked to stop
+    fn report(&self, stage: ChassisProgressStage, done: u64, total: u64) -> bool {
+        // SAFETY: The caller of `chassis_set_progress_callback()` guarantees
+        // the function and user data stay valid while the callback is set.
+        unsafe { (self.callback)(stage as u32, done, total, self.user_data) == 0 }
+    }
+}
+
+/// Index configuration for `chassis_open_with_config()`
+///
+/// Start from `chassis_options_default()` and change the fields you need, so
+/// code keeps compiling with the defaults when fields are added in a later
+/// ABI version.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ChassisOptions {
+    /// Maximum connections per node (M parameter, at most 65535). Default: 16
+    pub max_connections: u32,
+
+    /// Construction quality (efConstruction). Default: 200
+    pub ef_construction: u32,
+
+    /// Search quality (efSearch). Default: 50
+    pub ef_search: u32,
+
+    /// Maximum number of graph layers (1 to 16). Default: 16
+    pub max_layers: u32,
+
+    /// A `ChassisElementType` value. Default: `CHASSIS_ELEMENT_TYPE_F32`
+    pub element_type: u32,
+
+    /// Dimensionality of incoming vectors, truncated to `dimensions` on add
+    /// and search; 0 stores vectors as given. Default: 0
+    pub input_dimensions: u32,
+
+    /// Scan every vector instead of searching the graph while the index holds
+    /// at most this many. Default: 0 (always use the graph)
+    pub exact_search_threshold: u64,
+
+    /// Normalize vectors to unit length and compare them by cosine distance.
+    /// Default: false
+    pub normalize: bool,
+
+    /// Keep the graph in a second file next to the index. Default: false
+    pub graph_file: bool,
+}
+
+impl ChassisOptions {
+    /// Convert to core options, or describe the invalid field
+    fn to_index_options(self) -> Result<IndexOptions, String> {
+        let max_connections = u16::try_from(self.max_connections)
+            .map_err(|_| format!("max_connections must be <= {}", u16::MAX))?;
+        let max_layers = u8::try_from(self.max_layers)
+            .map_err(|_| format!("max_layers out of range: {}", self.max_layers))?;
+        let element_type = match self.element_type {
+            0 => ElementType::F32,
+            1 => ElementType::F16,
+            2 => ElementType::BF16,
+            3 => ElementType::Binary,
+            other => return Err(format!("Unknown element type: {}", other)),
+        };
+
+        Ok(IndexOptions {
+            max_connections,
+            ef_construction: self.ef_construction as usize,
+            ef_search: self.ef_search as usize,
+            max_layers,
+            element_type,
+            input_dimensions: (self.input_dimensions > 0).then_some(self.input_dimensions),
+            exact_search_threshold: self.exact_search_threshold,
+            normalize: self.normalize,
+            graph_file: self.graph_file,
+            ..IndexOptions::default()
+        })
+    }
+}
+
+impl From<ErrorKind> for ChassisErrorCode {
+    fn from(kind: ErrorKind) -> Self {
+        match kind {
+            ErrorKind::InvalidArgument => Self::InvalidArgument,
+            ErrorKind::DimensionMismatch => Self::DimensionMismatch,
+            ErrorKind::Locked => Self::Locked,
+            ErrorKind::Corrupted => Self::Corrupted,
+            ErrorKind::Io => Self::Io,
+            ErrorKind::OutOfBounds => Self::OutOfBounds,
+            ErrorKind::FileStolen => Self::FileStolen,
+            ErrorKind::ReadOnly => Self::ReadOnly,
+            ErrorKind::IncompatibleVersion => Self::IncompatibleVersion,
+            ErrorKind::QuotaExceeded => Self::QuotaExceeded,
+            ErrorKind::Cancelled => Self::Cancelled,
+            _ => Self::Unknown,
+        }
+    }
+}
+
 thread_local! {
     /// Thread-local storage for error messages
     ///
     /// Each thread maintains its own error message to ensure thread safety
     /// without requiring locks. The `RefCell` allows interior mutability.
     static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+
+    /// Thread-local error code, set together with `LAST_ERROR`
+    static LAST_ERROR_CODE: Cell<ChassisErrorCode> = const { Cell::new(ChassisErrorCode::Ok) };
 }
 
-/// Set the last error message for the current thread
+/// Set the last error code and message for the current thread
 ///
 /// # Safety
 ///
 /// This function handles interior NULs gracefully to prevent panics during
 /// error reporting. If the error message contains NUL bytes, they are
 /// replaced with the escaped sequence "\\0".
-fn set_last_error(err: impl std::fmt::Display) {
+fn set_last_error(code: ChassisErrorCode, err: impl std::fmt::Display) {
     LAST_ERROR.with(|cell| {
         // Handle interior NULs gracefully to avoid panic during error reporting
         let safe_msg = err.to_string().replace('\0', "\\0");
         let c_str = CString::new(safe_msg).unwrap_or_default();
         *cell.borrow_mut() = Some(c_str);
     });
+    LAST_ERROR_CODE.with(|cell| cell.set(code));
+}
+
+/// Set the last error from a core error, classifying it by `ErrorKind`
+fn set_core_error(err: &anyhow::Error) {
+    set_last_error(ErrorKind::of(err).into(), err);
 }
 
-/// Clear the last error message for the current thread
+/// Clear the last error message and code for the current thread
 fn clear_last_error() {
     LAST_ERROR.with(|cell| {
         *cell.borrow_mut() = None;
     });
+    LAST_ERROR_CODE.with(|cell| cell.set(ChassisErrorCode::Ok));
 }
 
 /// Panic barrier that catches all panics at the FFI boundary
@@ -89,7 +447,7 @@ fn clear_last_error() {
 /// # Implementation
 ///
 /// - Wraps all FFI operations in `std::panic::catch_unwind`
-/// - Converts panics to error messages via `set_last_error`
+/// - Converts panics to `ChassisErrorCode::Panic` via `set_last_error`
 /// - Returns `None` on panic, allowing callers to use sentinel values
 ///
 /// # AssertUnwindSafe Justification
@@ -114,12 +472,128 @@ where
             } else {
                 "Unknown panic".to_string()
             };
-            set_last_error(msg);
+            set_last_error(ChassisErrorCode::Panic, msg);
             None
         }
     }
 }
 
+/// Add one vector, reporting the outcome through the thread-local error
+///
+/// Shared by `chassis_add` and `chassis_writer_add`.
+///
+/// # Safety
+///
+/// `vector` must be NULL or point to `len` valid f32 values
+unsafe fn add_vector(index: &mut VectorIndex, vector: *const c_float, len: size_t) -> u64 {
+    if vector.is_null() {
+        set_last_error(ChassisErrorCode::NullPointer, "Null vector pointer");
+        return u64::MAX;
+    }
+
+    if len == 0 {
+        set_last_error(ChassisErrorCode::InvalidArgument, "Vector length must be > 0");
+        return u64::MAX;
+    }
+
+    // SAFETY: Caller guarantees vector points to len valid f32 values
+    let slice = unsafe { slice::from_raw_parts(vector, len) };
+
+    match index.add(slice) {
+        Ok(id) => {
+            clear_last_error();
+            id
+        }
+        Err(e) => {
+            set_core_error(&e);
+            u64::MAX
+        }
+    }
+}
+
+/// Search into caller buffers, reporting the outcome through the thread-local error
+///
+/// Shared by `chassis_search` and `chassis_reader_search`.
+///
+/// # Safety
+///
+/// Non-NULL `query` must point to `len` f32 values, and non-NULL `out_ids`
+/// and `out_dists` to space for `k` elements
+unsafe fn search_vectors(
+    index: &VectorIndex,
+    query: *const c_float,
+    len: size_t,
+    k: size_t,
+    out_ids: *mut u64,
+    out_dists: *mut c_float,
+) -> size_t {
+    if query.is_null() || out_ids.is_null() || out_dists.is_null() {
+        set_last_error(ChassisErrorCode::NullPointer, "Null buffer pointers");
+        return 0;
+    }
+
+    if k == 0 {
+        set_last_error(ChassisErrorCode::InvalidArgument, "k must be > 0");
+        return 0;
+    }
+
+    // SAFETY: Caller guarantees query points to len valid f32 values
+    let query_slice = unsafe { slice::from_raw_parts(query, len) };
+
+    // SAFETY: Caller guarantees out_ids and out_dists have space for k elements
+    let ids = unsafe { slice::from_raw_parts_mut(out_ids, k) };
+    let distances = unsafe { slice::from_raw_parts_mut(out_dists, k) };
+
+    match index.search_into(query_slice, k, ids, distances) {
+        Ok(count) => {
+            clear_last_error();
+            count
+        }
+        Err(e) => {
+            set_core_error(&e);
+            0
+        }
+    }
+}
+
+/// Copy a stored vector into a caller buffer, reporting the outcome through
+/// the thread-local error
+///
+/// Shared by `chassis_get_vector` and `chassis_reader_get_vector`.
+///
+/// # Safety
+///
+/// Non-NULL `out_buf` must have space for `len` f32 values
+unsafe fn copy_vector(index: &VectorIndex, id: u64, out_buf: *mut c_float, len: size_t) -> size_t {
+    if out_buf.is_null() {
+        set_last_error(ChassisErrorCode::NullPointer, "Null output buffer");
+        return 0;
+    }
+
+    let dimensions = index.dimensions() as usize;
+    if len < dimensions {
+        set_last_error(
+            ChassisErrorCode::InvalidArgument,
+            format!("Output buffer holds {} floats, need {}", len, dimensions),
+        );
+        return 0;
+    }
+
+    match index.get_vector(id) {
+        Ok(vector) => {
+            // SAFETY: Caller guarantees out_buf has space for len >= dimensions floats
+            let out = unsafe { slice::from_raw_parts_mut(out_buf, vector.len()) };
+            out.copy_from_slice(&vector);
+            clear_last_error();
+            vector.len()
+        }
+        Err(e) => {
+            set_core_error(&e);
+            0
+        }
+    }
+}
+
 //
 //  LIFECYCLE MANAGEMENT
 //
@@ -139,7 +613,7 @@ where
 /// # Thread Safety
 ///
 /// - Safe to call from multiple threads with different paths
-/// - The returned index requires exclusive access for writes
+/// - The returned index serializes writes against searches internally
 ///
 /// # Example (C)
 ///
@@ -160,12 +634,12 @@ where
 pub unsafe extern "C" fn chassis_open(path: *const c_char, dimensions: u32) -> *mut ChassisIndex {
     ffi_guard(|| {
         if path.is_null() {
-            set_last_error("Path cannot be NULL");
+            set_last_error(ChassisErrorCode::NullPointer, "Path cannot be NULL");
             return ptr::null_mut();
         }
 
         if dimensions == 0 {
-            set_last_error("Dimensions must be > 0");
+            set_last_error(ChassisErrorCode::InvalidArgument, "Dimensions must be > 0");
             return ptr::null_mut();
         }
 
@@ -176,7 +650,7 @@ pub unsafe extern "C" fn chassis_open(path: *const c_char, dimensions: u32) -> *
         let path_str = match c_path.to_str() {
             Ok(s) => s,
             Err(_) => {
-                set_last_error("Path must be valid UTF-8");
+                set_last_error(ChassisErrorCode::InvalidUtf8, "Path must be valid UTF-8");
                 return ptr::null_mut();
             }
         };
@@ -186,11 +660,11 @@ pub unsafe extern "C" fn chassis_open(path: *const c_char, dimensions: u32) -> *
         match VectorIndex::open(path_str, dimensions, options) {
             Ok(index) => {
                 clear_last_error(); // Success - clear any previous errors
-                let state = Box::new(ChassisIndexState { inner: index });
+                let state = Box::new(ChassisIndexState::new(index));
                 Box::into_raw(state) as *mut ChassisIndex
             }
             Err(e) => {
-                set_last_error(e);
+                set_core_error(&e);
                 ptr::null_mut()
             }
         }
@@ -232,18 +706,21 @@ pub unsafe extern "C" fn chassis_open_with_options(
 ) -> *mut ChassisIndex {
     ffi_guard(|| {
         if path.is_null() {
-            set_last_error("Path cannot be NULL");
+            set_last_error(ChassisErrorCode::NullPointer, "Path cannot be NULL");
             return ptr::null_mut();
         }
 
         if dimensions == 0 {
-            set_last_error("Dimensions must be > 0");
+            set_last_error(ChassisErrorCode::InvalidArgument, "Dimensions must be > 0");
             return ptr::null_mut();
         }
 
         // Validate max_connections is u16
         if max_connections > u16::MAX as u32 {
-            set_last_error(format!("max_connections must be <= {}", u16::MAX));
+            set_last_error(
+                ChassisErrorCode::InvalidArgument,
+                format!("max_connections must be <= {}", u16::MAX),
+            );
             return ptr::null_mut();
         }
 
@@ -253,7 +730,7 @@ pub unsafe extern "C" fn chassis_open_with_options(
         let path_str = match c_path.to_str() {
             Ok(s) => s,
             Err(_) => {
-                set_last_error("Path must be valid UTF-8");
+                set_last_error(ChassisErrorCode::InvalidUtf8, "Path must be valid UTF-8");
                 return ptr::null_mut();
             }
         };
@@ -262,16 +739,17 @@ pub unsafe extern "C" fn chassis_open_with_options(
             max_connections: max_connections as u16,
             ef_construction: ef_construction as usize,
             ef_search: ef_search as usize,
+            ..IndexOptions::default()
         };
 
         match VectorIndex::open(path_str, dimensions, options) {
             Ok(index) => {
                 clear_last_error();
-                let state = Box::new(ChassisIndexState { inner: index });
+                let state = Box::new(ChassisIndexState::new(index));
                 Box::into_raw(state) as *mut ChassisIndex
             }
             Err(e) => {
-                set_last_error(e);
+                set_core_error(&e);
                 ptr::null_mut()
             }
         }
@@ -279,127 +757,262 @@ pub unsafe extern "C" fn chassis_open_with_options(
     .unwrap_or(ptr::null_mut())
 }
 
-/// Free a Chassis index and release all resources
-///
-/// # Arguments
-///
-/// - `ptr`: Pointer returned by `chassis_open()` or NULL
-///
-/// # Safety
-///
-/// - `ptr` must be NULL or a valid pointer from `chassis_open()`
-/// - After this call, `ptr` is invalid and must not be used
-/// - Safe to call with NULL (no-op)
-/// - Must not be called more than once with the same non-NULL pointer
+/// Default options for `chassis_open_with_config()`
 ///
 /// # Example (C)
 ///
 /// ```c
-/// chassis_free(index);
-/// index = NULL; // Good practice
+/// ChassisOptions options = chassis_options_default();
+/// options.element_type = CHASSIS_ELEMENT_TYPE_F16;
+/// options.normalize = true;
+/// ChassisIndex* index = chassis_open_with_config("vectors.chassis", 768, &options);
 /// ```
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn chassis_free(ptr: *mut ChassisIndex) {
-    if !ptr.is_null() {
-        ffi_guard(|| {
-            // SAFETY: Caller guarantees ptr is valid (from chassis_open)
-            let _ = unsafe { Box::from_raw(ptr as *mut ChassisIndexState) };
-        });
+pub extern "C" fn chassis_options_default() -> ChassisOptions {
+    let defaults = IndexOptions::default();
+    ChassisOptions {
+        max_connections: u32::from(defaults.max_connections),
+        ef_construction: defaults.ef_construction as u32,
+        ef_search: defaults.ef_search as u32,
+        max_layers: u32::from(defaults.max_layers),
+        element_type: ChassisElementType::F32 as u32,
+        input_dimensions: 0,
+        exact_search_threshold: defaults.exact_search_threshold,
+        normalize: defaults.normalize,
+        graph_file: defaults.graph_file,
     }
 }
 
-//
-//  VECTOR OPERATIONS
-//
-
-/// Add a vector to the index
+/// Open or create a Chassis vector index with a `ChassisOptions` struct
 ///
 /// # Arguments
 ///
-/// - `ptr`: Non-NULL pointer to index (requires exclusive access)
-/// - `vector`: Pointer to f32 array (must not be NULL)
-/// - `len`: Number of elements in vector (must match index dimensions)
+/// - `path`: UTF-8 encoded path to the index file (must not be NULL)
+/// - `dimensions`: Number of dimensions per vector (must be > 0)
+/// - `options`: Options from `chassis_options_default()`, possibly modified
+///   (must not be NULL)
 ///
 /// # Returns
 ///
-/// - Vector ID (0-based) on success
-/// - `UINT64_MAX` on failure (check `chassis_last_error_message()`)
-///
-/// # Thread Safety
-///
-/// **SINGLE-WRITER**: Only one thread may call this function at a time for a
-/// given index. Concurrent writes will cause data corruption.
-///
-/// # Performance Note
-///
-/// This operation does NOT guarantee durability. Call `chassis_flush()` to
-/// ensure data is written to disk.
-///
-/// # Example (C)
-///
-/// ```c
-/// float vec[768] = {0.1, 0.2, ...};
-/// u64 id = chassis_add(index, vec, 768);
-/// if (id == UINT64_MAX) {
-///     fprintf(stderr, "Add failed: %s\n", chassis_last_error_message());
-/// }
-/// ```
+/// - Non-NULL pointer on success
+/// - NULL on failure (check `chassis_last_error_message()`); an out-of-range
+///   field reports `CHASSIS_ERROR_CODE_INVALID_ARGUMENT`
 ///
 /// # Safety
 ///
-/// - `ptr` must be non-NULL and valid
-/// - `vector` must point to `len` valid f32 values
-/// - `len` must match the dimensions specified in `chassis_open()`
-/// - No other thread may access `ptr` during this call
+/// Same safety requirements as `chassis_open()`; `options` must point to a
+/// valid `ChassisOptions` for the duration of this call
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn chassis_add(
-    ptr: *mut ChassisIndex,
-    vector: *const c_float,
-    len: size_t,
-) -> u64 {
+pub unsafe extern "C" fn chassis_open_with_config(
+    path: *const c_char,
+    dimensions: u32,
+    options: *const ChassisOptions,
+) -> *mut ChassisIndex {
     ffi_guard(|| {
-        // SAFETY: Caller guarantees ptr is valid and has exclusive access
-        let state = unsafe { (ptr as *mut ChassisIndexState).as_mut() };
-        let index = match state {
-            Some(s) => &mut s.inner,
-            None => {
-                set_last_error("Null index pointer");
-                return u64::MAX;
-            }
-        };
-
-        if vector.is_null() {
-            set_last_error("Null vector pointer");
-            return u64::MAX;
+        if path.is_null() || options.is_null() {
+            set_last_error(ChassisErrorCode::NullPointer, "Path and options cannot be NULL");
+            return ptr::null_mut();
         }
 
-        if len == 0 {
-            set_last_error("Vector length must be > 0");
-            return u64::MAX;
+        if dimensions == 0 {
+            set_last_error(ChassisErrorCode::InvalidArgument, "Dimensions must be > 0");
+            return ptr::null_mut();
         }
 
-        // SAFETY: Caller guarantees vector points to len valid f32 values
-        let slice = unsafe { slice::from_raw_parts(vector, len) };
-
-        match index.add(slice) {
-            Ok(id) => {
-                clear_last_error();
-                id
-            }
-            Err(e) => {
-                set_last_error(e);
-                u64::MAX
+        // SAFETY: Caller guarantees options points to a valid ChassisOptions
+        let options = match unsafe { *options }.to_index_options() {
+            Ok(options) => options,
+            Err(msg) => {
+                set_last_error(ChassisErrorCode::InvalidArgument, msg);
+                return ptr::null_mut();
             }
-        }
-    })
-    .unwrap_or(u64::MAX)
-}
+        };
 
-/// Add multiple vectors to the index in one call (row-major layout)
-///
-/// # Arguments
-///
-/// - `ptr`: Non-NULL pointer to index (requires exclusive access)
+        // SAFETY: Caller guarantees path is valid C string
+        let c_path = unsafe { CStr::from_ptr(path) };
+
+        let path_str = match c_path.to_str() {
+            Ok(s) => s,
+            Err(_) => {
+                set_last_error(ChassisErrorCode::InvalidUtf8, "Path must be valid UTF-8");
+                return ptr::null_mut();
+            }
+        };
+
+        match VectorIndex::open(path_str, dimensions, options) {
+            Ok(index) => {
+                clear_last_error();
+                let state = Box::new(ChassisIndexState::new(index));
+                Box::into_raw(state) as *mut ChassisIndex
+            }
+            Err(e) => {
+                set_core_error(&e);
+                ptr::null_mut()
+            }
+        }
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+/// Open an existing Chassis index read-only, shared with other processes
+///
+/// Any number of processes may hold a shared handle at once (shared
+/// `LockFileEx` lock on Windows, `flock(LOCK_SH)` on Unix). While one does,
+/// `chassis_open()` fails, and this call fails while a writer holds the file.
+/// The index must have been created with default options.
+///
+/// # Returns
+///
+/// - Non-NULL pointer on success; `chassis_add`, `chassis_add_batch` and
+///   `chassis_flush` report an error on it
+/// - NULL on failure (check `chassis_last_error_message()`)
+///
+/// # Safety
+///
+/// Same safety requirements as `chassis_open()`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_open_shared(
+    path: *const c_char,
+    dimensions: u32,
+) -> *mut ChassisIndex {
+    ffi_guard(|| {
+        if path.is_null() {
+            set_last_error(ChassisErrorCode::NullPointer, "Path cannot be NULL");
+            return ptr::null_mut();
+        }
+
+        if dimensions == 0 {
+            set_last_error(ChassisErrorCode::InvalidArgument, "Dimensions must be > 0");
+            return ptr::null_mut();
+        }
+
+        // SAFETY: Caller guarantees path is valid C string
+        let c_path = unsafe { CStr::from_ptr(path) };
+
+        let path_str = match c_path.to_str() {
+            Ok(s) => s,
+            Err(_) => {
+                set_last_error(ChassisErrorCode::InvalidUtf8, "Path must be valid UTF-8");
+                return ptr::null_mut();
+            }
+        };
+
+        match VectorIndex::open_shared(path_str, dimensions, IndexOptions::default()) {
+            Ok(index) => {
+                clear_last_error();
+                let state = Box::new(ChassisIndexState::new(index));
+                Box::into_raw(state) as *mut ChassisIndex
+            }
+            Err(e) => {
+                set_core_error(&e);
+                ptr::null_mut()
+            }
+        }
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+/// Free a Chassis index and release all resources
+///
+/// # Arguments
+///
+/// - `ptr`: Pointer returned by `chassis_open()` or NULL
+///
+/// # Safety
+///
+/// - `ptr` must be NULL or a valid pointer from `chassis_open()`
+/// - After this call, `ptr` is invalid and must not be used
+/// - Safe to call with NULL (no-op)
+/// - Must not be called more than once with the same non-NULL pointer
+/// - No other thread may use `ptr` during or after this call
+///
+/// # Example (C)
+///
+/// ```c
+/// chassis_free(index);
+/// index = NULL; // Good practice
+/// ```
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_free(ptr: *mut ChassisIndex) {
+    if !ptr.is_null() {
+        ffi_guard(|| {
+            // SAFETY: Caller guarantees ptr is valid (from chassis_open)
+            let _ = unsafe { Box::from_raw(ptr as *mut ChassisIndexState) };
+        });
+    }
+}
+
+//
+//  VECTOR OPERATIONS
+//
+
+/// Add a vector to the index
+///
+/// # Arguments
+///
+/// - `ptr`: Non-NULL pointer to index
+/// - `vector`: Pointer to f32 array (must not be NULL)
+/// - `len`: Number of elements in vector (must match index dimensions)
+///
+/// # Returns
+///
+/// - Vector ID (0-based) on success
+/// - `UINT64_MAX` on failure (check `chassis_last_error_message()`)
+///
+/// # Thread Safety
+///
+/// **SINGLE-WRITER**: Takes the index's exclusive lock. Calls from other
+/// threads, reads included, wait until the vector is inserted.
+///
+/// # Performance Note
+///
+/// This operation does NOT guarantee durability. Call `chassis_flush()` to
+/// ensure data is written to disk.
+///
+/// # Example (C)
+///
+/// ```c
+/// float vec[768] = {0.1, 0.2, ...};
+/// u64 id = chassis_add(index, vec, 768);
+/// if (id == UINT64_MAX) {
+///     fprintf(stderr, "Add failed: %s\n", chassis_last_error_message());
+/// }
+/// ```
+///
+/// # Safety
+///
+/// - `ptr` must be non-NULL and valid
+/// - `vector` must point to `len` valid f32 values
+/// - `len` must match the dimensions specified in `chassis_open()`
+/// - `ptr` must not be freed during this call
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_add(
+    ptr: *mut ChassisIndex,
+    vector: *const c_float,
+    len: size_t,
+) -> u64 {
+    ffi_guard(|| {
+        // SAFETY: Caller guarantees ptr is valid; the lock makes access exclusive
+        let state = unsafe { (ptr as *const ChassisIndexState).as_ref() };
+        let mut index = match state {
+            Some(s) => s.write(),
+            None => {
+                set_last_error(ChassisErrorCode::NullPointer, "Null index pointer");
+                return u64::MAX;
+            }
+        };
+
+        // SAFETY: Caller guarantees vector points to len valid f32 values
+        unsafe { add_vector(&mut index, vector, len) }
+    })
+    .unwrap_or(u64::MAX)
+}
+
+/// Add multiple vectors to the index in one call (row-major layout)
+///
+/// # Arguments
+///
+/// - `ptr`: Non-NULL pointer to index
 /// - `vectors`: Contiguous `count * dim` floats: row `i` is
 ///   `vectors[i*dim .. (i+1)*dim]`
 /// - `count`: Number of vectors to insert
@@ -411,6 +1024,8 @@ pub unsafe extern "C" fn chassis_add(
 /// - Number of vectors successfully inserted
 /// - On first error, stops and returns the count inserted so far; use
 ///   `chassis_last_error_message()` for the reason
+/// - If the progress callback asks to stop, returns the count inserted so far
+///   with `CHASSIS_ERROR_CODE_CANCELLED`
 /// - If `count == 0`, returns `0` and succeeds (pointers need not be valid)
 ///
 /// # Thread Safety
@@ -439,7 +1054,7 @@ pub unsafe extern "C" fn chassis_add(
 /// - If `count > 0`, `vectors` and `out_ids` must be non-NULL; `vectors` must point
 ///   to `count * dim` valid floats
 /// - `dim` must match dimensions passed to `chassis_open()`
-/// - No other thread may access `ptr` during this call
+/// - `ptr` must not be freed during this call
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn chassis_add_batch(
     ptr: *mut ChassisIndex,
@@ -450,7 +1065,7 @@ pub unsafe extern "C" fn chassis_add_batch(
 ) -> size_t {
     ffi_guard(|| {
         if ptr.is_null() {
-            set_last_error("Null index pointer");
+            set_last_error(ChassisErrorCode::NullPointer, "Null index pointer");
             return 0;
         }
 
@@ -460,46 +1075,59 @@ pub unsafe extern "C" fn chassis_add_batch(
         }
 
         if vectors.is_null() || out_ids.is_null() {
-            set_last_error("Null buffer pointers");
+            set_last_error(ChassisErrorCode::NullPointer, "Null buffer pointers");
             return 0;
         }
 
         if dim == 0 {
-            set_last_error("Vector dimension must be > 0");
+            set_last_error(ChassisErrorCode::InvalidArgument, "Vector dimension must be > 0");
             return 0;
         }
 
-        // SAFETY: Caller guarantees ptr is valid and has exclusive access
-        let state = unsafe { (ptr as *mut ChassisIndexState).as_mut() };
-        let index = match state {
-            Some(s) => &mut s.inner,
+        // SAFETY: Caller guarantees ptr is valid; the lock makes access exclusive
+        let state = unsafe { (ptr as *const ChassisIndexState).as_ref() };
+        let mut index = match state {
+            Some(s) => s.write(),
             None => {
-                set_last_error("Null index pointer");
+                set_last_error(ChassisErrorCode::NullPointer, "Null index pointer");
                 return 0;
             }
         };
 
-        let index_dim = index.dimensions() as usize;
+        let index_dim = index.input_dimensions() as usize;
         if dim != index_dim {
-            set_last_error(format!(
-                "Vector dimension mismatch: expected {}, got {}",
-                index_dim, dim
-            ));
+            set_last_error(
+                ChassisErrorCode::DimensionMismatch,
+                format!("Vector dimension mismatch: expected {}, got {}", index_dim, dim),
+            );
             return 0;
         }
 
         let total = match dim.checked_mul(count) {
             Some(t) => t,
             None => {
-                set_last_error("Vector batch size overflow");
+                set_last_error(ChassisErrorCode::InvalidArgument, "Vector batch size overflow");
                 return 0;
             }
         };
 
         // SAFETY: Caller guarantees `vectors` points to at least `total` floats
         let data = unsafe { slice::from_raw_parts(vectors, total) };
+        let progress = state.and_then(ChassisIndexState::progress);
+        let stage = ChassisProgressStage::Import;
 
         for i in 0..count {
+            if let Some(progress) = progress
+                && (i as u64).is_multiple_of(PROGRESS_INTERVAL)
+                && !progress.report(stage, i as u64, count as u64)
+            {
+                set_last_error(
+                    ChassisErrorCode::Cancelled,
+                    format!("chassis_add_batch() cancelled after {} of {} vectors", i, count),
+                );
+                return i;
+            }
+
             let start = i * dim;
             let row = &data[start..start + dim];
             match index.add(row) {
@@ -510,12 +1138,15 @@ pub unsafe extern "C" fn chassis_add_batch(
                     clear_last_error();
                 }
                 Err(e) => {
-                    set_last_error(e);
+                    set_core_error(&e);
                     return i;
                 }
             }
         }
 
+        if let Some(progress) = progress {
+            progress.report(stage, count as u64, count as u64);
+        }
         count
     })
     .unwrap_or(0)
@@ -540,7 +1171,8 @@ pub unsafe extern "C" fn chassis_add_batch(
 /// # Thread Safety
 ///
 /// **MULTI-READER**: Multiple threads may call this function concurrently
-/// on the same index. Reads do not block other reads.
+/// on the same index. Reads do not block other reads; they wait for a write
+/// in progress.
 ///
 /// # Output Format
 ///
@@ -550,140 +1182,847 @@ pub unsafe extern "C" fn chassis_add_batch(
 ///
 /// # Example (C)
 ///
-/// ```c
-/// float query[768] = {0.1, 0.2, ...};
-/// u64 ids[10];
-/// float dists[10];
+/// ```c
+/// float query[768] = {0.1, 0.2, ...};
+/// u64 ids[10];
+/// float dists[10];
+///
+/// size_t count = chassis_search(index, query, 768, 10, ids, dists);
+/// for (size_t i = 0; i < count; i++) {
+///     printf("ID: %llu, Distance: %f\n", ids[i], dists[i]);
+/// }
+/// ```
+///
+/// # Safety
+///
+/// - `ptr` must be non-NULL and valid
+/// - `query` must point to `len` valid f32 values
+/// - `out_ids` must have space for at least `k` u64 values
+/// - `out_dists` must have space for at least `k` float values
+/// - Buffers must not overlap
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_search(
+    ptr: *const ChassisIndex,
+    query: *const c_float,
+    len: size_t,
+    k: size_t,
+    out_ids: *mut u64,
+    out_dists: *mut c_float,
+) -> size_t {
+    ffi_guard(|| {
+        // SAFETY: Caller guarantees ptr is valid (shared access)
+        let state = unsafe { (ptr as *const ChassisIndexState).as_ref() };
+        let index = match state {
+            Some(s) => s.read(),
+            None => {
+                set_last_error(ChassisErrorCode::NullPointer, "Null index pointer");
+                return 0;
+            }
+        };
+
+        // SAFETY: Caller guarantees the query and output buffers are valid
+        unsafe { search_vectors(&index, query, len, k, out_ids, out_dists) }
+    })
+    .unwrap_or(0)
+}
+
+/// Flush all changes to disk
+///
+/// # Arguments
+///
+/// - `ptr`: Non-NULL pointer to index
+///
+/// # Returns
+///
+/// - 0 on success
+/// - -1 on failure (check `chassis_last_error_message()`)
+///
+/// # Thread Safety
+///
+/// **SINGLE-WRITER**: Other writes wait for the flush to complete. Searches
+/// only wait while the changes are handed to the OS, not for the `fsync`.
+///
+/// # Performance Warning
+///
+/// This operation is expensive (1-50ms depending on storage device).
+/// Batch multiple `chassis_add()` calls and flush once at the end.
+///
+/// # Example (C)
+///
+/// ```c
+/// // Add many vectors
+/// for (int i = 0; i < 1000; i++) {
+///     chassis_add(index, vectors[i], 768);
+/// }
+///
+/// // Flush once at the end
+/// if (chassis_flush(index) != 0) {
+///     fprintf(stderr, "Flush failed: %s\n", chassis_last_error_message());
+/// }
+/// ```
+///
+/// # Safety
+///
+/// - `ptr` must be non-NULL and valid
+/// - `ptr` must not be freed during this call
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_flush(ptr: *mut ChassisIndex) -> c_int {
+    ffi_guard(|| {
+        // SAFETY: Caller guarantees ptr is valid; the locks make access exclusive
+        let state = unsafe { (ptr as *const ChassisIndexState).as_ref() };
+        let Some(state) = state else {
+            set_last_error(ChassisErrorCode::NullPointer, "Null index pointer");
+            return -1;
+        };
+
+        match state.flush() {
+            Ok(_) => {
+                clear_last_error();
+                0
+            }
+            Err(e) => {
+                set_core_error(&e);
+                -1
+            }
+        }
+    })
+    .unwrap_or(-1)
+}
+
+/// Write a crash-consistent copy of the index to `path` without closing it
+///
+/// Flushes first (unless the handle came from `chassis_open_shared`), then
+/// copies the file while the handle keeps its lock. The copy is staged in a
+/// temporary file and renamed into place, so `path` never holds a partial
+/// snapshot. Uses a reflink (copy-on-write clone) where the filesystem allows.
+///
+/// # Arguments
+///
+/// - `ptr`: Non-NULL pointer to index
+/// - `path`: Null-terminated UTF-8 destination path
+///
+/// # Returns
+///
+/// - 0 on success
+/// - -1 on failure (check `chassis_last_error_message()`)
+///
+/// # Thread Safety
+///
+/// **SINGLE-WRITER**: Same rules as `chassis_flush()`.
+///
+/// # Example (C)
+///
+/// ```c
+/// if (chassis_snapshot_to(index, "backup/vectors.chassis") != 0) {
+///     fprintf(stderr, "Backup failed: %s\n", chassis_last_error_message());
+/// }
+/// ```
+///
+/// # Safety
+///
+/// - `ptr` must be non-NULL and valid
+/// - `path` must be a valid null-terminated C string
+/// - `ptr` must not be freed during this call
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_snapshot_to(ptr: *mut ChassisIndex, path: *const c_char) -> c_int {
+    ffi_guard(|| {
+        // SAFETY: Caller guarantees ptr is valid; the lock makes access exclusive
+        let state = unsafe { (ptr as *const ChassisIndexState).as_ref() };
+        let mut index = match state {
+            Some(s) => s.write(),
+            None => {
+                set_last_error(ChassisErrorCode::NullPointer, "Null index pointer");
+                return -1;
+            }
+        };
+
+        if path.is_null() {
+            set_last_error(ChassisErrorCode::NullPointer, "Path cannot be NULL");
+            return -1;
+        }
+
+        // SAFETY: Caller guarantees path is valid C string
+        let c_path = unsafe { CStr::from_ptr(path) };
+        let path_str = match c_path.to_str() {
+            Ok(s) => s,
+            Err(_) => {
+                set_last_error(ChassisErrorCode::InvalidUtf8, "Path must be valid UTF-8");
+                return -1;
+            }
+        };
+
+        match index.snapshot_to(path_str) {
+            Ok(()) => {
+                clear_last_error();
+                0
+            }
+            Err(e) => {
+                set_core_error(&e);
+                -1
+            }
+        }
+    })
+    .unwrap_or(-1)
+}
+
+/// Delete a vector so that searches no longer return it
+///
+/// # Arguments
+///
+/// - `ptr`: Non-NULL pointer to index
+/// - `id`: ID of the vector to delete
+///
+/// # Returns
+///
+/// - `1` if the vector was deleted
+/// - `0` if it was already deleted
+/// - `-1` on failure (check `chassis_last_error_message()`); an unknown ID
+///   reports `CHASSIS_ERROR_CODE_OUT_OF_BOUNDS`
+///
+/// IDs are never reused. Like `chassis_add`, the deletion becomes durable on
+/// the next `chassis_flush()`.
+///
+/// # Thread Safety
+///
+/// **SINGLE-WRITER**: Takes the index's exclusive lock.
+///
+/// # Safety
+///
+/// - `ptr` must be non-NULL and valid
+/// - `ptr` must not be freed during this call
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_delete(ptr: *mut ChassisIndex, id: u64) -> c_int {
+    ffi_guard(|| {
+        // SAFETY: Caller guarantees ptr is valid; the lock makes access exclusive
+        let state = unsafe { (ptr as *const ChassisIndexState).as_ref() };
+        let mut index = match state {
+            Some(s) => s.write(),
+            None => {
+                set_last_error(ChassisErrorCode::NullPointer, "Null index pointer");
+                return -1;
+            }
+        };
+
+        match index.delete(id) {
+            Ok(deleted) => {
+                clear_last_error();
+                c_int::from(deleted)
+            }
+            Err(e) => {
+                set_core_error(&e);
+                -1
+            }
+        }
+    })
+    .unwrap_or(-1)
+}
+
+/// Replace a vector, returning the ID of the replacement
+///
+/// # Arguments
+///
+/// - `ptr`: Non-NULL pointer to index
+/// - `id`: ID of the vector to replace
+/// - `vector`: Pointer to f32 array (must not be NULL)
+/// - `len`: Number of elements in vector (must match index dimensions)
+///
+/// # Returns
+///
+/// - ID of the new vector on success; `id` is deleted
+/// - `UINT64_MAX` on failure (check `chassis_last_error_message()`); a
+///   missing or deleted `id` reports `CHASSIS_ERROR_CODE_OUT_OF_BOUNDS`
+///
+/// The graph has no in-place updates, so the replacement always gets a new
+/// ID. Durable on the next `chassis_flush()`.
+///
+/// # Thread Safety
+///
+/// **SINGLE-WRITER**: Takes the index's exclusive lock.
+///
+/// # Safety
+///
+/// - `ptr` must be non-NULL and valid
+/// - `vector` must point to `len` valid f32 values
+/// - `ptr` must not be freed during this call
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_update(
+    ptr: *mut ChassisIndex,
+    id: u64,
+    vector: *const c_float,
+    len: size_t,
+) -> u64 {
+    ffi_guard(|| {
+        // SAFETY: Caller guarantees ptr is valid; the lock makes access exclusive
+        let state = unsafe { (ptr as *const ChassisIndexState).as_ref() };
+        let mut index = match state {
+            Some(s) => s.write(),
+            None => {
+                set_last_error(ChassisErrorCode::NullPointer, "Null index pointer");
+                return u64::MAX;
+            }
+        };
+
+        if vector.is_null() {
+            set_last_error(ChassisErrorCode::NullPointer, "Null vector pointer");
+            return u64::MAX;
+        }
+
+        if len == 0 {
+            set_last_error(ChassisErrorCode::InvalidArgument, "Vector length must be > 0");
+            return u64::MAX;
+        }
+
+        // SAFETY: Caller guarantees vector points to len valid f32 values
+        let slice = unsafe { slice::from_raw_parts(vector, len) };
+
+        match index.update(id, slice) {
+            Ok(new_id) => {
+                clear_last_error();
+                new_id
+            }
+            Err(e) => {
+                set_core_error(&e);
+                u64::MAX
+            }
+        }
+    })
+    .unwrap_or(u64::MAX)
+}
+
+/// Copy a stored vector into a caller-provided buffer
+///
+/// # Arguments
+///
+/// - `ptr`: Non-NULL pointer to index (shared access)
+/// - `id`: ID of the vector to read
+/// - `out_buf`: Output buffer (must not be NULL)
+/// - `len`: Capacity of `out_buf` in floats (at least `chassis_dimensions()`)
+///
+/// # Returns
+///
+/// - Number of floats written (the index dimensions) on success
+/// - `0` on failure (check `chassis_last_error_message()`); a missing or
+///   deleted `id` reports `CHASSIS_ERROR_CODE_OUT_OF_BOUNDS`, a short buffer
+///   `CHASSIS_ERROR_CODE_INVALID_ARGUMENT`
+///
+/// The vector is returned as stored: truncated to the index dimensions and
+/// normalized if the index normalizes, decoded to f32 whatever the element
+/// type.
+///
+/// # Thread Safety
+///
+/// **MULTI-READER**: Runs concurrently with searches.
+///
+/// # Safety
+///
+/// - `ptr` must be non-NULL and valid
+/// - `out_buf` must have space for `len` f32 values
+/// - `ptr` must not be freed during this call
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_get_vector(
+    ptr: *const ChassisIndex,
+    id: u64,
+    out_buf: *mut c_float,
+    len: size_t,
+) -> size_t {
+    ffi_guard(|| {
+        // SAFETY: Caller guarantees ptr is valid (shared access)
+        let state = unsafe { (ptr as *const ChassisIndexState).as_ref() };
+        let index = match state {
+            Some(s) => s.read(),
+            None => {
+                set_last_error(ChassisErrorCode::NullPointer, "Null index pointer");
+                return 0;
+            }
+        };
+
+        // SAFETY: Caller guarantees out_buf has space for len floats
+        unsafe { copy_vector(&index, id, out_buf, len) }
+    })
+    .unwrap_or(0)
+}
+
+//
+//  INTROSPECTION
+//
+
+/// Get the number of vectors in the index
+///
+/// # Arguments
+///
+/// - `ptr`: Non-NULL pointer to index (shared access)
+///
+/// # Returns
+///
+/// - Number of vectors, or 0 if `ptr` is NULL
+///
+/// # Thread Safety
+///
+/// Safe to call concurrently with `chassis_search()`.
+///
+/// # Safety
+///
+/// - `ptr` must be non-NULL and valid
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_len(ptr: *const ChassisIndex) -> u64 {
+    ffi_guard(|| {
+        let state = unsafe { (ptr as *const ChassisIndexState).as_ref() };
+        let index = match state {
+            Some(s) => s.read(),
+            None => return 0,
+        };
+
+        index.len()
+    })
+    .unwrap_or(0)
+}
+
+/// Check if the index is empty
+///
+/// # Arguments
+///
+/// - `ptr`: Non-NULL pointer to index (shared access)
+///
+/// # Returns
+///
+/// - 1 if empty, 0 if not empty or `ptr` is NULL
+///
+/// # Safety
+///
+/// - `ptr` must be non-NULL and valid
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_is_empty(ptr: *const ChassisIndex) -> c_int {
+    ffi_guard(|| {
+        let state = unsafe { (ptr as *const ChassisIndexState).as_ref() };
+        let index = match state {
+            Some(s) => s.read(),
+            None => return 0,
+        };
+
+        if index.is_empty() { 1 } else { 0 }
+    })
+    .unwrap_or(0)
+}
+
+/// Get the dimensionality of vectors in the index
+///
+/// # Arguments
+///
+/// - `ptr`: Non-NULL pointer to index (shared access)
+///
+/// # Returns
+///
+/// - Number of dimensions, or 0 if `ptr` is NULL
+///
+/// # Safety
+///
+/// - `ptr` must be non-NULL and valid
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_dimensions(ptr: *const ChassisIndex) -> u32 {
+    ffi_guard(|| {
+        let state = unsafe { (ptr as *const ChassisIndexState).as_ref() };
+        let index = match state {
+            Some(s) => s.read(),
+            None => return 0,
+        };
+
+        index.dimensions()
+    })
+    .unwrap_or(0)
+}
+
+/// Limit the size the index file may grow to
+///
+/// # Arguments
+///
+/// - `ptr`: Non-NULL pointer to index
+/// - `max_file_bytes`: Limit in bytes for the index file and its graph file
+///   together, or `0` for no limit (the default)
+///
+/// # Returns
+///
+/// - 0 on success
+/// - -1 if `ptr` is NULL
+///
+/// Once set, an add that needs the file to grow past the limit fails with
+/// `CHASSIS_ERROR_CODE_QUOTA_EXCEEDED` and leaves the index unchanged, so
+/// apps can keep to a storage budget instead of running out of disk space.
+///
+/// # Thread Safety
+///
+/// **SINGLE-WRITER**: Takes the index's exclusive lock.
+///
+/// # Safety
+///
+/// - `ptr` must be non-NULL and valid
+/// - `ptr` must not be freed during this call
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_set_max_file_bytes(
+    ptr: *mut ChassisIndex,
+    max_file_bytes: u64,
+) -> c_int {
+    ffi_guard(|| {
+        // SAFETY: Caller guarantees ptr is valid; the lock makes access exclusive
+        let state = unsafe { (ptr as *const ChassisIndexState).as_ref() };
+        let mut index = match state {
+            Some(s) => s.write(),
+            None => {
+                set_last_error(ChassisErrorCode::NullPointer, "Null index pointer");
+                return -1;
+            }
+        };
+
+        index.set_max_file_bytes((max_file_bytes > 0).then_some(max_file_bytes));
+        clear_last_error();
+        0
+    })
+    .unwrap_or(-1)
+}
+
+/// Report the progress of long operations on this index, and let the host
+/// cancel them
+///
+/// # Arguments
+///
+/// - `ptr`: Non-NULL pointer to index
+/// - `callback`: Called with the stage, work done, total and `user_data`, or
+///   NULL to remove the callback
+/// - `user_data`: Passed to every call unchanged; may be NULL
+///
+/// # Returns
+///
+/// - 0 on success
+/// - -1 if `ptr` is NULL
+///
+/// `chassis_add_batch()` and `chassis_optimize()` call the callback when they
+/// start, every 64 units of work and when they finish, so GUI hosts can show
+/// a progress bar. Returning nonzero stops the operation before the next unit:
+/// it fails with `CHASSIS_ERROR_CODE_CANCELLED` and keeps the work done so far.
+/// The return value of the final call, made when the work is complete, is
+/// ignored.
+///
+/// # Thread Safety
+///
+/// **SINGLE-WRITER**: Takes the index's exclusive lock to set the callback.
+/// The callback runs on whichever thread performs the operation, while that
+/// thread holds the index lock: it must not call `chassis_*` functions on the
+/// same index, and should hand UI updates to the UI thread.
+///
+/// # Example (C)
+///
+/// ```c
+/// int on_progress(uint32_t stage, uint64_t done, uint64_t total, void *user_data) {
+///     struct job *job = user_data;
+///     atomic_store(&job->percent, total ? (int)(100 * done / total) : 100);
+///     return atomic_load(&job->cancel_requested);
+/// }
+///
+/// chassis_set_progress_callback(index, on_progress, &job);
+/// size_t added = chassis_add_batch(index, batch, n, 768, ids);
+/// if (added < n && chassis_last_error_code() == CHASSIS_ERROR_CODE_CANCELLED) {
+///     // the first `added` vectors are in the index
+/// }
+/// ```
+///
+/// # Safety
+///
+/// - `ptr` must be non-NULL and valid
+/// - `callback` and `user_data` must stay valid, and be safe to use from any
+///   thread that calls into the index, until the callback is replaced or the
+///   index is freed
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_set_progress_callback(
+    ptr: *mut ChassisIndex,
+    callback: ChassisProgressFn,
+    user_data: *mut c_void,
+) -> c_int {
+    ffi_guard(|| {
+        // SAFETY: Caller guarantees ptr is valid; the lock makes access exclusive
+        let Some(state) = (unsafe { (ptr as *const ChassisIndexState).as_ref() }) else {
+            set_last_error(ChassisErrorCode::NullPointer, "Null index pointer");
+            return -1;
+        };
+        let mut index = state.write();
+
+        let progress = callback.map(|callback| ProgressCallback { callback, user_data });
+        index.set_progress_handler(progress.map(|progress| {
+            Arc::new(move |stage, done, total| {
+                ChassisProgressStage::from_core(stage)
+                    .is_none_or(|stage| progress.report(stage, done, total))
+            }) as Arc<ProgressHandler>
+        }));
+        *state.progress.lock().unwrap_or_else(PoisonError::into_inner) = progress;
+        clear_last_error();
+        0
+    })
+    .unwrap_or(-1)
+}
+
+/// Improve search quality by selecting every node's neighbors again
+///
+/// # Arguments
+///
+/// - `ptr`: Non-NULL pointer to index
+/// - `iterations`: Passes over the graph; 1 is usually enough
+///
+/// # Returns
+///
+/// - Number of nodes whose neighbors changed
+/// - -1 on failure (check `chassis_last_error_message()`), including
+///   `CHASSIS_ERROR_CODE_CANCELLED` if the progress callback stopped it
+///
+/// Nodes added early chose their neighbors among the few vectors present
+/// then; a pass rewrites their neighbor lists against the whole index. A pass
+/// costs about as much as adding every vector again, so run it when the app
+/// is idle, with a progress callback for long runs, and call `chassis_flush()`
+/// afterwards. A cancelled pass keeps the lists it already rewrote.
+///
+/// # Thread Safety
+///
+/// **SINGLE-WRITER**: Takes the index's exclusive lock; searches wait until
+/// it returns.
+///
+/// # Safety
+///
+/// - `ptr` must be non-NULL and valid
+/// - `ptr` must not be freed during this call
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_optimize(ptr: *mut ChassisIndex, iterations: u32) -> i64 {
+    ffi_guard(|| {
+        // SAFETY: Caller guarantees ptr is valid; the lock makes access exclusive
+        let state = unsafe { (ptr as *const ChassisIndexState).as_ref() };
+        let mut index = match state {
+            Some(s) => s.write(),
+            None => {
+                set_last_error(ChassisErrorCode::NullPointer, "Null index pointer");
+                return -1;
+            }
+        };
+
+        match index.optimize(iterations as usize) {
+            Ok(changed) => {
+                clear_last_error();
+                changed as i64
+            }
+            Err(e) => {
+                set_core_error(&e);
+                -1
+            }
+        }
+    })
+    .unwrap_or(-1)
+}
+
+//
+//  WRITER AND READER HANDLES
+//
+
+/// Open or create an index behind a single-writer handle
+///
+/// Unlike `chassis_open()`, the handle cannot be passed to searches: create
+/// readers with `chassis_open_reader_from()`. Writes take the index lock
+/// exclusively for one call, so readers never observe a remap, and writer
+/// calls from several threads are serialized.
+///
+/// # Arguments
+///
+/// - `path`: UTF-8 encoded path to the index file (must not be NULL)
+/// - `dimensions`: Number of dimensions per vector (must be > 0)
+/// - `options`: Options from `chassis_options_default()`, or NULL for the defaults
+///
+/// # Returns
+///
+/// - Non-NULL pointer on success; free it with `chassis_writer_free()`
+/// - NULL on failure (check `chassis_last_error_message()`)
+///
+/// # Example (C)
+///
+/// ```c
+/// ChassisWriter* writer = chassis_open_writer("vectors.chassis", 768, NULL);
+/// ChassisReader* reader = chassis_open_reader_from(writer);
+/// // Hand `reader` to search threads; keep `writer` on the ingest thread
+/// ```
+///
+/// # Safety
+///
+/// Same safety requirements as `chassis_open_with_config()`, except that
+/// `options` may be NULL
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_open_writer(
+    path: *const c_char,
+    dimensions: u32,
+    options: *const ChassisOptions,
+) -> *mut ChassisWriter {
+    ffi_guard(|| {
+        if path.is_null() {
+            set_last_error(ChassisErrorCode::NullPointer, "Path cannot be NULL");
+            return ptr::null_mut();
+        }
+
+        if dimensions == 0 {
+            set_last_error(ChassisErrorCode::InvalidArgument, "Dimensions must be > 0");
+            return ptr::null_mut();
+        }
+
+        // SAFETY: Caller guarantees options is NULL or points to a valid ChassisOptions
+        let options = match unsafe { options.as_ref() } {
+            None => IndexOptions::default(),
+            Some(options) => match options.to_index_options() {
+                Ok(options) => options,
+                Err(msg) => {
+                    set_last_error(ChassisErrorCode::InvalidArgument, msg);
+                    return ptr::null_mut();
+                }
+            },
+        };
+
+        // SAFETY: Caller guarantees path is valid C string
+        let c_path = unsafe { CStr::from_ptr(path) };
+
+        let path_str = match c_path.to_str() {
+            Ok(s) => s,
+            Err(_) => {
+                set_last_error(ChassisErrorCode::InvalidUtf8, "Path must be valid UTF-8");
+                return ptr::null_mut();
+            }
+        };
+
+        match VectorIndex::open(path_str, dimensions, options) {
+            Ok(index) => {
+                clear_last_error();
+                let (writer, _) = index.into_handles();
+                let state = Box::new(ChassisWriterState { inner: Mutex::new(writer) });
+                Box::into_raw(state) as *mut ChassisWriter
+            }
+            Err(e) => {
+                set_core_error(&e);
+                ptr::null_mut()
+            }
+        }
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+/// Create a reader for the index held by `writer`
+///
+/// Each reader is independent: free it with `chassis_reader_free()`. The
+/// index stays open until the writer and every reader have been freed, so a
+/// reader outlives its writer safely (it then sees the final state).
+///
+/// # Returns
+///
+/// - Non-NULL pointer on success
+/// - NULL if `writer` is NULL
+///
+/// # Safety
+///
+/// - `writer` must be NULL or a valid pointer from `chassis_open_writer()`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_open_reader_from(
+    writer: *const ChassisWriter,
+) -> *mut ChassisReader {
+    ffi_guard(|| {
+        // SAFETY: Caller guarantees writer is NULL or valid
+        let Some(state) = (unsafe { (writer as *const ChassisWriterState).as_ref() }) else {
+            set_last_error(ChassisErrorCode::NullPointer, "Null writer pointer");
+            return ptr::null_mut();
+        };
+
+        clear_last_error();
+        Box::into_raw(Box::new(state.lock().reader())) as *mut ChassisReader
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+/// Add a vector through a writer; see `chassis_add()`
+///
+/// # Returns
+///
+/// - Vector ID on success; readers see it once this returns
+/// - `UINT64_MAX` on failure (check `chassis_last_error_message()`)
+///
+/// # Safety
+///
+/// - `writer` must be non-NULL and valid, and not freed during this call
+/// - `vector` must point to `len` valid f32 values
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_writer_add(
+    writer: *mut ChassisWriter,
+    vector: *const c_float,
+    len: size_t,
+) -> u64 {
+    ffi_guard(|| {
+        // SAFETY: Caller guarantees writer is valid; the mutex serializes writers
+        let Some(state) = (unsafe { (writer as *const ChassisWriterState).as_ref() }) else {
+            set_last_error(ChassisErrorCode::NullPointer, "Null writer pointer");
+            return u64::MAX;
+        };
+
+        // SAFETY: Caller guarantees vector points to len valid f32 values
+        state.lock().write(|index| unsafe { add_vector(index, vector, len) })
+    })
+    .unwrap_or(u64::MAX)
+}
+
+/// Delete a vector through a writer; see `chassis_delete()`
+///
+/// # Returns
 ///
-/// size_t count = chassis_search(index, query, 768, 10, ids, dists);
-/// for (size_t i = 0; i < count; i++) {
-///     printf("ID: %llu, Distance: %f\n", ids[i], dists[i]);
-/// }
-/// ```
+/// - `1` if the vector was deleted, `0` if it was already deleted
+/// - `-1` on failure (check `chassis_last_error_message()`)
 ///
 /// # Safety
 ///
-/// - `ptr` must be non-NULL and valid
-/// - `query` must point to `len` valid f32 values
-/// - `out_ids` must have space for at least `k` u64 values
-/// - `out_dists` must have space for at least `k` float values
-/// - Buffers must not overlap
+/// - `writer` must be non-NULL and valid, and not freed during this call
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn chassis_search(
-    ptr: *const ChassisIndex,
-    query: *const c_float,
-    len: size_t,
-    k: size_t,
-    out_ids: *mut u64,
-    out_dists: *mut c_float,
-) -> size_t {
+pub unsafe extern "C" fn chassis_writer_delete(writer: *mut ChassisWriter, id: u64) -> c_int {
     ffi_guard(|| {
-        // SAFETY: Caller guarantees ptr is valid (shared access)
-        let state = unsafe { (ptr as *const ChassisIndexState).as_ref() };
-        let index = match state {
-            Some(s) => &s.inner,
-            None => {
-                set_last_error("Null index pointer");
-                return 0;
-            }
+        // SAFETY: Caller guarantees writer is valid; the mutex serializes writers
+        let Some(state) = (unsafe { (writer as *const ChassisWriterState).as_ref() }) else {
+            set_last_error(ChassisErrorCode::NullPointer, "Null writer pointer");
+            return -1;
         };
 
-        if query.is_null() || out_ids.is_null() || out_dists.is_null() {
-            set_last_error("Null buffer pointers");
-            return 0;
-        }
-
-        if k == 0 {
-            set_last_error("k must be > 0");
-            return 0;
-        }
-
-        // SAFETY: Caller guarantees query points to len valid f32 values
-        let query_slice = unsafe { slice::from_raw_parts(query, len) };
-
-        match index.search(query_slice, k) {
-            Ok(results) => {
-                let count = results.len();
-
-                // SAFETY: Caller guarantees out_ids and out_dists have space for k elements
-                for (i, result) in results.iter().enumerate() {
-                    unsafe {
-                        *out_ids.add(i) = result.id;
-                        *out_dists.add(i) = result.distance;
-                    }
-                }
-
+        match state.lock().write(|index| index.delete(id)) {
+            Ok(deleted) => {
                 clear_last_error();
-                count
+                c_int::from(deleted)
             }
             Err(e) => {
-                set_last_error(e);
-                0
+                set_core_error(&e);
+                -1
             }
         }
     })
-    .unwrap_or(0)
+    .unwrap_or(-1)
 }
 
-/// Flush all changes to disk
-///
-/// # Arguments
-///
-/// - `ptr`: Non-NULL pointer to index (requires exclusive access)
+/// Flush all changes made through a writer to disk; see `chassis_flush()`
 ///
 /// # Returns
 ///
 /// - 0 on success
 /// - -1 on failure (check `chassis_last_error_message()`)
 ///
-/// # Thread Safety
-///
-/// **SINGLE-WRITER**: Only one thread may call this function at a time for a
-/// given index. No other operations (read or write) may occur during flush.
-///
-/// # Performance Warning
-///
-/// This operation is expensive (1-50ms depending on storage device).
-/// Batch multiple `chassis_add()` calls and flush once at the end.
-///
-/// # Example (C)
-///
-/// ```c
-/// // Add many vectors
-/// for (int i = 0; i < 1000; i++) {
-///     chassis_add(index, vectors[i], 768);
-/// }
-///
-/// // Flush once at the end
-/// if (chassis_flush(index) != 0) {
-///     fprintf(stderr, "Flush failed: %s\n", chassis_last_error_message());
-/// }
-/// ```
-///
 /// # Safety
 ///
-/// - `ptr` must be non-NULL and valid
-/// - No other thread may access `ptr` during this call
+/// - `writer` must be non-NULL and valid, and not freed during this call
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn chassis_flush(ptr: *mut ChassisIndex) -> c_int {
+pub unsafe extern "C" fn chassis_writer_flush(writer: *mut ChassisWriter) -> c_int {
     ffi_guard(|| {
-        // SAFETY: Caller guarantees ptr is valid and has exclusive access
-        let state = unsafe { (ptr as *mut ChassisIndexState).as_mut() };
-        let index = match state {
-            Some(s) => &mut s.inner,
-            None => {
-                set_last_error("Null index pointer");
-                return -1;
-            }
+        // SAFETY: Caller guarantees writer is valid; the mutex serializes writers
+        let Some(state) = (unsafe { (writer as *const ChassisWriterState).as_ref() }) else {
+            set_last_error(ChassisErrorCode::NullPointer, "Null writer pointer");
+            return -1;
         };
 
-        match index.flush() {
-            Ok(_) => {
+        match state.lock().flush() {
+            Ok(()) => {
                 clear_last_error();
                 0
             }
             Err(e) => {
-                set_last_error(e);
+                set_core_error(&e);
                 -1
             }
         }
@@ -691,95 +2030,131 @@ pub unsafe extern "C" fn chassis_flush(ptr: *mut ChassisIndex) -> c_int {
     .unwrap_or(-1)
 }
 
-//
-//  INTROSPECTION
-//
-
-/// Get the number of vectors in the index
-///
-/// # Arguments
+/// Free a writer
 ///
-/// - `ptr`: Non-NULL pointer to index (shared access)
+/// Readers created from it stay valid; the index closes once they are freed
+/// too. Unflushed changes are not written: call `chassis_writer_flush()` first.
 ///
-/// # Returns
+/// # Safety
 ///
-/// - Number of vectors, or 0 if `ptr` is NULL
+/// - `writer` must be NULL or a valid pointer from `chassis_open_writer()`
+/// - No other thread may use `writer` during or after this call
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_writer_free(writer: *mut ChassisWriter) {
+    if !writer.is_null() {
+        ffi_guard(|| {
+            // SAFETY: Caller guarantees writer is valid (from chassis_open_writer)
+            let _ = unsafe { Box::from_raw(writer as *mut ChassisWriterState) };
+        });
+    }
+}
+
+/// Search through a reader; see `chassis_search()`
 ///
 /// # Thread Safety
 ///
-/// Safe to call concurrently with `chassis_search()`.
+/// **MULTI-READER**: Any number of threads may search through one reader, or
+/// through several, while the writer inserts. A search waits for at most one
+/// write call in progress.
 ///
 /// # Safety
 ///
-/// - `ptr` must be non-NULL and valid
+/// - `reader` must be non-NULL and valid, and not freed during this call
+/// - Same buffer requirements as `chassis_search()`
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn chassis_len(ptr: *const ChassisIndex) -> u64 {
+pub unsafe extern "C" fn chassis_reader_search(
+    reader: *const ChassisReader,
+    query: *const c_float,
+    len: size_t,
+    k: size_t,
+    out_ids: *mut u64,
+    out_dists: *mut c_float,
+) -> size_t {
     ffi_guard(|| {
-        let state = unsafe { (ptr as *const ChassisIndexState).as_ref() };
-        let index = match state {
-            Some(s) => &s.inner,
-            None => return 0,
+        // SAFETY: Caller guarantees reader is valid; ReadHandle is thread-safe
+        let Some(reader) = (unsafe { (reader as *const ReadHandle).as_ref() }) else {
+            set_last_error(ChassisErrorCode::NullPointer, "Null reader pointer");
+            return 0;
         };
 
-        index.len()
+        // SAFETY: Caller guarantees the query and output buffers are valid
+        reader.read(|index| unsafe { search_vectors(index, query, len, k, out_ids, out_dists) })
     })
     .unwrap_or(0)
 }
 
-/// Check if the index is empty
-///
-/// # Arguments
-///
-/// - `ptr`: Non-NULL pointer to index (shared access)
-///
-/// # Returns
-///
-/// - 1 if empty, 0 if not empty or `ptr` is NULL
+/// Copy a stored vector through a reader; see `chassis_get_vector()`
 ///
 /// # Safety
 ///
-/// - `ptr` must be non-NULL and valid
+/// - `reader` must be non-NULL and valid, and not freed during this call
+/// - `out_buf` must have space for `len` f32 values
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn chassis_is_empty(ptr: *const ChassisIndex) -> c_int {
+pub unsafe extern "C" fn chassis_reader_get_vector(
+    reader: *const ChassisReader,
+    id: u64,
+    out_buf: *mut c_float,
+    len: size_t,
+) -> size_t {
     ffi_guard(|| {
-        let state = unsafe { (ptr as *const ChassisIndexState).as_ref() };
-        let index = match state {
-            Some(s) => &s.inner,
-            None => return 0,
+        // SAFETY: Caller guarantees reader is valid; ReadHandle is thread-safe
+        let Some(reader) = (unsafe { (reader as *const ReadHandle).as_ref() }) else {
+            set_last_error(ChassisErrorCode::NullPointer, "Null reader pointer");
+            return 0;
         };
 
-        if index.is_empty() { 1 } else { 0 }
+        // SAFETY: Caller guarantees out_buf has space for len floats
+        reader.read(|index| unsafe { copy_vector(index, id, out_buf, len) })
     })
     .unwrap_or(0)
 }
 
-/// Get the dimensionality of vectors in the index
-///
-/// # Arguments
+/// Get the number of vectors visible to a reader, or 0 if `reader` is NULL
 ///
-/// - `ptr`: Non-NULL pointer to index (shared access)
+/// Never waits for a write in progress.
 ///
-/// # Returns
+/// # Safety
 ///
-/// - Number of dimensions, or 0 if `ptr` is NULL
+/// - `reader` must be NULL or valid
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_reader_len(reader: *const ChassisReader) -> u64 {
+    ffi_guard(|| {
+        // SAFETY: Caller guarantees reader is NULL or valid
+        unsafe { (reader as *const ReadHandle).as_ref() }.map_or(0, ReadHandle::len)
+    })
+    .unwrap_or(0)
+}
+
+/// Get the dimensionality of vectors in a reader's index, or 0 if `reader` is NULL
 ///
 /// # Safety
 ///
-/// - `ptr` must be non-NULL and valid
+/// - `reader` must be NULL or valid
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn chassis_dimensions(ptr: *const ChassisIndex) -> u32 {
+pub unsafe extern "C" fn chassis_reader_dimensions(reader: *const ChassisReader) -> u32 {
     ffi_guard(|| {
-        let state = unsafe { (ptr as *const ChassisIndexState).as_ref() };
-        let index = match state {
-            Some(s) => &s.inner,
-            None => return 0,
-        };
-
-        index.dimensions()
+        // SAFETY: Caller guarantees reader is NULL or valid
+        unsafe { (reader as *const ReadHandle).as_ref() }.map_or(0, ReadHandle::dimensions)
     })
     .unwrap_or(0)
 }
 
+/// Free a reader
+///
+/// # Safety
+///
+/// - `reader` must be NULL or a valid pointer from `chassis_open_reader_from()`
+/// - No other thread may use `reader` during or after this call
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chassis_reader_free(reader: *mut ChassisReader) {
+    if !reader.is_null() {
+        ffi_guard(|| {
+            // SAFETY: Caller guarantees reader is valid (from chassis_open_reader_from)
+            let _ = unsafe { Box::from_raw(reader as *mut ReadHandle) };
+        });
+    }
+}
+
 //
 //  ERROR HANDLING
 //
@@ -819,6 +2194,31 @@ pub extern "C" fn chassis_last_error_message() -> *const c_char {
     LAST_ERROR.with(|cell| cell.borrow().as_ref().map(|s| s.as_ptr()).unwrap_or(ptr::null()))
 }
 
+/// Get the last error code for the current thread
+///
+/// # Returns
+///
+/// - `CHASSIS_ERROR_CODE_OK` if the last call on this thread succeeded
+/// - Otherwise the category of the failure; the message from
+///   `chassis_last_error_message()` carries the details
+///
+/// Codes are stable across releases, so callers can branch on them instead of
+/// parsing messages. Like the message, the code is per-thread and reflects the
+/// most recent failing or clearing call.
+///
+/// # Example (C)
+///
+/// ```c
+/// ChassisIndex* index = chassis_open("vectors.chassis", 768);
+/// if (index == NULL && chassis_last_error_code() == CHASSIS_ERROR_CODE_LOCKED) {
+///     index = chassis_open_shared("vectors.chassis", 768);
+/// }
+/// ```
+#[unsafe(no_mangle)]
+pub extern "C" fn chassis_last_error_code() -> ChassisErrorCode {
+    LAST_ERROR_CODE.with(Cell::get)
+}
+
 //
 //  VERSIONING
 //
@@ -848,6 +2248,27 @@ pub extern "C" fn chassis_version() -> *const c_char {
 
     VERSION.as_ptr() as *const c_char
 }
+
+/// Get the version of the C ABI implemented by the loaded library
+///
+/// # Returns
+///
+/// `CHASSIS_ABI_VERSION` of the library build. Bindings should refuse to use
+/// a library whose ABI version differs from the `CHASSIS_ABI_VERSION` of the
+/// header they were built against.
+///
+/// # Example (C)
+///
+/// ```c
+/// if (chassis_abi_version() != CHASSIS_ABI_VERSION) {
+///     fprintf(stderr, "libchassis_ffi ABI mismatch\n");
+///     exit(1);
+/// }
+/// ```
+#[unsafe(no_mangle)]
+pub extern "C" fn chassis_abi_version() -> u32 {
+    CHASSIS_ABI_VERSION
+}
 //
 //  TESTS
 //
@@ -1071,6 +2492,371 @@ mod tests {
         unsafe { chassis_free(ptr) };
     }
 
+    #[test]
+    fn test_ffi_search_while_adding() {
+        let (_dir, path) = temp_index_path();
+        let ptr = unsafe { chassis_open(path.as_ptr(), 16) };
+        assert!(!ptr.is_null());
+        let vec = [0.0f32; 16];
+        assert_eq!(unsafe { chassis_add(ptr, vec.as_ptr(), 16) }, 0);
+
+        // Raw pointers are not Send; the index itself serializes access
+        let addr = ptr as usize;
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(move || {
+                    let ptr = addr as *const ChassisIndex;
+                    let mut ids = [0u64; 5];
+                    let mut dists = [0f32; 5];
+                    for _ in 0..200 {
+                        let found = unsafe {
+                            chassis_search(
+                                ptr,
+                                vec.as_ptr(),
+                                16,
+                                5,
+                                ids.as_mut_ptr(),
+                                dists.as_mut_ptr(),
+                            )
+                        };
+                        assert!(found >= 1);
+                        assert_eq!(dists[0], 0.0);
+                    }
+                });
+            }
+
+            // Enough inserts to grow and remap the file several times
+            scope.spawn(move || {
+                let ptr = addr as *mut ChassisIndex;
+                for i in 1..2000 {
+                    let row = [i as f32; 16];
+                    assert_eq!(unsafe { chassis_add(ptr, row.as_ptr(), 16) }, i);
+                }
+            });
+        });
+
+        assert_eq!(unsafe { chassis_len(ptr) }, 2000);
+        unsafe { chassis_free(ptr) };
+    }
+
+    #[test]
+    fn test_ffi_open_shared() {
+        let (_dir, path) = temp_index_path();
+        let writer = unsafe { chassis_open(path.as_ptr(), 32) };
+        assert!(!writer.is_null());
+        let vec = [0.5f32; 32];
+        assert_eq!(unsafe { chassis_add(writer, vec.as_ptr(), 32) }, 0);
+        assert_eq!(unsafe { chassis_flush(writer) }, 0);
+
+        // Writer holds the exclusive lock
+        assert!(unsafe { chassis_open_shared(path.as_ptr(), 32) }.is_null());
+        unsafe { chassis_free(writer) };
+
+        let first = unsafe { chassis_open_shared(path.as_ptr(), 32) };
+        let second = unsafe { chassis_open_shared(path.as_ptr(), 32) };
+        assert!(!first.is_null() && !second.is_null());
+        assert_eq!(unsafe { chassis_len(second) }, 1);
+        assert_eq!(unsafe { chassis_add(first, vec.as_ptr(), 32) }, u64::MAX);
+        assert!(unsafe { chassis_open(path.as_ptr(), 32) }.is_null());
+
+        unsafe { chassis_free(first) };
+        unsafe { chassis_free(second) };
+    }
+
+    #[test]
+    fn test_ffi_error_codes() {
+        let (_dir, path) = temp_index_path();
+
+        assert!(unsafe { chassis_open(ptr::null(), 32) }.is_null());
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::NullPointer);
+
+        assert!(unsafe { chassis_open(path.as_ptr(), 0) }.is_null());
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::InvalidArgument);
+
+        let index = unsafe { chassis_open(path.as_ptr(), 32) };
+        assert!(!index.is_null());
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::Ok);
+
+        // Second writer on the same file
+        assert!(unsafe { chassis_open(path.as_ptr(), 32) }.is_null());
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::Locked);
+
+        let short = [1.0f32; 16];
+        assert_eq!(unsafe { chassis_add(index, short.as_ptr(), 16) }, u64::MAX);
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::DimensionMismatch);
+
+        let vec = [0.5f32; 32];
+        assert_eq!(unsafe { chassis_add(index, vec.as_ptr(), 32) }, 0);
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::Ok);
+        assert_eq!(unsafe { chassis_flush(index) }, 0);
+        unsafe { chassis_free(index) };
+
+        // Existing file opened with the wrong dimensions
+        assert!(unsafe { chassis_open(path.as_ptr(), 64) }.is_null());
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::DimensionMismatch);
+
+        let reader = unsafe { chassis_open_shared(path.as_ptr(), 32) };
+        assert!(!reader.is_null());
+        assert_eq!(unsafe { chassis_add(reader, vec.as_ptr(), 32) }, u64::MAX);
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::ReadOnly);
+        unsafe { chassis_free(reader) };
+    }
+
+    #[test]
+    fn test_ffi_snapshot_to() {
+        let (dir, path) = temp_index_path();
+        let backup = CString::new(dir.path().join("backup.chassis").to_str().unwrap()).unwrap();
+
+        let index = unsafe { chassis_open(path.as_ptr(), 32) };
+        let vec = [0.5f32; 32];
+        assert_eq!(unsafe { chassis_add(index, vec.as_ptr(), 32) }, 0);
+        assert_eq!(unsafe { chassis_snapshot_to(index, backup.as_ptr()) }, 0);
+        assert_eq!(unsafe { chassis_snapshot_to(index, ptr::null()) }, -1);
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::NullPointer);
+        unsafe { chassis_free(index) };
+
+        let copy = unsafe { chassis_open(backup.as_ptr(), 32) };
+        assert!(!copy.is_null());
+        assert_eq!(unsafe { chassis_len(copy) }, 1);
+        unsafe { chassis_free(copy) };
+    }
+
+    #[test]
+    fn test_ffi_progress_callback() {
+        /// Records every report and cancels once `done` reaches `stop_at`
+        struct Job {
+            reports: Vec<(u32, u64, u64)>,
+            stop_at: u64,
+        }
+
+        unsafe extern "C" fn on_progress(
+            stage: u32,
+            done: u64,
+            total: u64,
+            user_data: *mut c_void,
+        ) -> c_int {
+            let job = unsafe { &mut *(user_data as *mut Job) };
+            job.reports.push((stage, done, total));
+            c_int::from(done >= job.stop_at)
+        }
+
+        let (_dir, path) = temp_index_path();
+        let index = unsafe { chassis_open(path.as_ptr(), 4) };
+        let mut job = Job { reports: Vec::new(), stop_at: u64::MAX };
+        let user_data = &mut job as *mut Job as *mut c_void;
+        assert_eq!(
+            unsafe { chassis_set_progress_callback(index, Some(on_progress), user_data) },
+            0
+        );
+
+        let vectors: Vec<f32> = (0..400).map(|i| (i as f32 * 0.37).sin()).collect();
+        let mut ids = [0u64; 100];
+        let added = unsafe { chassis_add_batch(index, vectors.as_ptr(), 100, 4, ids.as_mut_ptr()) };
+        assert_eq!(added, 100);
+        let import = ChassisProgressStage::Import as u32;
+        assert_eq!(job.reports, vec![(import, 0, 100), (import, 64, 100), (import, 100, 100)]);
+
+        // Nonzero stops before the next vector; the inserted ones stay
+        job.reports.clear();
+        job.stop_at = 64;
+        let added = unsafe { chassis_add_batch(index, vectors.as_ptr(), 100, 4, ids.as_mut_ptr()) };
+        assert_eq!(added, 64);
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::Cancelled);
+        assert_eq!(unsafe { chassis_len(index) }, 164);
+
+        // One unit per node and pass: 164 nodes, reports at 0, 64 and 128
+        job.reports.clear();
+        job.stop_at = 100;
+        assert_eq!(unsafe { chassis_optimize(index, 1) }, -1);
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::Cancelled);
+        let optimize = ChassisProgressStage::Optimize as u32;
+        assert_eq!(job.reports.last(), Some(&(optimize, 128, 164)));
+
+        job.stop_at = u64::MAX;
+        assert!(unsafe { chassis_optimize(index, 2) } >= 0);
+        assert_eq!(job.reports.last(), Some(&(optimize, 328, 328)));
+
+        // Removed callbacks are not called
+        job.reports.clear();
+        assert_eq!(unsafe { chassis_set_progress_callback(index, None, ptr::null_mut()) }, 0);
+        unsafe { chassis_add_batch(index, vectors.as_ptr(), 100, 4, ids.as_mut_ptr()) };
+        assert!(unsafe { chassis_optimize(index, 1) } >= 0);
+        assert!(job.reports.is_empty());
+
+        assert_eq!(unsafe { chassis_set_progress_callback(ptr::null_mut(), None, user_data) }, -1);
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::NullPointer);
+        unsafe { chassis_free(index) };
+    }
+
+    #[test]
+    fn test_ffi_open_with_config() {
+        let (_dir, path) = temp_index_path();
+
+        let mut options = chassis_options_default();
+        assert_eq!(options.max_connections, 16);
+        assert_eq!(options.element_type, ChassisElementType::F32 as u32);
+
+        options.element_type = 7;
+        assert!(unsafe { chassis_open_with_config(path.as_ptr(), 32, &options) }.is_null());
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::InvalidArgument);
+        assert!(unsafe { chassis_open_with_config(path.as_ptr(), 32, ptr::null()) }.is_null());
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::NullPointer);
+
+        options.element_type = ChassisElementType::F16 as u32;
+        options.input_dimensions = 64;
+        options.max_connections = 8;
+        let index = unsafe { chassis_open_with_config(path.as_ptr(), 32, &options) };
+        assert!(!index.is_null());
+        let vec = [0.5f32; 64];
+        assert_eq!(unsafe { chassis_add(index, vec.as_ptr(), 64) }, 0);
+        assert_eq!(unsafe { chassis_flush(index) }, 0);
+        unsafe { chassis_free(index) };
+
+        let path = path.to_str().unwrap();
+        let index = VectorIndex::open_existing(path, IndexOptions::default()).unwrap();
+        assert_eq!(index.element_type(), ElementType::F16);
+        assert_eq!(index.options().max_connections, 8);
+    }
+
+    #[test]
+    fn test_header_declares_every_export() {
+        assert_eq!(chassis_abi_version(), CHASSIS_ABI_VERSION);
+
+        let header = include_str!("../include/chassis.h");
+        let abi = format!("#define CHASSIS_ABI_VERSION {}", CHASSIS_ABI_VERSION);
+        assert!(header.contains(&abi), "chassis.h is stale: missing {}", abi);
+
+        for line in include_str!("lib.rs").lines() {
+            let Some((_, rest)) = line.split_once("extern \"C\" fn ") else {
+                continue;
+            };
+            let name = &rest[..rest.find('(').unwrap()];
+            let declared = [' ', '*'].iter().any(|c| header.contains(&format!("{}{}(", c, name)));
+            assert!(declared, "chassis.h does not declare {}", name);
+        }
+    }
+
+    #[test]
+    fn test_ffi_writer_and_readers() {
+        let (_dir, path) = temp_index_path();
+        let writer = unsafe { chassis_open_writer(path.as_ptr(), 8, ptr::null()) };
+        assert!(!writer.is_null());
+        let reader = unsafe { chassis_open_reader_from(writer) };
+        assert!(!reader.is_null());
+        assert_eq!(unsafe { chassis_reader_dimensions(reader) }, 8);
+
+        // Send the pointers across threads as integers, as C callers would
+        let reader_addr = reader as usize;
+        let searchers: Vec<_> = (0..4)
+            .map(|_| {
+                std::thread::spawn(move || {
+                    let reader = reader_addr as *const ChassisReader;
+                    let query = [0.0f32; 8];
+                    let mut ids = [0u64; 4];
+                    let mut dists = [0.0f32; 4];
+                    let mut seen = 0;
+                    while seen < 100 {
+                        let len = unsafe { chassis_reader_len(reader) };
+                        assert!(len >= seen);
+                        seen = len;
+                        let count = unsafe {
+                            chassis_reader_search(
+                                reader,
+                                query.as_ptr(),
+                                8,
+                                4,
+                                ids.as_mut_ptr(),
+                                dists.as_mut_ptr(),
+                            )
+                        };
+                        assert!(count <= 4);
+                    }
+                })
+            })
+            .collect();
+
+        // Enough vectors to grow and remap the file under the searches
+        for i in 0..100 {
+            let vector = [i as f32; 8];
+            assert_eq!(unsafe { chassis_writer_add(writer, vector.as_ptr(), 8) }, i);
+        }
+        for searcher in searchers {
+            searcher.join().unwrap();
+        }
+
+        assert_eq!(unsafe { chassis_writer_delete(writer, 3) }, 1);
+        assert_eq!(unsafe { chassis_writer_flush(writer) }, 0);
+
+        // A reader outlives its writer
+        unsafe { chassis_writer_free(writer) };
+        let mut out = [0.0f32; 8];
+        assert_eq!(unsafe { chassis_reader_get_vector(reader, 7, out.as_mut_ptr(), 8) }, 8);
+        assert_eq!(out, [7.0; 8]);
+        assert_eq!(unsafe { chassis_reader_get_vector(reader, 3, out.as_mut_ptr(), 8) }, 0);
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::OutOfBounds);
+        unsafe { chassis_reader_free(reader) };
+
+        // Null handles are reported, not dereferenced
+        assert!(unsafe { chassis_open_reader_from(ptr::null()) }.is_null());
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::NullPointer);
+        assert_eq!(unsafe { chassis_writer_add(ptr::null_mut(), out.as_ptr(), 8) }, u64::MAX);
+        assert_eq!(unsafe { chassis_reader_len(ptr::null()) }, 0);
+        unsafe { chassis_writer_free(ptr::null_mut()) };
+        unsafe { chassis_reader_free(ptr::null_mut()) };
+    }
+
+    #[test]
+    fn test_ffi_delete_update_get_vector() {
+        let (_dir, path) = temp_index_path();
+        let index = unsafe { chassis_open(path.as_ptr(), 4) };
+        for i in 0..3 {
+            let vec = [i as f32; 4];
+            assert_eq!(unsafe { chassis_add(index, vec.as_ptr(), 4) }, i);
+        }
+
+        let mut out = [0.0f32; 4];
+        assert_eq!(unsafe { chassis_get_vector(index, 1, out.as_mut_ptr(), 4) }, 4);
+        assert_eq!(out, [1.0; 4]);
+        assert_eq!(unsafe { chassis_get_vector(index, 1, out.as_mut_ptr(), 3) }, 0);
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::InvalidArgument);
+        assert_eq!(unsafe { chassis_get_vector(index, 9, out.as_mut_ptr(), 4) }, 0);
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::OutOfBounds);
+
+        let replacement = [5.0f32; 4];
+        let new_id = unsafe { chassis_update(index, 1, replacement.as_ptr(), 4) };
+        assert_eq!(new_id, 3);
+        assert_eq!(unsafe { chassis_get_vector(index, 3, out.as_mut_ptr(), 4) }, 4);
+        assert_eq!(out, replacement);
+        assert_eq!(unsafe { chassis_get_vector(index, 1, out.as_mut_ptr(), 4) }, 0);
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::OutOfBounds);
+        assert_eq!(unsafe { chassis_update(index, 1, replacement.as_ptr(), 4) }, u64::MAX);
+
+        assert_eq!(unsafe { chassis_delete(index, 0) }, 1);
+        assert_eq!(unsafe { chassis_delete(index, 0) }, 0);
+        assert_eq!(unsafe { chassis_delete(index, 9) }, -1);
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::OutOfBounds);
+
+        let mut ids = [0u64; 4];
+        let mut dists = [0.0f32; 4];
+        let query = [0.0f32; 4];
+        let count = unsafe {
+            chassis_search(index, query.as_ptr(), 4, 4, ids.as_mut_ptr(), dists.as_mut_ptr())
+        };
+        assert_eq!(&ids[..count], &[2, 3]);
+        unsafe { chassis_free(index) };
+    }
+
+    #[test]
+    fn test_ffi_error_code_corrupted_file() {
+        let (dir, _path) = temp_index_path();
+        let garbage = dir.path().join("garbage.chassis");
+        std::fs::write(&garbage, vec![0xAB; 8192]).unwrap();
+        let c_path = CString::new(garbage.to_str().unwrap()).unwrap();
+
+        assert!(unsafe { chassis_open(c_path.as_ptr(), 32) }.is_null());
+        assert_eq!(chassis_last_error_code(), ChassisErrorCode::Corrupted);
+    }
+
     #[test]
     fn test_ffi_invalid_utf8_path() {
         // Create a path with invalid UTF-8
@@ -1088,7 +2874,7 @@ mod tests {
         use std::thread;
 
         // Set an error on main thread
-        set_last_error("Main thread error");
+        set_last_error(ChassisErrorCode::Unknown, "Main thread error");
         let main_error = unsafe { CStr::from_ptr(chassis_last_error_message()) };
         assert_eq!(main_error.to_string_lossy(), "Main thread error");
 
@@ -1098,7 +2884,7 @@ mod tests {
             assert!(error_ptr.is_null(), "New thread should have no error");
 
             // Set error on spawned thread
-            set_last_error("Spawned thread error");
+            set_last_error(ChassisErrorCode::Unknown, "Spawned thread error");
             let spawned_error = unsafe { CStr::from_ptr(chassis_last_error_message()) };
             assert_eq!(spawned_error.to_string_lossy(), "Spawned thread error");
         });
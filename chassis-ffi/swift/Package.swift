@@ -0,0 +1,23 @@
+// swift-tools-version:5.9
+
+import PackageDescription
+
+// Swift wrapper around the Chassis C API.
+//
+// `ChassisFFI.xcframework` is not checked in: run `./build-xcframework.sh`
+// first to build it from the Rust sources.
+let package = Package(
+    name: "Chassis",
+    platforms: [
+        .iOS(.v13),
+        .macOS(.v10_15),
+    ],
+    products: [
+        .library(name: "Chassis", targets: ["Chassis"]),
+    ],
+    targets: [
+        .binaryTarget(name: "ChassisFFI", path: "ChassisFFI.xcframework"),
+        .target(name: "Chassis", dependencies: ["ChassisFFI"]),
+        .testTarget(name: "ChassisTests", dependencies: ["Chassis"]),
+    ]
+)
@@ -0,0 +1,56 @@
+import ChassisFFI
+
+/// An error reported by the Chassis library
+public struct ChassisError: Error, Equatable, CustomStringConvertible {
+    /// Category of the failure, mirroring `ChassisErrorCode` in `chassis.h`
+    public enum Code: UInt32, Sendable {
+        case invalidArgument = 1
+        case nullPointer = 2
+        case invalidUTF8 = 3
+        case dimensionMismatch = 4
+        case locked = 5
+        case corrupted = 6
+        case io = 7
+        case outOfBounds = 8
+        case fileStolen = 9
+        case readOnly = 10
+        case panic = 11
+        case unknown = 12
+        case incompatibleVersion = 13
+        case quotaExceeded = 14
+        case cancelled = 15
+    }
+
+    /// Category of the failure
+    public let code: Code
+
+    /// Details from the library
+    public let message: String
+
+    public init(code: Code, message: String) {
+        self.code = code
+        self.message = message
+    }
+
+    public var description: String {
+        message
+    }
+
+    /// The error left by the last C call on this thread, if it failed
+    ///
+    /// Errors are thread-local and overwritten by the next call, so this must
+    /// run right after the failing call, on the same thread.
+    static func last() -> ChassisError? {
+        let raw = chassis_last_error_code().rawValue
+        guard raw != 0 else {
+            return nil
+        }
+        let message = chassis_last_error_message().map { String(cString: $0) } ?? "Unknown error"
+        return ChassisError(code: Code(rawValue: raw) ?? .unknown, message: message)
+    }
+
+    /// Like `last()`, for calls whose return value already signalled a failure
+    static func lastOrUnknown() -> ChassisError {
+        last() ?? ChassisError(code: .unknown, message: "Unknown error")
+    }
+}
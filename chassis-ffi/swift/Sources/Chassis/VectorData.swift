@@ -0,0 +1,35 @@
+import Foundation
+
+/// Conversions between vectors and their packed binary form
+///
+/// A vector is packed as consecutive 32-bit floats in native byte order
+/// (little-endian on every Apple platform), which is also what NumPy's
+/// `tobytes()` produces for a `float32` array and how embeddings are usually
+/// stored in Core Data or SQLite blobs.
+extension Array where Element == Float {
+    /// Decode a packed vector
+    ///
+    /// - Throws: `ChassisError` with code `.invalidArgument` if the length of
+    ///   `vectorData` is not a multiple of 4 bytes.
+    public init(vectorData data: Data) throws {
+        let stride = MemoryLayout<Float>.stride
+        guard data.count % stride == 0 else {
+            throw ChassisError(
+                code: .invalidArgument,
+                message: "Vector data is \(data.count) bytes, not a multiple of \(stride)"
+            )
+        }
+        let count = data.count / stride
+        self.init(unsafeUninitializedCapacity: count) { buffer, initialized in
+            _ = data.copyBytes(to: buffer)
+            initialized = count
+        }
+    }
+}
+
+extension Data {
+    /// Pack a vector as consecutive 32-bit floats
+    public init(vector: [Float]) {
+        self = vector.withUnsafeBytes { Data($0) }
+    }
+}
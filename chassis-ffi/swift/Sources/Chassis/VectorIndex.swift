@@ -0,0 +1,260 @@
+import ChassisFFI
+import Foundation
+
+/// A search result
+public struct SearchResult: Equatable, Sendable {
+    /// Vector ID in the index
+    public let id: UInt64
+
+    /// Distance to the query vector (lower is closer)
+    public let distance: Float
+}
+
+/// A Chassis vector index stored in one file
+///
+/// The index is closed when the object is released. Instances are safe to
+/// share between threads: searches run concurrently, while `add` and `flush`
+/// wait for exclusive access, matching the single-writer rules of the C API.
+///
+/// ```swift
+/// let url = FileManager.default.urls(for: .applicationSupportDirectory, in: .userDomainMask)[0]
+/// let index = try VectorIndex(path: url.appendingPathComponent("notes.chassis").path, dimensions: 384)
+/// let id = try index.add(embedding)
+/// try index.flush()
+/// let nearest = try index.search(query, k: 10)
+/// ```
+public final class VectorIndex: @unchecked Sendable {
+    private let handle: UnsafeMutablePointer<ChassisIndex>
+    private let lock = ReadWriteLock()
+
+    /// Open or create the index at `path` with default parameters
+    ///
+    /// - Throws: `ChassisError` if the file cannot be opened, is locked by
+    ///   another handle, or holds vectors of other dimensions.
+    public convenience init(path: String, dimensions: Int) throws {
+        let dimensions = try Self.dimensions(dimensions)
+        try self.init(handle: chassis_open(path, dimensions))
+    }
+
+    /// Open or create the index at `path` with custom HNSW parameters
+    ///
+    /// The graph parameters of an existing file take precedence over
+    /// `maxConnections` and `efConstruction`.
+    ///
+    /// - Throws: `ChassisError` for the same reasons as `init(path:dimensions:)`.
+    public convenience init(
+        path: String,
+        dimensions: Int,
+        maxConnections: UInt32 = 16,
+        efConstruction: UInt32 = 200,
+        efSearch: UInt32 = 50
+    ) throws {
+        let dimensions = try Self.dimensions(dimensions)
+        try self.init(
+            handle: chassis_open_with_options(
+                path, dimensions, maxConnections, efConstruction, efSearch
+            )
+        )
+    }
+
+    /// Open an existing index read-only, shared with other processes
+    ///
+    /// Useful for app extensions reading an index their app writes. `add` and
+    /// `flush` throw `.readOnly` on the returned index.
+    ///
+    /// - Throws: `ChassisError` if the file cannot be opened or a writer holds it.
+    public static func openShared(path: String, dimensions: Int) throws -> VectorIndex {
+        let dimensions = try Self.dimensions(dimensions)
+        return try VectorIndex(handle: chassis_open_shared(path, dimensions))
+    }
+
+    private init(handle: UnsafeMutablePointer<ChassisIndex>?) throws {
+        guard let handle else {
+            throw ChassisError.lastOrUnknown()
+        }
+        self.handle = handle
+    }
+
+    deinit {
+        chassis_free(handle)
+    }
+
+    private static func dimensions(_ dimensions: Int) throws -> UInt32 {
+        guard let dimensions = UInt32(exactly: dimensions), dimensions > 0 else {
+            throw ChassisError(
+                code: .invalidArgument,
+                message: "Dimensions must be between 1 and \(UInt32.max), got \(dimensions)"
+            )
+        }
+        return dimensions
+    }
+
+    /// Version of the Chassis library
+    public static var version: String {
+        String(cString: chassis_version())
+    }
+
+    /// Number of vectors in the index
+    public var count: Int {
+        lock.read { Int(chassis_len(handle)) }
+    }
+
+    /// Whether the index holds no vectors
+    public var isEmpty: Bool {
+        count == 0
+    }
+
+    /// Number of values in each vector
+    public var dimensions: Int {
+        lock.read { Int(chassis_dimensions(handle)) }
+    }
+
+    /// Add a vector and return its ID
+    ///
+    /// The vector is not durable until the next `flush()`.
+    ///
+    /// - Throws: `ChassisError` if `vector` has the wrong length or the file
+    ///   cannot grow.
+    @discardableResult
+    public func add(_ vector: [Float]) throws -> UInt64 {
+        try lock.write {
+            let id = vector.withUnsafeBufferPointer { chassis_add(handle, $0.baseAddress, $0.count) }
+            guard id != UInt64.max else {
+                throw ChassisError.lastOrUnknown()
+            }
+            return id
+        }
+    }
+
+    /// Add a vector packed as 32-bit floats (see `Data(vector:)`)
+    ///
+    /// - Throws: `ChassisError` if `vectorData` is not a whole number of
+    ///   floats, or for the same reasons as `add(_:)`.
+    @discardableResult
+    public func add(_ vectorData: Data) throws -> UInt64 {
+        try add([Float](vectorData: vectorData))
+    }
+
+    /// Add several vectors in one call and return their IDs, in order
+    ///
+    /// Stops at the first vector that fails; the vectors before it stay in
+    /// the index.
+    ///
+    /// - Throws: `ChassisError` if the vectors differ in length, or for the
+    ///   same reasons as `add(_:)`.
+    @discardableResult
+    public func add(contentsOf vectors: [[Float]]) throws -> [UInt64] {
+        guard let dim = vectors.first?.count else {
+            return []
+        }
+        guard vectors.allSatisfy({ $0.count == dim }) else {
+            throw ChassisError(
+                code: .dimensionMismatch,
+                message: "All vectors in a batch must have the same length"
+            )
+        }
+
+        let rows = vectors.flatMap { $0 }
+        var ids = [UInt64](repeating: 0, count: vectors.count)
+        return try lock.write {
+            let added = rows.withUnsafeBufferPointer { rows in
+                ids.withUnsafeMutableBufferPointer { ids in
+                    chassis_add_batch(handle, rows.baseAddress, ids.count, dim, ids.baseAddress)
+                }
+            }
+            guard added == ids.count else {
+                throw ChassisError.lastOrUnknown()
+            }
+            return ids
+        }
+    }
+
+    /// Find the `k` nearest neighbors of `query`, closest first
+    ///
+    /// - Throws: `ChassisError` if `query` has the wrong length or `k` is 0.
+    public func search(_ query: [Float], k: Int) throws -> [SearchResult] {
+        guard k > 0 else {
+            throw ChassisError(code: .invalidArgument, message: "k must be > 0")
+        }
+
+        var ids = [UInt64](repeating: 0, count: k)
+        var distances = [Float](repeating: 0, count: k)
+        let found = try lock.read {
+            let found = query.withUnsafeBufferPointer { query in
+                ids.withUnsafeMutableBufferPointer { ids in
+                    distances.withUnsafeMutableBufferPointer { distances in
+                        chassis_search(
+                            handle, query.baseAddress, query.count, k,
+                            ids.baseAddress, distances.baseAddress
+                        )
+                    }
+                }
+            }
+            // 0 results is also the answer for an empty index
+            if found == 0, let error = ChassisError.last() {
+                throw error
+            }
+            return found
+        }
+        return (0..<found).map { SearchResult(id: ids[$0], distance: distances[$0]) }
+    }
+
+    /// Find the `k` nearest neighbors of a query packed as 32-bit floats
+    ///
+    /// - Throws: `ChassisError` if `queryData` is not a whole number of
+    ///   floats, or for the same reasons as `search(_:k:)`.
+    public func search(_ queryData: Data, k: Int) throws -> [SearchResult] {
+        try search([Float](vectorData: queryData), k: k)
+    }
+
+    /// Make every added vector durable
+    ///
+    /// - Throws: `ChassisError` if the index is read-only or the write fails.
+    public func flush() throws {
+        try lock.write {
+            guard chassis_flush(handle) == 0 else {
+                throw ChassisError.lastOrUnknown()
+            }
+        }
+    }
+
+    /// Write a crash-consistent copy of the index to `path` without closing it
+    ///
+    /// - Throws: `ChassisError` if the flush or the copy fails.
+    public func snapshot(to path: String) throws {
+        try lock.write {
+            guard chassis_snapshot_to(handle, path) == 0 else {
+                throw ChassisError.lastOrUnknown()
+            }
+        }
+    }
+}
+
+/// `pthread_rwlock_t` in stable heap storage, as POSIX requires
+private final class ReadWriteLock {
+    private let lock: UnsafeMutablePointer<pthread_rwlock_t>
+
+    init() {
+        lock = .allocate(capacity: 1)
+        lock.initialize(to: pthread_rwlock_t())
+        pthread_rwlock_init(lock, nil)
+    }
+
+    deinit {
+        pthread_rwlock_destroy(lock)
+        lock.deinitialize(count: 1)
+        lock.deallocate()
+    }
+
+    func read<T>(_ body: () throws -> T) rethrows -> T {
+        pthread_rwlock_rdlock(lock)
+        defer { pthread_rwlock_unlock(lock) }
+        return try body()
+    }
+
+    func write<T>(_ body: () throws -> T) rethrows -> T {
+        pthread_rwlock_wrlock(lock)
+        defer { pthread_rwlock_unlock(lock) }
+        return try body()
+    }
+}
@@ -0,0 +1,82 @@
+import Chassis
+import Foundation
+import XCTest
+
+final class VectorIndexTests: XCTestCase {
+    private var directory: URL!
+
+    override func setUpWithError() throws {
+        directory = FileManager.default.temporaryDirectory
+            .appendingPathComponent(UUID().uuidString, isDirectory: true)
+        try FileManager.default.createDirectory(at: directory, withIntermediateDirectories: true)
+    }
+
+    override func tearDownWithError() throws {
+        try FileManager.default.removeItem(at: directory)
+    }
+
+    private func path(_ name: String) -> String {
+        directory.appendingPathComponent(name).path
+    }
+
+    func testAddSearchAndReopen() throws {
+        var index: VectorIndex? = try VectorIndex(path: path("test.chassis"), dimensions: 4)
+        XCTAssertEqual(try index?.add([1, 0, 0, 0]), 0)
+        XCTAssertEqual(try index?.add(contentsOf: [[0, 1, 0, 0], [0, 0, 1, 0]]), [1, 2])
+        XCTAssertEqual(try index?.search([0, 1, 0, 0], k: 1).first?.id, 1)
+        try index?.flush()
+
+        // Releasing the object closes the file and frees its lock
+        index = nil
+        let reopened = try VectorIndex(path: path("test.chassis"), dimensions: 4)
+        XCTAssertEqual(reopened.count, 3)
+        XCTAssertEqual(reopened.dimensions, 4)
+    }
+
+    func testDataConversions() throws {
+        let vector: [Float] = [0.5, -1, 2, 4]
+        let data = Data(vector: vector)
+        XCTAssertEqual(data.count, 16)
+        XCTAssertEqual(try [Float](vectorData: data), vector)
+        XCTAssertThrowsError(try [Float](vectorData: data.prefix(3)))
+
+        let index = try VectorIndex(path: path("data.chassis"), dimensions: 4)
+        let id = try index.add(data)
+        XCTAssertEqual(try index.search(data, k: 1), [SearchResult(id: id, distance: 0)])
+    }
+
+    func testErrorsCarryCodes() throws {
+        let index = try VectorIndex(path: path("errors.chassis"), dimensions: 4)
+        XCTAssertTrue(try index.search([0, 0, 0, 0], k: 5).isEmpty)
+
+        XCTAssertThrowsError(try index.add([1, 2, 3])) { error in
+            XCTAssertEqual((error as? ChassisError)?.code, .dimensionMismatch)
+        }
+        XCTAssertThrowsError(try VectorIndex(path: path("errors.chassis"), dimensions: 4)) { error in
+            XCTAssertEqual((error as? ChassisError)?.code, .locked)
+        }
+        XCTAssertThrowsError(try VectorIndex(path: path("zero.chassis"), dimensions: 0)) { error in
+            XCTAssertEqual((error as? ChassisError)?.code, .invalidArgument)
+        }
+    }
+
+    func testConcurrentSearches() throws {
+        let index = try VectorIndex(path: path("threads.chassis"), dimensions: 4)
+        for i in 0..<100 {
+            try index.add([Float(i), 0, 0, 0])
+        }
+
+        DispatchQueue.concurrentPerform(iterations: 8) { i in
+            if i == 0 {
+                for j in 100..<200 {
+                    XCTAssertNoThrow(try index.add([Float(j), 0, 0, 0]))
+                }
+            } else {
+                for _ in 0..<50 {
+                    XCTAssertEqual(try index.search([42, 0, 0, 0], k: 1).first?.id, 42)
+                }
+            }
+        }
+        XCTAssertEqual(index.count, 200)
+    }
+}
@@ -0,0 +1,64 @@
+#!/usr/bin/env bash
+# Build ChassisFFI.xcframework for the Swift package in this directory.
+#
+# Slices: iOS devices (arm64), the iOS simulator (arm64 + x86_64) and macOS
+# (arm64 + x86_64), so the package builds for apps and `swift test` runs on
+# a Mac. Requires Xcode and rustup.
+#
+# Usage: ./build-xcframework.sh
+
+set -euo pipefail
+
+cd "$(dirname "$0")"
+SWIFT_DIR="$PWD"
+ROOT="$(cd ../.. && pwd)"
+PROFILE=ios
+OUT="$SWIFT_DIR/ChassisFFI.xcframework"
+WORK="$ROOT/target/xcframework"
+
+TARGETS=(
+    aarch64-apple-ios
+    aarch64-apple-ios-sim
+    x86_64-apple-ios
+    aarch64-apple-darwin
+    x86_64-apple-darwin
+)
+
+export IPHONEOS_DEPLOYMENT_TARGET=13.0
+export MACOSX_DEPLOYMENT_TARGET=10.15
+
+rustup target add "${TARGETS[@]}"
+for target in "${TARGETS[@]}"; do
+    cargo build --manifest-path "$ROOT/Cargo.toml" -p chassis-ffi \
+        --profile "$PROFILE" --target "$target"
+done
+
+lib() {
+    echo "$ROOT/target/$1/$PROFILE/libchassis_ffi.a"
+}
+
+rm -rf "$WORK" "$OUT"
+mkdir -p "$WORK/headers" "$WORK/ios-simulator" "$WORK/macos"
+
+# The generated header plus a module map, so Swift can `import ChassisFFI`
+cp "$ROOT/chassis-ffi/include/chassis.h" "$WORK/headers/"
+cat > "$WORK/headers/module.modulemap" <<'MODULEMAP'
+module ChassisFFI {
+    header "chassis.h"
+    export *
+}
+MODULEMAP
+
+# One library per platform: merge the architectures of each
+lipo -create "$(lib aarch64-apple-ios-sim)" "$(lib x86_64-apple-ios)" \
+    -output "$WORK/ios-simulator/libchassis_ffi.a"
+lipo -create "$(lib aarch64-apple-darwin)" "$(lib x86_64-apple-darwin)" \
+    -output "$WORK/macos/libchassis_ffi.a"
+
+xcodebuild -create-xcframework \
+    -library "$(lib aarch64-apple-ios)" -headers "$WORK/headers" \
+    -library "$WORK/ios-simulator/libchassis_ffi.a" -headers "$WORK/headers" \
+    -library "$WORK/macos/libchassis_ffi.a" -headers "$WORK/headers" \
+    -output "$OUT"
+
+echo "Built $OUT"
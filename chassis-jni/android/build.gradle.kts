@@ -0,0 +1,47 @@
+// Android library (AAR) wrapping the chassis-jni crate.
+//
+//     gradle assembleRelease   # -> build/outputs/aar/chassis-release.aar
+//
+// Requires the Android NDK (ANDROID_NDK_HOME) and cargo-ndk
+// (`cargo install cargo-ndk`). The `cargoBuild` task compiles the native
+// library for every ABI below before the Android build packages it.
+
+plugins {
+    id("com.android.library") version "8.5.2"
+}
+
+val abis = listOf("arm64-v8a", "armeabi-v7a", "x86_64", "x86")
+val rustJniLibs = layout.buildDirectory.dir("rustJniLibs")
+
+android {
+    namespace = "io.github.tanvincible.chassis"
+    compileSdk = 34
+
+    defaultConfig {
+        minSdk = 21
+        ndk {
+            abiFilters += abis
+        }
+    }
+
+    compileOptions {
+        sourceCompatibility = JavaVersion.VERSION_1_8
+        targetCompatibility = JavaVersion.VERSION_1_8
+    }
+
+    sourceSets["main"].jniLibs.srcDir(rustJniLibs)
+}
+
+val cargoBuild by tasks.registering(Exec::class) {
+    description = "Builds libchassis_jni.so for every Android ABI with cargo-ndk"
+    workingDir = rootDir.resolve("../..")
+    commandLine(
+        listOf("cargo", "ndk", "--platform", "21") +
+            abis.flatMap { listOf("-t", it) } +
+            listOf("-o", rustJniLibs.get().asFile.path, "build", "--release", "-p", "chassis-jni")
+    )
+}
+
+tasks.named("preBuild") {
+    dependsOn(cargoBuild)
+}
@@ -0,0 +1,80 @@
+package io.github.tanvincible.chassis;
+
+/**
+ * Thrown when a Chassis operation fails.
+ *
+ * <p>{@link #getCode()} classifies the failure with the same stable codes as
+ * {@code ChassisErrorCode} in the C API; the message carries the details.
+ */
+public class ChassisException extends RuntimeException {
+    /** Category of a failure. */
+    public enum Code {
+        /** An argument was out of range (zero dimensions, zero k, ...). */
+        INVALID_ARGUMENT(1),
+        /** A required argument was null. */
+        NULL_POINTER(2),
+        /** A string argument could not be decoded. */
+        INVALID_UTF8(3),
+        /** Vector, query, or file dimensions did not match. */
+        DIMENSION_MISMATCH(4),
+        /** The file is locked by another process or handle. */
+        LOCKED(5),
+        /** The file is not a Chassis index or is corrupted. */
+        CORRUPTED(6),
+        /** An operating system I/O call failed. */
+        IO(7),
+        /** A vector ID or internal offset was out of range. */
+        OUT_OF_BOUNDS(8),
+        /** The index file was deleted or replaced while open. */
+        FILE_STOLEN(9),
+        /** A write was attempted on a read-only index. */
+        READ_ONLY(10),
+        /** The native library panicked; the operation was abandoned. */
+        PANIC(11),
+        /** Any other failure. */
+        UNKNOWN(12),
+        /** The file was written by a library version that is refused. */
+        INCOMPATIBLE_VERSION(13),
+        /** The index would grow past its configured size limit. */
+        QUOTA_EXCEEDED(14);
+
+        private final int value;
+
+        Code(int value) {
+            this.value = value;
+        }
+
+        /** The numeric code, as in {@code chassis.h}. */
+        public int value() {
+            return value;
+        }
+
+        static Code of(int value) {
+            for (Code code : values()) {
+                if (code.value == value) {
+                    return code;
+                }
+            }
+            return UNKNOWN;
+        }
+    }
+
+    private static final long serialVersionUID = 1L;
+
+    private final Code code;
+
+    /** Called by the native library. */
+    ChassisException(int code, String message) {
+        this(Code.of(code), message);
+    }
+
+    ChassisException(Code code, String message) {
+        super(message);
+        this.code = code;
+    }
+
+    /** Category of the failure. */
+    public Code getCode() {
+        return code;
+    }
+}
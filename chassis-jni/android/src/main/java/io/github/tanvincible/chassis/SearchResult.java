@@ -0,0 +1,41 @@
+package io.github.tanvincible.chassis;
+
+/** A search result: a vector ID and its distance to the query. */
+public final class SearchResult {
+    private final long id;
+    private final float distance;
+
+    SearchResult(long id, float distance) {
+        this.id = id;
+        this.distance = distance;
+    }
+
+    /** Vector ID in the index. */
+    public long getId() {
+        return id;
+    }
+
+    /** Distance to the query vector (lower is closer). */
+    public float getDistance() {
+        return distance;
+    }
+
+    @Override
+    public boolean equals(Object other) {
+        if (!(other instanceof SearchResult)) {
+            return false;
+        }
+        SearchResult that = (SearchResult) other;
+        return id == that.id && Float.compare(distance, that.distance) == 0;
+    }
+
+    @Override
+    public int hashCode() {
+        return Long.hashCode(id) * 31 + Float.hashCode(distance);
+    }
+
+    @Override
+    public String toString() {
+        return "SearchResult(id=" + id + ", distance=" + distance + ")";
+    }
+}
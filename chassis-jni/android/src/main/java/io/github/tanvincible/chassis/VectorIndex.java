@@ -0,0 +1,159 @@
+package io.github.tanvincible.chassis;
+
+import java.util.ArrayList;
+import java.util.List;
+import java.util.Objects;
+import java.util.concurrent.locks.ReentrantReadWriteLock;
+
+/**
+ * A Chassis vector index stored in one file.
+ *
+ * <p>The index holds a native handle and a lock on its file until
+ * {@link #close()}; use try-with-resources (Java) or {@code use} (Kotlin).
+ * Instances are safe to share between threads: searches run concurrently,
+ * while {@link #add}, {@link #flush} and {@link #close} wait for exclusive
+ * access, matching Chassis' single-writer rule.
+ *
+ * <pre>{@code
+ * try (VectorIndex index = new VectorIndex(context.getFilesDir() + "/notes.chassis", 384)) {
+ *     long id = index.add(embedding);
+ *     index.flush();
+ *     for (SearchResult result : index.search(query, 10)) {
+ *         Log.d("chassis", result.getId() + " " + result.getDistance());
+ *     }
+ * }
+ * }</pre>
+ *
+ * <p>Every method throws {@link ChassisException} on failure.
+ */
+public final class VectorIndex implements AutoCloseable {
+    static {
+        System.loadLibrary("chassis_jni");
+    }
+
+    private final ReentrantReadWriteLock lock = new ReentrantReadWriteLock();
+
+    /** Address of the native index, or 0 once closed. */
+    private long handle;
+
+    /** Open or create the index at {@code path} with default HNSW parameters. */
+    public VectorIndex(String path, int dimensions) {
+        this(path, dimensions, 16, 200, 50);
+    }
+
+    /**
+     * Open or create the index at {@code path} with custom HNSW parameters.
+     *
+     * <p>The graph parameters of an existing file take precedence over
+     * {@code maxConnections} and {@code efConstruction}.
+     */
+    public VectorIndex(
+            String path, int dimensions, int maxConnections, int efConstruction, int efSearch) {
+        Objects.requireNonNull(path, "path");
+        handle = nativeOpen(path, dimensions, maxConnections, efConstruction, efSearch);
+    }
+
+    /**
+     * Add a vector and return its ID.
+     *
+     * <p>The vector is not durable until the next {@link #flush()}.
+     */
+    public long add(float[] vector) {
+        Objects.requireNonNull(vector, "vector");
+        lock.writeLock().lock();
+        try {
+            return nativeAdd(handle, vector);
+        } finally {
+            lock.writeLock().unlock();
+        }
+    }
+
+    /** Find the {@code k} nearest neighbors of {@code query}, closest first. */
+    public List<SearchResult> search(float[] query, int k) {
+        Objects.requireNonNull(query, "query");
+        if (k <= 0) {
+            throw new ChassisException(ChassisException.Code.INVALID_ARGUMENT, "k must be > 0");
+        }
+
+        long[] ids = new long[k];
+        float[] distances = new float[k];
+        int found;
+        lock.readLock().lock();
+        try {
+            found = nativeSearch(handle, query, k, ids, distances);
+        } finally {
+            lock.readLock().unlock();
+        }
+
+        List<SearchResult> results = new ArrayList<>(found);
+        for (int i = 0; i < found; i++) {
+            results.add(new SearchResult(ids[i], distances[i]));
+        }
+        return results;
+    }
+
+    /** Make every added vector durable. */
+    public void flush() {
+        lock.writeLock().lock();
+        try {
+            nativeFlush(handle);
+        } finally {
+            lock.writeLock().unlock();
+        }
+    }
+
+    /** Number of vectors in the index. */
+    public long size() {
+        lock.readLock().lock();
+        try {
+            return nativeLen(handle);
+        } finally {
+            lock.readLock().unlock();
+        }
+    }
+
+    /** Number of values in each vector. */
+    public int dimensions() {
+        lock.readLock().lock();
+        try {
+            return nativeDimensions(handle);
+        } finally {
+            lock.readLock().unlock();
+        }
+    }
+
+    /**
+     * Close the index and release its file lock.
+     *
+     * <p>Changes since the last {@link #flush()} are not guaranteed to be
+     * durable. Closing twice is a no-op; any other call afterwards throws.
+     */
+    @Override
+    public void close() {
+        lock.writeLock().lock();
+        try {
+            if (handle != 0) {
+                nativeClose(handle);
+                handle = 0;
+            }
+        } finally {
+            lock.writeLock().unlock();
+        }
+    }
+
+    private static native long nativeOpen(
+            String path, int dimensions, int maxConnections, int efConstruction, int efSearch);
+
+    private static native void nativeClose(long handle);
+
+    private static native long nativeAdd(long handle, float[] vector);
+
+    private static native int nativeSearch(
+            long handle, float[] query, int k, long[] outIds, float[] outDistances);
+
+    private static native void nativeFlush(long handle);
+
+    private static native long nativeLen(long handle);
+
+    private static native int nativeDimensions(long handle);
+}
@@ -0,0 +1,329 @@
+//! JNI bindings for Chassis vector index
+//!
+//! This crate backs the Java class `io.github.tanvincible.chassis.VectorIndex`
+//! (usable from Kotlin and Java alike, see `android/`). Each `native` method of
+//! that class maps to one `Java_..._native*` function here. The index lives in
+//! a `Box` whose address the Java object keeps as a `long` handle until
+//! `close()`.
+//!
+//! # Error Handling
+//!
+//! Failures are thrown as `io.github.tanvincible.chassis.ChassisException`,
+//! carrying the stable error code of `chassis.h` (`ChassisErrorCode`) and the
+//! full `anyhow` context chain as the message. Panics are caught before they
+//! reach the JVM and thrown the same way with the `PANIC` code.
+//!
+//! # Thread Safety
+//!
+//! The Java class serializes calls with a read-write lock: `search` may run
+//! concurrently, while `add`, `flush` and `close` are exclusive. The functions
+//! here rely on that and do no locking of their own.
+
+use anyhow::{Context, Result};
+use chassis_core::{ErrorKind, IndexOptions, VectorIndex};
+use jni::JNIEnv;
+use jni::objects::{JClass, JFloatArray, JLongArray, JString, JThrowable, JValue};
+use jni::sys::{jint, jlong};
+
+/// Java class thrown for every failure
+const EXCEPTION_CLASS: &str = "io/github/tanvincible/chassis/ChassisException";
+
+/// `ChassisErrorCode` values of `chassis.h`, shared by every binding
+mod code {
+    use jni::sys::jint;
+
+    pub(crate) const INVALID_ARGUMENT: jint = 1;
+    pub(crate) const NULL_POINTER: jint = 2;
+    pub(crate) const DIMENSION_MISMATCH: jint = 4;
+    pub(crate) const LOCKED: jint = 5;
+    pub(crate) const CORRUPTED: jint = 6;
+    pub(crate) const IO: jint = 7;
+    pub(crate) const OUT_OF_BOUNDS: jint = 8;
+    pub(crate) const FILE_STOLEN: jint = 9;
+    pub(crate) const READ_ONLY: jint = 10;
+    pub(crate) const PANIC: jint = 11;
+    pub(crate) const UNKNOWN: jint = 12;
+    pub(crate) const INCOMPATIBLE_VERSION: jint = 13;
+    pub(crate) const QUOTA_EXCEEDED: jint = 14;
+}
+
+/// A failure detected by the bindings themselves
+#[derive(Debug)]
+struct InvalidArgument(String);
+
+impl std::fmt::Display for InvalidArgument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for InvalidArgument {}
+
+/// Stable error code for a failure
+fn error_code(err: &anyhow::Error) -> jint {
+    if err.downcast_ref::<InvalidArgument>().is_some() {
+        return code::INVALID_ARGUMENT;
+    }
+    if err.downcast_ref::<jni::errors::Error>().is_some_and(|e| {
+        matches!(e, jni::errors::Error::NullPtr(_) | jni::errors::Error::NullDeref(_))
+    }) {
+        return code::NULL_POINTER;
+    }
+    match ErrorKind::of(err) {
+        ErrorKind::InvalidArgument => code::INVALID_ARGUMENT,
+        ErrorKind::DimensionMismatch => code::DIMENSION_MISMATCH,
+        ErrorKind::Locked => code::LOCKED,
+        ErrorKind::Corrupted => code::CORRUPTED,
+        ErrorKind::Io => code::IO,
+        ErrorKind::OutOfBounds => code::OUT_OF_BOUNDS,
+        ErrorKind::FileStolen => code::FILE_STOLEN,
+        ErrorKind::ReadOnly => code::READ_ONLY,
+        ErrorKind::IncompatibleVersion => code::INCOMPATIBLE_VERSION,
+        ErrorKind::QuotaExceeded => code::QUOTA_EXCEEDED,
+        _ => code::UNKNOWN,
+    }
+}
+
+/// Throw a `ChassisException`, unless a Java exception is already pending
+fn throw(env: &mut JNIEnv<'_>, code: jint, message: &str) {
+    if env.exception_check().unwrap_or(true) {
+        return;
+    }
+    let thrown = env.new_string(message).and_then(|message| {
+        let exception = env.new_object(
+            EXCEPTION_CLASS,
+            "(ILjava/lang/String;)V",
+            &[JValue::Int(code), JValue::Object(&message)],
+        )?;
+        env.throw(JThrowable::from(exception))
+    });
+    // Fall back to a plain exception if the class could not be instantiated
+    if thrown.is_err() && !env.exception_check().unwrap_or(true) {
+        let _ = env.throw_new("java/lang/RuntimeException", message);
+    }
+}
+
+/// Panic and error barrier around every JNI entry point
+///
+/// Runs `f`, throws its error or panic as a `ChassisException`, and returns
+/// `R::default()` in that case (the JVM ignores the return value of a call
+/// that threw).
+fn jni_guard<'local, R: Default>(
+    env: &mut JNIEnv<'local>,
+    f: impl FnOnce(&mut JNIEnv<'local>) -> Result<R>,
+) -> R {
+    // AssertUnwindSafe: the operation is abandoned on panic, as in chassis-ffi
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(env))) {
+        Ok(Ok(result)) => result,
+        Ok(Err(err)) => {
+            throw(env, error_code(&err), &format!("{:#}", err));
+            R::default()
+        }
+        Err(panic) => {
+            let message = if let Some(s) = panic.downcast_ref::<&str>() {
+                format!("Panic: {}", s)
+            } else if let Some(s) = panic.downcast_ref::<String>() {
+                format!("Panic: {}", s)
+            } else {
+                "Unknown panic".to_string()
+            };
+            throw(env, code::PANIC, &message);
+            R::default()
+        }
+    }
+}
+
+/// Options for a new index from the Java constructor's arguments
+fn options(max_connections: jint, ef_construction: jint, ef_search: jint) -> Result<IndexOptions> {
+    let invalid = |name: &str, value: jint| {
+        InvalidArgument(format!("{} must be positive, got {}", name, value))
+    };
+    let max_connections = u16::try_from(max_connections)
+        .ok()
+        .filter(|&m| m > 0)
+        .ok_or_else(|| invalid("maxConnections", max_connections))?;
+    let ef_construction = usize::try_from(ef_construction)
+        .ok()
+        .filter(|&ef| ef > 0)
+        .ok_or_else(|| invalid("efConstruction", ef_construction))?;
+    let ef_search = usize::try_from(ef_search)
+        .ok()
+        .filter(|&ef| ef > 0)
+        .ok_or_else(|| invalid("efSearch", ef_search))?;
+    Ok(IndexOptions { max_connections, ef_construction, ef_search, ..IndexOptions::default() })
+}
+
+/// Borrow the index behind a handle
+///
+/// # Safety
+///
+/// `handle` must come from `nativeOpen` and not have been closed, and the
+/// Java lock must allow the access taken.
+unsafe fn index<'a>(handle: jlong) -> Result<&'a mut VectorIndex> {
+    // SAFETY: Upheld by the caller; 0 is the Java class's "closed" value.
+    unsafe { (handle as *mut VectorIndex).as_mut() }
+        .ok_or_else(|| InvalidArgument("The index has been closed".into()).into())
+}
+
+/// `VectorIndex.nativeOpen(String, int, int, int, int)`: open or create an index
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_io_github_tanvincible_chassis_VectorIndex_nativeOpen<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    path: JString<'local>,
+    dimensions: jint,
+    max_connections: jint,
+    ef_construction: jint,
+    ef_search: jint,
+) -> jlong {
+    jni_guard(&mut env, |env| {
+        let path: String = env.get_string(&path).context("Invalid path")?.into();
+        let dimensions = u32::try_from(dimensions).ok().filter(|&d| d > 0).ok_or_else(|| {
+            InvalidArgument(format!("dimensions must be positive, got {}", dimensions))
+        })?;
+        let options = options(max_connections, ef_construction, ef_search)?;
+        let index = VectorIndex::open(&path, dimensions, options)?;
+        Ok(Box::into_raw(Box::new(index)) as jlong)
+    })
+}
+
+/// `VectorIndex.nativeClose(long)`: close the index and free the handle
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_io_github_tanvincible_chassis_VectorIndex_nativeClose<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+) {
+    jni_guard(&mut env, |_| {
+        if handle != 0 {
+            // SAFETY: The Java class passes each handle from `nativeOpen` to
+            // this function exactly once, under its write lock.
+            drop(unsafe { Box::from_raw(handle as *mut VectorIndex) });
+        }
+        Ok(())
+    });
+}
+
+/// `VectorIndex.nativeAdd(long, float[])`: add a vector and return its ID
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_io_github_tanvincible_chassis_VectorIndex_nativeAdd<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+    vector: JFloatArray<'local>,
+) -> jlong {
+    jni_guard(&mut env, |env| {
+        // SAFETY: Called under the Java class's write lock.
+        let index = unsafe { index(handle) }?;
+        let mut values = vec![0.0f32; env.get_array_length(&vector)? as usize];
+        env.get_float_array_region(&vector, 0, &mut values)?;
+        Ok(index.add(&values)? as jlong)
+    })
+}
+
+/// `VectorIndex.nativeSearch(long, float[], int, long[], float[])`: search,
+/// writing up to `k` IDs and distances and returning how many were found
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_io_github_tanvincible_chassis_VectorIndex_nativeSearch<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+    query: JFloatArray<'local>,
+    k: jint,
+    out_ids: JLongArray<'local>,
+    out_distances: JFloatArray<'local>,
+) -> jint {
+    jni_guard(&mut env, |env| {
+        // SAFETY: Called under the Java class's read lock; search takes `&self`.
+        let index: &VectorIndex = unsafe { index(handle) }?;
+        let mut values = vec![0.0f32; env.get_array_length(&query)? as usize];
+        env.get_float_array_region(&query, 0, &mut values)?;
+
+        let k = usize::try_from(k).unwrap_or(0);
+        let results = index.search(&values, k)?;
+        let ids: Vec<jlong> = results.iter().map(|r| r.id as jlong).collect();
+        let distances: Vec<f32> = results.iter().map(|r| r.distance).collect();
+        env.set_long_array_region(&out_ids, 0, &ids)?;
+        env.set_float_array_region(&out_distances, 0, &distances)?;
+        Ok(results.len() as jint)
+    })
+}
+
+/// `VectorIndex.nativeFlush(long)`: flush all changes to disk
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_io_github_tanvincible_chassis_VectorIndex_nativeFlush<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+) {
+    jni_guard(&mut env, |_| {
+        // SAFETY: Called under the Java class's write lock.
+        unsafe { index(handle) }?.flush()
+    });
+}
+
+/// `VectorIndex.nativeLen(long)`: number of vectors in the index
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_io_github_tanvincible_chassis_VectorIndex_nativeLen<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+) -> jlong {
+    jni_guard(&mut env, |_| {
+        // SAFETY: Called under the Java class's read lock.
+        Ok(unsafe { index(handle) }?.len() as jlong)
+    })
+}
+
+/// `VectorIndex.nativeDimensions(long)`: dimensionality of the index
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_io_github_tanvincible_chassis_VectorIndex_nativeDimensions<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+) -> jint {
+    jni_guard(&mut env, |_| {
+        // SAFETY: Called under the Java class's read lock.
+        Ok(unsafe { index(handle) }?.dimensions() as jint)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes_match_chassis_h() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("index.chassis");
+        let mut index = VectorIndex::open(&path, 4, IndexOptions::default()).unwrap();
+
+        let err = index.add(&[1.0; 3]).unwrap_err();
+        assert_eq!(error_code(&err), code::DIMENSION_MISMATCH);
+        let err = VectorIndex::open(&path, 4, IndexOptions::default()).unwrap_err();
+        assert_eq!(error_code(&err), code::LOCKED);
+        assert_eq!(error_code(&anyhow::anyhow!("untagged")), code::UNKNOWN);
+
+        let err = anyhow::Error::from(jni::errors::Error::NullPtr("array")).context("Invalid path");
+        assert_eq!(error_code(&err), code::NULL_POINTER);
+    }
+
+    #[test]
+    fn test_options_reject_out_of_range_values() {
+        let parsed = options(32, 100, 20).unwrap();
+        assert_eq!(parsed.max_connections, 32);
+        assert_eq!(parsed.ef_construction, 100);
+        assert_eq!(parsed.ef_search, 20);
+
+        for (m, efc, efs) in [(0, 100, 20), (70_000, 100, 20), (16, -1, 20), (16, 100, 0)] {
+            let err = options(m, efc, efs).unwrap_err();
+            assert_eq!(error_code(&err), code::INVALID_ARGUMENT);
+        }
+    }
+
+    #[test]
+    fn test_closed_handle_is_an_error() {
+        let err = unsafe { index(0) }.unwrap_err();
+        assert_eq!(error_code(&err), code::INVALID_ARGUMENT);
+    }
+}
@@ -0,0 +1,158 @@
+//! WebAssembly bindings for Chassis vector index
+//!
+//! This crate wraps `chassis_core::VectorIndex` with `wasm-bindgen` so it can
+//! run in browsers (`wasm32-unknown-unknown`). Browsers have no `mmap` or file
+//! locks, so the index lives in an in-memory buffer whose bytes are identical
+//! to the on-disk format.
+//!
+//! # Persistence
+//!
+//! Persist an index by writing `toBytes()` to storage of your choice (OPFS,
+//! IndexedDB, a download) and restore it with `ChassisIndex.fromBytes()`.
+//! Files produced by native builds can be loaded the same way.
+//!
+//! # Error Handling
+//!
+//! Every fallible method throws a JavaScript `Error` carrying the full
+//! `anyhow` context chain.
+
+use chassis_core::{IndexOptions, VectorIndex};
+use wasm_bindgen::prelude::*;
+
+/// Convert a core error into a JavaScript exception
+fn to_js_error(err: &anyhow::Error) -> JsError {
+    JsError::new(&format!("{err:#}"))
+}
+
+/// In-memory Chassis index exposed to JavaScript
+#[wasm_bindgen]
+pub struct ChassisIndex {
+    inner: VectorIndex,
+}
+
+#[wasm_bindgen]
+impl ChassisIndex {
+    /// Create an empty index with default HNSW parameters
+    ///
+    /// # Errors
+    ///
+    /// Throws if the index cannot be initialized.
+    #[wasm_bindgen(constructor)]
+    pub fn new(dims: u32) -> Result<ChassisIndex, JsError> {
+        Self::with_options(dims, None, None, None)
+    }
+
+    /// Create an empty index with explicit HNSW parameters
+    ///
+    /// Omitted (`undefined`) parameters use the `IndexOptions` defaults.
+    ///
+    /// # Errors
+    ///
+    /// Throws if the index cannot be initialized.
+    #[wasm_bindgen(js_name = withOptions)]
+    pub fn with_options(
+        dims: u32,
+        max_connections: Option<u16>,
+        ef_construction: Option<usize>,
+        ef_search: Option<usize>,
+    ) -> Result<ChassisIndex, JsError> {
+        let defaults = IndexOptions::default();
+        let options = IndexOptions {
+            max_connections: max_connections.unwrap_or(defaults.max_connections),
+            ef_construction: ef_construction.unwrap_or(defaults.ef_construction),
+            ef_search: ef_search.unwrap_or(defaults.ef_search),
+            ..defaults
+        };
+
+        let inner = VectorIndex::open_in_memory(dims, options).map_err(|e| to_js_error(&e))?;
+        Ok(Self { inner })
+    }
+
+    /// Restore an index from bytes produced by `toBytes()` or a native index file
+    ///
+    /// # Errors
+    ///
+    /// Throws if the image is corrupted or has different dimensions.
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8], dims: u32) -> Result<ChassisIndex, JsError> {
+        let inner = VectorIndex::from_bytes(bytes, dims, IndexOptions::default())
+            .map_err(|e| to_js_error(&e))?;
+        Ok(Self { inner })
+    }
+
+    /// Serialize the index (copies the whole image into a `Uint8Array`)
+    ///
+    /// # Errors
+    ///
+    /// Throws if the graph header cannot be written.
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&mut self) -> Result<Vec<u8>, JsError> {
+        self.inner.flush().map_err(|e| to_js_error(&e))?;
+        Ok(self.inner.as_bytes().to_vec())
+    }
+
+    /// Add a vector and return its ID
+    ///
+    /// # Errors
+    ///
+    /// Throws if the vector has the wrong dimensionality.
+    pub fn add(&mut self, vector: &[f32]) -> Result<u64, JsError> {
+        self.inner.add(vector).map_err(|e| to_js_error(&e))
+    }
+
+    /// Search for the `k` nearest neighbors of `query`
+    ///
+    /// # Errors
+    ///
+    /// Throws if the query has the wrong dimensionality.
+    pub fn search(&self, query: &[f32], k: usize) -> Result<SearchResults, JsError> {
+        let results = self.inner.search(query, k).map_err(|e| to_js_error(&e))?;
+        Ok(SearchResults {
+            ids: results.iter().map(|r| r.id).collect(),
+            distances: results.iter().map(|r| r.distance).collect(),
+        })
+    }
+
+    /// Number of vectors in the index
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.inner.len()
+    }
+
+    /// Whether the index holds no vectors
+    #[wasm_bindgen(js_name = isEmpty)]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Number of dimensions per stored vector
+    #[must_use]
+    pub fn dimensions(&self) -> u32 {
+        self.inner.dimensions()
+    }
+}
+
+/// Search results as parallel arrays, sorted by ascending distance
+#[wasm_bindgen]
+pub struct SearchResults {
+    ids: Vec<u64>,
+    distances: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl SearchResults {
+    /// Result IDs (`BigUint64Array`)
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    pub fn ids(&self) -> Vec<u64> {
+        self.ids.clone()
+    }
+
+    /// Result distances (`Float32Array`)
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    pub fn distances(&self) -> Vec<f32> {
+        self.distances.clone()
+    }
+}
@@ -0,0 +1,75 @@
+//! Chassis - Embeddable on-disk vector index
+//!
+//! This crate is the stable face of Chassis. It re-exports the parts of
+//! `chassis-core` that applications build on, and follows semver for them:
+//! a name exported here is only removed or changed incompatibly in a new major
+//! version. `chassis-core` itself keeps evolving its internals (the HNSW graph,
+//! storage layout helpers, builders) between minor releases; depend on it
+//! directly only for those.
+//!
+//! The default build exports the index itself: `VectorIndex`, its options and
+//! the types they mention, search results and errors. Larger subsystems are
+//! opt-in:
+//!
+//! | Feature | Exports |
+//! |---|---|
+//! | `async` | `AsyncVectorIndex` (Tokio wrapper) |
+//! | `collections` | `Collections` (several indexes in one file) |
+//! | `writer` | `IndexWriter`, `InsertPriority`, `Pending` |
+//! | `tiered` | `TieredIndex`, `TieredOptions`, `MergingTieredIndex` |
+//! | `linalg` | `Rotation` and `VectorIndex::train_rotation()` |
+//! | `io-formats` | `ExportFormat`, `VectorIndex::import_npy()`, `import_npz()` and `export()` |
+//! | `wasm` | `VectorIndex::open_in_memory()` and `from_bytes()` |
+//!
+//! # Example
+//!
+//! ```no_run
+//! use chassis::{IndexOptions, VectorIndex};
+//!
+//! # fn main() -> chassis::Result<()> {
+//! let mut index = VectorIndex::open("embeddings.chassis", 768, IndexOptions::default())?;
+//! index.add(&vec![0.1; 768])?;
+//! index.flush()?;
+//!
+//! for result in index.search(&vec![0.1; 768], 10)? {
+//!     println!("{} at {}", result.id, result.distance);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub use chassis_core::{
+    AddEvent, BuildProgress, CancelToken, CustomDistance, Distance, EarlyTermination, ElementType,
+    Embedder, ErrorKind, FileStolen, FlushEvent, FlushPolicy, GroupedResult, IndexOptions,
+    IndexStats, Instrumentation, KeyedResult, LibraryVersion, MAX_CONNECTIONS_0, MAX_KEY_LEN,
+    MAX_TAG, MAX_USER_META_LEN, MemoryFootprint, MemoryMode, OpenReport, Preset, ProgressHandler,
+    ProgressStage, ReadHandle, SearchBudget, SearchConsistency, SearchEvent, SearchOptions,
+    SearchResult, VectorIndex, VectorResult, VersionPolicy, WorkloadProfile, WriteHandle,
+    include_index,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use chassis_core::{IndexFork, MergedIndex, graph_file_path};
+
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+pub use chassis_core::AsyncVectorIndex;
+#[cfg(all(feature = "collections", not(target_arch = "wasm32")))]
+pub use chassis_core::Collections;
+#[cfg(all(feature = "io-formats", not(target_arch = "wasm32")))]
+pub use chassis_core::ExportFormat;
+#[cfg(all(feature = "tiered", not(target_arch = "wasm32")))]
+pub use chassis_core::MergingTieredIndex;
+#[cfg(feature = "linalg")]
+pub use chassis_core::Rotation;
+#[cfg(all(feature = "writer", not(target_arch = "wasm32")))]
+pub use chassis_core::{IndexWriter, InsertPriority, Pending};
+#[cfg(feature = "tiered")]
+pub use chassis_core::{TieredIndex, TieredOptions};
+
+/// Error returned by every fallible Chassis call
+///
+/// Use `ErrorKind::of()` to branch on the cause, or downcast to `FileStolen`.
+pub type Error = anyhow::Error;
+
+/// Result of a fallible Chassis call
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,29 @@
+//! The stable surface is enough to build, persist and query an index.
+
+use chassis::{ErrorKind, IndexOptions, SearchOptions, SearchResult, VectorIndex};
+use tempfile::tempdir;
+
+#[test]
+fn test_index_round_trip_through_facade() -> chassis::Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("facade.chassis");
+
+    {
+        let mut index = VectorIndex::open(&path, 4, IndexOptions::default())?;
+        for i in 0..20u8 {
+            index.add(&[f32::from(i), 0.0, 0.0, 1.0])?;
+        }
+        index.flush()?;
+    }
+
+    let index = VectorIndex::open(&path, 4, IndexOptions::default())?;
+    assert_eq!(index.len(), 20);
+
+    let results: Vec<SearchResult> =
+        index.search_with_options(&[7.0, 0.0, 0.0, 1.0], 3, &SearchOptions::default())?;
+    assert_eq!(results[0].id, 7);
+
+    let err = index.search(&[1.0, 2.0], 1).unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::DimensionMismatch);
+    Ok(())
+}
@@ -0,0 +1,49 @@
+// Photo similarity for an iOS app, on top of the Chassis Swift package
+// (`chassis-ffi/swift`).
+//
+// The index file lives in Application Support so it is backed up and survives
+// relaunches, and it is flushed whenever the app moves to the background:
+// iOS can terminate suspended apps without notice, and only flushed vectors
+// survive that.
+
+import Chassis
+import Foundation
+import UIKit
+
+/// Embeddings of the user's photos, keyed by vector ID
+final class PhotoIndex {
+    private let index: VectorIndex
+    private var observer: NSObjectProtocol?
+
+    init(dimensions: Int) throws {
+        let directory = try FileManager.default.url(
+            for: .applicationSupportDirectory, in: .userDomainMask,
+            appropriateFor: nil, create: true
+        )
+        index = try VectorIndex(
+            path: directory.appendingPathComponent("photos.chassis").path,
+            dimensions: dimensions
+        )
+
+        observer = NotificationCenter.default.addObserver(
+            forName: UIApplication.didEnterBackgroundNotification, object: nil, queue: nil
+        ) { [index] _ in
+            try? index.flush()
+        }
+    }
+
+    deinit {
+        observer.map(NotificationCenter.default.removeObserver)
+    }
+
+    /// Add embeddings computed by the app's model; returns their IDs
+    @discardableResult
+    func add(_ embeddings: [[Float]]) throws -> [UInt64] {
+        try index.add(contentsOf: embeddings)
+    }
+
+    /// The `k` photos most similar to `embedding`; safe to call off the main thread
+    func similar(to embedding: [Float], k: Int = 12) throws -> [SearchResult] {
+        try index.search(embedding, k: k)
+    }
+}
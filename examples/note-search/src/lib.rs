@@ -0,0 +1,308 @@
+//! Search plain-text notes by meaning.
+//!
+//! Notes live in two files side by side: `notes.chassis` holds one vector per
+//! note, and `notes.chassis.notes` holds the text and tags, one line per note
+//! keyed by the vector ID. The vector ID is the only link between them.
+//!
+//! Embeddings come from `embed()`, a feature-hashing stand-in for a real
+//! embedding model: it has no model files and needs no network, so the example
+//! runs anywhere. Swapping in a model only changes `embed()` and `DIMS`.
+//!
+//! The example exercises:
+//!
+//! - batch ingest: `import()` adds a whole file of notes and lets the index's
+//!   `FlushPolicy` flush in the background as it goes;
+//! - filtering: tag filters are applied to over-fetched search results, and
+//!   `related()` uses a distance threshold (`search_within()`);
+//! - backups: `backup()` writes a crash-consistent snapshot of the index.
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chassis_core::{FlushPolicy, IndexOptions, Preset, VectorIndex};
+
+/// Dimensionality of `embed()` vectors
+pub const DIMS: u32 = 256;
+
+/// Inserts between background flushes while importing
+const FLUSH_EVERY: u64 = 256;
+
+/// Below this many notes a linear scan is as fast as the graph, and exact
+const EXACT_SEARCH_THRESHOLD: u64 = 2_000;
+
+/// Embed `text` by hashing its words and character trigrams into `DIMS` buckets
+///
+/// The result is L2-normalized, so Euclidean distance ranks like cosine
+/// similarity. Texts sharing words or word fragments end up close together.
+pub fn embed(text: &str) -> Vec<f32> {
+    let mut vector = vec![0.0f32; DIMS as usize];
+    let lower = text.to_lowercase();
+
+    for word in lower.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()) {
+        vector[bucket(word.as_bytes())] += 1.0;
+
+        let padded: Vec<u8> = [b" ", word.as_bytes(), b" "].concat();
+        for trigram in padded.windows(3) {
+            vector[bucket(trigram)] += 0.5;
+        }
+    }
+
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    vector
+}
+
+/// FNV-1a hash of `bytes`, reduced to a vector component
+fn bucket(bytes: &[u8]) -> usize {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    (hash % u64::from(DIMS)) as usize
+}
+
+/// A stored note
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Note {
+    /// Tags for filtering, without the leading `#`
+    pub tags: Vec<String>,
+
+    /// Note text
+    pub text: String,
+}
+
+/// A search result
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    /// Vector ID of the note
+    pub id: u64,
+
+    /// Euclidean distance between the query and note embeddings
+    pub distance: f32,
+
+    /// The note
+    pub note: Note,
+}
+
+/// Notes plus their vector index
+pub struct NoteStore {
+    index: VectorIndex,
+    notes: HashMap<u64, Note>,
+    log: BufWriter<File>,
+}
+
+impl NoteStore {
+    /// Open the store at `path`, creating it if needed
+    ///
+    /// Notes whose vectors were not flushed before a crash are dropped, and
+    /// vectors whose note line was lost are never returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either file cannot be opened or parsed.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let index = VectorIndex::open(path, DIMS, Self::options())?;
+
+        let log_path = Self::log_path(path);
+        let mut notes = HashMap::new();
+        if log_path.exists() {
+            for line in BufReader::new(File::open(&log_path)?).lines() {
+                let line = line?;
+                let (id, note) = parse_line(&line)
+                    .with_context(|| format!("Malformed line in {}", log_path.display()))?;
+                if id < index.len() {
+                    notes.insert(id, note);
+                }
+            }
+        }
+
+        // Rewrite the log without notes that lost their vectors
+        let mut log = BufWriter::new(File::create(&log_path)?);
+        let mut ids: Vec<_> = notes.keys().copied().collect();
+        ids.sort_unstable();
+        for id in ids {
+            write_line(&mut log, id, &notes[&id])?;
+        }
+        log.flush()?;
+
+        let log = OpenOptions::new().append(true).open(&log_path)?;
+        Ok(Self { index, notes, log: BufWriter::new(log) })
+    }
+
+    /// Index options shared by every open of a store
+    ///
+    /// Sized by a preset for a personal notebook; the preset arguments must not
+    /// change between opens, since they fix the graph layout.
+    fn options() -> IndexOptions {
+        IndexOptions {
+            flush_policy: FlushPolicy::EveryInserts(FLUSH_EVERY),
+            exact_search_threshold: EXACT_SEARCH_THRESHOLD,
+            ..IndexOptions::preset(Preset::Balanced, DIMS, 100_000)
+        }
+    }
+
+    fn log_path(path: &Path) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(".notes");
+        PathBuf::from(name)
+    }
+
+    /// Add one note and return its ID
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index or the notes file cannot be written.
+    pub fn add(&mut self, text: &str, tags: &[&str]) -> Result<u64> {
+        let note = Note { tags: tags.iter().map(|t| (*t).to_owned()).collect(), text: text.into() };
+        let id = self.index.add(&embed(text))?;
+        write_line(&mut self.log, id, &note)?;
+        self.notes.insert(id, note);
+        Ok(id)
+    }
+
+    /// Add every note in `reader` and flush; returns how many were added
+    ///
+    /// Each line is a note; leading `#tag` words become its tags. Blank lines
+    /// are skipped. The index flushes in the background every few hundred
+    /// notes, so a crash mid-import keeps most of the work.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading or writing fails.
+    pub fn import<R: BufRead>(&mut self, reader: R) -> Result<usize> {
+        let mut added = 0;
+        for line in reader.lines() {
+            let line = line?;
+            let mut words = line.split_whitespace().peekable();
+            let mut tags = Vec::new();
+            while let Some(tag) = words.next_if(|w| w.len() > 1 && w.starts_with('#')) {
+                tags.push(&tag[1..]);
+            }
+            let text = words.collect::<Vec<_>>().join(" ");
+            if text.is_empty() {
+                continue;
+            }
+            self.add(&text, &tags)?;
+            added += 1;
+        }
+        self.flush()?;
+        Ok(added)
+    }
+
+    /// The `k` notes closest to `query`, optionally only those tagged `tag`
+    ///
+    /// Chassis has no filtered search, so the filter is applied to a larger
+    /// result set that grows until `k` notes pass it or the index is exhausted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the search fails.
+    pub fn search(&self, query: &str, k: usize, tag: Option<&str>) -> Result<Vec<Hit>> {
+        let query = embed(query);
+        let total = usize::try_from(self.index.len()).unwrap_or(usize::MAX);
+        let mut fetch = k;
+        loop {
+            let hits: Vec<Hit> = self
+                .index
+                .search(&query, fetch)?
+                .into_iter()
+                .filter_map(|r| self.hit(r.id, r.distance))
+                .filter(|hit| tag.is_none_or(|tag| hit.note.tags.iter().any(|t| t == tag)))
+                .take(k)
+                .collect();
+            if hits.len() >= k || fetch >= total {
+                return Ok(hits);
+            }
+            fetch = fetch.saturating_mul(4).min(total);
+        }
+    }
+
+    /// Notes within `max_distance` of note `id`, nearest first, excluding itself
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `id` is not a note or the search fails.
+    pub fn related(&self, id: u64, max_distance: f32) -> Result<Vec<Hit>> {
+        let note = self.notes.get(&id).with_context(|| format!("No note with ID {id}"))?;
+        Ok(self
+            .index
+            .search_within(&embed(&note.text), max_distance)?
+            .into_iter()
+            .filter(|r| r.id != id)
+            .filter_map(|r| self.hit(r.id, r.distance))
+            .collect())
+    }
+
+    fn hit(&self, id: u64, distance: f32) -> Option<Hit> {
+        Some(Hit { id, distance, note: self.notes.get(&id)?.clone() })
+    }
+
+    /// Make every added note durable
+    ///
+    /// The notes file is synced before the index.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either file cannot be synced.
+    pub fn flush(&mut self) -> Result<()> {
+        self.log.flush()?;
+        self.log.get_ref().sync_data()?;
+        self.index.flush()
+    }
+
+    /// Write a crash-consistent copy of the index and notes next to `path`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the flush or either copy fails.
+    pub fn backup<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        self.flush()?;
+        self.index.snapshot_to(path)?;
+
+        let mut log = BufWriter::new(File::create(Self::log_path(path))?);
+        let mut ids: Vec<_> = self.notes.keys().copied().collect();
+        ids.sort_unstable();
+        for id in ids {
+            write_line(&mut log, id, &self.notes[&id])?;
+        }
+        log.flush()?;
+        log.get_ref().sync_all()?;
+        Ok(())
+    }
+
+    /// Number of notes
+    pub fn len(&self) -> usize {
+        self.notes.len()
+    }
+
+    /// Returns `true` if the store holds no notes
+    pub fn is_empty(&self) -> bool {
+        self.notes.is_empty()
+    }
+
+    /// Number of notes that survive a crash right now
+    pub fn durable_len(&self) -> u64 {
+        self.index.durable_len()
+    }
+}
+
+/// Append `id<TAB>tags<TAB>text` to the notes log
+fn write_line(log: &mut impl Write, id: u64, note: &Note) -> Result<()> {
+    let text = note.text.replace(['\t', '\n'], " ");
+    writeln!(log, "{id}\t{}\t{text}", note.tags.join(","))?;
+    Ok(())
+}
+
+fn parse_line(line: &str) -> Option<(u64, Note)> {
+    let mut fields = line.splitn(3, '\t');
+    let id = fields.next()?.parse().ok()?;
+    let tags = fields.next()?.split(',').filter(|t| !t.is_empty()).map(str::to_owned).collect();
+    let text = fields.next()?.to_owned();
+    Some((id, Note { tags, text }))
+}
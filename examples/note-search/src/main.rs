@@ -0,0 +1,85 @@
+//! `note-search` command line tool.
+//!
+//! ```text
+//! note-search <index> add [#tag ...] <text ...>
+//! note-search <index> import <file>
+//! note-search <index> search [--tag <tag>] [-k <n>] <query ...>
+//! note-search <index> related <id> [max-distance]
+//! note-search <index> backup <path>
+//! ```
+
+use std::fs::File;
+use std::io::BufReader;
+use std::process::ExitCode;
+
+use anyhow::{Context, Result, bail};
+use note_search::{Hit, NoteStore};
+
+const USAGE: &str = "usage: note-search <index> (add | import | search | related | backup) ...";
+
+fn main() -> ExitCode {
+    match run(std::env::args().skip(1).collect()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Vec<String>) -> Result<()> {
+    let [index, command, rest @ ..] = args.as_slice() else {
+        bail!(USAGE);
+    };
+    let mut store = NoteStore::open(index)?;
+
+    match command.as_str() {
+        "add" => {
+            let line = rest.join(" ");
+            store.import(line.as_bytes())?;
+            println!("{} notes", store.len());
+        }
+        "import" => {
+            let [file] = rest else { bail!("usage: note-search <index> import <file>") };
+            let file = File::open(file).with_context(|| format!("Cannot open {file}"))?;
+            let added = store.import(BufReader::new(file))?;
+            println!("imported {added} notes ({} total)", store.len());
+        }
+        "search" => {
+            let mut k = 5;
+            let mut tag = None;
+            let mut words = Vec::new();
+            let mut rest = rest.iter();
+            while let Some(arg) = rest.next() {
+                match arg.as_str() {
+                    "--tag" => tag = Some(rest.next().context("--tag needs a value")?.as_str()),
+                    "-k" => k = rest.next().context("-k needs a value")?.parse()?,
+                    word => words.push(word),
+                }
+            }
+            print_hits(&store.search(&words.join(" "), k, tag)?);
+        }
+        "related" => {
+            let (id, max_distance) = match rest {
+                [id] => (id, 1.0),
+                [id, max] => (id, max.parse()?),
+                _ => bail!("usage: note-search <index> related <id> [max-distance]"),
+            };
+            print_hits(&store.related(id.parse()?, max_distance)?);
+        }
+        "backup" => {
+            let [path] = rest else { bail!("usage: note-search <index> backup <path>") };
+            store.backup(path)?;
+            println!("backed up {} notes to {path}", store.len());
+        }
+        _ => bail!(USAGE),
+    }
+    Ok(())
+}
+
+fn print_hits(hits: &[Hit]) {
+    for hit in hits {
+        let tags: Vec<_> = hit.note.tags.iter().map(|t| format!("#{t}")).collect();
+        println!("{:>6}  {:.3}  {} {}", hit.id, hit.distance, tags.join(" "), hit.note.text);
+    }
+}
@@ -0,0 +1,72 @@
+//! End-to-end tests of the note store
+
+use note_search::{DIMS, NoteStore, embed};
+
+const NOTES: &str = "\
+#work quarterly budget review with finance
+#work prepare slides for the budget meeting
+#home fix the leaking kitchen sink
+#home buy groceries: milk, eggs and bread
+#travel book train tickets to the coast
+#travel pack sunscreen and a beach towel
+";
+
+#[test]
+fn test_embeddings_are_normalized_and_deterministic() {
+    let a = embed("Budget review");
+    assert_eq!(a.len(), DIMS as usize);
+    assert_eq!(a, embed("budget   REVIEW"));
+    assert!((a.iter().map(|x| x * x).sum::<f32>() - 1.0).abs() < 1e-5);
+    assert!(embed("").iter().all(|&x| x == 0.0));
+}
+
+#[test]
+fn test_import_search_filter_and_reopen() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.chassis");
+
+    let mut store = NoteStore::open(&path).unwrap();
+    assert_eq!(store.import(NOTES.as_bytes()).unwrap(), 6);
+    assert_eq!(store.durable_len(), 6);
+
+    let hits = store.search("budget", 2, None).unwrap();
+    assert!(hits.iter().all(|hit| hit.note.tags == ["work"]));
+
+    // The filter reaches past nearer notes with other tags
+    let hits = store.search("budget", 1, Some("travel")).unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].note.tags, ["travel"]);
+
+    let related = store.related(0, 1.2).unwrap();
+    assert_eq!(related[0].id, 1);
+    assert!(related.iter().all(|hit| hit.id != 0));
+
+    store.add("sink still leaking, call a plumber", &["home"]).unwrap();
+    store.flush().unwrap();
+    drop(store);
+
+    let store = NoteStore::open(&path).unwrap();
+    assert_eq!(store.len(), 7);
+    assert_eq!(store.search("leaking sink plumber", 1, None).unwrap()[0].id, 6);
+}
+
+#[test]
+fn test_bulk_import_flushes_in_background_and_backs_up() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.chassis");
+
+    let notes: String = (0..300).map(|i| format!("#n{} note number {i}\n", i % 10)).collect();
+    let mut store = NoteStore::open(&path).unwrap();
+    assert_eq!(store.import(notes.as_bytes()).unwrap(), 300);
+
+    let hits = store.search("note number 221", 3, Some("n1")).unwrap();
+    assert_eq!(hits[0].note.text, "note number 221");
+    assert!(hits.iter().all(|hit| hit.note.tags == ["n1"]));
+
+    let backup = dir.path().join("backup.chassis");
+    store.backup(&backup).unwrap();
+    drop(store);
+
+    let copy = NoteStore::open(&backup).unwrap();
+    assert_eq!(copy.len(), 300);
+}
@@ -0,0 +1,172 @@
+//! Find similar photos from precomputed image embeddings.
+//!
+//! Photo apps usually compute embeddings once, with an image model running
+//! elsewhere (a server, a GPU box, an on-device accelerator), and ship them as
+//! a flat file. This example indexes such a library:
+//!
+//! - `embeddings.f32`: packed little-endian `f32` values, `dims` per photo;
+//! - `names.txt`: one photo name per line, in the same order, such as
+//!   `holidays/beach-01.jpg` (the directory part is the album).
+//!
+//! `PhotoLibrary::open()` bulk-loads the embeddings with
+//! `HnswBuilder::resume_from_reader()`, which streams the file, flushes
+//! periodically and resumes where it stopped if the process is killed
+//! mid-build. Searches filter by album on top of the index, and
+//! `duplicates()` finds near-identical shots with distance-bounded searches.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use chassis_core::{HnswBuilder, HnswParams, MemoryMode, VectorIndex};
+
+/// Index file name inside a library directory
+pub const INDEX_FILE: &str = "photos.chassis";
+
+/// Embeddings file name inside a library directory
+pub const EMBEDDINGS_FILE: &str = "embeddings.f32";
+
+/// Photo names file inside a library directory
+pub const NAMES_FILE: &str = "names.txt";
+
+/// A photo found by a search
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    /// Photo name from `names.txt`
+    pub name: String,
+
+    /// Euclidean distance between the two embeddings
+    pub distance: f32,
+}
+
+/// An indexed photo library
+pub struct PhotoLibrary {
+    index: VectorIndex,
+    names: Vec<String>,
+    embeddings: PathBuf,
+}
+
+impl PhotoLibrary {
+    /// Open the library in `dir`, building or finishing its index if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the files are missing, the embeddings file does not
+    /// hold one `dims`-dimensional vector per name, or the index cannot be
+    /// built.
+    pub fn open<P: AsRef<Path>>(dir: P, dims: u32) -> Result<Self> {
+        let dir = dir.as_ref();
+        let names_path = dir.join(NAMES_FILE);
+        let names = BufReader::new(
+            File::open(&names_path)
+                .with_context(|| format!("Cannot open {}", names_path.display()))?,
+        )
+        .lines()
+        .collect::<std::io::Result<Vec<_>>>()?;
+
+        let embeddings = dir.join(EMBEDDINGS_FILE);
+        let source = File::open(&embeddings)
+            .with_context(|| format!("Cannot open {}", embeddings.display()))?;
+        let expected = names.len() as u64 * u64::from(dims) * 4;
+        if source.metadata()?.len() != expected {
+            bail!(
+                "{} holds {} bytes, expected {} names x {} dims x 4 = {}",
+                embeddings.display(),
+                source.metadata()?.len(),
+                names.len(),
+                dims,
+                expected
+            );
+        }
+
+        // A no-op when the index is complete; otherwise continues from the last flush
+        let mut index = HnswBuilder::new(HnswParams::default())
+            .flush_interval(10_000)
+            .resume_from_reader(dir.join(INDEX_FILE), source, dims, names.len() as u64)?;
+        index.set_memory_mode(MemoryMode::Random)?;
+
+        Ok(Self { index, names, embeddings })
+    }
+
+    /// Number of photos in the library
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Returns `true` if the library holds no photos
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// The `k` photos most similar to `name`, optionally only from `album`
+    ///
+    /// The photo itself is never returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `name` is unknown or the search fails.
+    pub fn similar(&self, name: &str, k: usize, album: Option<&str>) -> Result<Vec<Match>> {
+        let id = self.id_of(name)?;
+        let query = self.embedding(id)?;
+
+        // Over-fetch until enough photos pass the filters or the library is exhausted
+        let mut fetch = k + 1;
+        loop {
+            let matches: Vec<Match> = self
+                .index
+                .search(&query, fetch)?
+                .into_iter()
+                .filter(|r| r.id != id)
+                .map(|r| Match { name: self.names[r.id as usize].clone(), distance: r.distance })
+                .filter(|m| album.is_none_or(|album| album_of(&m.name) == album))
+                .take(k)
+                .collect();
+            if matches.len() >= k || fetch >= self.len() {
+                return Ok(matches);
+            }
+            fetch = (fetch * 4).min(self.len());
+        }
+    }
+
+    /// Pairs of photos whose embeddings are within `max_distance`, closest first
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an embedding cannot be read or a search fails.
+    pub fn duplicates(&self, max_distance: f32) -> Result<Vec<(String, String, f32)>> {
+        let mut pairs = Vec::new();
+        for id in 0..self.len() as u64 {
+            for r in self.index.search_within(&self.embedding(id)?, max_distance)? {
+                if r.id > id {
+                    pairs.push((id, r.id, r.distance));
+                }
+            }
+        }
+        pairs.sort_by(|a, b| a.2.total_cmp(&b.2));
+        Ok(pairs
+            .into_iter()
+            .map(|(a, b, d)| (self.names[a as usize].clone(), self.names[b as usize].clone(), d))
+            .collect())
+    }
+
+    fn id_of(&self, name: &str) -> Result<u64> {
+        let id = self.names.iter().position(|n| n == name);
+        Ok(id.with_context(|| format!("No photo named {name}"))? as u64)
+    }
+
+    /// Read the embedding of photo `id` back from the embeddings file
+    fn embedding(&self, id: u64) -> Result<Vec<f32>> {
+        let dims = self.index.dimensions() as usize;
+        let mut bytes = vec![0u8; dims * 4];
+        let mut file = File::open(&self.embeddings)?;
+        file.seek(SeekFrom::Start(id * bytes.len() as u64))?;
+        file.read_exact(&mut bytes)?;
+        Ok(bytes.as_chunks::<4>().0.iter().map(|&b| f32::from_le_bytes(b)).collect())
+    }
+}
+
+/// Album of a photo: the directory part of its name, or `""`
+pub fn album_of(name: &str) -> &str {
+    name.rsplit_once('/').map_or("", |(album, _)| album)
+}
@@ -0,0 +1,60 @@
+//! `photo-similarity` command line tool.
+//!
+//! ```text
+//! photo-similarity <dir> <dims> similar <name> [-k <n>] [--album <album>]
+//! photo-similarity <dir> <dims> duplicates [max-distance]
+//! ```
+//!
+//! `<dir>` holds `embeddings.f32` and `names.txt`; the index is built there on
+//! first use.
+
+use std::process::ExitCode;
+
+use anyhow::{Context, Result, bail};
+use photo_similarity::PhotoLibrary;
+
+const USAGE: &str = "usage: photo-similarity <dir> <dims> (similar <name> | duplicates) ...";
+
+fn main() -> ExitCode {
+    match run(std::env::args().skip(1).collect()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Vec<String>) -> Result<()> {
+    let [dir, dims, command, rest @ ..] = args.as_slice() else {
+        bail!(USAGE);
+    };
+    let library = PhotoLibrary::open(dir, dims.parse().context("<dims> must be a number")?)?;
+
+    match (command.as_str(), rest) {
+        ("similar", [name, options @ ..]) => {
+            let mut k = 10;
+            let mut album = None;
+            let mut options = options.iter();
+            while let Some(option) = options.next() {
+                let value = options.next().with_context(|| format!("{option} needs a value"))?;
+                match option.as_str() {
+                    "-k" => k = value.parse()?,
+                    "--album" => album = Some(value.as_str()),
+                    _ => bail!("unknown option {option}"),
+                }
+            }
+            for m in library.similar(name, k, album)? {
+                println!("{:.3}  {}", m.distance, m.name);
+            }
+        }
+        ("duplicates", options) => {
+            let max_distance = options.first().map_or(Ok(0.1), |d| d.parse())?;
+            for (a, b, distance) in library.duplicates(max_distance)? {
+                println!("{distance:.3}  {a}  {b}");
+            }
+        }
+        _ => bail!(USAGE),
+    }
+    Ok(())
+}
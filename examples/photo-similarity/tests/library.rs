@@ -0,0 +1,88 @@
+//! End-to-end tests of the photo library
+
+use std::fs;
+use std::path::Path;
+
+use photo_similarity::{EMBEDDINGS_FILE, NAMES_FILE, PhotoLibrary, album_of};
+
+const DIMS: u32 = 64;
+const ALBUMS: [&str; 4] = ["beach", "city", "forest", "pets"];
+const PER_ALBUM: usize = 50;
+
+/// Deterministic pseudo-random floats in -1.0..1.0
+fn noise(state: &mut u64) -> f32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    (*state >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+}
+
+/// Write a library whose albums are tight clusters, plus one near-duplicate shot
+fn write_library(dir: &Path) {
+    let mut state = 0x9e37_79b9_7f4a_7c15;
+    let mut names = String::new();
+    let mut bytes = Vec::new();
+
+    for album in ALBUMS {
+        let center: Vec<f32> = (0..DIMS).map(|_| noise(&mut state) * 4.0).collect();
+        for i in 0..PER_ALBUM {
+            names.push_str(&format!("{album}/img-{i:03}.jpg\n"));
+            for &c in &center {
+                bytes.extend_from_slice(&(c + noise(&mut state) * 0.5).to_le_bytes());
+            }
+        }
+    }
+
+    // A burst shot: the last photo again, nudged slightly
+    let last = bytes[bytes.len() - DIMS as usize * 4..].to_vec();
+    names.push_str("pets/img-burst.jpg\n");
+    for &chunk in last.as_chunks::<4>().0 {
+        let value = f32::from_le_bytes(chunk) + 0.001;
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fs::write(dir.join(NAMES_FILE), names).unwrap();
+    fs::write(dir.join(EMBEDDINGS_FILE), bytes).unwrap();
+}
+
+#[test]
+fn test_similar_photos_come_from_the_same_album() {
+    let dir = tempfile::tempdir().unwrap();
+    write_library(dir.path());
+
+    let library = PhotoLibrary::open(dir.path(), DIMS).unwrap();
+    assert_eq!(library.len(), ALBUMS.len() * PER_ALBUM + 1);
+
+    let matches = library.similar("forest/img-007.jpg", 10, None).unwrap();
+    assert_eq!(matches.len(), 10);
+    assert!(matches.iter().all(|m| album_of(&m.name) == "forest"));
+    assert!(matches.iter().all(|m| m.name != "forest/img-007.jpg"));
+
+    // The album filter reaches past the whole nearer cluster
+    let matches = library.similar("forest/img-007.jpg", 3, Some("city")).unwrap();
+    assert_eq!(matches.len(), 3);
+    assert!(matches.iter().all(|m| album_of(&m.name) == "city"));
+
+    assert!(library.similar("missing.jpg", 3, None).is_err());
+}
+
+#[test]
+fn test_duplicates_and_reopen() {
+    let dir = tempfile::tempdir().unwrap();
+    write_library(dir.path());
+
+    let library = PhotoLibrary::open(dir.path(), DIMS).unwrap();
+    let duplicates = library.duplicates(0.1).unwrap();
+    assert_eq!(duplicates.len(), 1);
+    assert_eq!(duplicates[0].0, "pets/img-049.jpg");
+    assert_eq!(duplicates[0].1, "pets/img-burst.jpg");
+    drop(library);
+
+    // The finished index is reused as-is
+    let library = PhotoLibrary::open(dir.path(), DIMS).unwrap();
+    assert_eq!(library.similar("pets/img-049.jpg", 1, None).unwrap()[0].name, "pets/img-burst.jpg");
+
+    // A mismatched embeddings file is rejected
+    fs::write(dir.path().join(NAMES_FILE), "only-one.jpg\n").unwrap();
+    assert!(PhotoLibrary::open(dir.path(), DIMS).is_err());
+}
@@ -5,6 +5,7 @@ Users should use the high-level VectorIndex class instead.
 """
 
 import ctypes
+import enum
 import os
 import platform
 from pathlib import Path
@@ -86,6 +87,25 @@ class ChassisIndex(ctypes.Structure):
 ChassisIndexPtr = ctypes.POINTER(ChassisIndex)
 
 
+class ErrorCode(enum.IntEnum):
+    """Stable error codes returned by chassis_last_error_code()."""
+
+    OK = 0
+    INVALID_ARGUMENT = 1
+    NULL_POINTER = 2
+    INVALID_UTF8 = 3
+    DIMENSION_MISMATCH = 4
+    LOCKED = 5
+    CORRUPTED = 6
+    IO = 7
+    OUT_OF_BOUNDS = 8
+    FILE_STOLEN = 9
+    READ_ONLY = 10
+    PANIC = 11
+    UNKNOWN = 12
+    INCOMPATIBLE_VERSION = 13
+
+
 # Function signatures
 
 # chassis_open
@@ -145,6 +165,10 @@ _lib.chassis_dimensions.restype = ctypes.c_uint32
 _lib.chassis_last_error_message.argtypes = []
 _lib.chassis_last_error_message.restype = ctypes.c_char_p
 
+# chassis_last_error_code
+_lib.chassis_last_error_code.argtypes = []
+_lib.chassis_last_error_code.restype = ctypes.c_int
+
 # chassis_version
 _lib.chassis_version.argtypes = []
 _lib.chassis_version.restype = ctypes.c_char_p
@@ -165,6 +189,19 @@ def get_last_error() -> Optional[str]:
     return None
 
 
+def get_last_error_code() -> ErrorCode:
+    """Get the last error code from the Chassis library.
+
+    Returns:
+        Error code; codes added by newer libraries map to ErrorCode.UNKNOWN
+    """
+    code = _lib.chassis_last_error_code()
+    try:
+        return ErrorCode(code)
+    except ValueError:
+        return ErrorCode.UNKNOWN
+
+
 def get_version() -> str:
     """Get the Chassis library version.
 
@@ -180,6 +217,8 @@ __all__ = [
     "_lib",
     "ChassisIndex",
     "ChassisIndexPtr",
+    "ErrorCode",
     "get_last_error",
+    "get_last_error_code",
     "get_version",
 ]
@@ -140,11 +140,10 @@ class VectorIndex:
         if not ptr:
             error_msg = _ffi.get_last_error()
             if error_msg:
-                if "dimension" in error_msg.lower():
+                code = _ffi.get_last_error_code()
+                if code == _ffi.ErrorCode.DIMENSION_MISMATCH:
                     raise DimensionMismatchError(error_msg)
-                elif (
-                    "path" in error_msg.lower() or "utf-8" in error_msg.lower()
-                ):
+                elif code in (_ffi.ErrorCode.INVALID_UTF8, _ffi.ErrorCode.IO):
                     raise InvalidPathError(error_msg)
                 else:
                     raise ChassisError(error_msg)
@@ -236,7 +235,8 @@ class VectorIndex:
         if vector_id == 2**64 - 1:
             error_msg = _ffi.get_last_error()
             if error_msg:
-                if "dimension" in error_msg.lower():
+                code = _ffi.get_last_error_code()
+                if code == _ffi.ErrorCode.DIMENSION_MISMATCH:
                     raise DimensionMismatchError(error_msg)
                 else:
                     raise ChassisError(error_msg)
@@ -313,7 +313,8 @@ class VectorIndex:
         if count == 0:
             error_msg = _ffi.get_last_error()
             if error_msg:
-                if "dimension" in error_msg.lower():
+                code = _ffi.get_last_error_code()
+                if code == _ffi.ErrorCode.DIMENSION_MISMATCH:
                     raise DimensionMismatchError(error_msg)
                 else:
                     raise ChassisError(error_msg)